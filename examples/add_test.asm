@@ -1,5 +1,8 @@
 ; Simple Setun test - data follows code immediately
 ; This layout works with the basic assembler
+;
+;@ expect S == 59
+;@ expect [6] == 59
 
 ; Code starts at address 0
     LDA 4       ; Load value at address 4 (first data word after code)
@@ -0,0 +1,34 @@
+; Ternary checksum
+; Folds a table of words into one with h := 10h + w. Multiplying by 10
+; (0t+0+ = 9 + 1) is a shift and an add, and STA keeps only the low nine
+; trits of S, so the sum wraps the same way as
+; setun::ternary::hash::checksum.
+;
+;@ expect S == -1186
+
+START:
+    LDF I           ; F := index of the next word
+    LDA H
+    SHL 2           ; S := 9h
+    ADD H           ; S := 10h
+    ADD TABLE,F+    ; S := 10h + w
+    STA H           ; Keep the low nine trits
+    LDA I
+    ADD ONE
+    STA I
+    SUB N
+    JLT START       ; Loop while I < N
+    LDA H
+    HLT
+
+; Data
+I:      DAT 0
+H:      DAT 0
+ONE:    DAT 1
+N:      DAT 6
+TABLE:  DAT 3
+        DAT -1
+        DAT 4
+        DAT 1
+        DAT -5
+        DAT 9
@@ -0,0 +1,18 @@
+; Counting loop
+; Counts from 0 up to LIMIT, leaving the final count in S.
+;
+;@ expect S == 10
+
+LOOP:
+    LDA COUNT       ; S := count
+    ADD ONE         ; S := count + 1
+    STA COUNT       ; Save the new count
+    SUB LIMIT       ; S := count - limit
+    JNE LOOP        ; Keep going while count < limit
+    LDA COUNT       ; Result in S
+    HLT
+
+; Data
+COUNT:  DAT 0
+ONE:    DAT 1
+LIMIT:  DAT 10
@@ -0,0 +1,41 @@
+; I/O demo
+; Reads a zero-terminated list of numbers from the INPUT buffer and
+; writes the running totals to the OUTPUT buffer, the way a Setun
+; program would consume a tape and punch its results.
+;
+;@ expect S == 15
+
+START:
+    LDF ZERO        ; F := 0 (buffer position)
+NEXT:
+    LDA INPUT,F+    ; S := next input value
+    JZ DONE         ; A zero marks the end of input
+    ADD SUM         ; S := running total
+    STA SUM
+    STA OUTPUT,F+   ; Write the total to the output buffer
+    STF POS         ; Advance F by one
+    LDA POS
+    ADD ONE
+    STA POS
+    LDF POS
+    JMP NEXT
+DONE:
+    LDA SUM         ; Final total in S
+    HLT             ; S = 15
+
+; Data
+ZERO:   DAT 0
+ONE:    DAT 1
+POS:    DAT 0
+SUM:    DAT 0
+INPUT:  DAT 1
+        DAT 2
+        DAT 3
+        DAT 4
+        DAT 5
+        DAT 0
+OUTPUT: DAT 0
+        DAT 0
+        DAT 0
+        DAT 0
+        DAT 0
@@ -0,0 +1,22 @@
+; Multiplication via shifts
+; In balanced ternary a left shift multiplies by 3, so multiplying by
+; 13 (0tPPP = 9 + 3 + 1) needs only shifts and additions:
+;     13x = (x << 2) + (x << 1) + x
+;
+;@ expect S == 91
+
+START:
+    LDA X           ; S := x
+    SHL 1           ; S := 3x
+    STA TRIPLE      ; Keep 3x
+    LDA X           ; S := x
+    SHL 2           ; S := 9x
+    ADD TRIPLE      ; S := 12x
+    ADD X           ; S := 13x
+    STA RESULT
+    HLT             ; S = 91
+
+; Data
+X:      DAT 7
+TRIPLE: DAT 0
+RESULT: DAT 0
@@ -0,0 +1,24 @@
+; Table lookup
+; Uses the index register F to fetch the square of N from a table.
+;
+;@ expect S == 49
+
+START:
+    LDF N           ; F := N
+    LDA SQUARES,F+  ; S := SQUARES[N]
+    STA RESULT
+    HLT             ; S = 49
+
+; Data
+N:       DAT 7
+RESULT:  DAT 0
+SQUARES: DAT 0
+         DAT 1
+         DAT 4
+         DAT 9
+         DAT 16
+         DAT 25
+         DAT 36
+         DAT 49
+         DAT 64
+         DAT 81
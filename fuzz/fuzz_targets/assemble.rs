@@ -0,0 +1,11 @@
+//! Assemble arbitrary source; errors are fine, panics are not.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use setun::asm::{assemble_collecting, check};
+
+fuzz_target!(|source: &str| {
+    let (assembly, _errors) = assemble_collecting(source);
+    let _ = setun::disassemble(&assembly.program);
+    let _ = check(source);
+});
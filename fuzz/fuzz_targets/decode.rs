@@ -0,0 +1,31 @@
+//! Decode arbitrary words, disassemble them, and execute one instruction.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use setun::asm::disasm::disassemble_instruction;
+use setun::cpu::decode::{decode, encode};
+use setun::{Cpu, Trit, Tryte9};
+
+/// Build a word from the low trit of each byte's value mod 3.
+fn word(bytes: &[u8]) -> Tryte9 {
+    let mut trits = [Trit::O; 9];
+    for (trit, byte) in trits.iter_mut().zip(bytes) {
+        *trit = Trit::ALL[(byte % 3) as usize];
+    }
+    Tryte9::from_trits(trits)
+}
+
+fuzz_target!(|data: &[u8]| {
+    let raw = word(data);
+    let _ = disassemble_instruction(raw);
+    if let Ok(instr) = decode(raw) {
+        assert_eq!(decode(encode(&instr)).ok(), Some(instr));
+    }
+
+    // Execute the word with the rest of the input as memory contents
+    let program: Vec<Tryte9> = data.chunks(9).map(word).take(162).collect();
+    let mut cpu = Cpu::new();
+    if cpu.load_program(&program).is_ok() {
+        let _ = cpu.run_limited(64);
+    }
+});
@@ -0,0 +1,16 @@
+//! Parse arbitrary text as trit strings and numbers.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use setun::ternary::convert::parse_number;
+use setun::{Tryte9, Word18};
+
+fuzz_target!(|text: &str| {
+    if let Ok(word) = Tryte9::parse(text) {
+        assert_eq!(Tryte9::parse(&word.to_string()), Ok(word));
+    }
+    if let Ok(word) = Word18::parse(text) {
+        assert_eq!(Word18::parse(&word.to_string()), Ok(word));
+    }
+    let _ = parse_number(text);
+});
@@ -0,0 +1,13 @@
+//! Parse arbitrary TROM text and load the result into a CPU.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use setun::asm::parse_trom;
+use setun::Cpu;
+
+fuzz_target!(|text: &str| {
+    if let Ok(trom) = parse_trom(text) {
+        let mut cpu = Cpu::new();
+        let _ = cpu.load_program(&trom.instructions);
+    }
+});
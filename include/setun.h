@@ -0,0 +1,77 @@
+/*
+ * C interface to the Setun emulator.
+ *
+ * Build the library with `cargo build --release --features ffi` and link
+ * against libsetun (.so / .dylib / .dll). Mirrors src/ffi.rs; a unit test
+ * there checks that every exported function is declared here.
+ */
+
+#ifndef SETUN_H
+#define SETUN_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Result of a call. Negative values are errors. */
+typedef enum SetunStatus {
+    SETUN_OK = 0,
+    SETUN_HALTED = 1,
+    SETUN_NULL_POINTER = -1,
+    SETUN_INVALID_ARGUMENT = -2,
+    SETUN_ASSEMBLY_ERROR = -3,
+    SETUN_MEMORY_ERROR = -4,
+    SETUN_CPU_ERROR = -5,
+} SetunStatus;
+
+/* Register selector. */
+typedef enum SetunRegister {
+    SETUN_REG_S = 0,     /* accumulator, 18 trits */
+    SETUN_REG_R = 1,     /* multiplier, 18 trits */
+    SETUN_REG_F = 2,     /* index, 5 trits */
+    SETUN_REG_C = 3,     /* program counter */
+    SETUN_REG_OMEGA = 4, /* sign: -1, 0 or 1 */
+} SetunRegister;
+
+/* Opaque CPU handle. */
+typedef struct SetunCpu SetunCpu;
+
+/* Store the next input word in *word and return true, or return false at end of input. */
+typedef bool (*SetunInputFn)(void *user_data, int32_t *word);
+
+/* Receive a word written to the teletype. */
+typedef void (*SetunOutputFn)(void *user_data, int32_t word);
+
+SetunCpu *setun_cpu_new(void);
+void setun_cpu_free(SetunCpu *cpu);
+SetunStatus setun_cpu_reset(SetunCpu *cpu);
+
+SetunStatus setun_cpu_load(SetunCpu *cpu, const int32_t *words, size_t len);
+SetunStatus setun_cpu_load_asm(SetunCpu *cpu, const char *source);
+
+SetunStatus setun_cpu_step(SetunCpu *cpu);
+SetunStatus setun_cpu_run(SetunCpu *cpu, uint64_t max_cycles, uint64_t *executed);
+
+int64_t setun_cpu_get_register(const SetunCpu *cpu, SetunRegister reg);
+SetunStatus setun_cpu_set_register(SetunCpu *cpu, SetunRegister reg, int64_t value);
+SetunStatus setun_cpu_read_memory(const SetunCpu *cpu, int32_t addr, int32_t *value);
+SetunStatus setun_cpu_write_memory(SetunCpu *cpu, int32_t addr, int32_t value);
+
+uint64_t setun_cpu_cycles(const SetunCpu *cpu);
+bool setun_cpu_is_halted(const SetunCpu *cpu);
+
+SetunStatus setun_cpu_set_io(SetunCpu *cpu, SetunInputFn input, SetunOutputFn output, void *user_data);
+SetunStatus setun_cpu_feed_input(SetunCpu *cpu, const int32_t *words, size_t len);
+
+/* Message for the last failed call; valid until the next call on the same handle. */
+const char *setun_cpu_last_error(const SetunCpu *cpu);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SETUN_H */
@@ -11,51 +11,271 @@
 //!     
 //!     ORG 50      ; Set origin address
 //!     DAT 42      ; Define data value
+//!     DAT 0k1OW   ; Data in base-27 letter codes (401)
+//!     LDL BIG     ; Load an 18-trit constant into S (3 words)
+//! BIG: DAT18 1000000 ; 18-trit constant in two cells
 //! ```
+//!
+//! An `ORG` before the first word sets the program's load address
+//! ([`Assembly::origin`]); a later `ORG` skips ahead, filling the gap with
+//! zero words so every word lands at the address its labels assume.
+//!
+//! `DAT18` (alias `.LONG`) stores a constant of up to 18 trits
+//! (±193710244) in two consecutive cells: the low 9 trits at the label's
+//! address and the high 9 trits in the next cell. The `LDL addr`
+//! pseudo-instruction expands to `LDA addr+1`, `SHL 9`, `ADD addr`, which
+//! rebuilds the full value in S.
+//!
+//! `VAR NAME` or `VAR NAME = value` declares a variable: a cell in the data
+//! segment that follows the last word of the program, in declaration order.
+//! `NAME` labels the cell, which holds `value` (a number or a label) or zero,
+//! so scratch storage needs no hard-coded addresses:
+//!
+//! ```
+//! use setun::asm::assemble_detailed;
+//!
+//! let assembly = assemble_detailed("VAR COUNT = 3\nVAR TOTAL\nLDA COUNT\nSTA TOTAL\nHLT").unwrap();
+//! assert_eq!(assembly.symbols["COUNT"], 3);
+//! assert_eq!(assembly.symbols["TOTAL"], 4);
+//! assert_eq!(assembly.program[3].to_i32(), 3);
+//! ```
+//!
+//! `PUSH` and `POP` keep a stack that grows down from the address in F,
+//! the way `--stack` (`CpuConfig::stack`) arranges it. `PUSH` moves F down
+//! two cells and stores all 18 trits of S there, laid out like `DAT18`;
+//! `POP` rebuilds S from the two cells at F the way `LDL` does and moves F
+//! back up, so a push/pop pair round-trips S. Both leave ω the sign of S.
+//! They expand to short sequences that go through four hidden variables
+//! (`__STACK_SP`, `__STACK_TWO`, `__STACK_LOW`, `__STACK_HIGH`), so F is
+//! not available for indexing in code that uses them.
 
-use crate::ternary::Tryte9;
-use crate::cpu::decode::{Instruction, AddrMode, encode};
-use std::collections::HashMap;
+use crate::ternary::{Trit, Tryte9, Word18};
+use crate::ternary::convert::parse_number;
+use crate::cpu::decode::{Instruction, AddrMode, EncodeError, OperandKind, try_encode};
+use crate::cpu::extension::ExtensionRegistry;
+use crate::diagnostic::Diagnostic;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
 use thiserror::Error;
 
 /// Assemble source code to a list of instructions.
 pub fn assemble(source: &str) -> Result<Vec<Tryte9>, AssemblerError> {
+    assemble_detailed(source).map(|a| a.program)
+}
+
+/// Assemble source code, keeping the symbol table, source map, and warnings.
+pub fn assemble_detailed(source: &str) -> Result<Assembly, AssemblerError> {
+    let (assembly, errors) = assemble_collecting(source);
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(assembly),
+    }
+}
+
+/// Assemble source code without stopping at the first error.
+///
+/// Lines that fail to assemble are skipped and every error is returned,
+/// in source order. The assembly is only meaningful if there are no errors.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(lines = source.lines().count())))]
+pub fn assemble_collecting(source: &str) -> (Assembly, Vec<AssemblerError>) {
+    assemble_collecting_with(source, &ExtensionRegistry::default())
+}
+
+/// Assemble source code that may use the mnemonics of `extensions`.
+pub fn assemble_with_extensions(source: &str, extensions: &ExtensionRegistry) -> Result<Vec<Tryte9>, AssemblerError> {
+    assemble_detailed_with_extensions(source, extensions).map(|a| a.program)
+}
+
+/// [`assemble_detailed`] for source that may use the mnemonics of `extensions`.
+pub fn assemble_detailed_with_extensions(source: &str, extensions: &ExtensionRegistry) -> Result<Assembly, AssemblerError> {
+    let (assembly, errors) = assemble_collecting_with(source, extensions);
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(assembly),
+    }
+}
+
+/// Assemble source code with `defines` already in the symbol table, as if
+/// each were a label at that address, so `DAT SIZE` or `LDA TABLE` can
+/// use a value set from outside the source.
+pub fn assemble_with_defines(source: &str, defines: &BTreeMap<String, i32>) -> (Assembly, Vec<AssemblerError>) {
     let mut asm = Assembler::new();
-    asm.assemble(source)
+    asm.symbols = defines.iter().map(|(name, &value)| (name.to_uppercase(), value)).collect();
+    finish(asm, source)
+}
+
+fn assemble_collecting_with(source: &str, extensions: &ExtensionRegistry) -> (Assembly, Vec<AssemblerError>) {
+    let mut asm = Assembler::new();
+    asm.extensions = extensions.clone();
+    finish(asm, source)
+}
+
+fn finish(mut asm: Assembler, source: &str) -> (Assembly, Vec<AssemblerError>) {
+    let errors = asm.assemble(source);
+    event!(debug, words = asm.output.len(), labels = asm.symbols.len(), errors = errors.len(), "assembled");
+    let assembly = Assembly {
+        origin: asm.origin,
+        program: asm.output,
+        symbols: asm.symbols,
+        references: asm.references,
+        source_map: asm.source_map,
+        warnings: asm.warnings,
+    };
+    (assembly, errors)
+}
+
+/// The output of a successful assembly, with metadata for tooling.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Assembly {
+    /// Address of the first word of `program`.
+    #[serde(default)]
+    pub origin: i32,
+    /// The assembled words, in load order.
+    pub program: Vec<Tryte9>,
+    /// Label addresses.
+    pub symbols: BTreeMap<String, i32>,
+    /// Source lines referencing each label.
+    #[serde(default)]
+    pub references: BTreeMap<String, Vec<usize>>,
+    /// Source line (1-based) that produced each word of `program`, or 0
+    /// for padding inserted by `ORG`.
+    pub source_map: Vec<usize>,
+    /// Non-fatal problems found while assembling.
+    pub warnings: Vec<AssemblerWarning>,
+}
+
+impl Assembly {
+    /// Source line that produced the word at `index`, if any.
+    pub fn line_for_index(&self, index: usize) -> Option<usize> {
+        self.source_map.get(index).copied().filter(|&line| line != 0)
+    }
+    
+    /// Index of the first word produced by source `line`, if any.
+    pub fn index_for_line(&self, line: usize) -> Option<usize> {
+        self.source_map.iter().position(|&l| l == line)
+    }
+}
+
+/// A non-fatal assembler diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssemblerWarning {
+    /// Source line (1-based).
+    pub line: usize,
+    /// Kind of problem, as a [`Diagnostic`] code.
+    #[serde(skip_deserializing)]
+    pub code: &'static str,
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl AssemblerWarning {
+    /// The warning as a [`Diagnostic`].
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::warning(self.code, self.message.clone()).at_line(self.line)
+    }
+}
+
+/// A label reference waiting for pass 2.
+struct PendingRef {
+    /// Index of the word to patch.
+    out_idx: usize,
+    /// Referenced label.
+    label: String,
+    /// Added to the label's address (`LDL` reads the cell after its label).
+    offset: i32,
+    /// Source line of the reference.
+    line: usize,
+    /// Whether the word is a data value (patched whole) or an instruction
+    /// (only the address field is patched).
+    is_data: bool,
+}
+
+/// A `VAR` declaration, allocated after pass 1.
+struct PendingVar {
+    /// Variable name, in upper case.
+    name: String,
+    /// Initial value as written, if any.
+    init: Option<String>,
+    /// Source line of the declaration.
+    line: usize,
 }
 
 /// The assembler state.
 struct Assembler {
-    /// Current address (origin).
+    /// Current address.
     current_addr: i32,
+    /// Address of the first output word.
+    origin: i32,
     /// Symbol table (label -> address).
-    symbols: HashMap<String, i32>,
-    /// Pending references (address -> label).
-    pending: Vec<(usize, String, usize)>, // (output_index, label, source_line)
+    symbols: BTreeMap<String, i32>,
+    /// Pending label references.
+    pending: Vec<PendingRef>,
+    /// Lines referencing each label.
+    references: BTreeMap<String, Vec<usize>>,
     /// Output instructions.
     output: Vec<Tryte9>,
+    /// Source line for each output word.
+    source_map: Vec<usize>,
+    /// Collected warnings.
+    warnings: Vec<AssemblerWarning>,
+    /// Source line currently being processed.
+    line: usize,
+    /// Whether an indirect operand has been warned about.
+    uses_indirect: bool,
+    /// Whether SHM has been warned about.
+    uses_shm: bool,
+    /// Custom instructions the source may use.
+    extensions: ExtensionRegistry,
+    /// Declared variables, in source order.
+    vars: Vec<PendingVar>,
+    /// Whether the stack variables have been declared.
+    uses_stack: bool,
 }
 
 impl Assembler {
     fn new() -> Self {
         Self {
             current_addr: 0,
-            symbols: HashMap::new(),
+            origin: 0,
+            symbols: BTreeMap::new(),
             pending: Vec::new(),
+            references: BTreeMap::new(),
             output: Vec::new(),
+            source_map: Vec::new(),
+            warnings: Vec::new(),
+            line: 0,
+            uses_indirect: false,
+            uses_shm: false,
+            extensions: ExtensionRegistry::default(),
+            vars: Vec::new(),
+            uses_stack: false,
         }
     }
     
-    fn assemble(&mut self, source: &str) -> Result<Vec<Tryte9>, AssemblerError> {
+    /// Assemble `source`, returning every error encountered.
+    fn assemble(&mut self, source: &str) -> Vec<AssemblerError> {
+        let mut errors = Vec::new();
+        
         // Pass 1: Collect labels and generate code
         for (line_num, line) in source.lines().enumerate() {
-            self.process_line(line, line_num + 1)?;
+            self.line = line_num + 1;
+            if let Err(e) = self.process_line(line, line_num + 1) {
+                event!(debug, "{}", e);
+                errors.push(e);
+            }
         }
         
-        // Pass 2: Resolve forward references
-        self.resolve_references()?;
+        // Variables go after the last word
+        errors.extend(self.allocate_vars());
         
-        Ok(self.output.clone())
+        // Pass 2: Resolve forward references
+        errors.extend(self.resolve_references());
+        errors.sort_by_key(|e| e.line());
+        errors
+    }
+    
+    fn warn(&mut self, line: usize, code: &'static str, message: impl Into<String>) {
+        self.warnings.push(AssemblerWarning { line, code, message: message.into() });
     }
     
     fn process_line(&mut self, line: &str, line_num: usize) -> Result<(), AssemblerError> {
@@ -81,7 +301,12 @@ impl Assembler {
         if let Some(colon_idx) = line.find(':') {
             let label = line[..colon_idx].trim().to_uppercase();
             if !label.is_empty() {
-                self.symbols.insert(label, self.current_addr);
+                event!(trace, label = %label, addr = self.current_addr, "label defined");
+                if let Some(old) = self.symbols.insert(label.clone(), self.current_addr) {
+                    self.warn(line_num, "redefined-label", format!(
+                        "label {} redefined (previously {}, now {})", label, old, self.current_addr
+                    ));
+                }
             }
             
             // Process rest of line if any
@@ -102,7 +327,13 @@ impl Assembler {
         }
         
         let mnemonic = parts[0].to_uppercase();
+        if mnemonic == "VAR" {
+            return self.declare_var(line[parts[0].len()..].trim(), line_num);
+        }
         let operand = if parts.len() > 1 { Some(parts[1]) } else { None };
+        if parts.len() > 2 {
+            self.warn(line_num, "extra-operands", format!("ignoring extra operands: {}", parts[2..].join(" ")));
+        }
         
         match mnemonic.as_str() {
             // Directives
@@ -110,6 +341,20 @@ impl Assembler {
                 let addr = self.parse_operand_value(operand.ok_or_else(|| {
                     AssemblerError::SyntaxError { line: line_num, message: "ORG requires address".into() }
                 })?, line_num)?;
+                checked_word(addr, line_num)?;
+                if self.output.is_empty() {
+                    self.origin = addr;
+                } else if addr < self.current_addr {
+                    return Err(AssemblerError::SyntaxError {
+                        line: line_num,
+                        message: format!("ORG {} is below the current address {}", addr, self.current_addr),
+                    });
+                }
+                while !self.output.is_empty() && self.current_addr < addr {
+                    self.output.push(Tryte9::zero());
+                    self.source_map.push(0);
+                    self.current_addr += 1;
+                }
                 self.current_addr = addr;
             }
             
@@ -117,13 +362,76 @@ impl Assembler {
                 let value = self.parse_operand_value(operand.ok_or_else(|| {
                     AssemblerError::SyntaxError { line: line_num, message: "DAT requires value".into() }
                 })?, line_num)?;
-                self.emit(Tryte9::from_i32(value));
+                if let Some(pending) = self.pending.last_mut() {
+                    if pending.out_idx == self.output.len() {
+                        pending.is_data = true;
+                    }
+                }
+                self.emit(checked_word(value, line_num)?);
+            }
+            
+            "DAT18" | ".LONG" => {
+                let text = operand.ok_or_else(|| {
+                    AssemblerError::SyntaxError { line: line_num, message: format!("{} requires value", mnemonic) }
+                })?;
+                let word = parse_number(text)
+                    .ok()
+                    .and_then(|v| Word18::try_from_i64(v).ok())
+                    .ok_or_else(|| AssemblerError::SyntaxError {
+                        line: line_num,
+                        message: format!("{} needs a number within ±{}, got {}", mnemonic, Word18::MAX, text),
+                    })?;
+                self.emit(word.low());
+                self.emit(word.high());
+            }
+            
+            // LDL addr: S := 18-trit value stored by DAT18 at addr
+            "LDL" => {
+                let operand = operand.ok_or_else(|| {
+                    AssemblerError::SyntaxError { line: line_num, message: "LDL requires address".into() }
+                })?;
+                // Parse once, so a label is cross-referenced once, and patch
+                // both words from the same reference
+                let pending = self.pending.len();
+                let (addr, mode) = self.parse_address_operand(operand, line_num)?;
+                let label = self.pending.get(pending).map(|reference| reference.label.clone());
+                let high = if let Some(reference) = self.pending.get_mut(pending) {
+                    reference.offset = 1;
+                    addr
+                } else {
+                    checked_word(addr.to_i32() + 1, line_num)?
+                };
+                self.emit(encode_at(&Instruction::Lda { addr: high, mode }, line_num)?);
+                self.emit(encode_at(&Instruction::Shl { count: Tryte9::WIDTH as i8 }, line_num)?);
+                if let Some(label) = label {
+                    let out_idx = self.output.len();
+                    self.pending.push(PendingRef { out_idx, label, offset: 0, line: line_num, is_data: false });
+                }
+                self.emit(encode_at(&Instruction::Add { addr, mode }, line_num)?);
+            }
+            
+            // PUSH: F := F - 2, [F], [F+1] := S; POP: S := [F], [F+1], F := F + 2
+            "PUSH" | "POP" => {
+                if let Some(op) = operand {
+                    self.warn(line_num, "extra-operands", format!("{} takes no operand; ignoring {}", mnemonic, op));
+                }
+                if !self.uses_stack {
+                    self.uses_stack = true;
+                    for (name, init) in [(STACK_SP, None), (STACK_TWO, Some("2")), (STACK_LOW, None), (STACK_HIGH, None)] {
+                        self.vars.push(PendingVar { name: name.into(), init: init.map(str::to_string), line: line_num });
+                    }
+                }
+                let sequence = if mnemonic == "PUSH" { PUSH_SEQUENCE } else { POP_SEQUENCE };
+                for &(mnemonic, operand) in sequence {
+                    let instr = self.parse_instruction(mnemonic, Some(operand), line_num)?;
+                    self.emit(encode_at(&instr, line_num)?);
+                }
             }
             
             // Instructions
             _ => {
                 let instr = self.parse_instruction(&mnemonic, operand, line_num)?;
-                self.emit(encode(&instr));
+                self.emit(encode_at(&instr, line_num)?);
             }
         }
         
@@ -134,7 +442,12 @@ impl Assembler {
         -> Result<Instruction, AssemblerError> 
     {
         // Parse operand and mode
+        let takes_no_operand = matches!(mnemonic, "HLT" | "HALT" | "NOP" | "TST")
+            || self.extensions.by_mnemonic(mnemonic).is_some_and(|e| e.operand == OperandKind::None);
         let (addr, mode) = if let Some(op) = operand {
+            if takes_no_operand {
+                self.warn(line_num, "extra-operands", format!("{} takes no operand; ignoring {}", mnemonic, op));
+            }
             self.parse_address_operand(op, line_num)?
         } else {
             (Tryte9::zero(), AddrMode::Direct)
@@ -169,23 +482,28 @@ impl Assembler {
             "HLT" | "HALT" => Instruction::Hlt,
             
             // Shift
-            "SHL" | "ASL" => {
-                let count = addr.to_i32() as i8;
-                Instruction::Shl { count }
-            }
-            "SHR" | "ASR" => {
-                let count = addr.to_i32() as i8;
-                Instruction::Shr { count }
+            "SHL" | "ASL" => Instruction::Shl { count: shift_count(addr, line_num)? },
+            "SHR" | "ASR" => Instruction::Shr { count: shift_count(addr, line_num)? },
+            "SHM" => {
+                if !self.uses_shm {
+                    self.uses_shm = true;
+                    self.warn(line_num, "historical-only", "SHM exists only on the historical machine");
+                }
+                Instruction::Shm { addr, mode }
             }
             
             // Special
             "NOP" => Instruction::Nop,
             "TST" => Instruction::Tst,
             
-            _ => return Err(AssemblerError::UnknownMnemonic { 
-                line: line_num, 
-                mnemonic: mnemonic.to_string() 
-            }),
+            // Registered extensions
+            _ => match self.extensions.by_mnemonic(mnemonic) {
+                Some(extension) => Instruction::Ext { opcode: extension.opcode, addr, mode },
+                None => return Err(AssemblerError::UnknownMnemonic {
+                    line: line_num,
+                    mnemonic: mnemonic.to_string()
+                }),
+            },
         };
         
         Ok(instr)
@@ -194,6 +512,16 @@ impl Assembler {
     fn parse_address_operand(&mut self, operand: &str, line_num: usize) 
         -> Result<(Tryte9, AddrMode), AssemblerError> 
     {
+        // `(PTR)` takes the address from memory cell PTR
+        if let Some(inner) = operand.strip_prefix('(').and_then(|o| o.strip_suffix(')')) {
+            if !self.uses_indirect {
+                self.uses_indirect = true;
+                self.warn(line_num, "needs-indirect", "indirect operand needs indirect addressing enabled (run --indirect)");
+            }
+            let addr = self.parse_operand_value(inner, line_num)?;
+            return Ok((checked_word(addr, line_num)?, AddrMode::Indirect));
+        }
+        
         // Check for mode suffix: ,F+ or ,F- or just bare address
         let (addr_part, mode) = if operand.ends_with(",F+") || operand.ends_with(",f+") {
             (&operand[..operand.len()-3], AddrMode::IndexAdd)
@@ -206,7 +534,7 @@ impl Assembler {
         };
         
         let addr = self.parse_operand_value(addr_part, line_num)?;
-        Ok((Tryte9::from_i32(addr), mode))
+        Ok((checked_word(addr, line_num)?, mode))
     }
     
     fn parse_operand_value(&mut self, operand: &str, line_num: usize) -> Result<i32, AssemblerError> {
@@ -225,6 +553,15 @@ impl Assembler {
                 });
         }
         
+        // Base-27 letter codes, as in historical listings
+        if operand.starts_with("0k") || operand.starts_with("0K") {
+            return parse_number(operand).ok().and_then(|value| i32::try_from(value).ok())
+                .ok_or_else(|| AssemblerError::SyntaxError {
+                    line: line_num,
+                    message: format!("invalid base-27 literal: {}", operand),
+                });
+        }
+        
         // Check for hex literal
         if operand.starts_with("0x") || operand.starts_with("0X") {
             return i32::from_str_radix(&operand[2..], 16)
@@ -241,37 +578,192 @@ impl Assembler {
         
         // Must be a label reference - store for pass 2
         // For now, just return 0 and add to pending
-        let out_idx = self.output.len();
-        self.pending.push((out_idx, operand.to_uppercase(), line_num));
+        self.references.entry(operand.to_uppercase()).or_default().push(line_num);
+        self.pending.push(PendingRef {
+            out_idx: self.output.len(),
+            label: operand.to_uppercase(),
+            offset: 0,
+            line: line_num,
+            is_data: false,
+        });
         Ok(0) // Placeholder, will be resolved in pass 2
     }
     
+    /// Record `VAR NAME [= value]` for [`Assembler::allocate_vars`].
+    fn declare_var(&mut self, declaration: &str, line_num: usize) -> Result<(), AssemblerError> {
+        let (name, init) = match declaration.split_once('=') {
+            Some((name, init)) => (name.trim(), Some(init.trim())),
+            None => (declaration, None),
+        };
+        let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(AssemblerError::SyntaxError {
+                line: line_num,
+                message: format!("VAR requires a name, got '{}'", name),
+            });
+        }
+        if init == Some("") {
+            return Err(AssemblerError::SyntaxError { line: line_num, message: format!("VAR {} = requires value", name) });
+        }
+        self.vars.push(PendingVar { name: name.to_uppercase(), init: init.map(str::to_string), line: line_num });
+        Ok(())
+    }
+    
+    /// Give each declared variable the next cell after the program.
+    fn allocate_vars(&mut self) -> Vec<AssemblerError> {
+        let mut errors = Vec::new();
+        for var in std::mem::take(&mut self.vars) {
+            self.line = var.line;
+            if self.output.is_empty() {
+                self.origin = self.current_addr;
+            }
+            if let Some(old) = self.symbols.insert(var.name.clone(), self.current_addr) {
+                self.warn(var.line, "redefined-label", format!(
+                    "label {} redefined (previously {}, now {})", var.name, old, self.current_addr
+                ));
+            }
+            let value = match &var.init {
+                Some(init) => self.parse_operand_value(init, var.line).and_then(|value| {
+                    if let Some(pending) = self.pending.last_mut().filter(|p| p.out_idx == self.output.len()) {
+                        pending.is_data = true;
+                    }
+                    checked_word(value, var.line)
+                }),
+                None => Ok(Tryte9::zero()),
+            };
+            match value {
+                Ok(word) => self.emit(word),
+                Err(e) => {
+                    errors.push(e);
+                    self.emit(Tryte9::zero());
+                }
+            }
+        }
+        errors
+    }
+    
     fn emit(&mut self, instr: Tryte9) {
         self.output.push(instr);
+        self.source_map.push(self.line);
         self.current_addr += 1;
     }
     
-    fn resolve_references(&mut self) -> Result<(), AssemblerError> {
-        for (out_idx, label, line_num) in &self.pending {
-            let addr = self.symbols.get(label)
-                .ok_or_else(|| AssemblerError::UndefinedLabel { 
-                    line: *line_num, 
-                    label: label.clone() 
-                })?;
+    fn resolve_references(&mut self) -> Vec<AssemblerError> {
+        let mut errors = Vec::new();
+        
+        for pending in &self.pending {
+            let Some(addr) = self.symbols.get(&pending.label).map(|addr| addr + pending.offset) else {
+                errors.push(AssemblerError::UndefinedLabel { 
+                    line: pending.line, 
+                    label: pending.label.clone() 
+                });
+                continue;
+            };
             
-            // Re-encode the instruction with the correct address
-            // This is a simplified approach - we just update the address portion
-            // In a real assembler, we'd fully re-encode
-            if *out_idx < self.output.len() {
-                // For now, just store the address directly (simple case)
-                // This works for JMP and similar instructions
-                self.output[*out_idx] = Tryte9::from_i32(*addr);
+            let Some(word) = self.output.get_mut(pending.out_idx) else {
+                continue;
+            };
+            let target = match checked_word(addr, pending.line) {
+                Ok(target) => target,
+                Err(e) => {
+                    errors.push(e);
+                    continue;
+                }
+            };
+            if pending.is_data {
+                *word = target;
+            } else {
+                // Patch only the 5-trit address field, keeping opcode and mode
+                for i in 0..5 {
+                    word.set(i, target.get(i));
+                }
+                if target.trits()[5..].iter().any(|t| *t != Trit::O) {
+                    errors.push(AssemblerError::ValueOutOfRange { line: pending.line, value: addr });
+                }
             }
         }
-        Ok(())
+        errors
     }
 }
 
+/// Hidden variable holding F while it is moved.
+const STACK_SP: &str = "__STACK_SP";
+/// Hidden constant 2, the cells one pushed word takes, for moving F.
+const STACK_TWO: &str = "__STACK_TWO";
+/// Hidden variable holding the low half of S while `PUSH` moves F.
+const STACK_LOW: &str = "__STACK_LOW";
+/// Hidden variable holding the high half of S while `PUSH` moves F.
+const STACK_HIGH: &str = "__STACK_HIGH";
+
+/// `PUSH`: save both halves of S, move F down, then store the high half at
+/// F+1 and, once S is rebuilt, the low half at F, so an overflow stops the
+/// CPU before anything is written below the stack.
+const PUSH_SEQUENCE: &[(&str, &str)] = &[
+    ("STA", STACK_LOW),
+    ("SHR", "9"),
+    ("STA", STACK_HIGH),
+    ("STF", STACK_SP),
+    ("LDA", STACK_SP),
+    ("SUB", STACK_TWO),
+    ("STA", STACK_SP),
+    ("LDF", STACK_SP),
+    ("LDA", STACK_HIGH),
+    ("STA", "1,F+"),
+    ("SHL", "9"),
+    ("ADD", STACK_LOW),
+    ("STA", "0,F+"),
+];
+
+/// `POP`: move F up, then rebuild S from the two cells F just left.
+const POP_SEQUENCE: &[(&str, &str)] = &[
+    ("STF", STACK_SP),
+    ("LDA", STACK_SP),
+    ("ADD", STACK_TWO),
+    ("STA", STACK_SP),
+    ("LDF", STACK_SP),
+    ("LDA", "-1,F+"),
+    ("SHL", "9"),
+    ("ADD", "-2,F+"),
+];
+
+/// Mnemonics, aliases and directives the assembler already knows, which
+/// an extension may not reuse.
+const RESERVED_MNEMONICS: &[&str] = &[
+    "ORG", "DAT", "DATA", "DAT18", ".LONG", "LDL", "VAR", "PUSH", "POP",
+    "ADD", "SUB", "MUL", "DIV", "ADDABS", "ADA", "SUBABS", "SBA",
+    "LDA", "LD", "STA", "ST", "LDAU", "LDF", "STF", "LDR", "STR", "XCHG", "XCH",
+    "JMP", "JP", "J", "JZ", "JE", "JPO", "JGT", "JNE", "JLT", "JOP", "JON", "HLT", "HALT",
+    "SHL", "ASL", "SHR", "ASR", "SHM", "NOP", "TST",
+];
+
+/// Whether `mnemonic` is an instruction or directive of the assembler.
+pub(crate) fn is_reserved_mnemonic(mnemonic: &str) -> bool {
+    RESERVED_MNEMONICS.iter().any(|m| m.eq_ignore_ascii_case(mnemonic))
+}
+
+/// Encode an instruction from source `line`, reporting operands that do
+/// not fit their field instead of truncating them.
+fn encode_at(instr: &Instruction, line: usize) -> Result<Tryte9, AssemblerError> {
+    try_encode(instr).map_err(|e| match e {
+        EncodeError::AddressOutOfRange(value) => AssemblerError::ValueOutOfRange { line, value },
+        EncodeError::ShiftOutOfRange(count) => AssemblerError::ValueOutOfRange { line, value: count as i32 },
+        EncodeError::Unencodable(_) => AssemblerError::SyntaxError { line, message: e.to_string() },
+    })
+}
+
+/// A shift count operand, which must fit in an `i8` before range checks.
+fn shift_count(addr: Tryte9, line: usize) -> Result<i8, AssemblerError> {
+    let value = addr.to_i32();
+    i8::try_from(value).map_err(|_| AssemblerError::ValueOutOfRange { line, value })
+}
+
+/// Convert a literal or label value to a word, rejecting values outside
+/// the 9-trit range instead of panicking.
+fn checked_word(value: i32, line: usize) -> Result<Tryte9, AssemblerError> {
+    Tryte9::try_from_i32(value).map_err(|_| AssemblerError::ValueOutOfRange { line, value })
+}
+
 /// Errors that can occur during assembly.
 #[derive(Debug, Clone, Error)]
 pub enum AssemblerError {
@@ -288,6 +780,29 @@ pub enum AssemblerError {
     ValueOutOfRange { line: usize, value: i32 },
 }
 
+impl AssemblerError {
+    /// The error as a [`Diagnostic`].
+    pub fn diagnostic(&self) -> Diagnostic {
+        let code = match self {
+            AssemblerError::SyntaxError { .. } => "syntax-error",
+            AssemblerError::UnknownMnemonic { .. } => "unknown-mnemonic",
+            AssemblerError::UndefinedLabel { .. } => "undefined-label",
+            AssemblerError::ValueOutOfRange { .. } => "value-out-of-range",
+        };
+        Diagnostic::error(code, self.to_string()).at_line(self.line())
+    }
+
+    /// Source line (1-based) the error refers to.
+    pub fn line(&self) -> usize {
+        match self {
+            AssemblerError::SyntaxError { line, .. }
+            | AssemblerError::UnknownMnemonic { line, .. }
+            | AssemblerError::UndefinedLabel { line, .. }
+            | AssemblerError::ValueOutOfRange { line, .. } => *line,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -321,6 +836,75 @@ mod tests {
         assert_eq!(result.len(), 4);
     }
     
+    #[test]
+    fn test_label_reference_keeps_opcode() {
+        use crate::cpu::decode::decode;
+        
+        let source = "JMP END\nNOP\nEND: HLT\n";
+        let result = assemble(source).unwrap();
+        
+        assert_eq!(decode(result[0]).unwrap(), Instruction::Jmp {
+            addr: Tryte9::from_i32(2),
+            mode: AddrMode::Direct,
+        });
+        
+        // The mode survives too
+        let result = assemble("LDA VALUE,F+\nHLT\nVALUE: DAT 7\n").unwrap();
+        assert_eq!(decode(result[0]).unwrap(), Instruction::Lda {
+            addr: Tryte9::from_i32(2),
+            mode: AddrMode::IndexAdd,
+        });
+        
+        // A DAT naming a label holds the whole address
+        let result = assemble("HLT\nPTR: DAT END\nEND: HLT\n").unwrap();
+        assert_eq!(result[1].to_i32(), 2);
+        
+        // An address that does not fit the 5-trit field is an error
+        assert!(matches!(
+            assemble("JMP FAR\nORG 200\nFAR: HLT\n"),
+            Err(AssemblerError::ValueOutOfRange { line: 1, value: 200 })
+        ));
+    }
+    
+    #[test]
+    fn test_assemble_detailed_metadata() {
+        let source = "START: LDA VALUE\n\nHLT 3\nVALUE: DAT START\n";
+        let assembly = assemble_detailed(source).unwrap();
+        
+        assert_eq!(assembly.symbols.get("START"), Some(&0));
+        assert_eq!(assembly.symbols.get("VALUE"), Some(&2));
+        assert_eq!(assembly.source_map, vec![1, 3, 4]);
+        assert_eq!(assembly.index_for_line(4), Some(2));
+        assert_eq!(assembly.program[2].to_i32(), 0);
+        assert_eq!(assembly.warnings.len(), 1);
+        assert_eq!(assembly.warnings[0].line, 3);
+    }
+    
+    #[test]
+    fn test_assemble_collecting_reports_all_errors() {
+        let source = "FOO 1\nLDA MISSING\nJMP START\nSTART: BAR\n";
+        let (assembly, errors) = assemble_collecting(source);
+        
+        let lines: Vec<usize> = errors.iter().map(|e| e.line()).collect();
+        assert_eq!(lines, vec![1, 2, 4]);
+        assert!(matches!(errors[1], AssemblerError::UndefinedLabel { .. }));
+        assert_eq!(assembly.references["START"], vec![3]);
+    }
+    
+    #[test]
+    fn test_org_places_words_at_their_addresses() {
+        let source = "ORG -5\nSTART: LDA VALUE\nHLT\nORG 0\nVALUE: DAT 7\n";
+        let assembly = assemble_detailed(source).unwrap();
+        
+        assert_eq!(assembly.origin, -5);
+        assert_eq!(assembly.symbols["VALUE"], 0);
+        assert_eq!(assembly.program.len(), 6);
+        assert_eq!(assembly.program[5].to_i32(), 7);
+        assert_eq!(assembly.line_for_index(3), None);
+        
+        assert!(assemble("ORG 5\nNOP\nORG 2\nNOP\n").is_err());
+    }
+    
     #[test]
     fn test_assemble_data() {
         let source = r#"
@@ -335,4 +919,108 @@ mod tests {
         assert_eq!(result[1].to_i32(), -17);
         assert_eq!(result[2].to_i32(), 0);
     }
+    
+    #[test]
+    fn test_base27_literals() {
+        let assembly = assemble_detailed("LDA 0k1D\nHLT\nDAT 0k1ow\nDAT18 0kD0000Z").unwrap();
+        assert_eq!(assembly.program[0], try_encode(&Instruction::Lda { addr: Tryte9::from_i32(40), mode: AddrMode::Direct }).unwrap());
+        assert_eq!(assembly.program[2].to_i32(), 401);
+        assert_eq!(Word18::from_halves(assembly.program[3], assembly.program[4]).to_i64(), 13 * 27i64.pow(5) - 1);
+        
+        assert!(assemble("DAT 0kE").is_err());
+        assert!(matches!(assemble("DAT 0k1000"), Err(AssemblerError::ValueOutOfRange { value: 19683, .. })));
+    }
+    
+    #[test]
+    fn test_dat18_and_ldl() {
+        let source = "LDL BIG\nSTA 10\nLDL -5\nHLT\nBIG: DAT18 -100000000\n.LONG 0t+-000000000000000";
+        let assembly = assemble_detailed(source).unwrap();
+        let program = &assembly.program;
+        assert_eq!(program.len(), 12);
+        // Low half at the label, high half after it
+        let big = Word18::from_i64(-100_000_000);
+        assert_eq!(program[8..10], [big.low(), big.high()]);
+        assert_eq!(program[10..], [Tryte9::zero(), Tryte9::from_i32(2 * 729)]);
+        assert_eq!(assembly.symbols["BIG"], 8);
+        assert_eq!(assembly.references["BIG"], vec![1]);
+
+        let decoded: Vec<_> = program[..3].iter().map(|&w| crate::cpu::decode::decode(w).unwrap()).collect();
+        assert_eq!(decoded, [
+            Instruction::Lda { addr: Tryte9::from_i32(9), mode: AddrMode::Direct },
+            Instruction::Shl { count: 9 },
+            Instruction::Add { addr: Tryte9::from_i32(8), mode: AddrMode::Direct },
+        ]);
+        assert_eq!(crate::cpu::decode::decode(program[4]).unwrap(), Instruction::Lda {
+            addr: Tryte9::from_i32(-4),
+            mode: AddrMode::Direct,
+        });
+
+        let mut cpu = crate::Cpu::new();
+        cpu.load_program(&assemble("LDL X\nHLT\nX: DAT18 -100000000").unwrap()).unwrap();
+        cpu.run().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), -100_000_000);
+
+        assert!(assemble("DAT18 193710245").is_err());
+        assert!(assemble("LDL").is_err());
+        assert!(assemble("LDL 121").is_err());
+    }
+    
+    #[test]
+    fn test_var_declarations() {
+        let source = "ORG 10\nVAR COUNT = 3\nLOOP: LDA COUNT\nSUB ONE\nSTA COUNT\nJGT LOOP\nHLT\nVAR ONE=1\nvar ptr = LOOP\nVAR SUM";
+        let assembly = assemble_detailed(source).unwrap();
+        assert_eq!(assembly.origin, 10);
+        assert_eq!([assembly.symbols["COUNT"], assembly.symbols["ONE"], assembly.symbols["PTR"], assembly.symbols["SUM"]], [15, 16, 17, 18]);
+        let values: Vec<i32> = assembly.program[5..].iter().map(|w| w.to_i32()).collect();
+        assert_eq!(values, [3, 1, 10, 0]);
+        assert_eq!(assembly.source_map[5..], [2, 8, 9, 10]);
+        
+        let mut cpu = crate::Cpu::new();
+        cpu.load_program_at(10, &assembly.program).unwrap();
+        cpu.set_entry(10).unwrap();
+        cpu.run().unwrap();
+        assert_eq!(cpu.mem.read_ternary(Tryte9::from_i32(15)).unwrap().to_i32(), 0);
+        
+        assert!(matches!(assemble("VAR\nHLT"), Err(AssemblerError::SyntaxError { line: 1, .. })));
+        assert!(matches!(assemble("VAR 5 = 1"), Err(AssemblerError::SyntaxError { .. })));
+        assert!(matches!(assemble("HLT\nVAR X = NOPE"), Err(AssemblerError::UndefinedLabel { line: 2, .. })));
+    }
+    
+    #[test]
+    fn test_push_and_pop() {
+        let assembly = assemble_detailed("PUSH\nPOP\nPUSH\nHLT").unwrap();
+        // 13 + 8 + 13 words and HLT, then the four stack variables
+        assert_eq!(assembly.program.len(), 39);
+        assert_eq!(assembly.symbols[STACK_SP], 35);
+        assert_eq!(assembly.program[36].to_i32(), 2);
+        assert_eq!(crate::cpu::decode::decode(assembly.program[12]).unwrap(), Instruction::Sta {
+            addr: Tryte9::zero(),
+            mode: AddrMode::IndexAdd,
+        });
+        assert!(assembly.source_map[13..21].iter().all(|&line| line == 2));
+        
+        let assembly = assemble_detailed("PUSH 3\nHLT").unwrap();
+        assert_eq!(assembly.warnings[0].message, "PUSH takes no operand; ignoring 3");
+    }
+    
+    #[test]
+    fn test_indirect_operand() {
+        let assembly = assemble_detailed("LDA (PTR)\nSTA (PTR)\nPTR: DAT 5").unwrap();
+        assert_eq!(crate::cpu::decode::decode(assembly.program[0]).unwrap(), Instruction::Lda { addr: Tryte9::from_i32(2), mode: AddrMode::IndexSub });
+        assert_eq!(assembly.warnings.len(), 1);
+        assert_eq!(assembly.warnings[0].line, 1);
+    }
+    
+    #[test]
+    fn test_out_of_range_values_are_errors() {
+        for source in [
+            "DAT 10000", "ADD -99999", "ORG 2147483647\nNOP", "ORG 9841\nDAT 1\nL: DAT 2\nDAT L",
+            "LDA 122", "JMP -500,F+", "SHL 200", "SHR -122",
+        ] {
+            assert!(
+                matches!(assemble(source), Err(AssemblerError::ValueOutOfRange { .. })),
+                "{:?}", source
+            );
+        }
+    }
 }
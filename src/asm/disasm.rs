@@ -1,9 +1,32 @@
 //! Disassembler for Setun programs.
 //!
 //! Converts binary TROM instructions back to readable assembly.
+//!
+//! [`disassemble_lines`] goes further for tooling: it names addresses from
+//! a symbol table, can invent labels for the cells instructions refer to,
+//! and writes words that are not instructions as `DAT`, so the
+//! [`DisasmFormat::Asm`] output assembles back to the same words:
+//!
+//! ```
+//! use setun::asm::assemble;
+//! use setun::asm::disasm::{disassemble_lines, format_lines, DisasmFormat, DisasmOptions};
+//!
+//! let program = assemble("LOOP: LDA 5\nJGT LOOP\nHLT\nDAT 4").unwrap();
+//! let options = DisasmOptions { labels: true, ..Default::default() };
+//! let source = format_lines(&disassemble_lines(&program, &options), DisasmFormat::Asm);
+//! assert!(source.contains("L0:       LDA 5"));
+//! assert!(source.contains("JGT L0"));
+//! assert_eq!(assemble(&source).unwrap(), program);
+//! ```
 
-use crate::ternary::Tryte9;
-use crate::cpu::decode::{decode, Instruction, AddrMode};
+use crate::ternary::{Trit, Tryte9};
+use crate::cpu::config::CpuConfig;
+use crate::cpu::coverage::Coverage;
+use crate::cpu::decode::{decode, decode_for, try_encode, Instruction, AddrMode, OperandKind};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
 
 /// Disassemble a single instruction to text.
 pub fn disassemble_instruction(instr: Tryte9) -> String {
@@ -13,6 +36,15 @@ pub fn disassemble_instruction(instr: Tryte9) -> String {
     }
 }
 
+/// Disassemble a single instruction as a machine with `config` reads it,
+/// so indirect operands show as `(addr)`.
+pub fn disassemble_instruction_for(instr: Tryte9, config: &CpuConfig) -> String {
+    match decode_for(instr, config) {
+        Ok(decoded) => format_instruction(&decoded),
+        Err(_) => format!("??? ; {}", instr),
+    }
+}
+
 /// Disassemble a slice of instructions.
 pub fn disassemble(instructions: &[Tryte9]) -> String {
     let mut output = String::new();
@@ -27,8 +59,239 @@ pub fn disassemble(instructions: &[Tryte9]) -> String {
     output
 }
 
+/// Disassemble a program loaded at `origin`, marking each line with its
+/// coverage: `+` executed, `-` never executed, and for conditional
+/// branches the directions seen. A summary comment closes the listing.
+pub fn disassemble_with_coverage(instructions: &[Tryte9], origin: i32, coverage: &Coverage) -> String {
+    let mut output = String::new();
+    output.push_str("; Setun Disassembly (coverage: + executed, - not executed)\n");
+    output.push_str("; -----------------\n\n");
+    
+    for (offset, instr) in instructions.iter().enumerate() {
+        let addr = origin + offset as i32;
+        let (marker, branch) = coverage_marks(coverage, addr);
+        let line = disassemble_instruction(*instr);
+        output.push_str(&format!("{} {:03}: {}  ; {}{}\n", marker, addr, line, instr, branch));
+    }
+    
+    output.push_str(&format!("\n; Coverage: {}\n", coverage.report(origin, instructions.len())));
+    output
+}
+
+/// The coverage marker of `addr` and the note on its branch directions.
+fn coverage_marks(coverage: &Coverage, addr: i32) -> (char, &'static str) {
+    let marker = if coverage.is_executed(addr) { '+' } else { '-' };
+    let branch = match coverage.branches.get(&addr) {
+        Some(b) if b.taken && b.fallthrough => " [taken, fell through]",
+        Some(b) if b.taken => " [taken only]",
+        Some(_) => " [fell through only]",
+        None => "",
+    };
+    (marker, branch)
+}
+
+/// Output layout of [`format_lines`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DisasmFormat {
+    /// Source the assembler accepts, reproducing the same words.
+    Asm,
+    /// Address, label, instruction and trit pattern on each line.
+    #[default]
+    Listing,
+    /// A JSON array of [`DisasmLine`]s.
+    Json,
+}
+
+impl FromStr for DisasmFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "asm" => Ok(DisasmFormat::Asm),
+            "listing" => Ok(DisasmFormat::Listing),
+            "json" => Ok(DisasmFormat::Json),
+            _ => Err(format!("unknown disassembly format '{}' (expected asm, listing or json)", s)),
+        }
+    }
+}
+
+impl fmt::Display for DisasmFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisasmFormat::Asm => write!(f, "asm"),
+            DisasmFormat::Listing => write!(f, "listing"),
+            DisasmFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// What [`disassemble_lines`] names and where the program sits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DisasmOptions {
+    /// Address of the first word.
+    pub origin: i32,
+    /// Label addresses, as in [`Assembly::symbols`](crate::asm::Assembly::symbols).
+    /// Those inside the program label their cell and name the operands
+    /// that refer to it.
+    pub symbols: BTreeMap<String, i32>,
+    /// Invent labels (`L12`, or `LN5` for -5) for the other cells of the
+    /// program that instructions refer to.
+    pub labels: bool,
+}
+
+/// One disassembled word.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisasmLine {
+    /// Address of the word.
+    pub addr: i32,
+    /// The word itself.
+    pub word: Tryte9,
+    /// Label of the cell, if it has one.
+    pub label: Option<String>,
+    /// The instruction with named operands, or `DAT value` for a word that
+    /// no instruction encodes to.
+    pub text: String,
+}
+
+/// Disassemble a program into lines, naming addresses as `options` asks.
+pub fn disassemble_lines(program: &[Tryte9], options: &DisasmOptions) -> Vec<DisasmLine> {
+    let end = options.origin + program.len() as i32;
+    let in_program = |addr: i32| (options.origin..end).contains(&addr);
+    let instructions: Vec<Option<Instruction>> = program.iter().map(|&word| as_instruction(word)).collect();
+    
+    // The first symbol for each address, then invented labels
+    let mut names: BTreeMap<i32, String> = BTreeMap::new();
+    for (name, &addr) in &options.symbols {
+        if in_program(addr) {
+            names.entry(addr).or_insert_with(|| name.clone());
+        }
+    }
+    if options.labels {
+        let taken: BTreeSet<String> = names.values().cloned().collect();
+        let operands = program.iter().zip(&instructions)
+            .filter_map(|(&word, instr)| instr.as_ref().and_then(|instr| address_operand(word, instr)));
+        for (addr, _) in operands {
+            let label = if addr < 0 { format!("LN{}", -addr) } else { format!("L{}", addr) };
+            if in_program(addr) && !taken.contains(&label) {
+                names.entry(addr).or_insert(label);
+            }
+        }
+    }
+    
+    program.iter().zip(&instructions).enumerate()
+        .map(|(offset, (&word, instr))| {
+            let addr = options.origin + offset as i32;
+            let text = match instr {
+                Some(instr) => match address_operand(word, instr).and_then(|(target, mode)| names.get(&target).map(|n| (n, mode))) {
+                    Some((name, mode)) => format!("{} {}", asm_mnemonic(instr), named_operand(name, mode)),
+                    None => asm_text(instr),
+                },
+                None => format!("DAT {}", word.to_i32()),
+            };
+            DisasmLine { addr, word, label: names.get(&addr).cloned(), text }
+        })
+        .collect()
+}
+
+/// Render disassembled lines in `format`.
+pub fn format_lines(lines: &[DisasmLine], format: DisasmFormat) -> String {
+    let mut output = String::new();
+    match format {
+        DisasmFormat::Asm => {
+            output.push_str("; Setun Disassembly\n");
+            if let Some(first) = lines.first() {
+                output.push_str(&format!("          ORG {}\n", first.addr));
+            }
+            for line in lines {
+                let label = line.label.as_ref().map(|l| format!("{}:", l)).unwrap_or_default();
+                output.push_str(&format!("{:<9} {}\n", label, line.text));
+            }
+        }
+        DisasmFormat::Listing => {
+            output.push_str("; Setun Disassembly\n");
+            output.push_str("; -----------------\n\n");
+            for line in lines {
+                output.push_str(&listing_line(line));
+                output.push('\n');
+            }
+        }
+        DisasmFormat::Json => {
+            output = serde_json::to_string_pretty(lines).expect("disassembly serialization cannot fail");
+            output.push('\n');
+        }
+    }
+    output
+}
+
+/// Render disassembled lines as a listing with coverage markers, like
+/// [`disassemble_with_coverage`].
+pub fn format_lines_with_coverage(lines: &[DisasmLine], coverage: &Coverage) -> String {
+    let mut output = String::new();
+    output.push_str("; Setun Disassembly (coverage: + executed, - not executed)\n");
+    output.push_str("; -----------------\n\n");
+    for line in lines {
+        let (marker, branch) = coverage_marks(coverage, line.addr);
+        output.push_str(&format!("{} {}{}\n", marker, listing_line(line), branch));
+    }
+    let origin = lines.first().map_or(0, |line| line.addr);
+    output.push_str(&format!("\n; Coverage: {}\n", coverage.report(origin, lines.len())));
+    output
+}
+
+fn listing_line(line: &DisasmLine) -> String {
+    let label = line.label.as_ref().map(|l| format!("{}: ", l)).unwrap_or_default();
+    format!("{:03}: {}{}  ; {}", line.addr, label, line.text, line.word)
+}
+
+/// The instruction `word` encodes, if decoding and encoding it again
+/// gives the same word (so `HLT` with a stray address is data).
+fn as_instruction(word: Tryte9) -> Option<Instruction> {
+    decode(word).ok().filter(|instr| try_encode(instr).ok() == Some(word))
+}
+
+/// The address an instruction's operand refers to, with its mode. The
+/// address is the low five trits of the instruction word.
+fn address_operand(word: Tryte9, instr: &Instruction) -> Option<(i32, AddrMode)> {
+    if instr.operand() != OperandKind::Address {
+        return None;
+    }
+    let mode = instr.mode()?;
+    let mut trits = *word.trits();
+    trits[5..].fill(Trit::O);
+    Some((Tryte9::from_trits(trits).to_i32(), mode))
+}
+
+/// Mnemonic the assembler reads back as the same instruction; it takes
+/// `JP` for `JMP`, so the sign jumps are written `JGT` and `JLT`.
+fn asm_mnemonic(instr: &Instruction) -> &'static str {
+    match instr {
+        Instruction::Jp { .. } => "JGT",
+        Instruction::Jn { .. } => "JLT",
+        _ => instr.mnemonic(),
+    }
+}
+
+/// Assembly text of an instruction without named operands.
+fn asm_text(instr: &Instruction) -> String {
+    match instr {
+        Instruction::Jp { addr, mode } | Instruction::Jn { addr, mode } => {
+            format!("{} {}", asm_mnemonic(instr), format_operand(addr, mode))
+        }
+        _ => format_instruction(instr),
+    }
+}
+
+fn named_operand(name: &str, mode: AddrMode) -> String {
+    match mode {
+        AddrMode::Direct => name.to_string(),
+        AddrMode::IndexAdd => format!("{},F+", name),
+        AddrMode::IndexSub => format!("{},F-", name),
+        AddrMode::Indirect => format!("({})", name),
+    }
+}
+
 /// Format a decoded instruction as assembly text.
-fn format_instruction(instr: &Instruction) -> String {
+pub(crate) fn format_instruction(instr: &Instruction) -> String {
     match instr {
         // Arithmetic
         Instruction::Add { addr, mode } => format!("ADD {}", format_operand(addr, mode)),
@@ -60,20 +323,23 @@ fn format_instruction(instr: &Instruction) -> String {
         // Shift
         Instruction::Shl { count } => format!("SHL {}", count),
         Instruction::Shr { count } => format!("SHR {}", count),
+        Instruction::Shm { addr, mode } => format!("SHM {}", format_operand(addr, mode)),
         
         // Special
         Instruction::Nop => "NOP".to_string(),
         Instruction::Tst => "TST".to_string(),
+        Instruction::Ext { opcode, addr, mode } => format!("EXT {} ; opcode {}", format_operand(addr, mode), opcode),
     }
 }
 
 /// Format an address operand with mode suffix.
-fn format_operand(addr: &Tryte9, mode: &AddrMode) -> String {
+pub(crate) fn format_operand(addr: &Tryte9, mode: &AddrMode) -> String {
     let addr_val = addr.to_i32();
     match mode {
         AddrMode::Direct => format!("{}", addr_val),
         AddrMode::IndexAdd => format!("{},F+", addr_val),
         AddrMode::IndexSub => format!("{},F-", addr_val),
+        AddrMode::Indirect => format!("({})", addr_val),
     }
 }
 
@@ -109,4 +375,41 @@ mod tests {
         assert!(result.contains("JMP"));
         assert!(result.contains("F+"));
     }
+    
+    #[test]
+    fn test_disassemble_with_coverage() {
+        let program = [encode(&Instruction::Jz { addr: Tryte9::from_i32(2), mode: AddrMode::Direct }), encode(&Instruction::Hlt), encode(&Instruction::Hlt)];
+        let mut coverage = Coverage::new();
+        coverage.record_step(0, &decode(program[0]).unwrap(), 2);
+        coverage.record_step(2, &Instruction::Hlt, 3);
+        
+        let listing = disassemble_with_coverage(&program, 0, &coverage);
+        assert!(listing.contains("+ 000: JZ 2"));
+        assert!(listing.contains("[taken only]"));
+        assert!(listing.contains("- 001: HLT"));
+        assert!(listing.contains("; Coverage: cells 2/3"));
+    }
+    
+    #[test]
+    fn test_disassemble_lines_with_symbols() {
+        // HLT with a stray address field is data, not an instruction
+        let stray = encode(&Instruction::Hlt).to_i32() + 1;
+        let source = format!("ORG -3\nSTART: LDA TABLE,F+\nJLT PTR,F-\nJMP START\nTABLE: DAT 7\nPTR: DAT -2\nDAT {}", stray);
+        let assembly = crate::asm::assemble_detailed(&source).unwrap();
+        let options = DisasmOptions { origin: -3, symbols: assembly.symbols.clone(), labels: false };
+        let lines = disassemble_lines(&assembly.program, &options);
+        assert_eq!(lines[0].label.as_deref(), Some("START"));
+        assert_eq!(lines[0].text, "LDA TABLE,F+");
+        assert_eq!(lines[1].text, "JLT PTR,F-");
+        assert_eq!(lines[2].text, "JMP START");
+        assert_eq!(lines[5].text, format!("DAT {}", stray));
+        
+        let asm = format_lines(&lines, DisasmFormat::Asm);
+        assert!(asm.contains("ORG -3"));
+        assert_eq!(crate::asm::assemble(&asm).unwrap(), assembly.program);
+        assert!(format_lines(&lines, DisasmFormat::Listing).contains("-03: START: LDA TABLE,F+"));
+        let json: Vec<DisasmLine> = serde_json::from_str(&format_lines(&lines, DisasmFormat::Json)).unwrap();
+        assert_eq!(json, lines);
+        assert_eq!("JSON".parse::<DisasmFormat>(), Ok(DisasmFormat::Json));
+    }
 }
@@ -0,0 +1,213 @@
+//! Drum placement warnings for the historical machine.
+//!
+//! The Setun's ferrite store held three zones of 54 cells, exchanged a
+//! zone at a time with the magnetic drum. Control passing from one zone
+//! to another could mean waiting up to a full revolution for the drum to
+//! bring the next zone round, so where code sat in memory mattered as much
+//! as which instructions it used. [`drum_lint`] takes that worst case for
+//! every zone crossing and reports:
+//! - loops whose body straddles a zone boundary
+//! - straight-line code that runs off the end of a zone
+//! - jumps to a target in another zone
+//!
+//! Where moving a block of code to the start of the next zone would keep
+//! it in one zone, the warning suggests the `ORG` that does it.
+//!
+//! ```
+//! use setun::asm::{check_for, drum::ZONE_SIZE};
+//! use setun::cpu::CpuConfig;
+//!
+//! // A two-word loop placed across the boundary between zones -27..26 and 27..80
+//! let source = "ORG 26\nLOOP: SUB ONE\nJP LOOP\nHLT\nONE: DAT 1";
+//! let report = check_for(source, &CpuConfig::historical());
+//! assert!(report.warnings[0].message.contains("`ORG 27` before LOOP"));
+//! assert_eq!(ZONE_SIZE, 54);
+//! ```
+
+use crate::asm::assembler::{Assembly, AssemblerWarning};
+use crate::asm::lint::{is_data, line_mnemonic};
+use crate::cpu::config::CpuConfig;
+use crate::cpu::decode::{decode_for, AddrMode, Instruction};
+
+/// Cells in a zone, the unit exchanged with the drum.
+pub const ZONE_SIZE: usize = 54;
+
+/// A forward jump landing this few cells into the next zone is taken to
+/// belong with the code before the boundary.
+const JUST_PAST: i32 = 9;
+
+/// Zone arithmetic for a memory whose lowest address is `min`.
+#[derive(Debug, Clone, Copy)]
+struct Zones {
+    min: i32,
+}
+
+impl Zones {
+    /// First address of the zone holding `addr`.
+    fn start(&self, addr: i32) -> i32 {
+        self.min + (addr - self.min).div_euclid(ZONE_SIZE as i32) * ZONE_SIZE as i32
+    }
+
+    fn same(&self, a: i32, b: i32) -> bool {
+        self.start(a) == self.start(b)
+    }
+
+    /// The zone holding `addr`, as its range of addresses.
+    fn describe(&self, addr: i32) -> String {
+        let start = self.start(addr);
+        format!("zone {}..{}", start, start + ZONE_SIZE as i32 - 1)
+    }
+}
+
+/// Warn about code in `assembly` whose placement costs drum exchanges on
+/// a machine with `config`. Only the historical machine had a drum, so
+/// other configurations get no warnings.
+pub fn drum_lint(source: &str, assembly: &Assembly, config: &CpuConfig) -> Vec<AssemblerWarning> {
+    if !config.is_historical() {
+        return Vec::new();
+    }
+    let lines: Vec<&str> = source.lines().collect();
+    let mnemonics: Vec<Option<String>> = (0..assembly.program.len())
+        .map(|index| {
+            let line = *lines.get(assembly.source_map[index].checked_sub(1)?)?;
+            line_mnemonic(line).filter(|m| !is_data(m))
+        })
+        .collect();
+    let zones = Zones { min: -((config.memory_size / 2) as i32) };
+    let addr_of = |index: usize| assembly.origin + index as i32;
+    let index_of = |addr: i32| usize::try_from(addr - assembly.origin).ok().filter(|&i| i < mnemonics.len());
+    let line_of = |index: usize| assembly.source_map[index];
+
+    // A label naming `addr`, or the line holding it
+    let place = |index: usize| {
+        assembly.symbols.iter()
+            .find(|(name, &value)| value == addr_of(index) && !name.starts_with("__"))
+            .map_or_else(|| format!("line {}", line_of(index)), |(name, _)| name.clone())
+    };
+    // Start of the labelled block of code holding `index`
+    let block_start = |index: usize| {
+        (0..=index).rev()
+            .take_while(|&i| mnemonics[i].is_some())
+            .find(|&i| i == 0 || assembly.symbols.iter().any(|(name, &value)| value == addr_of(i) && !name.starts_with("__")))
+            .unwrap_or(index)
+    };
+    // Suggest moving the code from `first` to `last` to the start of the next zone
+    let suggestion = |first: usize, last: usize| {
+        let (lo, hi) = (addr_of(first), addr_of(last));
+        if hi - lo < ZONE_SIZE as i32 {
+            format!("; `ORG {}` before {} keeps it in one zone", zones.start(hi), place(first))
+        } else {
+            format!("; at {} cells it cannot fit in one zone", hi - lo + 1)
+        }
+    };
+
+    let mut warnings = Vec::new();
+    let mut loop_boundaries = Vec::new();
+    for (index, mnemonic) in mnemonics.iter().enumerate() {
+        if mnemonic.is_none() {
+            continue;
+        }
+        let addr = addr_of(index);
+        let Some((mnemonic, target)) = direct_jump(decode_for(assembly.program[index], config).ok()) else {
+            continue;
+        };
+        if zones.same(addr, target) {
+            continue;
+        }
+        let target_name = index_of(target).map_or_else(|| target.to_string(), place);
+        let (code, message) = match index_of(target) {
+            Some(first) if target < addr => {
+                loop_boundaries.push(first..index);
+                ("drum-loop", format!(
+                    "loop from {} straddles {} and {}: every pass waits for the drum{}",
+                    target_name, zones.describe(target), zones.describe(addr), suggestion(first, index),
+                ))
+            }
+            Some(last) if zones.start(target) == zones.start(addr) + ZONE_SIZE as i32
+                && target - zones.start(target) < JUST_PAST => {
+                let block = block_start(index);
+                ("drum-jump", format!(
+                    "{} to {} lands {} cell(s) past the end of {}: a taken jump waits for the drum{}",
+                    mnemonic, target_name, target - zones.start(target) + 1, zones.describe(addr),
+                    suggestion(block, last),
+                ))
+            }
+            _ => ("drum-jump", format!(
+                "{} to {} leaves {} for {}: a taken jump waits for the drum",
+                mnemonic, target_name, zones.describe(addr), zones.describe(target),
+            )),
+        };
+        warnings.push(AssemblerWarning { line: line_of(index), code, message });
+    }
+
+    // Straight-line code crossing into the next zone, unless a loop warning covers it
+    for index in 1..mnemonics.len() {
+        let addr = addr_of(index);
+        let falls = mnemonics[index].is_some()
+            && mnemonics[index - 1].as_deref().is_some_and(|m| !matches!(m, "HLT" | "HALT" | "JMP" | "J"));
+        if !falls || zones.start(addr) != addr || loop_boundaries.iter().any(|body| body.contains(&(index - 1))) {
+            continue;
+        }
+        let first = block_start(index - 1);
+        let last = (index..mnemonics.len())
+            .take_while(|&i| mnemonics[i].is_some())
+            .find(|&i| matches!(mnemonics[i].as_deref(), Some("HLT" | "HALT" | "JMP" | "J")))
+            .unwrap_or(index);
+        warnings.push(AssemblerWarning {
+            line: line_of(index),
+            code: "drum-fallthrough",
+            message: format!(
+                "execution runs off the end of {} into the next zone and waits for the drum{}",
+                zones.describe(addr - 1), suggestion(first, last),
+            ),
+        });
+    }
+    warnings
+}
+
+/// The mnemonic and target of a jump to a fixed address.
+fn direct_jump(instr: Option<Instruction>) -> Option<(&'static str, i32)> {
+    match instr? {
+        instr @ (Instruction::Jmp { addr, mode: AddrMode::Direct }
+        | Instruction::Jz { addr, mode: AddrMode::Direct }
+        | Instruction::Jp { addr, mode: AddrMode::Direct }
+        | Instruction::Jn { addr, mode: AddrMode::Direct }
+        | Instruction::Jop { addr, mode: AddrMode::Direct }
+        | Instruction::Jon { addr, mode: AddrMode::Direct }) => Some((instr.mnemonic(), addr.to_i32())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble_detailed;
+
+    fn messages(source: &str) -> Vec<(usize, String)> {
+        let assembly = assemble_detailed(source).unwrap();
+        drum_lint(source, &assembly, &CpuConfig::historical()).into_iter().map(|w| (w.line, w.message)).collect()
+    }
+
+    #[test]
+    fn test_drum_lint() {
+        // Straight-line code and a jump just past the zone
+        let source = "ORG 24\nSTART: LDA ONE\nJZ DONE\nADD ONE\nADD ONE\nDONE: HLT\nONE: DAT 1";
+        assert_eq!(messages(source), [
+            (3, "JZ to DONE lands 2 cell(s) past the end of zone -27..26: a taken jump waits for the drum; \
+                `ORG 27` before START keeps it in one zone".to_string()),
+            (5, "execution runs off the end of zone -27..26 into the next zone and waits for the drum; \
+                `ORG 27` before START keeps it in one zone".to_string()),
+        ]);
+
+        // The fix leaves nothing to report
+        assert!(messages(&source.replace("ORG 24", "ORG 27")).is_empty());
+
+        // A jump far into another zone has no fix
+        let far = messages("JMP FAR\nORG 60\nFAR: HLT");
+        assert_eq!(far, [(1, "JMP to FAR leaves zone -27..26 for zone 27..80: a taken jump waits for the drum".to_string())]);
+
+        // Only the historical machine has a drum
+        let assembly = assemble_detailed(source).unwrap();
+        assert!(drum_lint(source, &assembly, &CpuConfig::default()).is_empty());
+    }
+}
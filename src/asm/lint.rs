@@ -0,0 +1,189 @@
+//! Static checks on assembly source.
+//!
+//! [`check`] runs the assembler front-end without producing output and
+//! adds lints for common mistakes that still assemble:
+//! - labels that are defined but never referenced (other than `START`)
+//! - programs without a `HLT` instruction
+//! - instructions that fall through into data
+//! - programs larger than the default memory
+//!
+//! [`check_for`] adds the checks that depend on the machine, such as
+//! [drum placement](crate::asm::drum) on the historical one.
+
+use crate::asm::assembler::{assemble_collecting, Assembly, AssemblerError, AssemblerWarning};
+use crate::asm::drum::drum_lint;
+use crate::cpu::config::CpuConfig;
+use crate::diagnostic::Diagnostic;
+use crate::cpu::memory::MEMORY_SIZE;
+
+/// All diagnostics for one source file.
+#[derive(Debug, Clone, Default)]
+pub struct CheckReport {
+    /// Assembly errors, in source order.
+    pub errors: Vec<AssemblerError>,
+    /// Assembler warnings followed by lint findings, in source order.
+    pub warnings: Vec<AssemblerWarning>,
+}
+
+impl CheckReport {
+    /// True if the source assembled without errors.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Errors then warnings as [`Diagnostic`]s in `file`.
+    pub fn diagnostics(&self, file: &str) -> Vec<Diagnostic> {
+        self.errors.iter().map(AssemblerError::diagnostic)
+            .chain(self.warnings.iter().map(AssemblerWarning::diagnostic))
+            .map(|diagnostic| diagnostic.in_file(file))
+            .collect()
+    }
+}
+
+/// Assemble `source` and lint the result, collecting every diagnostic.
+pub fn check(source: &str) -> CheckReport {
+    check_for(source, &CpuConfig::default())
+}
+
+/// [`check`] for a machine with `config`, adding the lints specific to it.
+pub fn check_for(source: &str, config: &CpuConfig) -> CheckReport {
+    let (assembly, errors) = assemble_collecting(source);
+    let mut warnings = assembly.warnings.clone();
+
+    // Lints assume a complete assembly
+    if errors.is_empty() {
+        warnings.extend(lint(source, &assembly));
+        warnings.extend(drum_lint(source, &assembly, config));
+    }
+    warnings.sort_by_key(|w| w.line);
+
+    CheckReport { errors, warnings }
+}
+
+/// Run the lints on a successful assembly of `source`.
+pub fn lint(source: &str, assembly: &Assembly) -> Vec<AssemblerWarning> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mnemonic_at = |index: usize| -> Option<String> {
+        let line = *lines.get(assembly.source_map.get(index)?.checked_sub(1)?)?;
+        line_mnemonic(line)
+    };
+    let mut warnings = Vec::new();
+
+    // Labels nobody refers to (START conventionally marks the entry point)
+    for label in assembly.symbols.keys() {
+        if label != "START" && !assembly.references.contains_key(label) {
+            let line = definition_line(&lines, label).unwrap_or(0);
+            warnings.push(AssemblerWarning {
+                line,
+                code: "unused-label",
+                message: format!("label {} is never referenced", label),
+            });
+        }
+    }
+
+    // A program that never halts will run until the cycle limit
+    let has_halt = (0..assembly.program.len())
+        .any(|i| matches!(mnemonic_at(i).as_deref(), Some("HLT" | "HALT")));
+    if !assembly.program.is_empty() && !has_halt {
+        let line = assembly.source_map.last().copied().unwrap_or(0);
+        warnings.push(AssemblerWarning { line, code: "no-halt", message: "program contains no HLT instruction".into() });
+    }
+
+    // Instructions immediately followed by data execute the data
+    for i in 1..assembly.program.len() {
+        let prev = mnemonic_at(i - 1);
+        let is_data = mnemonic_at(i).as_deref().is_some_and(is_data);
+        let prev_stops = matches!(
+            prev.as_deref(),
+            Some("HLT" | "HALT" | "JMP" | "J" | "DAT" | "DATA" | "DAT18" | ".LONG" | "VAR")
+        );
+        if is_data && prev.is_some() && !prev_stops {
+            warnings.push(AssemblerWarning {
+                line: assembly.source_map[i],
+                code: "falls-into-data",
+                message: "execution falls through into data".into(),
+            });
+        }
+    }
+
+    if assembly.program.len() > MEMORY_SIZE {
+        warnings.push(AssemblerWarning {
+            line: 0,
+            code: "program-too-large",
+            message: format!("program has {} words but memory holds {}", assembly.program.len(), MEMORY_SIZE),
+        });
+    }
+
+    warnings
+}
+
+/// Is `mnemonic` a directive that emits data rather than code?
+pub(crate) fn is_data(mnemonic: &str) -> bool {
+    matches!(mnemonic, "DAT" | "DATA" | "DAT18" | ".LONG" | "VAR")
+}
+
+/// The upper-cased mnemonic or directive on a source line, ignoring labels and comments.
+pub(crate) fn line_mnemonic(line: &str) -> Option<String> {
+    let code = line.split(';').next()?;
+    let code = match code.find(':') {
+        Some(idx) => &code[idx + 1..],
+        None => code,
+    };
+    code.split_whitespace().next().map(|m| m.to_uppercase())
+}
+
+/// Line (1-based) where `label` is defined, as a label or a `VAR`.
+fn definition_line(lines: &[&str], label: &str) -> Option<usize> {
+    lines.iter().position(|line| {
+        let code = line.split(';').next().unwrap_or("");
+        let var = code.trim().split_once(char::is_whitespace)
+            .filter(|(directive, _)| directive.eq_ignore_ascii_case("VAR"))
+            .map(|(_, rest)| rest.split('=').next().unwrap_or("").trim());
+        var.is_some_and(|name| name.eq_ignore_ascii_case(label))
+            || code.find(':').is_some_and(|idx| code[..idx].trim().eq_ignore_ascii_case(label))
+    }).map(|i| i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_clean_program() {
+        let report = check("START: LDA VALUE\nJMP START\nVALUE: DAT 5\nHLT\n");
+        assert!(report.is_ok());
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    }
+
+    #[test]
+    fn test_check_lints() {
+        let report = check("UNUSED: LDA 5\nADD 6\nDAT 7\n");
+        assert!(report.is_ok());
+
+        let messages: Vec<(usize, &str)> = report.warnings.iter()
+            .map(|w| (w.line, w.message.as_str()))
+            .collect();
+        assert_eq!(messages, vec![
+            (1, "label UNUSED is never referenced"),
+            (3, "program contains no HLT instruction"),
+            (3, "execution falls through into data"),
+        ]);
+    }
+
+    #[test]
+    fn test_check_unused_var() {
+        let report = check("LDA USED\nHLT\nVAR USED = 1\nVAR SPARE\n");
+        assert!(report.is_ok());
+        let messages: Vec<(usize, &str)> = report.warnings.iter()
+            .map(|w| (w.line, w.message.as_str()))
+            .collect();
+        assert_eq!(messages, vec![(4, "label SPARE is never referenced")]);
+    }
+
+    #[test]
+    fn test_check_collects_errors() {
+        let report = check("LDA NOWHERE\nBOGUS\nHLT\n");
+        assert!(!report.is_ok());
+        assert_eq!(report.errors.len(), 2);
+    }
+}
@@ -3,11 +3,26 @@
 //! This module provides:
 //! - A simple two-pass assembler (text → TROM binary format)
 //! - A disassembler (TROM → readable text)
+//! - Source checks and lints for editor integration
+//! - Drum placement warnings for the historical machine
+//! - Hexdump-style views of memory images
+//! - Instruction usage statistics across many programs
+//! - Multi-file projects built from a TOML project file
+//! - Round-trip property generators (tests and the `testing` feature)
 
 pub mod assembler;
 pub mod disasm;
 pub mod trom;
+pub mod lint;
+pub mod drum;
+pub mod project;
+pub mod view;
+pub mod stats;
+#[cfg(any(test, feature = "testing"))]
+pub mod roundtrip;
 
-pub use assembler::{assemble, AssemblerError};
+pub use assembler::{assemble, assemble_detailed, assemble_collecting, assemble_with_defines, Assembly, AssemblerError, AssemblerWarning};
+pub use lint::{check, check_for, CheckReport};
+pub use project::{Build, Project, ProjectError};
 pub use disasm::disassemble;
-pub use trom::{TromFile, load_trom, save_trom};
+pub use trom::{TromFile, load_trom, parse_trom, save_trom, save_image};
@@ -0,0 +1,355 @@
+//! Multi-file projects.
+//!
+//! A project file (TOML, usually `setun.toml`) lists the sources of one
+//! program, in link order, with the settings `setun-emu build` needs:
+//!
+//! ```toml
+//! name = "demo"
+//! include = ["lib"]          # searched by INCLUDE after the including file's directory
+//!
+//! [defines]                  # symbols set before assembly, like labels
+//! SIZE = 10
+//!
+//! [[source]]
+//! path = "main.asm"
+//!
+//! [[source]]
+//! path = "tables.asm"
+//! org = 40                   # load address of this file's first word
+//!
+//! [output]
+//! format = "trom"            # or "tape" for a boot tape
+//! trom = "build/demo.trom"   # default: <name>.trom (or .tape)
+//! symbols = "build/demo.sym"
+//! listing = "build/demo.lst"
+//! ```
+//!
+//! Linking joins the sources into one assembly, so labels are shared
+//! between files. A line `INCLUDE file` in any source is replaced by that
+//! file's lines. Paths are relative to the directory of the project file,
+//! and every word of the result keeps the file and line it came from:
+//!
+//! ```
+//! use setun::asm::project::Project;
+//!
+//! let dir = std::env::temp_dir().join("setun-project-doc");
+//! std::fs::create_dir_all(&dir).unwrap();
+//! std::fs::write(dir.join("main.asm"), "LDA VALUE\nHLT").unwrap();
+//! std::fs::write(dir.join("data.asm"), "VALUE: DAT SIZE").unwrap();
+//!
+//! let project = Project::from_toml(
+//!     "name = \"demo\"\n[defines]\nSIZE = 7\n[[source]]\npath = \"main.asm\"\n[[source]]\npath = \"data.asm\"",
+//!     &dir,
+//! ).unwrap();
+//! let build = project.build().unwrap();
+//! assert_eq!(build.assembly.program[2].to_i32(), 7);
+//! assert_eq!(build.location(2).unwrap().file, "data.asm");
+//! ```
+
+use crate::asm::assembler::{assemble_with_defines, Assembly};
+use crate::asm::disasm::disassemble_instruction;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Deepest chain of `INCLUDE`s followed before giving up.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// A project file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Project {
+    /// Project name, used for default output names.
+    pub name: String,
+    /// Source files, in link order.
+    #[serde(default, rename = "source")]
+    pub sources: Vec<ProjectSource>,
+    /// Directories searched by `INCLUDE`.
+    #[serde(default)]
+    pub include: Vec<PathBuf>,
+    /// Symbols defined before assembly.
+    #[serde(default)]
+    pub defines: BTreeMap<String, i32>,
+    /// What `build` writes.
+    #[serde(default)]
+    pub output: ProjectOutput,
+    /// Directory that relative paths are resolved against.
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+/// One source file of a project.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectSource {
+    /// Path of the file.
+    pub path: PathBuf,
+    /// Address of the file's first word; by default it follows the
+    /// previous file.
+    #[serde(default)]
+    pub org: Option<i32>,
+}
+
+/// Image format written by `build`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+    /// A TROM file.
+    #[default]
+    Trom,
+    /// A boot tape for `run --boot-from-tape`.
+    Tape,
+}
+
+/// Output files of a project.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectOutput {
+    /// Format of the linked image.
+    #[serde(default)]
+    pub format: ImageFormat,
+    /// Path of the linked image; defaults to the project name with
+    /// `.trom` or `.tape`.
+    #[serde(default)]
+    pub trom: Option<PathBuf>,
+    /// Where to write the symbol table as `NAME = addr` lines.
+    #[serde(default)]
+    pub symbols: Option<PathBuf>,
+    /// Where to write an annotated listing.
+    #[serde(default)]
+    pub listing: Option<PathBuf>,
+}
+
+/// Where a line of the linked source came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceLocation {
+    /// File, as written in the project or `INCLUDE` line.
+    pub file: String,
+    /// Line in that file (1-based), or 0 for the `ORG` of a load address.
+    pub line: usize,
+    /// The line's text.
+    pub text: String,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Line 0 is an `ORG` added for the file's load address
+        match self.line {
+            0 => write!(f, "{}", self.file),
+            line => write!(f, "{}:{}", self.file, line),
+        }
+    }
+}
+
+/// A linked project.
+#[derive(Debug, Clone)]
+pub struct Build {
+    /// The assembled program.
+    pub assembly: Assembly,
+    /// Origin of each line of the linked source, by line number - 1.
+    pub lines: Vec<SourceLocation>,
+}
+
+/// Errors loading or building a project.
+#[derive(Debug, Clone, Error)]
+pub enum ProjectError {
+    #[error("cannot read {path}: {message}")]
+    Io { path: String, message: String },
+
+    #[error("invalid project file: {0}")]
+    Manifest(String),
+
+    #[error("project has no sources")]
+    NoSources,
+
+    #[error("{location}: {message}")]
+    Include { location: String, message: String },
+
+    #[error("{location}: {message}")]
+    Assembly { location: String, message: String },
+}
+
+impl Project {
+    /// Read a project file; relative paths are resolved against its directory.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ProjectError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| io_error(path, e))?;
+        let root = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        Self::from_toml(&text, &root)
+    }
+
+    /// Parse project TOML, resolving relative paths against `root`.
+    pub fn from_toml(text: &str, root: &Path) -> Result<Self, ProjectError> {
+        let mut project: Project = toml::from_str(text).map_err(|e| ProjectError::Manifest(e.message().to_string()))?;
+        project.root = root.to_path_buf();
+        Ok(project)
+    }
+
+    /// Path of the linked image.
+    pub fn image_path(&self) -> PathBuf {
+        match &self.output.trom {
+            Some(path) => self.root.join(path),
+            None => {
+                let extension = match self.output.format {
+                    ImageFormat::Trom => "trom",
+                    ImageFormat::Tape => "tape",
+                };
+                self.root.join(format!("{}.{}", self.name, extension))
+            }
+        }
+    }
+
+    /// Path of the symbol file, if requested.
+    pub fn symbols_path(&self) -> Option<PathBuf> {
+        self.output.symbols.as_ref().map(|path| self.root.join(path))
+    }
+
+    /// Path of the listing file, if requested.
+    pub fn listing_path(&self) -> Option<PathBuf> {
+        self.output.listing.as_ref().map(|path| self.root.join(path))
+    }
+
+    /// Link the sources and assemble them.
+    pub fn build(&self) -> Result<Build, ProjectError> {
+        if self.sources.is_empty() {
+            return Err(ProjectError::NoSources);
+        }
+        let mut lines = Vec::new();
+        for source in &self.sources {
+            if let Some(org) = source.org {
+                lines.push(SourceLocation {
+                    file: source.path.display().to_string(),
+                    line: 0,
+                    text: format!("ORG {}", org),
+                });
+            }
+            let path = self.root.join(&source.path);
+            self.read_into(&path, &source.path.display().to_string(), 0, &mut lines)?;
+        }
+
+        let text: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+        let (assembly, errors) = assemble_with_defines(&text.join("\n"), &self.defines);
+        if let Some(e) = errors.first() {
+            let location = lines.get(e.line().saturating_sub(1))
+                .map_or_else(|| "<project>".to_string(), |l| l.to_string());
+            return Err(ProjectError::Assembly { location, message: e.to_string() });
+        }
+        Ok(Build { assembly, lines })
+    }
+
+    /// Append the lines of `path`, expanding `INCLUDE`s.
+    fn read_into(&self, path: &Path, name: &str, depth: usize, lines: &mut Vec<SourceLocation>) -> Result<(), ProjectError> {
+        let text = std::fs::read_to_string(path).map_err(|e| io_error(path, e))?;
+        for (index, line) in text.lines().enumerate() {
+            let location = SourceLocation { file: name.to_string(), line: index + 1, text: line.to_string() };
+            let Some(target) = include_target(line) else {
+                lines.push(location);
+                continue;
+            };
+            if depth >= MAX_INCLUDE_DEPTH {
+                return Err(ProjectError::Include {
+                    location: location.to_string(),
+                    message: format!("INCLUDE nested more than {} deep (is a file including itself?)", MAX_INCLUDE_DEPTH),
+                });
+            }
+            let here = path.parent().unwrap_or(Path::new(""));
+            let found = std::iter::once(here.to_path_buf())
+                .chain(self.include.iter().map(|dir| self.root.join(dir)))
+                .map(|dir| dir.join(target))
+                .find(|candidate| candidate.is_file())
+                .ok_or_else(|| ProjectError::Include {
+                    location: location.to_string(),
+                    message: format!("cannot find {} in {} or the include paths", target, here.display()),
+                })?;
+            self.read_into(&found, target, depth + 1, lines)?;
+        }
+        Ok(())
+    }
+}
+
+impl Build {
+    /// Where the word at `index` of the program came from, if it came
+    /// from a source line.
+    pub fn location(&self, index: usize) -> Option<&SourceLocation> {
+        let line = self.assembly.line_for_index(index)?;
+        self.lines.get(line - 1)
+    }
+
+    /// The symbol table as `NAME = addr` lines, the form `disasm --symbols` reads.
+    pub fn symbols_text(&self) -> String {
+        self.assembly.symbols.iter()
+            .map(|(name, addr)| format!("{} = {}\n", name, addr))
+            .collect()
+    }
+
+    /// Every word with its address, disassembly and source line.
+    pub fn listing(&self) -> String {
+        let mut out = String::new();
+        for (index, word) in self.assembly.program.iter().enumerate() {
+            let addr = self.assembly.origin + index as i32;
+            let text = disassemble_instruction(*word);
+            match self.location(index) {
+                Some(location) => out.push_str(&format!(
+                    "{:+04}  {}  {:<12} {:<20} {}\n",
+                    addr, word, text, location.to_string(), location.text.trim(),
+                )),
+                None => out.push_str(&format!("{:+04}  {}  {}\n", addr, word, text)),
+            }
+        }
+        out
+    }
+}
+
+/// The file named by an `INCLUDE file` or `INCLUDE "file"` line.
+fn include_target(line: &str) -> Option<&str> {
+    let code = line.split(';').next()?.trim();
+    let (directive, rest) = code.split_once(char::is_whitespace)?;
+    if !directive.eq_ignore_ascii_case("INCLUDE") {
+        return None;
+    }
+    let rest = rest.trim();
+    Some(rest.strip_prefix('"').and_then(|r| r.strip_suffix('"')).unwrap_or(rest))
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> ProjectError {
+    ProjectError::Io { path: path.display().to_string(), message: e.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_include_and_errors() {
+        let dir = std::env::temp_dir().join(format!("setun-project-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(dir.join("main.asm"), "LDA ONE\nINCLUDE \"util.asm\" ; shared\nHLT").unwrap();
+        std::fs::write(dir.join("lib/util.asm"), "ADD ONE").unwrap();
+        std::fs::write(dir.join("data.asm"), "ONE: DAT 1\nDAT SIZE").unwrap();
+        let manifest = "name = \"t\"\ninclude = [\"lib\"]\n[defines]\nSIZE = 4\n\
+            [[source]]\npath = \"main.asm\"\n[[source]]\npath = \"data.asm\"\norg = 10\n\
+            [output]\nformat = \"tape\"\nsymbols = \"t.sym\"";
+        let project = Project::from_toml(manifest, &dir).unwrap();
+        assert_eq!(project.image_path(), dir.join("t.tape"));
+
+        let build = project.build().unwrap();
+        let program = &build.assembly.program;
+        assert_eq!(program.len(), 12);
+        assert_eq!(program[11].to_i32(), 4);
+        assert_eq!(build.assembly.symbols["ONE"], 10);
+        assert_eq!(build.location(1).unwrap().to_string(), "util.asm:1");
+        assert!(build.symbols_text().contains("ONE = 10\n"));
+        assert!(build.listing().contains("+010  "));
+
+        std::fs::write(dir.join("data.asm"), "ONE: DAT 1\nLDA NOPE").unwrap();
+        let error = project.build().unwrap_err().to_string();
+        assert!(error.starts_with("data.asm:2: "), "{}", error);
+
+        std::fs::write(dir.join("lib/util.asm"), "INCLUDE util.asm").unwrap();
+        assert!(matches!(project.build(), Err(ProjectError::Include { .. })));
+        assert!(matches!(Project::from_toml("name = \"t\"\nbogus = 1", &dir), Err(ProjectError::Manifest(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,184 @@
+//! Round-trip property checks for the instruction encoding and the
+//! assembler/disassembler pair.
+//!
+//! The strategies generate random valid [`Instruction`]s and random
+//! programs for a machine configuration, and the checks assert the two
+//! fixpoints every encoding must have: encode → decode gives back the
+//! instruction, and disassemble → assemble gives back the program, with
+//! a second disassembly identical to the first. Downstream ISA-variant
+//! authors can run them against their own configurations with
+//! [proptest](https://docs.rs/proptest); the module is built for this
+//! crate's tests and, for other crates, with the `testing` feature:
+//!
+//! ```
+//! use proptest::prelude::*;
+//! use setun::asm::roundtrip::{check_encode_decode, instruction};
+//! use setun::cpu::CpuConfig;
+//!
+//! let config = CpuConfig::historical();
+//! proptest!(|(instr in instruction(&config))| {
+//!     prop_assert_eq!(check_encode_decode(&instr, &config), Ok(()));
+//! });
+//! ```
+
+use crate::asm::assemble_detailed;
+use crate::asm::disasm::{disassemble_lines, format_lines, DisasmFormat, DisasmOptions};
+use crate::cpu::config::CpuConfig;
+use crate::cpu::decode::{decode_for, try_encode, AddrMode, Instruction, ADDR_FIELD_MAX};
+use crate::ternary::Tryte9;
+use proptest::prelude::*;
+use thiserror::Error;
+
+/// Instructions with an address operand, by constructor.
+const ADDRESSED: [fn(Tryte9, AddrMode) -> Instruction; 20] = [
+    |addr, mode| Instruction::Add { addr, mode },
+    |addr, mode| Instruction::Sub { addr, mode },
+    |addr, mode| Instruction::Mul { addr, mode },
+    |addr, mode| Instruction::Div { addr, mode },
+    |addr, mode| Instruction::AddAbs { addr, mode },
+    |addr, mode| Instruction::SubAbs { addr, mode },
+    |addr, mode| Instruction::Lda { addr, mode },
+    |addr, mode| Instruction::Sta { addr, mode },
+    |addr, mode| Instruction::LdaUnsigned { addr, mode },
+    |addr, mode| Instruction::Ldf { addr, mode },
+    |addr, mode| Instruction::Stf { addr, mode },
+    |addr, mode| Instruction::Ldr { addr, mode },
+    |addr, mode| Instruction::Str { addr, mode },
+    |addr, mode| Instruction::Xchg { addr, mode },
+    |addr, mode| Instruction::Jmp { addr, mode },
+    |addr, mode| Instruction::Jz { addr, mode },
+    |addr, mode| Instruction::Jp { addr, mode },
+    |addr, mode| Instruction::Jn { addr, mode },
+    |addr, mode| Instruction::Jop { addr, mode },
+    |addr, mode| Instruction::Jon { addr, mode },
+];
+
+/// Random instructions that a machine with `config` can encode and
+/// decode: addresses and shift counts within the 5-trit field, the
+/// address modes `config` reads, and `SHM` only on the historical
+/// machine. Extension instructions and `TST`, which has no 3-trit
+/// opcode, are not generated.
+pub fn instruction(config: &CpuConfig) -> BoxedStrategy<Instruction> {
+    let modes = if config.indirect {
+        vec![AddrMode::Direct, AddrMode::IndexAdd, AddrMode::Indirect]
+    } else {
+        vec![AddrMode::Direct, AddrMode::IndexAdd, AddrMode::IndexSub]
+    };
+    let mut constructors = ADDRESSED.to_vec();
+    if config.is_historical() {
+        constructors.push(|addr, mode| Instruction::Shm { addr, mode });
+    }
+    let addr = (-ADDR_FIELD_MAX..=ADDR_FIELD_MAX).prop_map(Tryte9::from_i32);
+    let count = -(ADDR_FIELD_MAX as i8)..=ADDR_FIELD_MAX as i8;
+    let addressed = (proptest::sample::select(constructors), addr, proptest::sample::select(modes))
+        .prop_map(|(constructor, addr, mode)| constructor(addr, mode));
+    prop_oneof![
+        6 => addressed,
+        1 => count.clone().prop_map(|count| Instruction::Shl { count }),
+        1 => count.prop_map(|count| Instruction::Shr { count }),
+        1 => proptest::sample::select(vec![Instruction::Hlt, Instruction::Nop]),
+    ].boxed()
+}
+
+/// Random programs of up to `max_len` words for `config`: encoded
+/// instructions mixed with arbitrary data words.
+pub fn program(config: &CpuConfig, max_len: usize) -> BoxedStrategy<Vec<Tryte9>> {
+    let word = prop_oneof![
+        3 => instruction(config).prop_map(|instr| try_encode(&instr).expect("generated instructions encode")),
+        1 => (Tryte9::MIN..=Tryte9::MAX).prop_map(Tryte9::from_i32),
+    ];
+    proptest::collection::vec(word, 1..=max_len).boxed()
+}
+
+/// A broken round trip.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RoundtripError {
+    #[error("{instr:?} does not encode: {message}")]
+    Encode { instr: Instruction, message: String },
+
+    #[error("{instr:?} encodes to {word}, which decodes to {decoded}")]
+    Decode { instr: Instruction, word: Tryte9, decoded: String },
+
+    #[error("disassembly does not assemble: {message}\n{source_text}")]
+    Assemble { source_text: String, message: String },
+
+    #[error("word {index} is {expected} but reassembles to {got}")]
+    Mismatch { index: usize, expected: Tryte9, got: String },
+
+    #[error("disassembling the reassembled program gives different text:\n{first}\n---\n{second}")]
+    NotFixpoint { first: String, second: String },
+}
+
+/// Check that `instr` encodes, and that the word decodes back to it and
+/// re-encodes to the same word on a machine with `config`.
+pub fn check_encode_decode(instr: &Instruction, config: &CpuConfig) -> Result<(), RoundtripError> {
+    let word = try_encode(instr)
+        .map_err(|e| RoundtripError::Encode { instr: *instr, message: e.to_string() })?;
+    match decode_for(word, config) {
+        Ok(decoded) if decoded == *instr && try_encode(&decoded).ok() == Some(word) => Ok(()),
+        Ok(decoded) => Err(RoundtripError::Decode { instr: *instr, word, decoded: format!("{:?}", decoded) }),
+        Err(e) => Err(RoundtripError::Decode { instr: *instr, word, decoded: e.to_string() }),
+    }
+}
+
+/// Check that `program`, loaded at `origin`, disassembles (with invented
+/// labels) to source that assembles back to the same words, and that
+/// disassembling those words again gives the same source.
+pub fn check_assembly_fixpoint(program: &[Tryte9], origin: i32) -> Result<(), RoundtripError> {
+    let options = DisasmOptions { origin, labels: true, ..Default::default() };
+    let first = format_lines(&disassemble_lines(program, &options), DisasmFormat::Asm);
+    let assembly = assemble_detailed(&first)
+        .map_err(|e| RoundtripError::Assemble { source_text: first.clone(), message: e.to_string() })?;
+
+    for index in 0..program.len().max(assembly.program.len()) {
+        let got = assembly.program.get(index);
+        if got != program.get(index) {
+            return Err(RoundtripError::Mismatch {
+                index,
+                expected: program.get(index).copied().unwrap_or_default(),
+                got: got.map_or("nothing".into(), |word| word.to_string()),
+            });
+        }
+    }
+
+    let second = format_lines(&disassemble_lines(&assembly.program, &options), DisasmFormat::Asm);
+    if second != first {
+        return Err(RoundtripError::NotFixpoint { first, second });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configs() -> [CpuConfig; 3] {
+        [CpuConfig::default(), CpuConfig::historical(), CpuConfig { indirect: true, ..CpuConfig::default() }]
+    }
+
+    #[test]
+    fn test_checks_report_holes() {
+        // The address field holds five trits, so 122 cannot survive
+        let wide = Instruction::Lda { addr: Tryte9::from_i32(122), mode: AddrMode::Direct };
+        assert!(matches!(check_encode_decode(&wide, &CpuConfig::default()), Err(RoundtripError::Encode { .. })));
+
+        // SHM does not decode outside the historical machine
+        let shm = Instruction::Shm { addr: Tryte9::from_i32(1), mode: AddrMode::Direct };
+        assert!(matches!(check_encode_decode(&shm, &CpuConfig::default()), Err(RoundtripError::Decode { .. })));
+        assert_eq!(check_encode_decode(&shm, &CpuConfig::historical()), Ok(()));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_encode_decode((config, instr) in proptest::sample::select(configs().to_vec())
+            .prop_flat_map(|config| (Just(config.clone()), instruction(&config))))
+        {
+            prop_assert_eq!(check_encode_decode(&instr, &config), Ok(()));
+        }
+
+        #[test]
+        fn prop_assembly_fixpoint(program in program(&CpuConfig::historical(), 40), origin in -81i32..=40) {
+            prop_assert_eq!(check_assembly_fixpoint(&program, origin), Ok(()));
+        }
+    }
+}
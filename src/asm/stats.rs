@@ -0,0 +1,210 @@
+//! Instruction usage across a corpus of programs.
+//!
+//! [`CorpusStats`] reads many programs without running them and counts how
+//! often each opcode and address mode appears, along with how large the
+//! programs are. Assembly sources are counted by their instruction lines,
+//! so `DAT` words are never mistaken for code; in a TROM image, which
+//! keeps no such distinction, every word that decodes counts as an
+//! instruction, the way the disassembler reads it.
+//!
+//! ```
+//! use setun::asm::stats::CorpusStats;
+//! use setun::asm::assemble;
+//! use setun::cpu::CpuConfig;
+//!
+//! let config = CpuConfig::default();
+//! let mut stats = CorpusStats::new();
+//! stats.add_source("add.asm", "LDA 4\nADD 5,F+\nHLT\nDAT 42\nDAT 17", &config).unwrap();
+//! stats.add_image("loop.trom", &assemble("LDA 3\nJMP 0\nHLT").unwrap(), &config);
+//!
+//! assert_eq!(stats.opcodes["LDA"], 2);
+//! assert_eq!(stats.modes["F+"], 1);
+//! assert_eq!(stats.instructions(), 6);
+//! assert_eq!(stats.sizes().unwrap().max, 5);
+//! ```
+
+use crate::asm::assembler::{assemble_detailed, AssemblerError};
+use crate::asm::lint::{is_data, line_mnemonic};
+use crate::asm::trom::load_trom;
+use crate::cpu::config::CpuConfig;
+use crate::cpu::decode::{decode_for, AddrMode};
+use crate::ternary::Tryte9;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Size of one program.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProgramSize {
+    /// File the program came from.
+    pub name: String,
+    /// Words in the image.
+    pub words: usize,
+    /// Words counted as instructions.
+    pub instructions: usize,
+}
+
+/// Smallest, median, largest and mean program size, in words.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SizeSummary {
+    pub min: usize,
+    pub median: usize,
+    pub max: usize,
+    pub mean: f64,
+}
+
+/// Aggregate static statistics of a set of programs.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CorpusStats {
+    /// Each program counted, in the order added.
+    pub programs: Vec<ProgramSize>,
+    /// Instructions per mnemonic.
+    pub opcodes: BTreeMap<&'static str, usize>,
+    /// Instructions with an address operand per address mode, written as
+    /// in assembly: `direct`, `F+`, `F-` or `indirect`.
+    pub modes: BTreeMap<&'static str, usize>,
+    /// Files that could not be read, with the reason.
+    pub failures: Vec<(String, String)>,
+}
+
+impl CorpusStats {
+    /// No programs yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every `.asm` and `.trom` file under `dir`, in its subdirectories
+    /// too, decoded for a machine with `config`. Files that fail to load
+    /// are listed in [`failures`](Self::failures); only an unreadable
+    /// directory is an error.
+    pub fn scan(dir: &Path, config: &CpuConfig) -> std::io::Result<Self> {
+        let mut stats = Self::new();
+        let mut files = Vec::new();
+        collect_files(dir, &mut files)?;
+        files.sort();
+        for path in files {
+            if let Err(message) = stats.add_file(&path, config) {
+                stats.failures.push((path.display().to_string(), message));
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Count the program in `path`, an assembly source or a TROM image.
+    pub fn add_file(&mut self, path: &Path, config: &CpuConfig) -> Result<(), String> {
+        let name = path.display().to_string();
+        if path.extension().is_some_and(|ext| ext == "asm") {
+            let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+            self.add_source(name, &source, config).map_err(|e| e.to_string())
+        } else {
+            let trom = load_trom(path).map_err(|e| e.to_string())?;
+            self.add_image(name, &trom.instructions, config);
+            Ok(())
+        }
+    }
+
+    /// Assemble `source` and count its instruction lines.
+    pub fn add_source(&mut self, name: impl Into<String>, source: &str, config: &CpuConfig) -> Result<(), AssemblerError> {
+        let assembly = assemble_detailed(source)?;
+        let lines: Vec<&str> = source.lines().collect();
+        let is_code = |index: usize| {
+            assembly.line_for_index(index)
+                .and_then(|line| line_mnemonic(lines.get(line - 1)?))
+                .is_some_and(|mnemonic| !is_data(&mnemonic))
+        };
+        self.count(name.into(), &assembly.program, is_code, config);
+        Ok(())
+    }
+
+    /// Count an image, taking every word that decodes as an instruction.
+    pub fn add_image(&mut self, name: impl Into<String>, words: &[Tryte9], config: &CpuConfig) {
+        self.count(name.into(), words, |_| true, config);
+    }
+
+    fn count(&mut self, name: String, words: &[Tryte9], is_code: impl Fn(usize) -> bool, config: &CpuConfig) {
+        let mut instructions = 0;
+        for (index, word) in words.iter().enumerate() {
+            let Some(instr) = decode_for(*word, config).ok().filter(|_| is_code(index)) else {
+                continue;
+            };
+            instructions += 1;
+            *self.opcodes.entry(instr.mnemonic()).or_default() += 1;
+            if let Some(mode) = instr.mode() {
+                let mode = match mode {
+                    AddrMode::Direct => "direct",
+                    AddrMode::IndexAdd => "F+",
+                    AddrMode::IndexSub => "F-",
+                    AddrMode::Indirect => "indirect",
+                };
+                *self.modes.entry(mode).or_default() += 1;
+            }
+        }
+        self.programs.push(ProgramSize { name, words: words.len(), instructions });
+    }
+
+    /// Instructions counted in all programs.
+    pub fn instructions(&self) -> usize {
+        self.programs.iter().map(|p| p.instructions).sum()
+    }
+
+    /// Words in all programs.
+    pub fn words(&self) -> usize {
+        self.programs.iter().map(|p| p.words).sum()
+    }
+
+    /// Mnemonics from the most to the least used, with their counts.
+    pub fn ranked_opcodes(&self) -> Vec<(&'static str, usize)> {
+        let mut ranked: Vec<_> = self.opcodes.iter().map(|(&m, &n)| (m, n)).collect();
+        ranked.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        ranked
+    }
+
+    /// Program sizes in words, or `None` without programs.
+    pub fn sizes(&self) -> Option<SizeSummary> {
+        let mut words: Vec<usize> = self.programs.iter().map(|p| p.words).collect();
+        words.sort_unstable();
+        Some(SizeSummary {
+            min: *words.first()?,
+            median: words[words.len() / 2],
+            max: *words.last()?,
+            mean: self.words() as f64 / words.len() as f64,
+        })
+    }
+}
+
+/// Add the `.asm` and `.trom` files under `dir` to `files`.
+fn collect_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "asm" || ext == "trom") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_examples() {
+        let config = CpuConfig::default();
+        let stats = CorpusStats::scan(Path::new("examples"), &config).unwrap();
+        assert!(stats.programs.len() >= 5, "{:?}", stats.programs);
+        assert!(stats.failures.is_empty(), "{:?}", stats.failures);
+        assert_eq!(stats.opcodes.values().sum::<usize>(), stats.instructions());
+        assert!(stats.ranked_opcodes().windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+        // Data lines of a source are not instructions, but in an image they decode
+        let mut stats = CorpusStats::new();
+        stats.add_source("a.asm", "LDA 2\nHLT\nDAT 3", &config).unwrap();
+        stats.add_image("a.trom", &crate::asm::assemble("LDA 2\nHLT\nDAT 3").unwrap(), &config);
+        let counts: Vec<usize> = stats.programs.iter().map(|p| p.instructions).collect();
+        assert_eq!(counts, [2, 3]);
+        assert_eq!(stats.sizes().map(|s| (s.min, s.median, s.max)), Some((3, 3, 3)));
+        assert!(stats.add_source("bad.asm", "BOGUS", &config).is_err());
+    }
+}
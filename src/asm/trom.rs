@@ -5,12 +5,30 @@
 //! - Trits represented as N/O/P characters
 //! - Lines starting with `;` are comments
 //! - Blank lines are ignored
+//!
+//! Files written by this crate start with a version header, `;! trom 1`,
+//! which older readers skip as a comment. Files without one predate the
+//! header and are read as version 0, whose body is the same; a version
+//! newer than [`TROM_VERSION`] is rejected rather than misread.
+//!
+//! Written files also carry `;! checksum <word>`, the
+//! [ternary checksum](crate::ternary::hash::checksum) of their words. A
+//! file with that header is checked when loaded, so a damaged or
+//! hand-edited image is reported instead of run; files without one load
+//! unchecked.
 
 use crate::ternary::Tryte9;
+use crate::ternary::hash::checksum;
+use crate::cpu::Memory;
+use crate::diagnostic::Diagnostic;
+use serde::{Serialize, Deserialize};
 use std::path::Path;
-use std::io::{BufRead, BufReader, Write};
+use std::io::Write;
 use thiserror::Error;
 
+/// Current TROM format version.
+pub const TROM_VERSION: u32 = 1;
+
 /// A loaded TROM file.
 #[derive(Debug, Clone)]
 pub struct TromFile {
@@ -53,17 +71,31 @@ impl Default for TromFile {
 }
 
 /// Load a TROM file from disk.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display())))]
 pub fn load_trom<P: AsRef<Path>>(path: P) -> Result<TromFile, TromError> {
-    let file = std::fs::File::open(path.as_ref())
+    let text = std::fs::read_to_string(path.as_ref())
         .map_err(|e| TromError::IoError(e.to_string()))?;
-    let reader = BufReader::new(file);
-    
+    parse_trom(&text)
+}
+
+/// Parse TROM text.
+///
+/// Never panics, whatever the input, so it is safe to use on untrusted
+/// files (the `trom` fuzz target exercises it).
+pub fn parse_trom(text: &str) -> Result<TromFile, TromError> {
     let mut trom = TromFile::new();
+    let mut expected_checksum = None;
     
-    for (line_num, line_result) in reader.lines().enumerate() {
-        let line = line_result.map_err(|e| TromError::IoError(e.to_string()))?;
+    for (line_num, line) in text.lines().enumerate() {
         let trimmed = line.trim();
         
+        if let Some(header) = trimmed.strip_prefix(";!") {
+            if let Some(sum) = check_header(header, line_num + 1)? {
+                expected_checksum = Some(sum);
+            }
+            continue;
+        }
+        
         // Skip empty lines and comments
         if trimmed.is_empty() || trimmed.starts_with(';') {
             continue;
@@ -90,10 +122,43 @@ pub fn load_trom<P: AsRef<Path>>(path: P) -> Result<TromFile, TromError> {
         
         trom.push(instr, trimmed);
     }
+    event!(debug, words = trom.len(), "parsed TROM");
+    
+    if let Some(expected) = expected_checksum {
+        let found = checksum(&trom.instructions);
+        if found != expected {
+            return Err(TromError::ChecksumMismatch { expected, found });
+        }
+    }
     
     Ok(trom)
 }
 
+/// Check a `;!` header line, rejecting versions this reader cannot read,
+/// and return the checksum it declares, if any. Unknown header keys are
+/// ignored so later versions can add some.
+fn check_header(header: &str, line: usize) -> Result<Option<Tryte9>, TromError> {
+    let mut words = header.split_whitespace();
+    match words.next() {
+        Some("trom") => {
+            let version = words.next()
+                .and_then(|v| v.parse::<u32>().ok())
+                .ok_or_else(|| TromError::ParseError { line, message: "invalid version header".into() })?;
+            if version > TROM_VERSION {
+                return Err(TromError::UnsupportedVersion { found: version, newest: TROM_VERSION });
+            }
+            Ok(None)
+        }
+        Some("checksum") => {
+            let sum = words.next()
+                .and_then(|v| Tryte9::parse(v).ok())
+                .ok_or_else(|| TromError::ParseError { line, message: "invalid checksum header".into() })?;
+            Ok(Some(sum))
+        }
+        _ => Ok(None),
+    }
+}
+
 /// Save a TROM file to disk.
 pub fn save_trom<P: AsRef<Path>>(path: P, trom: &TromFile) -> Result<(), TromError> {
     let mut file = std::fs::File::create(path.as_ref())
@@ -101,6 +166,10 @@ pub fn save_trom<P: AsRef<Path>>(path: P, trom: &TromFile) -> Result<(), TromErr
     
     writeln!(file, "; Setun TROM file")
         .map_err(|e| TromError::IoError(e.to_string()))?;
+    writeln!(file, ";! trom {}", TROM_VERSION)
+        .map_err(|e| TromError::IoError(e.to_string()))?;
+    writeln!(file, ";! checksum {}", checksum(&trom.instructions))
+        .map_err(|e| TromError::IoError(e.to_string()))?;
     writeln!(file, "; {} instructions", trom.len())
         .map_err(|e| TromError::IoError(e.to_string()))?;
     writeln!(file).map_err(|e| TromError::IoError(e.to_string()))?;
@@ -123,6 +192,77 @@ pub fn save_instructions<P: AsRef<Path>>(path: P, instructions: &[Tryte9]) -> Re
     save_trom(path, &trom)
 }
 
+/// Save every memory cell as a TROM image, annotated with addresses.
+///
+/// The first line is the lowest address, so the image loads back into an
+/// identical memory with `--load-at` set to [`Memory::min_addr`].
+pub fn save_image<P: AsRef<Path>>(path: P, mem: &Memory) -> Result<(), TromError> {
+    let mut file = std::fs::File::create(path.as_ref())
+        .map_err(|e| TromError::IoError(e.to_string()))?;
+    
+    writeln!(file, "; Setun memory image")
+        .map_err(|e| TromError::IoError(e.to_string()))?;
+    writeln!(file, ";! trom {}", TROM_VERSION)
+        .map_err(|e| TromError::IoError(e.to_string()))?;
+    writeln!(file, ";! checksum {}", checksum(mem.as_slice()))
+        .map_err(|e| TromError::IoError(e.to_string()))?;
+    writeln!(file, "; {} cells, addresses {} to {}", mem.size(), mem.min_addr(), mem.max_addr())
+        .map_err(|e| TromError::IoError(e.to_string()))?;
+    writeln!(file).map_err(|e| TromError::IoError(e.to_string()))?;
+    
+    for (addr, cell) in mem.iter() {
+        writeln!(file, "{} ; {:+}", cell, addr)
+            .map_err(|e| TromError::IoError(e.to_string()))?;
+    }
+    
+    Ok(())
+}
+
+/// How a word differs between two programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    /// Present in both with different values.
+    Changed,
+    /// Only present in the new program.
+    Added,
+    /// Only present in the old program.
+    Removed,
+}
+
+/// One differing word between two programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WordDiff {
+    /// Offset from the start of the program (its address when loaded at 0).
+    pub index: usize,
+    /// Kind of difference.
+    pub kind: DiffKind,
+    /// Word in the old program.
+    pub old: Option<Tryte9>,
+    /// Word in the new program.
+    pub new: Option<Tryte9>,
+}
+
+/// Compare two programs word by word.
+///
+/// Words are matched by position; the result lists every differing
+/// position in ascending order.
+pub fn diff(old: &[Tryte9], new: &[Tryte9]) -> Vec<WordDiff> {
+    (0..old.len().max(new.len()))
+        .filter_map(|index| {
+            let (a, b) = (old.get(index).copied(), new.get(index).copied());
+            let kind = match (a, b) {
+                (Some(x), Some(y)) if x == y => return None,
+                (Some(_), Some(_)) => DiffKind::Changed,
+                (None, Some(_)) => DiffKind::Added,
+                (Some(_), None) => DiffKind::Removed,
+                (None, None) => unreachable!(),
+            };
+            Some(WordDiff { index, kind, old: a, new: b })
+        })
+        .collect()
+}
+
 /// Errors that can occur during TROM operations.
 #[derive(Debug, Clone, Error)]
 pub enum TromError {
@@ -131,6 +271,26 @@ pub enum TromError {
     
     #[error("parse error on line {line}: {message}")]
     ParseError { line: usize, message: String },
+    
+    #[error("unsupported TROM version {found} (this emulator reads up to {newest})")]
+    UnsupportedVersion { found: u32, newest: u32 },
+    
+    #[error("checksum mismatch: header says {expected}, words sum to {found}")]
+    ChecksumMismatch { expected: Tryte9, found: Tryte9 },
+}
+
+impl TromError {
+    /// The error as a [`Diagnostic`].
+    pub fn diagnostic(&self) -> Diagnostic {
+        match self {
+            TromError::IoError(_) => Diagnostic::error("trom-io", self.to_string()),
+            TromError::ParseError { line, .. } => Diagnostic::error("trom-parse", self.to_string()).at_line(*line),
+            TromError::UnsupportedVersion { .. } => Diagnostic::error("trom-version", self.to_string())
+                .with_note("the file was written by a newer version of the emulator"),
+            TromError::ChecksumMismatch { .. } => Diagnostic::error("trom-checksum", self.to_string())
+                .with_note("the file was damaged or edited after it was written"),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -146,4 +306,64 @@ mod tests {
         // Would need a temp file to test full roundtrip
         assert_eq!(trom.len(), 2);
     }
+    
+    #[test]
+    fn test_memory_image_roundtrip() {
+        let mut mem = Memory::new();
+        mem.write(0, Tryte9::from_i32(-9841));
+        mem.write(100, Tryte9::from_i32(77));
+        
+        let path = std::env::temp_dir().join(format!("setun-image-{}.trom", std::process::id()));
+        save_image(&path, &mem).unwrap();
+        let image = load_trom(&path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        // Any changed word breaks the checksum
+        let damaged = text.replacen("0tNNNNNNNNN ;", "0tNNNNNNNNO ;", 1);
+        assert_ne!(damaged, text);
+        assert!(matches!(parse_trom(&damaged), Err(TromError::ChecksumMismatch { .. })));
+        
+        assert_eq!(image.len(), mem.size());
+        assert_eq!(image.instructions[0].to_i32(), -9841);
+        assert_eq!(image.instructions[100].to_i32(), 77);
+    }
+    
+    #[test]
+    fn test_parse_trom_rejects_garbage() {
+        let trom = parse_trom("; header\n\nPPPPPPPPP ; 000\nnnnnoooop trailing\n").unwrap();
+        assert_eq!(trom.instructions, vec![Tryte9::from_i32(9841), Tryte9::from_i32(-9719)]);
+        
+        assert!(matches!(parse_trom("PPP\n"), Err(TromError::ParseError { line: 1, .. })));
+        assert!(parse_trom("ééé\u{0}PNO").is_err());
+    }
+    
+    #[test]
+    fn test_trom_version_header() {
+        assert_eq!(parse_trom(";! trom 1\nPPPPPPPPP\n").unwrap().len(), 1);
+        assert!(matches!(
+            parse_trom(";! trom 9\nPPPPPPPPP\n"),
+            Err(TromError::UnsupportedVersion { found: 9, .. })
+        ));
+        assert!(matches!(parse_trom(";! trom x\n"), Err(TromError::ParseError { line: 1, .. })));
+        assert!(parse_trom(";! origin 5\n").unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_diff() {
+        let old = [Tryte9::from_i32(1), Tryte9::from_i32(2), Tryte9::from_i32(3)];
+        let new = [Tryte9::from_i32(1), Tryte9::from_i32(5)];
+        
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0], WordDiff {
+            index: 1,
+            kind: DiffKind::Changed,
+            old: Some(Tryte9::from_i32(2)),
+            new: Some(Tryte9::from_i32(5)),
+        });
+        assert_eq!(changes[1].kind, DiffKind::Removed);
+        assert_eq!(diff(&new, &old)[1].kind, DiffKind::Added);
+        assert!(diff(&old, &old).is_empty());
+    }
 }
@@ -0,0 +1,108 @@
+//! Hexdump-style views of memory images.
+//!
+//! [`view_rows`] lays a program or memory image out one cell per row:
+//! address, trits, balanced base 9, decimal and a guess at the instruction
+//! the word encodes. [`format_view`] prints the rows in columns, folding
+//! runs of repeated words into a `*` line as `hexdump` does, and
+//! [`ViewRow::matches`] filters them by value or text.
+//!
+//! ```
+//! use setun::asm::{assemble, view::{format_view, view_rows}};
+//!
+//! let rows = view_rows(&assemble("LDA 3\nHLT\nDAT 0\nDAT 7").unwrap(), 0);
+//! let hits: Vec<_> = rows.iter().filter(|row| row.matches("7")).collect();
+//! assert_eq!(hits[0].addr, 3);
+//! assert!(format_view(&rows).contains("LDA 3"));
+//! ```
+
+use crate::asm::disasm::{disassemble_lines, DisasmOptions};
+use crate::ternary::convert::format_base9;
+use crate::ternary::{parse_number, Tryte9};
+use serde::Serialize;
+use std::fmt::Write;
+
+/// One cell of a view.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ViewRow {
+    /// Address of the cell.
+    pub addr: i32,
+    /// Decimal value.
+    pub value: i32,
+    /// Trits, `0t`-prefixed.
+    pub trits: String,
+    /// Balanced base 9, `0n`-prefixed.
+    pub base9: String,
+    /// The instruction the word encodes, or `DAT value` if none does.
+    pub disasm: String,
+}
+
+impl ViewRow {
+    /// Does the row match `query`? A number in any notation
+    /// [`parse_number`] reads matches cells holding that value; anything
+    /// else matches the trits or the disassembly, ignoring case.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim();
+        if let Ok(value) = parse_number(query) {
+            return value == self.value as i64;
+        }
+        let query = query.to_uppercase();
+        self.trits.to_uppercase().contains(&query) || self.disasm.to_uppercase().contains(&query)
+    }
+}
+
+/// The rows for `words` loaded at `origin`.
+pub fn view_rows(words: &[Tryte9], origin: i32) -> Vec<ViewRow> {
+    let options = DisasmOptions { origin, ..Default::default() };
+    disassemble_lines(words, &options).into_iter()
+        .map(|line| ViewRow {
+            addr: line.addr,
+            value: line.word.to_i32(),
+            trits: line.word.to_string(),
+            base9: format_base9(line.word.to_i32() as i64, Tryte9::WIDTH),
+            disasm: line.text,
+        })
+        .collect()
+}
+
+/// Print rows in columns under a header. A row holding the same word as
+/// the one before it, unless it is the last, is folded into a single `*`
+/// line with the rest of its run.
+pub fn format_view(rows: &[ViewRow]) -> String {
+    let mut out = format!("{:>5}  {:<11}  {:<7}  {:>6}  {}\n", "addr", "trits", "base-9", "dec", "disasm");
+    let mut folded = false;
+    for (i, row) in rows.iter().enumerate() {
+        let repeat = i > 0 && i + 1 < rows.len() && rows[i - 1].value == row.value && rows[i - 1].addr + 1 == row.addr;
+        if repeat {
+            if !folded {
+                out.push_str("    *\n");
+                folded = true;
+            }
+            continue;
+        }
+        folded = false;
+        let _ = writeln!(out, "{:>5}  {:<11}  {:<7}  {:>6}  {}", row.addr, row.trits, row.base9, row.value, row.disasm);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_folds_and_filters() {
+        let mut words = vec![Tryte9::from_i32(5); 2];
+        words.extend([Tryte9::zero(); 4]);
+        words.push(Tryte9::from_i32(-13));
+        let rows = view_rows(&words, -2);
+
+        assert_eq!((rows[0].addr, rows[0].trits.as_str()), (-2, "0tOOOOOOPNN"));
+        assert_eq!((rows[6].addr, rows[6].base9.as_str()), (4, "0n000ZW"));
+        assert_eq!(format_view(&rows).lines().filter(|line| line.trim() == "*").count(), 2);
+        assert_eq!(format_view(&rows).lines().count(), 6);
+
+        let hits: Vec<i32> = rows.iter().filter(|row| row.matches("0tOOOOOONNN")).map(|row| row.addr).collect();
+        assert_eq!(hits, [4]);
+        assert!(rows.iter().filter(|row| row.matches("hlt")).all(|row| row.value == 0));
+    }
+}
@@ -0,0 +1,231 @@
+//! Emulation throughput benchmarks.
+//!
+//! Standardized workloads are run for a fixed number of instructions in
+//! each execution mode, so the effect of performance work on the core
+//! can be measured as instructions per second. Workloads loop forever;
+//! with the default wrapping overflow policy they never halt or fault.
+
+use crate::asm::{assemble, AssemblerError};
+use crate::cpu::{Cpu, CpuError};
+use crate::cpu::memory::MemoryError;
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// A benchmark program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Workload {
+    /// Short name used on the command line.
+    pub name: &'static str,
+    /// One-line description.
+    pub description: &'static str,
+    /// Assembly source; must never halt.
+    pub source: &'static str,
+}
+
+/// The standard workloads.
+pub const WORKLOADS: &[Workload] = &[
+    Workload {
+        name: "add-loop",
+        description: "Tight ADD/JMP loop",
+        source: "
+        LOOP:
+            ADD ONE
+            JMP LOOP
+        ONE: DAT 1
+        ",
+    },
+    Workload {
+        name: "multiply",
+        description: "Repeated 18-trit multiplications",
+        source: "
+        LOOP:
+            LDA X
+            MUL Y
+            MUL Y
+            MUL Y
+            JMP LOOP
+        X: DAT 123
+        Y: DAT 45
+        ",
+    },
+    Workload {
+        name: "memory-churn",
+        description: "Loads and stores across several cells",
+        source: "
+        LOOP:
+            LDA A
+            ADD B
+            STA C
+            LDA C
+            SUB A
+            STA B
+            LDA C
+            STA A
+            JMP LOOP
+        A: DAT 1
+        B: DAT 1
+        C: DAT 0
+        ",
+    },
+];
+
+/// Look up a workload by name.
+pub fn find(name: &str) -> Option<&'static Workload> {
+    WORKLOADS.iter().find(|w| w.name.eq_ignore_ascii_case(name))
+}
+
+/// How instructions are driven through the CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// [`Cpu::run_limited`].
+    Run,
+    /// [`Cpu::step`] in a loop.
+    Step,
+    /// [`Cpu::step_many`] in batches.
+    StepMany,
+    /// [`Cpu::step_traced`] in a loop.
+    Traced,
+    /// [`Cpu::run_profiled`].
+    Profiled,
+}
+
+impl Mode {
+    /// Every mode, fastest first.
+    pub const ALL: [Mode; 5] = [Mode::Run, Mode::Step, Mode::StepMany, Mode::Traced, Mode::Profiled];
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Mode::Run => "run",
+            Mode::Step => "step",
+            Mode::StepMany => "step-many",
+            Mode::Traced => "traced",
+            Mode::Profiled => "profiled",
+        };
+        f.pad(name)
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Mode::ALL.into_iter()
+            .find(|mode| mode.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown mode '{}' (expected run, step, step-many, traced or profiled)", s))
+    }
+}
+
+/// Batch size used by [`Mode::StepMany`].
+const STEP_MANY_BATCH: u64 = 1_000;
+
+/// Throughput of one workload in one mode.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchResult {
+    /// Workload name.
+    pub workload: String,
+    /// Execution mode.
+    pub mode: Mode,
+    /// Instructions executed.
+    pub instructions: u64,
+    /// Wall-clock time spent executing.
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    /// Instructions per second.
+    pub fn instructions_per_second(&self) -> f64 {
+        self.instructions as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Run `workload` for `instructions` instructions in `mode`.
+pub fn measure(workload: &Workload, mode: Mode, instructions: u64) -> Result<BenchResult, BenchError> {
+    let program = assemble(workload.source)?;
+    let mut cpu = Cpu::new();
+    cpu.load_program(&program)?;
+
+    let start = Instant::now();
+    match mode {
+        Mode::Run => {
+            cpu.run_limited(instructions)?;
+        }
+        Mode::Step => {
+            for _ in 0..instructions {
+                cpu.step()?;
+            }
+        }
+        Mode::StepMany => {
+            while cpu.cycles < instructions {
+                let summary = cpu.step_many(STEP_MANY_BATCH.min(instructions - cpu.cycles));
+                if let Some(message) = summary.error {
+                    return Err(BenchError::Stopped(message));
+                }
+            }
+        }
+        Mode::Traced => {
+            for _ in 0..instructions {
+                cpu.step_traced()?;
+            }
+        }
+        Mode::Profiled => {
+            cpu.run_profiled(instructions)?;
+        }
+    }
+    let elapsed = start.elapsed();
+
+    if cpu.cycles != instructions {
+        return Err(BenchError::Stopped(format!("workload stopped after {} instructions", cpu.cycles)));
+    }
+    Ok(BenchResult {
+        workload: workload.name.to_string(),
+        mode,
+        instructions,
+        elapsed,
+    })
+}
+
+/// Errors that abort a benchmark.
+#[derive(Debug, Clone, Error)]
+pub enum BenchError {
+    #[error("assembly error: {0}")]
+    Assembly(#[from] AssemblerError),
+
+    #[error("failed to load workload: {0}")]
+    Memory(#[from] MemoryError),
+
+    #[error("CPU error: {0}")]
+    Cpu(#[from] CpuError),
+
+    #[error("{0}")]
+    Stopped(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workloads_run_in_every_mode() {
+        for workload in WORKLOADS {
+            for mode in Mode::ALL {
+                let result = measure(workload, mode, 2_500).unwrap();
+                assert_eq!(result.instructions, 2_500, "{} {}", workload.name, mode);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mode_names_roundtrip() {
+        for mode in Mode::ALL {
+            assert_eq!(mode.to_string().parse::<Mode>(), Ok(mode));
+        }
+        assert!("warp".parse::<Mode>().is_err());
+        assert!(find("ADD-LOOP").is_some());
+    }
+}
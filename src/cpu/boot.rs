@@ -0,0 +1,207 @@
+//! Bootstrap loading from paper tape.
+//!
+//! The Setun was started by an initial input routine that read a program
+//! from paper tape into memory and jumped to it. [`Cpu::boot_from_tape`]
+//! does the same thing with real machine code: it places a short loader
+//! at the bottom of memory, mounts the tape on the reader and starts the
+//! machine at the loader. The tape begins with a three-word header,
+//!
+//! ```text
+//! load address, word count, entry point, words...
+//! ```
+//!
+//! which [`boot_tape`] writes for an assembled program. Anything after the
+//! program stays on the tape for the program itself to read:
+//!
+//! ```
+//! use setun::{assemble, Cpu, Tryte9};
+//! use setun::cpu::boot::boot_tape;
+//!
+//! let program = assemble("LDA 120\nADD 120\nHLT").unwrap();
+//! let mut tape = boot_tape(&program, 0, 0);
+//! tape.extend([Tryte9::from_i32(20), Tryte9::from_i32(22)]);
+//!
+//! let mut cpu = Cpu::new();
+//! cpu.boot_from_tape(&tape).unwrap();
+//! cpu.run().unwrap();
+//! assert_eq!(cpu.regs.s.to_i64(), 42);
+//! ```
+//!
+//! The loader runs on the machine, so its cycles are counted and show in
+//! traces. It leaves F holding the entry point and S the last word read.
+
+use crate::asm::assemble;
+use crate::cpu::{Cpu, TAPE_READER_ADDR};
+use crate::ternary::Tryte9;
+use thiserror::Error;
+
+/// Number of words the loader occupies.
+pub const BOOT_ROUTINE_LEN: usize = 23;
+
+/// Number of header words before the program on a boot tape.
+pub const BOOT_HEADER_LEN: usize = 3;
+
+/// The header of a boot tape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootHeader {
+    /// Address of the first program word.
+    pub load_at: i32,
+    /// Number of program words.
+    pub len: usize,
+    /// Address the loader jumps to.
+    pub entry: i32,
+}
+
+impl BootHeader {
+    /// Read the header at the start of `tape`, checking that the whole
+    /// program follows it.
+    pub fn parse(tape: &[Tryte9]) -> Result<Self, BootError> {
+        let [load_at, len, entry] = match tape {
+            [a, b, c, ..] => [a.to_i32(), b.to_i32(), c.to_i32()],
+            _ => return Err(BootError::MissingHeader(tape.len())),
+        };
+        if len < 0 {
+            return Err(BootError::NegativeLength(len));
+        }
+        let len = len as usize;
+        let found = tape.len() - BOOT_HEADER_LEN;
+        if found < len {
+            return Err(BootError::Truncated { expected: len, found });
+        }
+        Ok(Self { load_at, len, entry })
+    }
+
+    /// Address just past the last program word.
+    pub fn end(&self) -> i32 {
+        self.load_at + self.len as i32
+    }
+}
+
+/// Errors from booting a tape.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum BootError {
+    #[error("boot tape has {0} words, too few for the load address, length and entry header")]
+    MissingHeader(usize),
+
+    #[error("boot tape header gives a negative length ({0})")]
+    NegativeLength(i32),
+
+    #[error("boot tape is truncated: header promises {expected} words, {found} follow")]
+    Truncated { expected: usize, found: usize },
+
+    #[error("program ({start}..{end}) does not fit in memory")]
+    OutOfRange { start: i32, end: i32 },
+
+    #[error("entry point {0} is outside memory")]
+    BadEntry(i32),
+
+    #[error("program ({start}..{end}) overlaps the loader at {loader}..{loader_end}")]
+    OverlapsLoader { start: i32, end: i32, loader: i32, loader_end: i32 },
+}
+
+/// Prefix `program` with the header the loader expects.
+pub fn boot_tape(program: &[Tryte9], load_at: i32, entry: i32) -> Vec<Tryte9> {
+    let mut tape = vec![
+        Tryte9::from_i32(load_at),
+        Tryte9::from_i32(program.len() as i32),
+        Tryte9::from_i32(entry),
+    ];
+    tape.extend_from_slice(program);
+    tape
+}
+
+/// The loader's machine code, assembled to run at `at`.
+pub fn boot_routine(at: i32) -> Vec<Tryte9> {
+    let source = format!(
+        "        ORG {at}
+        LDA {tape}       ; load address
+        STA PTR
+        LDA {tape}       ; word count
+        STA LEFT
+        LDA {tape}       ; entry point
+        STA ENTRY
+LOOP:   LDA LEFT
+        JZ DONE
+        SUB ONE
+        STA LEFT
+        LDF PTR
+        LDA {tape}
+        STA 0,F+
+        LDA PTR
+        ADD ONE
+        STA PTR
+        JMP LOOP
+DONE:   LDF ENTRY
+        JMP 0,F+
+PTR:    DAT 0
+LEFT:   DAT 0
+ENTRY:  DAT 0
+ONE:    DAT 1
+",
+        tape = TAPE_READER_ADDR,
+    );
+    assemble(&source).expect("boot routine assembles")
+}
+
+impl Cpu {
+    /// Mount `tape` on the reader and start the machine at a loader that
+    /// reads the program from it. The program runs once the loader has
+    /// jumped to it; nothing else in memory or the registers is cleared.
+    pub fn boot_from_tape(&mut self, tape: &[Tryte9]) -> Result<BootHeader, BootError> {
+        let header = BootHeader::parse(tape)?;
+        let (min, max) = (self.mem.min_addr(), self.mem.max_addr());
+        if header.load_at < min || header.end() > max + 1 {
+            return Err(BootError::OutOfRange { start: header.load_at, end: header.end() - 1 });
+        }
+        if !(min..=max).contains(&header.entry) {
+            return Err(BootError::BadEntry(header.entry));
+        }
+        let loader_end = min + BOOT_ROUTINE_LEN as i32;
+        if header.len > 0 && header.load_at < loader_end {
+            return Err(BootError::OverlapsLoader {
+                start: header.load_at,
+                end: header.end() - 1,
+                loader: min,
+                loader_end: loader_end - 1,
+            });
+        }
+
+        self.load_program_at(min, &boot_routine(min)).expect("loader fits in memory");
+        self.set_entry(min).expect("loader address is in memory");
+        self.io.enable();
+        self.io.feed(tape.iter().copied());
+        Ok(header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boot_loads_and_jumps() {
+        // Entry in the middle of the program, with a data word before it
+        let program = crate::asm::assemble("DAT 7\nSTART: LDA 30\nADD 30\nHLT").unwrap();
+        assert_eq!(boot_routine(-81).len(), BOOT_ROUTINE_LEN);
+
+        let mut cpu = Cpu::new();
+        let header = cpu.boot_from_tape(&boot_tape(&program, 30, 31)).unwrap();
+        assert_eq!(header, BootHeader { load_at: 30, len: 4, entry: 31 });
+        cpu.run().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), 14);
+        assert_eq!(cpu.io.pending_input(), 0);
+    }
+
+    #[test]
+    fn test_bad_tapes() {
+        let mut cpu = Cpu::new();
+        let words = vec![Tryte9::from_i32(1); 5];
+        assert_eq!(cpu.boot_from_tape(&words[..2]), Err(BootError::MissingHeader(2)));
+        let mut tape = boot_tape(&words, 0, 0);
+        tape.pop();
+        assert_eq!(cpu.boot_from_tape(&tape), Err(BootError::Truncated { expected: 5, found: 4 }));
+        assert!(matches!(cpu.boot_from_tape(&boot_tape(&words, -70, -70)), Err(BootError::OverlapsLoader { .. })));
+        assert!(matches!(cpu.boot_from_tape(&boot_tape(&words, 78, 78)), Err(BootError::OutOfRange { .. })));
+        assert_eq!(cpu.boot_from_tape(&boot_tape(&words, 0, 90)), Err(BootError::BadEntry(90)));
+    }
+}
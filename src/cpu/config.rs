@@ -0,0 +1,263 @@
+//! Machine configuration.
+//!
+//! The emulator can model more than one flavour of the Setun. A
+//! [`CpuConfig`] selects the memory size, the instruction-set variant,
+//! what happens on arithmetic overflow or when an indexed address leaves
+//! memory, whether emulated time and cost are accounted, whether shifts
+//! report lost trits, where the stack lives, and whether the indirect
+//! addressing extension and experimental instructions are enabled.
+
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+use crate::cpu::cost::CostModel;
+use crate::cpu::memory::MEMORY_SIZE;
+use crate::cpu::registers::Tryte5;
+use std::fmt;
+use std::str::FromStr;
+
+/// Largest supported memory: every address a 9-trit word can express.
+pub const MAX_MEMORY_SIZE: usize = 19_683;
+
+/// Instruction-set variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IsaVariant {
+    /// The emulator's simplified teaching ISA.
+    #[default]
+    Simplified,
+    /// Behaviour following the documented 1958 machine where it differs.
+    Historical,
+}
+
+/// What to do when an addition or subtraction overflows 18 trits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Discard the carry out of the top trit.
+    #[default]
+    Wrap,
+    /// Stop with [`CpuError::Overflow`](crate::cpu::CpuError::Overflow).
+    Trap,
+}
+
+/// What to do when an index-modified address (`base ± F`) falls outside
+/// memory. Addresses of I/O ports and attached devices are never changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressPolicy {
+    /// Stop with [`CpuError::AddressOutOfRange`](crate::cpu::CpuError::AddressOutOfRange).
+    #[default]
+    Trap,
+    /// Wrap around to the other end of memory, so an indexed loop that
+    /// walks off the top continues at the bottom.
+    Wrap,
+}
+
+/// Cells reserved for a stack that grows down, with F as its pointer.
+///
+/// The stack is empty when F is `base`; `PUSH` moves F down two cells and
+/// stores S at the new F, so pushed words occupy `limit..base`. Written
+/// `LIMIT..BASE`, like a Rust range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StackRegion {
+    /// F for an empty stack, one above the first pushed cell.
+    pub base: i32,
+    /// Lowest cell the stack may use.
+    pub limit: i32,
+}
+
+impl StackRegion {
+    /// Number of cells the stack holds; each pushed word takes two.
+    pub fn capacity(&self) -> usize {
+        (self.base - self.limit).max(0) as usize
+    }
+}
+
+impl fmt::Display for StackRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.limit, self.base)
+    }
+}
+
+impl FromStr for StackRegion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (limit, base) = s.split_once("..")
+            .ok_or_else(|| format!("expected LIMIT..BASE, got '{}'", s))?;
+        let parse = |text: &str| text.trim().parse::<i32>().map_err(|_| format!("invalid address '{}'", text.trim()));
+        Ok(Self { base: parse(base)?, limit: parse(limit)? })
+    }
+}
+
+/// Configuration for a [`Cpu`](crate::cpu::Cpu).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CpuConfig {
+    /// Number of 9-trit memory cells (addresses are centred on zero).
+    pub memory_size: usize,
+    /// Instruction-set variant.
+    pub isa: IsaVariant,
+    /// Overflow behaviour for additive instructions.
+    pub overflow: OverflowPolicy,
+    /// Behaviour of indexed addresses outside memory.
+    pub addressing: AddressPolicy,
+    /// Accumulate emulated execution time (see [`timing`](crate::cpu::timing)).
+    pub timing: bool,
+    /// Read mode trit N as [`AddrMode::Indirect`](crate::cpu::AddrMode::Indirect)
+    /// instead of index subtraction. An extension of the simplified ISA;
+    /// the historical machine had no indirect addressing.
+    pub indirect: bool,
+    /// Decode the opcodes registered in the CPU's
+    /// [`ExtensionRegistry`](crate::cpu::ExtensionRegistry). Off by
+    /// default, so unassigned opcodes stay invalid.
+    pub experimental: bool,
+    /// Make `SHL` and `SHM` set ω to the sign of the significant trits
+    /// shifted out of S instead of the sign of S, so ω is O after a shift
+    /// that lost nothing. `TST` recovers the sign of S.
+    pub strict_shifts: bool,
+    /// Reserve F as the pointer of this stack: F starts at its base and an
+    /// instruction that moves F outside it stops with
+    /// [`CpuError::StackOverflow`](crate::cpu::CpuError::StackOverflow) or
+    /// [`CpuError::StackUnderflow`](crate::cpu::CpuError::StackUnderflow).
+    pub stack: Option<StackRegion>,
+    /// Accumulate execution cost under this model (see [`cost`](crate::cpu::cost)).
+    pub cost: Option<CostModel>,
+}
+
+impl CpuConfig {
+    /// The simplified teaching machine (the default).
+    pub fn simplified() -> Self {
+        Self {
+            memory_size: MEMORY_SIZE,
+            isa: IsaVariant::Simplified,
+            overflow: OverflowPolicy::Wrap,
+            addressing: AddressPolicy::Trap,
+            timing: false,
+            indirect: false,
+            experimental: false,
+            strict_shifts: false,
+            stack: None,
+            cost: None,
+        }
+    }
+    
+    /// The historical 1958 machine.
+    pub fn historical() -> Self {
+        Self {
+            memory_size: MEMORY_SIZE,
+            isa: IsaVariant::Historical,
+            overflow: OverflowPolicy::Trap,
+            addressing: AddressPolicy::Trap,
+            timing: true,
+            indirect: false,
+            experimental: false,
+            strict_shifts: false,
+            stack: None,
+            cost: None,
+        }
+    }
+    
+    /// Look up a preset by name ("simplified" or "historical").
+    pub fn preset(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "simplified" => Some(Self::simplified()),
+            "historical" => Some(Self::historical()),
+            _ => None,
+        }
+    }
+    
+    /// Check that the configuration describes a buildable machine.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.memory_size == 0 || self.memory_size > MAX_MEMORY_SIZE {
+            return Err(ConfigError::InvalidMemorySize(self.memory_size));
+        }
+        if self.indirect && self.is_historical() {
+            return Err(ConfigError::IndirectOnHistorical);
+        }
+        if let Some(stack) = self.stack {
+            // Same layout as `Memory`: addresses centred on zero
+            let origin = (self.memory_size / 2) as i32;
+            let top = self.memory_size as i32 - origin;
+            let fits = stack.limit >= -origin.min(Tryte5::MAX) && stack.base <= top.min(Tryte5::MAX);
+            if stack.limit >= stack.base || !fits {
+                return Err(ConfigError::InvalidStack(stack));
+            }
+        }
+        Ok(())
+    }
+    
+    /// Whether the historical ISA variant is selected.
+    pub fn is_historical(&self) -> bool {
+        self.isa == IsaVariant::Historical
+    }
+}
+
+impl Default for CpuConfig {
+    fn default() -> Self {
+        Self::simplified()
+    }
+}
+
+/// Errors in a machine configuration.
+#[derive(Debug, Clone, Error)]
+pub enum ConfigError {
+    #[error("invalid memory size {0} (must be 1 to {max})", max = MAX_MEMORY_SIZE)]
+    InvalidMemorySize(usize),
+    
+    #[error("indirect addressing is an extension of the simplified ISA, not the historical one")]
+    IndirectOnHistorical,
+    
+    #[error("invalid stack {0} (LIMIT must be below BASE, both addresses F can hold inside memory)")]
+    InvalidStack(StackRegion),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_presets() {
+        assert_eq!(CpuConfig::default(), CpuConfig::simplified());
+        assert_eq!(CpuConfig::preset("Historical"), Some(CpuConfig::historical()));
+        assert_eq!(CpuConfig::preset("bogus"), None);
+    }
+    
+    #[test]
+    fn test_validate_memory_size() {
+        let mut config = CpuConfig::simplified();
+        assert!(config.validate().is_ok());
+        
+        config.memory_size = 0;
+        assert!(config.validate().is_err());
+        
+        config.memory_size = MAX_MEMORY_SIZE + 1;
+        assert!(config.validate().is_err());
+        
+        let indirect = CpuConfig { indirect: true, ..CpuConfig::historical() };
+        assert!(matches!(indirect.validate(), Err(ConfigError::IndirectOnHistorical)));
+        assert!(CpuConfig { indirect: true, ..CpuConfig::simplified() }.validate().is_ok());
+    }
+    
+    #[test]
+    fn test_stack_region() {
+        let region: StackRegion = "-40..-20".parse().unwrap();
+        assert_eq!(region, StackRegion { base: -20, limit: -40 });
+        assert_eq!(region.capacity(), 20);
+        assert_eq!(region.to_string(), "-40..-20");
+        assert!("-20".parse::<StackRegion>().is_err());
+        
+        assert!(CpuConfig { stack: Some(region), ..CpuConfig::default() }.validate().is_ok());
+        for bad in ["-20..-40", "-90..-20", "70..82"] {
+            let config = CpuConfig { stack: Some(bad.parse().unwrap()), ..CpuConfig::default() };
+            assert!(matches!(config.validate(), Err(ConfigError::InvalidStack(_))), "{}", bad);
+        }
+    }
+    
+    #[test]
+    fn test_config_json_defaults() {
+        let config: CpuConfig = serde_json::from_str(r#"{"isa":"historical"}"#).unwrap();
+        assert_eq!(config.isa, IsaVariant::Historical);
+        assert_eq!(config.memory_size, MEMORY_SIZE);
+    }
+}
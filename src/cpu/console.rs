@@ -0,0 +1,208 @@
+//! Console character device.
+//!
+//! [`ConsoleDevice`] is a [`Device`] at [`CONSOLE_ADDR`] that speaks
+//! characters instead of numbers: writing a cell prints the character
+//! whose code is the cell's value, and reading returns the code of the
+//! next typed character. Codes follow a Setun-style character set of 81
+//! characters, -40..=40, so each fits in four trits:
+//!
+//! | Codes       | Characters                       |
+//! |-------------|----------------------------------|
+//! | 0           | space                            |
+//! | 1..=26      | `A`..`Z`                         |
+//! | 27..=36     | `0`..`9`                         |
+//! | 37..=40     | `.` `,` `+` `-`                  |
+//! | -1..=-32    | Cyrillic `А`..`Я`                |
+//! | -33..=-40   | newline `(` `)` `=` `*` `/` `:` `?` |
+//!
+//! Lowercase letters are typed as their capitals. Writing a code with no
+//! character prints `�`; typing a character outside the set reads as `?`.
+//!
+//! ```
+//! use setun::{assemble, Cpu};
+//! use setun::cpu::ConsoleDevice;
+//!
+//! let program = assemble("LDA 117\nADD ONE\nSTA 117\nHLT\nONE: DAT 1").unwrap();
+//! let mut cpu = Cpu::new();
+//! let console = cpu.devices.attach(ConsoleDevice::new()).unwrap();
+//! cpu.devices.get_mut::<ConsoleDevice>(console).unwrap().feed("H");
+//! cpu.load_program(&program).unwrap();
+//! cpu.run().unwrap();
+//! assert_eq!(cpu.devices.get_mut::<ConsoleDevice>(console).unwrap().take_output(), "I");
+//! ```
+
+use crate::cpu::device::{Device, DeviceError};
+use crate::ternary::Tryte9;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+
+/// Address of the console.
+pub const CONSOLE_ADDR: i32 = 117;
+
+/// The character set, indexed by code + 40.
+const CHARSET: [char; 81] = [
+    '?', ':', '/', '*', '=', ')', '(', '\n',
+    'Я', 'Ю', 'Э', 'Ь', 'Ы', 'Ъ', 'Щ', 'Ш', 'Ч', 'Ц', 'Х', 'Ф', 'У', 'Т', 'С', 'Р',
+    'П', 'О', 'Н', 'М', 'Л', 'К', 'Й', 'И', 'З', 'Ж', 'Е', 'Д', 'Г', 'В', 'Б', 'А',
+    ' ',
+    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M',
+    'N', 'O', 'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
+    '.', ',', '+', '-',
+];
+
+/// The character printed for a code outside the set.
+pub const UNKNOWN_CHAR: char = '\u{FFFD}';
+
+/// The character with code `code`, if any.
+pub fn char_for_code(code: i32) -> Option<char> {
+    usize::try_from(code + 40).ok().and_then(|i| CHARSET.get(i)).copied()
+}
+
+/// The code of `c`, typing lowercase letters as capitals.
+pub fn code_for_char(c: char) -> Option<i32> {
+    let upper = c.to_uppercase().next().unwrap_or(c);
+    CHARSET.iter().position(|&x| x == upper).map(|i| i as i32 - 40)
+}
+
+/// Supplies more typed text when the console runs out; `None` means the
+/// input has ended.
+pub type ConsoleInput = Arc<dyn Fn() -> Option<String> + Send + Sync>;
+
+/// Memory-mapped character console.
+#[derive(Clone, Default)]
+pub struct ConsoleDevice {
+    input: VecDeque<char>,
+    output: String,
+    source: Option<ConsoleInput>,
+}
+
+impl ConsoleDevice {
+    /// A console with no input.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A console that asks `source` for more text whenever a program
+    /// reads with nothing left to read, e.g. a line from stdin.
+    pub fn with_input(source: ConsoleInput) -> Self {
+        Self { source: Some(source), ..Self::default() }
+    }
+
+    /// Queue typed text for the program to read.
+    pub fn feed(&mut self, text: &str) {
+        self.input.extend(text.chars());
+    }
+
+    /// Characters typed but not yet read.
+    pub fn pending_input(&self) -> usize {
+        self.input.len()
+    }
+
+    /// Take the text printed since the last call.
+    pub fn take_output(&mut self) -> String {
+        std::mem::take(&mut self.output)
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        while self.input.is_empty() {
+            let text = self.source.as_ref()?();
+            self.feed(&text?);
+        }
+        self.input.pop_front()
+    }
+}
+
+impl fmt::Debug for ConsoleDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsoleDevice")
+            .field("input", &self.input)
+            .field("output", &self.output)
+            .field("interactive", &self.source.is_some())
+            .finish()
+    }
+}
+
+impl Device for ConsoleDevice {
+    fn name(&self) -> &str {
+        "console"
+    }
+
+    fn addresses(&self) -> Vec<i32> {
+        vec![CONSOLE_ADDR]
+    }
+
+    fn read(&mut self, _addr: i32) -> Result<Tryte9, DeviceError> {
+        let c = self.next_char().ok_or_else(|| DeviceError::Failed {
+            device: self.name().to_string(),
+            message: "no input".into(),
+        })?;
+        Ok(Tryte9::from_i32(code_for_char(c).unwrap_or(-40)))
+    }
+
+    fn write(&mut self, _addr: i32, value: Tryte9) -> Result<(), DeviceError> {
+        self.output.push(char_for_code(value.to_i32()).unwrap_or(UNKNOWN_CHAR));
+        Ok(())
+    }
+
+    fn peek(&self, _addr: i32) -> Option<Tryte9> {
+        self.input.front().map(|&c| Tryte9::from_i32(code_for_char(c).unwrap_or(-40)))
+    }
+
+    fn reset(&mut self) {
+        self.input.clear();
+        self.output.clear();
+    }
+
+    fn clone_box(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::Cpu;
+
+    #[test]
+    fn test_charset_round_trip() {
+        for code in -40..=40 {
+            let c = char_for_code(code).unwrap();
+            assert_eq!(code_for_char(c), Some(code), "code {}", code);
+        }
+        assert_eq!(char_for_code(0), Some(' '));
+        assert_eq!(code_for_char('a'), Some(1));
+        assert_eq!(code_for_char('ж'), code_for_char('Ж'));
+        assert_eq!(char_for_code(41), None);
+        assert_eq!(code_for_char('%'), None);
+    }
+
+    #[test]
+    fn test_echo_program() {
+        // Echo characters until a full stop
+        let program = assemble("
+            LOOP: LDA 117
+                  STA 117
+                  SUB STOP
+                  JZ DONE
+                  JMP LOOP
+            DONE: HLT
+            STOP: DAT 37
+        ").unwrap();
+        let mut cpu = Cpu::new();
+        let id = cpu.devices.attach(ConsoleDevice::new()).unwrap();
+        cpu.devices.get_mut::<ConsoleDevice>(id).unwrap().feed("Setun 1958.");
+        cpu.load_program(&program).unwrap();
+        cpu.run_limited(1000).unwrap();
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.devices.get_mut::<ConsoleDevice>(id).unwrap().take_output(), "SETUN 1958.");
+
+        // Reading past the end of the input is an error
+        let mut cpu = Cpu::new();
+        cpu.devices.attach(ConsoleDevice::new()).unwrap();
+        cpu.load_program(&program).unwrap();
+        assert!(cpu.run_limited(10).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! Configurable energy/cost model.
+//!
+//! Where the [timing model](crate::cpu::timing) estimates how long the
+//! real machine took, a [`CostModel`] charges whatever an exercise wants to
+//! count: a price per opcode plus a price per memory read and write. When
+//! [`CpuConfig::cost`](crate::cpu::CpuConfig::cost) is set, each executed
+//! instruction adds its price to [`Cpu::cost`](crate::Cpu::cost), next to
+//! the cycle count and emulated time:
+//!
+//! ```
+//! use setun::{assemble, Cpu};
+//! use setun::cpu::{CostModel, CpuConfig};
+//!
+//! let model: CostModel = serde_json::from_str(r#"{"opcodes": {"MUL": 10}, "memory_read": 2}"#).unwrap();
+//! let mut cpu = Cpu::with_config(CpuConfig { cost: Some(model), ..CpuConfig::default() });
+//! cpu.load_program(&assemble("LDA 3\nMUL 3\nHLT\nDAT 4").unwrap()).unwrap();
+//! cpu.run().unwrap();
+//!
+//! // LDA: 1 + one read, MUL: 10 + one read, HLT: 1
+//! assert_eq!(cpu.cost, 3 + 12 + 1);
+//! ```
+//!
+//! Models load from JSON or TOML; omitted fields take the defaults, which
+//! charge 1 per instruction and nothing for memory, so the cost equals the
+//! cycle count.
+
+use crate::cpu::decode::{AddrMode, Instruction};
+use crate::cpu::trace::operand_access;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Prices charged for executing instructions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CostModel {
+    /// Price of an instruction whose mnemonic is not in `opcodes`.
+    pub instruction: u64,
+    /// Price per mnemonic, e.g. `"MUL": 10`.
+    pub opcodes: BTreeMap<String, u64>,
+    /// Price of each data memory read, including indirect address words.
+    pub memory_read: u64,
+    /// Price of each data memory write.
+    pub memory_write: u64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            instruction: 1,
+            opcodes: BTreeMap::new(),
+            memory_read: 0,
+            memory_write: 0,
+        }
+    }
+}
+
+impl CostModel {
+    /// Read a model from a `.toml` file, or JSON for any other extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&text).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str(&text).map_err(|e| e.to_string())
+        }
+    }
+
+    /// Price of executing `instr` once.
+    pub fn instruction_cost(&self, instr: &Instruction) -> u64 {
+        let base = self.opcodes.get(instr.mnemonic()).copied().unwrap_or(self.instruction);
+        let memory = match operand_access(instr) {
+            Some((_, mode, reads, writes)) => {
+                let pointer = (mode == AddrMode::Indirect) as u64;
+                (reads as u64 + pointer) * self.memory_read + writes as u64 * self.memory_write
+            }
+            None => 0,
+        };
+        base + memory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ternary::Tryte9;
+
+    #[test]
+    fn test_instruction_cost() {
+        let model = CostModel {
+            opcodes: BTreeMap::from([("DIV".to_string(), 20)]),
+            memory_read: 2,
+            memory_write: 3,
+            ..CostModel::default()
+        };
+        let addr = Tryte9::from_i32(5);
+        assert_eq!(model.instruction_cost(&Instruction::Hlt), 1);
+        assert_eq!(model.instruction_cost(&Instruction::Div { addr, mode: AddrMode::Direct }), 22);
+        assert_eq!(model.instruction_cost(&Instruction::Xchg { addr, mode: AddrMode::Direct }), 6);
+        assert_eq!(model.instruction_cost(&Instruction::Sta { addr, mode: AddrMode::Indirect }), 6);
+
+        let parsed: CostModel = toml::from_str("memory_write = 3\n[opcodes]\nDIV = 20").unwrap();
+        assert_eq!(parsed, CostModel { memory_read: 0, ..model });
+    }
+}
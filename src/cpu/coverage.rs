@@ -0,0 +1,209 @@
+//! Instruction and branch coverage.
+//!
+//! [`Coverage`] records which cells were executed and which directions
+//! each conditional branch went, and keeps accumulating across runs, so a
+//! set of test inputs can be judged by what it exercised:
+//!
+//! ```
+//! use setun::{assemble, Cpu};
+//! use setun::cpu::Coverage;
+//!
+//! let program = assemble("LDA 10\nJZ 4\nLDA 0\nHLT\nHLT").unwrap();
+//! let mut coverage = Coverage::new();
+//! for input in [0, 1] {
+//!     let mut cpu = Cpu::new();
+//!     cpu.load_program(&program).unwrap();
+//!     cpu.mem.write(91, setun::Tryte9::from_i32(input));
+//!     coverage.merge(&cpu.run_covered(100).unwrap());
+//! }
+//!
+//! let report = coverage.report(0, program.len());
+//! assert_eq!((report.executed, report.cells), (5, 5));
+//! assert_eq!((report.branch_directions, report.branch_sites), (2, 1));
+//! ```
+//!
+//! A program's data words are never executed, so they show up as
+//! uncovered cells; branch coverage is the better measure of a test set.
+
+use crate::cpu::{Cpu, CpuError, Instruction, Profile, TraceRecord};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Directions a conditional branch has gone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchCoverage {
+    /// The branch has jumped.
+    pub taken: bool,
+    /// Execution has fallen through.
+    pub fallthrough: bool,
+}
+
+impl BranchCoverage {
+    /// Number of directions exercised (0 to 2).
+    pub fn directions(&self) -> usize {
+        self.taken as usize + self.fallthrough as usize
+    }
+}
+
+/// Executed cells and branch directions, accumulated over runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Coverage {
+    /// Addresses of executed instructions.
+    pub executed: BTreeSet<i32>,
+    /// Directions taken per conditional branch address.
+    pub branches: BTreeMap<i32, BranchCoverage>,
+}
+
+impl Coverage {
+    /// Create empty coverage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one executed instruction, fetched from `pc`, after which the
+    /// program counter was `next`.
+    pub fn record_step(&mut self, pc: i32, instruction: &Instruction, next: i32) {
+        self.executed.insert(pc);
+        if instruction.is_conditional_branch() {
+            let branch = self.branches.entry(pc).or_default();
+            if next == pc + 1 {
+                branch.fallthrough = true;
+            } else {
+                branch.taken = true;
+            }
+        }
+    }
+
+    /// Add one trace record.
+    pub fn record(&mut self, record: &TraceRecord) {
+        self.record_step(record.pc, &record.instruction, record.regs.c);
+    }
+
+    /// Add everything covered by `other`.
+    pub fn merge(&mut self, other: &Coverage) {
+        self.executed.extend(&other.executed);
+        for (&pc, branch) in &other.branches {
+            let entry = self.branches.entry(pc).or_default();
+            entry.taken |= branch.taken;
+            entry.fallthrough |= branch.fallthrough;
+        }
+    }
+
+    /// Whether the instruction at `addr` has been executed.
+    pub fn is_executed(&self, addr: i32) -> bool {
+        self.executed.contains(&addr)
+    }
+
+    /// Summarize coverage of the `len` cells starting at `start`.
+    pub fn report(&self, start: i32, len: usize) -> CoverageReport {
+        let range = start..start + len as i32;
+        let uncovered: Vec<i32> = range.clone().filter(|addr| !self.is_executed(*addr)).collect();
+        let branches: Vec<(i32, BranchCoverage)> = self.branches
+            .range(range)
+            .map(|(&pc, &branch)| (pc, branch))
+            .collect();
+        CoverageReport {
+            cells: len,
+            executed: len - uncovered.len(),
+            branch_sites: branches.len(),
+            branch_directions: branches.iter().map(|(_, b)| b.directions()).sum(),
+            uncovered,
+            partial_branches: branches.iter().filter(|(_, b)| b.directions() < 2).map(|&(pc, _)| pc).collect(),
+        }
+    }
+}
+
+impl From<&Profile> for Coverage {
+    fn from(profile: &Profile) -> Self {
+        Self {
+            executed: profile.address_counts.keys().copied().collect(),
+            branches: profile.branches.iter()
+                .map(|(&pc, stats)| (pc, BranchCoverage { taken: stats.taken > 0, fallthrough: stats.not_taken > 0 }))
+                .collect(),
+        }
+    }
+}
+
+/// Coverage of a program's cells.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// Cells in the program.
+    pub cells: usize,
+    /// Cells executed at least once.
+    pub executed: usize,
+    /// Conditional branches executed at least once.
+    pub branch_sites: usize,
+    /// Branch directions exercised, out of two per site.
+    pub branch_directions: usize,
+    /// Addresses never executed.
+    pub uncovered: Vec<i32>,
+    /// Branches that have gone only one way.
+    pub partial_branches: Vec<i32>,
+}
+
+impl CoverageReport {
+    /// Percentage of cells executed.
+    pub fn cell_percent(&self) -> f64 {
+        percent(self.executed, self.cells)
+    }
+
+    /// Percentage of branch directions exercised.
+    pub fn branch_percent(&self) -> f64 {
+        percent(self.branch_directions, 2 * self.branch_sites)
+    }
+}
+
+fn percent(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        100.0
+    } else {
+        100.0 * part as f64 / whole as f64
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cells {}/{} ({:.1}%), branch directions {}/{} ({:.1}%)",
+            self.executed, self.cells, self.cell_percent(),
+            self.branch_directions, 2 * self.branch_sites, self.branch_percent()
+        )
+    }
+}
+
+impl Cpu {
+    /// Run for at most `max_cycles` instructions, collecting coverage.
+    pub fn run_covered(&mut self, max_cycles: u64) -> Result<Coverage, CpuError> {
+        let mut coverage = Coverage::new();
+        self.run_traced(max_cycles, |record| coverage.record(&record))?;
+        Ok(coverage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    #[test]
+    fn test_partial_coverage() {
+        // Input 5 never reaches the zero case
+        let program = assemble("LDA 20\nJZ ZERO\nHLT\nZERO: HLT").unwrap();
+        let mut cpu = Cpu::new();
+        cpu.load_program(&program).unwrap();
+        cpu.mem.write(101, crate::Tryte9::from_i32(5));
+        let coverage = cpu.run_covered(100).unwrap();
+
+        let report = coverage.report(0, program.len());
+        assert_eq!(report.uncovered, vec![3]);
+        assert_eq!(report.partial_branches, vec![1]);
+        assert_eq!(coverage.branches[&1], BranchCoverage { taken: false, fallthrough: true });
+        assert_eq!(report.to_string(), "cells 3/4 (75.0%), branch directions 1/2 (50.0%)");
+
+        let mut cpu = Cpu::new();
+        cpu.load_program(&program).unwrap();
+        assert_eq!(Coverage::from(&cpu.run_profiled(100).unwrap()).report(0, 4).uncovered, vec![2]);
+    }
+}
@@ -4,6 +4,7 @@
 //! Each 18-trit word contains two instructions.
 
 use crate::ternary::{Trit, Tryte9};
+use crate::cpu::config::CpuConfig;
 use serde::{Serialize, Deserialize};
 use thiserror::Error;
 
@@ -19,6 +20,9 @@ pub enum AddrMode {
     IndexAdd,
     /// Address - F (mode = N)
     IndexSub,
+    /// The cell at the address holds the effective address (mode = N when
+    /// [`CpuConfig::indirect`](crate::cpu::CpuConfig::indirect) is set)
+    Indirect,
 }
 
 impl AddrMode {
@@ -36,11 +40,23 @@ impl AddrMode {
         match self {
             AddrMode::Direct => Trit::O,
             AddrMode::IndexAdd => Trit::P,
-            AddrMode::IndexSub => Trit::N,
+            AddrMode::IndexSub | AddrMode::Indirect => Trit::N,
         }
     }
 }
 
+/// What the low six trits of an instruction word hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperandKind {
+    /// Nothing; the trits are ignored.
+    None,
+    /// A 5-trit address and an address-mode trit.
+    Address,
+    /// A signed shift count in the low 5 trits.
+    ShiftCount,
+}
+
 /// Decoded Setun instruction.
 /// 
 /// The Setun had 24 instructions organized into groups:
@@ -127,6 +143,10 @@ pub enum Instruction {
     /// Shift right by n trits (divide by 3^n)
     Shr { count: i8 },
     
+    /// Shift by the count held in memory: left for a positive [addr],
+    /// right for a negative one (historical ISA only)
+    Shm { addr: Tryte9, mode: AddrMode },
+    
     // ==================== Special ====================
     
     /// No operation
@@ -134,6 +154,129 @@ pub enum Instruction {
     
     /// Set omega based on S sign
     Tst,
+    
+    /// An instruction from the CPU's
+    /// [`ExtensionRegistry`](crate::cpu::ExtensionRegistry), decoded only
+    /// with [`CpuConfig::experimental`] set
+    Ext { opcode: i8, addr: Tryte9, mode: AddrMode },
+}
+
+impl Instruction {
+    /// The assembler mnemonic for this instruction.
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Add { .. } => "ADD",
+            Instruction::Sub { .. } => "SUB",
+            Instruction::Mul { .. } => "MUL",
+            Instruction::Div { .. } => "DIV",
+            Instruction::AddAbs { .. } => "ADDABS",
+            Instruction::SubAbs { .. } => "SUBABS",
+            Instruction::Lda { .. } => "LDA",
+            Instruction::Sta { .. } => "STA",
+            Instruction::LdaUnsigned { .. } => "LDAU",
+            Instruction::Ldf { .. } => "LDF",
+            Instruction::Stf { .. } => "STF",
+            Instruction::Ldr { .. } => "LDR",
+            Instruction::Str { .. } => "STR",
+            Instruction::Xchg { .. } => "XCHG",
+            Instruction::Jmp { .. } => "JMP",
+            Instruction::Jz { .. } => "JZ",
+            Instruction::Jp { .. } => "JP",
+            Instruction::Jn { .. } => "JN",
+            Instruction::Jop { .. } => "JOP",
+            Instruction::Jon { .. } => "JON",
+            Instruction::Hlt => "HLT",
+            Instruction::Shl { .. } => "SHL",
+            Instruction::Shr { .. } => "SHR",
+            Instruction::Shm { .. } => "SHM",
+            Instruction::Nop => "NOP",
+            Instruction::Tst => "TST",
+            Instruction::Ext { .. } => "EXT",
+        }
+    }
+    
+    /// The operand the instruction takes.
+    pub fn operand(&self) -> OperandKind {
+        match self {
+            Instruction::Hlt | Instruction::Nop | Instruction::Tst => OperandKind::None,
+            Instruction::Shl { .. } | Instruction::Shr { .. } => OperandKind::ShiftCount,
+            _ => OperandKind::Address,
+        }
+    }
+    
+    /// The address mode, for instructions with an address operand.
+    pub fn mode(&self) -> Option<AddrMode> {
+        match self {
+            Instruction::Add { mode, .. } | Instruction::Sub { mode, .. } | Instruction::Mul { mode, .. }
+            | Instruction::Div { mode, .. } | Instruction::AddAbs { mode, .. } | Instruction::SubAbs { mode, .. }
+            | Instruction::Lda { mode, .. } | Instruction::LdaUnsigned { mode, .. } | Instruction::Sta { mode, .. }
+            | Instruction::Ldf { mode, .. } | Instruction::Stf { mode, .. } | Instruction::Ldr { mode, .. }
+            | Instruction::Str { mode, .. } | Instruction::Xchg { mode, .. } | Instruction::Jmp { mode, .. }
+            | Instruction::Jz { mode, .. } | Instruction::Jp { mode, .. } | Instruction::Jn { mode, .. }
+            | Instruction::Jop { mode, .. } | Instruction::Jon { mode, .. }
+            | Instruction::Shm { mode, .. } | Instruction::Ext { mode, .. } => Some(*mode),
+            Instruction::Hlt | Instruction::Nop | Instruction::Tst
+            | Instruction::Shl { .. } | Instruction::Shr { .. } => None,
+        }
+    }
+    
+    /// The address mode, mutably, for instructions with an address operand.
+    pub fn mode_mut(&mut self) -> Option<&mut AddrMode> {
+        match self {
+            Instruction::Add { mode, .. } | Instruction::Sub { mode, .. } | Instruction::Mul { mode, .. }
+            | Instruction::Div { mode, .. } | Instruction::AddAbs { mode, .. } | Instruction::SubAbs { mode, .. }
+            | Instruction::Lda { mode, .. } | Instruction::LdaUnsigned { mode, .. } | Instruction::Sta { mode, .. }
+            | Instruction::Ldf { mode, .. } | Instruction::Stf { mode, .. } | Instruction::Ldr { mode, .. }
+            | Instruction::Str { mode, .. } | Instruction::Xchg { mode, .. } | Instruction::Jmp { mode, .. }
+            | Instruction::Jz { mode, .. } | Instruction::Jp { mode, .. } | Instruction::Jn { mode, .. }
+            | Instruction::Jop { mode, .. } | Instruction::Jon { mode, .. }
+            | Instruction::Shm { mode, .. } | Instruction::Ext { mode, .. } => Some(mode),
+            Instruction::Hlt | Instruction::Nop | Instruction::Tst
+            | Instruction::Shl { .. } | Instruction::Shr { .. } => None,
+        }
+    }
+    
+    /// One-line description of what the instruction does.
+    pub fn summary(&self) -> &'static str {
+        match self {
+            Instruction::Add { .. } => "S := S + [addr]; ω := sign(S)",
+            Instruction::Sub { .. } => "S := S - [addr]; ω := sign(S)",
+            Instruction::Mul { .. } => "(S, R) := S × [addr], high half in S, low half in R; ω := sign(S)",
+            Instruction::Div { .. } => "S := S / [addr], R := remainder; ω := sign(S)",
+            Instruction::AddAbs { .. } => "S := S + |[addr]|; ω := sign(S)",
+            Instruction::SubAbs { .. } => "S := S - |[addr]|; ω := sign(S)",
+            Instruction::Lda { .. } => "S := [addr]; ω := sign(S)",
+            Instruction::Sta { .. } => "[addr] := low 9 trits of S",
+            Instruction::LdaUnsigned { .. } => "S := [addr] (zero-extended); ω := sign(S)",
+            Instruction::Ldf { .. } => "F := low 5 trits of [addr]",
+            Instruction::Stf { .. } => "[addr] := F",
+            Instruction::Ldr { .. } => "R := [addr]",
+            Instruction::Str { .. } => "[addr] := low 9 trits of R",
+            Instruction::Xchg { .. } => "S := [addr], [addr] := low 9 trits of old S; ω := sign(S)",
+            Instruction::Jmp { .. } => "C := addr",
+            Instruction::Jz { .. } => "if S = 0 then C := addr",
+            Instruction::Jp { .. } => "if S > 0 then C := addr",
+            Instruction::Jn { .. } => "if S < 0 then C := addr",
+            Instruction::Jop { .. } => "if ω = + then C := addr",
+            Instruction::Jon { .. } => "if ω = - then C := addr",
+            Instruction::Hlt => "stop the machine",
+            Instruction::Shl { .. } => "S := S × 3^n, dropping trits shifted out; ω := sign(S)",
+            Instruction::Shr { .. } => "S := S / 3^n, rounded; ω := sign(S)",
+            Instruction::Shm { .. } => "S := S × 3^[addr], or S / 3^-[addr] when negative; ω := sign(S)",
+            Instruction::Nop => "do nothing",
+            Instruction::Tst => "ω := sign(S)",
+            Instruction::Ext { .. } => "defined by an ISA extension",
+        }
+    }
+    
+    /// Is this a conditional jump?
+    pub fn is_conditional_branch(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Jz { .. } | Instruction::Jp { .. } | Instruction::Jn { .. }
+                | Instruction::Jop { .. } | Instruction::Jon { .. }
+        )
+    }
 }
 
 /// Opcode values for decoding.
@@ -141,7 +284,7 @@ pub enum Instruction {
 /// The Setun used a subset of the 9-trit space for opcodes.
 /// The high trits encode the instruction, low trits encode the address.
 #[derive(Debug, Clone, Copy)]
-struct Opcode(i8);
+struct Opcode;
 
 impl Opcode {
     // Instruction opcode values (occupying high trits of the nitrit)
@@ -161,6 +304,7 @@ impl Opcode {
     const NOP: i8 = 8;
     const SHL: i8 = 9;
     const SHR: i8 = -9;
+    const SHM: i8 = -12;     // historical only
     const LDR: i8 = 10;
     const STR: i8 = -10;
     const ADDABS: i8 = 11;
@@ -222,15 +366,33 @@ pub fn decode(nitrit: Tryte9) -> Result<Instruction, DecodeError> {
         op if op == Opcode::TST => Instruction::Tst,
         op if op == Opcode::SHL => Instruction::Shl { count: addr_val as i8 },
         op if op == Opcode::SHR => Instruction::Shr { count: addr_val as i8 },
+        op if op == Opcode::SHM => Instruction::Shm { addr, mode },
         _ => return Err(DecodeError::InvalidOpcode(op_val)),
     };
     
     Ok(instruction)
 }
 
-/// Encode an instruction back to a 9-trit word.
-pub fn encode(instr: &Instruction) -> Tryte9 {
-    let (opcode, addr, mode): (i8, i32, AddrMode) = match instr {
+/// Decode an instruction for a machine with `config`: with
+/// [`CpuConfig::indirect`] set, mode trit N reads as
+/// [`AddrMode::Indirect`] rather than [`AddrMode::IndexSub`], and
+/// [`Instruction::Shm`] only exists on the historical machine.
+pub fn decode_for(nitrit: Tryte9, config: &CpuConfig) -> Result<Instruction, DecodeError> {
+    let mut instr = decode(nitrit)?;
+    if matches!(instr, Instruction::Shm { .. }) && !config.is_historical() {
+        return Err(DecodeError::InvalidOpcode(Opcode::SHM));
+    }
+    if config.indirect {
+        if let Some(mode @ AddrMode::IndexSub) = instr.mode_mut() {
+            *mode = AddrMode::Indirect;
+        }
+    }
+    Ok(instr)
+}
+
+/// Opcode, address (or shift count) and mode of an instruction.
+fn fields(instr: &Instruction) -> (i8, i32, AddrMode) {
+    match instr {
         Instruction::Add { addr, mode } => (Opcode::ADD, addr.to_i32(), *mode),
         Instruction::Sub { addr, mode } => (Opcode::SUB, addr.to_i32(), *mode),
         Instruction::Mul { addr, mode } => (Opcode::MUL, addr.to_i32(), *mode),
@@ -256,14 +418,54 @@ pub fn encode(instr: &Instruction) -> Tryte9 {
         Instruction::Tst => (Opcode::TST, 0, AddrMode::Direct),
         Instruction::Shl { count } => (Opcode::SHL, *count as i32, AddrMode::Direct),
         Instruction::Shr { count } => (Opcode::SHR, *count as i32, AddrMode::Direct),
-    };
+        Instruction::Shm { addr, mode } => (Opcode::SHM, addr.to_i32(), *mode),
+        Instruction::Ext { opcode, addr, mode } => (*opcode, addr.to_i32(), *mode),
+    }
+}
+
+/// Largest magnitude of the 5-trit address field.
+pub const ADDR_FIELD_MAX: i32 = 121;
+
+/// Largest magnitude of the 3-trit opcode field.
+const OPCODE_FIELD_MAX: i8 = 13;
+
+/// Encode an instruction to a 9-trit word, rejecting operands that do not
+/// fit their fields.
+pub fn try_encode(instr: &Instruction) -> Result<Tryte9, EncodeError> {
+    let (opcode, addr, _) = fields(instr);
+    if opcode.unsigned_abs() > OPCODE_FIELD_MAX as u8 {
+        return Err(EncodeError::Unencodable(instr.mnemonic()));
+    }
+    if addr.abs() > ADDR_FIELD_MAX {
+        return Err(match instr {
+            Instruction::Shl { count } | Instruction::Shr { count } => EncodeError::ShiftOutOfRange(*count),
+            _ => EncodeError::AddressOutOfRange(addr),
+        });
+    }
+    Ok(encode_lossy(instr))
+}
+
+/// Encode an instruction to a 9-trit word.
+///
+/// # Panics
+/// Panics if an operand does not fit its field; use [`try_encode`] for
+/// untrusted instructions.
+pub fn encode(instr: &Instruction) -> Tryte9 {
+    try_encode(instr).unwrap_or_else(|e| panic!("cannot encode {:?}: {}", instr, e))
+}
+
+/// Encode an instruction, keeping only the low 5 trits of the address or
+/// shift count and the low 3 trits of the opcode. Out-of-range operands
+/// silently produce a different instruction; prefer [`try_encode`].
+pub fn encode_lossy(instr: &Instruction) -> Tryte9 {
+    let (opcode, addr, mode) = fields(instr);
     
     let mut trits = [Trit::O; 9];
     
     // Encode address in low 5 trits
     let mut addr_work = if addr < 0 { -addr } else { addr };
     let addr_negative = addr < 0;
-    for i in 0..5 {
+    for slot in trits.iter_mut().take(5) {
         let remainder = ((addr_work % 3) + 1) as i8;
         let (trit, carry) = match remainder {
             1 => (Trit::O, 0),
@@ -271,15 +473,13 @@ pub fn encode(instr: &Instruction) -> Tryte9 {
             3 => (Trit::N, 1),
             _ => unreachable!(),
         };
-        trits[i] = if addr_negative { trit.neg() } else { trit };
+        *slot = if addr_negative { trit.neg() } else { trit };
         addr_work = addr_work / 3 + carry;
     }
     if addr_negative {
         // Re-negate properly using the conversion
         let proper_addr = Tryte9::from_i32(addr);
-        for i in 0..5 {
-            trits[i] = proper_addr.trits()[i];
-        }
+        trits[..5].copy_from_slice(&proper_addr.trits()[..5]);
     }
     
     // Encode mode in trit 5
@@ -303,6 +503,19 @@ pub fn encode(instr: &Instruction) -> Tryte9 {
     Tryte9::from_trits(trits)
 }
 
+/// Errors from [`try_encode`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EncodeError {
+    #[error("address {0} does not fit in the 5-trit address field (±121)")]
+    AddressOutOfRange(i32),
+    
+    #[error("shift count {0} does not fit in the 5-trit field (±121)")]
+    ShiftOutOfRange(i8),
+    
+    #[error("{0} has no 3-trit opcode")]
+    Unencodable(&'static str),
+}
+
 /// Errors that can occur during instruction decoding.
 #[derive(Debug, Clone, Error)]
 pub enum DecodeError {
@@ -332,6 +545,18 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_decode_for_indirect() {
+        let word = encode(&Instruction::Lda { addr: Tryte9::from_i32(9), mode: AddrMode::Indirect });
+        let mut config = CpuConfig::default();
+        assert_eq!(decode_for(word, &config).unwrap(), Instruction::Lda { addr: Tryte9::from_i32(9), mode: AddrMode::IndexSub });
+        config.indirect = true;
+        assert_eq!(decode_for(word, &config).unwrap(), Instruction::Lda { addr: Tryte9::from_i32(9), mode: AddrMode::Indirect });
+        assert_eq!(decode_for(encode(&Instruction::Hlt), &config).unwrap(), Instruction::Hlt);
+        assert_eq!(decode_for(word, &config).unwrap().mode(), Some(AddrMode::Indirect));
+        assert_eq!(Instruction::Shl { count: 2 }.mode(), None);
+    }
+    
     #[test]
     fn test_encode_decode_roundtrip() {
         let test_cases = [
@@ -359,4 +584,28 @@ mod tests {
             }
         }
     }
+    
+    #[test]
+    fn test_try_encode_rejects_wide_operands() {
+        let lda = |addr| Instruction::Lda { addr: Tryte9::from_i32(addr), mode: AddrMode::IndexSub };
+        assert_eq!(decode(try_encode(&lda(-121)).unwrap()).unwrap(), lda(-121));
+        assert_eq!(try_encode(&lda(122)), Err(EncodeError::AddressOutOfRange(122)));
+        assert_eq!(try_encode(&Instruction::Shl { count: 127 }), Err(EncodeError::ShiftOutOfRange(127)));
+        assert_eq!(try_encode(&Instruction::Tst), Err(EncodeError::Unencodable("TST")));
+        let ext = Instruction::Ext { opcode: i8::MIN, addr: Tryte9::zero(), mode: AddrMode::Direct };
+        assert_eq!(try_encode(&ext), Err(EncodeError::Unencodable("EXT")));
+        
+        // The lossy form keeps only the low five trits
+        assert_eq!(decode(encode_lossy(&lda(122))).unwrap(), lda(-121));
+        assert!(std::panic::catch_unwind(|| encode(&lda(500))).is_err());
+    }
+    
+    #[test]
+    fn test_every_word_decodes_or_errors() {
+        for value in Tryte9::MIN..=Tryte9::MAX {
+            if let Ok(instr) = decode(Tryte9::from_i32(value)) {
+                assert_eq!(decode(encode(&instr)).unwrap(), instr);
+            }
+        }
+    }
 }
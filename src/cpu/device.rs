@@ -0,0 +1,440 @@
+//! Pluggable memory-mapped peripherals.
+//!
+//! A [`Device`] claims a set of addresses; operand reads and writes to
+//! those addresses go to the device instead of memory. Devices are attached
+//! to a CPU's [`DeviceBus`] (`cpu.devices`), so peripherals such as
+//! displays or sensors can live outside this crate:
+//!
+//! ```
+//! use setun::cpu::{Cpu, Device, DeviceError};
+//! use setun::Tryte9;
+//!
+//! /// Counts instructions; reading address 100 returns the count.
+//! #[derive(Clone, Default)]
+//! struct Clock(i32);
+//!
+//! impl Device for Clock {
+//!     fn name(&self) -> &str { "clock" }
+//!     fn addresses(&self) -> Vec<i32> { vec![100] }
+//!     fn read(&mut self, _addr: i32) -> Result<Tryte9, DeviceError> { Ok(Tryte9::from_i32(self.0)) }
+//!     fn write(&mut self, _addr: i32, _value: Tryte9) -> Result<(), DeviceError> { Ok(()) }
+//!     fn reset(&mut self) { self.0 = 0; }
+//!     fn tick(&mut self) { self.0 = (self.0 + 1) % Tryte9::MAX; }
+//!     fn clone_box(&self) -> Box<dyn Device> { Box::new(self.clone()) }
+//! }
+//!
+//! let mut cpu = Cpu::new();
+//! cpu.devices.attach(Clock::default()).unwrap();
+//! ```
+//!
+//! The built-in tape reader and teletype ([`IoPorts`](super::IoPorts)) take
+//! precedence over devices at their addresses while they are enabled.
+//! Devices are not part of snapshots: a restored CPU has an empty bus.
+//!
+//! Devices that need to act at particular cycles, such as timers or a
+//! rotating drum, schedule events with the bus's cycle-exact
+//! [`Scheduler`](super::Scheduler); see [`schedule`](super::schedule).
+//!
+//! The Setun had no interrupt mechanism, so [`Device::pending_interrupt`]
+//! does not divert execution; hosts poll [`DeviceBus::pending_interrupt`]
+//! (for example to pause a run) and decide what to do.
+
+use crate::cpu::schedule::{Schedule, ScheduledEvent, Scheduler};
+use crate::ternary::Tryte9;
+use std::any::Any;
+use thiserror::Error;
+
+/// A memory-mapped peripheral.
+pub trait Device: Any + Send + Sync {
+    /// Short name used in messages and lookups.
+    fn name(&self) -> &str;
+
+    /// Addresses this device answers; must not overlap other devices.
+    fn addresses(&self) -> Vec<i32>;
+
+    /// Handle an operand read at `addr`.
+    fn read(&mut self, addr: i32) -> Result<Tryte9, DeviceError>;
+
+    /// Handle an operand write of `value` at `addr`.
+    fn write(&mut self, addr: i32, value: Tryte9) -> Result<(), DeviceError>;
+
+    /// What a read at `addr` would return, without side effects. Used by
+    /// tracing and debuggers; `None` means the value cannot be previewed.
+    fn peek(&self, _addr: i32) -> Option<Tryte9> {
+        None
+    }
+
+    /// Return to power-on state (called by [`Cpu::reset`](super::Cpu::reset)).
+    fn reset(&mut self) {}
+
+    /// Advance by one executed instruction.
+    fn tick(&mut self) {}
+
+    /// Called when attached and again after every reset, so the device
+    /// can schedule its first events.
+    fn attached(&mut self, _schedule: &mut Schedule<'_>) {}
+
+    /// Handle `event`, which was scheduled for the current cycle.
+    fn event(&mut self, _event: u32, _schedule: &mut Schedule<'_>) {}
+
+    /// Whether the device is requesting attention.
+    fn pending_interrupt(&self) -> bool {
+        false
+    }
+
+    /// Clone into a box, so CPUs with devices can be cloned.
+    fn clone_box(&self) -> Box<dyn Device>;
+}
+
+/// Identifies an attached device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeviceId(usize);
+
+/// The devices attached to a CPU.
+#[derive(Default)]
+pub struct DeviceBus {
+    devices: Vec<Box<dyn Device>>,
+    scheduler: Scheduler,
+}
+
+impl DeviceBus {
+    /// Attach a device, failing if it claims an address already in use.
+    pub fn attach(&mut self, device: impl Device) -> Result<DeviceId, DeviceError> {
+        self.attach_boxed(Box::new(device))
+    }
+
+    /// Attach an already-boxed device.
+    pub fn attach_boxed(&mut self, device: Box<dyn Device>) -> Result<DeviceId, DeviceError> {
+        for addr in device.addresses() {
+            if addr.unsigned_abs() > Tryte9::MAX as u32 {
+                return Err(DeviceError::InvalidAddress { device: device.name().to_string(), addr });
+            }
+            if let Some(owner) = self.find(addr) {
+                return Err(DeviceError::AddressInUse {
+                    device: device.name().to_string(),
+                    owner: owner.name().to_string(),
+                    addr,
+                });
+            }
+        }
+        let id = DeviceId(self.devices.len());
+        self.devices.push(device);
+        self.devices[id.0].attached(&mut Schedule::new(id, &mut self.scheduler));
+        Ok(id)
+    }
+
+    /// Number of attached devices.
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    /// Whether no devices are attached.
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// Names of the attached devices, in attach order.
+    pub fn names(&self) -> Vec<&str> {
+        self.devices.iter().map(|d| d.name()).collect()
+    }
+
+    /// The attached device `id` as its concrete type.
+    pub fn get<T: Device>(&self, id: DeviceId) -> Option<&T> {
+        let device: &dyn Any = self.devices.get(id.0)?.as_ref();
+        device.downcast_ref()
+    }
+
+    /// The attached device `id` as its concrete type, mutably.
+    pub fn get_mut<T: Device>(&mut self, id: DeviceId) -> Option<&mut T> {
+        let device: &mut dyn Any = self.devices.get_mut(id.0)?.as_mut();
+        device.downcast_mut()
+    }
+
+    /// The device mapped at `addr`, if any.
+    pub fn find(&self, addr: i32) -> Option<&dyn Device> {
+        self.devices.iter().find(|d| d.addresses().contains(&addr)).map(|d| d.as_ref())
+    }
+
+    fn find_mut(&mut self, addr: i32) -> Option<&mut Box<dyn Device>> {
+        self.devices.iter_mut().find(|d| d.addresses().contains(&addr))
+    }
+
+    /// Whether any device is mapped at `addr`.
+    pub fn is_mapped(&self, addr: i32) -> bool {
+        !self.devices.is_empty() && self.find(addr).is_some()
+    }
+
+    /// Read from the device at `addr`; `None` if nothing is mapped there.
+    pub fn read(&mut self, addr: i32) -> Option<Result<Tryte9, DeviceError>> {
+        self.find_mut(addr).map(|d| d.read(addr))
+    }
+
+    /// Write to the device at `addr`; `None` if nothing is mapped there.
+    pub fn write(&mut self, addr: i32, value: Tryte9) -> Option<Result<(), DeviceError>> {
+        self.find_mut(addr).map(|d| d.write(addr, value))
+    }
+
+    /// Preview a read at `addr` (see [`Device::peek`]).
+    pub fn peek(&self, addr: i32) -> Option<Tryte9> {
+        self.find(addr)?.peek(addr)
+    }
+
+    /// Reset every device and the clock, dropping pending events.
+    pub fn reset(&mut self) {
+        self.scheduler.clear();
+        for (index, device) in self.devices.iter_mut().enumerate() {
+            device.reset();
+            device.attached(&mut Schedule::new(DeviceId(index), &mut self.scheduler));
+        }
+    }
+
+    /// Advance one cycle (see [`DeviceBus::advance_to`]).
+    pub fn tick(&mut self) {
+        self.advance_to(self.scheduler.now() + 1);
+    }
+
+    /// Advance the clock to `cycle`: tick every device once, in attach
+    /// order, then deliver the events due by `cycle`. A bus that is behind,
+    /// e.g. one attached to a restored CPU, jumps straight to `cycle`.
+    pub fn advance_to(&mut self, cycle: u64) {
+        self.scheduler.set_now(cycle);
+        self.devices.iter_mut().for_each(|d| d.tick());
+        while let Some(due) = self.scheduler.pop_due() {
+            if let Some(device) = self.devices.get_mut(due.device.0) {
+                device.event(due.event, &mut Schedule::new(due.device, &mut self.scheduler));
+            }
+        }
+    }
+
+    /// The bus clock, which follows [`Cpu::cycles`](super::Cpu::cycles).
+    pub fn now(&self) -> u64 {
+        self.scheduler.now()
+    }
+
+    /// Deliver `event` to device `id` at `cycle`.
+    pub fn schedule_at(&mut self, id: DeviceId, cycle: u64, event: u32) {
+        self.scheduler.schedule_at(cycle, id, event);
+    }
+
+    /// Pending events in delivery order.
+    pub fn pending_events(&self) -> Vec<ScheduledEvent> {
+        self.scheduler.pending()
+    }
+
+    /// Name of the first device with an interrupt pending.
+    pub fn pending_interrupt(&self) -> Option<&str> {
+        self.devices.iter().find(|d| d.pending_interrupt()).map(|d| d.name())
+    }
+}
+
+impl Clone for DeviceBus {
+    fn clone(&self) -> Self {
+        DeviceBus {
+            devices: self.devices.iter().map(|d| d.clone_box()).collect(),
+            scheduler: self.scheduler.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for DeviceBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// Errors from attaching or accessing devices.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DeviceError {
+    #[error("{device}: address {addr} is already mapped to {owner}")]
+    AddressInUse { device: String, owner: String, addr: i32 },
+
+    #[error("{device}: address {addr} is outside the address space")]
+    InvalidAddress { device: String, addr: i32 },
+
+    #[error("{device}: {message}")]
+    Failed { device: String, message: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::cpu::Cpu;
+
+    /// Latches written values and counts ticks; raises an interrupt on a negative write.
+    #[derive(Clone, Default)]
+    struct Latch {
+        value: Tryte9,
+        ticks: u32,
+    }
+
+    impl Device for Latch {
+        fn name(&self) -> &str {
+            "latch"
+        }
+
+        fn addresses(&self) -> Vec<i32> {
+            vec![90, 91]
+        }
+
+        fn read(&mut self, addr: i32) -> Result<Tryte9, DeviceError> {
+            match addr {
+                90 => Ok(self.value),
+                _ => Err(DeviceError::Failed { device: "latch".into(), message: "write-only".into() }),
+            }
+        }
+
+        fn write(&mut self, _addr: i32, value: Tryte9) -> Result<(), DeviceError> {
+            self.value = value;
+            Ok(())
+        }
+
+        fn peek(&self, _addr: i32) -> Option<Tryte9> {
+            Some(self.value)
+        }
+
+        fn reset(&mut self) {
+            *self = Latch::default();
+        }
+
+        fn tick(&mut self) {
+            self.ticks += 1;
+        }
+
+        fn pending_interrupt(&self) -> bool {
+            self.value.to_i32() < 0
+        }
+
+        fn clone_box(&self) -> Box<dyn Device> {
+            Box::new(self.clone())
+        }
+    }
+
+    /// A device that claims an address no machine has.
+    #[derive(Clone)]
+    struct Stray;
+
+    impl Device for Stray {
+        fn name(&self) -> &str {
+            "stray"
+        }
+
+        fn addresses(&self) -> Vec<i32> {
+            vec![i32::MIN]
+        }
+
+        fn read(&mut self, _addr: i32) -> Result<Tryte9, DeviceError> {
+            Ok(Tryte9::zero())
+        }
+
+        fn write(&mut self, _addr: i32, _value: Tryte9) -> Result<(), DeviceError> {
+            Ok(())
+        }
+
+        fn clone_box(&self) -> Box<dyn Device> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_device_mapped_access() {
+        let mut cpu = Cpu::new();
+        let id = cpu.devices.attach(Latch::default()).unwrap();
+        cpu.load_program(&assemble("LDA 4\nSTA 90\nLDA 90\nHLT\nDAT -7").unwrap()).unwrap();
+        cpu.run().unwrap();
+
+        let latch = cpu.devices.get::<Latch>(id).unwrap();
+        assert_eq!(latch.value.to_i32(), -7);
+        assert_eq!(latch.ticks, 4);
+        assert_eq!(cpu.regs.s.to_i64(), -7);
+        assert_eq!(cpu.devices.pending_interrupt(), Some("latch"));
+        assert_eq!(cpu.peek_operand(Tryte9::from_i32(90)).unwrap().to_i32(), -7);
+
+        // Clones get their own copy of the device
+        let mut copy = cpu.clone();
+        copy.reset();
+        assert_eq!(copy.devices.get::<Latch>(id).unwrap().ticks, 0);
+        assert_eq!(cpu.devices.get::<Latch>(id).unwrap().ticks, 4);
+    }
+
+    /// Logs every event it receives with the cycle it arrived on.
+    #[derive(Clone)]
+    struct Recorder {
+        addr: i32,
+        log: Vec<(u64, u32)>,
+    }
+
+    impl Device for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn addresses(&self) -> Vec<i32> {
+            vec![self.addr]
+        }
+
+        fn read(&mut self, _addr: i32) -> Result<Tryte9, DeviceError> {
+            Ok(Tryte9::zero())
+        }
+
+        fn write(&mut self, _addr: i32, _value: Tryte9) -> Result<(), DeviceError> {
+            Ok(())
+        }
+
+        fn reset(&mut self) {
+            self.log.clear();
+        }
+
+        fn attached(&mut self, schedule: &mut Schedule<'_>) {
+            schedule.schedule_at(2, 1);
+        }
+
+        fn event(&mut self, event: u32, schedule: &mut Schedule<'_>) {
+            self.log.push((schedule.now(), event));
+            // Event 1 chains an event due in the same cycle
+            if event == 1 {
+                schedule.schedule_in(0, 9);
+            }
+        }
+
+        fn clone_box(&self) -> Box<dyn Device> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_scheduled_events() {
+        let mut cpu = Cpu::new();
+        let a = cpu.devices.attach(Recorder { addr: 95, log: Vec::new() }).unwrap();
+        let b = cpu.devices.attach(Recorder { addr: 96, log: Vec::new() }).unwrap();
+        cpu.devices.schedule_at(a, 3, 5);
+        cpu.devices.schedule_at(b, 2, 7);
+        cpu.load_program(&assemble("NOP\nNOP\nNOP\nHLT").unwrap()).unwrap();
+
+        let mut replay = cpu.clone();
+        cpu.run().unwrap();
+        replay.run().unwrap();
+        assert_eq!(cpu.devices.now(), 4);
+        assert_eq!(cpu.devices.get::<Recorder>(a).unwrap().log, vec![(2, 1), (2, 9), (3, 5)]);
+        assert_eq!(cpu.devices.get::<Recorder>(b).unwrap().log, vec![(2, 1), (2, 7), (2, 9)]);
+        assert_eq!(replay.devices.get::<Recorder>(b).unwrap().log, cpu.devices.get::<Recorder>(b).unwrap().log);
+        assert!(cpu.devices.pending_events().is_empty());
+
+        // Reset rewinds the clock and reschedules from scratch
+        cpu.reset();
+        assert_eq!(cpu.devices.now(), 0);
+        assert_eq!(cpu.devices.pending_events().len(), 2);
+        assert!(cpu.devices.get::<Recorder>(a).unwrap().log.is_empty());
+    }
+
+    #[test]
+    fn test_device_errors() {
+        let mut cpu = Cpu::new();
+        cpu.devices.attach(Latch::default()).unwrap();
+        assert!(matches!(cpu.devices.attach(Latch::default()), Err(DeviceError::AddressInUse { addr: 90, .. })));
+        assert!(matches!(cpu.devices.attach(Stray), Err(DeviceError::InvalidAddress { addr: i32::MIN, .. })));
+
+        cpu.load_program(&assemble("LDA 91\nHLT").unwrap()).unwrap();
+        assert!(cpu.step().is_err());
+    }
+}
@@ -2,11 +2,21 @@
 //!
 //! Implements the fetch-decode-execute cycle and all instruction behaviors.
 
-use crate::ternary::{Trit, Tryte9, Word18, arith};
+use crate::ternary::{ConvertError, Trit, Tryte9, Word18, arith};
 use crate::cpu::{Memory, Registers};
-use crate::cpu::decode::{self, Instruction, AddrMode, DecodeError};
+use crate::cpu::decode::{AddrMode, Instruction, DecodeError};
 use crate::cpu::registers::Tryte5;
 use crate::cpu::memory::MemoryError;
+use crate::cpu::trace::RegisterState;
+use crate::cpu::config::{AddressPolicy, CpuConfig, OverflowPolicy};
+use crate::cpu::timing;
+use crate::cpu::io::IoPorts;
+use crate::cpu::device::{DeviceBus, DeviceError};
+use crate::cpu::protect::{MemoryProtection, WriteFault, WritePolicy};
+use crate::cpu::fault::{FaultInjector, FaultSite, FaultTarget, InjectedFault};
+use crate::cpu::overlay::SegmentTable;
+use crate::cpu::extension::ExtensionRegistry;
+use crate::diagnostic::Diagnostic;
 use serde::{Serialize, Deserialize};
 use thiserror::Error;
 
@@ -21,6 +31,36 @@ pub enum CpuState {
     Error,
 }
 
+/// Why a bounded run stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopReason {
+    /// The CPU executed HLT (or was already stopped).
+    Halted,
+    /// The requested number of instructions was executed.
+    CycleLimit,
+    /// The next instruction is at a breakpoint (see [`EmulatorSession`](crate::session::EmulatorSession)).
+    Breakpoint,
+    /// The predicate given to [`Cpu::run_until`] held.
+    Condition,
+    /// An instruction raised an error.
+    Error,
+}
+
+/// Compact result of [`Cpu::step_many`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepSummary {
+    /// Number of instructions executed.
+    pub executed: u64,
+    /// Why execution stopped.
+    pub stop_reason: StopReason,
+    /// Error message when `stop_reason` is `Error`.
+    pub error: Option<String>,
+    /// Memory indices whose contents changed, in ascending order.
+    pub changed_memory: Vec<usize>,
+    /// Registers after the batch.
+    pub regs: RegisterState,
+}
+
 /// The Setun CPU.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Cpu {
@@ -32,6 +72,34 @@ pub struct Cpu {
     pub state: CpuState,
     /// Instruction count (for profiling).
     pub cycles: u64,
+    /// Emulated time in microseconds (only advanced when timing is enabled).
+    #[serde(default)]
+    pub elapsed_us: u64,
+    /// Accumulated cost (only advanced when a cost model is configured).
+    #[serde(default)]
+    pub cost: u64,
+    /// Machine configuration.
+    #[serde(default)]
+    pub config: CpuConfig,
+    /// Paper tape and teletype ports.
+    #[serde(default)]
+    pub io: IoPorts,
+    /// Attached peripherals (not saved in snapshots).
+    #[serde(skip)]
+    pub devices: DeviceBus,
+    /// Read-only regions and the stores they caught.
+    #[serde(default)]
+    pub protection: MemoryProtection,
+    /// Fault schedule and the faults injected so far.
+    #[serde(default)]
+    pub injector: FaultInjector,
+    /// Loaded segments and registered overlays.
+    #[serde(default)]
+    pub segments: SegmentTable,
+    /// Custom instructions, decoded when
+    /// [`CpuConfig::experimental`] is set (not saved in snapshots).
+    #[serde(skip)]
+    pub extensions: ExtensionRegistry,
     /// Last executed instruction (for debugging).
     last_instr: Option<Instruction>,
 }
@@ -39,13 +107,35 @@ pub struct Cpu {
 impl Cpu {
     /// Create a new CPU with zeroed state.
     pub fn new() -> Self {
-        Self {
+        Self::with_config(CpuConfig::default())
+    }
+    
+    /// Create a new CPU for the given machine configuration.
+    ///
+    /// # Panics
+    /// Panics if the configuration is invalid (see [`CpuConfig::validate`]).
+    pub fn with_config(config: CpuConfig) -> Self {
+        if let Err(e) = config.validate() {
+            panic!("{}", e);
+        }
+        let mut cpu = Self {
             regs: Registers::new(),
-            mem: Memory::new(),
+            mem: Memory::with_size(config.memory_size),
             state: CpuState::Running,
             cycles: 0,
+            elapsed_us: 0,
+            cost: 0,
+            config,
+            io: IoPorts::default(),
+            devices: DeviceBus::default(),
+            protection: MemoryProtection::default(),
+            injector: FaultInjector::default(),
+            segments: SegmentTable::default(),
+            extensions: ExtensionRegistry::default(),
             last_instr: None,
-        }
+        };
+        cpu.reset_stack_pointer();
+        cpu
     }
     
     /// Reset the CPU to initial state.
@@ -54,17 +144,55 @@ impl Cpu {
         self.mem.clear();
         self.state = CpuState::Running;
         self.cycles = 0;
+        self.elapsed_us = 0;
+        self.cost = 0;
         self.last_instr = None;
+        self.devices.reset();
+        self.protection.take_faults();
+        self.injector.take_log();
+        self.segments.clear_resident();
+        self.reset_stack_pointer();
+    }
+    
+    /// Point F at the base of the configured stack, if any.
+    fn reset_stack_pointer(&mut self) {
+        if let Some(stack) = self.config.stack {
+            self.regs.f = Tryte5::from_i32(stack.base);
+        }
     }
     
     /// Load a program into memory.
     pub fn load_program(&mut self, program: &[Tryte9]) -> Result<(), MemoryError> {
-        self.mem.load_program(81, program) // Load at address 0 (index 81)
+        let origin = self.mem.origin();
+        self.mem.load_program(origin, program) // Load at address 0 (index 81)
+    }
+    
+    /// Load a program so its first word is at address `addr`.
+    pub fn load_program_at(&mut self, addr: i32, program: &[Tryte9]) -> Result<(), MemoryError> {
+        if addr.unsigned_abs() > Tryte9::MAX as u32 {
+            return Err(MemoryError::AddressOutOfRange(addr));
+        }
+        let index = self.mem.addr_to_index(Tryte9::from_i32(addr))?;
+        self.mem.load_program(index, program)
+    }
+    
+    /// Start execution at address `addr` instead of 0.
+    pub fn set_entry(&mut self, addr: i32) -> Result<(), MemoryError> {
+        if addr.unsigned_abs() > Tryte9::MAX as u32 {
+            return Err(MemoryError::AddressOutOfRange(addr));
+        }
+        let addr = Tryte9::from_i32(addr);
+        self.mem.addr_to_index(addr)?;
+        self.regs.c = addr;
+        Ok(())
     }
     
     /// Execute a single instruction.
     /// 
     /// Returns the instruction that was executed, or an error.
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        level = "trace", name = "step", skip_all, fields(pc = self.regs.c.to_i32(), cycle = self.cycles)
+    ))]
     pub fn step(&mut self) -> Result<Instruction, CpuError> {
         if self.state != CpuState::Running {
             return Err(CpuError::NotRunning(self.state));
@@ -73,20 +201,38 @@ impl Cpu {
         // Fetch
         let pc = self.regs.c;
         let raw = self.mem.read_ternary(pc)
-            .map_err(|e| CpuError::MemoryError(e))?;
+            .map_err(CpuError::MemoryError)?;
         
         // Advance PC before decode (some jumps will override)
-        self.regs.advance_pc();
+        self.regs.advance_pc()?;
         
         // Decode
-        let instr = decode::decode(raw)
-            .map_err(|e| CpuError::DecodeError(e))?;
+        let instr = self.extensions.decode(raw, &self.config).map_err(|e| {
+            event!(warn, word = raw.to_i32(), "decode error: {}", e);
+            CpuError::DecodeError(e)
+        })?;
         
         // Execute
-        self.execute(instr)?;
-        
+        if let Err(e) = self.execute(instr).and_then(|()| self.check_stack()) {
+            event!(debug, instruction = instr.mnemonic(), "execution error: {}", e);
+            return Err(e);
+        }
+
         // Update state
         self.cycles += 1;
+        self.devices.advance_to(self.cycles);
+        if self.injector.is_due(self.cycles) {
+            self.inject_random_fault();
+        }
+        if self.state == CpuState::Halted {
+            event!(debug, pc = pc.to_i32(), cycles = self.cycles, "halted");
+        }
+        if self.config.timing {
+            self.elapsed_us += timing::instruction_time_us(&instr);
+        }
+        if let Some(model) = &self.config.cost {
+            self.cost += model.instruction_cost(&instr);
+        }
         self.last_instr = Some(instr);
         
         Ok(instr)
@@ -95,6 +241,7 @@ impl Cpu {
     /// Run until halt or error.
     /// 
     /// Returns the number of instructions executed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(start = self.cycles)))]
     pub fn run(&mut self) -> Result<u64, CpuError> {
         let start_cycles = self.cycles;
         
@@ -106,6 +253,7 @@ impl Cpu {
     }
     
     /// Run for at most `max_cycles` instructions.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self), fields(start = self.cycles)))]
     pub fn run_limited(&mut self, max_cycles: u64) -> Result<u64, CpuError> {
         let start_cycles = self.cycles;
         let limit = self.cycles + max_cycles;
@@ -117,33 +265,108 @@ impl Cpu {
         Ok(self.cycles - start_cycles)
     }
     
+    /// Run until `pred` holds, the CPU halts, or an instruction fails.
+    ///
+    /// The predicate is checked before every instruction, including the
+    /// first, so a run whose condition already holds executes nothing.
+    /// There is no instruction limit; include one in the predicate (e.g.
+    /// `cpu.cycles >= limit`) if the program may not terminate.
+    pub fn run_until(&mut self, mut pred: impl FnMut(&Cpu) -> bool) -> Result<StopReason, CpuError> {
+        while self.state == CpuState::Running {
+            if pred(self) {
+                return Ok(StopReason::Condition);
+            }
+            self.step()?;
+        }
+        Ok(StopReason::Halted)
+    }
+    
+    /// Run until the next instruction to execute is at `addr`.
+    pub fn run_until_pc(&mut self, addr: i32) -> Result<StopReason, CpuError> {
+        self.run_until(|cpu| cpu.regs.c.to_i32() == addr)
+    }
+    
+    /// Run until the accumulator holds `value`.
+    pub fn run_until_s_equals(&mut self, value: i64) -> Result<StopReason, CpuError> {
+        self.run_until(|cpu| cpu.regs.s.to_i64() == value)
+    }
+    
+    /// Execute up to `n` instructions and summarize what happened.
+    ///
+    /// Unlike [`Cpu::run_limited`], errors are reported in the summary
+    /// rather than discarding the progress made before them.
+    pub fn step_many(&mut self, n: u64) -> StepSummary {
+        let before = self.mem.clone();
+        let start_cycles = self.cycles;
+        let mut stop_reason = StopReason::CycleLimit;
+        let mut error = None;
+        
+        while self.cycles - start_cycles < n {
+            if !self.is_running() {
+                stop_reason = StopReason::Halted;
+                break;
+            }
+            if let Err(e) = self.step() {
+                stop_reason = StopReason::Error;
+                error = Some(e.to_string());
+                break;
+            }
+        }
+        if stop_reason == StopReason::CycleLimit && self.is_halted() {
+            stop_reason = StopReason::Halted;
+        }
+        
+        let changed_memory = before.as_slice().iter().zip(self.mem.as_slice())
+            .enumerate()
+            .filter(|(_, (was, is))| was != is)
+            .map(|(i, _)| i)
+            .collect();
+        
+        StepSummary {
+            executed: self.cycles - start_cycles,
+            stop_reason,
+            error,
+            changed_memory,
+            regs: RegisterState::capture(&self.regs),
+        }
+    }
+    
     /// Execute a decoded instruction.
     fn execute(&mut self, instr: Instruction) -> Result<(), CpuError> {
         match instr {
             // ==================== Arithmetic ====================
             
             Instruction::Add { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                let eff_addr = self.operand_address(addr, mode)?;
                 let operand = self.load_word(eff_addr)?;
-                let (result, _carry) = arith::add(&self.regs.s, &operand);
+                let (result, carry) = arith::add(&self.regs.s, &operand);
+                self.check_overflow(carry)?;
                 self.regs.s = result;
                 let sign = self.regs.s.sign();
                 self.regs.set_omega(sign);
             }
             
             Instruction::Sub { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                let eff_addr = self.operand_address(addr, mode)?;
                 let operand = self.load_word(eff_addr)?;
-                let (result, _carry) = arith::subtract(&self.regs.s, &operand);
+                let (result, carry) = arith::subtract(&self.regs.s, &operand);
+                self.check_overflow(carry)?;
                 self.regs.s = result;
                 let sign = self.regs.s.sign();
                 self.regs.set_omega(sign);
             }
             
             Instruction::Mul { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                let eff_addr = self.operand_address(addr, mode)?;
                 let operand = self.load_word(eff_addr)?;
-                let (low, high) = arith::multiply(&self.regs.s, &operand);
+                // Schoolbook multiply does one pass per non-zero trit of its
+                // first word, so a sparse operand such as a small constant goes first
+                let nonzero = |w: &Word18| w.trits().iter().filter(|t| !t.is_zero()).count();
+                let (low, high) = if nonzero(&operand) < nonzero(&self.regs.s) {
+                    arith::multiply(&operand, &self.regs.s)
+                } else {
+                    arith::multiply(&self.regs.s, &operand)
+                };
                 self.regs.s = high; // High part in S
                 self.regs.r = low;  // Low part in R
                 let sign = self.regs.s.sign();
@@ -151,48 +374,51 @@ impl Cpu {
             }
             
             Instruction::Div { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                let eff_addr = self.operand_address(addr, mode)?;
                 let divisor = self.load_word(eff_addr)?;
                 
                 if divisor.is_zero() {
                     return Err(CpuError::DivisionByZero);
                 }
                 
-                // Simple integer division
-                let dividend = self.regs.s.to_i64();
-                let divisor_val = divisor.to_i64();
-                let quotient = dividend / divisor_val;
-                let remainder = dividend % divisor_val;
+                if self.config.is_historical() {
+                    return self.divide_historical(&divisor);
+                }
                 
-                self.regs.s = Word18::from_i64(quotient);
-                self.regs.r = Word18::from_i64(remainder);
+                // Truncating division; the quotient never outgrows S
+                let (quotient, remainder) = arith::divide_toward_zero(&self.regs.s, &divisor)
+                    .ok_or(CpuError::DivisionByZero)?;
+                self.regs.s = quotient;
+                self.regs.r = remainder;
                 let sign = self.regs.s.sign();
                 self.regs.set_omega(sign);
             }
             
             Instruction::AddAbs { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                let eff_addr = self.operand_address(addr, mode)?;
                 let operand = self.load_word(eff_addr)?;
                 let abs_operand = if operand.sign() == Trit::N {
                     operand.neg()
                 } else {
                     operand
                 };
-                let (result, _carry) = arith::add(&self.regs.s, &abs_operand);
+                let (result, carry) = arith::add(&self.regs.s, &abs_operand);
+                self.check_overflow(carry)?;
                 self.regs.s = result;
                 let sign = self.regs.s.sign();
                 self.regs.set_omega(sign);
             }
             
             Instruction::SubAbs { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                let eff_addr = self.operand_address(addr, mode)?;
                 let operand = self.load_word(eff_addr)?;
                 let abs_operand = if operand.sign() == Trit::N {
                     operand.neg()
                 } else {
                     operand
                 };
-                let (result, _carry) = arith::subtract(&self.regs.s, &abs_operand);
+                let (result, carry) = arith::subtract(&self.regs.s, &abs_operand);
+                self.check_overflow(carry)?;
                 self.regs.s = result;
                 let sign = self.regs.s.sign();
                 self.regs.set_omega(sign);
@@ -201,8 +427,8 @@ impl Cpu {
             // ==================== Data Transfer ====================
             
             Instruction::Lda { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
-                let value = self.mem.read_ternary(eff_addr)?;
+                let eff_addr = self.operand_address(addr, mode)?;
+                let value = self.read_operand(eff_addr)?;
                 // Zero-extend 9 trits to 18 trits (preserves value in balanced ternary)
                 self.regs.s = value.to_word18();
                 let s_sign = self.regs.s.sign();
@@ -210,8 +436,8 @@ impl Cpu {
             }
             
             Instruction::LdaUnsigned { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
-                let value = self.mem.read_ternary(eff_addr)?;
+                let eff_addr = self.operand_address(addr, mode)?;
+                let value = self.read_operand(eff_addr)?;
                 // Zero-extend (same as to_word18)
                 self.regs.s = value.to_word18();
                 let sign = self.regs.s.sign();
@@ -219,19 +445,19 @@ impl Cpu {
             }
             
             Instruction::Sta { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                let eff_addr = self.operand_address(addr, mode)?;
                 let value = self.regs.s.low();
-                self.mem.write_ternary(eff_addr, value)?;
+                self.write_operand(eff_addr, value)?;
             }
             
             Instruction::Ldf { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
-                let value = self.mem.read_ternary(eff_addr)?;
+                let eff_addr = self.operand_address(addr, mode)?;
+                let value = self.read_operand(eff_addr)?;
                 // Take low 5 trits
                 let trits = value.trits();
                 let f_trits = [trits[0], trits[1], trits[2], trits[3], trits[4]];
                 self.regs.f = Tryte5::from_i32(
-                    f_trits[0].to_i8() as i32 * 1 +
+                    f_trits[0].to_i8() as i32 +
                     f_trits[1].to_i8() as i32 * 3 +
                     f_trits[2].to_i8() as i32 * 9 +
                     f_trits[3].to_i8() as i32 * 27 +
@@ -240,29 +466,29 @@ impl Cpu {
             }
             
             Instruction::Stf { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                let eff_addr = self.operand_address(addr, mode)?;
                 let value = self.regs.f.to_tryte9();
-                self.mem.write_ternary(eff_addr, value)?;
+                self.write_operand(eff_addr, value)?;
             }
             
             Instruction::Ldr { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
-                let value = self.mem.read_ternary(eff_addr)?;
+                let eff_addr = self.operand_address(addr, mode)?;
+                let value = self.read_operand(eff_addr)?;
                 // Zero-extend like LDA
                 self.regs.r = value.to_word18();
             }
             
             Instruction::Str { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                let eff_addr = self.operand_address(addr, mode)?;
                 let value = self.regs.r.low();
-                self.mem.write_ternary(eff_addr, value)?;
+                self.write_operand(eff_addr, value)?;
             }
             
             Instruction::Xchg { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
-                let mem_value = self.mem.read_ternary(eff_addr)?;
+                let eff_addr = self.operand_address(addr, mode)?;
+                let mem_value = self.read_operand(eff_addr)?;
                 let s_low = self.regs.s.low();
-                self.mem.write_ternary(eff_addr, s_low)?;
+                self.write_operand(eff_addr, s_low)?;
                 self.regs.s = mem_value.to_word18();
                 let sign = self.regs.s.sign();
                 self.regs.set_omega(sign);
@@ -271,41 +497,41 @@ impl Cpu {
             // ==================== Control Flow ====================
             
             Instruction::Jmp { addr, mode } => {
-                let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                let eff_addr = self.operand_address(addr, mode)?;
                 self.regs.jump(eff_addr);
             }
             
             Instruction::Jz { addr, mode } => {
-                if self.regs.s.is_zero() {
-                    let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                if self.branch_sign() == Trit::O {
+                    let eff_addr = self.operand_address(addr, mode)?;
                     self.regs.jump(eff_addr);
                 }
             }
             
             Instruction::Jp { addr, mode } => {
-                if self.regs.s.sign() == Trit::P {
-                    let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                if self.branch_sign() == Trit::P {
+                    let eff_addr = self.operand_address(addr, mode)?;
                     self.regs.jump(eff_addr);
                 }
             }
             
             Instruction::Jn { addr, mode } => {
-                if self.regs.s.sign() == Trit::N {
-                    let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                if self.branch_sign() == Trit::N {
+                    let eff_addr = self.operand_address(addr, mode)?;
                     self.regs.jump(eff_addr);
                 }
             }
             
             Instruction::Jop { addr, mode } => {
                 if self.regs.omega == Trit::P {
-                    let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                    let eff_addr = self.operand_address(addr, mode)?;
                     self.regs.jump(eff_addr);
                 }
             }
             
             Instruction::Jon { addr, mode } => {
                 if self.regs.omega == Trit::N {
-                    let eff_addr = self.regs.effective_address(addr, mode.to_trit());
+                    let eff_addr = self.operand_address(addr, mode)?;
                     self.regs.jump(eff_addr);
                 }
             }
@@ -317,10 +543,7 @@ impl Cpu {
             // ==================== Shift Operations ====================
             
             Instruction::Shl { count } => {
-                let shifted = arith::shift_left(&self.regs.s, count as usize);
-                self.regs.s = shifted;
-                let sign = self.regs.s.sign();
-                self.regs.set_omega(sign);
+                self.shift_left(count as usize);
             }
             
             Instruction::Shr { count } => {
@@ -330,6 +553,18 @@ impl Cpu {
                 self.regs.set_omega(sign);
             }
             
+            Instruction::Shm { addr, mode } => {
+                let eff_addr = self.operand_address(addr, mode)?;
+                let count = self.read_operand(eff_addr)?.to_i32();
+                if count < 0 {
+                    self.regs.s = arith::shift_right(&self.regs.s, count.unsigned_abs() as usize);
+                    let sign = self.regs.s.sign();
+                    self.regs.set_omega(sign);
+                } else {
+                    self.shift_left(count as usize);
+                }
+            }
+            
             // ==================== Special ====================
             
             Instruction::Nop => {
@@ -340,18 +575,270 @@ impl Cpu {
                 let sign = self.regs.s.sign();
                 self.regs.set_omega(sign);
             }
+            
+            Instruction::Ext { opcode, addr, mode } => {
+                let execute = self.extensions.executor(opcode)
+                    .ok_or(CpuError::DecodeError(DecodeError::InvalidOpcode(opcode)))?;
+                execute(self, addr, mode)?;
+            }
         }
         
         Ok(())
     }
     
+    /// DIV on the historical machine: S := S / divisor rounded to nearest,
+    /// R := remainder. The divider develops one quotient trit per trit of
+    /// a memory word, nine in all, so although S could hold a wider result
+    /// the machine never forms it: a quotient beyond 9 trits (a divisor too
+    /// small for the dividend) overflows. Under [`OverflowPolicy::Wrap`] S
+    /// keeps those low 9 trits while ω takes the sign of the true quotient,
+    /// so ω disagreeing with S signals the overflow.
+    fn divide_historical(&mut self, divisor: &Word18) -> Result<(), CpuError> {
+        let (quotient, remainder) = arith::divide(&self.regs.s, divisor).ok_or(CpuError::DivisionByZero)?;
+        let overflow = quotient.to_i64().abs() > Tryte9::MAX as i64;
+        if overflow && self.config.overflow == OverflowPolicy::Trap {
+            return Err(CpuError::Overflow);
+        }
+        self.regs.s = if overflow { quotient.low().to_word18() } else { quotient };
+        self.regs.r = remainder;
+        self.regs.set_omega(quotient.sign());
+        Ok(())
+    }
+    
+    /// S := S × 3^count. ω takes the sign of S, or with
+    /// [`CpuConfig::strict_shifts`] the sign of the trits shifted out.
+    fn shift_left(&mut self, count: usize) {
+        let (shifted, lost) = arith::shift_left_checked(&self.regs.s, count);
+        self.regs.s = shifted;
+        let sign = if self.config.strict_shifts { lost.sign() } else { shifted.sign() };
+        self.regs.set_omega(sign);
+    }
+    
+    /// Apply the configured overflow policy to the carry out of an addition.
+    fn check_overflow(&self, carry: Trit) -> Result<(), CpuError> {
+        if !carry.is_zero() && self.config.overflow == OverflowPolicy::Trap {
+            return Err(CpuError::Overflow);
+        }
+        Ok(())
+    }
+    
     /// Load a memory word as an 18-trit value (zero-extended).
     /// In balanced ternary, zero-extension preserves the original value.
-    fn load_word(&self, addr: Tryte9) -> Result<Word18, CpuError> {
-        let value = self.mem.read_ternary(addr)?;
+    fn load_word(&mut self, addr: Tryte9) -> Result<Word18, CpuError> {
+        let value = self.read_operand(addr)?;
         Ok(value.to_word18())
     }
     
+    /// `error`, returned by the last [`step`](Self::step), as a
+    /// [`Diagnostic`] at the address of the instruction that raised it,
+    /// with a note on the setting that made the problem an error.
+    pub fn diagnostic(&self, error: &CpuError) -> Diagnostic {
+        // C has moved past the instruction unless the fetch itself failed
+        let pc = self.regs.c;
+        let fetched = !matches!(error, CpuError::NotRunning(_)) && self.mem.read_ternary(pc).is_ok();
+        let addr = if fetched { pc.to_i32() - 1 } else { pc.to_i32() };
+        let diagnostic = Diagnostic::error(error.code(), error.to_string()).at_addr(addr);
+        match error {
+            CpuError::Overflow => diagnostic
+                .with_note("the machine stops on overflow; `OverflowPolicy::Wrap` discards the carry instead"),
+            CpuError::AddressOutOfRange { .. } => diagnostic
+                .with_note("`AddressPolicy::Wrap` (run --wrap-addresses) wraps the address around memory instead"),
+            CpuError::StackOverflow { .. } | CpuError::StackUnderflow { .. } => match self.config.stack {
+                Some(stack) => diagnostic.with_note(format!("the stack occupies {}", stack)),
+                None => diagnostic,
+            },
+            _ => diagnostic,
+        }
+    }
+    
+    /// With a stack configured, F is its pointer and must stay inside it.
+    fn check_stack(&self) -> Result<(), CpuError> {
+        let Some(stack) = self.config.stack else {
+            return Ok(());
+        };
+        let sp = self.regs.f.to_i32();
+        if sp < stack.limit {
+            Err(CpuError::StackOverflow { sp, limit: stack.limit })
+        } else if sp > stack.base {
+            Err(CpuError::StackUnderflow { sp, base: stack.base })
+        } else {
+            Ok(())
+        }
+    }
+    
+    /// Read a data operand, dispatching I/O port and device addresses.
+    fn read_operand(&mut self, addr: Tryte9) -> Result<Tryte9, CpuError> {
+        if self.io.is_port(addr) {
+            return self.io.read(addr).ok_or(CpuError::InputExhausted);
+        }
+        if let Some(result) = self.devices.read(addr.to_i32()) {
+            event!(debug, addr = addr.to_i32(), ok = result.is_ok(), "device read");
+            return Ok(result?);
+        }
+        Ok(self.mem.read_ternary(addr)?)
+    }
+    
+    /// Write a data operand, dispatching I/O port and device addresses.
+    fn write_operand(&mut self, addr: Tryte9, value: Tryte9) -> Result<(), CpuError> {
+        if self.io.is_port(addr) {
+            event!(trace, addr = addr.to_i32(), value = value.to_i32(), "port write");
+            self.io.write(addr, value);
+            return Ok(());
+        }
+        if let Some(result) = self.devices.write(addr.to_i32(), value) {
+            event!(debug, addr = addr.to_i32(), value = value.to_i32(), ok = result.is_ok(), "device write");
+            return Ok(result?);
+        }
+        if let Some(region) = self.protection.region_at(addr.to_i32()) {
+            let policy = region.policy;
+            event!(debug, addr = addr.to_i32(), value = value.to_i32(), policy = %policy, "write to read-only memory");
+            self.protection.record(WriteFault {
+                cycle: self.cycles,
+                // C has already moved past the storing instruction
+                pc: self.regs.c.to_i32() - 1,
+                addr: addr.to_i32(),
+                value: value.to_i32(),
+                policy,
+            });
+            match policy {
+                WritePolicy::Trap => return Err(CpuError::WriteProtected { addr: addr.to_i32() }),
+                WritePolicy::Discard => return Ok(()),
+                WritePolicy::Log => {}
+            }
+        }
+        event!(trace, addr = addr.to_i32(), value = value.to_i32(), "memory write");
+        Ok(self.mem.write_ternary(addr, value)?)
+    }
+    
+    /// Change trit `trit` of `site` to a different value, chosen by the
+    /// [`injector`](Cpu::injector)'s generator, and log the fault.
+    ///
+    /// # Panics
+    /// Panics if `trit` is not below the site's width.
+    pub fn inject_fault(&mut self, site: FaultSite, trit: usize) -> Result<InjectedFault, CpuError> {
+        assert!(trit < site.width(), "{} has no trit {}", site, trit);
+        // Move the trit one or two steps around N -> O -> P -> N
+        let steps = 1 + self.injector.next_below(2) as i8;
+        let change = |t: Trit| Trit::from_i8((t.to_i8() + 1 + steps) % 3 - 1);
+
+        let (before, after) = match site {
+            FaultSite::Memory(addr) => {
+                if addr.unsigned_abs() > Tryte9::MAX as u32 {
+                    return Err(MemoryError::AddressOutOfRange(addr).into());
+                }
+                let index = self.mem.addr_to_index(Tryte9::from_i32(addr))?;
+                let mut word = self.mem.read(index);
+                let before = word.to_i32();
+                word.set(trit, change(word.get(trit)));
+                self.mem.write(index, word);
+                (before as i64, word.to_i32() as i64)
+            }
+            FaultSite::S | FaultSite::R => {
+                let reg = if site == FaultSite::S { &mut self.regs.s } else { &mut self.regs.r };
+                let before = reg.to_i64();
+                reg.set(trit, change(reg.get(trit)));
+                (before, reg.to_i64())
+            }
+            FaultSite::F => {
+                let mut wide = self.regs.f.to_tryte9();
+                wide.set(trit, change(wide.get(trit)));
+                let before = self.regs.f.to_i32();
+                self.regs.f = Tryte5::from_i32(wide.to_i32());
+                (before as i64, wide.to_i32() as i64)
+            }
+            FaultSite::C => {
+                let before = self.regs.c.to_i32();
+                self.regs.c.set(trit, change(self.regs.c.get(trit)));
+                (before as i64, self.regs.c.to_i32() as i64)
+            }
+        };
+        let fault = InjectedFault { cycle: self.cycles, site, trit, before, after };
+        event!(debug, "injected fault: {}", fault);
+        self.injector.record(fault);
+        Ok(fault)
+    }
+    
+    /// Inject a fault at a random trit of the injector's target, every
+    /// trit being equally likely.
+    pub fn inject_random_fault(&mut self) -> InjectedFault {
+        let registers: usize = FaultSite::REGISTERS.iter().map(|(_, width)| width).sum();
+        let memory = self.mem.size() * Tryte9::WIDTH;
+        let (memory, registers) = match self.injector.target() {
+            FaultTarget::Any => (memory, registers),
+            FaultTarget::Memory => (memory, 0),
+            FaultTarget::Registers => (0, registers),
+        };
+        let mut pick = self.injector.next_below((memory + registers) as u64) as usize;
+        let (site, trit) = if pick < memory {
+            let addr = self.mem.index_to_addr(pick / Tryte9::WIDTH).to_i32();
+            (FaultSite::Memory(addr), pick % Tryte9::WIDTH)
+        } else {
+            pick -= memory;
+            let mut chosen = (FaultSite::S, 0);
+            for (site, width) in FaultSite::REGISTERS {
+                if pick < width {
+                    chosen = (site, pick);
+                    break;
+                }
+                pick -= width;
+            }
+            chosen
+        };
+        self.inject_fault(site, trit).expect("sites are drawn from the machine itself")
+    }
+    
+    /// The sign JZ, JP and JN test: ω, as set by the last operation, on the
+    /// historical machine; the current sign of S on the simplified one.
+    pub fn branch_sign(&self) -> Trit {
+        if self.config.is_historical() {
+            self.regs.omega
+        } else {
+            self.regs.s.sign()
+        }
+    }
+    
+    /// The effective address of an operand: `addr` modified by F, or for
+    /// [`AddrMode::Indirect`] the address held in memory cell `addr`.
+    ///
+    /// An index-modified address that leaves memory is handled by the
+    /// configured [`AddressPolicy`], unless it names an I/O port or a
+    /// device.
+    pub fn operand_address(&self, addr: Tryte9, mode: AddrMode) -> Result<Tryte9, CpuError> {
+        match mode {
+            AddrMode::Indirect => Ok(self.mem.read_ternary(addr)?),
+            AddrMode::Direct => Ok(addr),
+            AddrMode::IndexAdd | AddrMode::IndexSub => {
+                let offset = mode.to_trit().to_i8() as i64 * self.regs.f.to_i32() as i64;
+                let effective = addr.to_i32() as i64 + offset;
+                if self.mem.contains(effective) {
+                    return Ok(Tryte9::from_i32(effective as i32));
+                }
+                if let Ok(word) = Tryte9::try_from_i64(effective) {
+                    if self.io.is_port(word) || self.devices.is_mapped(word.to_i32()) {
+                        return Ok(word);
+                    }
+                }
+                match self.config.addressing {
+                    AddressPolicy::Wrap => Ok(Tryte9::from_i32(self.mem.wrap_address(effective))),
+                    AddressPolicy::Trap => Err(CpuError::AddressOutOfRange { base: addr.to_i32(), addr: effective }),
+                }
+            }
+        }
+    }
+    
+    /// The value an operand read at `addr` would see, without side effects.
+    ///
+    /// Device addresses whose device cannot preview a read show zero.
+    pub fn peek_operand(&self, addr: Tryte9) -> Result<Tryte9, CpuError> {
+        if self.io.is_port(addr) {
+            return self.io.peek(addr).ok_or(CpuError::InputExhausted);
+        }
+        if self.devices.is_mapped(addr.to_i32()) {
+            return Ok(self.devices.peek(addr.to_i32()).unwrap_or_default());
+        }
+        Ok(self.mem.read_ternary(addr)?)
+    }
+    
     /// Get the last executed instruction.
     pub fn last_instruction(&self) -> Option<Instruction> {
         self.last_instr
@@ -401,15 +888,169 @@ pub enum CpuError {
     
     #[error("arithmetic overflow")]
     Overflow,
+    
+    #[error("input tape exhausted")]
+    InputExhausted,
+    
+    #[error("write to read-only address {addr}")]
+    WriteProtected { addr: i32 },
+    
+    #[error("device error: {0}")]
+    Device(#[from] DeviceError),
+    
+    #[error("value out of range: {0}")]
+    OutOfRange(#[from] ConvertError),
+    
+    #[error("indexed address {base}±F = {addr} is outside memory")]
+    AddressOutOfRange { base: i32, addr: i64 },
+    
+    #[error("stack overflow: F = {sp} is below the stack limit {limit}")]
+    StackOverflow { sp: i32, limit: i32 },
+    
+    #[error("stack underflow: F = {sp} is above the stack base {base}")]
+    StackUnderflow { sp: i32, base: i32 },
+    
+    #[error("host call failed: {0}")]
+    Semihost(String),
+}
+
+impl CpuError {
+    /// Stable name of the kind of error, the code of its [`Diagnostic`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            CpuError::NotRunning(_) => "not-running",
+            CpuError::MemoryError(_) => "memory",
+            CpuError::DecodeError(_) => "invalid-instruction",
+            CpuError::DivisionByZero => "division-by-zero",
+            CpuError::Overflow => "overflow",
+            CpuError::InputExhausted => "input-exhausted",
+            CpuError::WriteProtected { .. } => "write-protected",
+            CpuError::Device(_) => "device",
+            CpuError::OutOfRange(_) => "out-of-range",
+            CpuError::AddressOutOfRange { .. } => "address-out-of-range",
+            CpuError::StackOverflow { .. } => "stack-overflow",
+            CpuError::StackUnderflow { .. } => "stack-underflow",
+            CpuError::Semihost(_) => "semihost",
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::cpu::decode::encode;
+    use crate::cpu::decode::{encode, AddrMode};
+    use crate::cpu::io::{TAPE_READER_ADDR, TELETYPE_ADDR};
     
     fn make_program(instructions: &[Instruction]) -> Vec<Tryte9> {
-        instructions.iter().map(|i| encode(i)).collect()
+        instructions.iter().map(encode).collect()
+    }
+    
+    #[test]
+    fn test_address_overflow_is_an_error() {
+        // With the full 9-trit address space the PC can run off the end
+        let mut cpu = Cpu::with_config(CpuConfig { memory_size: crate::cpu::config::MAX_MEMORY_SIZE, ..CpuConfig::default() });
+        cpu.load_program_at(Tryte9::MAX, &make_program(&[Instruction::Nop])).unwrap();
+        cpu.set_entry(Tryte9::MAX).unwrap();
+        assert!(matches!(cpu.step(), Err(CpuError::OutOfRange(_))));
+
+        cpu.regs.f = Tryte5::from_i32(10);
+        let addr = Tryte9::from_i32(Tryte9::MAX);
+        assert!(matches!(cpu.operand_address(addr, AddrMode::IndexAdd), Err(CpuError::AddressOutOfRange { addr: 9851, .. })));
+    }
+    
+    #[test]
+    fn test_stack_push_pop_and_limits() {
+        use crate::asm::{assemble, assemble_detailed};
+        use crate::cpu::config::StackRegion;
+        
+        let source = "LDA A\nPUSH\nLDA B\nPUSH\nPOP\nSTA C\nPOP\nHLT\nA: DAT 7\nB: DAT -5\nC: DAT 0";
+        let assembly = assemble_detailed(source).unwrap();
+        let stack = StackRegion { base: -20, limit: -24 };
+        let mut cpu = Cpu::with_config(CpuConfig { stack: Some(stack), ..CpuConfig::default() });
+        assert_eq!(cpu.regs.f.to_i32(), -20);
+        cpu.load_program(&assembly.program).unwrap();
+        cpu.run().unwrap();
+        // Last in, first out, and F back at the base
+        let c = Tryte9::from_i32(assembly.symbols["C"]);
+        assert_eq!(cpu.mem.read_ternary(c).unwrap().to_i32(), -5);
+        assert_eq!(cpu.regs.s.to_i64(), 7);
+        assert_eq!(cpu.regs.f.to_i32(), -20);
+        // Each word takes two cells, low half first
+        assert_eq!(cpu.mem.read_ternary(Tryte9::from_i32(-22)).unwrap().to_i32(), 7);
+        assert_eq!(cpu.mem.read_ternary(Tryte9::from_i32(-21)).unwrap().to_i32(), 0);
+        
+        // A push/pop pair round-trips all 18 trits of S
+        cpu.reset();
+        cpu.load_program(&assemble("LDL A\nPUSH\nLDA 0\nPOP\nHLT\nA: DAT18 -100000000").unwrap()).unwrap();
+        cpu.run().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), -100_000_000);
+        assert_eq!(cpu.regs.omega, Trit::N);
+        let wide = Word18::from_i64(-100_000_000);
+        assert_eq!(cpu.mem.read_ternary(Tryte9::from_i32(-22)).unwrap(), wide.low());
+        assert_eq!(cpu.mem.read_ternary(Tryte9::from_i32(-21)).unwrap(), wide.high());
+        
+        // PUSH leaves S as it was
+        cpu.reset();
+        cpu.load_program(&assemble("LDL A\nPUSH\nHLT\nA: DAT18 20000").unwrap()).unwrap();
+        cpu.run().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), 20000);
+        assert_eq!(cpu.regs.omega, Trit::P);
+        
+        // A third push is one too many, and nothing is written below the limit
+        cpu.reset();
+        cpu.load_program(&assemble("PUSH\nPUSH\nPUSH\nHLT").unwrap()).unwrap();
+        assert!(matches!(cpu.run(), Err(CpuError::StackOverflow { sp: -26, limit: -24 })));
+        assert_eq!(cpu.mem.read_ternary(Tryte9::from_i32(-25)).unwrap().to_i32(), 0);
+        
+        cpu.reset();
+        cpu.load_program(&assemble("POP\nHLT").unwrap()).unwrap();
+        assert!(matches!(cpu.run(), Err(CpuError::StackUnderflow { sp: -18, base: -20 })));
+    }
+    
+    #[test]
+    fn test_indexed_addresses_leaving_memory() {
+        // Sum the five cells from 78 upwards: 78, 79, 80, then -81, -80
+        let source = "
+                LDF COUNT
+        loop:   LDA SUM
+                ADD 83,F-
+                STA SUM
+                LDA COUNT
+                SUB ONE
+                STA COUNT
+                LDF COUNT
+                JGT loop
+                LDA SUM
+                HLT
+        COUNT:  DAT 5
+        SUM:    DAT 0
+        ONE:    DAT 1
+        ";
+        let program = crate::asm::assemble(source).unwrap();
+        let run = |addressing| {
+            let mut cpu = Cpu::with_config(CpuConfig { addressing, ..CpuConfig::default() });
+            cpu.load_program(&program).unwrap();
+            for (addr, value) in [(78, 1), (79, 2), (80, 3), (-81, 4), (-80, 5)] {
+                cpu.mem.write_ternary(Tryte9::from_i32(addr), Tryte9::from_i32(value)).unwrap();
+            }
+            cpu.run().map(|_| cpu.regs.s.to_i64())
+        };
+        assert_eq!(run(AddressPolicy::Wrap).unwrap(), 15);
+        assert!(matches!(run(AddressPolicy::Trap), Err(CpuError::AddressOutOfRange { base: 83, addr: 81 })));
+
+        // Ports stay reachable through F whatever the policy
+        let mut cpu = Cpu::with_config(CpuConfig { addressing: AddressPolicy::Wrap, ..CpuConfig::default() });
+        cpu.io.enable();
+        cpu.regs.f = Tryte5::from_i32(100);
+        let port = cpu.operand_address(Tryte9::from_i32(20), AddrMode::IndexAdd).unwrap();
+        assert_eq!(port.to_i32(), TAPE_READER_ADDR);
+    }
+    
+    #[test]
+    fn test_cpu_is_send() {
+        // Frontends move the CPU into worker threads.
+        fn assert_send<T: Send + 'static>() {}
+        assert_send::<Cpu>();
     }
     
     #[test]
@@ -489,6 +1130,83 @@ mod tests {
         assert_eq!(cpu.regs.s.to_i64(), 15);
     }
     
+    #[test]
+    fn test_step_many_summary() {
+        let mut cpu = Cpu::new();
+        cpu.mem.write(91, Tryte9::from_i32(5));
+        
+        let program = make_program(&[
+            Instruction::Lda { addr: Tryte9::from_i32(10), mode: AddrMode::Direct },
+            Instruction::Sta { addr: Tryte9::from_i32(12), mode: AddrMode::Direct },
+            Instruction::Hlt,
+        ]);
+        cpu.load_program(&program).unwrap();
+        
+        let summary = cpu.step_many(2);
+        assert_eq!(summary.executed, 2);
+        assert_eq!(summary.stop_reason, StopReason::CycleLimit);
+        assert_eq!(summary.changed_memory, vec![93]);
+        assert_eq!(summary.regs.s, 5);
+        
+        let summary = cpu.step_many(10);
+        assert_eq!(summary.executed, 1);
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert!(summary.changed_memory.is_empty());
+    }
+    
+    #[test]
+    fn test_run_until() {
+        let program = crate::asm::assemble("LOOP: LDA C\nADD ONE\nSTA C\nJMP LOOP\nC: DAT 0\nONE: DAT 1").unwrap();
+        let mut cpu = Cpu::new();
+        cpu.load_program(&program).unwrap();
+        
+        assert_eq!(cpu.run_until_s_equals(3).unwrap(), StopReason::Condition);
+        assert_eq!((cpu.cycles, cpu.regs.c.to_i32()), (10, 2));
+        assert_eq!(cpu.run_until_pc(0).unwrap(), StopReason::Condition);
+        assert_eq!(cpu.cycles, 12);
+        // Already there: nothing executes
+        assert_eq!(cpu.run_until_pc(0).unwrap(), StopReason::Condition);
+        assert_eq!(cpu.cycles, 12);
+        assert_eq!(cpu.run_until(|cpu| cpu.cycles >= 100).unwrap(), StopReason::Condition);
+        assert_eq!(cpu.mem.read(85).to_i32(), 25);
+        
+        let mut cpu = Cpu::new();
+        cpu.load_program(&make_program(&[Instruction::Hlt])).unwrap();
+        assert_eq!(cpu.run_until_pc(50).unwrap(), StopReason::Halted);
+    }
+    
+    #[test]
+    fn test_overflow_policy() {
+        let program = make_program(&[
+            Instruction::Add { addr: Tryte9::from_i32(10), mode: AddrMode::Direct },
+            Instruction::Hlt,
+        ]);
+        
+        for (overflow, should_trap) in [(OverflowPolicy::Wrap, false), (OverflowPolicy::Trap, true)] {
+            let mut cpu = Cpu::with_config(CpuConfig { overflow, ..CpuConfig::default() });
+            cpu.load_program(&program).unwrap();
+            cpu.mem.write(91, Tryte9::from_i32(1));
+            cpu.regs.s = Word18::from_i64(Word18::MAX);
+            
+            assert_eq!(matches!(cpu.step(), Err(CpuError::Overflow)), should_trap);
+        }
+    }
+    
+    #[test]
+    fn test_timing_accumulates_when_enabled() {
+        let program = make_program(&[Instruction::Nop, Instruction::Hlt]);
+        
+        let mut cpu = Cpu::new();
+        cpu.load_program(&program).unwrap();
+        cpu.run().unwrap();
+        assert_eq!(cpu.elapsed_us, 0);
+        
+        let mut cpu = Cpu::with_config(CpuConfig { timing: true, ..CpuConfig::default() });
+        cpu.load_program(&program).unwrap();
+        cpu.run().unwrap();
+        assert_eq!(cpu.elapsed_us, 2 * timing::SHORT_OP_US);
+    }
+    
     #[test]
     fn test_cpu_conditional_jump() {
         let mut cpu = Cpu::new();
@@ -537,4 +1255,149 @@ mod tests {
         
         assert_eq!(cpu.regs.s.to_i64(), 9);
     }
+    
+    #[test]
+    fn test_strict_shifts_flag_lost_trits() {
+        // 5000 × 3^13 does not fit in 18 trits; 5 × 3^13 does
+        for (strict, value, omega) in [(false, 5000, Trit::N), (true, 5000, Trit::P), (true, 5, Trit::O)] {
+            let mut cpu = Cpu::with_config(CpuConfig { strict_shifts: strict, ..CpuConfig::default() });
+            cpu.load_program(&crate::asm::assemble(&format!("LDA 3\nSHL 13\nHLT\nDAT {}", value)).unwrap()).unwrap();
+            cpu.run().unwrap();
+            assert_eq!(cpu.regs.s, arith::shift_left(&Word18::from_i64(value), 13));
+            assert_eq!(cpu.regs.omega, omega, "strict {} value {}", strict, value);
+        }
+    }
+    
+    #[test]
+    fn test_load_at_and_entry() {
+        let mut cpu = Cpu::new();
+        let mut image = vec![Tryte9::from_i32(5), Tryte9::from_i32(9)];
+        image.extend(make_program(&[
+            Instruction::Lda { addr: Tryte9::from_i32(-10), mode: AddrMode::Direct },
+            Instruction::Hlt,
+        ]));
+        
+        cpu.load_program_at(-10, &image).unwrap();
+        cpu.set_entry(-8).unwrap();
+        cpu.run().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), 5);
+        
+        assert!(cpu.load_program_at(80, &image).is_err());
+        assert!(cpu.set_entry(200).is_err());
+        assert!(matches!(cpu.load_program_at(i32::MIN, &image), Err(MemoryError::AddressOutOfRange(i32::MIN))));
+        assert!(matches!(cpu.set_entry(i32::MIN), Err(MemoryError::AddressOutOfRange(i32::MIN))));
+    }
+    
+    #[test]
+    fn test_tape_and_teletype_ports() {
+        let tape = Tryte9::from_i32(TAPE_READER_ADDR);
+        let teletype = Tryte9::from_i32(TELETYPE_ADDR);
+        let mut cpu = Cpu::new();
+        let program = make_program(&[
+            Instruction::Lda { addr: tape, mode: AddrMode::Direct },
+            Instruction::Add { addr: tape, mode: AddrMode::Direct },
+            Instruction::Sta { addr: teletype, mode: AddrMode::Direct },
+            Instruction::Lda { addr: tape, mode: AddrMode::Direct },
+        ]);
+        cpu.load_program(&program).unwrap();
+        
+        // Unmapped ports are ordinary (here out-of-range) addresses
+        assert!(matches!(cpu.step(), Err(CpuError::MemoryError(_))));
+        
+        cpu.reset();
+        cpu.load_program(&program).unwrap();
+        cpu.io.enable();
+        cpu.io.feed([Tryte9::from_i32(20), Tryte9::from_i32(22)]);
+        
+        assert!(matches!(cpu.run(), Err(CpuError::InputExhausted)));
+        assert_eq!(cpu.io.take_output(), vec![Tryte9::from_i32(42)]);
+    }
+    
+    #[test]
+    fn test_indirect_addressing() {
+        let program = crate::asm::assemble(
+            "LDA (P)\nJMP (Q)\nHLT\nT: ADD V\nHLT\nP: DAT V\nQ: DAT T\nV: DAT 21",
+        ).unwrap();
+        let mut cpu = Cpu::with_config(CpuConfig { indirect: true, ..CpuConfig::default() });
+        cpu.load_program(&program).unwrap();
+        cpu.run().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), 42);
+        assert_eq!(cpu.regs.c.to_i32(), 5);
+        
+        // Without the extension the same words index by F instead
+        let mut cpu = Cpu::new();
+        cpu.load_program(&program).unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), 7);
+    }
+    
+    #[test]
+    fn test_historical_jumps_test_omega() {
+        let program = make_program(&[
+            Instruction::Jn { addr: Tryte9::from_i32(3), mode: AddrMode::Direct },
+            Instruction::Jz { addr: Tryte9::from_i32(4), mode: AddrMode::Direct },
+            Instruction::Hlt,
+            Instruction::Hlt,
+            Instruction::Hlt,
+        ]);
+        for (config, halted_after) in [(CpuConfig::historical(), 5), (CpuConfig::simplified(), 4)] {
+            let mut cpu = Cpu::with_config(config);
+            cpu.load_program(&program).unwrap();
+            // S changed behind ω's back, as a debugger edit would
+            cpu.regs.s = Word18::from_i64(-7);
+            cpu.run().unwrap();
+            assert_eq!(cpu.regs.c.to_i32(), halted_after);
+        }
+    }
+    
+    #[test]
+    fn test_memory_operand_shift() {
+        let program = crate::asm::assemble(
+            "LDA V\nSHM UP\nSHM DOWN\nHLT\nV: DAT -5\nUP: DAT 3\nDOWN: DAT -2",
+        ).unwrap();
+        let mut cpu = Cpu::with_config(CpuConfig::historical());
+        cpu.load_program(&program).unwrap();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), -135);
+        cpu.run().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), -15);
+        assert_eq!(cpu.regs.omega, Trit::N);
+        
+        // The simplified machine has no such instruction
+        let mut cpu = Cpu::new();
+        cpu.load_program(&program).unwrap();
+        assert!(matches!(cpu.run(), Err(CpuError::DecodeError(DecodeError::InvalidOpcode(-12)))));
+    }
+    
+    #[test]
+    fn test_historical_division() {
+        let program = crate::asm::assemble("LDA A\nDIV B\nHLT\nA: DAT 8\nB: DAT 3").unwrap();
+        for (config, quotient, remainder) in [(CpuConfig::historical(), 3, -1), (CpuConfig::simplified(), 2, 2)] {
+            let mut cpu = Cpu::with_config(config);
+            cpu.load_program(&program).unwrap();
+            cpu.run().unwrap();
+            assert_eq!((cpu.regs.s.to_i64(), cpu.regs.r.to_i64()), (quotient, remainder));
+        }
+        
+        // The widest 9-trit quotient still fits; one more trit overflows
+        let program = crate::asm::assemble("LDL A\nDIV B\nHLT\nA: DAT18 -19682\nB: DAT 2").unwrap();
+        let mut cpu = Cpu::with_config(CpuConfig::historical());
+        cpu.load_program(&program).unwrap();
+        cpu.run().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), -9841);
+        
+        // A quotient wider than a word overflows
+        let program = crate::asm::assemble("LDL A\nDIV B\nHLT\nA: DAT18 -30000\nB: DAT 2").unwrap();
+        let mut cpu = Cpu::with_config(CpuConfig::historical());
+        cpu.load_program(&program).unwrap();
+        assert!(matches!(cpu.run(), Err(CpuError::Overflow)));
+        
+        let mut cpu = Cpu::with_config(CpuConfig { overflow: OverflowPolicy::Wrap, ..CpuConfig::historical() });
+        cpu.load_program(&program).unwrap();
+        cpu.run().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), Word18::from_i64(-15000).low().to_i32() as i64);
+        assert_eq!(cpu.regs.omega, Trit::N);
+        assert_ne!(cpu.regs.s.sign(), Trit::N);
+    }
 }
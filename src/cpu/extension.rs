@@ -0,0 +1,289 @@
+//! Experimental instruction-set extensions.
+//!
+//! Of the 27 values the 3-trit opcode field can hold, the decoder leaves a
+//! few unassigned. An [`ExtensionRegistry`] lets those slots carry new
+//! instructions at run time, so a ternary instruction can be prototyped
+//! without touching [`decode`](crate::cpu::decode) or the executor. Each
+//! [`IsaExtension`] gives an opcode, an assembler mnemonic, a closure that
+//! executes it and, optionally, how the disassembler shows it.
+//!
+//! Registered opcodes decode as [`Instruction::Ext`] only on a machine with
+//! [`CpuConfig::experimental`] set; otherwise they stay invalid opcodes:
+//!
+//! ```
+//! use setun::asm::assembler::assemble_with_extensions;
+//! use setun::cpu::{Cpu, CpuConfig, IsaExtension};
+//! use setun::ternary::Word18;
+//!
+//! // NEG: S := -S
+//! let neg = IsaExtension::new(-6, "NEG", |cpu, _, _| {
+//!     cpu.regs.s = cpu.regs.s.neg();
+//!     Ok(())
+//! }).without_operand();
+//!
+//! let mut cpu = Cpu::with_config(CpuConfig { experimental: true, ..CpuConfig::default() });
+//! cpu.extensions.register(neg).unwrap();
+//! let program = assemble_with_extensions("LDA 10\nNEG\nHLT\nORG 10\nDAT 7", &cpu.extensions).unwrap();
+//! cpu.load_program(&program).unwrap();
+//! cpu.run().unwrap();
+//! assert_eq!(cpu.regs.s, Word18::from_i64(-7));
+//! ```
+//!
+//! Extensions are code, so they are not saved in snapshots; register them
+//! again after restoring a machine.
+
+use crate::cpu::config::CpuConfig;
+use crate::cpu::decode::{decode, decode_for, AddrMode, DecodeError, Instruction, OperandKind};
+use crate::cpu::execute::{Cpu, CpuError};
+use crate::ternary::{Trit, Tryte9};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Executes an extension instruction, given its address field and mode.
+pub type ExecuteFn = Arc<dyn Fn(&mut Cpu, Tryte9, AddrMode) -> Result<(), CpuError> + Send + Sync>;
+
+/// Formats an extension instruction for the disassembler.
+pub type FormatFn = Arc<dyn Fn(Tryte9, AddrMode) -> String + Send + Sync>;
+
+/// Largest magnitude of the 3-trit opcode field.
+const OPCODE_FIELD_MAX: i8 = 13;
+
+/// One custom instruction.
+#[derive(Clone)]
+pub struct IsaExtension {
+    /// Opcode value in trits 8-6; must be one of [`free_opcodes`].
+    pub opcode: i8,
+    /// Assembler mnemonic, in upper case.
+    pub mnemonic: String,
+    /// What the low six trits hold.
+    pub operand: OperandKind,
+    execute: ExecuteFn,
+    format: Option<FormatFn>,
+}
+
+impl IsaExtension {
+    /// An instruction taking an address operand, executed by `execute`.
+    pub fn new(
+        opcode: i8,
+        mnemonic: &str,
+        execute: impl Fn(&mut Cpu, Tryte9, AddrMode) -> Result<(), CpuError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            opcode,
+            mnemonic: mnemonic.to_ascii_uppercase(),
+            operand: OperandKind::Address,
+            execute: Arc::new(execute),
+            format: None,
+        }
+    }
+
+    /// The instruction takes no operand.
+    pub fn without_operand(mut self) -> Self {
+        self.operand = OperandKind::None;
+        self
+    }
+
+    /// Disassemble the instruction with `format` instead of as
+    /// `MNEMONIC operand`.
+    pub fn with_formatter(mut self, format: impl Fn(Tryte9, AddrMode) -> String + Send + Sync + 'static) -> Self {
+        self.format = Some(Arc::new(format));
+        self
+    }
+
+    /// Assembly text for the instruction with these operand fields.
+    pub fn format(&self, addr: Tryte9, mode: AddrMode) -> String {
+        match (&self.format, self.operand) {
+            (Some(format), _) => format(addr, mode),
+            (None, OperandKind::None) => self.mnemonic.clone(),
+            (None, _) => format!("{} {}", self.mnemonic, crate::asm::disasm::format_operand(&addr, &mode)),
+        }
+    }
+}
+
+impl fmt::Debug for IsaExtension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IsaExtension")
+            .field("opcode", &self.opcode)
+            .field("mnemonic", &self.mnemonic)
+            .field("operand", &self.operand)
+            .finish()
+    }
+}
+
+/// Opcode values the decoder leaves unassigned on every ISA variant.
+pub fn free_opcodes() -> Vec<i8> {
+    (-OPCODE_FIELD_MAX..=OPCODE_FIELD_MAX)
+        .filter(|&opcode| decode(opcode_word(opcode)).is_err())
+        .collect()
+}
+
+/// An instruction word with `opcode` and zero operand fields.
+fn opcode_word(opcode: i8) -> Tryte9 {
+    Tryte9::from_i32(opcode as i32 * 729)
+}
+
+/// Custom instructions by opcode.
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionRegistry {
+    entries: BTreeMap<i8, IsaExtension>,
+}
+
+impl ExtensionRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `extension`, checking that its opcode is free and its mnemonic
+    /// is new to the assembler.
+    pub fn register(&mut self, extension: IsaExtension) -> Result<(), ExtensionError> {
+        let opcode = extension.opcode;
+        if opcode.unsigned_abs() > OPCODE_FIELD_MAX as u8 {
+            return Err(ExtensionError::OpcodeOutOfRange(opcode));
+        }
+        if let Ok(builtin) = decode(opcode_word(opcode)) {
+            return Err(ExtensionError::OpcodeInUse { opcode, mnemonic: builtin.mnemonic().to_string() });
+        }
+        if let Some(existing) = self.entries.get(&opcode) {
+            return Err(ExtensionError::OpcodeInUse { opcode, mnemonic: existing.mnemonic.clone() });
+        }
+        if crate::asm::assembler::is_reserved_mnemonic(&extension.mnemonic) || self.by_mnemonic(&extension.mnemonic).is_some() {
+            return Err(ExtensionError::MnemonicInUse(extension.mnemonic));
+        }
+        self.entries.insert(opcode, extension);
+        Ok(())
+    }
+
+    /// Remove the extension with `opcode`, returning it.
+    pub fn unregister(&mut self, opcode: i8) -> Option<IsaExtension> {
+        self.entries.remove(&opcode)
+    }
+
+    /// The extension with `opcode`.
+    pub fn get(&self, opcode: i8) -> Option<&IsaExtension> {
+        self.entries.get(&opcode)
+    }
+
+    /// The extension with `mnemonic` (case-insensitive).
+    pub fn by_mnemonic(&self, mnemonic: &str) -> Option<&IsaExtension> {
+        self.entries.values().find(|e| e.mnemonic.eq_ignore_ascii_case(mnemonic))
+    }
+
+    /// Registered extensions in opcode order.
+    pub fn iter(&self) -> impl Iterator<Item = &IsaExtension> {
+        self.entries.values()
+    }
+
+    /// Number of registered extensions.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no extensions are registered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Decode like [`decode_for`], reading registered opcodes as
+    /// [`Instruction::Ext`] when [`CpuConfig::experimental`] is set.
+    pub fn decode(&self, nitrit: Tryte9, config: &CpuConfig) -> Result<Instruction, DecodeError> {
+        match decode_for(nitrit, config) {
+            Err(DecodeError::InvalidOpcode(opcode)) if config.experimental && self.entries.contains_key(&opcode) => {
+                let mut trits = *nitrit.trits();
+                let mode = match AddrMode::from_trit(trits[5]) {
+                    AddrMode::IndexSub if config.indirect => AddrMode::Indirect,
+                    mode => mode,
+                };
+                trits[5..].fill(Trit::O);
+                Ok(Instruction::Ext { opcode, addr: Tryte9::from_trits(trits), mode })
+            }
+            other => other,
+        }
+    }
+
+    /// Disassemble `nitrit` as a machine with `config` and these
+    /// extensions reads it.
+    pub fn disassemble(&self, nitrit: Tryte9, config: &CpuConfig) -> String {
+        match self.decode(nitrit, config) {
+            Ok(Instruction::Ext { opcode, addr, mode }) => self.entries[&opcode].format(addr, mode),
+            Ok(instr) => crate::asm::disasm::format_instruction(&instr),
+            Err(_) => format!("??? ; {}", nitrit),
+        }
+    }
+
+    /// The closure executing the extension with `opcode`.
+    pub(crate) fn executor(&self, opcode: i8) -> Option<ExecuteFn> {
+        self.entries.get(&opcode).map(|e| e.execute.clone())
+    }
+}
+
+/// Errors from [`ExtensionRegistry::register`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ExtensionError {
+    #[error("opcode {0} does not fit in the 3-trit opcode field (±13)")]
+    OpcodeOutOfRange(i8),
+
+    #[error("opcode {opcode} is already {mnemonic}")]
+    OpcodeInUse { opcode: i8, mnemonic: String },
+
+    #[error("mnemonic {0} is already taken")]
+    MnemonicInUse(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assembler::assemble_with_extensions;
+    use crate::ternary::Word18;
+
+    /// SWP addr: exchange S with the 18-trit value in addr, addr+1
+    fn swap() -> IsaExtension {
+        IsaExtension::new(-8, "swp", |cpu, addr, mode| {
+            let at = cpu.operand_address(addr, mode)?;
+            let low = cpu.mem.read_ternary(at)?;
+            let high = cpu.mem.read_ternary(Tryte9::from_i32(at.to_i32() + 1))?;
+            let old = cpu.regs.s;
+            cpu.regs.s = Word18::from_i64(high.to_i32() as i64 * 19683 + low.to_i32() as i64);
+            cpu.mem.write_ternary(at, old.low())?;
+            cpu.mem.write_ternary(Tryte9::from_i32(at.to_i32() + 1), old.high())?;
+            Ok(())
+        })
+        .with_formatter(|addr, _| format!("SWP <{}>", addr.to_i32()))
+    }
+
+    #[test]
+    fn test_register_and_run() {
+        assert_eq!(free_opcodes(), vec![-8, -6]);
+        let mut registry = ExtensionRegistry::new();
+        registry.register(swap()).unwrap();
+        assert_eq!(registry.register(IsaExtension::new(1, "X", |_, _, _| Ok(()))),
+                   Err(ExtensionError::OpcodeInUse { opcode: 1, mnemonic: "ADD".to_string() }));
+        assert_eq!(registry.register(IsaExtension::new(-6, "LDA", |_, _, _| Ok(()))),
+                   Err(ExtensionError::MnemonicInUse("LDA".to_string())));
+        assert_eq!(registry.register(IsaExtension::new(20, "X", |_, _, _| Ok(()))),
+                   Err(ExtensionError::OpcodeOutOfRange(20)));
+        assert_eq!(registry.register(IsaExtension::new(i8::MIN, "Y", |_, _, _| Ok(()))),
+                   Err(ExtensionError::OpcodeOutOfRange(i8::MIN)));
+
+        let source = "LDA 20\nSWP BIG\nHLT\nORG 20\nDAT 5\nBIG: DAT18 1000";
+        let program = assemble_with_extensions(source, &registry).unwrap();
+        assert!(crate::asm::assemble(source).is_err());
+
+        // Only an experimental machine decodes it
+        let mut cpu = Cpu::new();
+        cpu.extensions = registry.clone();
+        cpu.load_program(&program).unwrap();
+        assert!(matches!(cpu.run(), Err(CpuError::DecodeError(DecodeError::InvalidOpcode(-8)))));
+
+        let config = CpuConfig { experimental: true, ..CpuConfig::default() };
+        assert_eq!(registry.disassemble(program[1], &config), "SWP <21>");
+        let mut cpu = Cpu::with_config(config);
+        cpu.extensions = registry;
+        cpu.load_program(&program).unwrap();
+        cpu.run().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), 1000);
+        assert_eq!(cpu.mem.read_ternary(Tryte9::from_i32(21)).unwrap().to_i32(), 5);
+    }
+}
@@ -0,0 +1,242 @@
+//! Fault injection.
+//!
+//! A [`FaultInjector`] corrupts the machine on purpose, the way a failing
+//! ferrite core or a noisy transistor would: every N instructions, or on
+//! demand with [`Cpu::inject_fault`](crate::Cpu::inject_fault), one trit
+//! of a memory cell or register changes to a different value. Each change
+//! is logged as an [`InjectedFault`], so a run shows which corruption
+//! caused which misbehaviour, and why checksums and redundancy matter:
+//!
+//! ```
+//! use setun::{assemble, Cpu};
+//! use setun::cpu::FaultTarget;
+//!
+//! let program = assemble("LOOP: ADD 3\nJMP LOOP\nHLT\nDAT 1").unwrap();
+//! let mut cpu = Cpu::new();
+//! cpu.load_program(&program).unwrap();
+//! cpu.injector.every(50, FaultTarget::Memory);
+//! let _ = cpu.run_limited(200);
+//!
+//! assert_eq!(cpu.injector.log().len(), 4);
+//! assert_eq!(cpu.injector.log()[0].cycle, 50);
+//! ```
+//!
+//! Sites are chosen uniformly over trits, so memory, which holds most of
+//! the machine's trits, is hit far more often than the registers. The
+//! choices come from a seeded generator and repeat from run to run.
+
+use crate::cpu::random::Xorshift;
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A place a fault can be injected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultSite {
+    /// The memory cell at an address.
+    Memory(i32),
+    /// The accumulator.
+    S,
+    /// The multiplier register.
+    R,
+    /// The index register.
+    F,
+    /// The program counter.
+    C,
+}
+
+impl FaultSite {
+    /// The registers, with their widths in trits.
+    pub const REGISTERS: [(FaultSite, usize); 4] =
+        [(FaultSite::S, 18), (FaultSite::R, 18), (FaultSite::F, 5), (FaultSite::C, 9)];
+
+    /// Width of the site in trits.
+    pub fn width(&self) -> usize {
+        match self {
+            FaultSite::Memory(_) => 9,
+            FaultSite::S | FaultSite::R => 18,
+            FaultSite::F => 5,
+            FaultSite::C => 9,
+        }
+    }
+}
+
+impl fmt::Display for FaultSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultSite::Memory(addr) => write!(f, "[{}]", addr),
+            FaultSite::S => write!(f, "S"),
+            FaultSite::R => write!(f, "R"),
+            FaultSite::F => write!(f, "F"),
+            FaultSite::C => write!(f, "C"),
+        }
+    }
+}
+
+/// Where periodic faults land.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultTarget {
+    /// Any trit of memory or the registers.
+    #[default]
+    Any,
+    /// Memory cells only.
+    Memory,
+    /// Registers only.
+    Registers,
+}
+
+impl FromStr for FaultTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "any" => Ok(FaultTarget::Any),
+            "memory" | "mem" => Ok(FaultTarget::Memory),
+            "registers" | "regs" => Ok(FaultTarget::Registers),
+            _ => Err(format!("unknown fault target '{}' (expected any, memory or registers)", s)),
+        }
+    }
+}
+
+/// One injected fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InjectedFault {
+    /// Instructions completed when the fault was injected.
+    pub cycle: u64,
+    /// What was corrupted.
+    pub site: FaultSite,
+    /// Position of the changed trit (0 is the least significant).
+    pub trit: usize,
+    /// Value of the site before the fault.
+    pub before: i64,
+    /// Value of the site after the fault.
+    pub after: i64,
+}
+
+impl fmt::Display for InjectedFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cycle {}: {} trit {} changed, {} -> {}",
+            self.cycle, self.site, self.trit, self.before, self.after
+        )
+    }
+}
+
+/// The fault schedule of a CPU and the faults it injected.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaultInjector {
+    every: Option<u64>,
+    target: FaultTarget,
+    rng: Xorshift,
+    log: Vec<InjectedFault>,
+}
+
+impl FaultInjector {
+    /// Inject a fault into `target` after every `n` instructions.
+    ///
+    /// # Panics
+    /// Panics if `n` is zero.
+    pub fn every(&mut self, n: u64, target: FaultTarget) {
+        assert!(n > 0, "fault interval must be positive");
+        self.every = Some(n);
+        self.target = target;
+    }
+
+    /// Stop injecting periodic faults, keeping the log.
+    pub fn disable(&mut self) {
+        self.every = None;
+    }
+
+    /// The periodic schedule, if enabled: the interval and the target.
+    pub fn schedule(&self) -> Option<(u64, FaultTarget)> {
+        self.every.map(|n| (n, self.target))
+    }
+
+    /// Restart the generator choosing sites, trits and values.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Xorshift::new(seed);
+    }
+
+    /// Faults injected so far, oldest first.
+    pub fn log(&self) -> &[InjectedFault] {
+        &self.log
+    }
+
+    /// Take the log, leaving it empty.
+    pub fn take_log(&mut self) -> Vec<InjectedFault> {
+        std::mem::take(&mut self.log)
+    }
+
+    /// Whether a periodic fault is due after `cycle` instructions.
+    pub(crate) fn is_due(&self, cycle: u64) -> bool {
+        self.every.is_some_and(|n| cycle.is_multiple_of(n))
+    }
+
+    /// The target of periodic faults.
+    pub(crate) fn target(&self) -> FaultTarget {
+        self.target
+    }
+
+    /// A pseudo-random number below `bound`.
+    pub(crate) fn next_below(&mut self, bound: u64) -> u64 {
+        self.rng.below(bound)
+    }
+
+    /// Log a fault.
+    pub(crate) fn record(&mut self, fault: InjectedFault) {
+        self.log.push(fault);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::Cpu;
+
+    #[test]
+    fn test_inject_on_demand() {
+        let mut cpu = Cpu::new();
+        cpu.regs.s = crate::ternary::Word18::from_i64(4);
+        let fault = cpu.inject_fault(FaultSite::S, 0).unwrap();
+        assert_eq!((fault.before, fault.trit), (4, 0));
+        // Trit 0 of 4 is P; it becomes O or N
+        assert!(fault.after == 3 || fault.after == 2);
+        assert_eq!(cpu.regs.s.to_i64(), fault.after);
+
+        let fault = cpu.inject_fault(FaultSite::Memory(-5), 8).unwrap();
+        assert_eq!(fault.after.abs(), 6561);
+        assert_eq!(cpu.mem.read(76).to_i32() as i64, fault.after);
+        assert!(cpu.inject_fault(FaultSite::Memory(200), 0).is_err());
+        assert!(cpu.inject_fault(FaultSite::Memory(i32::MIN), 0).is_err());
+        assert_eq!(cpu.injector.log().len(), 2);
+    }
+
+    #[test]
+    fn test_periodic_faults_repeat_with_seed() {
+        let program = assemble("LOOP: ADD 3\nJMP LOOP\nHLT\nDAT 1").unwrap();
+        let run = |seed| {
+            let mut cpu = Cpu::new();
+            cpu.load_program(&program).unwrap();
+            cpu.injector.seed(seed);
+            cpu.injector.every(10, FaultTarget::Registers);
+            let _ = cpu.run_limited(100);
+            cpu.injector.take_log()
+        };
+        let log = run(7);
+        assert!(!log.is_empty());
+        assert!(log.iter().all(|f| !matches!(f.site, FaultSite::Memory(_))));
+        assert!(log.iter().all(|f| f.before != f.after));
+        assert_eq!(run(7), log);
+    }
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!("Memory".parse(), Ok(FaultTarget::Memory));
+        assert_eq!("regs".parse(), Ok(FaultTarget::Registers));
+        assert!("disk".parse::<FaultTarget>().is_err());
+    }
+}
@@ -0,0 +1,148 @@
+//! Paper tape input and teletype output.
+//!
+//! The Setun read programs and data from punched paper tape and printed
+//! results on a teletype. The emulator models both as ports at two
+//! addresses just past the end of the default memory, reachable by any
+//! instruction's address field:
+//!
+//! - reading [`TAPE_READER_ADDR`] consumes the next word of input tape
+//! - writing [`TELETYPE_ADDR`] appends a word to the output
+//!
+//! Ports are only active once enabled (see [`IoPorts::enable`]); until
+//! then those addresses behave like any other memory address. Reading
+//! the teletype returns zero and writes to the tape reader are ignored.
+//!
+//! A memory of 242 cells or more reaches address 120, so the ports then
+//! sit on top of real cells. While enabled they take precedence for every
+//! instruction; the cells underneath keep whatever a loader or debugger
+//! writes to them directly, but programs cannot see it.
+
+use crate::ternary::{parse_number, ConvertError, Tryte9};
+use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
+
+/// Address of the paper tape reader.
+pub const TAPE_READER_ADDR: i32 = 120;
+
+/// Address of the teletype.
+pub const TELETYPE_ADDR: i32 = 121;
+
+/// Host-side buffers behind the I/O ports.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IoPorts {
+    /// Whether the port addresses are mapped.
+    pub enabled: bool,
+    /// Words not yet read from the tape.
+    input: VecDeque<Tryte9>,
+    /// Words written to the teletype and not yet taken by the host.
+    output: Vec<Tryte9>,
+}
+
+impl IoPorts {
+    /// Map the port addresses, hiding the cells at those addresses from
+    /// instructions in a memory large enough to have them.
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    /// Append words to the input tape.
+    pub fn feed(&mut self, words: impl IntoIterator<Item = Tryte9>) {
+        self.input.extend(words);
+    }
+
+    /// Number of words left on the input tape.
+    pub fn pending_input(&self) -> usize {
+        self.input.len()
+    }
+
+    /// Words not yet read from the tape, next first.
+    pub fn input_tape(&self) -> impl Iterator<Item = Tryte9> + '_ {
+        self.input.iter().copied()
+    }
+
+    /// Remove and return everything written to the teletype so far.
+    pub fn take_output(&mut self) -> Vec<Tryte9> {
+        std::mem::take(&mut self.output)
+    }
+
+    /// The most recent word written to the teletype, if not yet taken.
+    pub fn last_output(&self) -> Option<Tryte9> {
+        self.output.last().copied()
+    }
+
+    /// Is `addr` a mapped port?
+    #[inline]
+    pub fn is_port(&self, addr: Tryte9) -> bool {
+        self.enabled && matches!(addr.to_i32(), TAPE_READER_ADDR | TELETYPE_ADDR)
+    }
+
+    /// Read from a port. Returns `None` when the input tape is exhausted.
+    pub fn read(&mut self, addr: Tryte9) -> Option<Tryte9> {
+        match addr.to_i32() {
+            TAPE_READER_ADDR => self.input.pop_front(),
+            _ => Some(Tryte9::zero()),
+        }
+    }
+
+    /// What [`read`](Self::read) would return, without consuming input.
+    pub fn peek(&self, addr: Tryte9) -> Option<Tryte9> {
+        match addr.to_i32() {
+            TAPE_READER_ADDR => self.input.front().copied(),
+            _ => Some(Tryte9::zero()),
+        }
+    }
+
+    /// Write to a port.
+    pub fn write(&mut self, addr: Tryte9, value: Tryte9) {
+        if addr.to_i32() == TELETYPE_ADDR {
+            self.output.push(value);
+        }
+    }
+}
+
+/// Parse tape contents: whitespace- or comma-separated numbers in any
+/// notation accepted by [`parse_number`]. Text after `#` on a line is ignored.
+pub fn parse_tape(text: &str) -> Result<Vec<Tryte9>, ConvertError> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+        .filter(|token| !token.is_empty())
+        .map(|token| Tryte9::try_from_i64(parse_number(token)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ports() {
+        let tape = Tryte9::from_i32(TAPE_READER_ADDR);
+        let teletype = Tryte9::from_i32(TELETYPE_ADDR);
+        let mut io = IoPorts::default();
+        assert!(!io.is_port(tape));
+
+        io.enable();
+        io.feed(parse_tape("5, -3 # comment\n0tPN").unwrap());
+        assert!(io.is_port(tape) && io.is_port(teletype));
+        assert_eq!(io.pending_input(), 3);
+        assert_eq!(io.peek(tape), Some(Tryte9::from_i32(5)));
+        assert_eq!(io.read(tape), Some(Tryte9::from_i32(5)));
+        assert_eq!(io.read(tape), Some(Tryte9::from_i32(-3)));
+        assert_eq!(io.read(tape), Some(Tryte9::from_i32(2)));
+        assert_eq!(io.read(tape), None);
+
+        io.write(teletype, Tryte9::from_i32(7));
+        io.write(tape, Tryte9::from_i32(8));
+        assert_eq!(io.take_output(), vec![Tryte9::from_i32(7)]);
+        assert!(io.take_output().is_empty());
+    }
+
+    #[test]
+    fn test_parse_tape_rejects_bad_values() {
+        assert!(parse_tape("1 x").is_err());
+        assert!(parse_tape("10000").is_err());
+        assert!(parse_tape("-9223372036854775808").is_err());
+        assert!(parse_tape("-9223372036854775807").is_err());
+    }
+}
@@ -0,0 +1,317 @@
+//! Machine-readable description of the instruction set.
+//!
+//! [`IsaDescription::new`] walks every opcode through the decoder and
+//! records what comes out: mnemonic, opcode, encoding, operand, a
+//! semantics summary and emulated timing. Documentation, web front ends
+//! and editor tooling can load the JSON or TOML form instead of keeping
+//! their own copy of the tables in [`decode`](crate::cpu::decode).
+//!
+//! ```
+//! use setun::cpu::{IsaDescription, IsaVariant};
+//!
+//! let isa = IsaDescription::new(IsaVariant::Simplified);
+//! let add = isa.instruction("ADD").unwrap();
+//! assert_eq!(add.opcode, 1);
+//! assert_eq!(add.encoding, "00+ m aaaaa");
+//! ```
+
+use crate::cpu::config::{CpuConfig, IsaVariant};
+use crate::cpu::decode::{decode_for, AddrMode, Instruction, OperandKind};
+use crate::cpu::timing::instruction_time_us;
+use crate::ternary::{Trit, Tryte9};
+use serde::{Serialize, Deserialize};
+
+/// Broad class of an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstructionGroup {
+    /// Arithmetic on S.
+    Arithmetic,
+    /// Moves between registers and memory.
+    Transfer,
+    /// Jumps and halting.
+    Control,
+    /// Shifts of S.
+    Shift,
+    /// Everything else.
+    Special,
+}
+
+/// How an instruction uses the memory cell at its effective address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryEffect {
+    /// The cell is not accessed.
+    None,
+    /// The cell is read.
+    Read,
+    /// The cell is written.
+    Write,
+    /// The cell is read and then written.
+    ReadWrite,
+}
+
+/// One instruction of the set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionSpec {
+    /// Assembler mnemonic.
+    pub mnemonic: String,
+    /// Opcode value held in trits 8-6 (-13..=13).
+    pub opcode: i8,
+    /// The opcode trits, most significant first.
+    pub opcode_trits: String,
+    /// Layout of the whole word, most significant trit first: opcode
+    /// trits, then `m` for the mode trit, `a` for address trits, `n` for
+    /// shift-count trits and `x` for ignored trits.
+    pub encoding: String,
+    /// Broad class.
+    pub group: InstructionGroup,
+    /// What the low trits hold.
+    pub operand: OperandKind,
+    /// Use of the operand cell.
+    pub memory: MemoryEffect,
+    /// Whether the instruction jumps only when a condition holds.
+    pub conditional: bool,
+    /// Whether ω is set from the result.
+    pub sets_omega: bool,
+    /// Semantics in one line.
+    pub summary: String,
+    /// Emulated duration, in microseconds.
+    pub time_us: u64,
+}
+
+/// One field of the instruction word.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldSpec {
+    /// Field name.
+    pub name: String,
+    /// Most significant trit of the field.
+    pub high: usize,
+    /// Least significant trit of the field.
+    pub low: usize,
+    /// What the field holds.
+    pub description: String,
+}
+
+/// One address-modification mode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddrModeSpec {
+    /// Name of the mode.
+    pub name: String,
+    /// Value of the mode trit.
+    pub trit: String,
+    /// How the effective address is formed.
+    pub effective_address: String,
+}
+
+/// One register.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisterSpec {
+    /// Register name.
+    pub name: String,
+    /// Width in trits.
+    pub trits: usize,
+    /// What the register is for.
+    pub description: String,
+}
+
+/// The complete instruction set of one [`IsaVariant`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IsaDescription {
+    /// The variant described.
+    pub variant: IsaVariant,
+    /// Width of an instruction word in trits.
+    pub word_trits: usize,
+    /// Fields of the instruction word.
+    pub fields: Vec<FieldSpec>,
+    /// Address-modification modes.
+    pub addressing_modes: Vec<AddrModeSpec>,
+    /// The register file.
+    pub registers: Vec<RegisterSpec>,
+    /// Every instruction, grouped by class and ordered by opcode.
+    pub instructions: Vec<InstructionSpec>,
+}
+
+impl IsaDescription {
+    /// Describe `variant` by decoding every possible opcode.
+    pub fn new(variant: IsaVariant) -> Self {
+        let config = CpuConfig { isa: variant, ..CpuConfig::default() };
+        let mut instructions: Vec<InstructionSpec> = (-13i8..=13)
+            .filter_map(|opcode| {
+                // Opcode in trits 8-6, everything else zero
+                let word = Tryte9::from_i32(opcode as i32 * 729);
+                decode_for(word, &config).ok().map(|instr| spec(variant, opcode, word, &instr))
+            })
+            .collect();
+        instructions.sort_by_key(|i| (i.group, i.opcode));
+
+        let field = |name: &str, high, low, description: &str| FieldSpec {
+            name: name.into(),
+            high,
+            low,
+            description: description.into(),
+        };
+        let mode = |mode: AddrMode, name: &str, effective_address: &str| AddrModeSpec {
+            name: name.into(),
+            trit: mode.to_trit().to_string(),
+            effective_address: effective_address.into(),
+        };
+        let register = |name: &str, trits, description: &str| RegisterSpec {
+            name: name.into(),
+            trits,
+            description: description.into(),
+        };
+
+        Self {
+            variant,
+            word_trits: Tryte9::WIDTH,
+            fields: vec![
+                field("opcode", 8, 6, "operation, -13..=13"),
+                field("mode", 5, 5, "address modification by F"),
+                field("address", 4, 0, "operand address or shift count, -121..=121"),
+            ],
+            addressing_modes: vec![
+                mode(AddrMode::Direct, "direct", "addr"),
+                mode(AddrMode::IndexAdd, "index_add", "addr + F"),
+                mode(AddrMode::IndexSub, "index_sub", "addr - F"),
+            ],
+            registers: vec![
+                register("S", 18, "accumulator"),
+                register("R", 18, "multiplier/remainder register, low half of products"),
+                register("F", 5, "index register"),
+                register("C", 9, "program counter"),
+                register("omega", 1, "sign of the last result"),
+            ],
+            instructions,
+        }
+    }
+
+    /// Look up an instruction by mnemonic (case-insensitive).
+    pub fn instruction(&self, mnemonic: &str) -> Option<&InstructionSpec> {
+        self.instructions.iter().find(|i| i.mnemonic.eq_ignore_ascii_case(mnemonic))
+    }
+
+    /// Pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("ISA description serialization cannot fail")
+    }
+
+    /// TOML, with one `[[instructions]]` table per instruction.
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("ISA description serialization cannot fail")
+    }
+}
+
+impl Default for IsaDescription {
+    fn default() -> Self {
+        Self::new(IsaVariant::default())
+    }
+}
+
+/// Describe one decoded opcode.
+fn spec(variant: IsaVariant, opcode: i8, word: Tryte9, instr: &Instruction) -> InstructionSpec {
+    let trits = word.trits();
+    let opcode_trits: String = trits[6..].iter().rev().map(Trit::to_string).collect();
+    let operand = instr.operand();
+    let low = match operand {
+        OperandKind::None => "x xxxxx",
+        OperandKind::Address => "m aaaaa",
+        OperandKind::ShiftCount => "x nnnnn",
+    };
+
+    let (group, memory) = match instr {
+        Instruction::Add { .. } | Instruction::Sub { .. } | Instruction::Mul { .. }
+        | Instruction::Div { .. } | Instruction::AddAbs { .. } | Instruction::SubAbs { .. } => {
+            (InstructionGroup::Arithmetic, MemoryEffect::Read)
+        }
+        Instruction::Lda { .. } | Instruction::LdaUnsigned { .. } | Instruction::Ldf { .. }
+        | Instruction::Ldr { .. } => (InstructionGroup::Transfer, MemoryEffect::Read),
+        Instruction::Sta { .. } | Instruction::Stf { .. } | Instruction::Str { .. } => {
+            (InstructionGroup::Transfer, MemoryEffect::Write)
+        }
+        Instruction::Xchg { .. } => (InstructionGroup::Transfer, MemoryEffect::ReadWrite),
+        Instruction::Jmp { .. } | Instruction::Jz { .. } | Instruction::Jp { .. } | Instruction::Jn { .. }
+        | Instruction::Jop { .. } | Instruction::Jon { .. } | Instruction::Hlt => {
+            (InstructionGroup::Control, MemoryEffect::None)
+        }
+        Instruction::Shl { .. } | Instruction::Shr { .. } => (InstructionGroup::Shift, MemoryEffect::None),
+        Instruction::Shm { .. } => (InstructionGroup::Shift, MemoryEffect::Read),
+        Instruction::Nop | Instruction::Tst | Instruction::Ext { .. } => (InstructionGroup::Special, MemoryEffect::None),
+    };
+    let sets_omega = matches!(
+        instr,
+        Instruction::Add { .. } | Instruction::Sub { .. } | Instruction::Mul { .. } | Instruction::Div { .. }
+            | Instruction::AddAbs { .. } | Instruction::SubAbs { .. } | Instruction::Lda { .. }
+            | Instruction::LdaUnsigned { .. } | Instruction::Xchg { .. } | Instruction::Shl { .. }
+            | Instruction::Shr { .. } | Instruction::Shm { .. } | Instruction::Tst
+    );
+
+    // The historical machine branches on ω rather than on S itself, and
+    // rounds quotients
+    let summary = match (variant, instr) {
+        (IsaVariant::Historical, Instruction::Jz { .. }) => "if ω = 0 then C := addr",
+        (IsaVariant::Historical, Instruction::Jp { .. }) => "if ω = + then C := addr",
+        (IsaVariant::Historical, Instruction::Jn { .. }) => "if ω = - then C := addr",
+        (IsaVariant::Historical, Instruction::Div { .. }) => {
+            "S := S / [addr] rounded to nearest, R := remainder; ω := sign of the quotient"
+        }
+        _ => instr.summary(),
+    };
+
+    InstructionSpec {
+        mnemonic: instr.mnemonic().into(),
+        opcode,
+        encoding: format!("{} {}", opcode_trits, low),
+        opcode_trits,
+        group,
+        operand,
+        memory,
+        conditional: instr.is_conditional_branch(),
+        sets_omega,
+        summary: summary.into(),
+        time_us: instruction_time_us(instr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::decode::decode;
+
+    #[test]
+    fn test_covers_every_opcode() {
+        let isa = IsaDescription::default();
+        // TST's opcode (14) does not fit in three trits, so no word decodes to it
+        assert_eq!(isa.instructions.len(), 24);
+        assert!(isa.instruction("TST").is_none());
+        for spec in &isa.instructions {
+            // The recorded encoding decodes back to the same mnemonic
+            let word = Tryte9::from_i32(spec.opcode as i32 * 729);
+            assert_eq!(decode(word).unwrap().mnemonic(), spec.mnemonic);
+            assert_eq!(spec.encoding.len(), 11);
+
+        }
+
+        let mul = isa.instruction("mul").unwrap();
+        assert_eq!((mul.group, mul.memory, mul.time_us), (InstructionGroup::Arithmetic, MemoryEffect::Read, 335));
+        assert!(isa.instruction("JZ").unwrap().conditional);
+        assert_eq!(isa.instruction("SHL").unwrap().operand, OperandKind::ShiftCount);
+        assert_eq!(isa.instruction("XCHG").unwrap().memory, MemoryEffect::ReadWrite);
+    }
+
+    #[test]
+    fn test_json_and_toml_round_trip() {
+        let isa = IsaDescription::new(IsaVariant::Historical);
+        let from_json: IsaDescription = serde_json::from_str(&isa.to_json()).unwrap();
+        assert_eq!(from_json, isa);
+
+        let text = isa.to_toml();
+        assert!(text.contains("variant = \"historical\""));
+        assert!(text.contains("[[instructions]]\nmnemonic = \"ADD\""));
+        let from_toml: IsaDescription = toml::from_str(&text).unwrap();
+        assert_eq!(from_toml, isa);
+        assert_eq!(isa.instruction("JN").unwrap().summary, "if ω = - then C := addr");
+        assert_eq!(isa.instruction("SHM").unwrap().memory, MemoryEffect::Read);
+        assert!(IsaDescription::default().instruction("SHM").is_none());
+    }
+}
@@ -0,0 +1,261 @@
+//! Memory initialization files.
+//!
+//! A memory initialization file presets memory cells before a program
+//! runs, so input data does not have to be embedded as `DAT` lines:
+//!
+//! ```text
+//! ; comments start with ';' or '#'
+//! 10 = 42          ; single cell
+//! 11 = 0tPNO
+//! @20              ; following bare words go to 20, 21, ...
+//! -5
+//! PNOOOOOOP        ; TROM-style trit pattern
+//! ```
+//!
+//! Bare words before any `@` line start at address 0, so a plain TROM
+//! file can be used directly as a data segment. [`MemInit::from_memory`]
+//! and the `Display` impl go the other way, writing a memory image back
+//! out in the same format.
+//!
+//! [`Memory::dump_text`] writes an annotated listing in this format, with
+//! each cell's decimal value and optionally its disassembly as comments,
+//! and [`Memory::load_text`] restores memory from one after editing:
+//!
+//! ```
+//! use setun::{Memory, Tryte9};
+//!
+//! let mut mem = Memory::new();
+//! mem.write_ternary(Tryte9::from_i32(10), Tryte9::from_i32(42)).unwrap();
+//! let listing = mem.dump_text(false);
+//! assert!(listing.contains("10 = 0tOOOOPNNNO  ;     42"));
+//!
+//! let mut restored = Memory::new();
+//! restored.load_text(&listing.replace("0tOOOOPNNNO", "43")).unwrap();
+//! assert_eq!(restored.read_ternary(Tryte9::from_i32(10)).unwrap().to_i32(), 43);
+//! ```
+
+use crate::ternary::{parse_number, Tryte9};
+use crate::cpu::{Cpu, Memory};
+use crate::cpu::memory::MemoryError;
+use crate::asm::disasm::disassemble_instruction;
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::path::Path;
+use thiserror::Error;
+
+/// Cell values to preset, in file order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemInit {
+    /// `(address, value)` pairs; later entries overwrite earlier ones.
+    pub entries: Vec<(i32, Tryte9)>,
+}
+
+impl MemInit {
+    /// Parse the text of a memory initialization file.
+    pub fn parse(text: &str) -> Result<Self, MemInitError> {
+        let mut init = MemInit::default();
+        let mut next_addr = 0;
+
+        for (index, raw) in text.lines().enumerate() {
+            let line = raw.split([';', '#']).next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |message: String| MemInitError::Parse { line: index + 1, message };
+
+            if let Some(base) = line.strip_prefix('@') {
+                next_addr = parse_address(base).map_err(invalid)?;
+            } else if let Some((addr, value)) = line.split_once('=') {
+                let addr = parse_address(addr).map_err(invalid)?;
+                init.entries.push((addr, parse_word(value).map_err(invalid)?));
+            } else {
+                init.entries.push((next_addr, parse_word(line).map_err(invalid)?));
+                next_addr += 1;
+            }
+        }
+
+        Ok(init)
+    }
+
+    /// Read and parse a memory initialization file.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display())))]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, MemInitError> {
+        let text = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| MemInitError::Io(e.to_string()))?;
+        Self::parse(&text)
+    }
+
+    /// Capture every non-zero cell of `mem`, in address order.
+    pub fn from_memory(mem: &Memory) -> Self {
+        let entries = mem.iter().filter(|(_, cell)| !cell.is_zero()).collect();
+        MemInit { entries }
+    }
+}
+
+impl fmt::Display for MemInit {
+    /// One `addr = value` line per entry, with the trit pattern as a comment.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (addr, value) in &self.entries {
+            writeln!(f, "{} = {}  ; {}", addr, value.to_i32(), value)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_address(s: &str) -> Result<i32, String> {
+    let value = parse_number(s.trim()).map_err(|e| e.to_string())?;
+    Tryte9::try_from_i64(value)
+        .map(|addr| addr.to_i32())
+        .map_err(|_| format!("address {} out of range", value))
+}
+
+/// A word is a number in any notation, or a 9-trit NOP pattern as in TROM files.
+fn parse_word(s: &str) -> Result<Tryte9, String> {
+    let s = s.trim();
+    if s.len() == Tryte9::WIDTH && s.chars().all(|c| matches!(c, 'N' | 'O' | 'P' | 'n' | 'o' | 'p')) {
+        return Tryte9::parse(s).map_err(|e| e.to_string());
+    }
+    let value = parse_number(s).map_err(|e| e.to_string())?;
+    Tryte9::try_from_i64(value).map_err(|_| format!("value {} does not fit in 9 trits", value))
+}
+
+impl Memory {
+    /// An editable listing of every non-zero cell: address, trit pattern,
+    /// and as a comment the decimal value and, with `disasm`, the cell
+    /// read as an instruction. [`Memory::load_text`] reads it back.
+    pub fn dump_text(&self, disasm: bool) -> String {
+        let mut text = format!(
+            "; Setun memory, {} cells ({}..{}); cells not listed are zero\n",
+            self.size(), self.min_addr(), self.max_addr(),
+        );
+        for (addr, value) in MemInit::from_memory(self).entries {
+            let line = format!("{:>6} = {}  ; {:>6}", addr, value, value.to_i32());
+            if disasm {
+                text.push_str(&format!("{}  {}\n", line, disassemble_instruction(value)));
+            } else {
+                text.push_str(&line);
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    /// Replace the contents of memory with a listing from
+    /// [`Memory::dump_text`] (or any memory initialization file), zeroing
+    /// the cells it does not mention. Returns the number of cells listed.
+    ///
+    /// Memory is unchanged if the text does not parse or names an address
+    /// outside memory.
+    pub fn load_text(&mut self, text: &str) -> Result<usize, MemInitError> {
+        let init = MemInit::parse(text)?;
+        for &(addr, _) in &init.entries {
+            if !self.contains(addr as i64) {
+                return Err(MemInitError::OutOfRange(addr));
+            }
+        }
+        self.clear();
+        for &(addr, value) in &init.entries {
+            self.write_ternary(Tryte9::from_i32(addr), value).expect("address checked");
+        }
+        Ok(init.entries.len())
+    }
+}
+
+impl Cpu {
+    /// Write every entry of `init` to memory.
+    ///
+    /// Nothing is written if any address is outside memory.
+    pub fn apply_mem_init(&mut self, init: &MemInit) -> Result<(), MemoryError> {
+        for &(addr, _) in &init.entries {
+            self.mem.addr_to_index(Tryte9::from_i32(addr))?;
+        }
+        for &(addr, value) in &init.entries {
+            self.mem.write_ternary(Tryte9::from_i32(addr), value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors reading a memory initialization file.
+#[derive(Debug, Clone, Error)]
+pub enum MemInitError {
+    #[error("line {line}: {message}")]
+    Parse { line: usize, message: String },
+
+    #[error("I/O error: {0}")]
+    Io(String),
+
+    #[error("address {0} is outside memory")]
+    OutOfRange(i32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_apply() {
+        let init = MemInit::parse("
+            ; preset data
+            10 = 42
+            -3 = 0tPNO   # trailing comment
+            @20
+            -5
+            PNOOOOOOP
+        ").unwrap();
+        assert_eq!(init.entries.len(), 4);
+        assert_eq!(init.entries[1], (-3, Tryte9::from_i32(6)));
+        assert_eq!(init.entries[3].0, 21);
+
+        let mut cpu = Cpu::new();
+        cpu.apply_mem_init(&init).unwrap();
+        let read = |addr| cpu.mem.read_ternary(Tryte9::from_i32(addr)).unwrap().to_i32();
+        assert_eq!(read(10), 42);
+        assert_eq!(read(20), -5);
+        assert_eq!(read(21), Tryte9::parse("PNOOOOOOP").unwrap().to_i32());
+    }
+
+    #[test]
+    fn test_memory_roundtrip() {
+        let mut cpu = Cpu::new();
+        cpu.mem.write_ternary(Tryte9::from_i32(-7), Tryte9::from_i32(-300)).unwrap();
+        cpu.mem.write_ternary(Tryte9::from_i32(12), Tryte9::from_i32(5)).unwrap();
+
+        let image = MemInit::from_memory(&cpu.mem);
+        assert_eq!(image.entries.len(), 2);
+        assert_eq!(image.entries[0], (-7, Tryte9::from_i32(-300)));
+        assert_eq!(MemInit::parse(&image.to_string()).unwrap(), image);
+    }
+
+    #[test]
+    fn test_text_listing_roundtrip() {
+        let mut cpu = Cpu::new();
+        cpu.load_program(&crate::asm::assemble("LDA 20\nHLT").unwrap()).unwrap();
+        cpu.mem.write_ternary(Tryte9::from_i32(-81), Tryte9::from_i32(-9841)).unwrap();
+        let listing = cpu.mem.dump_text(true);
+        assert!(listing.lines().any(|line| line.ends_with("LDA 20")));
+
+        let mut mem = Memory::new();
+        mem.write_ternary(Tryte9::from_i32(5), Tryte9::from_i32(1)).unwrap();
+        assert_eq!(mem.load_text(&listing).unwrap(), 2);
+        assert_eq!(MemInit::from_memory(&mem), MemInit::from_memory(&cpu.mem));
+
+        // A bad line or address leaves memory alone
+        assert!(matches!(mem.load_text("0 = 1\n90 = 1"), Err(MemInitError::OutOfRange(90))));
+        assert!(mem.load_text("0 = x").is_err());
+        assert_eq!(MemInit::from_memory(&mem), MemInit::from_memory(&cpu.mem));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(MemInit::parse("1 = 2\n3 = x"), Err(MemInitError::Parse { line: 2, .. })));
+        assert!(MemInit::parse("5 = 10000").is_err());
+        assert!(MemInit::parse("0 = -9223372036854775808").is_err());
+        assert!(MemInit::parse("-9223372036854775807 = 1").is_err());
+
+        let mut cpu = Cpu::new();
+        let init = MemInit::parse("0 = 7\n100 = 1").unwrap();
+        assert!(cpu.apply_mem_init(&init).is_err());
+        assert_eq!(cpu.mem.read_ternary(Tryte9::zero()).unwrap().to_i32(), 0);
+    }
+}
@@ -1,50 +1,158 @@
 //! Setun memory subsystem.
 //!
 //! The original Setun had 162 nine-trit memory cells organized as
-//! 3 pages of 54 cells each, with magnetic drum backup.
+//! 3 pages of 54 cells each, with magnetic drum backup. Larger memories
+//! can be configured; addresses are always centred on zero.
 
 use crate::ternary::Tryte9;
 use serde::{Serialize, Deserialize};
+use std::ops::Range;
 
 /// The number of memory cells in the Setun.
 pub const MEMORY_SIZE: usize = 162;
 
-/// Setun memory: 162 nine-trit cells.
+/// Setun memory: 162 nine-trit cells by default.
+///
+/// A deserialized memory counts every cell as written, so delta queries
+/// after restoring a snapshot see the whole restored image.
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "StoredMemory")]
 pub struct Memory {
     cells: Vec<Tryte9>,
+    /// Per-cell "written since last `take_changes`" flags.
+    #[serde(skip)]
+    dirty: Vec<bool>,
+    /// Written indices in first-write order.
+    #[serde(skip)]
+    changes: Vec<usize>,
+}
+
+/// The serialized form of [`Memory`]: just the cells.
+#[derive(Deserialize)]
+struct StoredMemory {
+    cells: Vec<Tryte9>,
+}
+
+impl From<StoredMemory> for Memory {
+    fn from(stored: StoredMemory) -> Self {
+        let mut mem = Memory { cells: stored.cells, dirty: Vec::new(), changes: Vec::new() };
+        mem.mark_all_written();
+        mem
+    }
 }
 
 impl Memory {
     /// Create a new memory with all cells zeroed.
     pub fn new() -> Self {
+        Self::with_size(MEMORY_SIZE)
+    }
+    
+    /// Create a zeroed memory with `size` cells.
+    pub fn with_size(size: usize) -> Self {
         Self {
-            cells: vec![Tryte9::zero(); MEMORY_SIZE],
+            cells: vec![Tryte9::zero(); size],
+            dirty: Vec::new(),
+            changes: Vec::new(),
         }
     }
     
-    /// Read a cell by address (0-161).
+    /// Number of cells.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.cells.len()
+    }
+    
+    /// Index of ternary address 0 (81 for the standard 162-cell memory).
+    #[inline]
+    pub fn origin(&self) -> usize {
+        self.cells.len() / 2
+    }
+    
+    /// Lowest valid ternary address.
+    pub fn min_addr(&self) -> i32 {
+        -(self.origin() as i32)
+    }
+    
+    /// Highest valid ternary address.
+    pub fn max_addr(&self) -> i32 {
+        (self.cells.len() - self.origin()) as i32 - 1
+    }
+    
+    /// Read a cell by index (0-161).
     /// 
     /// # Panics
     /// Panics if address is out of range.
     #[inline]
     pub fn read(&self, addr: usize) -> Tryte9 {
-        assert!(addr < MEMORY_SIZE, "Memory address {} out of range (0-{})", addr, MEMORY_SIZE - 1);
+        assert!(addr < self.cells.len(), "Memory address {} out of range (0-{})", addr, self.cells.len() - 1);
         self.cells[addr]
     }
     
-    /// Write a cell by address (0-161).
+    /// Write a cell by index (0-161).
     ///
     /// # Panics
     /// Panics if address is out of range.
     #[inline]
     pub fn write(&mut self, addr: usize, value: Tryte9) {
-        assert!(addr < MEMORY_SIZE, "Memory address {} out of range (0-{})", addr, MEMORY_SIZE - 1);
+        assert!(addr < self.cells.len(), "Memory address {} out of range (0-{})", addr, self.cells.len() - 1);
         self.cells[addr] = value;
+        self.mark_written(addr);
+    }
+    
+    /// Cells with their ternary addresses, lowest address first.
+    pub fn iter(&self) -> impl Iterator<Item = (i32, Tryte9)> + '_ {
+        let min = self.min_addr();
+        self.cells.iter().enumerate().map(move |(index, &cell)| (min + index as i32, cell))
+    }
+    
+    /// All cells by index.
+    #[inline]
+    pub fn as_slice(&self) -> &[Tryte9] {
+        &self.cells
+    }
+    
+    /// All cells by index, for bulk changes.
+    ///
+    /// Every cell counts as written for [`Memory::take_changes`], since the
+    /// caller may change any of them.
+    pub fn as_mut_slice(&mut self) -> &mut [Tryte9] {
+        self.mark_all_written();
+        &mut self.cells
+    }
+    
+    /// The cells at indices `range`.
+    pub fn read_range(&self, range: Range<usize>) -> Result<&[Tryte9], MemoryError> {
+        self.check_range(&range)?;
+        Ok(&self.cells[range])
+    }
+    
+    /// Overwrite the cells from index `start` with `values`.
+    pub fn write_range(&mut self, start: usize, values: &[Tryte9]) -> Result<(), MemoryError> {
+        let range = start..start.saturating_add(values.len());
+        self.check_range(&range)?;
+        self.cells[range.clone()].copy_from_slice(values);
+        for index in range {
+            self.mark_written(index);
+        }
+        Ok(())
+    }
+    
+    /// Fail with the first address of `range` outside memory, or with the
+    /// raw indices when `range` is reversed or starts past the end.
+    fn check_range(&self, range: &Range<usize>) -> Result<(), MemoryError> {
+        if range.start > range.end || range.start > self.cells.len() {
+            return Err(MemoryError::InvalidRange { start: range.start, end: range.end });
+        }
+        if range.end > self.cells.len() {
+            let first = range.start.max(self.cells.len());
+            return Err(MemoryError::AddressOutOfRange(first as i32 - self.origin() as i32));
+        }
+        Ok(())
     }
     
     /// Read using a ternary address.
     /// Converts the balanced ternary value to an unsigned index.
+    #[inline]
     pub fn read_ternary(&self, addr: Tryte9) -> Result<Tryte9, MemoryError> {
         let index = self.addr_to_index(addr)?;
         Ok(self.cells[index])
@@ -54,27 +162,43 @@ impl Memory {
     pub fn write_ternary(&mut self, addr: Tryte9, value: Tryte9) -> Result<(), MemoryError> {
         let index = self.addr_to_index(addr)?;
         self.cells[index] = value;
+        self.mark_written(index);
         Ok(())
     }
     
+    /// Bring any address into range by wrapping it around the ends of
+    /// memory: one past the highest address is the lowest.
+    #[inline]
+    pub fn wrap_address(&self, addr: i64) -> i32 {
+        let min = self.min_addr() as i64;
+        (min + (addr - min).rem_euclid(self.cells.len() as i64)) as i32
+    }
+    
+    /// Whether `addr` names a cell.
+    #[inline]
+    pub fn contains(&self, addr: i64) -> bool {
+        (self.min_addr() as i64..=self.max_addr() as i64).contains(&addr)
+    }
+    
     /// Convert a ternary address to a memory index.
     /// 
     /// The Setun used addresses from approximately -81 to +80 (162 values).
-    /// We map this to 0-161 by adding 81.
-    fn addr_to_index(&self, addr: Tryte9) -> Result<usize, MemoryError> {
+    /// We map this to 0-161 by adding 81 (half the memory size in general).
+    #[inline]
+    pub fn addr_to_index(&self, addr: Tryte9) -> Result<usize, MemoryError> {
         let signed_addr = addr.to_i32();
         // Map balanced ternary range to 0-based index
         // Addresses -81 to +80 map to indices 0 to 161
-        let index = (signed_addr + 81) as usize;
-        if index >= MEMORY_SIZE {
+        let index = signed_addr + self.origin() as i32;
+        if index < 0 || index as usize >= self.cells.len() {
             return Err(MemoryError::AddressOutOfRange(signed_addr));
         }
-        Ok(index)
+        Ok(index as usize)
     }
     
     /// Convert a memory index to a ternary address.
     pub fn index_to_addr(&self, index: usize) -> Tryte9 {
-        let signed_addr = (index as i32) - 81;
+        let signed_addr = (index as i32) - self.origin() as i32;
         Tryte9::from_i32(signed_addr)
     }
     
@@ -83,27 +207,61 @@ impl Memory {
         for cell in &mut self.cells {
             *cell = Tryte9::zero();
         }
+        self.mark_all_written();
+    }
+    
+    /// Take the indices written since the last call, in ascending order.
+    ///
+    /// Every write counts, even one that stores the value already present.
+    pub fn take_changes(&mut self) -> Vec<usize> {
+        let mut changes = std::mem::take(&mut self.changes);
+        for &index in &changes {
+            self.dirty[index] = false;
+        }
+        changes.sort_unstable();
+        changes
+    }
+    
+    /// Count every cell as written for [`Memory::take_changes`], as after
+    /// replacing the whole memory.
+    pub fn mark_all_written(&mut self) {
+        for index in 0..self.cells.len() {
+            self.mark_written(index);
+        }
+    }
+    
+    /// Record a write to `index` for [`Memory::take_changes`].
+    fn mark_written(&mut self, index: usize) {
+        if self.dirty.len() != self.cells.len() {
+            self.dirty.resize(self.cells.len(), false);
+        }
+        if !self.dirty[index] {
+            self.dirty[index] = true;
+            self.changes.push(index);
+        }
     }
     
     /// Load a program into memory starting at the given address.
     pub fn load_program(&mut self, start_addr: usize, program: &[Tryte9]) -> Result<(), MemoryError> {
-        if start_addr + program.len() > MEMORY_SIZE {
+        if start_addr + program.len() > self.cells.len() {
             return Err(MemoryError::ProgramTooLarge {
                 size: program.len(),
-                available: MEMORY_SIZE - start_addr,
+                available: self.cells.len().saturating_sub(start_addr),
             });
         }
         
         for (i, &word) in program.iter().enumerate() {
             self.cells[start_addr + i] = word;
+            self.mark_written(start_addr + i);
         }
+        event!(debug, index = start_addr, words = program.len(), "program loaded");
         
         Ok(())
     }
     
     /// Dump memory contents (for debugging).
     pub fn dump(&self, start: usize, count: usize) -> Vec<(usize, Tryte9)> {
-        let end = (start + count).min(MEMORY_SIZE);
+        let end = (start + count).min(self.cells.len());
         (start..end)
             .map(|i| (i, self.cells[i]))
             .collect()
@@ -127,7 +285,7 @@ impl std::fmt::Debug for Memory {
         
         f.debug_struct("Memory")
             .field("non_zero_cells", &non_zero.len())
-            .field("total_cells", &MEMORY_SIZE)
+            .field("total_cells", &self.cells.len())
             .finish()
     }
 }
@@ -139,17 +297,22 @@ pub enum MemoryError {
     AddressOutOfRange(i32),
     /// Program is too large to fit in memory.
     ProgramTooLarge { size: usize, available: usize },
+    /// Index range is reversed or starts past the end of memory.
+    InvalidRange { start: usize, end: usize },
 }
 
 impl std::fmt::Display for MemoryError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             MemoryError::AddressOutOfRange(addr) => {
-                write!(f, "memory address {} out of range (-81 to +80)", addr)
+                write!(f, "memory address {} out of range", addr)
             }
             MemoryError::ProgramTooLarge { size, available } => {
                 write!(f, "program size {} exceeds available space {}", size, available)
             }
+            MemoryError::InvalidRange { start, end } => {
+                write!(f, "invalid memory index range {}..{}", start, end)
+            }
         }
     }
 }
@@ -192,6 +355,64 @@ mod tests {
         assert!(mem.read_ternary(Tryte9::from_i32(81)).is_err());
     }
     
+    #[test]
+    fn test_memory_custom_size() {
+        let mem = Memory::with_size(729);
+        assert_eq!(mem.origin(), 364);
+        assert_eq!(mem.min_addr(), -364);
+        assert_eq!(mem.max_addr(), 364);
+        assert!(mem.read_ternary(Tryte9::from_i32(364)).is_ok());
+        assert!(mem.read_ternary(Tryte9::from_i32(-365)).is_err());
+        assert_eq!(mem.index_to_addr(0).to_i32(), -364);
+    }
+    
+    #[test]
+    fn test_take_changes() {
+        let mut mem = Memory::new();
+        mem.write(20, Tryte9::from_i32(1));
+        mem.write(5, Tryte9::from_i32(2));
+        mem.write(20, Tryte9::from_i32(3));
+        mem.write_ternary(Tryte9::from_i32(0), Tryte9::from_i32(4)).unwrap();
+        
+        assert_eq!(mem.take_changes(), vec![5, 20, 81]);
+        assert!(mem.take_changes().is_empty());
+        
+        mem.write(5, Tryte9::from_i32(2));
+        assert_eq!(mem.take_changes(), vec![5]);
+        
+        // A restored memory reports every cell
+        let json = serde_json::to_string(&mem).unwrap();
+        let mut restored: Memory = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.take_changes(), (0..MEMORY_SIZE).collect::<Vec<_>>());
+        assert_eq!(restored.read(20).to_i32(), 3);
+    }
+    
+    #[test]
+    fn test_bulk_access() {
+        let mut mem = Memory::with_size(9);
+        let values = [Tryte9::from_i32(1), Tryte9::from_i32(-2)];
+        mem.write_range(3, &values).unwrap();
+        assert_eq!(mem.read_range(3..5).unwrap(), &values);
+        assert_eq!(mem.take_changes(), vec![3, 4]);
+        assert_eq!(mem.iter().nth(3), Some((-1, values[0])));
+        assert_eq!(mem.iter().map(|(addr, _)| addr).last(), Some(4));
+        
+        // Out of range fails naming the first missing address
+        assert_eq!(mem.read_range(7..10), Err(MemoryError::AddressOutOfRange(5)));
+        assert_eq!(mem.write_range(8, &values), Err(MemoryError::AddressOutOfRange(5)));
+        
+        // Reversed ranges, or ones starting past the end, report the raw indices
+        for (start, end) in [(100000, 5), (4, 3), (12, 20)] {
+            assert_eq!(mem.read_range(Range { start, end }), Err(MemoryError::InvalidRange { start, end }));
+        }
+        assert_eq!(mem.write_range(usize::MAX, &values), Err(MemoryError::InvalidRange { start: usize::MAX, end: usize::MAX }));
+        assert_eq!(mem.read(8).to_i32(), 0);
+        
+        mem.as_mut_slice()[0] = Tryte9::from_i32(7);
+        assert_eq!(mem.as_slice()[0].to_i32(), 7);
+        assert_eq!(mem.take_changes().len(), 9);
+    }
+    
     #[test]
     fn test_load_program() {
         let mut mem = Memory::new();
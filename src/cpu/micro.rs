@@ -0,0 +1,160 @@
+//! Trit-serial view of execution.
+//!
+//! [`Cpu::step_micro`] executes one instruction like
+//! [`Cpu::step_traced`] and also breaks it into the per-trit micro-steps
+//! a serial ternary machine would go through: the instruction word
+//! fetched a trit at a time, the operand read, every position of the
+//! adder with its carry in and out, and the result written back. The
+//! [`MicroOp`]s are plain serializable data so a frontend can animate
+//! the inside of the ALU at whatever pace it likes:
+//!
+//! ```
+//! use setun::{assemble, Cpu};
+//! use setun::cpu::MicroOp;
+//!
+//! let mut cpu = Cpu::new();
+//! cpu.load_program(&assemble("LDA 3\nADD 4\nHLT\nDAT 5\nDAT 7").unwrap()).unwrap();
+//! cpu.step().unwrap();
+//!
+//! let micro = cpu.step_micro().unwrap();
+//! assert_eq!(micro.record.regs.s, 12);
+//! // Trit 1 of 5 + 7: - plus - is -2, written as + with a carry of -
+//! let adds: Vec<_> = micro.ops.iter().filter_map(|op| match op {
+//!     MicroOp::Alu { step, .. } => Some(step),
+//!     _ => None,
+//! }).collect();
+//! assert_eq!((adds[1].sum.to_i8(), adds[1].carry_out.to_i8()), (1, -1));
+//! ```
+//!
+//! Adder steps are reconstructed with [`explain`](crate::ternary::arith::explain),
+//! so multiplication is always shown as the schoolbook method even when
+//! the CPU took its shift/add shortcut for a sparse operand; the result
+//! is the same either way.
+
+use crate::asm::disasm::disassemble_instruction_for;
+use crate::cpu::decode::Instruction;
+use crate::cpu::{AccessKind, Cpu, CpuError, TraceRecord};
+use crate::ternary::arith::explain::{explain_add, explain_mul, explain_sub, Explanation, TritStep};
+use crate::ternary::{Trit, Tryte9, Word18};
+use serde::Serialize;
+
+/// One micro-step of an instruction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MicroOp {
+    /// One trit of the instruction word read from the cell at C.
+    Fetch { addr: i32, position: usize, trit: Trit },
+    /// The fetched word decoded.
+    Decode { instruction: String },
+    /// One trit of a data operand read from memory.
+    Read { addr: i32, position: usize, trit: Trit },
+    /// One trit position of an adder pass. Addition and subtraction
+    /// have a single pass; multiplication has one per non-zero trit of S.
+    Alu { pass: usize, step: TritStep },
+    /// One trit of a result stored to memory.
+    Write { addr: i32, position: usize, trit: Trit },
+    /// A register took its new value.
+    Register { register: String, before: i64, after: i64 },
+}
+
+/// An executed instruction and its micro-steps.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MicroTrace {
+    /// The instruction as [`Cpu::step_traced`] reports it.
+    pub record: TraceRecord,
+    /// Micro-steps in execution order.
+    pub ops: Vec<MicroOp>,
+}
+
+impl Cpu {
+    /// Execute a single instruction and break it into trit-serial micro-steps.
+    pub fn step_micro(&mut self) -> Result<MicroTrace, CpuError> {
+        let s = self.regs.s;
+        let record = self.step_traced()?;
+
+        let mut ops: Vec<MicroOp> = trits(record.raw)
+            .map(|(position, trit)| MicroOp::Fetch { addr: record.pc, position, trit })
+            .collect();
+        ops.push(MicroOp::Decode { instruction: disassemble_instruction_for(record.raw, &self.config) });
+
+        for access in record.accesses.iter().filter(|a| a.kind == AccessKind::Read) {
+            ops.extend(trits(Tryte9::from_i32(access.value))
+                .map(|(position, trit)| MicroOp::Read { addr: access.addr, position, trit }));
+        }
+
+        let operand = record.accesses.iter()
+            .find(|a| a.kind == AccessKind::Read)
+            .map(|a| Tryte9::from_i32(a.value).to_word18());
+        if let Some(explanation) = operand.and_then(|operand| adder(&record.instruction, &s, &operand)) {
+            for (pass, p) in explanation.passes.iter().enumerate() {
+                ops.extend(p.steps.iter().map(|&step| MicroOp::Alu { pass, step }));
+            }
+        }
+
+        for access in record.accesses.iter().filter(|a| a.kind == AccessKind::Write) {
+            ops.extend(trits(Tryte9::from_i32(access.value))
+                .map(|(position, trit)| MicroOp::Write { addr: access.addr, position, trit }));
+        }
+
+        ops.extend(record.deltas.iter().map(|d| MicroOp::Register {
+            register: d.register.clone(),
+            before: d.before,
+            after: d.after,
+        }));
+
+        Ok(MicroTrace { record, ops })
+    }
+}
+
+/// Trits of a cell with their positions, least significant first.
+fn trits(word: Tryte9) -> impl Iterator<Item = (usize, Trit)> {
+    (0..Tryte9::WIDTH).map(move |i| (i, word.get(i)))
+}
+
+/// The adder work of an arithmetic instruction on `s` and its operand.
+fn adder(instr: &Instruction, s: &Word18, operand: &Word18) -> Option<Explanation> {
+    let abs = if operand.sign() == Trit::N { operand.neg() } else { *operand };
+    Some(match instr {
+        Instruction::Add { .. } => explain_add(s, operand),
+        Instruction::Sub { .. } => explain_sub(s, operand),
+        Instruction::AddAbs { .. } => explain_add(s, &abs),
+        Instruction::SubAbs { .. } => explain_sub(s, &abs),
+        Instruction::Mul { .. } => explain_mul(s, operand),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    #[test]
+    fn test_store_and_multiply() {
+        let mut cpu = Cpu::new();
+        cpu.load_program(&assemble("LDA 5\nMUL 6\nADD 5\nSTA 7\nHLT\nDAT 4\nDAT -3\nDAT 0").unwrap()).unwrap();
+        cpu.step().unwrap();
+
+        // 4 = ++ has two non-zero trits, so two passes
+        let mul = cpu.step_micro().unwrap();
+        let passes: Vec<usize> = mul.ops.iter().filter_map(|op| match op {
+            MicroOp::Alu { pass, .. } => Some(*pass),
+            _ => None,
+        }).collect();
+        assert_eq!(passes.first(), Some(&0));
+        assert_eq!(passes.last(), Some(&1));
+        assert!(mul.ops.contains(&MicroOp::Register { register: "R".into(), before: 0, after: -12 }));
+
+        // The high part of -12 is 0, so S is back to 4 after the ADD
+        cpu.step().unwrap();
+        let sta = cpu.step_micro().unwrap();
+        assert_eq!(sta.ops.iter().filter(|op| matches!(op, MicroOp::Fetch { addr: 3, .. })).count(), 9);
+        assert!(matches!(&sta.ops[9], MicroOp::Decode { instruction } if instruction == "STA 7"));
+        assert!(!sta.ops.iter().any(|op| matches!(op, MicroOp::Alu { .. })));
+        let written: Vec<Trit> = sta.ops.iter().filter_map(|op| match op {
+            MicroOp::Write { addr: 7, trit, .. } => Some(*trit),
+            _ => None,
+        }).collect();
+        assert_eq!(written, Tryte9::from_i32(4).trits());
+    }
+}
@@ -9,8 +9,53 @@ pub mod memory;
 pub mod registers;
 pub mod decode;
 pub mod execute;
+pub mod config;
+pub mod timing;
+pub mod cost;
+pub mod snapshot;
+pub mod trace;
+pub mod profile;
+pub mod coverage;
+pub mod io;
+pub mod meminit;
+pub mod device;
+pub mod vcd;
+pub mod isa;
+pub mod protect;
+pub mod fault;
+pub mod random;
+pub mod console;
+pub mod panel;
+pub mod overlay;
+pub mod boot;
+pub mod extension;
+pub mod micro;
+pub mod semihost;
+pub mod schedule;
 
 pub use memory::Memory;
 pub use registers::Registers;
-pub use decode::{Instruction, AddrMode, DecodeError};
-pub use execute::{Cpu, CpuError, CpuState};
+pub use decode::{Instruction, AddrMode, DecodeError, EncodeError, OperandKind};
+pub use cost::CostModel;
+pub use config::{AddressPolicy, CpuConfig, IsaVariant, OverflowPolicy, ConfigError, StackRegion};
+pub use execute::{Cpu, CpuError, CpuState, StepSummary, StopReason};
+pub use snapshot::{Snapshot, SnapshotError};
+pub use profile::{Profile, BranchStats, ExecutionReport, OpcodeStats, MemoryTraffic};
+pub use coverage::{Coverage, CoverageReport, BranchCoverage};
+pub use io::{IoPorts, TAPE_READER_ADDR, TELETYPE_ADDR};
+pub use meminit::{MemInit, MemInitError};
+pub use device::{Device, DeviceBus, DeviceError, DeviceId};
+pub use trace::{TraceRecord, Steps, RegisterState, RegisterDelta, MemoryAccess, AccessKind, TraceFormat, TraceWriter};
+pub use vcd::{VcdFormat, VcdWriter};
+pub use isa::{IsaDescription, InstructionSpec, InstructionGroup, MemoryEffect};
+pub use protect::{MemoryProtection, ReadOnlyRegion, WriteFault, WritePolicy};
+pub use fault::{FaultInjector, FaultSite, FaultTarget, InjectedFault};
+pub use random::{RandomDevice, RANDOM_TRIT_ADDR, RANDOM_WORD_ADDR};
+pub use panel::{FrontPanel, LampChange};
+pub use console::{ConsoleDevice, ConsoleInput, CONSOLE_ADDR};
+pub use overlay::{Segment, SegmentError, SegmentTable};
+pub use boot::{BootError, BootHeader};
+pub use extension::{ExtensionError, ExtensionRegistry, IsaExtension};
+pub use micro::{MicroOp, MicroTrace};
+pub use semihost::{HostService, Semihost, SEMIHOST_OPCODE};
+pub use schedule::{Schedule, ScheduledEvent, Scheduler};
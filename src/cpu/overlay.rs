@@ -0,0 +1,238 @@
+//! Multi-image loading and overlays.
+//!
+//! [`Cpu::load_program_at`] places one contiguous image. Larger programs
+//! on the real machine were split into segments: a resident part that
+//! stays in memory and overlays that are read in over each other as the
+//! program moves between phases. [`Cpu::load_segments`] places several
+//! named [`Segment`]s at once and refuses any two that overlap;
+//! [`Cpu::add_overlay`] registers an image without loading it, and
+//! [`Cpu::load_overlay`] copies it in later, even while a program runs,
+//! evicting whatever resident segments it overwrites:
+//!
+//! ```
+//! use setun::{assemble, Cpu};
+//! use setun::cpu::Segment;
+//!
+//! let mut cpu = Cpu::new();
+//! cpu.load_segments(vec![
+//!     Segment::new("main", 0, assemble("LDA 10\nHLT").unwrap()),
+//!     Segment::new("data", 10, assemble("DAT 1").unwrap()),
+//! ]).unwrap();
+//! cpu.add_overlay(Segment::new("data2", 10, assemble("DAT 2").unwrap())).unwrap();
+//!
+//! assert_eq!(cpu.load_overlay("data2").unwrap(), vec!["data".to_string()]);
+//! cpu.run().unwrap();
+//! assert_eq!(cpu.regs.s.to_i64(), 2);
+//! ```
+//!
+//! The segment table is saved with snapshots, so a restored session can
+//! still swap overlays. [`Cpu::reset`] clears memory and with it the
+//! resident set, but keeps the registered images.
+
+use crate::cpu::{Cpu, Memory};
+use crate::ternary::Tryte9;
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+
+/// A named program image and the address of its first word.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment {
+    /// Name used to reload it.
+    pub name: String,
+    /// Address of the first word.
+    pub addr: i32,
+    /// The image.
+    pub words: Vec<Tryte9>,
+}
+
+impl Segment {
+    /// Create a segment.
+    pub fn new(name: impl Into<String>, addr: i32, words: Vec<Tryte9>) -> Self {
+        Self { name: name.into(), addr, words }
+    }
+
+    /// Address just past the last word.
+    pub fn end(&self) -> i32 {
+        self.addr + self.words.len() as i32
+    }
+
+    /// Whether the two segments share an address.
+    pub fn overlaps(&self, other: &Segment) -> bool {
+        self.addr < other.end() && other.addr < self.end()
+    }
+
+    fn check_fits(&self, mem: &Memory) -> Result<(), SegmentError> {
+        if self.addr < mem.min_addr() || self.end() > mem.max_addr() + 1 {
+            return Err(SegmentError::OutOfRange { name: self.name.clone(), start: self.addr, end: self.end() - 1 });
+        }
+        Ok(())
+    }
+}
+
+/// Registered images and which of them are in memory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentTable {
+    images: Vec<Segment>,
+    resident: Vec<String>,
+}
+
+impl SegmentTable {
+    /// Every registered image, in registration order.
+    pub fn images(&self) -> &[Segment] {
+        &self.images
+    }
+
+    /// Names of the images currently in memory, oldest load first.
+    pub fn resident(&self) -> &[String] {
+        &self.resident
+    }
+
+    /// Look up an image by name.
+    pub fn get(&self, name: &str) -> Option<&Segment> {
+        self.images.iter().find(|s| s.name == name)
+    }
+
+    /// The resident segment holding `addr`, if any.
+    pub fn segment_at(&self, addr: i32) -> Option<&Segment> {
+        self.resident
+            .iter()
+            .filter_map(|name| self.get(name))
+            .find(|s| (s.addr..s.end()).contains(&addr))
+    }
+
+    /// Forget which images are in memory (after memory was cleared).
+    pub fn clear_resident(&mut self) {
+        self.resident.clear();
+    }
+}
+
+/// Errors from loading segments.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SegmentError {
+    #[error("segments '{first}' and '{second}' overlap at address {addr}")]
+    Overlap { first: String, second: String, addr: i32 },
+
+    #[error("segment '{name}' ({start}..{end}) does not fit in memory")]
+    OutOfRange { name: String, start: i32, end: i32 },
+
+    #[error("a segment named '{0}' is already registered")]
+    Duplicate(String),
+
+    #[error("no segment named '{0}'")]
+    Unknown(String),
+}
+
+impl Cpu {
+    /// Load several segments at their addresses, replacing the segment
+    /// table. Nothing is written unless every segment fits and no two
+    /// overlap.
+    pub fn load_segments(&mut self, segments: Vec<Segment>) -> Result<(), SegmentError> {
+        for (i, segment) in segments.iter().enumerate() {
+            segment.check_fits(&self.mem)?;
+            if let Some(other) = segments[..i].iter().find(|other| other.name == segment.name) {
+                return Err(SegmentError::Duplicate(other.name.clone()));
+            }
+            if let Some(other) = segments[..i].iter().find(|other| other.overlaps(segment)) {
+                return Err(SegmentError::Overlap {
+                    first: other.name.clone(),
+                    second: segment.name.clone(),
+                    addr: segment.addr.max(other.addr),
+                });
+            }
+        }
+        for segment in &segments {
+            self.write_segment(segment);
+        }
+        self.segments = SegmentTable {
+            resident: segments.iter().map(|s| s.name.clone()).collect(),
+            images: segments,
+        };
+        Ok(())
+    }
+
+    /// Register an overlay image without loading it. Overlays may share
+    /// addresses with each other and with resident segments.
+    pub fn add_overlay(&mut self, segment: Segment) -> Result<(), SegmentError> {
+        segment.check_fits(&self.mem)?;
+        if self.segments.get(&segment.name).is_some() {
+            return Err(SegmentError::Duplicate(segment.name));
+        }
+        self.segments.images.push(segment);
+        Ok(())
+    }
+
+    /// Copy a registered image into memory, returning the names of the
+    /// resident segments it overwrote. Registers and the program counter
+    /// are left alone, so a running program continues with the new code.
+    pub fn load_overlay(&mut self, name: &str) -> Result<Vec<String>, SegmentError> {
+        let segment = self.segments.get(name).cloned().ok_or_else(|| SegmentError::Unknown(name.to_string()))?;
+        self.write_segment(&segment);
+
+        let mut evicted = Vec::new();
+        let images = &self.segments.images;
+        self.segments.resident.retain(|resident| {
+            let overwritten = resident == name
+                || images.iter().any(|s| &s.name == resident && s.overlaps(&segment));
+            if overwritten && resident != name {
+                evicted.push(resident.clone());
+            }
+            !overwritten
+        });
+        self.segments.resident.push(segment.name);
+        Ok(evicted)
+    }
+
+    fn write_segment(&mut self, segment: &Segment) {
+        let index = self.mem.addr_to_index(Tryte9::from_i32(segment.addr)).expect("segment range checked");
+        self.mem.load_program(index, &segment.words).expect("segment range checked");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    #[test]
+    fn test_overlap_detection() {
+        let mut cpu = Cpu::new();
+        let words = |n: usize| vec![Tryte9::from_i32(1); n];
+        let result = cpu.load_segments(vec![
+            Segment::new("a", -10, words(5)),
+            Segment::new("b", 0, words(3)),
+            Segment::new("c", -6, words(2)),
+        ]);
+        assert_eq!(result, Err(SegmentError::Overlap { first: "a".into(), second: "c".into(), addr: -6 }));
+        // Nothing was written
+        assert_eq!(cpu.mem.read_ternary(Tryte9::from_i32(0)).unwrap(), Tryte9::zero());
+
+        assert!(matches!(
+            cpu.load_segments(vec![Segment::new("end", 79, words(3))]),
+            Err(SegmentError::OutOfRange { .. })
+        ));
+        cpu.load_segments(vec![Segment::new("a", -10, words(5)), Segment::new("b", -5, words(3))]).unwrap();
+        assert_eq!(cpu.segments.segment_at(-4).map(|s| s.name.as_str()), Some("b"));
+        assert_eq!(cpu.add_overlay(Segment::new("b", 20, words(1))), Err(SegmentError::Duplicate("b".into())));
+        assert_eq!(cpu.load_overlay("x"), Err(SegmentError::Unknown("x".into())));
+    }
+
+    #[test]
+    fn test_overlay_swapped_at_runtime() {
+        // Phase one doubles, phase two triples the value in 20
+        let mut cpu = Cpu::new();
+        cpu.load_segments(vec![
+            Segment::new("main", 0, assemble("LDA 20\nADD 20\nHLT").unwrap()),
+            Segment::new("value", 20, vec![Tryte9::from_i32(5)]),
+        ]).unwrap();
+        cpu.add_overlay(Segment::new("triple", 0, assemble("LDA 20\nSHL 1\nHLT").unwrap())).unwrap();
+        cpu.run().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), 10);
+
+        assert_eq!(cpu.load_overlay("triple").unwrap(), vec!["main".to_string()]);
+        assert_eq!(cpu.segments.resident(), ["value".to_string(), "triple".to_string()]);
+        cpu.set_entry(0).unwrap();
+        cpu.state = crate::CpuState::Running;
+        cpu.run().unwrap();
+        assert_eq!(cpu.regs.s.to_i64(), 15);
+    }
+}
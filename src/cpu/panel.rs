@@ -0,0 +1,153 @@
+//! Front-panel view of the machine.
+//!
+//! The Setun's console showed every register trit as a lamp. A
+//! [`FrontPanel`] is that view in data: one [`Trit`] per lamp for S, R, F,
+//! C and ω, the run/halt indicator, and the instruction at C, ready for a
+//! web page or a string of LEDs without each frontend unpacking registers
+//! itself. Capture one after every step and draw it, or send only the
+//! lamps that [changed](FrontPanel::changes):
+//!
+//! ```
+//! use setun::{assemble, Cpu, Trit};
+//! use setun::cpu::FrontPanel;
+//!
+//! let mut cpu = Cpu::new();
+//! cpu.load_program(&assemble("LDA 2\nHLT\nDAT 4").unwrap()).unwrap();
+//! let before = FrontPanel::capture(&cpu);
+//! assert_eq!(before.instruction, "LDA 2");
+//!
+//! cpu.step().unwrap();
+//! let after = FrontPanel::capture(&cpu);
+//! assert_eq!(after.s[..2], [Trit::P, Trit::P]); // 4 = ++ in balanced ternary
+//! assert!(after.changes(&before).iter().any(|lamp| lamp.register == "S"));
+//! ```
+//!
+//! Lamp arrays are least significant trit first, matching
+//! [`Word18::get`](crate::Word18::get).
+
+use crate::asm::disasm::disassemble_instruction_for;
+use crate::cpu::{Cpu, CpuState};
+use crate::ternary::{Trit, Tryte9};
+use serde::{Serialize, Deserialize};
+
+/// Lamp states of the machine at one moment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrontPanel {
+    /// Accumulator lamps.
+    pub s: [Trit; 18],
+    /// Multiplier register lamps.
+    pub r: [Trit; 18],
+    /// Index register lamps.
+    pub f: [Trit; 5],
+    /// Program counter lamps.
+    pub c: [Trit; 9],
+    /// Sign register lamp.
+    pub omega: Trit,
+    /// Run/halt indicator: the CPU will execute another instruction.
+    pub running: bool,
+    /// Execution state.
+    pub state: CpuState,
+    /// Instructions executed.
+    pub cycles: u64,
+    /// Word at the address in C, about to execute.
+    pub word: Tryte9,
+    /// That word disassembled.
+    pub instruction: String,
+}
+
+/// One lamp and its state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LampChange {
+    /// Register name: `S`, `R`, `F`, `C` or `ω`.
+    pub register: &'static str,
+    /// Trit position (0 is the least significant).
+    pub index: usize,
+    /// New lamp state.
+    pub value: Trit,
+}
+
+impl FrontPanel {
+    /// Read the panel of `cpu`.
+    pub fn capture(cpu: &Cpu) -> Self {
+        let regs = &cpu.regs;
+        let mut f = [Trit::O; 5];
+        f.copy_from_slice(&regs.f.to_tryte9().trits()[..5]);
+        let word = cpu.mem.read_ternary(regs.c).unwrap_or_default();
+        Self {
+            s: *regs.s.trits(),
+            r: *regs.r.trits(),
+            f,
+            c: *regs.c.trits(),
+            omega: regs.omega,
+            running: cpu.state == CpuState::Running,
+            state: cpu.state,
+            cycles: cpu.cycles,
+            word,
+            instruction: disassemble_instruction_for(word, &cpu.config),
+        }
+    }
+
+    /// Every lamp in panel order (S, R, F, C, then ω), e.g. for shifting
+    /// out to a chain of LED drivers.
+    pub fn lamps(&self) -> Vec<LampChange> {
+        let registers: [(&'static str, &[Trit]); 5] = [
+            ("S", &self.s),
+            ("R", &self.r),
+            ("F", &self.f),
+            ("C", &self.c),
+            ("ω", std::slice::from_ref(&self.omega)),
+        ];
+        registers
+            .into_iter()
+            .flat_map(|(register, trits)| {
+                trits.iter().enumerate().map(move |(index, &value)| LampChange { register, index, value })
+            })
+            .collect()
+    }
+
+    /// Lamps that differ from `previous`, with their new states.
+    pub fn changes(&self, previous: &FrontPanel) -> Vec<LampChange> {
+        self.lamps()
+            .into_iter()
+            .zip(previous.lamps())
+            .filter(|(now, before)| now.value != before.value)
+            .map(|(now, _)| now)
+            .collect()
+    }
+}
+
+impl Cpu {
+    /// The front panel as it stands (see [`FrontPanel`]).
+    pub fn front_panel(&self) -> FrontPanel {
+        FrontPanel::capture(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    #[test]
+    fn test_panel_tracks_steps() {
+        let mut cpu = Cpu::new();
+        cpu.load_program(&assemble("LDA 3\nLDF 3\nHLT\nDAT -1").unwrap()).unwrap();
+        let start = cpu.front_panel();
+        assert!(start.running);
+        assert_eq!(start.lamps().len(), 18 + 18 + 5 + 9 + 1);
+
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        let panel = cpu.front_panel();
+        assert_eq!(panel.s[0], Trit::N);
+        assert_eq!(panel.f[0], Trit::N);
+        assert_eq!(panel.instruction, "HLT");
+
+        cpu.step().unwrap();
+        let halted = cpu.front_panel();
+        assert!(!halted.running);
+        assert_eq!(halted.state, CpuState::Halted);
+        // Only C changed: 2 (+-) -> 3 (+0)
+        assert_eq!(halted.changes(&panel), vec![LampChange { register: "C", index: 0, value: Trit::O }]);
+    }
+}
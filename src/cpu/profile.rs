@@ -0,0 +1,307 @@
+//! Execution profiling.
+//!
+//! A [`Profile`] aggregates trace records into per-address execution
+//! counts, per-opcode totals and conditional branch statistics, so the
+//! hot spots of an emulated program can be found without reading traces.
+//! An [`ExecutionReport`] summarizes the same records by opcode instead,
+//! with emulated latencies from the [timing model](crate::cpu::timing),
+//! memory traffic and branch behaviour:
+//!
+//! ```
+//! use setun::{assemble, Cpu};
+//!
+//! let mut cpu = Cpu::new();
+//! cpu.load_program(&assemble("LDA 4\nMUL 4\nSTA 5\nHLT\nDAT 6").unwrap()).unwrap();
+//! let report = cpu.run_reported(100).unwrap();
+//!
+//! assert_eq!(report.instructions, 4);
+//! assert_eq!(report.opcodes["MUL"].mean_us(), 335.0);
+//! assert_eq!((report.memory.reads, report.memory.writes), (2, 1));
+//! ```
+
+use crate::cpu::timing::instruction_time_us;
+use crate::cpu::{AccessKind, Cpu, CpuError, TraceRecord};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Taken/not-taken counts for conditional branches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchStats {
+    /// Times the branch jumped.
+    pub taken: u64,
+    /// Times execution fell through.
+    pub not_taken: u64,
+}
+
+impl BranchStats {
+    /// Total number of times the branch executed.
+    pub fn total(&self) -> u64 {
+        self.taken + self.not_taken
+    }
+}
+
+/// Aggregated execution statistics.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    /// Instructions executed.
+    pub instructions: u64,
+    /// Executions per instruction address.
+    pub address_counts: BTreeMap<i32, u64>,
+    /// Executions per mnemonic.
+    pub opcode_counts: BTreeMap<String, u64>,
+    /// Conditional branch outcomes per branch address.
+    pub branches: BTreeMap<i32, BranchStats>,
+    /// Emulated time in microseconds, when the timing model is enabled.
+    pub elapsed_us: Option<u64>,
+    /// Accumulated cost, when a cost model is configured.
+    #[serde(default)]
+    pub cost: Option<u64>,
+}
+
+impl Profile {
+    /// Create an empty profile.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one executed instruction.
+    pub fn record(&mut self, record: &TraceRecord) {
+        self.instructions += 1;
+        *self.address_counts.entry(record.pc).or_default() += 1;
+        *self.opcode_counts.entry(record.instruction.mnemonic().to_string()).or_default() += 1;
+
+        if record.instruction.is_conditional_branch() {
+            let stats = self.branches.entry(record.pc).or_default();
+            if record.regs.c == record.pc + 1 {
+                stats.not_taken += 1;
+            } else {
+                stats.taken += 1;
+            }
+        }
+    }
+
+    /// Branch outcomes summed over all branch sites.
+    pub fn branch_totals(&self) -> BranchStats {
+        self.branches.values().fold(BranchStats::default(), |acc, b| BranchStats {
+            taken: acc.taken + b.taken,
+            not_taken: acc.not_taken + b.not_taken,
+        })
+    }
+
+    /// The `n` most executed addresses, busiest first.
+    pub fn hottest(&self, n: usize) -> Vec<(i32, u64)> {
+        let mut counts: Vec<(i32, u64)> = self.address_counts.iter().map(|(&a, &c)| (a, c)).collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+}
+
+/// Totals for one opcode in an [`ExecutionReport`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpcodeStats {
+    /// Times executed.
+    pub count: u64,
+    /// Emulated time spent, in microseconds.
+    pub total_us: u64,
+    /// Data memory reads made.
+    pub reads: u64,
+    /// Data memory writes made.
+    pub writes: u64,
+}
+
+impl OpcodeStats {
+    /// Average emulated latency, in microseconds.
+    pub fn mean_us(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.total_us as f64 / self.count as f64
+    }
+}
+
+/// Data memory traffic in an [`ExecutionReport`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryTraffic {
+    /// Reads, including I/O ports.
+    pub reads: u64,
+    /// Writes, including I/O ports.
+    pub writes: u64,
+    /// Distinct addresses read.
+    pub cells_read: usize,
+    /// Distinct addresses written.
+    pub cells_written: usize,
+    #[serde(skip)]
+    read_addrs: BTreeSet<i32>,
+    #[serde(skip)]
+    written_addrs: BTreeSet<i32>,
+}
+
+/// Per-opcode statistics of a completed run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    /// Instructions executed.
+    pub instructions: u64,
+    /// Emulated time of all instructions, in microseconds.
+    pub total_us: u64,
+    /// Totals per mnemonic.
+    pub opcodes: BTreeMap<String, OpcodeStats>,
+    /// Data memory reads and writes.
+    pub memory: MemoryTraffic,
+    /// Conditional branch outcomes, summed over all branch sites.
+    pub branches: BranchStats,
+    /// Number of distinct conditional branch sites executed.
+    pub branch_sites: usize,
+    #[serde(skip)]
+    sites: BTreeSet<i32>,
+}
+
+impl ExecutionReport {
+    /// Create an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a report from recorded trace records.
+    pub fn from_records<'a>(records: impl IntoIterator<Item = &'a TraceRecord>) -> Self {
+        let mut report = Self::new();
+        for record in records {
+            report.record(record);
+        }
+        report
+    }
+
+    /// Add one executed instruction.
+    pub fn record(&mut self, record: &TraceRecord) {
+        let time = instruction_time_us(&record.instruction);
+        self.instructions += 1;
+        self.total_us += time;
+
+        let stats = self.opcodes.entry(record.instruction.mnemonic().to_string()).or_default();
+        stats.count += 1;
+        stats.total_us += time;
+        let memory = &mut self.memory;
+        for access in &record.accesses {
+            match access.kind {
+                AccessKind::Read => {
+                    stats.reads += 1;
+                    memory.reads += 1;
+                    memory.read_addrs.insert(access.addr);
+                }
+                AccessKind::Write => {
+                    stats.writes += 1;
+                    memory.writes += 1;
+                    memory.written_addrs.insert(access.addr);
+                }
+            }
+        }
+        memory.cells_read = memory.read_addrs.len();
+        memory.cells_written = memory.written_addrs.len();
+
+        if record.instruction.is_conditional_branch() {
+            self.sites.insert(record.pc);
+            self.branch_sites = self.sites.len();
+            if record.regs.c == record.pc + 1 {
+                self.branches.not_taken += 1;
+            } else {
+                self.branches.taken += 1;
+            }
+        }
+    }
+
+    /// Mean emulated latency over all instructions, in microseconds.
+    pub fn mean_us(&self) -> f64 {
+        if self.instructions == 0 {
+            return 0.0;
+        }
+        self.total_us as f64 / self.instructions as f64
+    }
+}
+
+impl Cpu {
+    /// Run for at most `max_cycles` instructions, collecting an execution report.
+    pub fn run_reported(&mut self, max_cycles: u64) -> Result<ExecutionReport, CpuError> {
+        let mut report = ExecutionReport::new();
+        self.run_traced(max_cycles, |record| report.record(&record))?;
+        Ok(report)
+    }
+
+    /// Run for at most `max_cycles` instructions, collecting a profile.
+    pub fn run_profiled(&mut self, max_cycles: u64) -> Result<Profile, CpuError> {
+        let mut profile = Profile::new();
+        let start_us = self.elapsed_us;
+        let start_cost = self.cost;
+
+        self.run_traced(max_cycles, |record| profile.record(&record))?;
+
+        if self.config.timing {
+            profile.elapsed_us = Some(self.elapsed_us - start_us);
+        }
+        if self.config.cost.is_some() {
+            profile.cost = Some(self.cost - start_cost);
+        }
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::CpuConfig;
+    use crate::asm::assemble;
+
+    #[test]
+    fn test_profile_counts_loop() {
+        // Count S down from 3 to 0
+        let program = assemble("
+            LDA 20
+        loop:
+            JZ done
+            SUB 21
+            JMP loop
+        done:
+            HLT
+        ").unwrap();
+        let mut cpu = Cpu::with_config(CpuConfig { timing: true, ..CpuConfig::default() });
+        cpu.mem.write(101, crate::Tryte9::from_i32(3));
+        cpu.mem.write(102, crate::Tryte9::from_i32(1));
+        cpu.load_program(&program).unwrap();
+
+        let profile = cpu.run_profiled(1000).unwrap();
+
+        assert_eq!(profile.instructions, 1 + 3 * 3 + 2);
+        assert_eq!(profile.address_counts[&1], 4);
+        assert_eq!(profile.opcode_counts["SUB"], 3);
+        assert_eq!(profile.branches[&1], BranchStats { taken: 1, not_taken: 3 });
+        assert_eq!(profile.branch_totals().total(), 4);
+        assert_eq!(profile.hottest(1), vec![(1, 4)]);
+        assert!(profile.elapsed_us.unwrap() > 0);
+    }
+
+    #[test]
+    fn test_execution_report() {
+        let program = assemble("
+            LDA 20
+        loop:
+            JZ done
+            SUB 21
+            STA 20
+            JMP loop
+        done:
+            HLT
+        ").unwrap();
+        let mut cpu = Cpu::new();
+        cpu.mem.write(101, crate::Tryte9::from_i32(2));
+        cpu.mem.write(102, crate::Tryte9::from_i32(1));
+        cpu.load_program(&program).unwrap();
+
+        let report = cpu.run_reported(1000).unwrap();
+        assert_eq!(report.instructions, 1 + 2 * 4 + 2);
+        assert_eq!(report.opcodes["SUB"], OpcodeStats { count: 2, total_us: 360, reads: 2, writes: 0 });
+        assert_eq!((report.memory.reads, report.memory.writes), (3, 2));
+        assert_eq!((report.memory.cells_read, report.memory.cells_written), (2, 1));
+        assert_eq!(report.branches, BranchStats { taken: 1, not_taken: 2 });
+        assert_eq!(report.branch_sites, 1);
+        assert_eq!(report.mean_us(), 180.0);
+    }
+}
@@ -0,0 +1,208 @@
+//! Read-only memory regions.
+//!
+//! Address ranges registered with [`MemoryProtection::protect`] reject
+//! stores made by executing instructions. Each region has a
+//! [`WritePolicy`]: trap with [`CpuError::WriteProtected`], silently
+//! discard the store as ROM would, or let it through and only record it.
+//! Every attempt is logged as a [`WriteFault`], so self-modifying code
+//! shows up at the instruction that did it:
+//!
+//! ```
+//! use setun::{assemble, Cpu, CpuError};
+//! use setun::cpu::WritePolicy;
+//!
+//! let program = assemble("LDA 3\nSTA 0\nHLT\nDAT 7").unwrap();
+//! let mut cpu = Cpu::new();
+//! cpu.load_program(&program).unwrap();
+//! cpu.protection.protect(0, 2, WritePolicy::Trap);
+//!
+//! assert!(matches!(cpu.run(), Err(CpuError::WriteProtected { addr: 0 })));
+//! assert_eq!(cpu.protection.faults()[0].pc, 1);
+//! ```
+//!
+//! Loading programs, memory initialization and debugger edits write
+//! memory directly and are not affected.
+//!
+//! [`CpuError::WriteProtected`]: crate::cpu::CpuError::WriteProtected
+
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// What happens when an instruction stores into a read-only region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WritePolicy {
+    /// Stop with [`CpuError::WriteProtected`](crate::cpu::CpuError::WriteProtected).
+    #[default]
+    Trap,
+    /// Drop the store and continue.
+    Discard,
+    /// Perform the store and continue; only the fault is recorded.
+    Log,
+}
+
+impl FromStr for WritePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "trap" => Ok(WritePolicy::Trap),
+            "discard" => Ok(WritePolicy::Discard),
+            "log" => Ok(WritePolicy::Log),
+            _ => Err(format!("unknown write policy '{}' (expected trap, discard or log)", s)),
+        }
+    }
+}
+
+impl fmt::Display for WritePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WritePolicy::Trap => write!(f, "trap"),
+            WritePolicy::Discard => write!(f, "discard"),
+            WritePolicy::Log => write!(f, "log"),
+        }
+    }
+}
+
+/// An inclusive range of read-only addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadOnlyRegion {
+    /// First protected address.
+    pub start: i32,
+    /// Last protected address.
+    pub end: i32,
+    /// What stores into the region do.
+    pub policy: WritePolicy,
+}
+
+impl ReadOnlyRegion {
+    /// Whether `addr` lies in the region.
+    pub fn contains(&self, addr: i32) -> bool {
+        (self.start..=self.end).contains(&addr)
+    }
+}
+
+/// Parses `START..END` or `START..END:POLICY` (the policy defaults to trap).
+impl FromStr for ReadOnlyRegion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (range, policy) = match s.split_once(':') {
+            Some((range, policy)) => (range, policy.parse()?),
+            None => (s, WritePolicy::default()),
+        };
+        let bad = || format!("invalid region '{}' (expected START..END[:POLICY])", s);
+        let (start, end) = range.split_once("..").ok_or_else(bad)?;
+        let start: i32 = start.trim().parse().map_err(|_| bad())?;
+        let end: i32 = end.trim().parse().map_err(|_| bad())?;
+        if start > end {
+            return Err(format!("region {} starts after it ends", s));
+        }
+        Ok(ReadOnlyRegion { start, end, policy })
+    }
+}
+
+/// One store into a read-only region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteFault {
+    /// Instructions completed before the faulting one.
+    pub cycle: u64,
+    /// Address of the storing instruction.
+    pub pc: i32,
+    /// Address written.
+    pub addr: i32,
+    /// Value the instruction tried to store.
+    pub value: i32,
+    /// Policy of the region that was hit.
+    pub policy: WritePolicy,
+}
+
+/// The read-only regions of a CPU and the faults they caught.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryProtection {
+    regions: Vec<ReadOnlyRegion>,
+    faults: Vec<WriteFault>,
+}
+
+impl MemoryProtection {
+    /// Make `start..=end` read-only. Where regions overlap, the one added
+    /// last decides the policy.
+    pub fn protect(&mut self, start: i32, end: i32, policy: WritePolicy) {
+        self.add(ReadOnlyRegion { start, end, policy });
+    }
+
+    /// Add a parsed region.
+    pub fn add(&mut self, region: ReadOnlyRegion) {
+        self.regions.push(region);
+    }
+
+    /// Remove every region, keeping recorded faults.
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    /// The registered regions, in the order they were added.
+    pub fn regions(&self) -> &[ReadOnlyRegion] {
+        &self.regions
+    }
+
+    /// The region deciding what a store to `addr` does, if any.
+    pub fn region_at(&self, addr: i32) -> Option<&ReadOnlyRegion> {
+        self.regions.iter().rev().find(|region| region.contains(addr))
+    }
+
+    /// Faults recorded so far, oldest first.
+    pub fn faults(&self) -> &[WriteFault] {
+        &self.faults
+    }
+
+    /// Take the recorded faults, leaving the log empty.
+    pub fn take_faults(&mut self) -> Vec<WriteFault> {
+        std::mem::take(&mut self.faults)
+    }
+
+    /// Log a fault.
+    pub(crate) fn record(&mut self, fault: WriteFault) {
+        self.faults.push(fault);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::Cpu;
+
+    #[test]
+    fn test_policies() {
+        // Stores 5 into address 4, inside the protected range
+        let program = assemble("LDA 3\nSTA 4\nHLT\nDAT 5\nDAT 1").unwrap();
+        for (policy, stored) in [(WritePolicy::Discard, 1), (WritePolicy::Log, 5)] {
+            let mut cpu = Cpu::new();
+            cpu.load_program(&program).unwrap();
+            cpu.protection.protect(-10, 10, WritePolicy::Trap);
+            cpu.protection.protect(4, 4, policy);
+            cpu.run().unwrap();
+            assert!(cpu.is_halted());
+            assert_eq!(cpu.mem.read(85).to_i32(), stored);
+            assert_eq!(cpu.protection.faults(), [WriteFault { cycle: 1, pc: 1, addr: 4, value: 5, policy }]);
+        }
+
+        // Unprotected stores are untouched
+        let mut cpu = Cpu::new();
+        cpu.load_program(&program).unwrap();
+        cpu.protection.protect(0, 3, WritePolicy::Trap);
+        cpu.run().unwrap();
+        assert!(cpu.protection.faults().is_empty());
+    }
+
+    #[test]
+    fn test_parse_region() {
+        assert_eq!("-5..10".parse(), Ok(ReadOnlyRegion { start: -5, end: 10, policy: WritePolicy::Trap }));
+        assert_eq!("0..0:log".parse(), Ok(ReadOnlyRegion { start: 0, end: 0, policy: WritePolicy::Log }));
+        assert!("3..1".parse::<ReadOnlyRegion>().is_err());
+        assert!("0..4:ignore".parse::<ReadOnlyRegion>().is_err());
+        assert!("7".parse::<ReadOnlyRegion>().is_err());
+    }
+}
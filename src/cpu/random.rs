@@ -0,0 +1,175 @@
+//! Random number device.
+//!
+//! [`RandomDevice`] is a [`Device`] answering two addresses next to the
+//! I/O ports: reading [`RANDOM_WORD_ADDR`] returns a random word spread
+//! over the whole ±9841 range, and reading [`RANDOM_TRIT_ADDR`] returns
+//! -1, 0 or 1. Writes are ignored. The sequence is fixed by the seed and
+//! restarts on reset, so Monte Carlo programs and randomized tests can be
+//! repeated exactly:
+//!
+//! ```
+//! use setun::{assemble, Cpu};
+//! use setun::cpu::RandomDevice;
+//!
+//! let program = assemble("LDA 118\nHLT").unwrap();
+//! let mut cpu = Cpu::new();
+//! cpu.devices.attach(RandomDevice::new(42)).unwrap();
+//! cpu.load_program(&program).unwrap();
+//! cpu.run().unwrap();
+//! let first = cpu.regs.s;
+//!
+//! cpu.reset();
+//! cpu.load_program(&program).unwrap();
+//! cpu.run().unwrap();
+//! assert_eq!(cpu.regs.s, first);
+//! ```
+
+use crate::cpu::device::{Device, DeviceError};
+use crate::ternary::Tryte9;
+use serde::{Serialize, Deserialize};
+
+/// Address returning a random word.
+pub const RANDOM_WORD_ADDR: i32 = 118;
+
+/// Address returning a random trit.
+pub const RANDOM_TRIT_ADDR: i32 = 119;
+
+/// Seed used when none is given.
+pub const DEFAULT_SEED: u64 = 0x5e70_1958;
+
+/// A small xorshift64 generator: fast, seedable and the same everywhere.
+/// Not suitable for anything security-related.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Xorshift(u64);
+
+impl Xorshift {
+    /// A generator starting from `seed` (zero, which xorshift cannot
+    /// leave, is replaced by [`DEFAULT_SEED`]).
+    pub fn new(seed: u64) -> Self {
+        Self(if seed == 0 { DEFAULT_SEED } else { seed })
+    }
+
+    /// The next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number below `bound`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    /// A number in `min..=max`.
+    pub fn between(&mut self, min: i64, max: i64) -> i64 {
+        min + self.below((max - min + 1) as u64) as i64
+    }
+}
+
+impl Default for Xorshift {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+/// Memory-mapped source of pseudo-random words and trits.
+#[derive(Debug, Clone)]
+pub struct RandomDevice {
+    seed: u64,
+    rng: Xorshift,
+}
+
+impl RandomDevice {
+    /// A device producing the sequence for `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self { seed, rng: Xorshift::new(seed) }
+    }
+
+    /// The seed the sequence restarts from on reset.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Default for RandomDevice {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl Device for RandomDevice {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn addresses(&self) -> Vec<i32> {
+        vec![RANDOM_WORD_ADDR, RANDOM_TRIT_ADDR]
+    }
+
+    fn read(&mut self, addr: i32) -> Result<Tryte9, DeviceError> {
+        let value = match addr {
+            RANDOM_TRIT_ADDR => self.rng.between(-1, 1),
+            _ => self.rng.between(Tryte9::MIN as i64, Tryte9::MAX as i64),
+        };
+        Ok(Tryte9::from_i32(value as i32))
+    }
+
+    fn write(&mut self, _addr: i32, _value: Tryte9) -> Result<(), DeviceError> {
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.rng = Xorshift::new(self.seed);
+    }
+
+    fn clone_box(&self) -> Box<dyn Device> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::Cpu;
+
+    #[test]
+    fn test_random_reads() {
+        let mut device = RandomDevice::new(7);
+        let trits: Vec<i32> = (0..300).map(|_| device.read(RANDOM_TRIT_ADDR).unwrap().to_i32()).collect();
+        for t in -1..=1 {
+            assert!(trits.iter().filter(|&&x| x == t).count() > 50);
+        }
+        let words: Vec<i32> = (0..100).map(|_| device.read(RANDOM_WORD_ADDR).unwrap().to_i32()).collect();
+        assert!(words.iter().any(|w| w.abs() > 5000));
+
+        device.reset();
+        assert_eq!(device.read(RANDOM_TRIT_ADDR).unwrap().to_i32(), trits[0]);
+        assert_ne!(RandomDevice::new(8).read(RANDOM_WORD_ADDR), RandomDevice::new(7).read(RANDOM_WORD_ADDR));
+    }
+
+    #[test]
+    fn test_program_reads_device() {
+        // Sum ten random trits
+        let program = assemble("
+            LOOP: ADD 119
+                  XCHG N
+                  SUB ONE
+                  JZ DONE
+                  XCHG N
+                  JMP LOOP
+            DONE: XCHG N
+                  HLT
+            N:    DAT 10
+            ONE:  DAT 1
+        ").unwrap();
+        let mut cpu = Cpu::new();
+        cpu.devices.attach(RandomDevice::default()).unwrap();
+        cpu.load_program(&program).unwrap();
+        cpu.run_limited(1000).unwrap();
+        assert!(cpu.is_halted());
+        assert!(cpu.regs.s.to_i64().abs() <= 10);
+    }
+}
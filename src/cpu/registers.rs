@@ -7,102 +7,10 @@
 //! - C: 9-trit program counter
 //! - ω (omega): 1-trit sign register
 
-use crate::ternary::{Trit, Tryte9, Word18};
+pub use crate::ternary::Tryte5;
+use crate::ternary::{ConvertError, Trit, Tryte9, Word18};
 use serde::{Serialize, Deserialize};
 
-/// A 5-trit value for the index register.
-/// Range: -121 to +121
-#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
-pub struct Tryte5 {
-    trits: [Trit; 5],
-}
-
-impl Tryte5 {
-    /// Maximum value: 121 (PPPPP)
-    pub const MAX: i32 = 121;
-    /// Minimum value: -121 (NNNNN)
-    pub const MIN: i32 = -121;
-    
-    /// Create a zero value.
-    pub const fn zero() -> Self {
-        Self { trits: [Trit::O; 5] }
-    }
-    
-    /// Create from an integer.
-    pub fn from_i32(mut value: i32) -> Self {
-        assert!(
-            value >= Self::MIN && value <= Self::MAX,
-            "Value {} out of range for Tryte5 [{}, {}]",
-            value, Self::MIN, Self::MAX
-        );
-        
-        let mut trits = [Trit::O; 5];
-        let negative = value < 0;
-        if negative {
-            value = -value;
-        }
-        
-        for i in 0..5 {
-            let remainder = ((value % 3) + 1) as i8;
-            let (trit, carry) = match remainder {
-                1 => (Trit::O, 0),
-                2 => (Trit::P, 0),
-                3 => (Trit::N, 1),
-                _ => unreachable!(),
-            };
-            trits[i] = trit;
-            value = value / 3 + carry;
-        }
-        
-        let mut result = Self { trits };
-        if negative {
-            result = result.neg();
-        }
-        result
-    }
-    
-    /// Convert to integer.
-    pub fn to_i32(&self) -> i32 {
-        let mut result: i32 = 0;
-        let mut power: i32 = 1;
-        
-        for i in 0..5 {
-            result += self.trits[i].to_i8() as i32 * power;
-            power *= 3;
-        }
-        
-        result
-    }
-    
-    /// Negate.
-    pub fn neg(&self) -> Self {
-        let mut trits = [Trit::O; 5];
-        for i in 0..5 {
-            trits[i] = self.trits[i].neg();
-        }
-        Self { trits }
-    }
-    
-    /// Extend to 9-trit Tryte9 (zero-extended).
-    pub fn to_tryte9(&self) -> Tryte9 {
-        let mut trits = [Trit::O; 9];
-        for i in 0..5 {
-            trits[i] = self.trits[i];
-        }
-        Tryte9::from_trits(trits)
-    }
-}
-
-impl std::fmt::Debug for Tryte5 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "F=")?;
-        for i in (0..5).rev() {
-            write!(f, "{:?}", self.trits[i])?;
-        }
-        write!(f, " ({})", self.to_i32())
-    }
-}
-
 /// The Setun register file.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Registers {
@@ -164,11 +72,11 @@ impl Registers {
     
     /// Increment the program counter by 1.
     /// Returns the old value.
-    pub fn advance_pc(&mut self) -> Tryte9 {
+    #[inline]
+    pub fn advance_pc(&mut self) -> Result<Tryte9, ConvertError> {
         let old = self.c;
-        let new_val = self.c.to_i32() + 1;
-        self.c = Tryte9::from_i32(new_val);
-        old
+        self.c = Tryte9::try_from_i32(self.c.to_i32() + 1)?;
+        Ok(old)
     }
     
     /// Set the program counter to an absolute address.
@@ -181,7 +89,10 @@ impl Registers {
     /// - mode = P (+1): address + F
     /// - mode = O (0): address unchanged  
     /// - mode = N (-1): address - F
-    pub fn effective_address(&self, base_addr: Tryte9, mode: Trit) -> Tryte9 {
+    ///
+    /// Fails if the result does not fit in nine trits.
+    #[inline]
+    pub fn effective_address(&self, base_addr: Tryte9, mode: Trit) -> Result<Tryte9, ConvertError> {
         let base = base_addr.to_i32();
         let f_val = self.f.to_i32();
         
@@ -191,7 +102,7 @@ impl Registers {
             Trit::N => base - f_val,
         };
         
-        Tryte9::from_i32(effective)
+        Tryte9::try_from_i32(effective)
     }
 }
 
@@ -212,6 +123,8 @@ mod tests {
         assert_eq!(Tryte5::from_i32(-42).to_i32(), -42);
         assert_eq!(Tryte5::from_i32(121).to_i32(), 121);
         assert_eq!(Tryte5::from_i32(-121).to_i32(), -121);
+        assert_eq!(Tryte5::try_from(-121), Ok(Tryte5::from_i32(-121)));
+        assert!(Tryte5::try_from_i32(122).is_err());
     }
     
     #[test]
@@ -222,13 +135,16 @@ mod tests {
         let base = Tryte9::from_i32(50);
         
         // Mode O: unchanged
-        assert_eq!(regs.effective_address(base, Trit::O).to_i32(), 50);
+        assert_eq!(regs.effective_address(base, Trit::O).unwrap().to_i32(), 50);
         
         // Mode P: add F
-        assert_eq!(regs.effective_address(base, Trit::P).to_i32(), 60);
+        assert_eq!(regs.effective_address(base, Trit::P).unwrap().to_i32(), 60);
         
         // Mode N: subtract F
-        assert_eq!(regs.effective_address(base, Trit::N).to_i32(), 40);
+        assert_eq!(regs.effective_address(base, Trit::N).unwrap().to_i32(), 40);
+
+        // Past the edge of the address space
+        assert!(regs.effective_address(Tryte9::from_i32(Tryte9::MAX), Trit::P).is_err());
     }
     
     #[test]
@@ -250,8 +166,11 @@ mod tests {
         let mut regs = Registers::new();
         regs.c = Tryte9::from_i32(10);
         
-        let old = regs.advance_pc();
+        let old = regs.advance_pc().unwrap();
         assert_eq!(old.to_i32(), 10);
         assert_eq!(regs.c.to_i32(), 11);
+
+        regs.c = Tryte9::from_i32(Tryte9::MAX);
+        assert!(regs.advance_pc().is_err());
     }
 }
@@ -0,0 +1,141 @@
+//! Cycle-exact device scheduling.
+//!
+//! A [`DeviceBus`](super::DeviceBus) keeps a clock in lock-step with
+//! [`Cpu::cycles`](super::Cpu::cycles). After every instruction the CPU
+//! advances the bus to its new cycle count: each device is ticked once, in
+//! attach order, and then every event that has fallen due is delivered to
+//! [`Device::event`](super::Device::event), in cycle order and, within a
+//! cycle, in the order the events were scheduled. Nothing depends on the
+//! host's clock or on map iteration order, so a replayed run interleaves
+//! devices exactly as the original did.
+//!
+//! Devices schedule events through the [`Schedule`] they are handed when
+//! attached and when an event fires, which makes periodic behaviour such as
+//! a timer or a rotating drum a chain of events:
+//!
+//! ```
+//! use setun::{assemble, Cpu, Tryte9};
+//! use setun::cpu::{Device, DeviceError, Schedule};
+//!
+//! /// Counts periods of 3 cycles; reading address 100 returns the count.
+//! #[derive(Clone, Default)]
+//! struct Timer(i32);
+//!
+//! impl Device for Timer {
+//!     fn name(&self) -> &str { "timer" }
+//!     fn addresses(&self) -> Vec<i32> { vec![100] }
+//!     fn read(&mut self, _addr: i32) -> Result<Tryte9, DeviceError> { Ok(Tryte9::from_i32(self.0)) }
+//!     fn write(&mut self, _addr: i32, _value: Tryte9) -> Result<(), DeviceError> { Ok(()) }
+//!     fn reset(&mut self) { self.0 = 0; }
+//!     fn attached(&mut self, schedule: &mut Schedule<'_>) { schedule.schedule_in(3, 0); }
+//!     fn event(&mut self, _event: u32, schedule: &mut Schedule<'_>) {
+//!         self.0 += 1;
+//!         schedule.schedule_in(3, 0);
+//!     }
+//!     fn clone_box(&self) -> Box<dyn Device> { Box::new(self.clone()) }
+//! }
+//!
+//! let mut cpu = Cpu::new();
+//! cpu.devices.attach(Timer::default()).unwrap();
+//! cpu.load_program(&assemble("NOP\nNOP\nNOP\nNOP\nNOP\nNOP\nLDA 100\nHLT").unwrap()).unwrap();
+//! cpu.run().unwrap();
+//! assert_eq!(cpu.regs.s.to_i64(), 2);
+//! ```
+
+use crate::cpu::device::DeviceId;
+use std::collections::BTreeMap;
+
+/// An event waiting to be delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduledEvent {
+    /// Cycle the event is due.
+    pub cycle: u64,
+    /// Device it is delivered to.
+    pub device: DeviceId,
+    /// Device-defined event number.
+    pub event: u32,
+}
+
+/// The bus clock and its pending events.
+#[derive(Debug, Clone, Default)]
+pub struct Scheduler {
+    now: u64,
+    next_seq: u64,
+    /// Pending events keyed by (cycle, scheduling order).
+    queue: BTreeMap<(u64, u64), (DeviceId, u32)>,
+}
+
+impl Scheduler {
+    /// The current cycle.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Queue `event` for `device` at `cycle`. An event for a cycle
+    /// already reached is due at once.
+    pub fn schedule_at(&mut self, cycle: u64, device: DeviceId, event: u32) {
+        let cycle = cycle.max(self.now);
+        self.queue.insert((cycle, self.next_seq), (device, event));
+        self.next_seq += 1;
+    }
+
+    /// Pending events in delivery order.
+    pub fn pending(&self) -> Vec<ScheduledEvent> {
+        self.queue.iter()
+            .map(|(&(cycle, _), &(device, event))| ScheduledEvent { cycle, device, event })
+            .collect()
+    }
+
+    /// Drop every pending event and go back to cycle 0.
+    pub fn clear(&mut self) {
+        *self = Scheduler::default();
+    }
+
+    /// Move the clock to `cycle`; it never runs backwards.
+    pub(crate) fn set_now(&mut self, cycle: u64) {
+        self.now = self.now.max(cycle);
+    }
+
+    /// Remove and return the next event due by now.
+    pub(crate) fn pop_due(&mut self) -> Option<ScheduledEvent> {
+        let entry = self.queue.first_entry()?;
+        let (cycle, _) = *entry.key();
+        if cycle > self.now {
+            return None;
+        }
+        let (device, event) = entry.remove();
+        Some(ScheduledEvent { cycle, device, event })
+    }
+}
+
+/// A device's handle on the scheduler.
+pub struct Schedule<'a> {
+    device: DeviceId,
+    scheduler: &'a mut Scheduler,
+}
+
+impl<'a> Schedule<'a> {
+    pub(crate) fn new(device: DeviceId, scheduler: &'a mut Scheduler) -> Self {
+        Schedule { device, scheduler }
+    }
+
+    /// The current cycle.
+    pub fn now(&self) -> u64 {
+        self.scheduler.now()
+    }
+
+    /// The device this handle schedules for.
+    pub fn device(&self) -> DeviceId {
+        self.device
+    }
+
+    /// Deliver `event` to this device at `cycle`.
+    pub fn schedule_at(&mut self, cycle: u64, event: u32) {
+        self.scheduler.schedule_at(cycle, self.device, event);
+    }
+
+    /// Deliver `event` to this device `delay` cycles from now.
+    pub fn schedule_in(&mut self, delay: u64, event: u32) {
+        self.schedule_at(self.now().saturating_add(delay), event);
+    }
+}
@@ -0,0 +1,217 @@
+//! Semihosting: host services for guest programs.
+//!
+//! Like ARM semihosting, a [`Semihost`] gives programs a way to ask the
+//! host for things the emulated machine has no device for yet. It is an
+//! [`IsaExtension`] on the free opcode [`SEMIHOST_OPCODE`]: `HOST n` calls
+//! service `n`, passing and returning values in S. The built-in services
+//! are
+//!
+//! | Service | Name  | Effect                                                  |
+//! |---------|-------|---------------------------------------------------------|
+//! | 1       | print | print S in decimal, followed by a newline               |
+//! | 2       | read  | read a line holding a number into S; ω is its sign      |
+//! | 3       | time  | S := host time of day in seconds (UTC), R := milliseconds |
+//! | 4       | exit  | halt, with S as the exit code                           |
+//!
+//! and [`Semihost::register`] adds more. Installing a semihost turns on
+//! [`CpuConfig::experimental`](crate::cpu::CpuConfig::experimental), which
+//! registered opcodes need:
+//!
+//! ```
+//! use setun::asm::assembler::assemble_with_extensions;
+//! use setun::cpu::{Cpu, Semihost};
+//!
+//! let mut cpu = Cpu::new();
+//! let host = Semihost::new();
+//! host.install(&mut cpu).unwrap();
+//! host.feed("20\n");
+//!
+//! let source = "HOST 2\nADD 6\nHOST 1\nLDA 7\nHOST 4\nHLT\nDAT 22\nDAT 3";
+//! cpu.load_program(&assemble_with_extensions(source, &cpu.extensions).unwrap()).unwrap();
+//! cpu.run().unwrap();
+//! assert_eq!(host.take_output(), "42\n");
+//! assert_eq!(host.exit_code(), Some(3));
+//! ```
+//!
+//! Clones of a [`Semihost`] share their state, so keep one to collect the
+//! output and exit code after installing another.
+
+use crate::cpu::console::ConsoleInput;
+use crate::cpu::execute::{Cpu, CpuError, CpuState};
+use crate::cpu::extension::{ExtensionError, IsaExtension};
+use crate::ternary::{parse_number, Word18};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Opcode of the `HOST` instruction.
+pub const SEMIHOST_OPCODE: i8 = -6;
+
+/// Built-in service: print S in decimal.
+pub const PRINT: i32 = 1;
+/// Built-in service: read a number into S.
+pub const READ: i32 = 2;
+/// Built-in service: time of day into S and R.
+pub const TIME: i32 = 3;
+/// Built-in service: halt with exit code S.
+pub const EXIT: i32 = 4;
+
+/// A host service, called with the machine that asked for it.
+pub type HostService = Arc<dyn Fn(&mut Cpu) -> Result<(), CpuError> + Send + Sync>;
+
+#[derive(Default)]
+struct State {
+    input: VecDeque<String>,
+    source: Option<ConsoleInput>,
+    output: String,
+    exit_code: Option<i64>,
+    services: BTreeMap<i32, HostService>,
+}
+
+/// Host services reachable through the `HOST` instruction.
+#[derive(Clone, Default)]
+pub struct Semihost {
+    state: Arc<Mutex<State>>,
+}
+
+impl Semihost {
+    /// A semihost with the built-in services and no input.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A semihost that asks `source` for a line whenever the program
+    /// reads with nothing queued, e.g. a line from stdin.
+    pub fn with_input(source: ConsoleInput) -> Self {
+        let host = Self::new();
+        host.lock().source = Some(source);
+        host
+    }
+
+    /// The `HOST` instruction calling into this semihost, e.g. to
+    /// assemble with before the machine exists.
+    pub fn extension(&self) -> IsaExtension {
+        let host = self.clone();
+        IsaExtension::new(SEMIHOST_OPCODE, "HOST", move |cpu, addr, _| host.call(cpu, addr.to_i32()))
+    }
+
+    /// Register `HOST` on `cpu` and enable experimental opcodes.
+    pub fn install(&self, cpu: &mut Cpu) -> Result<(), ExtensionError> {
+        cpu.extensions.register(self.extension())?;
+        cpu.config.experimental = true;
+        Ok(())
+    }
+
+    /// Add service `number`, replacing a built-in with the same number.
+    pub fn register(&self, number: i32, service: impl Fn(&mut Cpu) -> Result<(), CpuError> + Send + Sync + 'static) {
+        self.lock().services.insert(number, Arc::new(service));
+    }
+
+    /// Queue input lines for the read service.
+    pub fn feed(&self, text: &str) {
+        self.lock().input.extend(text.lines().map(str::to_string));
+    }
+
+    /// Take the text printed since the last call.
+    pub fn take_output(&self) -> String {
+        std::mem::take(&mut self.lock().output)
+    }
+
+    /// The exit code, once the program has called the exit service.
+    pub fn exit_code(&self) -> Option<i64> {
+        self.lock().exit_code
+    }
+
+    /// Run service `number` for `cpu`.
+    fn call(&self, cpu: &mut Cpu, number: i32) -> Result<(), CpuError> {
+        let custom = self.lock().services.get(&number).cloned();
+        if let Some(service) = custom {
+            return service(cpu);
+        }
+        match number {
+            PRINT => {
+                let text = format!("{}\n", cpu.regs.s.to_i64());
+                self.lock().output.push_str(&text);
+            }
+            READ => {
+                let line = self.next_line().ok_or(CpuError::InputExhausted)?;
+                let value = parse_number(line.trim())
+                    .map_err(|e| CpuError::Semihost(format!("cannot read '{}': {}", line.trim(), e)))?;
+                cpu.regs.s = Word18::try_from_i64(value)?;
+                let sign = cpu.regs.s.sign();
+                cpu.regs.set_omega(sign);
+            }
+            TIME => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                cpu.regs.s = Word18::from_i64((now.as_secs() % 86_400) as i64);
+                cpu.regs.r = Word18::from_i64(now.subsec_millis() as i64);
+            }
+            EXIT => {
+                self.lock().exit_code = Some(cpu.regs.s.to_i64());
+                cpu.state = CpuState::Halted;
+            }
+            _ => return Err(CpuError::Semihost(format!("no service {}", number))),
+        }
+        Ok(())
+    }
+
+    fn next_line(&self) -> Option<String> {
+        let mut state = self.lock();
+        if let Some(line) = state.input.pop_front() {
+            return Some(line);
+        }
+        let source = state.source.clone()?;
+        drop(state);
+        source()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl fmt::Debug for Semihost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("Semihost")
+            .field("input", &state.input)
+            .field("output", &state.output)
+            .field("exit_code", &state.exit_code)
+            .field("services", &state.services.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assembler::assemble_with_extensions;
+
+    #[test]
+    fn test_services_and_errors() {
+        let mut cpu = Cpu::new();
+        let host = Semihost::with_input(Arc::new(|| Some("0t+-\n".to_string())));
+        host.install(&mut cpu).unwrap();
+        // A custom service doubling S
+        host.register(10, |cpu| {
+            cpu.regs.s = Word18::from_i64(cpu.regs.s.to_i64() * 2);
+            Ok(())
+        });
+
+        let source = "HOST 2\nHOST 10\nHOST 1\nHOST 3\nHOST 9\nHLT";
+        cpu.load_program(&assemble_with_extensions(source, &cpu.extensions).unwrap()).unwrap();
+        assert!(matches!(cpu.run(), Err(CpuError::Semihost(message)) if message == "no service 9"));
+        assert_eq!(host.take_output(), "4\n");
+        assert!((0..86_400).contains(&cpu.regs.s.to_i64()));
+        assert_eq!(host.exit_code(), None);
+
+        // Without input, reading ends the run
+        let mut cpu = Cpu::new();
+        let host = Semihost::new();
+        host.install(&mut cpu).unwrap();
+        cpu.load_program(&assemble_with_extensions("HOST 2\nHLT", &cpu.extensions).unwrap()).unwrap();
+        assert!(matches!(cpu.run(), Err(CpuError::InputExhausted)));
+        assert!(host.install(&mut cpu).is_err());
+    }
+}
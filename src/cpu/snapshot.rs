@@ -0,0 +1,255 @@
+//! Versioned machine snapshots.
+//!
+//! A snapshot captures the complete CPU state (registers, memory, cycle
+//! count) together with the originally loaded program image, tagged with
+//! a format version so saved sessions can be validated before restoring.
+//!
+//! Every snapshot written carries [`SNAPSHOT_FORMAT`] and
+//! [`SNAPSHOT_VERSION`]. Older versions down to
+//! [`OLDEST_SNAPSHOT_VERSION`] are migrated step by step on load, so a
+//! state saved by an earlier release still restores after the register or
+//! memory structures change; a version outside that range is reported as
+//! such rather than as whatever field happens to fail to parse. When the
+//! format changes, bump [`SNAPSHOT_VERSION`] and add a step to
+//! `MIGRATIONS` that rewrites the previous version's JSON.
+//!
+//! [`Cpu::state_digest`] condenses the same registers and memory into one
+//! 18-trit [digest](crate::ternary::hash::Digest), for checking that two
+//! runs reached the same state without comparing whole snapshots.
+
+use crate::ternary::hash::Digest;
+use crate::ternary::{Tryte9, Word18};
+use crate::cpu::Cpu;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use thiserror::Error;
+
+/// Current snapshot format version.
+pub const SNAPSHOT_VERSION: u32 = 3;
+
+/// Oldest snapshot format version that can still be loaded.
+pub const OLDEST_SNAPSHOT_VERSION: u32 = 1;
+
+/// Value of the `format` field identifying a snapshot.
+pub const SNAPSHOT_FORMAT: &str = "setun-snapshot";
+
+/// Migration steps: entry `i` rewrites version `OLDEST_SNAPSHOT_VERSION + i`
+/// into the next version.
+const MIGRATIONS: [fn(&mut serde_json::Map<String, serde_json::Value>); 2] = [
+    // 1 -> 2: snapshots name their format
+    |snapshot| {
+        snapshot.insert("format".into(), SNAPSHOT_FORMAT.into());
+    },
+    // 2 -> 3: words are written as trit strings; the older {"trits": [...]}
+    // form still deserializes, so nothing needs rewriting
+    |_| {},
+];
+
+/// A serializable capture of a running machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Format identifier, always [`SNAPSHOT_FORMAT`].
+    pub format: String,
+    /// Format version (see [`SNAPSHOT_VERSION`]).
+    pub version: u32,
+    /// The captured CPU (registers, memory, state, cycles).
+    pub cpu: Cpu,
+    /// The program image originally loaded, so a restored session can be reset.
+    #[serde(default)]
+    pub program: Vec<Tryte9>,
+    /// Address `program` was loaded at.
+    #[serde(default)]
+    pub load_at: i32,
+    /// Address execution started at after loading.
+    #[serde(default)]
+    pub entry: i32,
+    /// Memory cells pinned in the debugger, with their names.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pins: BTreeMap<i32, String>,
+}
+
+impl Snapshot {
+    /// Capture the current state of a CPU and its loaded program.
+    pub fn capture(cpu: &Cpu, program: &[Tryte9]) -> Self {
+        Self {
+            format: SNAPSHOT_FORMAT.into(),
+            version: SNAPSHOT_VERSION,
+            cpu: cpu.clone(),
+            program: program.to_vec(),
+            load_at: 0,
+            entry: 0,
+            pins: BTreeMap::new(),
+        }
+    }
+    
+    /// Record where the program was loaded and where it started.
+    pub fn with_layout(mut self, load_at: i32, entry: i32) -> Self {
+        self.load_at = load_at;
+        self.entry = entry;
+        self
+    }
+
+    /// Record the debugger's pinned cells.
+    pub fn with_pins(mut self, pins: BTreeMap<i32, String>) -> Self {
+        self.pins = pins;
+        self
+    }
+
+    /// Serialize to a JSON string.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("snapshot serialization cannot fail")
+    }
+
+    /// Parse a snapshot from JSON, checking the format version first and
+    /// migrating older versions to the current one.
+    pub fn from_json(json: &str) -> Result<Self, SnapshotError> {
+        let value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| SnapshotError::Parse(e.to_string()))?;
+        let serde_json::Value::Object(mut snapshot) = value else {
+            return Err(SnapshotError::Parse("expected a JSON object".into()));
+        };
+
+        let version = snapshot.get("version")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| SnapshotError::Parse("missing version field".into()))?;
+        if let Some(format) = snapshot.get("format") {
+            if format != SNAPSHOT_FORMAT {
+                return Err(SnapshotError::WrongFormat(format.to_string()));
+            }
+        }
+        if version < OLDEST_SNAPSHOT_VERSION as u64 || version > SNAPSHOT_VERSION as u64 {
+            return Err(SnapshotError::UnsupportedVersion {
+                found: version,
+                oldest: OLDEST_SNAPSHOT_VERSION,
+                newest: SNAPSHOT_VERSION,
+            });
+        }
+
+        for migrate in &MIGRATIONS[(version - OLDEST_SNAPSHOT_VERSION as u64) as usize..] {
+            migrate(&mut snapshot);
+        }
+        snapshot.insert("version".into(), SNAPSHOT_VERSION.into());
+
+        serde_json::from_value(serde_json::Value::Object(snapshot))
+            .map_err(|e| SnapshotError::Parse(format!("version {} snapshot: {}", version, e)))
+    }
+    
+    /// Write the snapshot to a JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SnapshotError> {
+        std::fs::write(path, self.to_json()).map_err(|e| SnapshotError::Io(e.to_string()))
+    }
+    
+    /// Read a snapshot from a JSON file, checking the format version.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display())))]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SnapshotError> {
+        let json = std::fs::read_to_string(path).map_err(|e| SnapshotError::Io(e.to_string()))?;
+        Self::from_json(&json)
+    }
+}
+
+/// Errors that can occur when restoring a snapshot.
+#[derive(Debug, Clone, Error)]
+pub enum SnapshotError {
+    #[error("invalid snapshot: {0}")]
+    Parse(String),
+
+    #[error("unsupported snapshot version {found} (this emulator reads versions {oldest} to {newest})")]
+    UnsupportedVersion { found: u64, oldest: u32, newest: u32 },
+
+    #[error("not a Setun snapshot (format {0})")]
+    WrongFormat(String),
+
+    #[error("I/O error: {0}")]
+    Io(String),
+}
+
+impl Cpu {
+    /// Digest of the registers and every memory cell. Cycle counts and
+    /// attached devices are not included.
+    pub fn state_digest(&self) -> Word18 {
+        let mut digest = Digest::new();
+        digest.update(self.regs.s.trits());
+        digest.update(self.regs.r.trits());
+        digest.update(self.regs.f.trits());
+        digest.update(self.regs.c.trits());
+        digest.update_trit(self.regs.omega);
+        for cell in self.mem.as_slice() {
+            digest.update(cell.trits());
+        }
+        digest.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::decode::{encode, Instruction};
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let program = vec![encode(&Instruction::Nop), encode(&Instruction::Hlt)];
+        let mut cpu = Cpu::new();
+        cpu.load_program(&program).unwrap();
+        cpu.step().unwrap();
+        cpu.mem.write(100, Tryte9::from_i32(-42));
+
+        let json = Snapshot::capture(&cpu, &program).to_json();
+        let restored = Snapshot::from_json(&json).unwrap();
+
+        assert_eq!(restored.program, program);
+        assert_eq!(restored.cpu.cycles, 1);
+        assert_eq!(restored.cpu.regs.c.to_i32(), 1);
+        assert_eq!(restored.cpu.mem.read(100).to_i32(), -42);
+        assert_eq!(restored.cpu.state_digest(), cpu.state_digest());
+        assert_ne!(Cpu::new().state_digest(), cpu.state_digest());
+    }
+
+    #[test]
+    fn test_snapshot_file_keeps_layout() {
+        let program = vec![encode(&Instruction::Hlt)];
+        let path = std::env::temp_dir().join(format!("setun-snapshot-{}.json", std::process::id()));
+        let pins = BTreeMap::from([(20, "COUNT".to_string())]);
+        Snapshot::capture(&Cpu::new(), &program).with_layout(-10, -8).with_pins(pins.clone()).save(&path).unwrap();
+        let restored = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((restored.load_at, restored.entry), (-10, -8));
+        assert_eq!(restored.pins, pins);
+        assert_eq!(restored.program, program);
+        assert!(matches!(Snapshot::load(&path), Err(SnapshotError::Io(_))));
+    }
+
+    #[test]
+    fn test_snapshot_rejects_unknown_version() {
+        let json = Snapshot::capture(&Cpu::new(), &[]).to_json()
+            .replacen(&format!("\"version\":{}", SNAPSHOT_VERSION), "\"version\":99", 1);
+
+        assert!(matches!(
+            Snapshot::from_json(&json),
+            Err(SnapshotError::UnsupportedVersion { found: 99, .. })
+        ));
+        assert!(matches!(
+            Snapshot::from_json(&json.replacen("\"version\":99", "\"version\":0", 1)),
+            Err(SnapshotError::UnsupportedVersion { found: 0, .. })
+        ));
+        assert!(matches!(
+            Snapshot::from_json(&json.replacen(SNAPSHOT_FORMAT, "setun-trace", 1)),
+            Err(SnapshotError::WrongFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_migrates_version_1() {
+        let mut cpu = Cpu::new();
+        cpu.mem.write(3, Tryte9::from_i32(12));
+        let mut value = serde_json::to_value(Snapshot::capture(&cpu, &[])).unwrap();
+        let object = value.as_object_mut().unwrap();
+        object.remove("format");
+        object.insert("version".into(), 1.into());
+
+        let restored = Snapshot::from_json(&value.to_string()).unwrap();
+        assert_eq!((restored.version, restored.format.as_str()), (SNAPSHOT_VERSION, SNAPSHOT_FORMAT));
+        assert_eq!(restored.cpu.mem.read(3).to_i32(), 12);
+    }
+}
@@ -0,0 +1,42 @@
+//! Emulated instruction timing.
+//!
+//! The Setun executed short operations (add, load, store, jumps) in
+//! roughly 180 µs and multiplication in roughly 335 µs. These figures
+//! are approximate and intended for comparing programs, not for
+//! cycle-exact reproduction.
+
+use crate::cpu::decode::Instruction;
+
+/// Duration of a short operation, in microseconds.
+pub const SHORT_OP_US: u64 = 180;
+
+/// Duration of a multiplication, in microseconds.
+pub const MUL_US: u64 = 335;
+
+/// Duration of a division, in microseconds (modelled as two multiplies).
+pub const DIV_US: u64 = 2 * MUL_US;
+
+/// Emulated duration of an instruction, in microseconds.
+pub fn instruction_time_us(instr: &Instruction) -> u64 {
+    match instr {
+        Instruction::Mul { .. } => MUL_US,
+        Instruction::Div { .. } => DIV_US,
+        _ => SHORT_OP_US,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ternary::Tryte9;
+    use crate::cpu::decode::AddrMode;
+    
+    #[test]
+    fn test_instruction_times() {
+        let addr = Tryte9::zero();
+        let mode = AddrMode::Direct;
+        assert_eq!(instruction_time_us(&Instruction::Add { addr, mode }), SHORT_OP_US);
+        assert_eq!(instruction_time_us(&Instruction::Mul { addr, mode }), MUL_US);
+        assert!(instruction_time_us(&Instruction::Div { addr, mode }) > MUL_US);
+    }
+}
@@ -0,0 +1,443 @@
+//! Structured execution trace records.
+//!
+//! A [`TraceRecord`] describes one executed instruction: where it was
+//! fetched from, what it decoded to, and the register file afterwards.
+//! Records are plain serializable data so frontends can store, stream,
+//! or replay them without re-running the program.
+//!
+//! [`Cpu::steps`] yields records one at a time, so execution composes with
+//! iterator adapters:
+//!
+//! ```
+//! use setun::{assemble, Cpu};
+//!
+//! let mut cpu = Cpu::new();
+//! cpu.load_program(&assemble("LDA 4\nADD 4\nADD 4\nHLT\nDAT 5").unwrap()).unwrap();
+//! let sums: Vec<i64> = cpu.steps()
+//!     .map_while(Result::ok)
+//!     .filter(|step| step.instruction.mnemonic() == "ADD")
+//!     .map(|step| step.regs.s)
+//!     .collect();
+//! assert_eq!(sums, [10, 15]);
+//! ```
+//!
+//! [`TraceWriter`] writes records as JSON Lines or CSV for offline
+//! analysis.
+
+use crate::ternary::{Trit, Tryte9};
+use crate::cpu::{Cpu, CpuError, Registers};
+use crate::cpu::decode::{decode_for, AddrMode, Instruction};
+use crate::asm::disasm::disassemble_instruction;
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Register values captured at a point in time, in decimal form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisterState {
+    /// Accumulator S.
+    pub s: i64,
+    /// Multiplier register R.
+    pub r: i64,
+    /// Index register F.
+    pub f: i32,
+    /// Program counter C.
+    pub c: i32,
+    /// Sign register ω.
+    pub omega: Trit,
+}
+
+impl RegisterState {
+    /// Capture the current register file.
+    pub fn capture(regs: &Registers) -> Self {
+        Self {
+            s: regs.s.to_i64(),
+            r: regs.r.to_i64(),
+            f: regs.f.to_i32(),
+            c: regs.c.to_i32(),
+            omega: regs.omega,
+        }
+    }
+
+    /// List the registers that differ between `self` and `after`.
+    pub fn diff(&self, after: &RegisterState) -> Vec<RegisterDelta> {
+        let pairs = [
+            ("S", self.s, after.s),
+            ("R", self.r, after.r),
+            ("F", self.f as i64, after.f as i64),
+            ("C", self.c as i64, after.c as i64),
+            ("omega", self.omega.to_i8() as i64, after.omega.to_i8() as i64),
+        ];
+
+        pairs.iter()
+            .filter(|(_, before, after)| before != after)
+            .map(|&(name, before, after)| RegisterDelta { register: name.to_string(), before, after })
+            .collect()
+    }
+}
+
+/// A register whose value changed during one instruction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisterDelta {
+    /// Register name (`S`, `R`, `F`, `C` or `omega`).
+    pub register: String,
+    /// Value before the instruction.
+    pub before: i64,
+    /// Value after the instruction.
+    pub after: i64,
+}
+
+/// Direction of a data memory access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// A data memory access made by an instruction (instruction fetches are not included).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryAccess {
+    /// Read or write.
+    pub kind: AccessKind,
+    /// Memory address accessed.
+    pub addr: i32,
+    /// Value read, or value written.
+    pub value: i32,
+}
+
+/// One executed instruction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceRecord {
+    /// Cycle count after this instruction completed.
+    pub cycle: u64,
+    /// Address the instruction was fetched from.
+    pub pc: i32,
+    /// The raw instruction word.
+    pub raw: Tryte9,
+    /// The decoded instruction.
+    pub instruction: Instruction,
+    /// Registers after execution.
+    pub regs: RegisterState,
+    /// Registers changed by this instruction.
+    #[serde(default)]
+    pub deltas: Vec<RegisterDelta>,
+    /// Data memory reads and writes, in execution order.
+    #[serde(default)]
+    pub accesses: Vec<MemoryAccess>,
+}
+
+impl Cpu {
+    /// Execute a single instruction and return a trace record for it.
+    pub fn step_traced(&mut self) -> Result<TraceRecord, CpuError> {
+        let pc = self.regs.c;
+        let raw = self.mem.read_ternary(pc)?;
+        let before = RegisterState::capture(&self.regs);
+
+        // Resolve the operand address with the registers as they were before execution
+        let operand = decode_for(raw, &self.config).ok()
+            .and_then(|instr| operand_access(&instr))
+            .and_then(|(addr, mode, reads, writes)| {
+                Some((self.operand_address(addr, mode).ok()?, reads, writes))
+            });
+        let read_value = match operand {
+            Some((addr, true, _)) => self.peek_operand(addr).ok(),
+            _ => None,
+        };
+
+        let instruction = self.step()?;
+        let regs = RegisterState::capture(&self.regs);
+
+        let mut accesses = Vec::new();
+        if let (Some((addr, _, _)), Some(value)) = (operand, read_value) {
+            accesses.push(MemoryAccess { kind: AccessKind::Read, addr: addr.to_i32(), value: value.to_i32() });
+        }
+        if let Some((addr, _, true)) = operand {
+            let written = if self.io.is_port(addr) {
+                self.io.last_output()
+            } else {
+                self.mem.read_ternary(addr).ok()
+            };
+            if let Some(value) = written {
+                accesses.push(MemoryAccess { kind: AccessKind::Write, addr: addr.to_i32(), value: value.to_i32() });
+            }
+        }
+
+        Ok(TraceRecord {
+            cycle: self.cycles,
+            pc: pc.to_i32(),
+            raw,
+            instruction,
+            deltas: before.diff(&regs),
+            regs,
+            accesses,
+        })
+    }
+
+    /// Iterate over execution, one trace record per instruction.
+    ///
+    /// The iterator ends after the CPU halts; an error is yielded once and
+    /// then ends it too. Runs are unbounded, so use `take` for programs
+    /// that may not halt.
+    pub fn steps(&mut self) -> Steps<'_> {
+        Steps { cpu: self, failed: false }
+    }
+
+    /// Run for at most `max_cycles` instructions, passing each trace record to `sink`.
+    ///
+    /// Returns the number of instructions executed.
+    pub fn run_traced<F>(&mut self, max_cycles: u64, mut sink: F) -> Result<u64, CpuError>
+    where
+        F: FnMut(TraceRecord),
+    {
+        let start_cycles = self.cycles;
+        let limit = self.cycles + max_cycles;
+
+        while self.is_running() && self.cycles < limit {
+            sink(self.step_traced()?);
+        }
+
+        Ok(self.cycles - start_cycles)
+    }
+}
+
+/// Iterator returned by [`Cpu::steps`].
+#[derive(Debug)]
+pub struct Steps<'a> {
+    cpu: &'a mut Cpu,
+    failed: bool,
+}
+
+impl Steps<'_> {
+    /// The CPU being stepped, e.g. to inspect memory between records.
+    pub fn cpu(&self) -> &Cpu {
+        self.cpu
+    }
+}
+
+impl Iterator for Steps<'_> {
+    type Item = Result<TraceRecord, CpuError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || !self.cpu.is_running() {
+            return None;
+        }
+        let step = self.cpu.step_traced();
+        self.failed = step.is_err();
+        Some(step)
+    }
+}
+
+/// The data operand of an instruction: `(addr, mode, reads, writes)`.
+pub(crate) fn operand_access(instr: &Instruction) -> Option<(Tryte9, AddrMode, bool, bool)> {
+    use Instruction::*;
+
+    match *instr {
+        Add { addr, mode } | Sub { addr, mode } | Mul { addr, mode } | Div { addr, mode }
+        | Lda { addr, mode } | LdaUnsigned { addr, mode } | Ldf { addr, mode }
+        | Ldr { addr, mode } | AddAbs { addr, mode } | SubAbs { addr, mode } | Shm { addr, mode } => {
+            Some((addr, mode, true, false))
+        }
+        Sta { addr, mode } | Stf { addr, mode } | Str { addr, mode } => Some((addr, mode, false, true)),
+        Xchg { addr, mode } => Some((addr, mode, true, true)),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Trace files
+
+/// Output format for [`TraceWriter`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// One JSON object per line.
+    #[default]
+    Jsonl,
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "jsonl" | "json" => Ok(TraceFormat::Jsonl),
+            "csv" => Ok(TraceFormat::Csv),
+            _ => Err(format!("unknown trace format '{}' (expected jsonl or csv)", s)),
+        }
+    }
+}
+
+impl fmt::Display for TraceFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceFormat::Jsonl => write!(f, "jsonl"),
+            TraceFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+/// Column names written as the first CSV row.
+const CSV_HEADER: &str = "cycle,pc,raw,instruction,s,r,f,c,omega,deltas,accesses";
+
+/// Writes trace records to a file or other sink.
+///
+/// In CSV output the instruction column is quoted (indexed operands contain
+/// commas), deltas are written as `S:7>14;C:1>2` and memory accesses as
+/// `R@10=7;W@11=14`.
+pub struct TraceWriter<W: Write> {
+    out: W,
+    format: TraceFormat,
+    header_written: bool,
+}
+
+impl<W: Write> TraceWriter<W> {
+    /// Create a writer producing `format` on `out`.
+    pub fn new(out: W, format: TraceFormat) -> Self {
+        Self { out, format, header_written: false }
+    }
+
+    /// Write one record.
+    pub fn write(&mut self, record: &TraceRecord) -> io::Result<()> {
+        match self.format {
+            TraceFormat::Jsonl => {
+                serde_json::to_writer(&mut self.out, record)?;
+                writeln!(self.out)
+            }
+            TraceFormat::Csv => {
+                if !self.header_written {
+                    writeln!(self.out, "{}", CSV_HEADER)?;
+                    self.header_written = true;
+                }
+
+                let deltas: Vec<String> = record.deltas.iter()
+                    .map(|d| format!("{}:{}>{}", d.register, d.before, d.after))
+                    .collect();
+                let accesses: Vec<String> = record.accesses.iter()
+                    .map(|a| {
+                        let kind = match a.kind {
+                            AccessKind::Read => 'R',
+                            AccessKind::Write => 'W',
+                        };
+                        format!("{}@{}={}", kind, a.addr, a.value)
+                    })
+                    .collect();
+                let regs = &record.regs;
+
+                writeln!(
+                    self.out,
+                    "{},{},{},\"{}\",{},{},{},{},{},{},{}",
+                    record.cycle, record.pc, record.raw, disassemble_instruction(record.raw),
+                    regs.s, regs.r, regs.f, regs.c, regs.omega.to_i8(),
+                    deltas.join(";"), accesses.join(";")
+                )
+            }
+        }
+    }
+
+    /// Flush and return the underlying sink.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::decode::{encode, AddrMode};
+
+    #[test]
+    fn test_run_traced_records_each_step() {
+        let mut cpu = Cpu::new();
+        cpu.mem.write(91, Tryte9::from_i32(7));
+        let program: Vec<Tryte9> = [
+            Instruction::Lda { addr: Tryte9::from_i32(10), mode: AddrMode::Direct },
+            Instruction::Nop,
+            Instruction::Hlt,
+        ].iter().map(encode).collect();
+        cpu.load_program(&program).unwrap();
+
+        let mut records = Vec::new();
+        let executed = cpu.run_traced(100, |r| records.push(r)).unwrap();
+
+        assert_eq!(executed, 3);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].pc, 0);
+        assert_eq!(records[0].regs.s, 7);
+        assert_eq!(records[1].instruction, Instruction::Nop);
+        assert_eq!(records[2].cycle, 3);
+        assert_eq!(records[2].raw, program[2]);
+    }
+
+    #[test]
+    fn test_trace_deltas_and_accesses() {
+        let mut cpu = Cpu::new();
+        cpu.mem.write(91, Tryte9::from_i32(7));
+        let program: Vec<Tryte9> = [
+            Instruction::Lda { addr: Tryte9::from_i32(10), mode: AddrMode::Direct },
+            Instruction::Sta { addr: Tryte9::from_i32(11), mode: AddrMode::Direct },
+        ].iter().map(encode).collect();
+        cpu.load_program(&program).unwrap();
+
+        let lda = cpu.step_traced().unwrap();
+        assert_eq!(lda.accesses, vec![MemoryAccess { kind: AccessKind::Read, addr: 10, value: 7 }]);
+        assert!(lda.deltas.contains(&RegisterDelta { register: "S".into(), before: 0, after: 7 }));
+        assert!(lda.deltas.iter().any(|d| d.register == "C"));
+
+        let sta = cpu.step_traced().unwrap();
+        assert_eq!(sta.accesses, vec![MemoryAccess { kind: AccessKind::Write, addr: 11, value: 7 }]);
+        assert_eq!(sta.deltas.len(), 1);
+    }
+
+    #[test]
+    fn test_steps_iterator() {
+        let mut cpu = Cpu::new();
+        let program: Vec<Tryte9> = [
+            Instruction::Nop,
+            Instruction::Jmp { addr: Tryte9::from_i32(0), mode: AddrMode::Direct },
+        ].iter().map(encode).collect();
+        cpu.load_program(&program).unwrap();
+        let pcs: Vec<i32> = cpu.steps().take(5).map(|step| step.unwrap().pc).collect();
+        assert_eq!(pcs, [0, 1, 0, 1, 0]);
+        assert_eq!(cpu.cycles, 5);
+
+        // An error is yielded once, then the iterator ends
+        let mut cpu = Cpu::new();
+        cpu.load_program(&[encode(&Instruction::Div { addr: Tryte9::from_i32(5), mode: AddrMode::Direct })]).unwrap();
+        let mut steps = cpu.steps();
+        assert!(matches!(steps.next(), Some(Err(CpuError::DivisionByZero))));
+        assert!(steps.next().is_none());
+
+        // A halted CPU yields nothing
+        let mut cpu = Cpu::new();
+        cpu.load_program(&[encode(&Instruction::Hlt)]).unwrap();
+        assert_eq!(cpu.steps().count(), 1);
+        assert_eq!(cpu.steps().count(), 0);
+    }
+
+    #[test]
+    fn test_trace_writer_formats() {
+        let mut cpu = Cpu::new();
+        cpu.load_program(&[encode(&Instruction::Hlt)]).unwrap();
+        let record = cpu.step_traced().unwrap();
+
+        let mut jsonl = TraceWriter::new(Vec::new(), TraceFormat::Jsonl);
+        jsonl.write(&record).unwrap();
+        let out = String::from_utf8(jsonl.finish().unwrap()).unwrap();
+        let parsed: TraceRecord = serde_json::from_str(out.trim()).unwrap();
+        assert_eq!(parsed, record);
+
+        let mut csv = TraceWriter::new(Vec::new(), TraceFormat::Csv);
+        csv.write(&record).unwrap();
+        csv.write(&record).unwrap();
+        let out = String::from_utf8(csv.finish().unwrap()).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].starts_with("1,0,0tOOOOOOOOO,\"HLT\","));
+        assert_eq!("csv".parse::<TraceFormat>().unwrap(), TraceFormat::Csv);
+    }
+}
@@ -0,0 +1,254 @@
+//! Value Change Dump (VCD) waveform export.
+//!
+//! [`VcdWriter`] samples the registers, ω and a chosen set of memory cells
+//! after every instruction and writes the changes as a VCD file, which
+//! waveform viewers such as GTKWave display like a logic analyser
+//! capture.
+//!
+//! Signals are encoded one of two ways (see [`VcdFormat`]):
+//!
+//! - `trits`: each trit becomes two bits in the machine's BCT encoding
+//!   (`01` = P, `00` = O, `10` = N), most significant trit first, so S is
+//!   a 36-bit vector and a memory cell 18 bits.
+//! - `real`: each signal is its decimal value, which viewers can plot as
+//!   an analogue trace.
+//!
+//! Time advances by one unit per instruction, or by emulated microseconds
+//! when the timing model is enabled.
+
+use crate::cpu::Cpu;
+use crate::ternary::{Trit, Tryte9};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// How signal values are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VcdFormat {
+    /// Two BCT bits per trit.
+    #[default]
+    Trits,
+    /// Decimal values as `real` variables.
+    Real,
+}
+
+impl FromStr for VcdFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "trits" | "bits" => Ok(VcdFormat::Trits),
+            "real" => Ok(VcdFormat::Real),
+            _ => Err(format!("unknown VCD format '{}' (expected trits or real)", s)),
+        }
+    }
+}
+
+impl fmt::Display for VcdFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VcdFormat::Trits => write!(f, "trits"),
+            VcdFormat::Real => write!(f, "real"),
+        }
+    }
+}
+
+/// What a signal samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Probe {
+    S,
+    R,
+    F,
+    C,
+    Omega,
+    Cell(i32),
+}
+
+impl Probe {
+    /// Trits of the sampled value, least significant first.
+    fn sample(self, cpu: &Cpu) -> Vec<Trit> {
+        match self {
+            Probe::S => cpu.regs.s.trits().to_vec(),
+            Probe::R => cpu.regs.r.trits().to_vec(),
+            Probe::F => cpu.regs.f.to_tryte9().trits()[..5].to_vec(),
+            Probe::C => cpu.regs.c.trits().to_vec(),
+            Probe::Omega => vec![cpu.regs.omega],
+            Probe::Cell(addr) => {
+                let word = (Tryte9::MIN..=Tryte9::MAX).contains(&addr)
+                    .then(|| cpu.mem.read_ternary(Tryte9::from_i32(addr)).ok())
+                    .flatten()
+                    .unwrap_or_default();
+                word.trits().to_vec()
+            }
+        }
+    }
+}
+
+/// One dumped variable.
+struct Signal {
+    probe: Probe,
+    name: String,
+    id: String,
+    last: Option<String>,
+}
+
+/// Writes CPU state as a VCD waveform.
+pub struct VcdWriter<W: Write> {
+    out: W,
+    format: VcdFormat,
+    signals: Vec<Signal>,
+    last_time: Option<u64>,
+}
+
+/// Short VCD identifier for the `index`th signal (`!`, `"`, ... then two characters).
+fn identifier(mut index: usize) -> String {
+    const FIRST: u8 = b'!';
+    const COUNT: usize = (b'~' - b'!' + 1) as usize;
+    let mut id = String::new();
+    loop {
+        id.push((FIRST + (index % COUNT) as u8) as char);
+        index /= COUNT;
+        if index == 0 {
+            return id;
+        }
+        index -= 1;
+    }
+}
+
+impl<W: Write> VcdWriter<W> {
+    /// Write the VCD header declaring S, R, F, C, ω and the memory cells at `cells`.
+    ///
+    /// Cell addresses outside memory sample as zero.
+    pub fn new(mut out: W, format: VcdFormat, cells: &[i32]) -> io::Result<Self> {
+        let mut probes = vec![Probe::S, Probe::R, Probe::F, Probe::C, Probe::Omega];
+        probes.extend(cells.iter().map(|&addr| Probe::Cell(addr)));
+
+        writeln!(out, "$version setun-emu {} $end", env!("CARGO_PKG_VERSION"))?;
+        writeln!(out, "$timescale 1 us $end")?;
+        writeln!(out, "$scope module setun $end")?;
+        let mut signals = Vec::new();
+        for (index, probe) in probes.into_iter().enumerate() {
+            let (name, trits) = match probe {
+                Probe::S => ("S".to_string(), 18),
+                Probe::R => ("R".to_string(), 18),
+                Probe::F => ("F".to_string(), 5),
+                Probe::C => ("C".to_string(), 9),
+                Probe::Omega => ("omega".to_string(), 1),
+                Probe::Cell(addr) => (format!("mem[{}]", addr), 9),
+            };
+            let id = identifier(index);
+            match format {
+                VcdFormat::Trits => writeln!(out, "$var wire {} {} {} [{}:0] $end", trits * 2, id, name, trits * 2 - 1)?,
+                VcdFormat::Real => writeln!(out, "$var real 64 {} {} $end", id, name)?,
+            }
+            signals.push(Signal { probe, name, id, last: None });
+        }
+        writeln!(out, "$upscope $end")?;
+        writeln!(out, "$enddefinitions $end")?;
+
+        Ok(Self { out, format, signals, last_time: None })
+    }
+
+    /// Names of the dumped signals, in declaration order.
+    pub fn signal_names(&self) -> Vec<&str> {
+        self.signals.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    /// Record the current state; only values that changed are written.
+    pub fn sample(&mut self, cpu: &Cpu) -> io::Result<()> {
+        let time = if cpu.config.timing { cpu.elapsed_us } else { cpu.cycles };
+        let first = self.last_time.is_none();
+        let mut time_written = false;
+
+        for signal in &mut self.signals {
+            let trits = signal.probe.sample(cpu);
+            let value = match self.format {
+                VcdFormat::Trits => {
+                    let bits: String = trits.iter().rev()
+                        .map(|t| match t {
+                            Trit::P => "01",
+                            Trit::O => "00",
+                            Trit::N => "10",
+                        })
+                        .collect();
+                    format!("b{} {}", bits, signal.id)
+                }
+                VcdFormat::Real => {
+                    let value: i64 = trits.iter().rev().fold(0, |acc, &t| acc * 3 + i8::from(t) as i64);
+                    format!("r{} {}", value, signal.id)
+                }
+            };
+            if signal.last.as_ref() == Some(&value) {
+                continue;
+            }
+            if !time_written && self.last_time != Some(time) {
+                writeln!(self.out, "#{}", time)?;
+                if first {
+                    writeln!(self.out, "$dumpvars")?;
+                }
+                time_written = true;
+            }
+            writeln!(self.out, "{}", value)?;
+            signal.last = Some(value);
+        }
+
+        if first {
+            writeln!(self.out, "$end")?;
+        }
+        self.last_time = Some(time);
+        Ok(())
+    }
+
+    /// Flush and return the underlying sink.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    fn dump(format: VcdFormat) -> String {
+        let mut cpu = Cpu::new();
+        cpu.load_program(&assemble("LDA 4\nSTA 5\nNOP\nHLT\nDAT -4").unwrap()).unwrap();
+        let mut vcd = VcdWriter::new(Vec::new(), format, &[5]).unwrap();
+        vcd.sample(&cpu).unwrap();
+        while cpu.is_running() {
+            cpu.step().unwrap();
+            vcd.sample(&cpu).unwrap();
+        }
+        String::from_utf8(vcd.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_trit_waveform() {
+        let text = dump(VcdFormat::Trits);
+        assert!(text.contains("$var wire 36 ! S [35:0] $end"));
+        assert!(text.contains("$var wire 18 & mem[5] [17:0] $end"));
+        assert!(text.starts_with("$version"));
+
+        // -4 = N·3 + N → trits ...ONN
+        let after_lda = text.split("#1\n").nth(1).unwrap();
+        assert!(after_lda.starts_with(&format!("b{}1010 !\n", "00".repeat(16))));
+        // ω = N, and mem[5] takes the value at cycle 2
+        assert!(after_lda.contains("b10 %\n"));
+        assert!(text.split("#2\n").nth(1).unwrap().contains(&format!("b{}1010 &", "00".repeat(7))));
+        // NOP changes only C
+        let after_nop = text.split("#3\n").nth(1).unwrap();
+        assert!(after_nop.split('#').next().unwrap().lines().all(|line| line.ends_with(" $")));
+    }
+
+    #[test]
+    fn test_real_waveform() {
+        let text = dump(VcdFormat::Real);
+        assert!(text.contains("$var real 64 $ C $end"));
+        assert!(text.contains("$dumpvars\nr0 !\n"));
+        assert!(text.contains("#1\nr-4 !\n"));
+        assert_eq!("real".parse::<VcdFormat>(), Ok(VcdFormat::Real));
+        assert_eq!(identifier(0), "!");
+        assert_eq!(identifier(94), "!!");
+    }
+}
@@ -0,0 +1,201 @@
+//! Diagnostics shared by the assembler, linter, TROM loader and CPU.
+//!
+//! Each part of the toolchain keeps its own error type, but all of them
+//! convert to a [`Diagnostic`]: a severity, a stable code naming the kind
+//! of problem, where it is (file, source line or memory address), a
+//! message and any notes. Tools that report problems from more than one
+//! stage render them the same way, as `file:line: warning[code]: message`
+//! text with [`render_text`] or as JSON with [`render_json`]:
+//!
+//! ```
+//! use setun::asm::check;
+//! use setun::diagnostic::{render_text, Severity};
+//!
+//! let diagnostics = check("UNUSED: LDA 5\nHLT").diagnostics("prog.asm");
+//! assert_eq!(diagnostics[0].severity, Severity::Warning);
+//! assert_eq!(diagnostics[0].code, "unused-label");
+//! assert_eq!(
+//!     render_text(&diagnostics),
+//!     "prog.asm:1: warning[unused-label]: label UNUSED is never referenced\n0 error(s), 1 warning(s)\n",
+//! );
+//! ```
+
+use serde::Serialize;
+use std::fmt;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The input cannot be used.
+    Error,
+    /// The input works but is probably not what was meant.
+    Warning,
+    /// Extra information.
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        })
+    }
+}
+
+/// Where a diagnostic points. Every part is optional: assembler
+/// diagnostics have a line, run-time ones an address.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Span {
+    /// File the problem is in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Source line (1-based).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// Memory address.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub addr: Option<i32>,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        parts.extend(self.file.clone());
+        parts.extend(self.line.map(|line| line.to_string()));
+        parts.extend(self.addr.map(|addr| format!("@{}", addr)));
+        f.write_str(&parts.join(":"))
+    }
+}
+
+/// A problem found by some stage of the toolchain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable kebab-case name of the kind of problem, e.g. `undefined-label`.
+    pub code: &'static str,
+    #[serde(flatten)]
+    pub span: Span,
+    pub message: String,
+    /// Further explanation, one sentence each.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    /// A diagnostic with no location or notes.
+    pub fn new(severity: Severity, code: &'static str, message: impl Into<String>) -> Self {
+        Self { severity, code, span: Span::default(), message: message.into(), notes: Vec::new() }
+    }
+
+    /// An error.
+    pub fn error(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, code, message)
+    }
+
+    /// A warning.
+    pub fn warning(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, code, message)
+    }
+
+    /// Place the diagnostic in `file`.
+    pub fn in_file(mut self, file: impl Into<String>) -> Self {
+        self.span.file = Some(file.into());
+        self
+    }
+
+    /// Place the diagnostic on source `line`; 0, which means no line, is ignored.
+    pub fn at_line(mut self, line: usize) -> Self {
+        self.span.line = (line != 0).then_some(line);
+        self
+    }
+
+    /// Place the diagnostic at memory address `addr`.
+    pub fn at_addr(mut self, addr: i32) -> Self {
+        self.span.addr = Some(addr);
+        self
+    }
+
+    /// Add a note.
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Is this an error?
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+/// `span: severity[code]: message`, then an indented line per note.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span.to_string();
+        if !span.is_empty() {
+            write!(f, "{}: ", span)?;
+        }
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)?;
+        for note in &self.notes {
+            write!(f, "\n  = note: {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+/// One diagnostic per line, followed by a count of errors and warnings.
+pub fn render_text(diagnostics: &[Diagnostic]) -> String {
+    let mut out = String::new();
+    for diagnostic in diagnostics {
+        out.push_str(&diagnostic.to_string());
+        out.push('\n');
+    }
+    let (errors, warnings) = counts(diagnostics);
+    out.push_str(&format!("{} error(s), {} warning(s)\n", errors, warnings));
+    out
+}
+
+/// The diagnostics with their counts as a JSON document.
+pub fn render_json(diagnostics: &[Diagnostic]) -> serde_json::Value {
+    let (errors, warnings) = counts(diagnostics);
+    serde_json::json!({ "diagnostics": diagnostics, "errors": errors, "warnings": warnings })
+}
+
+/// Number of errors and of warnings.
+fn counts(diagnostics: &[Diagnostic]) -> (usize, usize) {
+    let count = |severity| diagnostics.iter().filter(|d| d.severity == severity).count();
+    (count(Severity::Error), count(Severity::Warning))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::{assemble_detailed, parse_trom};
+    use crate::cpu::{CpuConfig, CpuError};
+    use crate::session::EmulatorSession;
+
+    #[test]
+    fn test_diagnostics_from_each_stage() {
+        let assembler = assemble_detailed("LDA NOWHERE").unwrap_err().diagnostic();
+        assert_eq!(assembler.to_string(), "1: error[undefined-label]: undefined label on line 1: NOWHERE");
+
+        let trom = parse_trom(";! trom 9\nOOOOOOOOO").unwrap_err().diagnostic().in_file("old.trom");
+        assert_eq!((trom.code, trom.span.file.as_deref()), ("trom-version", Some("old.trom")));
+
+        let mut session = EmulatorSession::with_config(CpuConfig::historical());
+        session.load_source("LDA BIG\nSHL 9\nADD BIG\nADD BIG\nHLT\nBIG: DAT 9841").unwrap();
+        let error: CpuError = session.run(100).error.unwrap();
+        let runtime = session.cpu.diagnostic(&error);
+        assert_eq!(runtime.code, "overflow");
+        assert_eq!(runtime.span.addr, Some(3));
+        assert!(runtime.to_string().starts_with("@3: error[overflow]: arithmetic overflow\n  = note: "));
+
+        let json = render_json(&[assembler, runtime]);
+        assert_eq!(json["errors"], 2);
+        assert_eq!(json["diagnostics"][1]["addr"], 3);
+        assert_eq!(json["diagnostics"][0]["line"], 1);
+        assert!(json["diagnostics"][0].get("notes").is_none());
+    }
+}
@@ -0,0 +1,292 @@
+//! Bounded program equivalence checking.
+//!
+//! Two short programs are equivalent over a set of input cells if, for
+//! every combination of input values, both halt with the same S and R and
+//! the same memory. The checker runs both programs on each combination
+//! and stops at the first one where they disagree, so a pass is a proof
+//! for the enumerated inputs, not a sample:
+//!
+//! ```
+//! use setun::asm::assemble;
+//! use setun::equiv::{check_equivalence, EquivOptions};
+//!
+//! // The same doubling, with and without a redundant NOP
+//! let a = assemble("LDA 10\nADD 10\nHLT").unwrap();
+//! let b = assemble("LDA 10\nADD 10\nNOP\nHLT").unwrap();
+//! let options = EquivOptions { inputs: vec!["10=-40..40".parse().unwrap()], ..Default::default() };
+//!
+//! let report = check_equivalence(&a, &b, &options).unwrap();
+//! assert!(report.equivalent());
+//! assert_eq!(report.cases, 81);
+//! ```
+//!
+//! Memory cells holding either program are skipped, since the code itself
+//! differs; results kept in `DAT` cells inside a program are compared
+//! when listed in [`EquivOptions::outputs`]. Both programs are loaded at
+//! address 0 on a default machine.
+
+use crate::cpu::{Cpu, CpuState};
+use crate::ternary::Tryte9;
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Cycle limit for each run when none is given.
+pub const DEFAULT_MAX_CYCLES: u64 = 10_000;
+
+/// Number of input combinations checked at most when no limit is given.
+pub const DEFAULT_MAX_CASES: u64 = 1_000_000;
+
+/// A memory cell enumerated over a range of values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputCell {
+    /// Address of the cell.
+    pub addr: i32,
+    /// Smallest value tried.
+    pub min: i32,
+    /// Largest value tried.
+    pub max: i32,
+}
+
+impl InputCell {
+    /// Number of values tried.
+    pub fn count(&self) -> u64 {
+        (self.max - self.min + 1) as u64
+    }
+}
+
+impl FromStr for InputCell {
+    type Err = String;
+
+    /// Parse `addr` (every value of a cell) or `addr=min..max`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, range) = match s.split_once('=') {
+            Some((addr, range)) => (addr, Some(range)),
+            None => (s, None),
+        };
+        let number = |text: &str| text.trim().parse::<i32>().map_err(|_| format!("invalid number '{}' in input '{}'", text.trim(), s));
+        let addr = number(addr)?;
+        let (min, max) = match range {
+            Some(range) => {
+                let (min, max) = range.split_once("..").ok_or_else(|| format!("expected addr=min..max, found '{}'", s))?;
+                (number(min)?, number(max)?)
+            }
+            None => (Tryte9::MIN, Tryte9::MAX),
+        };
+        if min > max || min < Tryte9::MIN || max > Tryte9::MAX {
+            return Err(format!("input range {}..{} must be ascending and within ±{}", min, max, Tryte9::MAX));
+        }
+        Ok(Self { addr, min, max })
+    }
+}
+
+/// What to enumerate and compare.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EquivOptions {
+    /// Cells enumerated over their ranges.
+    pub inputs: Vec<InputCell>,
+    /// Cells inside the programs that are compared as well.
+    pub outputs: Vec<i32>,
+    /// Cycle limit for each run.
+    pub max_cycles: u64,
+    /// Refuse to check more input combinations than this.
+    pub max_cases: u64,
+}
+
+impl Default for EquivOptions {
+    fn default() -> Self {
+        Self {
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            max_cycles: DEFAULT_MAX_CYCLES,
+            max_cases: DEFAULT_MAX_CASES,
+        }
+    }
+}
+
+/// One place where the two programs ended differently.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Difference {
+    /// What differs: `S`, `R`, a cell like `[12]`, or `outcome`.
+    pub what: String,
+    /// Final value in the first program.
+    pub a: String,
+    /// Final value in the second program.
+    pub b: String,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} vs {}", self.what, self.a, self.b)
+    }
+}
+
+/// Input values on which the programs disagree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counterexample {
+    /// Address and value of each input cell.
+    pub inputs: Vec<(i32, i32)>,
+    /// Everything that ended differently.
+    pub differences: Vec<Difference>,
+}
+
+/// Result of [`check_equivalence`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EquivReport {
+    /// Input combinations checked.
+    pub cases: u64,
+    /// The first combination the programs disagree on, if any.
+    pub counterexample: Option<Counterexample>,
+}
+
+impl EquivReport {
+    /// Whether the programs agreed on every input combination.
+    pub fn equivalent(&self) -> bool {
+        self.counterexample.is_none()
+    }
+}
+
+/// Errors that prevent a check from running.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EquivError {
+    #[error("{cases} input combinations exceed the limit of {limit}; narrow the input ranges")]
+    TooManyCases { cases: u128, limit: u64 },
+
+    #[error("cell {0} is outside memory")]
+    InvalidCell(i32),
+
+    #[error("program {which} does not fit in memory")]
+    ProgramTooLarge { which: char },
+}
+
+/// How a run ended.
+#[derive(Debug, PartialEq, Eq)]
+struct Outcome {
+    status: String,
+    s: i64,
+    r: i64,
+    cells: Vec<(i32, i32)>,
+}
+
+/// Check that `a` and `b` end the same way for every combination of input values.
+pub fn check_equivalence(a: &[Tryte9], b: &[Tryte9], options: &EquivOptions) -> Result<EquivReport, EquivError> {
+    let cases = options.inputs.iter().map(|c| c.count() as u128).product::<u128>();
+    if cases > options.max_cases as u128 {
+        return Err(EquivError::TooManyCases { cases, limit: options.max_cases });
+    }
+
+    let mem = Cpu::new().mem;
+    for addr in options.inputs.iter().map(|c| c.addr).chain(options.outputs.iter().copied()) {
+        if addr < mem.min_addr() || addr > mem.max_addr() {
+            return Err(EquivError::InvalidCell(addr));
+        }
+    }
+    for (which, program) in [('a', a), ('b', b)] {
+        if program.len() as i32 > mem.max_addr() + 1 {
+            return Err(EquivError::ProgramTooLarge { which });
+        }
+    }
+
+    // Cells compared: everything outside both programs, plus the outputs
+    let image = a.len().max(b.len()) as i32;
+    let compared: Vec<i32> = (mem.min_addr()..=mem.max_addr())
+        .filter(|addr| !(0..image).contains(addr) || options.outputs.contains(addr))
+        .collect();
+
+    let mut values: Vec<i32> = options.inputs.iter().map(|c| c.min).collect();
+    let mut checked = 0;
+    loop {
+        checked += 1;
+        let inputs: Vec<(i32, i32)> = options.inputs.iter().map(|c| c.addr).zip(values.iter().copied()).collect();
+        let first = run(a, &inputs, &compared, options.max_cycles);
+        let second = run(b, &inputs, &compared, options.max_cycles);
+        if first != second {
+            let differences = differences(&first, &second);
+            return Ok(EquivReport { cases: checked, counterexample: Some(Counterexample { inputs, differences }) });
+        }
+
+        // Advance the odometer; done once every input has wrapped
+        let mut position = 0;
+        loop {
+            let Some(cell) = options.inputs.get(position) else {
+                return Ok(EquivReport { cases: checked, counterexample: None });
+            };
+            if values[position] < cell.max {
+                values[position] += 1;
+                break;
+            }
+            values[position] = cell.min;
+            position += 1;
+        }
+    }
+}
+
+/// Run `program` with the input cells set.
+fn run(program: &[Tryte9], inputs: &[(i32, i32)], compared: &[i32], max_cycles: u64) -> Outcome {
+    let mut cpu = Cpu::new();
+    cpu.load_program(program).expect("program size checked");
+    for &(addr, value) in inputs {
+        cpu.mem.write_ternary(Tryte9::from_i32(addr), Tryte9::from_i32(value)).expect("input cell checked");
+    }
+    let status = match cpu.run_limited(max_cycles) {
+        Err(e) => format!("error: {}", e),
+        Ok(_) if cpu.state == CpuState::Running => format!("no halt within {} cycles", max_cycles),
+        Ok(_) => "halted".into(),
+    };
+    let cells = compared
+        .iter()
+        .map(|&addr| (addr, cpu.mem.read_ternary(Tryte9::from_i32(addr)).map(|v| v.to_i32()).unwrap_or(0)))
+        .collect();
+    Outcome { status, s: cpu.regs.s.to_i64(), r: cpu.regs.r.to_i64(), cells }
+}
+
+/// List what differs between two outcomes.
+fn differences(a: &Outcome, b: &Outcome) -> Vec<Difference> {
+    let mut found = Vec::new();
+    let mut differ = |what: String, x: String, y: String| {
+        if x != y {
+            found.push(Difference { what, a: x, b: y });
+        }
+    };
+    differ("outcome".into(), a.status.clone(), b.status.clone());
+    differ("S".into(), a.s.to_string(), b.s.to_string());
+    differ("R".into(), a.r.to_string(), b.r.to_string());
+    for (&(addr, x), &(_, y)) in a.cells.iter().zip(&b.cells) {
+        differ(format!("[{}]", addr), x.to_string(), y.to_string());
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    #[test]
+    fn test_finds_counterexample() {
+        // Absolute value, and one that forgets to negate
+        let a = assemble("LDA 20\nJGT DONE\nLDA ZERO\nSUB 20\nDONE: HLT\nZERO: DAT 0").unwrap();
+        let b = assemble("LDA 20\nJGT DONE\nLDA ZERO\nADD 20\nDONE: HLT\nZERO: DAT 0").unwrap();
+        let options = EquivOptions { inputs: vec!["20=-5..5".parse().unwrap()], ..Default::default() };
+        assert!(check_equivalence(&a, &a, &options).unwrap().equivalent());
+
+        let report = check_equivalence(&a, &b, &options).unwrap();
+        let counterexample = report.counterexample.unwrap();
+        assert_eq!(counterexample.inputs, vec![(20, -5)]);
+        assert_eq!(counterexample.differences, vec![Difference { what: "S".into(), a: "5".into(), b: "-5".into() }]);
+    }
+
+    #[test]
+    fn test_limits_and_parsing() {
+        assert_eq!("7".parse::<InputCell>().unwrap().count(), 19683);
+        assert_eq!("-3=-1..1".parse(), Ok(InputCell { addr: -3, min: -1, max: 1 }));
+        assert!("3=5..1".parse::<InputCell>().is_err());
+
+        let program = assemble("HLT").unwrap();
+        let options = EquivOptions { inputs: vec!["10".parse().unwrap(), "11".parse().unwrap()], ..Default::default() };
+        assert!(matches!(check_equivalence(&program, &program, &options), Err(EquivError::TooManyCases { .. })));
+        let options = EquivOptions { outputs: vec![500], ..Default::default() };
+        assert_eq!(check_equivalence(&program, &program, &options), Err(EquivError::InvalidCell(500)));
+    }
+}
@@ -0,0 +1,163 @@
+//! Typed event stream for message-driven frontends.
+//!
+//! [`EmulatorSession::execute`] hands each step to a callback, which suits
+//! frontends that drive the machine themselves. GUI toolkits built around
+//! a message loop (egui, iced) would rather receive messages: an
+//! [`EmulatorEvents`] runs a session and sends an [`EmulatorEvent`] over a
+//! std channel for every step, memory write, teletype word, breakpoint
+//! and halt, finishing with [`EmulatorEvent::Finished`]. Run it on a
+//! worker thread and drain the receiver from the UI thread:
+//!
+//! ```
+//! use setun::events::{EmulatorEvent, EmulatorEvents};
+//! use setun::session::EmulatorSession;
+//!
+//! let mut session = EmulatorSession::new();
+//! session.cpu.io.enable();
+//! session.load_source("LDA 5\nSTA 121\nSTA 6\nHLT\nNOP\nDAT 7").unwrap();
+//!
+//! let (events, receiver) = EmulatorEvents::channel();
+//! let worker = std::thread::spawn(move || {
+//!     events.without_steps().run(&mut session, 100);
+//!     session
+//! });
+//! let received: Vec<EmulatorEvent> = receiver.iter().collect();
+//! worker.join().unwrap();
+//!
+//! assert!(matches!(received[0], EmulatorEvent::Output(word) if word.to_i32() == 7));
+//! assert!(matches!(received[1], EmulatorEvent::MemoryWrite { addr: 6, .. }));
+//! assert!(matches!(received.last(), Some(EmulatorEvent::Finished { executed: 4, .. })));
+//! ```
+//!
+//! The run stops early once the receiver is dropped, so closing a window
+//! also ends its emulation thread.
+
+use crate::cpu::{AccessKind, StopReason, TraceRecord, TELETYPE_ADDR};
+use crate::session::{EmulatorSession, RunSummary};
+use crate::ternary::Tryte9;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Something that happened while a session ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmulatorEvent {
+    /// An instruction executed.
+    Step(Box<TraceRecord>),
+    /// An instruction stored a word in memory.
+    MemoryWrite { addr: i32, value: Tryte9 },
+    /// A word was written to the teletype.
+    Output(Tryte9),
+    /// The next instruction is at a breakpoint.
+    BreakpointHit { addr: i32 },
+    /// The machine halted after `cycles` cycles.
+    Halt { cycles: u64 },
+    /// The run ended; always the last event of a run.
+    Finished { executed: u64, stop_reason: StopReason, error: Option<String> },
+}
+
+/// The sending half of an event stream.
+#[derive(Debug, Clone)]
+pub struct EmulatorEvents {
+    sender: Sender<EmulatorEvent>,
+    steps: bool,
+}
+
+impl EmulatorEvents {
+    /// Wrap an existing sender.
+    pub fn new(sender: Sender<EmulatorEvent>) -> Self {
+        Self { sender, steps: true }
+    }
+
+    /// A new event stream and its receiver.
+    pub fn channel() -> (Self, Receiver<EmulatorEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        (Self::new(sender), receiver)
+    }
+
+    /// Leave out [`EmulatorEvent::Step`], which is by far the most
+    /// frequent event and often not needed while running freely.
+    pub fn without_steps(mut self) -> Self {
+        self.steps = false;
+        self
+    }
+
+    /// Run `session` like [`EmulatorSession::run`], sending events as it
+    /// goes. Stops early, reporting [`StopReason::CycleLimit`], if the
+    /// receiver has been dropped.
+    pub fn run(&self, session: &mut EmulatorSession, limit: u64) -> RunSummary {
+        let mut executed = 0;
+        let mut connected = true;
+        let summary = loop {
+            let mut step = session.execute(1, true, |cpu, record| {
+                connected &= self.send_step(record, cpu.io.enabled);
+            });
+            executed += step.executed;
+            if step.stop_reason != StopReason::CycleLimit || executed >= limit || !connected {
+                step.executed = executed;
+                break step;
+            }
+        };
+
+        match summary.stop_reason {
+            StopReason::Breakpoint => self.send(EmulatorEvent::BreakpointHit { addr: session.cpu.regs.c.to_i32() }),
+            StopReason::Halted if session.cpu.is_halted() => self.send(EmulatorEvent::Halt { cycles: session.cpu.cycles }),
+            _ => true,
+        };
+        self.send(EmulatorEvent::Finished {
+            executed: summary.executed,
+            stop_reason: summary.stop_reason,
+            error: summary.error.as_ref().map(|e| e.to_string()),
+        });
+        summary
+    }
+
+    /// Send the events for one executed instruction; `false` once the
+    /// receiver is gone.
+    fn send_step(&self, record: TraceRecord, io_enabled: bool) -> bool {
+        let mut connected = true;
+        for access in record.accesses.iter().filter(|a| a.kind == AccessKind::Write) {
+            let value = Tryte9::from_i32(access.value);
+            connected &= self.send(if io_enabled && access.addr == TELETYPE_ADDR {
+                EmulatorEvent::Output(value)
+            } else {
+                EmulatorEvent::MemoryWrite { addr: access.addr, value }
+            });
+        }
+        if self.steps {
+            connected &= self.send(EmulatorEvent::Step(Box::new(record)));
+        }
+        connected
+    }
+
+    fn send(&self, event: EmulatorEvent) -> bool {
+        self.sender.send(event).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_breakpoints_and_disconnect() {
+        let mut session = EmulatorSession::new();
+        session.load_source("NOP\nNOP\nNOP\nHLT").unwrap();
+        session.set_breakpoint(2);
+
+        let (events, receiver) = EmulatorEvents::channel();
+        let summary = events.run(&mut session, 100);
+        assert_eq!(summary.stop_reason, StopReason::Breakpoint);
+        let received: Vec<EmulatorEvent> = receiver.try_iter().collect();
+        assert_eq!(received.len(), 4);
+        assert!(matches!(&received[1], EmulatorEvent::Step(record) if record.pc == 1));
+        assert_eq!(received[2], EmulatorEvent::BreakpointHit { addr: 2 });
+
+        // The limit counts across the single steps
+        session.reset().unwrap();
+        session.clear_breakpoints();
+        assert_eq!(events.run(&mut session, 2).executed, 2);
+
+        drop(receiver);
+        session.reset().unwrap();
+        assert_eq!(events.run(&mut session, 100).executed, 1);
+    }
+}
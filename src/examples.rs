@@ -0,0 +1,85 @@
+//! Sample programs bundled with the emulator.
+//!
+//! The sources live in the repository's `examples/` directory and are
+//! embedded at compile time, so a fresh install always has something
+//! to run.
+
+/// A bundled example program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Example {
+    /// Short name used on the command line.
+    pub name: &'static str,
+    /// One-line description.
+    pub description: &'static str,
+    /// Assembly source.
+    pub source: &'static str,
+}
+
+/// All bundled examples, in suggested reading order.
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "counting",
+        description: "Counting loop from 0 to 10",
+        source: include_str!("../examples/counting.asm"),
+    },
+    Example {
+        name: "shift-multiply",
+        description: "Multiplication by 13 using ternary shifts and additions",
+        source: include_str!("../examples/shift_multiply.asm"),
+    },
+    Example {
+        name: "fibonacci",
+        description: "Iterative Fibonacci numbers",
+        source: include_str!("../examples/fibonacci.asm"),
+    },
+    Example {
+        name: "table-lookup",
+        description: "Indexed table lookup with the F register",
+        source: include_str!("../examples/table_lookup.asm"),
+    },
+    Example {
+        name: "io-demo",
+        description: "Running totals from an input buffer to an output buffer",
+        source: include_str!("../examples/io_demo.asm"),
+    },
+    Example {
+        name: "checksum",
+        description: "Ternary checksum of a table with shifts and additions",
+        source: include_str!("../examples/checksum.asm"),
+    },
+];
+
+/// Look up a bundled example by name.
+pub fn find(name: &str) -> Option<&'static Example> {
+    EXAMPLES.iter().find(|e| e.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, Cpu};
+
+    #[test]
+    fn test_examples_run_to_completion() {
+        let expected = [
+            ("counting", 10),
+            ("shift-multiply", 91),
+            ("fibonacci", 34),
+            ("table-lookup", 49),
+            ("io-demo", 15),
+            ("checksum", -1186),
+        ];
+        assert_eq!(EXAMPLES.len(), expected.len());
+
+        for (name, result) in expected {
+            let example = find(name).unwrap();
+            let program = assemble(example.source).unwrap();
+            let mut cpu = Cpu::new();
+            cpu.load_program(&program).unwrap();
+            cpu.run_limited(10_000).unwrap();
+
+            assert!(cpu.is_halted(), "{} did not halt", name);
+            assert_eq!(cpu.regs.s.to_i64(), result, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,506 @@
+//! C ABI for embedding the emulator.
+//!
+//! Built with the `ffi` feature; the `cdylib` then exports the functions
+//! declared in `include/setun.h`. A CPU is an opaque [`SetunCpu`] handle
+//! created by [`setun_cpu_new`] and released with [`setun_cpu_free`].
+//! Functions that can fail return a [`SetunStatus`]; the message for the
+//! last failure on a handle is available from [`setun_cpu_last_error`].
+//!
+//! ```c
+//! SetunCpu *cpu = setun_cpu_new();
+//! setun_cpu_load_asm(cpu, "LDA 3\nADD 3\nHLT\nDAT 21");
+//! setun_cpu_run(cpu, 1000, NULL);
+//! int64_t s = setun_cpu_get_register(cpu, SETUN_REG_S);   /* 42 */
+//! setun_cpu_free(cpu);
+//! ```
+//!
+//! I/O uses the tape reader and teletype ports (see [`crate::cpu::io`]).
+//! Instead of feeding the tape up front, a host can install callbacks with
+//! [`setun_cpu_set_io`]: the input callback is asked for a word whenever
+//! the tape runs dry, and the output callback receives every word the
+//! program prints.
+
+use crate::{Cpu, CpuState, Tryte9, Word18};
+use crate::asm::assembler::assemble;
+use crate::cpu::CpuError;
+use crate::cpu::registers::Tryte5;
+use crate::ternary::Trit;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::ptr;
+
+/// Result of an FFI call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetunStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// The CPU is halted (returned by step/run once HLT has executed).
+    Halted = 1,
+    /// A required pointer was null.
+    NullPointer = -1,
+    /// An argument was out of range or not valid UTF-8.
+    InvalidArgument = -2,
+    /// Assembly source had errors.
+    AssemblyError = -3,
+    /// A memory address was outside memory.
+    MemoryError = -4,
+    /// The CPU stopped on an error.
+    CpuError = -5,
+}
+
+/// Register selector for [`setun_cpu_get_register`] and [`setun_cpu_set_register`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetunRegister {
+    /// Accumulator S (18 trits).
+    S = 0,
+    /// Multiplier register R (18 trits).
+    R = 1,
+    /// Index register F (5 trits).
+    F = 2,
+    /// Program counter C (an address).
+    C = 3,
+    /// Sign register ω (-1, 0 or 1).
+    Omega = 4,
+}
+
+/// Supplies the next input word. Return `true` after storing it in `*word`,
+/// or `false` if there is no more input.
+pub type SetunInputFn = extern "C" fn(user_data: *mut c_void, word: *mut i32) -> bool;
+
+/// Receives a word written to the teletype.
+pub type SetunOutputFn = extern "C" fn(user_data: *mut c_void, word: i32);
+
+/// An emulated CPU owned by C code.
+pub struct SetunCpu {
+    cpu: Cpu,
+    input: Option<SetunInputFn>,
+    output: Option<SetunOutputFn>,
+    user_data: *mut c_void,
+    last_error: CString,
+}
+
+impl SetunCpu {
+    fn fail(&mut self, status: SetunStatus, message: impl ToString) -> SetunStatus {
+        self.last_error = CString::new(message.to_string().replace('\0', " "))
+            .expect("interior NULs were removed");
+        status
+    }
+
+    /// Execute one instruction, asking the input callback for words as needed.
+    fn step(&mut self) -> SetunStatus {
+        if self.cpu.state == CpuState::Halted {
+            return SetunStatus::Halted;
+        }
+        let pc = self.cpu.regs.c;
+        let result = match self.cpu.step() {
+            Err(CpuError::InputExhausted) => match self.request_input() {
+                Some(word) => {
+                    // Nothing was changed except the PC, so retry the instruction
+                    self.cpu.regs.c = pc;
+                    self.cpu.io.feed([word]);
+                    self.cpu.step()
+                }
+                None => Err(CpuError::InputExhausted),
+            },
+            result => result,
+        };
+        self.flush_output();
+
+        match result {
+            Ok(_) if self.cpu.is_halted() => SetunStatus::Halted,
+            Ok(_) => SetunStatus::Ok,
+            Err(e) => self.fail(SetunStatus::CpuError, e),
+        }
+    }
+
+    fn request_input(&mut self) -> Option<Tryte9> {
+        let input = self.input?;
+        let mut word = 0;
+        if !input(self.user_data, &mut word) {
+            return None;
+        }
+        (Tryte9::MIN..=Tryte9::MAX).contains(&word).then(|| Tryte9::from_i32(word))
+    }
+
+    fn flush_output(&mut self) {
+        if let Some(output) = self.output {
+            for word in self.cpu.io.take_output() {
+                output(self.user_data, word.to_i32());
+            }
+        }
+    }
+}
+
+/// Borrow the CPU behind a handle, returning `NullPointer` if it is null.
+macro_rules! handle {
+    ($cpu:expr) => {
+        match unsafe { $cpu.as_mut() } {
+            Some(cpu) => cpu,
+            None => return SetunStatus::NullPointer,
+        }
+    };
+}
+
+/// Create a CPU with I/O ports enabled. Free it with [`setun_cpu_free`].
+#[no_mangle]
+pub extern "C" fn setun_cpu_new() -> *mut SetunCpu {
+    let mut cpu = Cpu::new();
+    cpu.io.enable();
+    Box::into_raw(Box::new(SetunCpu {
+        cpu,
+        input: None,
+        output: None,
+        user_data: ptr::null_mut(),
+        last_error: CString::default(),
+    }))
+}
+
+/// Destroy a CPU created by [`setun_cpu_new`].
+///
+/// # Safety
+/// `cpu` must be null or a handle from [`setun_cpu_new`] that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_free(cpu: *mut SetunCpu) {
+    if !cpu.is_null() {
+        drop(unsafe { Box::from_raw(cpu) });
+    }
+}
+
+/// Reset registers, memory and ports (callbacks are kept).
+///
+/// # Safety
+/// `cpu` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_reset(cpu: *mut SetunCpu) -> SetunStatus {
+    let handle = handle!(cpu);
+    handle.cpu.reset();
+    handle.cpu.io = Default::default();
+    handle.cpu.io.enable();
+    SetunStatus::Ok
+}
+
+/// Reset the CPU and load `len` words (decimal values) at address 0.
+///
+/// # Safety
+/// `cpu` must be null or a live handle, and `words` must point to `len`
+/// readable `int32_t`s.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_load(cpu: *mut SetunCpu, words: *const i32, len: usize) -> SetunStatus {
+    let handle = handle!(cpu);
+    if words.is_null() {
+        return SetunStatus::NullPointer;
+    }
+    let words = unsafe { std::slice::from_raw_parts(words, len) };
+    if let Some(bad) = words.iter().find(|w| !(Tryte9::MIN..=Tryte9::MAX).contains(*w)) {
+        return handle.fail(SetunStatus::InvalidArgument, format!("word {} does not fit in 9 trits", bad));
+    }
+    let program: Vec<Tryte9> = words.iter().map(|&w| Tryte9::from_i32(w)).collect();
+    load(handle, &program)
+}
+
+/// Reset the CPU, assemble NUL-terminated `source` and load the result.
+///
+/// # Safety
+/// `cpu` must be null or a live handle, and `source` must be null or a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_load_asm(cpu: *mut SetunCpu, source: *const c_char) -> SetunStatus {
+    let handle = handle!(cpu);
+    if source.is_null() {
+        return SetunStatus::NullPointer;
+    }
+    let Ok(source) = unsafe { CStr::from_ptr(source) }.to_str() else {
+        return handle.fail(SetunStatus::InvalidArgument, "source is not valid UTF-8");
+    };
+    match assemble(source) {
+        Ok(program) => load(handle, &program),
+        Err(e) => handle.fail(SetunStatus::AssemblyError, e),
+    }
+}
+
+fn load(handle: &mut SetunCpu, program: &[Tryte9]) -> SetunStatus {
+    handle.cpu.reset();
+    handle.cpu.io = Default::default();
+    handle.cpu.io.enable();
+    match handle.cpu.load_program(program) {
+        Ok(()) => SetunStatus::Ok,
+        Err(e) => handle.fail(SetunStatus::MemoryError, e),
+    }
+}
+
+/// Execute one instruction.
+///
+/// # Safety
+/// `cpu` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_step(cpu: *mut SetunCpu) -> SetunStatus {
+    handle!(cpu).step()
+}
+
+/// Run until halt, an error, or `max_cycles` instructions. The number of
+/// instructions executed is stored in `*executed` unless it is null.
+///
+/// # Safety
+/// `cpu` must be null or a live handle, and `executed` must be null or
+/// point to a writable `uint64_t`.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_run(cpu: *mut SetunCpu, max_cycles: u64, executed: *mut u64) -> SetunStatus {
+    let handle = handle!(cpu);
+    let start = handle.cpu.cycles;
+    let mut status = SetunStatus::Ok;
+    while handle.cpu.cycles - start < max_cycles {
+        status = handle.step();
+        if status != SetunStatus::Ok {
+            break;
+        }
+    }
+    if let Some(executed) = unsafe { executed.as_mut() } {
+        *executed = handle.cpu.cycles - start;
+    }
+    status
+}
+
+/// Read a register. Returns 0 if `cpu` is null.
+///
+/// # Safety
+/// `cpu` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_get_register(cpu: *const SetunCpu, reg: SetunRegister) -> i64 {
+    let Some(handle) = (unsafe { cpu.as_ref() }) else {
+        return 0;
+    };
+    let regs = &handle.cpu.regs;
+    match reg {
+        SetunRegister::S => regs.s.to_i64(),
+        SetunRegister::R => regs.r.to_i64(),
+        SetunRegister::F => regs.f.to_i32() as i64,
+        SetunRegister::C => regs.c.to_i32() as i64,
+        SetunRegister::Omega => i8::from(regs.omega) as i64,
+    }
+}
+
+/// Write a register.
+///
+/// # Safety
+/// `cpu` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_set_register(cpu: *mut SetunCpu, reg: SetunRegister, value: i64) -> SetunStatus {
+    let handle = handle!(cpu);
+    let range = match reg {
+        SetunRegister::S | SetunRegister::R => Word18::MIN..=Word18::MAX,
+        SetunRegister::F => Tryte5::MIN as i64..=Tryte5::MAX as i64,
+        SetunRegister::C => Tryte9::MIN as i64..=Tryte9::MAX as i64,
+        SetunRegister::Omega => -1..=1,
+    };
+    if !range.contains(&value) {
+        return handle.fail(SetunStatus::InvalidArgument, format!("{} out of range for {:?}", value, reg));
+    }
+    let regs = &mut handle.cpu.regs;
+    match reg {
+        SetunRegister::S => regs.s = Word18::from_i64(value),
+        SetunRegister::R => regs.r = Word18::from_i64(value),
+        SetunRegister::F => regs.f = Tryte5::from_i32(value as i32),
+        SetunRegister::C => regs.c = Tryte9::from_i32(value as i32),
+        SetunRegister::Omega => regs.omega = Trit::from_i8(value as i8),
+    }
+    SetunStatus::Ok
+}
+
+/// Read the cell at address `addr` into `*value`.
+///
+/// # Safety
+/// `cpu` must be null or a live handle, and `value` must be null or point
+/// to a writable `int32_t`.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_read_memory(cpu: *const SetunCpu, addr: i32, value: *mut i32) -> SetunStatus {
+    let Some(handle) = (unsafe { cpu.as_ref() }) else {
+        return SetunStatus::NullPointer;
+    };
+    let Some(value) = (unsafe { value.as_mut() }) else {
+        return SetunStatus::NullPointer;
+    };
+    if !(Tryte9::MIN..=Tryte9::MAX).contains(&addr) {
+        return SetunStatus::MemoryError;
+    }
+    match handle.cpu.mem.read_ternary(Tryte9::from_i32(addr)) {
+        Ok(word) => {
+            *value = word.to_i32();
+            SetunStatus::Ok
+        }
+        Err(_) => SetunStatus::MemoryError,
+    }
+}
+
+/// Write `value` to the cell at address `addr`.
+///
+/// # Safety
+/// `cpu` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_write_memory(cpu: *mut SetunCpu, addr: i32, value: i32) -> SetunStatus {
+    let handle = handle!(cpu);
+    if !(Tryte9::MIN..=Tryte9::MAX).contains(&value) {
+        return handle.fail(SetunStatus::InvalidArgument, format!("value {} does not fit in 9 trits", value));
+    }
+    if !(Tryte9::MIN..=Tryte9::MAX).contains(&addr) {
+        return handle.fail(SetunStatus::MemoryError, format!("address {} out of range", addr));
+    }
+    match handle.cpu.mem.write_ternary(Tryte9::from_i32(addr), Tryte9::from_i32(value)) {
+        Ok(()) => SetunStatus::Ok,
+        Err(e) => handle.fail(SetunStatus::MemoryError, e),
+    }
+}
+
+/// Instructions executed since the last reset. Returns 0 if `cpu` is null.
+///
+/// # Safety
+/// `cpu` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_cycles(cpu: *const SetunCpu) -> u64 {
+    unsafe { cpu.as_ref() }.map_or(0, |handle| handle.cpu.cycles)
+}
+
+/// Whether the CPU has executed HLT.
+///
+/// # Safety
+/// `cpu` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_is_halted(cpu: *const SetunCpu) -> bool {
+    unsafe { cpu.as_ref() }.is_some_and(|handle| handle.cpu.is_halted())
+}
+
+/// Install I/O callbacks (either may be null). `user_data` is passed back
+/// to both unchanged.
+///
+/// # Safety
+/// `cpu` must be null or a live handle, and the callbacks must be safe to
+/// call with `user_data` for as long as they are installed.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_set_io(
+    cpu: *mut SetunCpu,
+    input: Option<SetunInputFn>,
+    output: Option<SetunOutputFn>,
+    user_data: *mut c_void,
+) -> SetunStatus {
+    let handle = handle!(cpu);
+    handle.input = input;
+    handle.output = output;
+    handle.user_data = user_data;
+    SetunStatus::Ok
+}
+
+/// Append `len` words to the input tape.
+///
+/// # Safety
+/// `cpu` must be null or a live handle, and `words` must point to `len`
+/// readable `int32_t`s.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_feed_input(cpu: *mut SetunCpu, words: *const i32, len: usize) -> SetunStatus {
+    let handle = handle!(cpu);
+    if words.is_null() {
+        return SetunStatus::NullPointer;
+    }
+    let words = unsafe { std::slice::from_raw_parts(words, len) };
+    if words.iter().any(|w| !(Tryte9::MIN..=Tryte9::MAX).contains(w)) {
+        return handle.fail(SetunStatus::InvalidArgument, "input word does not fit in 9 trits");
+    }
+    handle.cpu.io.feed(words.iter().map(|&w| Tryte9::from_i32(w)));
+    SetunStatus::Ok
+}
+
+/// Message for the last failed call on `cpu`, or an empty string. The
+/// pointer stays valid until the next call on the same handle.
+///
+/// # Safety
+/// `cpu` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn setun_cpu_last_error(cpu: *const SetunCpu) -> *const c_char {
+    match unsafe { cpu.as_ref() } {
+        Some(handle) => handle.last_error.as_ptr(),
+        None => c"".as_ptr(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Host-side tape and teletype for the callback test.
+    #[derive(Default)]
+    struct Host {
+        tape: Vec<i32>,
+        printed: Vec<i32>,
+    }
+
+    extern "C" fn read_tape(user_data: *mut c_void, word: *mut i32) -> bool {
+        let host = unsafe { &mut *(user_data as *mut Host) };
+        match host.tape.pop() {
+            Some(value) => {
+                unsafe { *word = value };
+                true
+            }
+            None => false,
+        }
+    }
+
+    extern "C" fn print(user_data: *mut c_void, word: i32) {
+        unsafe { (*(user_data as *mut Host)).printed.push(word) };
+    }
+
+    #[test]
+    fn test_load_run_and_registers() {
+        let cpu = setun_cpu_new();
+        unsafe {
+            assert_eq!(setun_cpu_load_asm(cpu, c"LDA 3\nADD 3\nHLT\nDAT 21".as_ptr()), SetunStatus::Ok);
+            let mut executed = 0;
+            assert_eq!(setun_cpu_run(cpu, 100, &mut executed), SetunStatus::Halted);
+            assert_eq!(executed, 3);
+            assert_eq!(setun_cpu_get_register(cpu, SetunRegister::S), 42);
+            assert!(setun_cpu_is_halted(cpu));
+
+            assert_eq!(setun_cpu_set_register(cpu, SetunRegister::F, 500), SetunStatus::InvalidArgument);
+            assert!(!CStr::from_ptr(setun_cpu_last_error(cpu)).to_bytes().is_empty());
+
+            let mut value = 0;
+            assert_eq!(setun_cpu_write_memory(cpu, -5, -77), SetunStatus::Ok);
+            assert_eq!(setun_cpu_read_memory(cpu, -5, &mut value), SetunStatus::Ok);
+            assert_eq!(value, -77);
+            assert_eq!(setun_cpu_read_memory(cpu, 500, &mut value), SetunStatus::MemoryError);
+
+            assert_eq!(setun_cpu_load_asm(cpu, c"FOO 1".as_ptr()), SetunStatus::AssemblyError);
+            setun_cpu_free(cpu);
+            assert_eq!(setun_cpu_step(ptr::null_mut()), SetunStatus::NullPointer);
+        }
+    }
+
+    #[test]
+    fn test_io_callbacks() {
+        let mut host = Host { tape: vec![5, 7], ..Host::default() };
+        let cpu = setun_cpu_new();
+        unsafe {
+            setun_cpu_load_asm(cpu, c"LDA 120\nSTA 121\nLDA 120\nSTA 121\nLDA 120\nHLT".as_ptr());
+            setun_cpu_set_io(cpu, Some(read_tape), Some(print), &mut host as *mut Host as *mut c_void);
+            // The third read finds the tape empty
+            assert_eq!(setun_cpu_run(cpu, 100, ptr::null_mut()), SetunStatus::CpuError);
+            assert_eq!(setun_cpu_cycles(cpu), 4);
+            setun_cpu_free(cpu);
+        }
+        assert_eq!(host.printed, vec![7, 5]);
+    }
+
+    #[test]
+    fn test_header_declares_every_function() {
+        let header = include_str!("../include/setun.h");
+        let source = include_str!("ffi.rs");
+        let exported: Vec<&str> = source.lines()
+            .filter_map(|line| line.split("extern \"C\" fn ").nth(1))
+            .filter_map(|rest| rest.split('(').next())
+            .filter(|name| name.starts_with("setun_"))
+            .collect();
+        assert!(exported.len() > 10);
+        for name in exported {
+            assert!(header.contains(&format!(" {}(", name)) || header.contains(&format!("*{}(", name)), "{} missing from setun.h", name);
+        }
+    }
+}
@@ -0,0 +1,478 @@
+//! GDB remote debugging.
+//!
+//! `setun-emu debug --gdb :3333` waits for a GDB (or IDE) connection and
+//! serves the CPU over the GDB remote serial protocol. GDB only knows
+//! byte-addressed binary machines, so the Setun is presented as follows:
+//!
+//! - Memory is a 324-byte space with one 16-bit little-endian cell per
+//!   two bytes, in index order: GDB address 0 is Setun address -81, and
+//!   Setun address `a` is at GDB address `2 * (a + 81)` (see [`gdb_address`]).
+//!   Cells hold plain integers, so `x/4dh` shows four cells in decimal.
+//! - Registers are 32-bit integers: `s`, `r`, `f`, `pc` (C, as a GDB
+//!   address) and `omega` (-1, 0 or 1), described by [`TARGET_XML`].
+//!
+//! ```text
+//! $ gdb
+//! (gdb) target remote :3333
+//! (gdb) break *0x0c          ; Setun address -75
+//! (gdb) continue
+//! (gdb) info registers
+//! ```
+
+use crate::{Cpu, CpuState, Tryte9, Word18};
+use crate::cpu::registers::Tryte5;
+use crate::ternary::Trit;
+use gdbstub::arch::{Arch, Registers};
+use gdbstub::common::Signal;
+use gdbstub::conn::ConnectionExt;
+use gdbstub::stub::{run_blocking, DisconnectReason, GdbStub, SingleThreadStopReason};
+use gdbstub::target::{Target, TargetError, TargetResult};
+use gdbstub::target::ext::base::BaseOps;
+use gdbstub::target::ext::base::singlethread::{
+    SingleThreadBase, SingleThreadResume, SingleThreadResumeOps,
+    SingleThreadSingleStep, SingleThreadSingleStepOps,
+};
+use gdbstub::target::ext::breakpoints::{Breakpoints, BreakpointsOps, SwBreakpoint, SwBreakpointOps};
+use std::collections::BTreeSet;
+use std::net::{TcpListener, TcpStream};
+use thiserror::Error;
+
+/// Target description sent to GDB.
+pub const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.setun.core">
+    <reg name="s" bitsize="32" type="int32" regnum="0"/>
+    <reg name="r" bitsize="32" type="int32"/>
+    <reg name="f" bitsize="32" type="int32"/>
+    <reg name="pc" bitsize="32" type="code_ptr"/>
+    <reg name="omega" bitsize="32" type="int32"/>
+  </feature>
+</target>
+"#;
+
+/// Bytes per memory cell in the GDB address space.
+pub const CELL_BYTES: u32 = 2;
+
+/// Instructions executed between checks for an interrupt from GDB.
+const POLL_INTERVAL: u64 = 1024;
+
+/// GDB address of the first byte of the cell at Setun address `addr`.
+pub fn gdb_address(cpu: &Cpu, addr: i32) -> Option<u32> {
+    let index = cpu.mem.addr_to_index(checked_tryte(addr)?).ok()?;
+    Some(index as u32 * CELL_BYTES)
+}
+
+/// Setun address of the cell holding GDB address `addr`.
+pub fn setun_address(cpu: &Cpu, addr: u32) -> Option<i32> {
+    let index = (addr / CELL_BYTES) as usize;
+    (index < cpu.mem.size()).then(|| cpu.mem.index_to_addr(index).to_i32())
+}
+
+fn checked_tryte(value: i32) -> Option<Tryte9> {
+    (Tryte9::MIN..=Tryte9::MAX).contains(&value).then(|| Tryte9::from_i32(value))
+}
+
+/// The Setun as a GDB architecture.
+pub enum SetunArch {}
+
+impl Arch for SetunArch {
+    type Usize = u32;
+    type Registers = SetunRegs;
+    type BreakpointKind = usize;
+    type RegId = ();
+
+    fn target_description_xml() -> Option<&'static str> {
+        Some(TARGET_XML)
+    }
+}
+
+/// The register file as GDB sees it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SetunRegs {
+    /// Accumulator S.
+    pub s: i32,
+    /// Multiplier register R.
+    pub r: i32,
+    /// Index register F.
+    pub f: i32,
+    /// Program counter C, as a GDB address.
+    pub pc: u32,
+    /// Sign register ω.
+    pub omega: i32,
+}
+
+impl Registers for SetunRegs {
+    type ProgramCounter = u32;
+
+    fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    fn gdb_serialize(&self, mut write_byte: impl FnMut(Option<u8>)) {
+        let words = [self.s, self.r, self.f, self.pc as i32, self.omega];
+        for byte in words.iter().flat_map(|word| word.to_le_bytes()) {
+            write_byte(Some(byte));
+        }
+    }
+
+    fn gdb_deserialize(&mut self, bytes: &[u8]) -> Result<(), ()> {
+        let words: Vec<i32> = bytes.chunks_exact(4)
+            .map(|chunk| i32::from_le_bytes(chunk.try_into().expect("chunks are 4 bytes")))
+            .collect();
+        let [s, r, f, pc, omega] = words[..] else {
+            return Err(());
+        };
+        *self = SetunRegs { s, r, f, pc: pc as u32, omega };
+        Ok(())
+    }
+}
+
+/// What the CPU does when GDB lets it run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExecMode {
+    Step,
+    Continue,
+}
+
+/// Why execution returned control to GDB.
+enum RunEvent {
+    IncomingData,
+    Stopped(SingleThreadStopReason<u32>),
+}
+
+/// A CPU being debugged over the GDB protocol.
+pub struct GdbTarget {
+    cpu: Cpu,
+    /// Breakpoints, as Setun addresses.
+    breakpoints: BTreeSet<i32>,
+    exec_mode: ExecMode,
+}
+
+impl GdbTarget {
+    /// Debug a CPU with its program already loaded.
+    pub fn new(cpu: Cpu) -> Self {
+        GdbTarget { cpu, breakpoints: BTreeSet::new(), exec_mode: ExecMode::Continue }
+    }
+
+    /// The CPU being debugged.
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    /// Give back the CPU, e.g. to save its state after the session.
+    pub fn into_cpu(self) -> Cpu {
+        self.cpu
+    }
+
+    /// Execute according to the last resume request until something
+    /// stops the CPU or `incoming_data` reports a packet from GDB.
+    fn run(&mut self, mut incoming_data: impl FnMut() -> bool) -> RunEvent {
+        if self.exec_mode == ExecMode::Step {
+            return RunEvent::Stopped(self.step_once().unwrap_or(SingleThreadStopReason::DoneStep));
+        }
+
+        let mut executed = 0u64;
+        loop {
+            if executed.is_multiple_of(POLL_INTERVAL) && incoming_data() {
+                return RunEvent::IncomingData;
+            }
+            if let Some(reason) = self.step_once() {
+                return RunEvent::Stopped(reason);
+            }
+            executed += 1;
+            if self.breakpoints.contains(&self.cpu.regs.c.to_i32()) {
+                return RunEvent::Stopped(SingleThreadStopReason::SwBreak(()));
+            }
+        }
+    }
+
+    /// Execute one instruction, reporting a stop if the CPU halted or faulted.
+    fn step_once(&mut self) -> Option<SingleThreadStopReason<u32>> {
+        match self.cpu.state {
+            CpuState::Halted => return Some(SingleThreadStopReason::Exited(0)),
+            CpuState::Error => return Some(SingleThreadStopReason::Signal(Signal::SIGILL)),
+            CpuState::Running => {}
+        }
+        match self.cpu.step() {
+            Ok(_) if self.cpu.is_halted() => Some(SingleThreadStopReason::Exited(0)),
+            Ok(_) => None,
+            Err(_) => Some(SingleThreadStopReason::Signal(Signal::SIGILL)),
+        }
+    }
+}
+
+impl Target for GdbTarget {
+    type Arch = SetunArch;
+    type Error = &'static str;
+
+    fn base_ops(&mut self) -> BaseOps<'_, SetunArch, Self::Error> {
+        BaseOps::SingleThread(self)
+    }
+
+    fn support_breakpoints(&mut self) -> Option<BreakpointsOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleThreadBase for GdbTarget {
+    fn read_registers(&mut self, regs: &mut SetunRegs) -> TargetResult<(), Self> {
+        let cpu_regs = &self.cpu.regs;
+        *regs = SetunRegs {
+            s: cpu_regs.s.to_i64() as i32,
+            r: cpu_regs.r.to_i64() as i32,
+            f: cpu_regs.f.to_i32(),
+            pc: gdb_address(&self.cpu, cpu_regs.c.to_i32()).unwrap_or(u32::MAX),
+            omega: i8::from(cpu_regs.omega) as i32,
+        };
+        Ok(())
+    }
+
+    fn write_registers(&mut self, regs: &SetunRegs) -> TargetResult<(), Self> {
+        let word = |value: i32| (Word18::MIN..=Word18::MAX).contains(&(value as i64))
+            .then(|| Word18::from_i64(value as i64));
+        let (Some(s), Some(r)) = (word(regs.s), word(regs.r)) else {
+            return Err(TargetError::NonFatal);
+        };
+        if !(Tryte5::MIN..=Tryte5::MAX).contains(&regs.f) || !(-1..=1).contains(&regs.omega) {
+            return Err(TargetError::NonFatal);
+        }
+        let Some(pc) = setun_address(&self.cpu, regs.pc) else {
+            return Err(TargetError::NonFatal);
+        };
+
+        let cpu_regs = &mut self.cpu.regs;
+        cpu_regs.s = s;
+        cpu_regs.r = r;
+        cpu_regs.f = Tryte5::from_i32(regs.f);
+        cpu_regs.c = Tryte9::from_i32(pc);
+        cpu_regs.omega = Trit::from_i8(regs.omega as i8);
+        Ok(())
+    }
+
+    fn read_addrs(&mut self, start_addr: u32, data: &mut [u8]) -> TargetResult<usize, Self> {
+        let mut read = 0;
+        for (addr, byte) in (start_addr..).zip(data.iter_mut()) {
+            let index = (addr / CELL_BYTES) as usize;
+            if index >= self.cpu.mem.size() {
+                break;
+            }
+            let cell = (self.cpu.mem.read(index).to_i32() as i16).to_le_bytes();
+            *byte = cell[(addr % CELL_BYTES) as usize];
+            read += 1;
+        }
+        Ok(read)
+    }
+
+    fn write_addrs(&mut self, start_addr: u32, data: &[u8]) -> TargetResult<(), Self> {
+        // Assemble the new cell values first so a bad write changes nothing
+        let mut cells: Vec<(usize, [u8; 2])> = Vec::new();
+        for (addr, &byte) in (start_addr..).zip(data) {
+            let index = (addr / CELL_BYTES) as usize;
+            if index >= self.cpu.mem.size() {
+                return Err(TargetError::NonFatal);
+            }
+            if cells.last().map(|&(last, _)| last) != Some(index) {
+                cells.push((index, (self.cpu.mem.read(index).to_i32() as i16).to_le_bytes()));
+            }
+            let (_, bytes) = cells.last_mut().expect("cell was just pushed");
+            bytes[(addr % CELL_BYTES) as usize] = byte;
+        }
+
+        let values = cells.iter()
+            .map(|&(index, bytes)| checked_tryte(i16::from_le_bytes(bytes) as i32).map(|value| (index, value)))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(TargetError::NonFatal)?;
+        for (index, value) in values {
+            self.cpu.mem.write(index, value);
+        }
+        Ok(())
+    }
+
+    fn support_resume(&mut self) -> Option<SingleThreadResumeOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleThreadResume for GdbTarget {
+    fn resume(&mut self, signal: Option<Signal>) -> Result<(), Self::Error> {
+        if signal.is_some() {
+            return Err("the Setun has no signals");
+        }
+        self.exec_mode = ExecMode::Continue;
+        Ok(())
+    }
+
+    fn support_single_step(&mut self) -> Option<SingleThreadSingleStepOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleThreadSingleStep for GdbTarget {
+    fn step(&mut self, signal: Option<Signal>) -> Result<(), Self::Error> {
+        if signal.is_some() {
+            return Err("the Setun has no signals");
+        }
+        self.exec_mode = ExecMode::Step;
+        Ok(())
+    }
+}
+
+impl Breakpoints for GdbTarget {
+    fn support_sw_breakpoint(&mut self) -> Option<SwBreakpointOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SwBreakpoint for GdbTarget {
+    fn add_sw_breakpoint(&mut self, addr: u32, _kind: usize) -> TargetResult<bool, Self> {
+        let Some(addr) = setun_address(&self.cpu, addr) else {
+            return Ok(false);
+        };
+        self.breakpoints.insert(addr);
+        Ok(true)
+    }
+
+    fn remove_sw_breakpoint(&mut self, addr: u32, _kind: usize) -> TargetResult<bool, Self> {
+        Ok(setun_address(&self.cpu, addr).is_some_and(|addr| self.breakpoints.remove(&addr)))
+    }
+}
+
+/// Drives [`GdbTarget`] from the blocking gdbstub event loop.
+enum EventLoop {}
+
+impl run_blocking::BlockingEventLoop for EventLoop {
+    type Target = GdbTarget;
+    type Connection = TcpStream;
+    type StopReason = SingleThreadStopReason<u32>;
+
+    #[allow(clippy::type_complexity)]
+    fn wait_for_stop_reason(
+        target: &mut GdbTarget,
+        conn: &mut TcpStream,
+    ) -> Result<
+        run_blocking::Event<Self::StopReason>,
+        run_blocking::WaitForStopReasonError<&'static str, std::io::Error>,
+    > {
+        let incoming_data = || conn.peek().map(|byte| byte.is_some()).unwrap_or(true);
+        match target.run(incoming_data) {
+            RunEvent::IncomingData => {
+                let byte = conn.read().map_err(run_blocking::WaitForStopReasonError::Connection)?;
+                Ok(run_blocking::Event::IncomingData(byte))
+            }
+            RunEvent::Stopped(reason) => Ok(run_blocking::Event::TargetStopped(reason)),
+        }
+    }
+
+    fn on_interrupt(_target: &mut GdbTarget) -> Result<Option<Self::StopReason>, &'static str> {
+        Ok(Some(SingleThreadStopReason::Signal(Signal::SIGINT)))
+    }
+}
+
+/// How a GDB session ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEnd {
+    /// GDB detached or disconnected.
+    Disconnected,
+    /// The program halted and GDB was told it exited.
+    Halted,
+    /// GDB killed the program.
+    Killed,
+}
+
+/// Accept one GDB connection on `listener` and debug `target` until the
+/// session ends.
+pub fn serve(listener: &TcpListener, target: &mut GdbTarget) -> Result<SessionEnd, GdbError> {
+    let (stream, _) = listener.accept().map_err(|e| GdbError::Io(e.to_string()))?;
+    stream.set_nodelay(true).map_err(|e| GdbError::Io(e.to_string()))?;
+
+    match GdbStub::new(stream).run_blocking::<EventLoop>(target) {
+        Ok(DisconnectReason::Disconnect) => Ok(SessionEnd::Disconnected),
+        Ok(DisconnectReason::TargetExited(_) | DisconnectReason::TargetTerminated(_)) => Ok(SessionEnd::Halted),
+        Ok(DisconnectReason::Kill) => Ok(SessionEnd::Killed),
+        Err(e) => Err(GdbError::Protocol(e.to_string())),
+    }
+}
+
+/// Errors that end a GDB session.
+#[derive(Debug, Clone, Error)]
+pub enum GdbError {
+    #[error("I/O error: {0}")]
+    Io(String),
+
+    #[error("GDB protocol error: {0}")]
+    Protocol(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(source: &str) -> GdbTarget {
+        let mut cpu = Cpu::new();
+        cpu.load_program(&crate::asm::assemble(source).unwrap()).unwrap();
+        GdbTarget::new(cpu)
+    }
+
+    /// `TargetError` is not `Debug`, so `unwrap` is unavailable.
+    fn ok<T>(result: TargetResult<T, GdbTarget>) -> T {
+        result.unwrap_or_else(|_| panic!("target error"))
+    }
+
+    #[test]
+    fn test_address_mapping() {
+        let cpu = Cpu::new();
+        assert_eq!(gdb_address(&cpu, -81), Some(0));
+        assert_eq!(gdb_address(&cpu, 0), Some(162));
+        assert_eq!(gdb_address(&cpu, 81), None);
+        assert_eq!(setun_address(&cpu, 163), Some(0));
+        assert_eq!(setun_address(&cpu, 324), None);
+    }
+
+    #[test]
+    fn test_registers_roundtrip() {
+        let mut target = target("LDA 3\nHLT\nHLT\nDAT -42");
+        target.cpu.step().unwrap();
+
+        let mut regs = SetunRegs::default();
+        ok(target.read_registers(&mut regs));
+        assert_eq!(regs.s, -42);
+        assert_eq!(regs.omega, -1);
+        assert_eq!(setun_address(&target.cpu, regs.pc), Some(1));
+
+        let mut bytes = Vec::new();
+        regs.gdb_serialize(|byte| bytes.push(byte.unwrap()));
+        let mut parsed = SetunRegs::default();
+        parsed.gdb_deserialize(&bytes).unwrap();
+        assert_eq!(parsed, regs);
+
+        parsed.f = 500;
+        assert!(target.write_registers(&parsed).is_err());
+    }
+
+    #[test]
+    fn test_memory_access() {
+        let mut target = target("HLT");
+        let base = gdb_address(&target.cpu, 5).unwrap();
+        ok(target.write_addrs(base, &(-300i16).to_le_bytes()));
+        assert_eq!(target.cpu.mem.read_ternary(Tryte9::from_i32(5)).unwrap().to_i32(), -300);
+
+        let mut data = [0u8; 2];
+        assert_eq!(ok(target.read_addrs(base, &mut data)), 2);
+        assert_eq!(i16::from_le_bytes(data), -300);
+
+        // 20000 does not fit in a cell
+        assert!(target.write_addrs(base, &20000i16.to_le_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_breakpoint_stops_run() {
+        let mut target = target("ADD 4\nADD 4\nADD 4\nHLT\nDAT 1");
+        let addr = gdb_address(&target.cpu, 2).unwrap();
+        assert!(ok(target.add_sw_breakpoint(addr, 0)));
+
+        assert!(matches!(target.run(|| false), RunEvent::Stopped(SingleThreadStopReason::SwBreak(()))));
+        assert_eq!(target.cpu.regs.c.to_i32(), 2);
+        assert!(matches!(target.run(|| false), RunEvent::Stopped(SingleThreadStopReason::Exited(0))));
+    }
+}
@@ -0,0 +1,473 @@
+//! Assertion-driven program tests.
+//!
+//! Assembly programs can carry their own expectations in special
+//! comments, turning any `.asm` file into a self-checking test:
+//!
+//! ```text
+//! ;@ max-cycles 500
+//! ;@ expect S == 15
+//! ;@ expect [12] == 3
+//! ;@ expect cycles < 100
+//! ;@ init [20] = 7
+//! ```
+//!
+//! Targets are the registers `S`, `R`, `F`, `C` and `W` (ω), memory cells
+//! `[addr]`, and `cycles`. Comparisons are `==`, `!=`, `<`, `<=`, `>` and
+//! `>=`; values may be decimal, `0t` ternary or `0n` base 9. A program
+//! must also halt within the cycle limit to pass. `init` presets a memory
+//! cell before the program starts, so input data need not be a `DAT` line.
+//!
+//! The same targets and comparisons make up a [`Condition`], which tools
+//! such as the debugger's `:until` evaluate against a running machine.
+//! Either side of a comparison may be a target or a number, and clauses
+//! combine with `&&` and `||` (`&&` binding tighter):
+//!
+//! ```
+//! use setun::harness::Condition;
+//! use setun::{assemble, Cpu};
+//!
+//! let condition: Condition = "S < 0 || [10] == 3 && cycles > 1".parse().unwrap();
+//! let mut cpu = Cpu::new();
+//! cpu.load_program(&assemble("LDA 5\nSUB 6\nHLT\nNOP\nNOP\nDAT 2\nDAT 4").unwrap()).unwrap();
+//! cpu.run_until(|cpu| condition.holds(cpu)).unwrap();
+//! assert_eq!(cpu.cycles, 2);
+//! ```
+
+use crate::asm::assemble;
+use crate::cpu::{Cpu, MemInit};
+use crate::ternary::{parse_number, Tryte9};
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use thiserror::Error;
+
+/// Cycle limit used when a program has no `max-cycles` directive.
+pub const DEFAULT_MAX_CYCLES: u64 = 10_000;
+
+/// What an expectation inspects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    /// A register, by name (`S`, `R`, `F`, `C` or `W`).
+    Register(char),
+    /// A memory cell, by address.
+    Memory(i32),
+    /// The number of executed instructions.
+    Cycles,
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Register(r) => write!(f, "{}", r),
+            Target::Memory(addr) => write!(f, "[{}]", addr),
+            Target::Cycles => write!(f, "cycles"),
+        }
+    }
+}
+
+/// Comparison operator of an expectation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "==" | "=" => Some(Comparison::Eq),
+            "!=" => Some(Comparison::Ne),
+            "<" => Some(Comparison::Lt),
+            "<=" => Some(Comparison::Le),
+            ">" => Some(Comparison::Gt),
+            ">=" => Some(Comparison::Ge),
+            _ => None,
+        }
+    }
+
+    /// Apply the comparison.
+    pub fn holds(self, actual: i64, expected: i64) -> bool {
+        match self {
+            Comparison::Eq => actual == expected,
+            Comparison::Ne => actual != expected,
+            Comparison::Lt => actual < expected,
+            Comparison::Le => actual <= expected,
+            Comparison::Gt => actual > expected,
+            Comparison::Ge => actual >= expected,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+/// One `;@ expect` directive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Expectation {
+    /// Source line (1-based) of the directive.
+    pub line: usize,
+    /// What is checked.
+    pub target: Target,
+    /// How it is compared.
+    pub comparison: Comparison,
+    /// The value compared against.
+    pub value: i64,
+}
+
+/// All directives found in a program.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestSpec {
+    /// Expectations, in source order.
+    pub expectations: Vec<Expectation>,
+    /// Cycle limit from a `max-cycles` directive.
+    pub max_cycles: Option<u64>,
+    /// Memory preset by `init` directives.
+    #[serde(default)]
+    pub init: MemInit,
+}
+
+impl TestSpec {
+    /// Extract the `;@` directives from assembly source.
+    pub fn parse(source: &str) -> Result<Self, HarnessError> {
+        let mut spec = TestSpec::default();
+
+        for (index, line) in source.lines().enumerate() {
+            let line_num = index + 1;
+            let Some(directive) = line.trim().strip_prefix(";@") else {
+                continue;
+            };
+            let words: Vec<&str> = directive.split_whitespace().collect();
+            let invalid = |message: &str| HarnessError::InvalidDirective {
+                line: line_num,
+                message: message.to_string(),
+            };
+
+            match words.as_slice() {
+                ["max-cycles", n] => {
+                    spec.max_cycles = Some(n.parse().map_err(|_| invalid("invalid cycle count"))?);
+                }
+                ["expect", target, op, value] => {
+                    spec.expectations.push(Expectation {
+                        line: line_num,
+                        target: parse_target(target).map_err(|message| invalid(&message))?,
+                        comparison: Comparison::parse(op).ok_or_else(|| invalid("unknown comparison"))?,
+                        value: parse_number(value).map_err(|e| invalid(&e.to_string()))?,
+                    });
+                }
+                ["init", target, "=", value] => {
+                    let Target::Memory(addr) = parse_target(target).map_err(|message| invalid(&message))? else {
+                        return Err(invalid("init target must be a memory cell"));
+                    };
+                    let value = parse_number(value).map_err(|e| invalid(&e.to_string()))?;
+                    let value = Tryte9::try_from_i64(value).map_err(|_| invalid("value does not fit in 9 trits"))?;
+                    spec.init.entries.push((addr, value));
+                }
+                _ => return Err(invalid("expected 'expect <target> <op> <value>', 'init [addr] = <value>' or 'max-cycles <n>'")),
+            }
+        }
+
+        Ok(spec)
+    }
+}
+
+fn parse_target(s: &str) -> Result<Target, String> {
+    if let Some(addr) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        let addr = parse_number(addr).map_err(|e| e.to_string())?;
+        return Tryte9::try_from_i64(addr)
+            .map(|addr| Target::Memory(addr.to_i32()))
+            .map_err(|_| format!("address {} does not fit in 9 trits", addr));
+    }
+    match s.to_ascii_uppercase().as_str() {
+        "S" => Ok(Target::Register('S')),
+        "R" => Ok(Target::Register('R')),
+        "F" => Ok(Target::Register('F')),
+        "C" => Ok(Target::Register('C')),
+        "W" | "OMEGA" => Ok(Target::Register('W')),
+        "CYCLES" => Ok(Target::Cycles),
+        _ => Err("unknown target".to_string()),
+    }
+}
+
+/// Result of running one test program.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestOutcome {
+    /// Instructions executed.
+    pub cycles: u64,
+    /// Whether the program halted within the cycle limit.
+    pub halted: bool,
+    /// Number of expectations checked.
+    pub checked: usize,
+    /// Human-readable descriptions of everything that failed.
+    pub failures: Vec<String>,
+}
+
+impl TestOutcome {
+    /// True if the program halted and every expectation held.
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Assemble and run `source`, checking its embedded expectations.
+pub fn run_test(source: &str) -> Result<TestOutcome, HarnessError> {
+    let spec = TestSpec::parse(source)?;
+    let program = assemble(source).map_err(|e| HarnessError::Assembly(e.to_string()))?;
+
+    let mut cpu = Cpu::new();
+    cpu.load_program(&program).map_err(|e| HarnessError::Load(e.to_string()))?;
+    cpu.apply_mem_init(&spec.init).map_err(|e| HarnessError::Load(e.to_string()))?;
+
+    let max_cycles = spec.max_cycles.unwrap_or(DEFAULT_MAX_CYCLES);
+    let mut failures = Vec::new();
+    if let Err(e) = cpu.run_limited(max_cycles) {
+        failures.push(format!("CPU error at PC={}: {}", cpu.regs.c.to_i32(), e));
+    } else if !cpu.is_halted() {
+        failures.push(format!("did not halt within {} cycles", max_cycles));
+    }
+
+    for expectation in &spec.expectations {
+        let actual = match read_target(&cpu, expectation.target) {
+            Some(value) => value,
+            None => {
+                failures.push(format!("line {}: {} is out of range", expectation.line, expectation.target));
+                continue;
+            }
+        };
+        if !expectation.comparison.holds(actual, expectation.value) {
+            failures.push(format!(
+                "line {}: expected {} {} {}, got {}",
+                expectation.line, expectation.target, expectation.comparison, expectation.value, actual
+            ));
+        }
+    }
+
+    Ok(TestOutcome {
+        cycles: cpu.cycles,
+        halted: cpu.is_halted(),
+        checked: spec.expectations.len(),
+        failures,
+    })
+}
+
+fn read_target(cpu: &Cpu, target: Target) -> Option<i64> {
+    let regs = &cpu.regs;
+    match target {
+        Target::Register('S') => Some(regs.s.to_i64()),
+        Target::Register('R') => Some(regs.r.to_i64()),
+        Target::Register('F') => Some(regs.f.to_i32() as i64),
+        Target::Register('C') => Some(regs.c.to_i32() as i64),
+        Target::Register(_) => Some(regs.omega.to_i8() as i64),
+        Target::Memory(addr) => {
+            let addr = Tryte9::try_from_i64(addr as i64).ok()?;
+            cpu.mem.read_ternary(addr).ok().map(|w| w.to_i32() as i64)
+        }
+        Target::Cycles => Some(cpu.cycles as i64),
+    }
+}
+
+/// One side of a comparison in a [`Condition`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operand {
+    /// The current value of a register, cell or the cycle count.
+    Target(Target),
+    /// A constant.
+    Value(i64),
+}
+
+impl Operand {
+    fn parse(s: &str) -> Result<Self, ConditionError> {
+        let s = s.trim();
+        parse_target(s)
+            .map(Operand::Target)
+            .or_else(|_| parse_number(s).map(Operand::Value))
+            .map_err(|_| ConditionError::BadOperand(s.to_string()))
+    }
+
+    fn read(self, cpu: &Cpu) -> Option<i64> {
+        match self {
+            Operand::Target(target) => read_target(cpu, target),
+            Operand::Value(value) => Some(value),
+        }
+    }
+}
+
+/// A comparison between two operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Clause {
+    /// Left-hand side.
+    pub left: Operand,
+    /// How the sides are compared.
+    pub comparison: Comparison,
+    /// Right-hand side.
+    pub right: Operand,
+}
+
+impl Clause {
+    fn parse(s: &str) -> Result<Self, ConditionError> {
+        // The leftmost operator, preferring the two-character ones
+        let (at, op) = ["==", "!=", "<=", ">=", "<", ">", "="]
+            .iter()
+            .filter_map(|op| s.find(op).map(|at| (at, *op)))
+            .min_by_key(|&(at, op)| (at, std::cmp::Reverse(op.len())))
+            .ok_or_else(|| ConditionError::MissingComparison(s.trim().to_string()))?;
+        Ok(Clause {
+            left: Operand::parse(&s[..at])?,
+            comparison: Comparison::parse(op).expect("listed operator"),
+            right: Operand::parse(&s[at + op.len()..])?,
+        })
+    }
+
+    /// Whether the comparison holds; false if a cell is outside memory.
+    pub fn holds(&self, cpu: &Cpu) -> bool {
+        match (self.left.read(cpu), self.right.read(cpu)) {
+            (Some(left), Some(right)) => self.comparison.holds(left, right),
+            _ => false,
+        }
+    }
+}
+
+/// Clauses joined by `||` and `&&`, evaluated against a machine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Condition {
+    /// Alternatives, each holding when all of its clauses hold.
+    pub any_of: Vec<Vec<Clause>>,
+}
+
+impl Condition {
+    /// Whether the condition holds for `cpu` as it is now.
+    pub fn holds(&self, cpu: &Cpu) -> bool {
+        self.any_of.iter().any(|all| all.iter().all(|clause| clause.holds(cpu)))
+    }
+}
+
+impl std::str::FromStr for Condition {
+    type Err = ConditionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let any_of = s
+            .split("||")
+            .map(|all| all.split("&&").map(Clause::parse).collect())
+            .collect::<Result<_, _>>()?;
+        Ok(Condition { any_of })
+    }
+}
+
+/// Errors in the text of a [`Condition`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConditionError {
+    #[error("'{0}' has no comparison (==, !=, <, <=, > or >=)")]
+    MissingComparison(String),
+
+    #[error("'{0}' is not a register, [address], cycles or number")]
+    BadOperand(String),
+}
+
+/// Errors that prevent a test program from running.
+#[derive(Debug, Clone, Error)]
+pub enum HarnessError {
+    #[error("invalid directive on line {line}: {message}")]
+    InvalidDirective { line: usize, message: String },
+
+    #[error("assembly error: {0}")]
+    Assembly(String),
+
+    #[error("failed to load program: {0}")]
+    Load(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "
+        ;@ max-cycles 50
+        ;@ expect S == 30
+        ;@ expect [5] == 0tPOPO
+        ;@ expect cycles <= 4
+            LDA 4
+            ADD 4
+            STA 5
+            HLT
+            DAT 15
+        ";
+
+    #[test]
+    fn test_parse_directives() {
+        let spec = TestSpec::parse(PROGRAM).unwrap();
+        assert_eq!(spec.max_cycles, Some(50));
+        assert_eq!(spec.expectations.len(), 3);
+        assert_eq!(spec.expectations[1].target, Target::Memory(5));
+        assert_eq!(spec.expectations[1].value, 30);
+        assert_eq!(spec.expectations[0].target, Target::Register('S'));
+        assert_eq!(spec.expectations[2].comparison, Comparison::Le);
+
+        assert!(matches!(
+            TestSpec::parse(";@ expect Q == 1"),
+            Err(HarnessError::InvalidDirective { line: 1, .. })
+        ));
+
+        // Addresses and values outside 9 trits are not wrapped into another cell
+        for directive in [";@ expect [4294967301] == 1", ";@ init [10000] = 1", ";@ init [5] = -9223372036854775807"] {
+            assert!(matches!(TestSpec::parse(directive), Err(HarnessError::InvalidDirective { .. })), "{}", directive);
+        }
+    }
+
+    #[test]
+    fn test_run_reports_failures() {
+        let outcome = run_test(PROGRAM).unwrap();
+        assert!(outcome.passed(), "{:?}", outcome.failures);
+        assert_eq!(outcome.checked, 3);
+
+        let outcome = run_test(&PROGRAM.replace("S == 30", "S == 16")).unwrap();
+        assert!(!outcome.passed());
+        assert_eq!(outcome.failures.len(), 1);
+        assert!(outcome.failures[0].contains("expected S == 16, got 30"));
+    }
+
+    #[test]
+    fn test_init_presets_memory() {
+        let source = ";@ init [3] = 40\n;@ expect S == 41\nLDA 3\nADD 4\nHLT\nDAT 0\nDAT 1\n";
+        assert_eq!(TestSpec::parse(source).unwrap().init.entries, vec![(3, Tryte9::from_i32(40))]);
+        assert!(run_test(source).unwrap().passed());
+        assert!(TestSpec::parse(";@ init S = 1").is_err());
+    }
+
+    #[test]
+    fn test_condition() {
+        let mut cpu = Cpu::new();
+        cpu.load_program(&assemble(PROGRAM).unwrap()).unwrap();
+        cpu.run().unwrap();
+
+        let holds = |text: &str| text.parse::<Condition>().unwrap().holds(&cpu);
+        assert!(holds("S==30"));
+        assert!(holds("[5] >= S && cycles = 4"));
+        assert!(holds("S < 0 || -1 <= W"));
+        assert!(!holds("S > 0 && [4] != 15"));
+        assert!(!holds("[500] == 0"));
+        assert_eq!("S".parse::<Condition>(), Err(ConditionError::MissingComparison("S".into())));
+        assert_eq!("S < x".parse::<Condition>(), Err(ConditionError::BadOperand("x".into())));
+    }
+
+    #[test]
+    fn test_run_requires_halt() {
+        let outcome = run_test(";@ max-cycles 10\nLOOP: JMP LOOP\n").unwrap();
+        assert!(!outcome.halted);
+        assert!(outcome.failures[0].contains("did not halt"));
+    }
+}
@@ -0,0 +1,15 @@
+//! Internal hooks for the `tracing` crate.
+//!
+//! With the `tracing` feature, [`event!`] forwards to the `tracing` macro
+//! of the same level; without it the macro expands to nothing, so
+//! instrumented code needs no `#[cfg]` of its own and default builds pay
+//! nothing. Spans use `#[cfg_attr(feature = "tracing", tracing::instrument)]`
+//! directly.
+
+/// Emit a `tracing` event: `event!(debug, addr, "memory write")`.
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        ::tracing::$level!($($arg)+);
+    }};
+}
@@ -0,0 +1,432 @@
+//! Assembly generation.
+//!
+//! Every expression is evaluated into S. Intermediate values that cannot be
+//! used directly as an operand are spilled to temporaries `T_n`, allocated
+//! by nesting depth. Labels are prefixed by what they name so user
+//! identifiers never collide with generated ones: `V_` variables, `P_`
+//! procedures, `K_` constants, `L_` branch targets.
+//!
+//! A call loads F with the address of its return point and jumps to the
+//! procedure, which saves F in its own slot and returns with `JMP 0,F+`.
+//! One slot per procedure is why recursion is rejected.
+
+use super::parser::{BinOp, CmpOp, Cond, Expr, Program, Stmt, StmtKind};
+use super::CompileError;
+use crate::cpu::io::{TAPE_READER_ADDR, TELETYPE_ADDR};
+use crate::ternary::arith::{ChainTerm, MulChain};
+use crate::ternary::{Trit, Tryte9};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// First address of memory; programs use every cell.
+pub const ORIGIN: i32 = -81;
+
+/// Generate assembly source for a checked program.
+pub fn generate(program: &Program, source: &str) -> Result<String, CompileError> {
+    let mut gen = Generator {
+        source: source.lines().collect(),
+        vars: program.vars.iter().map(|v| v.name.clone()).collect(),
+        procs: program.procs.iter().map(|p| p.name.clone()).collect(),
+        lines: Vec::new(),
+        constants: BTreeSet::new(),
+        temps: 0,
+        labels: 0,
+        returns: 0,
+        current_proc: None,
+    };
+
+    gen.raw(format!("    ORG {}", ORIGIN));
+    gen.block(&program.main)?;
+    if !matches!(program.main.last(), Some(Stmt { kind: StmtKind::Halt(_), .. })) {
+        gen.instr("HLT");
+    }
+
+    for proc in &program.procs {
+        gen.raw(String::new());
+        gen.comment(proc.line);
+        gen.label(&format!("P_{}", proc.name));
+        gen.instr(&format!("STF F_{}", proc.name));
+        gen.current_proc = Some(proc.name.clone());
+        gen.block(&proc.body)?;
+        gen.current_proc = None;
+        gen.label(&format!("X_{}", proc.name));
+        gen.instr(&format!("LDF F_{}", proc.name));
+        gen.instr("JMP 0,F+");
+    }
+
+    gen.raw(String::new());
+    gen.raw("; Data".into());
+    for var in &program.vars {
+        gen.data(&format!("V_{}", var.name), in_range(var.init, var.line)?.to_string());
+    }
+    for proc in &program.procs {
+        gen.data(&format!("F_{}", proc.name), "0".into());
+    }
+    for n in 0..gen.returns {
+        gen.data(&format!("RA_{}", n), format!("R_{}", n));
+    }
+    for n in 0..gen.temps {
+        gen.data(&format!("T_{}", n), "0".into());
+    }
+    let constants = std::mem::take(&mut gen.constants);
+    for value in constants {
+        gen.data(&constant_label(value), value.to_string());
+    }
+
+    let mut text = gen.lines.join("\n");
+    text.push('\n');
+    Ok(text)
+}
+
+/// Reject values that do not fit in a memory word.
+fn in_range(value: i64, line: usize) -> Result<i64, CompileError> {
+    if value.abs() <= Tryte9::MAX as i64 {
+        Ok(value)
+    } else {
+        Err(CompileError::ValueOutOfRange { line, value })
+    }
+}
+
+/// Label of the pooled constant `value`: `K_5`, `K_M5`.
+fn constant_label(value: i64) -> String {
+    if value < 0 {
+        format!("K_M{}", -value)
+    } else {
+        format!("K_{}", value)
+    }
+}
+
+struct Generator<'a> {
+    source: Vec<&'a str>,
+    vars: BTreeSet<String>,
+    procs: BTreeSet<String>,
+    lines: Vec<String>,
+    constants: BTreeSet<i64>,
+    /// Number of temporaries needed.
+    temps: usize,
+    /// Number of branch labels issued.
+    labels: usize,
+    /// Number of call sites, each with a return point `R_n`.
+    returns: usize,
+    current_proc: Option<String>,
+}
+
+impl Generator<'_> {
+    fn raw(&mut self, line: String) {
+        self.lines.push(line);
+    }
+
+    fn instr(&mut self, text: &str) {
+        self.lines.push(format!("    {}", text));
+    }
+
+    fn label(&mut self, name: &str) {
+        self.lines.push(format!("{}:", name));
+    }
+
+    fn data(&mut self, name: &str, value: String) {
+        let label = format!("{}:", name);
+        self.lines.push(format!("{:<8}DAT {}", label, value));
+    }
+
+    /// Echo source line `line` as a comment.
+    fn comment(&mut self, line: usize) {
+        if let Some(text) = self.source.get(line - 1) {
+            self.lines.push(format!("    ; {}: {}", line, text.trim()));
+        }
+    }
+
+    fn new_label(&mut self) -> String {
+        self.labels += 1;
+        format!("L_{}", self.labels)
+    }
+
+    fn temp(&mut self, depth: usize) -> String {
+        self.temps = self.temps.max(depth + 1);
+        format!("T_{}", depth)
+    }
+
+    fn var(&self, name: &str, line: usize) -> Result<String, CompileError> {
+        if self.vars.contains(name) {
+            Ok(format!("V_{}", name))
+        } else {
+            Err(CompileError::Undefined { line, name: name.to_string() })
+        }
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) -> Result<(), CompileError> {
+        stmts.iter().try_for_each(|stmt| self.statement(stmt))
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), CompileError> {
+        self.comment(stmt.line);
+        match &stmt.kind {
+            StmtKind::Assign { name, value } => {
+                let target = self.var(name, stmt.line)?;
+                self.expr(value, 0)?;
+                self.instr(&format!("STA {}", target));
+            }
+            StmtKind::If { cond, then, otherwise } => {
+                let else_label = self.new_label();
+                self.cond(cond, &else_label)?;
+                self.block(then)?;
+                if otherwise.is_empty() {
+                    self.label(&else_label);
+                } else {
+                    let end_label = self.new_label();
+                    self.instr(&format!("JMP {}", end_label));
+                    self.label(&else_label);
+                    self.block(otherwise)?;
+                    self.label(&end_label);
+                }
+            }
+            StmtKind::While { cond, body } => {
+                let top = self.new_label();
+                let end = self.new_label();
+                self.label(&top);
+                self.cond(cond, &end)?;
+                self.block(body)?;
+                self.instr(&format!("JMP {}", top));
+                self.label(&end);
+            }
+            StmtKind::Call { name } => {
+                if !self.procs.contains(name) {
+                    return Err(CompileError::Undefined { line: stmt.line, name: name.clone() });
+                }
+                let n = self.returns;
+                self.returns += 1;
+                self.instr(&format!("LDF RA_{}", n));
+                self.instr(&format!("JMP P_{}", name));
+                self.label(&format!("R_{}", n));
+            }
+            StmtKind::Return => match self.current_proc.clone() {
+                Some(proc) => self.instr(&format!("JMP X_{}", proc)),
+                None => {
+                    return Err(CompileError::Syntax {
+                        line: stmt.line,
+                        message: "'return' outside a procedure (use 'halt')".into(),
+                    })
+                }
+            },
+            StmtKind::Print(value) => {
+                self.expr(value, 0)?;
+                self.instr(&format!("STA {}", TELETYPE_ADDR));
+            }
+            StmtKind::Read { name } => {
+                let target = self.var(name, stmt.line)?;
+                self.instr(&format!("LDA {}", TAPE_READER_ADDR));
+                self.instr(&format!("STA {}", target));
+            }
+            StmtKind::Halt(value) => {
+                if let Some(value) = value {
+                    self.expr(value, 0)?;
+                }
+                self.instr("HLT");
+            }
+        }
+        Ok(())
+    }
+
+    /// Jump to `false_label` unless `cond` holds.
+    fn cond(&mut self, cond: &Cond, false_label: &str) -> Result<(), CompileError> {
+        let op = match cond {
+            Cond::NonZero(value) => {
+                self.expr(value, 0)?;
+                self.instr(&format!("JZ {}", false_label));
+                return Ok(());
+            }
+            Cond::Compare(op, left, Expr::Number { value: 0, .. }) => {
+                self.expr(left, 0)?;
+                op
+            }
+            Cond::Compare(op, left, right) => {
+                let difference = Expr::Binary(BinOp::Sub, Box::new(left.clone()), Box::new(right.clone()));
+                self.expr(&difference, 0)?;
+                op
+            }
+        };
+        // S now holds left - right
+        let jumps: &[&str] = match op {
+            CmpOp::Eq => &["JGT", "JLT"],
+            CmpOp::Ne => &["JZ"],
+            CmpOp::Lt => &["JZ", "JGT"],
+            CmpOp::Le => &["JGT"],
+            CmpOp::Gt => &["JZ", "JLT"],
+            CmpOp::Ge => &["JLT"],
+        };
+        for jump in jumps {
+            self.instr(&format!("{} {}", jump, false_label));
+        }
+        Ok(())
+    }
+
+    /// A label usable directly as an operand, if `expr` is that simple.
+    fn operand(&mut self, expr: &Expr) -> Result<Option<String>, CompileError> {
+        Ok(match expr {
+            Expr::Number { value, line } => {
+                let value = in_range(*value, *line)?;
+                self.constants.insert(value);
+                Some(constant_label(value))
+            }
+            Expr::Var { name, line } => Some(self.var(name, *line)?),
+            _ => None,
+        })
+    }
+
+    /// Evaluate `expr` into S, using temporaries from `depth` up.
+    fn expr(&mut self, expr: &Expr, depth: usize) -> Result<(), CompileError> {
+        if let Some(operand) = self.operand(expr)? {
+            self.instr(&format!("LDA {}", operand));
+            return Ok(());
+        }
+        match expr {
+            Expr::Neg(inner) => {
+                let operand = match self.operand(inner)? {
+                    Some(operand) => operand,
+                    None => {
+                        self.expr(inner, depth)?;
+                        let temp = self.temp(depth);
+                        self.instr(&format!("STA {}", temp));
+                        temp
+                    }
+                };
+                self.constants.insert(0);
+                self.instr(&format!("LDA {}", constant_label(0)));
+                self.instr(&format!("SUB {}", operand));
+            }
+            Expr::Binary(BinOp::Mul, left, right) if constant_chain(left, right).is_some() => {
+                let (value, chain) = constant_chain(left, right).expect("checked by the guard");
+                self.mul_chain(value, &chain, depth)?;
+            }
+            Expr::Binary(op, left, right) => {
+                let (operand, depth) = match self.operand(right)? {
+                    Some(operand) => (operand, depth),
+                    None => {
+                        self.expr(right, depth)?;
+                        let temp = self.temp(depth);
+                        self.instr(&format!("STA {}", temp));
+                        (temp, depth + 1)
+                    }
+                };
+                self.expr(left, depth)?;
+                match op {
+                    BinOp::Add => self.instr(&format!("ADD {}", operand)),
+                    BinOp::Sub => self.instr(&format!("SUB {}", operand)),
+                    BinOp::Mul | BinOp::Rem => {
+                        // The product's low half and the remainder land in R
+                        let mnemonic = if *op == BinOp::Mul { "MUL" } else { "DIV" };
+                        self.instr(&format!("{} {}", mnemonic, operand));
+                        let temp = self.temp(depth);
+                        self.instr(&format!("STR {}", temp));
+                        self.instr(&format!("LDA {}", temp));
+                    }
+                    BinOp::Div => self.instr(&format!("DIV {}", operand)),
+                }
+            }
+            Expr::Number { .. } | Expr::Var { .. } => unreachable!("handled as operands"),
+        }
+        Ok(())
+    }
+
+    /// Multiply `value` by a constant into S with the shifts, additions
+    /// and subtractions of `chain`.
+    fn mul_chain(&mut self, value: &Expr, chain: &MulChain, depth: usize) -> Result<(), CompileError> {
+        let (operand, depth) = match self.operand(value)? {
+            Some(operand) => {
+                self.instr(&format!("LDA {}", operand));
+                (operand, depth)
+            }
+            None => {
+                self.expr(value, depth)?;
+                let temp = self.temp(depth);
+                if chain.steps.iter().any(|step| step.term == ChainTerm::Multiplicand) {
+                    self.instr(&format!("STA {}", temp));
+                }
+                (temp, depth + 1)
+            }
+        };
+        for step in &chain.steps {
+            let term = match step.term {
+                ChainTerm::None => None,
+                ChainTerm::Multiplicand => Some(operand.clone()),
+                ChainTerm::Running => {
+                    let temp = self.temp(depth);
+                    self.instr(&format!("STA {}", temp));
+                    Some(temp)
+                }
+            };
+            if step.shift > 0 {
+                self.instr(&format!("SHL {}", step.shift));
+            }
+            if let Some(term) = term {
+                let mnemonic = if step.sign == Trit::P { "ADD" } else { "SUB" };
+                self.instr(&format!("{} {}", mnemonic, term));
+            }
+        }
+        if chain.negate {
+            let temp = self.temp(depth);
+            self.constants.insert(0);
+            self.instr(&format!("STA {}", temp));
+            self.instr(&format!("LDA {}", constant_label(0)));
+            self.instr(&format!("SUB {}", temp));
+        }
+        Ok(())
+    }
+}
+
+/// Instructions `MUL`, `STR` and `LDA` take to bring a product to S.
+const MUL_COST: usize = 3;
+
+/// The other side of a multiplication by a constant, with the chain to
+/// use instead of `MUL` when it takes no more instructions. Both leave the
+/// same low 18 trits in S.
+fn constant_chain<'a>(left: &'a Expr, right: &'a Expr) -> Option<(&'a Expr, MulChain)> {
+    let constant = |expr: &Expr| match expr {
+        Expr::Number { value, .. } if value.unsigned_abs() <= Tryte9::MAX as u64 => MulChain::new(*value),
+        _ => None,
+    };
+    let (value, chain) = match constant(right) {
+        Some(chain) => (left, chain),
+        None => (right, constant(left)?),
+    };
+
+    // A value that is not an operand has to be saved to be added back
+    let mut cost = chain.negate as usize * 3;
+    if !matches!(value, Expr::Number { .. } | Expr::Var { .. })
+        && chain.steps.iter().any(|step| step.term == ChainTerm::Multiplicand) {
+        cost += 1;
+    }
+    for step in &chain.steps {
+        cost += (step.shift > 0) as usize + match step.term {
+            ChainTerm::None => 0,
+            ChainTerm::Multiplicand => 1,
+            ChainTerm::Running => 2,
+        };
+    }
+    (cost <= MUL_COST).then_some((value, chain))
+}
+
+/// Procedures reachable from each procedure's body, for recursion checks.
+pub fn call_graph(program: &Program) -> BTreeMap<String, Vec<(String, usize)>> {
+    fn calls(stmts: &[Stmt], out: &mut Vec<(String, usize)>) {
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::Call { name } => out.push((name.clone(), stmt.line)),
+                StmtKind::If { then, otherwise, .. } => {
+                    calls(then, out);
+                    calls(otherwise, out);
+                }
+                StmtKind::While { body, .. } => calls(body, out),
+                _ => {}
+            }
+        }
+    }
+    program
+        .procs
+        .iter()
+        .map(|proc| {
+            let mut out = Vec::new();
+            calls(&proc.body, &mut out);
+            (proc.name.clone(), out)
+        })
+        .collect()
+}
@@ -0,0 +1,151 @@
+//! Tokenizer for the structured language.
+
+use super::CompileError;
+use crate::ternary::convert::parse_number;
+
+/// One token kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// Identifier, uppercased.
+    Ident(String),
+    /// Integer literal (decimal, `0t` or `0n`).
+    Number(i64),
+    Var,
+    Proc,
+    If,
+    Else,
+    While,
+    Print,
+    Read,
+    Halt,
+    Return,
+    LBrace,
+    RBrace,
+    LParen,
+    RParen,
+    Semicolon,
+    Assign,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+}
+
+/// A token and the source line (1-based) it starts on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spanned {
+    pub token: Token,
+    pub line: usize,
+}
+
+/// Split `source` into tokens. `//` starts a comment running to the end of
+/// the line.
+pub fn tokenize(source: &str) -> Result<Vec<Spanned>, CompileError> {
+    let mut tokens = Vec::new();
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let text = text.split("//").next().unwrap_or("");
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if c.is_whitespace() {
+                i += 1;
+                continue;
+            }
+            if c.is_ascii_alphanumeric() || c == '_' {
+                let start = i;
+                // `0t` literals may spell trits with `+` and `-`
+                let ternary = c == '0' && matches!(chars.get(i + 1), Some('t' | 'T'));
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || (ternary && matches!(chars[i], '+' | '-')))
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                tokens.push(Spanned { token: word_token(&word, line)?, line });
+                continue;
+            }
+
+            let next = chars.get(i + 1).copied();
+            let (token, len) = match (c, next) {
+                ('=', Some('=')) => (Token::Eq, 2),
+                ('!', Some('=')) => (Token::Ne, 2),
+                ('<', Some('=')) => (Token::Le, 2),
+                ('>', Some('=')) => (Token::Ge, 2),
+                ('=', _) => (Token::Assign, 1),
+                ('<', _) => (Token::Lt, 1),
+                ('>', _) => (Token::Gt, 1),
+                ('{', _) => (Token::LBrace, 1),
+                ('}', _) => (Token::RBrace, 1),
+                ('(', _) => (Token::LParen, 1),
+                (')', _) => (Token::RParen, 1),
+                (';', _) => (Token::Semicolon, 1),
+                ('+', _) => (Token::Plus, 1),
+                ('-', _) => (Token::Minus, 1),
+                ('*', _) => (Token::Star, 1),
+                ('/', _) => (Token::Slash, 1),
+                ('%', _) => (Token::Percent, 1),
+                _ => {
+                    return Err(CompileError::Syntax {
+                        line,
+                        message: format!("unexpected character '{}'", c),
+                    })
+                }
+            };
+            tokens.push(Spanned { token, line });
+            i += len;
+        }
+    }
+    Ok(tokens)
+}
+
+/// Classify a word as a keyword, number or identifier.
+fn word_token(word: &str, line: usize) -> Result<Token, CompileError> {
+    if word.starts_with(|c: char| c.is_ascii_digit()) {
+        return parse_number(word).map(Token::Number).map_err(|e| CompileError::Syntax {
+            line,
+            message: format!("bad number '{}': {}", word, e),
+        });
+    }
+    let upper = word.to_uppercase();
+    Ok(match upper.as_str() {
+        "VAR" => Token::Var,
+        "PROC" => Token::Proc,
+        "IF" => Token::If,
+        "ELSE" => Token::Else,
+        "WHILE" => Token::While,
+        "PRINT" => Token::Print,
+        "READ" => Token::Read,
+        "HALT" => Token::Halt,
+        "RETURN" => Token::Return,
+        _ => Token::Ident(upper),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<Token> = tokenize("var x = 0t+-; // note\nIf x <= -3 { }")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token)
+            .collect();
+        assert_eq!(tokens, vec![
+            Token::Var, Token::Ident("X".into()), Token::Assign, Token::Number(2), Token::Semicolon,
+            Token::If, Token::Ident("X".into()), Token::Le, Token::Minus, Token::Number(3),
+            Token::LBrace, Token::RBrace,
+        ]);
+        assert!(matches!(tokenize("x = 1;\ny = $;"), Err(CompileError::Syntax { line: 2, .. })));
+    }
+}
@@ -0,0 +1,240 @@
+//! A tiny structured language compiled to Setun assembly.
+//!
+//! Programs are made of global variables, parameterless procedures and
+//! statements. Names are case-insensitive and every value is one memory
+//! word (±9841):
+//!
+//! ```text
+//! var n = 5;              // globals, initialized with a constant
+//! var fact = 1;
+//!
+//! proc step() {           // procedures use globals; no recursion
+//!     fact = fact * n;
+//!     n = n - 1;
+//! }
+//!
+//! while n > 0 { step(); }
+//! print fact;             // to the teletype
+//! halt fact;              // leave fact in S and stop
+//! ```
+//!
+//! Statements are `x = expr;`, `if cond { } else { }`, `while cond { }`,
+//! `name();`, `return;`, `print expr;`, `read x;` (from the tape reader)
+//! and `halt [expr];`. Expressions use `+ - * / %`, unary minus and
+//! parentheses over variables and decimal, `0t` or `0n` literals.
+//! Conditions compare two expressions with `== != < <= > >=`, or test a
+//! single expression for nonzero. `/` and `%` truncate toward zero, and
+//! products keep only their low word.
+//!
+//! [`compile`] produces assembly source, which goes through the regular
+//! [`assembler`](crate::asm); the generated code starts at the lowest
+//! address so the whole memory is available.
+//!
+//! ```
+//! use setun::lang::compile_program;
+//! use setun::Cpu;
+//!
+//! let assembly = compile_program("var x = 6;\nhalt x * 7;").unwrap();
+//! let mut cpu = Cpu::new();
+//! cpu.load_program_at(assembly.origin, &assembly.program).unwrap();
+//! cpu.set_entry(assembly.origin).unwrap();
+//! cpu.run_limited(100).unwrap();
+//! assert_eq!(cpu.regs.s.to_i64(), 42);
+//! ```
+
+pub mod codegen;
+pub mod lexer;
+pub mod parser;
+
+pub use parser::Program;
+
+use crate::asm::{assemble_detailed, AssemblerError, Assembly};
+use crate::cpu::Memory;
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+/// Errors reported by the compiler.
+#[derive(Debug, Clone, Error)]
+pub enum CompileError {
+    #[error("syntax error on line {line}: {message}")]
+    Syntax { line: usize, message: String },
+
+    #[error("undefined name on line {line}: {name}")]
+    Undefined { line: usize, name: String },
+
+    #[error("duplicate definition on line {line}: {name}")]
+    Duplicate { line: usize, name: String },
+
+    #[error("recursive call on line {line}: {name} can reach itself")]
+    Recursion { line: usize, name: String },
+
+    #[error("value out of range on line {line}: {value}")]
+    ValueOutOfRange { line: usize, value: i64 },
+
+    #[error("program needs {words} words but memory holds {available}")]
+    TooLarge { words: usize, available: usize },
+
+    #[error("generated assembly failed to assemble: {0}")]
+    Assembly(#[from] AssemblerError),
+}
+
+impl CompileError {
+    /// Source line (1-based) the error refers to, if any.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            CompileError::Syntax { line, .. }
+            | CompileError::Undefined { line, .. }
+            | CompileError::Duplicate { line, .. }
+            | CompileError::Recursion { line, .. }
+            | CompileError::ValueOutOfRange { line, .. } => Some(*line),
+            CompileError::TooLarge { .. } | CompileError::Assembly(_) => None,
+        }
+    }
+}
+
+/// Parse `source` and check names and the call graph.
+pub fn parse(source: &str) -> Result<Program, CompileError> {
+    let program = parser::parse(&lexer::tokenize(source)?)?;
+
+    let mut seen = BTreeSet::new();
+    let names = program.vars.iter().map(|v| (&v.name, v.line))
+        .chain(program.procs.iter().map(|p| (&p.name, p.line)));
+    for (name, line) in names {
+        if !seen.insert(name) {
+            return Err(CompileError::Duplicate { line, name: name.clone() });
+        }
+    }
+    check_recursion(&codegen::call_graph(&program))?;
+    Ok(program)
+}
+
+/// Compile `source` to assembly text.
+pub fn compile(source: &str) -> Result<String, CompileError> {
+    codegen::generate(&parse(source)?, source)
+}
+
+/// Compile and assemble `source`, checking that it fits in memory.
+pub fn compile_program(source: &str) -> Result<Assembly, CompileError> {
+    let assembly = assemble_detailed(&compile(source)?)?;
+    let mem = Memory::new();
+    let available = (mem.max_addr() - assembly.origin + 1) as usize;
+    if assembly.program.len() > available {
+        return Err(CompileError::TooLarge { words: assembly.program.len(), available });
+    }
+    Ok(assembly)
+}
+
+/// Fail if any procedure can call itself, directly or indirectly. Each
+/// procedure has a single return-address slot, so recursion would lose it.
+fn check_recursion(graph: &BTreeMap<String, Vec<(String, usize)>>) -> Result<(), CompileError> {
+    fn visit<'a>(
+        name: &'a str,
+        graph: &'a BTreeMap<String, Vec<(String, usize)>>,
+        path: &mut Vec<&'a str>,
+        done: &mut BTreeSet<&'a str>,
+    ) -> Result<(), CompileError> {
+        if done.contains(name) {
+            return Ok(());
+        }
+        path.push(name);
+        for (callee, line) in graph.get(name).into_iter().flatten() {
+            if path.contains(&callee.as_str()) {
+                return Err(CompileError::Recursion { line: *line, name: callee.clone() });
+            }
+            visit(callee, graph, path, done)?;
+        }
+        path.pop();
+        done.insert(name);
+        Ok(())
+    }
+
+    let mut done = BTreeSet::new();
+    for name in graph.keys() {
+        visit(name, graph, &mut Vec::new(), &mut done)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ternary::Tryte9;
+    use crate::Cpu;
+
+    /// Compile, run and return the CPU with what was printed.
+    fn run(source: &str, input: &[i32]) -> (Cpu, Vec<i32>) {
+        let assembly = compile_program(source).unwrap();
+        let mut cpu = Cpu::new();
+        cpu.io.enable();
+        cpu.io.feed(input.iter().map(|&v| Tryte9::from_i32(v)));
+        cpu.load_program_at(assembly.origin, &assembly.program).unwrap();
+        cpu.set_entry(assembly.origin).unwrap();
+        cpu.run_limited(10_000).unwrap();
+        let output = cpu.io.take_output().iter().map(|w| w.to_i32()).collect();
+        (cpu, output)
+    }
+
+    #[test]
+    fn test_arithmetic_and_control_flow() {
+        let source = "
+            var i = 0;
+            var total;
+            read total;
+            while i < 5 {
+                i = i + 1;
+                if i % 2 == 0 { total = total + i * i; } else if i == 5 { print -(i - 10) / 2; }
+            }
+            print total;
+            halt total - 3 * (2 - 7);
+        ";
+        let (cpu, output) = run(source, &[100]);
+        assert_eq!(output, vec![2, 120]);
+        assert_eq!(cpu.regs.s.to_i64(), 135);
+
+        // Powers of three compile to shifts
+        let source = "var x;\nread x;\nhalt 9 * x - x * 27 + x * 1;";
+        let assembly = compile(source).unwrap();
+        assert!(assembly.contains("SHL 2") && assembly.contains("SHL 3") && !assembly.contains("MUL"));
+        assert_eq!(run(source, &[-7]).0.regs.s.to_i64(), 119);
+
+        // Small constants compile to shift/add chains, others to MUL
+        let source = "var x;\nread x;\nprint x * 2;\nprint (x + 1) * 10;\nprint x * -3;\nhalt x * 5;";
+        let assembly = compile(source).unwrap();
+        assert!(assembly.contains("SUB V_X") && assembly.contains("ADD T_0"));
+        assert_eq!(assembly.matches("MUL").count(), 2);
+        let (cpu, output) = run(source, &[-7]);
+        assert_eq!(output, vec![-14, -60, 21]);
+        assert_eq!(cpu.regs.s.to_i64(), -35);
+    }
+
+    #[test]
+    fn test_procedures() {
+        let source = "
+            var n = 5;
+            var fact = 1;
+            proc step() {
+                if n <= 1 { return; }
+                fact = fact * n;
+                n = n - 1;
+            }
+            proc twice() { step(); step(); }
+            twice(); twice(); twice();
+            halt fact;
+        ";
+        let (cpu, _) = run(source, &[]);
+        assert_eq!(cpu.regs.s.to_i64(), 120);
+    }
+
+    #[test]
+    fn test_semantic_errors() {
+        assert!(matches!(compile("x = 1;"), Err(CompileError::Undefined { line: 1, .. })));
+        assert!(matches!(compile("var a;\nproc a() { }"), Err(CompileError::Duplicate { line: 2, .. })));
+        let cycle = "proc a() { b(); }\nproc b() {\n  if 1 { a(); }\n}";
+        assert!(matches!(parse(cycle), Err(CompileError::Recursion { line: 3, ref name }) if name == "A"));
+        assert!(matches!(compile("var a;\nreturn;"), Err(CompileError::Syntax { line: 2, .. })));
+        assert!(matches!(compile("var a = 9842;"), Err(CompileError::ValueOutOfRange { .. })));
+
+        let long = format!("var a;\n{}", "a = a + 1;\n".repeat(60));
+        assert!(matches!(compile_program(&long), Err(CompileError::TooLarge { words: 183, available: 162 })));
+    }
+}
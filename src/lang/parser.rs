@@ -0,0 +1,352 @@
+//! Recursive-descent parser producing the syntax tree.
+
+use super::lexer::{Spanned, Token};
+use super::CompileError;
+
+/// Binary arithmetic operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+/// Comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// An expression; its value is left in S.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Number { value: i64, line: usize },
+    Var { name: String, line: usize },
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+/// A branch condition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cond {
+    /// `left op right`
+    Compare(CmpOp, Expr, Expr),
+    /// A bare expression, true when nonzero.
+    NonZero(Expr),
+}
+
+/// A statement together with the line it starts on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub line: usize,
+}
+
+/// Statement forms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StmtKind {
+    Assign { name: String, value: Expr },
+    If { cond: Cond, then: Vec<Stmt>, otherwise: Vec<Stmt> },
+    While { cond: Cond, body: Vec<Stmt> },
+    Call { name: String },
+    Return,
+    Print(Expr),
+    Read { name: String },
+    Halt(Option<Expr>),
+}
+
+/// A global variable with its initial value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VarDecl {
+    pub name: String,
+    pub init: i64,
+    pub line: usize,
+}
+
+/// A parameterless procedure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proc {
+    pub name: String,
+    pub body: Vec<Stmt>,
+    pub line: usize,
+}
+
+/// A whole program: declarations, procedures and the main statements, in
+/// the order they appear.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Program {
+    pub vars: Vec<VarDecl>,
+    pub procs: Vec<Proc>,
+    pub main: Vec<Stmt>,
+}
+
+/// Parse a token stream into a [`Program`].
+pub fn parse(tokens: &[Spanned]) -> Result<Program, CompileError> {
+    let mut parser = Parser { tokens, pos: 0 };
+    let mut program = Program::default();
+    while let Some(token) = parser.peek() {
+        match token {
+            Token::Var => program.vars.push(parser.var_decl()?),
+            Token::Proc => program.procs.push(parser.proc_decl()?),
+            _ => program.main.push(parser.statement()?),
+        }
+    }
+    Ok(program)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Spanned],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.token)
+    }
+
+    /// Line of the next token, or of the last one at end of input.
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |t| t.line)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, CompileError> {
+        Err(CompileError::Syntax { line: self.line(), message: message.into() })
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|t| t.token.clone());
+        self.pos += 1;
+        token
+    }
+
+    /// Consume `token` if it is next.
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token, what: &str) -> Result<(), CompileError> {
+        if self.eat(&token) {
+            Ok(())
+        } else {
+            self.error(format!("expected {}", what))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, CompileError> {
+        match self.peek() {
+            Some(Token::Ident(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => self.error("expected a name"),
+        }
+    }
+
+    /// `var name [= constant];`
+    fn var_decl(&mut self) -> Result<VarDecl, CompileError> {
+        let line = self.line();
+        self.expect(Token::Var, "'var'")?;
+        let name = self.ident()?;
+        let mut init = 0;
+        if self.eat(&Token::Assign) {
+            let negative = self.eat(&Token::Minus);
+            match self.next() {
+                Some(Token::Number(value)) => init = if negative { -value } else { value },
+                _ => {
+                    self.pos -= 1;
+                    return self.error("variables must be initialized with a constant");
+                }
+            }
+        }
+        self.expect(Token::Semicolon, "';'")?;
+        Ok(VarDecl { name, init, line })
+    }
+
+    /// `proc name() { ... }`
+    fn proc_decl(&mut self) -> Result<Proc, CompileError> {
+        let line = self.line();
+        self.expect(Token::Proc, "'proc'")?;
+        let name = self.ident()?;
+        self.expect(Token::LParen, "'('")?;
+        self.expect(Token::RParen, "')' (procedures take no parameters)")?;
+        let body = self.block()?;
+        Ok(Proc { name, body, line })
+    }
+
+    /// `{ statement* }`
+    fn block(&mut self) -> Result<Vec<Stmt>, CompileError> {
+        self.expect(Token::LBrace, "'{'")?;
+        let mut body = Vec::new();
+        while !self.eat(&Token::RBrace) {
+            match self.peek() {
+                None => return self.error("unclosed '{'"),
+                Some(Token::Var | Token::Proc) => {
+                    return self.error("declarations are only allowed at the top level")
+                }
+                _ => body.push(self.statement()?),
+            }
+        }
+        Ok(body)
+    }
+
+    fn statement(&mut self) -> Result<Stmt, CompileError> {
+        let line = self.line();
+        let kind = match self.next() {
+            Some(Token::If) => {
+                let cond = self.condition()?;
+                let then = self.block()?;
+                let otherwise = if self.eat(&Token::Else) {
+                    if self.peek() == Some(&Token::If) {
+                        vec![self.statement()?]
+                    } else {
+                        self.block()?
+                    }
+                } else {
+                    Vec::new()
+                };
+                return Ok(Stmt { kind: StmtKind::If { cond, then, otherwise }, line });
+            }
+            Some(Token::While) => {
+                let cond = self.condition()?;
+                let body = self.block()?;
+                return Ok(Stmt { kind: StmtKind::While { cond, body }, line });
+            }
+            Some(Token::Return) => StmtKind::Return,
+            Some(Token::Print) => StmtKind::Print(self.expr()?),
+            Some(Token::Read) => StmtKind::Read { name: self.ident()? },
+            Some(Token::Halt) => {
+                if self.peek() == Some(&Token::Semicolon) {
+                    StmtKind::Halt(None)
+                } else {
+                    StmtKind::Halt(Some(self.expr()?))
+                }
+            }
+            Some(Token::Ident(name)) => {
+                if self.eat(&Token::LParen) {
+                    self.expect(Token::RParen, "')' (procedures take no parameters)")?;
+                    StmtKind::Call { name }
+                } else {
+                    self.expect(Token::Assign, "'=' or '('")?;
+                    StmtKind::Assign { name, value: self.expr()? }
+                }
+            }
+            _ => {
+                self.pos -= 1;
+                return self.error("expected a statement");
+            }
+        };
+        self.expect(Token::Semicolon, "';'")?;
+        Ok(Stmt { kind, line })
+    }
+
+    fn condition(&mut self) -> Result<Cond, CompileError> {
+        let left = self.expr()?;
+        let op = match self.peek() {
+            Some(Token::Eq) => CmpOp::Eq,
+            Some(Token::Ne) => CmpOp::Ne,
+            Some(Token::Lt) => CmpOp::Lt,
+            Some(Token::Le) => CmpOp::Le,
+            Some(Token::Gt) => CmpOp::Gt,
+            Some(Token::Ge) => CmpOp::Ge,
+            _ => return Ok(Cond::NonZero(left)),
+        };
+        self.pos += 1;
+        Ok(Cond::Compare(op, left, self.expr()?))
+    }
+
+    /// `term (('+' | '-') term)*`
+    fn expr(&mut self) -> Result<Expr, CompileError> {
+        let mut left = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinOp::Add,
+                Some(Token::Minus) => BinOp::Sub,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.term()?));
+        }
+    }
+
+    /// `unary (('*' | '/' | '%') unary)*`
+    fn term(&mut self) -> Result<Expr, CompileError> {
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => BinOp::Mul,
+                Some(Token::Slash) => BinOp::Div,
+                Some(Token::Percent) => BinOp::Rem,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        let line = self.line();
+        match self.next() {
+            Some(Token::Minus) => Ok(match self.unary()? {
+                // Fold negative literals so they become a single constant
+                Expr::Number { value, line } => Expr::Number { value: -value, line },
+                inner => Expr::Neg(Box::new(inner)),
+            }),
+            Some(Token::Number(value)) => Ok(Expr::Number { value, line }),
+            Some(Token::Ident(name)) => Ok(Expr::Var { name, line }),
+            Some(Token::LParen) => {
+                let inner = self.expr()?;
+                self.expect(Token::RParen, "')'")?;
+                Ok(inner)
+            }
+            _ => {
+                self.pos -= 1;
+                self.error("expected an expression")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::lexer::tokenize;
+
+    fn parse_source(source: &str) -> Result<Program, CompileError> {
+        parse(&tokenize(source)?)
+    }
+
+    #[test]
+    fn test_precedence_and_structure() {
+        let program = parse_source("var x = -2;\nproc p() { return; }\nx = 1 + x * -3;\np();").unwrap();
+        assert_eq!(program.vars, vec![VarDecl { name: "X".into(), init: -2, line: 1 }]);
+        assert_eq!(program.procs[0].body[0].kind, StmtKind::Return);
+        let StmtKind::Assign { value, .. } = &program.main[0].kind else { panic!("not an assignment") };
+        let Expr::Binary(BinOp::Add, _, right) = value else { panic!("'+' should bind loosest") };
+        assert!(matches!(**right, Expr::Binary(BinOp::Mul, _, ref k) if **k == Expr::Number { value: -3, line: 3 }));
+        assert_eq!(program.main[1].kind, StmtKind::Call { name: "P".into() });
+    }
+
+    #[test]
+    fn test_syntax_errors_report_lines() {
+        let err = parse_source("x = 1;\nwhile x { x = x - 1 }").unwrap_err();
+        assert!(matches!(err, CompileError::Syntax { line: 2, ref message } if message == "expected ';'"));
+        assert!(matches!(parse_source("if x {\n  var y;\n}"), Err(CompileError::Syntax { line: 2, .. })));
+        assert!(matches!(parse_source("var y = z;"), Err(CompileError::Syntax { line: 1, .. })));
+    }
+}
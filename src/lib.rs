@@ -6,19 +6,50 @@
 //! for practical use. This emulator faithfully recreates its architecture
 //! for educational purposes.
 
+#[macro_use]
+mod instrument;
+
 pub mod ternary;
 pub mod cpu;
 pub mod asm;
+pub mod diagnostic;
+pub mod repl;
+pub mod examples;
+pub mod harness;
+pub mod equiv;
+pub mod simulate;
+pub mod bench;
+pub mod testing;
+pub mod report;
+pub mod session;
+pub mod events;
+pub mod lang;
 
 #[cfg(feature = "tui")]
 pub mod tui;
 
+#[cfg(feature = "serve")]
+pub mod server;
+
+#[cfg(feature = "gdb")]
+pub mod gdb;
+
+#[cfg(feature = "script")]
+pub mod script;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "python")]
+pub mod python;
+
 #[cfg(feature = "wasm")]
 pub mod wasm;
 
 // Re-export commonly used types
 pub use ternary::{Trit, Tryte9, Word18};
 pub use cpu::{Cpu, CpuState, CpuError, Memory, Registers, Instruction};
+pub use session::EmulatorSession;
 pub use asm::{assemble, disassemble, AssemblerError, TromFile, load_trom, save_trom};
 
 #[cfg(feature = "tui")]
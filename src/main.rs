@@ -3,10 +3,31 @@
 //! Commands:
 //! - `setun-emu run <program>` - Run a TROM or ASM file
 //! - `setun-emu debug <program>` - Interactive debugger (Phase 4)
+//! - `setun-emu debug <program> --gdb :3333` - Debug from GDB over the remote protocol
 //! - `setun-emu asm <source>` - Assemble to TROM
+//! - `setun-emu compile <source.tl>` - Compile the structured language to assembly
 //! - `setun-emu disasm <trom>` - Disassemble TROM
+//! - `setun-emu convert <value>` - Convert between number notations
+//! - `setun-emu repl` - Interactive ternary REPL
+//! - `setun-emu profile <program>` - Run with execution profiling
+//! - `setun-emu stats <dir>` - Opcode, address-mode and size statistics of many programs
+//! - `setun-emu check <source>` - Validate assembly without writing output
+//! - `setun-emu diff <a> <b>` - Compare two programs word by word
+//! - `setun-emu verify <program>` - Check the emulator against the reference model
+//! - `setun-emu examples list|show|run` - Bundled sample programs
+//! - `setun-emu test [dir]` - Self-test, or run `;@ expect` tests in a directory
+//! - `setun-emu serve --port N` - JSON control protocol over WebSocket/HTTP
+//! - `setun-emu report <program>` - Standalone HTML trace report
+//! - `setun-emu access-map <program>` - Memory accesses over time as SVG, CSV or DOT
+//! - `setun-emu bench` - Measure emulation throughput
+//! - `setun-emu isa` - Describe the instruction set as a table, JSON or TOML
+//! - `setun-emu dump <snapshot>` - Write a snapshot's memory image
+//! - `setun-emu script <file.rhai>` - Run a Rhai automation script
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use setun::cpu::{TraceFormat, VcdFormat};
+use setun::asm::disasm::DisasmFormat;
+use setun::report::AccessFormat;
 
 #[derive(Parser)]
 #[command(name = "setun-emu")]
@@ -16,25 +37,34 @@ use clap::{Parser, Subcommand};
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    /// Print machine-readable JSON instead of text (run, asm, compile, disasm, profile, test, check, diff, verify)
+    #[arg(long, global = true)]
+    json: bool,
+    /// Log emulator internals to stderr with a tracing filter such as
+    /// `debug` or `setun::cpu=trace` (defaults to `RUST_LOG`)
+    #[cfg(feature = "tracing")]
+    #[arg(long, global = true, value_name = "FILTER")]
+    log: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Run a program until it halts
-    Run {
-        /// Path to the TROM or ASM file to execute
-        program: String,
-        /// Maximum number of cycles to run (default: 10000)
-        #[arg(short, long, default_value = "10000")]
-        max_cycles: u64,
-        /// Show trace output
-        #[arg(short, long)]
-        trace: bool,
-    },
+    Run(Box<RunArgs>),
     /// Interactive debugger (coming in Phase 4)
+    #[cfg(feature = "tui")]
     Debug {
         /// Path to the TROM file to debug
-        program: String,
+        #[arg(required_unless_present = "restore_state")]
+        program: Option<String>,
+        #[command(flatten)]
+        layout: LayoutArgs,
+        #[command(flatten)]
+        state: StateArgs,
+        /// Serve the GDB remote protocol on this address (e.g. `:3333`) instead of the TUI
+        #[cfg(feature = "gdb")]
+        #[arg(long, value_name = "ADDR")]
+        gdb: Option<String>,
     },
     /// Assemble source to TROM
     Asm {
@@ -43,253 +73,2561 @@ enum Commands {
         /// Output TROM file
         #[arg(short, long)]
         output: Option<String>,
+        /// Reassemble whenever the source file changes
+        #[arg(short, long)]
+        watch: bool,
+        /// Write a boot tape for `run --boot-from-tape` instead of a TROM file
+        #[arg(long, conflicts_with = "watch")]
+        boot_tape: bool,
+    },
+    /// Link the sources of a project file into one image, with symbols and a listing
+    Build {
+        /// Path of the project file
+        #[arg(default_value = "setun.toml")]
+        project: String,
+    },
+    /// Compile a structured-language (.tl) program to Setun assembly
+    Compile {
+        /// Path to the source file
+        source: String,
+        /// Output assembly file ("-" for stdout); defaults to the source with .asm
+        #[arg(short, long)]
+        output: Option<String>,
     },
     /// Disassemble TROM to readable text
     Disasm {
         /// Path to the TROM file
         trom: String,
+        /// Run the program first and mark which cells and branch directions it exercised
+        #[arg(long)]
+        coverage: bool,
+        /// With --coverage, the maximum number of cycles to run
+        #[arg(short, long, default_value = "10000", requires = "coverage")]
+        max_cycles: u64,
+        /// Write the disassembly to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Output layout: asm (reassemblable source), listing, or json
+        #[arg(long, default_value_t = DisasmFormat::Listing)]
+        format: DisasmFormat,
+        /// Name addresses from a symbol file: `asm --json` output, a JSON
+        /// object of name to address, or `NAME = addr` lines
+        #[arg(long)]
+        symbols: Option<String>,
+        /// Invent labels (L12, LN5 for -5) for the cells instructions refer to
+        #[arg(long)]
+        labels: bool,
+    },
+    /// Run the built-in self-test, or the .asm tests in a directory
+    Test {
+        /// Directory of .asm programs with `;@ expect` directives
+        dir: Option<String>,
+    },
+    /// Convert values between decimal, ternary, trit-pattern and base-9 forms
+    Convert {
+        /// Values to convert (decimal, 0t ternary, or 0n base-9); reads stdin if omitted or "-"
+        #[arg(allow_negative_numbers = true)]
+        values: Vec<String>,
+        /// Word width in trits (9 or 18)
+        #[arg(short, long, default_value = "9")]
+        width: usize,
+        /// Treat the values as one expression (`5 + 7`, `-3 * 0t+-`) and explain it trit by trit
+        #[arg(long)]
+        explain: bool,
+        /// With --explain, print the explanation as an HTML fragment
+        #[arg(long, requires = "explain")]
+        html: bool,
+    },
+    /// Interactive REPL for ternary arithmetic, memory and stepping
+    Repl,
+    /// Run a program and report execution counts and branch statistics
+    Profile {
+        /// Path to the TROM or ASM file to profile
+        program: String,
+        /// Maximum number of cycles to run
+        #[arg(short, long, default_value = "10000")]
+        max_cycles: u64,
+        /// Enable the instruction timing model to report emulated time
+        #[arg(long)]
+        timing: bool,
+        /// Number of hottest addresses to list
+        #[arg(long, default_value = "10")]
+        top: usize,
+        /// Cost model (JSON or TOML) to report the program's total cost under
+        #[arg(long, value_name = "FILE")]
+        cost: Option<String>,
+    },
+    /// Check assembly source for errors and lint warnings without writing output
+    Check {
+        /// Path to the source file
+        source: String,
+        /// Treat warnings as errors
+        #[arg(long)]
+        strict: bool,
+        /// Machine preset to check for (simplified or historical); historical
+        /// adds warnings about code placed across drum zones
+        #[arg(long, default_value = "simplified")]
+        preset: String,
+    },
+    /// Check a program step by step against the reference model; exits with 1 on divergence
+    Verify {
+        /// Path to the TROM or ASM file to verify
+        program: String,
+        /// Maximum number of cycles to run
+        #[arg(short, long, default_value = "10000")]
+        max_cycles: u64,
+        /// Paper tape input file
+        #[arg(long)]
+        input: Option<String>,
+    },
+    /// Write a standalone HTML report (timeline, register plots, memory heatmap, disassembly)
+    Report {
+        /// TROM or ASM program to run, or a JSON Lines trace saved with `run --trace-file`
+        input: String,
+        /// Output HTML file (defaults to the input name with an .html extension)
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Maximum number of cycles to run
+        #[arg(short, long, default_value = "10000")]
+        max_cycles: u64,
+        /// Paper tape input file
+        #[arg(long)]
+        tape: Option<String>,
+    },
+    /// Export memory reads, writes and fetches over time as an SVG plot, CSV or DOT graph
+    AccessMap {
+        /// TROM or ASM program to run, or a JSON Lines trace saved with `run --trace-file`
+        input: String,
+        /// Output file (defaults to the input name with the format's extension)
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Output format: svg, csv or dot (defaults to the output file's extension, then svg)
+        #[arg(short, long)]
+        format: Option<AccessFormat>,
+        /// Maximum number of cycles to run
+        #[arg(short, long, default_value = "10000")]
+        max_cycles: u64,
+        /// Paper tape input file
+        #[arg(long)]
+        tape: Option<String>,
+    },
+    /// Compare two TROM (or ASM) programs; exits with 1 if they differ
+    Diff {
+        /// The old program
+        old: String,
+        /// The new program
+        new: String,
+    },
+    /// Check that two programs end the same way for every value of their input cells;
+    /// exits with 1 on a counterexample
+    Equiv {
+        /// The first program
+        a: String,
+        /// The second program
+        b: String,
+        /// Input cells to enumerate: `addr` for every value, or `addr=min..max`
+        #[arg(long, value_delimiter = ',', allow_negative_numbers = true)]
+        inputs: Vec<setun::equiv::InputCell>,
+        /// Cells inside the programs to compare as well (other cells holding code are skipped)
+        #[arg(long, value_delimiter = ',', allow_negative_numbers = true)]
+        outputs: Vec<i32>,
+        /// Maximum number of cycles for each run
+        #[arg(short, long, default_value = "10000")]
+        max_cycles: u64,
+        /// Refuse to check more input combinations than this
+        #[arg(long, default_value = "1000000")]
+        max_cases: u64,
+    },
+    /// Run a program many times with random input cells and report the spread of results
+    Simulate {
+        /// Path to the TROM or ASM file
+        program: String,
+        /// Input cells to randomize: `addr` for any value, or `addr=min..max`
+        #[arg(long, value_delimiter = ',', allow_negative_numbers = true)]
+        random_inputs: Vec<setun::equiv::InputCell>,
+        /// Number of runs
+        #[arg(short = 'n', long, default_value = "1000")]
+        trials: u64,
+        /// Seed for the input values
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Maximum number of cycles for each run
+        #[arg(short, long, default_value = "10000")]
+        max_cycles: u64,
+    },
+    /// List, show, or run the bundled example programs
+    Examples {
+        #[command(subcommand)]
+        command: ExamplesCommand,
+    },
+    /// Measure emulation speed on standard workloads in each execution mode
+    Bench {
+        /// Instructions to execute per measurement
+        #[arg(short = 'n', long, default_value = "1000000")]
+        instructions: u64,
+        /// Only run this workload (add-loop, multiply, memory-churn)
+        #[arg(short, long)]
+        workload: Option<String>,
+        /// Only use this mode (run, step, step-many, traced, profiled)
+        #[arg(short, long)]
+        mode: Option<setun::bench::Mode>,
+    },
+    /// Describe the instruction set as a table, JSON or TOML
+    Isa {
+        /// Output format: text, json or toml
+        #[arg(short, long, default_value = "text")]
+        format: String,
+        /// Machine preset whose instruction set to describe (simplified or historical)
+        #[arg(long, default_value = "simplified")]
+        preset: String,
+        /// Output file; prints if omitted
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Show a program, TROM image or snapshot's memory cell by cell
+    View {
+        /// Program (.asm, .tl, .trom) or snapshot (.json)
+        file: String,
+        /// Only show cells holding this value (any notation) or whose trits or disassembly contain this text
+        #[arg(short, long)]
+        grep: Option<String>,
+        /// Page to show, from 1
+        #[arg(short, long, default_value = "1")]
+        page: usize,
+        /// Rows per page; 0 shows everything
+        #[arg(long, default_value = "54")]
+        page_size: usize,
+    },
+    /// Count opcodes, address modes and program sizes over every .asm and
+    /// .trom file in a directory and its subdirectories
+    Stats {
+        /// Directory to scan
+        dir: String,
+        /// Machine preset to decode for (simplified or historical)
+        #[arg(long, default_value = "simplified")]
+        preset: String,
+    },
+    /// Write the memory image of a saved snapshot
+    Dump {
+        /// Snapshot file (JSON)
+        snapshot: String,
+        /// Output file (.trom for every cell, otherwise `addr = value` lines); prints if omitted
+        #[arg(short, long)]
+        output: Option<String>,
     },
-    /// Run the built-in self-test
-    Test,
+    /// Run a Rhai script that drives the emulator
+    #[cfg(feature = "script")]
+    Script {
+        /// Script file (.rhai)
+        file: String,
+        /// Program to load before the script starts
+        #[arg(short, long)]
+        program: Option<String>,
+    },
+    /// Serve the emulator over WebSocket/HTTP with a JSON protocol
+    #[cfg(feature = "serve")]
+    Serve {
+        /// Port to listen on
+        #[arg(short, long, default_value = "8080")]
+        port: u16,
+        /// Address to bind to
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExamplesCommand {
+    /// List the bundled examples
+    List,
+    /// Print the source of an example
+    Show {
+        /// Example name
+        name: String,
+    },
+    /// Assemble and run an example
+    Run {
+        /// Example name
+        name: String,
+        /// Maximum number of cycles to run
+        #[arg(short, long, default_value = "10000")]
+        max_cycles: u64,
+        /// Show trace output
+        #[arg(short, long)]
+        trace: bool,
+    },
+}
+
+/// Options for the `run` command.
+#[derive(Args)]
+struct RunArgs {
+    /// Path to the TROM or ASM file to execute
+    #[arg(required_unless_present_any = ["restore_state", "boot_from_tape"])]
+    program: Option<String>,
+    /// Maximum number of cycles to run (default: 10000)
+    #[arg(short, long, default_value = "10000")]
+    max_cycles: u64,
+    /// Show trace output
+    #[arg(short, long)]
+    trace: bool,
+    /// Write a structured trace (registers, deltas, memory accesses) to a file
+    #[arg(long)]
+    trace_file: Option<String>,
+    /// Format of the trace file: jsonl or csv
+    #[arg(long, default_value = "jsonl")]
+    trace_format: TraceFormat,
+    /// Write a VCD waveform of the registers and ω, viewable in GTKWave
+    #[arg(long)]
+    vcd: Option<String>,
+    /// Memory addresses to include in the VCD waveform, e.g. --vcd-cells=-3,10
+    #[arg(long, value_delimiter = ',', allow_negative_numbers = true, requires = "vcd")]
+    vcd_cells: Vec<i32>,
+    /// Encoding of VCD signals: trits (two bits per trit) or real
+    #[arg(long, default_value = "trits", requires = "vcd")]
+    vcd_format: VcdFormat,
+    /// Re-run whenever the program file changes, showing how the final
+    /// state differs from the previous run
+    #[arg(short, long, conflicts_with_all = [
+        "trace", "trace_file", "vcd", "output", "dump_mem", "boot_from_tape", "restore_state", "save_state",
+    ])]
+    watch: bool,
+    /// Paper tape input file (defaults to stdin when piped)
+    #[arg(long)]
+    input: Option<String>,
+    /// Write teletype output to a file instead of stdout
+    #[arg(long)]
+    output: Option<String>,
+    /// Only print the program's teletype output
+    #[arg(short, long)]
+    quiet: bool,
+    /// Preset memory from a file of `addr = value` lines (or a TROM data segment)
+    #[arg(long)]
+    mem_init: Option<String>,
+    /// Write the final memory image to a file (.trom for every cell, .lst for an
+    /// annotated listing with disassembly, otherwise `addr = value` lines)
+    #[arg(long)]
+    dump_mem: Option<String>,
+    /// Replace memory with an edited listing from --dump-mem before running
+    /// (cells it does not mention are zeroed)
+    #[arg(long)]
+    restore_mem: Option<String>,
+    /// Make addresses read-only: START..END or "program" (the loaded image),
+    /// optionally followed by :trap, :discard or :log (default trap); repeatable
+    #[arg(long, allow_hyphen_values = true)]
+    read_only: Vec<String>,
+    /// Enable indirect addressing: mode trit N loads the operand address from memory
+    #[arg(long)]
+    indirect: bool,
+    /// Wrap indexed addresses that run off one end of memory around to the
+    /// other end instead of stopping with an error
+    #[arg(long)]
+    wrap_addresses: bool,
+    /// Make SHL and SHM set ω to the sign of the trits shifted out of S,
+    /// so ω is 0 unless the shift lost significant trits
+    #[arg(long)]
+    strict_shifts: bool,
+    /// Reserve cells LIMIT..BASE for a stack with F as its pointer: F starts
+    /// at BASE, and PUSH past LIMIT or POP past BASE stops with an error
+    #[arg(long, value_name = "LIMIT..BASE", allow_hyphen_values = true)]
+    stack: Option<setun::cpu::StackRegion>,
+    /// Corrupt one random trit after every N instructions
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    inject_faults: Option<u64>,
+    /// Where injected faults land: any, memory or registers
+    #[arg(long, default_value = "any", requires = "inject_faults")]
+    fault_target: setun::cpu::FaultTarget,
+    /// Seed for choosing fault sites, so a faulty run can be repeated
+    #[arg(long, requires = "inject_faults")]
+    fault_seed: Option<u64>,
+    /// Attach the random number device (a random word at 118, a random trit at 119)
+    #[arg(long)]
+    rng: bool,
+    /// Seed for the random number device
+    #[arg(long, requires = "rng")]
+    rng_seed: Option<u64>,
+    /// Enable semihosting: `HOST n` calls host service n (1 print S,
+    /// 2 read a number from stdin into S, 3 time of day, 4 exit with code S)
+    #[arg(long)]
+    semihost: bool,
+    /// Attach the character console at 117, reading typed text from stdin
+    /// (instead of the tape reader) and printing to stdout
+    #[arg(long)]
+    console: bool,
+    /// Cost model (JSON or TOML) charging per opcode and memory access; the total is reported with the cycles
+    #[arg(long, value_name = "FILE")]
+    cost: Option<String>,
+    /// Load another image alongside the program, at ADDR or its own ORG
+    /// (repeatable); images that overlap each other are refused
+    #[arg(long, value_name = "FILE[@ADDR]")]
+    segment: Vec<String>,
+    /// Start from the bootstrap loader, reading the program from a boot
+    /// tape (as written by `asm --boot-tape`) instead of loading a file
+    #[arg(long, value_name = "TAPE", conflicts_with_all = ["program", "restore_state", "segment"])]
+    boot_from_tape: Option<String>,
+    #[command(flatten)]
+    layout: LayoutArgs,
+    #[command(flatten)]
+    state: StateArgs,
+}
+
+/// Snapshot files to resume from and to save to.
+#[derive(Args, Default)]
+struct StateArgs {
+    /// Save the machine state to a snapshot file when finished
+    #[arg(long)]
+    save_state: Option<String>,
+    /// Resume from a snapshot file instead of loading a program
+    #[arg(long, conflicts_with = "program")]
+    restore_state: Option<String>,
+}
+
+impl RunArgs {
+    /// Instruction extensions assembly may use: HOST with --semihost.
+    fn extensions(&self) -> setun::cpu::ExtensionRegistry {
+        let mut extensions = setun::cpu::ExtensionRegistry::default();
+        if self.semihost {
+            extensions.register(setun::cpu::Semihost::new().extension()).expect("HOST opcode is free");
+        }
+        extensions
+    }
+    
+    /// The machine configuration selected by the flags, exiting if it is invalid.
+    fn cpu_config(&self, json: bool) -> setun::cpu::CpuConfig {
+        use setun::cpu::AddressPolicy;
+        
+        let config = setun::cpu::CpuConfig {
+            indirect: self.indirect,
+            addressing: if self.wrap_addresses { AddressPolicy::Wrap } else { AddressPolicy::Trap },
+            strict_shifts: self.strict_shifts,
+            stack: self.stack,
+            ..Default::default()
+        };
+        if let Err(e) = config.validate() {
+            fail(json, e);
+        }
+        config
+    }
+}
+
+impl StateArgs {
+    /// Load the `--restore-state` snapshot, if one was given.
+    fn restore(&self, json: bool) -> Option<setun::cpu::Snapshot> {
+        let path = self.restore_state.as_ref()?;
+        match setun::cpu::Snapshot::load(path) {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) => fail(json, format!("Failed to restore {}: {}", path, e)),
+        }
+    }
+    
+    /// Write `snapshot` to the `--save-state` file, if one was given.
+    fn save(&self, snapshot: &setun::cpu::Snapshot) -> Result<(), String> {
+        match &self.save_state {
+            Some(path) => snapshot.save(path).map_err(|e| format!("Failed to save state to {}: {}", path, e)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Where a program is placed in memory and where it starts.
+#[derive(Args, Default)]
+struct LayoutArgs {
+    /// Address to start executing at (default: the load address)
+    #[arg(long, allow_hyphen_values = true)]
+    entry: Option<i32>,
+    /// Address to load the first word at (default: the program's ORG, or 0)
+    #[arg(long, allow_hyphen_values = true)]
+    load_at: Option<i32>,
+}
+
+impl LayoutArgs {
+    /// Load `instructions` into `cpu` and set the PC, given the program's own origin.
+    fn place(&self, cpu: &mut setun::Cpu, instructions: &[setun::Tryte9], origin: i32) -> Result<(), String> {
+        let (load_at, entry) = self.resolve(origin);
+        cpu.load_program_at(load_at, instructions)
+            .map_err(|e| format!("Failed to load program at {}: {}", load_at, e))?;
+        cpu.set_entry(entry)
+            .map_err(|e| format!("Invalid entry point {}: {}", entry, e))
+    }
+    
+    /// Resolved `(load address, entry point)` for a program with the given origin.
+    fn resolve(&self, origin: i32) -> (i32, i32) {
+        let load_at = self.load_at.unwrap_or(origin);
+        (load_at, self.entry.unwrap_or(load_at))
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let json = cli.json;
+    #[cfg(feature = "tracing")]
+    init_tracing(cli.log.as_deref());
+
+    match cli.command {
+        Some(Commands::Run(args)) if args.watch => {
+            watch_run(&args);
+        }
+        Some(Commands::Run(args)) => {
+            run_program(&args, json);
+        }
+        #[cfg(feature = "tui")]
+        Some(Commands::Debug { program, layout, state, #[cfg(feature = "gdb")] gdb }) => {
+            let app = debugger_app(program.as_deref(), &layout, &state);
+            #[cfg(feature = "gdb")]
+            if let Some(addr) = gdb {
+                debug_over_gdb(&addr, app.snapshot(), &state);
+                return;
+            }
+            debug_in_terminal(app, &state);
+        }
+        Some(Commands::Asm { source, output, watch: true, .. }) => {
+            watch_asm(&source, output);
+        }
+        Some(Commands::Asm { source, output, watch: false, boot_tape }) => {
+            assemble_file(&source, output, boot_tape, json);
+        }
+        Some(Commands::Build { project }) => {
+            build_project(&project, json);
+        }
+        Some(Commands::Compile { source, output }) => {
+            compile_file(&source, output, json);
+        }
+        Some(Commands::Disasm { trom, coverage, max_cycles, output, format, symbols, labels }) => {
+            let request = DisasmRequest { output, format, symbols, labels };
+            disassemble_file(&trom, coverage.then_some(max_cycles), &request, json);
+        }
+        Some(Commands::Test { dir: Some(dir) }) => {
+            run_program_tests(&dir, json);
+        }
+        Some(Commands::Test { dir: None }) => {
+            run_self_test(json);
+        }
+        Some(Commands::Convert { values, explain: true, html, .. }) => {
+            explain_arithmetic(&values, html, json);
+        }
+        Some(Commands::Convert { values, width, .. }) => {
+            convert_values(&values, width);
+        }
+        Some(Commands::Repl) => {
+            run_repl();
+        }
+        Some(Commands::Profile { program, max_cycles, timing, top, cost }) => {
+            profile_program(&program, max_cycles, timing, top, cost.as_deref(), json);
+        }
+        Some(Commands::Check { source, strict, preset }) => {
+            check_source(&source, strict, &preset, json);
+        }
+        Some(Commands::Verify { program, max_cycles, input }) => {
+            verify_program(&program, max_cycles, input.as_deref(), json);
+        }
+        Some(Commands::Report { input, output, max_cycles, tape }) => {
+            report_program(&input, output.as_deref(), max_cycles, tape.as_deref());
+        }
+        Some(Commands::AccessMap { input, output, format, max_cycles, tape }) => {
+            access_map(&input, output.as_deref(), format, max_cycles, tape.as_deref());
+        }
+        Some(Commands::Diff { old, new }) => {
+            diff_programs(&old, &new, json);
+        }
+        Some(Commands::Equiv { a, b, inputs, outputs, max_cycles, max_cases }) => {
+            let options = setun::equiv::EquivOptions { inputs, outputs, max_cycles, max_cases };
+            equiv_programs(&a, &b, &options, json);
+        }
+        Some(Commands::Simulate { program, random_inputs, trials, seed, max_cycles }) => {
+            let options = setun::simulate::SimOptions {
+                inputs: random_inputs,
+                trials,
+                seed: seed.unwrap_or(setun::cpu::random::DEFAULT_SEED),
+                max_cycles,
+            };
+            simulate_program(&program, &options, json);
+        }
+        Some(Commands::Examples { command }) => {
+            examples_command(command, json);
+        }
+        Some(Commands::Bench { instructions, workload, mode }) => {
+            run_bench(instructions, workload.as_deref(), mode, json);
+        }
+        Some(Commands::Isa { format, preset, output }) => {
+            describe_isa(&format, &preset, output.as_deref(), json);
+        }
+        Some(Commands::View { file, grep, page, page_size }) => {
+            view_image(&file, grep.as_deref(), page, page_size, json);
+        }
+        Some(Commands::Stats { dir, preset }) => {
+            corpus_stats(&dir, &preset, json);
+        }
+        Some(Commands::Dump { snapshot, output }) => {
+            dump_snapshot(&snapshot, output.as_deref(), json);
+        }
+        #[cfg(feature = "script")]
+        Some(Commands::Script { file, program }) => {
+            run_script(&file, program.as_deref(), json);
+        }
+        #[cfg(feature = "serve")]
+        Some(Commands::Serve { port, host }) => {
+            serve(&host, port);
+        }
+        None => {
+            println!("Setun Emulator v0.1.0");
+            println!("A balanced ternary computer emulator");
+            println!();
+            println!("Use --help for available commands");
+            println!();
+            demo_ternary_primitives();
+        }
+    }
+}
+
+/// Load a program from an ASM or TROM file.
+fn try_load_program(path: &str) -> Result<Vec<setun::Tryte9>, String> {
+    try_load_image(path).map(|(instructions, _)| instructions)
+}
+
+/// Load a program and the address its first word belongs at (its `ORG`;
+/// TROM files carry no origin and load at 0).
+fn try_load_image(path: &str) -> Result<(Vec<setun::Tryte9>, i32), String> {
+    try_load_image_with(path, &setun::cpu::ExtensionRegistry::default())
+}
+
+/// [`try_load_image`] for assembly that may use the mnemonics of `extensions`.
+fn try_load_image_with(path: &str, extensions: &setun::cpu::ExtensionRegistry) -> Result<(Vec<setun::Tryte9>, i32), String> {
+    use setun::load_trom;
+    use setun::asm::assembler::assemble_detailed_with_extensions;
+    
+    if path.ends_with(".asm") {
+        // Assemble first
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        assemble_detailed_with_extensions(&source, extensions)
+            .map(|assembly| (assembly.program, assembly.origin))
+            .map_err(|e| format!("Assembly error: {}", e))
+    } else if path.ends_with(".tl") {
+        // Compile, keeping the origin like assembly sources
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        setun::lang::compile_program(&source)
+            .map(|assembly| (assembly.program, assembly.origin))
+            .map_err(|e| format!("Compile error: {}", e))
+    } else {
+        load_trom(path)
+            .map(|trom| (trom.instructions, 0))
+            .map_err(|e| format!("Failed to load TROM: {}", e))
+    }
+}
+
+fn load_program_file(path: &str, quiet: bool, json: bool) -> (Vec<setun::Tryte9>, i32) {
+    load_program_file_with(path, quiet, json, &setun::cpu::ExtensionRegistry::default())
+}
+
+fn load_program_file_with(path: &str, quiet: bool, json: bool, extensions: &setun::cpu::ExtensionRegistry) -> (Vec<setun::Tryte9>, i32) {
+    let (instructions, origin) = match try_load_image_with(path, extensions) {
+        Ok(image) => image,
+        Err(e) => fail(json, e),
+    };
+    
+    if !quiet && !json {
+        if path.ends_with(".asm") {
+            println!("📝 Assembled {} instructions", instructions.len());
+        } else if path.ends_with(".tl") {
+            println!("📝 Compiled {} instructions", instructions.len());
+        } else {
+            println!("📂 Loaded {} instructions", instructions.len());
+        }
+    }
+    
+    if instructions.is_empty() {
+        fail(json, "No instructions to execute");
+    }
+    
+    (instructions, origin)
+}
+
+/// Report a fatal error (as `{"error": ...}` in JSON mode) and exit with status 1.
+fn fail(json: bool, message: impl std::fmt::Display) -> ! {
+    if json {
+        println!("{}", serde_json::json!({ "error": message.to_string() }));
+    } else {
+        eprintln!("❌ {}", message);
+    }
+    std::process::exit(1);
+}
+
+fn run_program(args: &RunArgs, json: bool) {
+    if let Some(snapshot) = args.state.restore(json) {
+        if !args.quiet && !json {
+            println!("⏯️  Resuming {} at cycle {}",
+                args.state.restore_state.as_deref().unwrap_or_default(), snapshot.cpu.cycles);
+        }
+        let layout = (snapshot.load_at, snapshot.entry);
+        execute_cpu(snapshot.cpu, &snapshot.program, layout, args, json);
+        return;
+    }
+    
+    if let Some(path) = &args.boot_from_tape {
+        boot_program(path, args, json);
+        return;
+    }
+    
+    let path = args.program.as_deref().unwrap_or_default();
+    if !args.quiet && !json {
+        println!("🔧 Running: {}", path);
+    }
+    
+    let (instructions, origin) = load_program_file_with(path, args.quiet, json, &args.extensions());
+    execute_program(&instructions, origin, args, json);
+}
+
+/// Run loaded instructions with the options from `args`, printing the result.
+///
+/// In JSON mode the result, teletype output (unless `--output` is given) and
+/// `--trace` records are printed as one JSON object instead.
+fn execute_program(instructions: &[setun::Tryte9], origin: i32, args: &RunArgs, json: bool) {
+    let cpu = load_cpu(args.cpu_config(json), instructions, origin, args).unwrap_or_else(|e| fail(json, e));
+    execute_cpu(cpu, instructions, args.layout.resolve(origin), args, json);
+}
+
+/// A CPU with `config` holding `instructions` and any `--segment` images,
+/// placed as `--load-at` and `--entry` say.
+fn load_cpu(config: setun::cpu::CpuConfig, instructions: &[setun::Tryte9], origin: i32, args: &RunArgs) -> Result<setun::Cpu, String> {
+    let mut cpu = setun::Cpu::with_config(config);
+    args.layout.place(&mut cpu, instructions, origin)?;
+    load_segments(&mut cpu, instructions, args.layout.resolve(origin).0, &args.segment)?;
+    Ok(cpu)
+}
+
+/// Run the bootstrap loader on the boot tape at `path`.
+fn boot_program(path: &str, args: &RunArgs, json: bool) {
+    use setun::cpu::boot::BOOT_HEADER_LEN;
+    use setun::cpu::io::parse_tape;
+    
+    let tape = match std::fs::read_to_string(path) {
+        Ok(text) => parse_tape(&text).unwrap_or_else(|e| fail(json, format!("Invalid boot tape {}: {}", path, e))),
+        Err(e) => fail(json, format!("Failed to read boot tape: {}", e)),
+    };
+    let mut cpu = setun::Cpu::with_config(args.cpu_config(json));
+    let header = match cpu.boot_from_tape(&tape) {
+        Ok(header) => header,
+        Err(e) => fail(json, format!("Cannot boot {}: {}", path, e)),
+    };
+    if !args.quiet && !json {
+        println!("🔧 Booting: {} ({} words at {}, entry {})", path, header.len, header.load_at, header.entry);
+    }
+    let program = &tape[BOOT_HEADER_LEN..BOOT_HEADER_LEN + header.len];
+    execute_cpu(cpu, program, (header.load_at, header.entry), args, json);
+}
+
+/// Load the `--segment FILE[@ADDR]` images next to the program at `load_at`.
+fn load_segments(cpu: &mut setun::Cpu, program: &[setun::Tryte9], load_at: i32, specs: &[String]) -> Result<(), String> {
+    use setun::cpu::Segment;
+    
+    if specs.is_empty() {
+        return Ok(());
+    }
+    let mut segments = vec![Segment::new("program", load_at, program.to_vec())];
+    for spec in specs {
+        let (path, addr) = match spec.rsplit_once('@') {
+            Some((path, addr)) => {
+                let addr = addr.parse::<i32>().map_err(|_| format!("Invalid segment address in '{}'", spec))?;
+                (path, Some(addr))
+            }
+            None => (spec.as_str(), None),
+        };
+        let (words, origin) = try_load_image(path).map_err(|e| format!("{}: {}", path, e))?;
+        segments.push(Segment::new(path, addr.unwrap_or(origin), words));
+    }
+    cpu.load_segments(segments).map_err(|e| format!("Failed to load segments: {}", e))
+}
+
+/// Devices `run` attached to a CPU, whose output it passes on.
+struct RunDevices {
+    /// The character console, with --console.
+    console: Option<setun::cpu::DeviceId>,
+    /// Host services, with --semihost.
+    semihost: Option<setun::cpu::Semihost>,
+}
+
+/// Set up `cpu`, holding `program` loaded at `layout`, as the run flags
+/// in `args` say: memory presets, read-only regions, fault injection, the
+/// cost model, devices and the input tape.
+fn prepare_cpu(cpu: &mut setun::Cpu, program: &[setun::Tryte9], layout: (i32, i32), args: &RunArgs, prose: bool) -> Result<RunDevices, String> {
+    use setun::cpu::{ConsoleDevice, MemInit, RandomDevice, ReadOnlyRegion, Semihost};
+    use setun::cpu::io::parse_tape;
+    use std::io::{IsTerminal, Read};
+    
+    if let Some(path) = &args.restore_mem {
+        let count = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| cpu.mem.load_text(&text).map_err(|e| e.to_string()))
+            .map_err(|e| format!("Failed to restore memory from {}: {}", path, e))?;
+        if prose {
+            println!("🧮 Restored memory ({} non-zero cells) from {}", count, path);
+        }
+    }
+    
+    if let Some(path) = &args.mem_init {
+        let count = MemInit::load(path)
+            .map_err(|e| e.to_string())
+            .and_then(|init| cpu.apply_mem_init(&init).map(|_| init.entries.len()).map_err(|e| e.to_string()))
+            .map_err(|e| format!("Failed to apply memory init {}: {}", path, e))?;
+        if prose {
+            println!("🧮 Initialized {} memory cells from {}", count, path);
+        }
+    }
+    
+    // Read-only regions; "program" stands for the loaded image
+    for spec in &args.read_only {
+        let region = match spec.split_once(':').unwrap_or((spec, "trap")) {
+            ("program", policy) => {
+                let end = layout.0 + program.len() as i32 - 1;
+                format!("{}..{}:{}", layout.0, end, policy).parse::<ReadOnlyRegion>()
+            }
+            _ => spec.parse::<ReadOnlyRegion>(),
+        };
+        let region = region.map_err(|e| format!("Invalid --read-only {}: {}", spec, e))?;
+        cpu.protection.add(region);
+    }
+    
+    if let Some(every) = args.inject_faults {
+        cpu.injector.every(every, args.fault_target);
+        if let Some(seed) = args.fault_seed {
+            cpu.injector.seed(seed);
+        }
+    }
+    
+    if let Some(path) = &args.cost {
+        let model = setun::cpu::CostModel::load(path)
+            .map_err(|e| format!("Failed to load cost model {}: {}", path, e))?;
+        cpu.config.cost = Some(model);
+    }
+    
+    if args.rng {
+        let device = RandomDevice::new(args.rng_seed.unwrap_or(setun::cpu::random::DEFAULT_SEED));
+        cpu.devices.attach(device)
+            .map_err(|e| format!("Failed to attach random number device: {}", e))?;
+    }
+    
+    // The console and semihosting read stdin a line at a time, as the
+    // program asks for it
+    let stdin_lines = || -> setun::cpu::ConsoleInput {
+        std::sync::Arc::new(|| {
+            let mut line = String::new();
+            match std::io::stdin().read_line(&mut line) {
+                Ok(0) | Err(_) => None,
+                Ok(_) => Some(line),
+            }
+        })
+    };
+    let console = match args.console {
+        true => Some(cpu.devices.attach(ConsoleDevice::with_input(stdin_lines()))
+            .map_err(|e| format!("Failed to attach console: {}", e))?),
+        false => None,
+    };
+    let semihost = match args.semihost {
+        true => {
+            let host = Semihost::with_input(stdin_lines());
+            host.install(cpu).map_err(|e| format!("Failed to enable semihosting: {}", e))?;
+            Some(host)
+        }
+        false => None,
+    };
+    
+    // Connect the tape reader to --input, or to stdin when it is piped
+    // and not taken by the console
+    cpu.io.enable();
+    let tape_text = match &args.input {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read input: {}", e))?,
+        None if !std::io::stdin().is_terminal() && console.is_none() && semihost.is_none() => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)
+                .map_err(|e| format!("Failed to read stdin: {}", e))?;
+            text
+        }
+        None => String::new(),
+    };
+    cpu.io.feed(parse_tape(&tape_text).map_err(|e| format!("Invalid input tape: {}", e))?);
+    
+    Ok(RunDevices { console, semihost })
+}
+
+/// Run a prepared CPU holding `program`, loaded with the given
+/// `(load address, entry point)`, and report the result.
+fn execute_cpu(mut cpu: setun::Cpu, program: &[setun::Tryte9], layout: (i32, i32), args: &RunArgs, json: bool) {
+    use setun::asm::disasm::disassemble_instruction_for;
+    use setun::cpu::{ConsoleDevice, RegisterState, Semihost, Snapshot, TraceWriter, VcdWriter};
+    use std::io::Write;
+    
+    let max_cycles = args.max_cycles;
+    let prose = !args.quiet && !json;
+    let RunDevices { console, semihost } = prepare_cpu(&mut cpu, program, layout, args, prose)
+        .unwrap_or_else(|e| fail(json, e));
+    let mut console_text = String::new();
+    
+    // Teletype output goes to stdout unless --output is given; in JSON mode
+    // it is collected into the result instead
+    let mut teletype: Option<Box<dyn Write>> = match &args.output {
+        Some(path) => match std::fs::File::create(path) {
+            Ok(file) => Some(Box::new(std::io::BufWriter::new(file))),
+            Err(e) => fail(json, format!("Failed to create output file: {}", e)),
+        },
+        None if json => None,
+        None => Some(Box::new(std::io::stdout())),
+    };
+    let mut printed = Vec::new();
+    
+    // Open the trace file, if requested
+    let mut trace_writer = args.trace_file.as_ref().map(|trace_path| {
+        match std::fs::File::create(trace_path) {
+            Ok(file) => TraceWriter::new(std::io::BufWriter::new(file), args.trace_format),
+            Err(e) => fail(json, format!("Failed to create trace file: {}", e)),
+        }
+    });
+    let mut trace = Vec::new();
+    
+    // Open the VCD waveform, if requested
+    if let Some(&addr) = args.vcd_cells.iter().find(|&&a| a < cpu.mem.min_addr() || a > cpu.mem.max_addr()) {
+        fail(json, format!("VCD cell {} is outside memory ({}..={})", addr, cpu.mem.min_addr(), cpu.mem.max_addr()));
+    }
+    let mut vcd_writer = args.vcd.as_ref().map(|vcd_path| {
+        let writer = std::fs::File::create(vcd_path)
+            .and_then(|file| VcdWriter::new(std::io::BufWriter::new(file), args.vcd_format, &args.vcd_cells));
+        match writer {
+            Ok(mut writer) => {
+                if let Err(e) = writer.sample(&cpu) {
+                    fail(json, format!("Failed to write VCD: {}", e));
+                }
+                writer
+            }
+            Err(e) => fail(json, format!("Failed to create VCD file: {}", e)),
+        }
+    });
+    
+    if prose {
+        println!();
+        println!("━━━ Execution ━━━");
+    }
+    
+    // Run with optional trace
+    let mut cycles = 0u64;
+    let mut error = None;
+    let mut diagnostic = None;
+    while cpu.is_running() && cycles < max_cycles {
+        let pc = cpu.regs.c.to_i32();
+        
+        match cpu.step_traced() {
+            Ok(record) => {
+                if let Some(writer) = trace_writer.as_mut() {
+                    if let Err(e) = writer.write(&record) {
+                        fail(json, format!("Failed to write trace: {}", e));
+                    }
+                }
+                if let Some(writer) = vcd_writer.as_mut() {
+                    if let Err(e) = writer.sample(&cpu) {
+                        fail(json, format!("Failed to write VCD: {}", e));
+                    }
+                }
+                if args.trace && json {
+                    trace.push(record);
+                } else if args.trace {
+                    let disasm = disassemble_instruction_for(record.raw, &cpu.config);
+                    println!("{:03}: {}  S={} ω={:?}", 
+                        pc, disasm, cpu.regs.s.to_i64(), cpu.regs.omega);
+                }
+                for word in cpu.io.take_output() {
+                    match teletype.as_mut() {
+                        Some(out) => {
+                            if let Err(e) = writeln!(out, "{}", word.to_i32()) {
+                                fail(json, format!("Failed to write output: {}", e));
+                            }
+                        }
+                        None => printed.push(word.to_i32()),
+                    }
+                }
+                let host_text = semihost.as_ref().map(Semihost::take_output).unwrap_or_default();
+                if json {
+                    console_text.push_str(&host_text);
+                } else if !host_text.is_empty() {
+                    print!("{}", host_text);
+                    let _ = std::io::stdout().flush();
+                }
+                if let Some(device) = console.and_then(|id| cpu.devices.get_mut::<ConsoleDevice>(id)) {
+                    let text = device.take_output();
+                    if json {
+                        console_text.push_str(&text);
+                    } else if !text.is_empty() {
+                        print!("{}", text);
+                        let _ = std::io::stdout().flush();
+                    }
+                }
+                cycles += 1;
+            }
+            Err(e) if json => {
+                error = Some(format!("CPU error at PC={}: {}", pc, e));
+                diagnostic = Some(cpu.diagnostic(&e));
+                break;
+            }
+            Err(e) => fail(json, cpu.diagnostic(&e)),
+        }
+    }
+    
+    if let Some(Err(e)) = teletype.as_mut().map(|out| out.flush()) {
+        fail(json, format!("Failed to write output: {}", e));
+    }
+    
+    if let Some(writer) = trace_writer {
+        if let Err(e) = writer.finish() {
+            fail(json, format!("Failed to write trace: {}", e));
+        }
+        if prose {
+            println!("📄 Trace written to {}", args.trace_file.as_deref().unwrap_or_default());
+        }
+    }
+    
+    if let Some(writer) = vcd_writer {
+        if let Err(e) = writer.finish() {
+            fail(json, format!("Failed to write VCD: {}", e));
+        }
+        if prose {
+            println!("📄 Waveform written to {}", args.vcd.as_deref().unwrap_or_default());
+        }
+    }
+    
+    if let Some(path) = &args.dump_mem {
+        if let Err(e) = write_memory_dump(path, &cpu.mem) {
+            fail(json, e);
+        }
+        if prose {
+            println!("💾 Memory image written to {}", path);
+        }
+    }
+    
+    let snapshot = Snapshot::capture(&cpu, program).with_layout(layout.0, layout.1);
+    if let Err(e) = args.state.save(&snapshot) {
+        fail(json, e);
+    }
+    if let (Some(path), true) = (&args.state.save_state, prose) {
+        println!("💾 State saved to {}", path);
+    }
+    
+    if json {
+        let mut result = serde_json::json!({
+            "program": args.program,
+            "cycles": cycles,
+            "cost": cpu.config.cost.as_ref().map(|_| cpu.cost),
+            "state": cpu.state,
+            "halted": cpu.is_halted(),
+            "cycle_limit_reached": cpu.is_running() && cycles >= max_cycles,
+            "registers": RegisterState::capture(&cpu.regs),
+            "output": printed,
+            "console": (console.is_some() || semihost.is_some()).then_some(console_text),
+            "exit_code": semihost.as_ref().and_then(Semihost::exit_code),
+            "error": error,
+            "diagnostic": diagnostic,
+            "write_faults": cpu.protection.faults(),
+            "injected_faults": cpu.injector.log(),
+        });
+        if args.trace {
+            result["trace"] = serde_json::json!(trace);
+        }
+        println!("{}", serde_json::to_string_pretty(&result).expect("run result serialization cannot fail"));
+        if error.is_some() {
+            std::process::exit(1);
+        }
+        exit_with_host_code(semihost.as_ref());
+        return;
+    }
+    
+    if args.quiet {
+        exit_with_host_code(semihost.as_ref());
+        return;
+    }
+    
+    println!();
+    println!("━━━ Result ━━━");
+    println!("Cycles: {}", cycles);
+    if cpu.config.cost.is_some() {
+        println!("Cost: {}", cpu.cost);
+    }
+    println!("State: {:?}", cpu.state);
+    println!("S (accumulator): {} ({})", cpu.regs.s, cpu.regs.s.to_i64());
+    println!("R (multiplier):  {} ({})", cpu.regs.r, cpu.regs.r.to_i64());
+    println!("F (index):       {}", cpu.regs.f.to_i32());
+    println!("ω (omega):       {:?}", cpu.regs.omega);
+    
+    let faults = cpu.protection.faults();
+    if !faults.is_empty() {
+        println!();
+        println!("⚠️  {} write(s) to read-only memory:", faults.len());
+        for fault in faults {
+            println!("   PC={}: stored {} at {} ({})", fault.pc, fault.value, fault.addr, fault.policy);
+        }
+    }
+    
+    let injected = cpu.injector.log();
+    if !injected.is_empty() {
+        println!();
+        println!("⚡ {} injected fault(s):", injected.len());
+        for fault in injected {
+            println!("   {}", fault);
+        }
+    }
+    
+    if cycles >= max_cycles {
+        println!();
+        println!("⚠️  Reached max cycles limit ({}). Use --max-cycles to increase.", max_cycles);
+    }
+    
+    if let Some(code) = semihost.as_ref().and_then(setun::cpu::Semihost::exit_code) {
+        println!("Exit code: {}", code);
+    }
+    exit_with_host_code(semihost.as_ref());
+}
+
+/// Exit the process with the code a semihosted program passed to its exit
+/// service, if it called it.
+fn exit_with_host_code(semihost: Option<&setun::cpu::Semihost>) {
+    if let Some(code) = semihost.and_then(setun::cpu::Semihost::exit_code) {
+        std::process::exit(code.clamp(i32::MIN as i64, i32::MAX as i64) as i32);
+    }
+}
+
+/// Write a memory image: every cell as TROM for `.trom` paths, an
+/// annotated listing for `.lst` paths, otherwise the non-zero cells as a
+/// memory initialization file.
+fn write_memory_dump(path: &str, mem: &setun::Memory) -> Result<(), String> {
+    use setun::asm::save_image;
+    use setun::cpu::MemInit;
+    
+    if path.ends_with(".trom") {
+        save_image(path, mem).map_err(|e| format!("Failed to write memory image: {}", e))
+    } else if path.ends_with(".lst") {
+        std::fs::write(path, mem.dump_text(true))
+            .map_err(|e| format!("Failed to write memory image: {}", e))
+    } else {
+        std::fs::write(path, MemInit::from_memory(mem).to_string())
+            .map_err(|e| format!("Failed to write memory image: {}", e))
+    }
+}
+
+// ============================================================================
+// Watch mode
+
+/// How often watched files are checked for changes.
+const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Block until `path` has a modification time different from `last`.
+fn wait_for_change(path: &str, last: Option<std::time::SystemTime>) -> Option<std::time::SystemTime> {
+    loop {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified.is_some() && modified != last {
+            return modified;
+        }
+        std::thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+/// Final machine state of one watched run, for diffing against the next.
+struct WatchResult {
+    state: setun::CpuState,
+    cycles: u64,
+    regs: setun::cpu::RegisterState,
+    memory: Vec<setun::Tryte9>,
+}
+
+fn watch_run(args: &RunArgs) {
+    use setun::cpu::{ConsoleDevice, RegisterState, Semihost};
+    
+    let Some(path) = args.program.as_deref() else {
+        fail(false, "--watch needs a program file, not a restored state");
+    };
+    let config = args.cpu_config(false);
+    let extensions = args.extensions();
+    println!("👀 Watching: {} (Ctrl+C to stop)", path);
+    
+    let mut last_modified = None;
+    let mut previous: Option<WatchResult> = None;
+    
+    loop {
+        last_modified = wait_for_change(path, last_modified);
+        println!();
+        println!("━━━ {} changed ━━━", path);
+        
+        // Set up the machine exactly as `run` would
+        let prepared = try_load_image_with(path, &extensions).and_then(|(instructions, origin)| {
+            let mut cpu = load_cpu(config.clone(), &instructions, origin, args)?;
+            let devices = prepare_cpu(&mut cpu, &instructions, args.layout.resolve(origin), args, false)?;
+            Ok((cpu, devices))
+        });
+        let (mut cpu, devices) = match prepared {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = cpu.run_limited(args.max_cycles) {
+            eprintln!("❌ {}", cpu.diagnostic(&e));
+        }
+        for word in cpu.io.take_output() {
+            println!("{}", word.to_i32());
+        }
+        print!("{}", devices.semihost.as_ref().map(Semihost::take_output).unwrap_or_default());
+        if let Some(device) = devices.console.and_then(|id| cpu.devices.get_mut::<ConsoleDevice>(id)) {
+            print!("{}", device.take_output());
+        }
+        
+        let result = WatchResult {
+            state: cpu.state,
+            cycles: cpu.cycles,
+            regs: RegisterState::capture(&cpu.regs),
+            memory: cpu.mem.as_slice().to_vec(),
+        };
+        
+        println!("Cycles: {}  State: {:?}  S={}  R={}  F={}  ω={:?}",
+            result.cycles, result.state, result.regs.s, result.regs.r, result.regs.f, result.regs.omega);
+        
+        if let Some(prev) = &previous {
+            let mut changes = Vec::new();
+            if prev.cycles != result.cycles {
+                changes.push(format!("cycles {} → {}", prev.cycles, result.cycles));
+            }
+            if prev.state != result.state {
+                changes.push(format!("state {:?} → {:?}", prev.state, result.state));
+            }
+            for delta in prev.regs.diff(&result.regs) {
+                changes.push(format!("{} {} → {}", delta.register, delta.before, delta.after));
+            }
+            for (index, (old, new)) in prev.memory.iter().zip(&result.memory).enumerate() {
+                if old != new {
+                    let addr = cpu.mem.index_to_addr(index).to_i32();
+                    changes.push(format!("[{:+}] {} → {}", addr, old.to_i32(), new.to_i32()));
+                }
+            }
+            
+            if changes.is_empty() {
+                println!("Δ no change from previous run");
+            } else {
+                println!("Δ {}", changes.join(", "));
+            }
+        }
+        
+        previous = Some(result);
+    }
+}
+
+fn watch_asm(source_path: &str, output: Option<String>) {
+    use setun::{assemble, save_trom, TromFile};
+    
+    let out_path = output.unwrap_or_else(|| source_path.replace(".asm", ".trom"));
+    println!("👀 Watching: {} → {} (Ctrl+C to stop)", source_path, out_path);
+    
+    let mut last_modified = None;
+    let mut previous: Option<Vec<setun::Tryte9>> = None;
+    
+    loop {
+        last_modified = wait_for_change(source_path, last_modified);
+        println!();
+        println!("━━━ {} changed ━━━", source_path);
+        
+        let source = match std::fs::read_to_string(source_path) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("❌ Failed to read file: {}", e);
+                continue;
+            }
+        };
+        let instructions = match assemble(&source) {
+            Ok(instrs) => instrs,
+            Err(e) => {
+                eprintln!("❌ Assembly error: {}", e);
+                continue;
+            }
+        };
+        
+        let trom = TromFile {
+            instructions: instructions.clone(),
+            source_lines: instructions.iter().map(|i| format!("{}", i)).collect(),
+        };
+        if let Err(e) = save_trom(&out_path, &trom) {
+            eprintln!("❌ Failed to save TROM: {}", e);
+            continue;
+        }
+        println!("✓ Assembled {} instructions → {}", instructions.len(), out_path);
+        
+        if let Some(prev) = &previous {
+            let changed = setun::asm::trom::diff(prev, &instructions).len();
+            println!("Δ {} word(s) changed, {} → {} instructions", changed, prev.len(), instructions.len());
+        }
+        
+        previous = Some(instructions);
+    }
+}
+
+fn profile_program(path: &str, max_cycles: u64, timing: bool, top: usize, cost: Option<&str>, json: bool) {
+    use setun::Cpu;
+    use setun::cpu::{CostModel, CpuConfig};
+    use setun::asm::disasm::disassemble_instruction;
+    
+    if !json {
+        println!("📊 Profiling: {}", path);
+    }
+    
+    let (instructions, origin) = load_program_file(path, json, json);
+    
+    let cost = cost.map(|path| match CostModel::load(path) {
+        Ok(model) => model,
+        Err(e) => fail(json, format!("Failed to load cost model {}: {}", path, e)),
+    });
+    let mut cpu = Cpu::with_config(CpuConfig { timing, cost, ..CpuConfig::default() });
+    if let Err(e) = cpu.load_program_at(origin, &instructions).and_then(|_| cpu.set_entry(origin)) {
+        fail(json, format!("Failed to load program: {}", e));
+    }
+    
+    let mut profile = setun::cpu::Profile::new();
+    let mut report = setun::cpu::ExecutionReport::new();
+    let (start_us, start_cost) = (cpu.elapsed_us, cpu.cost);
+    if let Err(e) = cpu.run_traced(max_cycles, |record| {
+        profile.record(&record);
+        report.record(&record);
+    }) {
+        fail(json, format!("CPU error at PC={}: {}", cpu.regs.c.to_i32(), e));
+    }
+    profile.elapsed_us = cpu.config.timing.then(|| cpu.elapsed_us - start_us);
+    profile.cost = cpu.config.cost.is_some().then(|| cpu.cost - start_cost);
+    
+    if json {
+        let mut result = serde_json::to_value(&profile).expect("profile serialization cannot fail");
+        result["report"] = serde_json::to_value(&report).expect("report serialization cannot fail");
+        println!("{}", serde_json::to_string_pretty(&result).expect("profile serialization cannot fail"));
+        return;
+    }
+    
+    println!();
+    println!("━━━ Profile ━━━");
+    println!("Instructions: {}", profile.instructions);
+    println!("State: {:?}", cpu.state);
+    if let Some(us) = profile.elapsed_us {
+        println!("Emulated time: {} µs ({:.3} ms)", us, us as f64 / 1000.0);
+    }
+    if let Some(cost) = profile.cost {
+        println!("Cost: {}", cost);
+    }
+    
+    println!();
+    println!("Hottest addresses:");
+    for (addr, count) in profile.hottest(top) {
+        let word = cpu.mem.read_ternary(setun::Tryte9::from_i32(addr)).unwrap_or_default();
+        let share = 100.0 * count as f64 / profile.instructions as f64;
+        println!("  {:+4}: {:>8} ({:5.1}%)  {}", addr, count, share, disassemble_instruction(word));
+    }
+    
+    println!();
+    println!("Opcodes:       count   mean µs   reads  writes");
+    let mut opcodes: Vec<_> = report.opcodes.iter().collect();
+    opcodes.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.count));
+    for (mnemonic, stats) in opcodes {
+        println!("  {:<7} {:>10} {:>9.0} {:>7} {:>7}", mnemonic, stats.count, stats.mean_us(), stats.reads, stats.writes);
+    }
+    println!("Memory traffic: {} reads of {} cells, {} writes to {} cells",
+        report.memory.reads, report.memory.cells_read, report.memory.writes, report.memory.cells_written);
+    
+    let totals = profile.branch_totals();
+    if totals.total() > 0 {
+        println!();
+        println!("Branches: {} executed, {} taken, {} not taken",
+            totals.total(), totals.taken, totals.not_taken);
+        for (addr, stats) in &profile.branches {
+            println!("  {:+4}: {:>8} taken, {:>8} not taken", addr, stats.taken, stats.not_taken);
+        }
+    }
+    
+    if cpu.is_running() {
+        println!();
+        println!("⚠️  Reached max cycles limit ({}). Use --max-cycles to increase.", max_cycles);
+    }
+}
+
+fn check_source(path: &str, strict: bool, preset: &str, json: bool) {
+    use setun::asm::check_for;
+    use setun::cpu::CpuConfig;
+    use setun::diagnostic::{render_json, render_text};
+    
+    let config = CpuConfig::preset(preset)
+        .unwrap_or_else(|| fail(json, format!("Unknown machine preset '{}' (expected simplified or historical)", preset)));
+    let source = match std::fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) => fail(json, format!("Failed to read file: {}", e)),
+    };
+    
+    let report = check_for(&source, &config);
+    let diagnostics = report.diagnostics(path);
+    
+    if json {
+        let mut output = render_json(&diagnostics);
+        output["file"] = serde_json::json!(path);
+        println!("{}", serde_json::to_string_pretty(&output).expect("diagnostics serialization cannot fail"));
+    } else {
+        // One diagnostic per line in the conventional file:line: form
+        print!("{}", render_text(&diagnostics));
+    }
+    
+    if !report.is_ok() || (strict && !report.warnings.is_empty()) {
+        std::process::exit(1);
+    }
+}
+
+/// Install a stderr subscriber for `--log`, or for `RUST_LOG` when set.
+#[cfg(feature = "tracing")]
+fn init_tracing(filter: Option<&str>) {
+    use std::io::IsTerminal;
+    use tracing_subscriber::EnvFilter;
+    
+    let filter = match filter {
+        Some(directives) => match EnvFilter::try_new(directives) {
+            Ok(filter) => filter,
+            Err(e) => {
+                eprintln!("❌ Invalid --log filter: {}", e);
+                std::process::exit(2);
+            }
+        },
+        None => match EnvFilter::try_from_default_env() {
+            Ok(filter) => filter,
+            Err(_) => return,
+        },
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .init();
+}
+
+fn verify_program(path: &str, max_cycles: u64, input: Option<&str>, json: bool) {
+    use setun::Cpu;
+    use setun::cpu::io::parse_tape;
+    use setun::testing::verify;
+    
+    let (instructions, origin) = load_program_file(path, json, json);
+    
+    let mut cpu = Cpu::new();
+    if let Err(e) = cpu.load_program_at(origin, &instructions).and_then(|_| cpu.set_entry(origin)) {
+        fail(json, format!("Failed to load program: {}", e));
+    }
+    cpu.io.enable();
+    if let Some(input) = input {
+        let tape = std::fs::read_to_string(input)
+            .map_err(|e| format!("Failed to read input: {}", e))
+            .and_then(|text| parse_tape(&text).map_err(|e| format!("Invalid input tape: {}", e)));
+        match tape {
+            Ok(words) => cpu.io.feed(words),
+            Err(e) => fail(json, e),
+        }
+    }
+    
+    let report = verify(cpu, max_cycles);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).expect("report serialization cannot fail"));
+    } else {
+        match &report.divergence {
+            None => {
+                println!("✓ {} instructions match the reference model ({:?})", report.steps, report.stop_reason);
+                if let Some(error) = &report.error {
+                    println!("  Both stopped with: {}", error);
+                }
+            }
+            Some(divergence) => println!("❌ Divergence at {}", divergence),
+        }
+    }
+    
+    if !report.passed() {
+        std::process::exit(1);
+    }
+}
+
+/// Trace records for `report` and `access-map`: read from a JSON Lines trace,
+/// or recorded by running a program. Also returns the program and its origin
+/// when one was run.
+fn record_trace(path: &str, max_cycles: u64, tape: Option<&str>) -> (Vec<setun::cpu::TraceRecord>, Option<(i32, Vec<setun::Tryte9>)>) {
+    use setun::Cpu;
+    use setun::cpu::TraceRecord;
+    use setun::cpu::io::parse_tape;
+    
+    if path.ends_with(".jsonl") {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => fail(false, format!("Failed to read trace: {}", e)),
+        };
+        let records: Result<Vec<TraceRecord>, _> = text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect();
+        return match records {
+            Ok(records) => (records, None),
+            Err(e) => fail(false, format!("Invalid trace: {}", e)),
+        };
+    }
+    
+    let (instructions, origin) = load_program_file(path, false, false);
+    let mut cpu = Cpu::new();
+    if let Err(e) = cpu.load_program_at(origin, &instructions).and_then(|_| cpu.set_entry(origin)) {
+        fail(false, format!("Failed to load program: {}", e));
+    }
+    cpu.io.enable();
+    if let Some(tape) = tape {
+        let words = std::fs::read_to_string(tape)
+            .map_err(|e| format!("Failed to read input: {}", e))
+            .and_then(|text| parse_tape(&text).map_err(|e| format!("Invalid input tape: {}", e)));
+        match words {
+            Ok(words) => cpu.io.feed(words),
+            Err(e) => fail(false, e),
+        }
+    }
+    
+    let mut records = Vec::new();
+    if let Err(e) = cpu.run_traced(max_cycles, |record| records.push(record)) {
+        println!("⚠️  CPU error at PC={}: {} (the output covers the run up to here)", cpu.regs.c.to_i32(), e);
+    } else if cpu.is_running() {
+        println!("⚠️  Reached max cycles limit ({}). Use --max-cycles to increase.", max_cycles);
+    }
+    (records, Some((origin, instructions)))
+}
+
+fn report_program(path: &str, output: Option<&str>, max_cycles: u64, tape: Option<&str>) {
+    use setun::report::HtmlReport;
+    
+    let title = std::path::Path::new(path).file_name().map_or(path.into(), |name| name.to_string_lossy());
+    let (records, program) = record_trace(path, max_cycles, tape);
+    let mut report = HtmlReport::from_records(title, records);
+    if let Some((origin, instructions)) = program {
+        report = report.with_program(origin, &instructions);
+    }
+    
+    let output = output.map_or_else(
+        || std::path::Path::new(path).with_extension("html").to_string_lossy().into_owned(),
+        str::to_string,
+    );
+    if let Err(e) = std::fs::write(&output, report.render()) {
+        fail(false, format!("Failed to write report: {}", e));
+    }
+    println!("📄 Report for {} instructions written to {}", report.len(), output);
+}
+
+fn access_map(path: &str, output: Option<&str>, format: Option<AccessFormat>, max_cycles: u64, tape: Option<&str>) {
+    use setun::report::AccessPattern;
+    use std::io::Write;
+    
+    let format = format.or_else(|| output.and_then(AccessFormat::from_path)).unwrap_or_default();
+    let (records, _) = record_trace(path, max_cycles, tape);
+    let mut pattern = AccessPattern::new();
+    for record in &records {
+        pattern.record(record);
+    }
+    
+    let output = output.map_or_else(
+        || std::path::Path::new(path).with_extension(format.to_string()).to_string_lossy().into_owned(),
+        str::to_string,
+    );
+    let written = std::fs::File::create(&output).and_then(|file| {
+        let mut out = std::io::BufWriter::new(file);
+        pattern.write(&mut out, format)?;
+        out.flush()
+    });
+    if let Err(e) = written {
+        fail(false, format!("Failed to write access map: {}", e));
+    }
+    println!("📄 {} memory accesses written to {}", pattern.events().len(), output);
+    let code_writes = pattern.code_writes();
+    if !code_writes.is_empty() {
+        println!("✏️  {} write(s) to executed addresses (self-modifying code)", code_writes.len());
+    }
+}
+
+fn diff_programs(old_path: &str, new_path: &str, json: bool) {
+    use setun::asm::trom::{diff, DiffKind};
+    use setun::asm::disasm::disassemble_instruction;
+    
+    let load = |path: &str| match try_load_program(path) {
+        Ok(instrs) => instrs,
+        Err(e) => {
+            eprintln!("❌ {}: {}", path, e);
+            std::process::exit(2);
+        }
+    };
+    let old = load(old_path);
+    let new = load(new_path);
+    let changes = diff(&old, &new);
+    
+    if json {
+        let entries: Vec<_> = changes.iter().map(|c| serde_json::json!({
+            "address": c.index,
+            "kind": c.kind,
+            "old": c.old.map(|w| w.to_string()),
+            "new": c.new.map(|w| w.to_string()),
+            "old_disasm": c.old.map(disassemble_instruction),
+            "new_disasm": c.new.map(disassemble_instruction),
+        })).collect();
+        println!("{}", serde_json::to_string_pretty(&entries).expect("diff serialization cannot fail"));
+    } else {
+        println!("--- {} ({} words)", old_path, old.len());
+        println!("+++ {} ({} words)", new_path, new.len());
+        
+        let describe = |word: Option<setun::Tryte9>| match word {
+            Some(w) => format!("{} {:+6} {:<12}", w, w.to_i32(), disassemble_instruction(w)),
+            None => format!("{:<31}", "-"),
+        };
+        for change in &changes {
+            let marker = match change.kind {
+                DiffKind::Changed => '~',
+                DiffKind::Added => '+',
+                DiffKind::Removed => '-',
+            };
+            println!("{} {:03}: {} → {}", marker, change.index, describe(change.old), describe(change.new).trim_end());
+        }
+        
+        if changes.is_empty() {
+            println!("✓ Programs are identical");
+        } else {
+            println!("{} word(s) differ", changes.len());
+        }
+    }
+    
+    if !changes.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn equiv_programs(a_path: &str, b_path: &str, options: &setun::equiv::EquivOptions, json: bool) {
+    use setun::equiv::check_equivalence;
+    
+    let load = |path: &str| match try_load_program(path) {
+        Ok(instrs) => instrs,
+        Err(e) => fail(json, format!("{}: {}", path, e)),
+    };
+    let a = load(a_path);
+    let b = load(b_path);
+    let report = match check_equivalence(&a, &b, options) {
+        Ok(report) => report,
+        Err(e) => fail(json, e.to_string()),
+    };
+    
+    if json {
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({
+            "equivalent": report.equivalent(),
+            "cases": report.cases,
+            "counterexample": report.counterexample,
+        })).expect("equivalence report serialization cannot fail"));
+    } else {
+        match &report.counterexample {
+            None => println!("✓ Equivalent on all {} input combination(s)", report.cases),
+            Some(counterexample) => {
+                let inputs: Vec<String> = counterexample.inputs.iter()
+                    .map(|(addr, value)| format!("[{}] = {}", addr, value))
+                    .collect();
+                println!("✗ Programs differ (after {} combination(s))", report.cases);
+                if !inputs.is_empty() {
+                    println!("  inputs: {}", inputs.join(", "));
+                }
+                for difference in &counterexample.differences {
+                    println!("  {}", difference);
+                }
+            }
+        }
+    }
+    
+    if !report.equivalent() {
+        std::process::exit(1);
+    }
+}
+
+fn simulate_program(path: &str, options: &setun::simulate::SimOptions, json: bool) {
+    use setun::simulate::simulate;
+    
+    let (program, origin) = match try_load_image(path) {
+        Ok(image) => image,
+        Err(e) => fail(json, format!("{}: {}", path, e)),
+    };
+    let report = match simulate(&program, origin, options) {
+        Ok(report) => report,
+        Err(e) => fail(json, e.to_string()),
+    };
+    
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).expect("simulation report serialization cannot fail"));
+        return;
+    }
+    println!("🎲 {} trial(s) of {} (seed {})", report.trials, path, report.seed);
+    println!("  halted: {}, failed: {}", report.halted, report.failed());
+    if let Some(s) = report.s {
+        println!("  final S: min {}, max {}, mean {:.2}", s.min, s.max, s.mean);
+    }
+    if let Some(cycles) = report.cycles {
+        println!("  cycles:  min {}, max {}, mean {:.2}", cycles.min, cycles.max, cycles.mean);
+    }
+    // The most frequent results, most common first
+    let mut counts: Vec<(i64, u64)> = report.s_counts.iter().map(|(&s, &n)| (s, n)).collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    if !counts.is_empty() {
+        println!("  most common S:");
+        for (s, n) in counts.iter().take(10) {
+            println!("    {:>8}  {:>6} ({:.1}%)", s, n, 100.0 * *n as f64 / report.trials as f64);
+        }
+        if counts.len() > 10 {
+            println!("    ... {} other value(s)", counts.len() - 10);
+        }
+    }
+    for (reason, n) in &report.failures {
+        println!("  ✗ {} × {}", n, reason);
+    }
+    if let Some(failure) = &report.first_failure {
+        let inputs: Vec<String> = failure.inputs.iter().map(|(addr, value)| format!("[{}] = {}", addr, value)).collect();
+        println!("  first failure: trial {}, inputs {}", failure.trial, inputs.join(", "));
+    }
+}
+
+fn examples_command(command: ExamplesCommand, json: bool) {
+    use setun::examples::{find, EXAMPLES};
+    
+    let lookup = |name: &str| match find(name) {
+        Some(example) => example,
+        None => {
+            eprintln!("❌ Unknown example '{}'. Use 'examples list' to see them all.", name);
+            std::process::exit(1);
+        }
+    };
+    
+    match command {
+        ExamplesCommand::List => {
+            println!("Bundled examples:");
+            for example in EXAMPLES {
+                println!("  {:<16} {}", example.name, example.description);
+            }
+            println!();
+            println!("Run one with: setun-emu examples run <name>");
+        }
+        ExamplesCommand::Show { name } => {
+            print!("{}", lookup(&name).source);
+        }
+        ExamplesCommand::Run { name, max_cycles, trace } => {
+            let example = lookup(&name);
+            if !json {
+                println!("🔧 Running example: {} - {}", example.name, example.description);
+            }
+            
+            let instructions = match setun::assemble(example.source) {
+                Ok(instrs) => instrs,
+                Err(e) => fail(json, format!("Assembly error: {}", e)),
+            };
+            if !json {
+                println!("📝 Assembled {} instructions", instructions.len());
+            }
+            
+            let args = RunArgs {
+                program: Some(example.name.to_string()),
+                max_cycles,
+                trace,
+                trace_file: None,
+                trace_format: TraceFormat::default(),
+                vcd: None,
+                vcd_cells: Vec::new(),
+                vcd_format: VcdFormat::default(),
+                watch: false,
+                input: None,
+                output: None,
+                quiet: false,
+                mem_init: None,
+                dump_mem: None,
+                restore_mem: None,
+                read_only: Vec::new(),
+                indirect: false,
+                wrap_addresses: false,
+                strict_shifts: false,
+                stack: None,
+                semihost: false,
+                inject_faults: None,
+                fault_target: Default::default(),
+                fault_seed: None,
+                rng: false,
+                rng_seed: None,
+                console: false,
+                cost: None,
+                segment: Vec::new(),
+                boot_from_tape: None,
+                layout: LayoutArgs::default(),
+                state: StateArgs::default(),
+            };
+            execute_program(&instructions, 0, &args, json);
+        }
+    }
+}
+
+/// The debugger for the program at `path`, or for the `--restore-state` snapshot.
+#[cfg(feature = "tui")]
+fn debugger_app(path: Option<&str>, layout: &LayoutArgs, state: &StateArgs) -> setun::tui::DebuggerApp {
+    use setun::tui::DebuggerApp;
+    
+    match state.restore(false) {
+        Some(snapshot) => {
+            println!("⏯️  Resuming {} at cycle {}",
+                state.restore_state.as_deref().unwrap_or_default(), snapshot.cpu.cycles);
+            DebuggerApp::from_snapshot(snapshot)
+        }
+        None => {
+            let path = path.unwrap_or_default();
+            println!("🔍 Loading: {}", path);
+            
+            let (instructions, origin) = load_program_file(path, false, false);
+            let (load_at, entry) = layout.resolve(origin);
+            
+            // Check the layout before taking over the terminal
+            if let Err(e) = layout.place(&mut setun::Cpu::new(), &instructions, origin) {
+                fail(false, e);
+            }
+            // Labels name pinned cells
+            let symbols = std::fs::read_to_string(path).ok()
+                .filter(|_| path.ends_with(".asm"))
+                .and_then(|source| setun::asm::assemble_detailed(&source).ok())
+                .map(|assembly| assembly.symbols)
+                .unwrap_or_default();
+            DebuggerApp::with_layout(instructions, load_at, entry).with_symbols(symbols)
+        }
+    }
+}
+
+/// Run the debugger TUI, saving the final state if `--save-state` asks.
+#[cfg(feature = "tui")]
+fn debug_in_terminal(app: setun::tui::DebuggerApp, state: &StateArgs) {
+    use setun::tui::run_debugger_app;
+    
+    println!("🚀 Launching debugger...");
+    println!();
+    
+    let app = match run_debugger_app(app) {
+        Ok(app) => app,
+        Err(e) => fail(false, format!("Debugger error: {}", e)),
+    };
+    
+    if let Err(e) = state.save(&app.snapshot()) {
+        fail(false, e);
+    }
+    if let Some(path) = &state.save_state {
+        println!("💾 State saved to {}", path);
+    }
+}
+
+#[cfg(all(feature = "tui", feature = "gdb"))]
+fn debug_over_gdb(addr: &str, snapshot: setun::cpu::Snapshot, state: &StateArgs) {
+    use setun::gdb::{GdbTarget, SessionEnd};
+    
+    // `:3333` means localhost, as in gdbserver
+    let addr = match addr.strip_prefix(':') {
+        Some(port) => format!("127.0.0.1:{}", port),
+        None => addr.to_string(),
+    };
+    let listener = match std::net::TcpListener::bind(&addr) {
+        Ok(listener) => listener,
+        Err(e) => fail(false, format!("Failed to bind {}: {}", addr, e)),
+    };
+    
+    println!("🐞 Waiting for GDB on {} (target remote {})", addr, addr);
+    let mut target = GdbTarget::new(snapshot.cpu);
+    match setun::gdb::serve(&listener, &mut target) {
+        Ok(SessionEnd::Disconnected) => println!("👋 GDB disconnected"),
+        Ok(SessionEnd::Halted) => println!("🏁 Program halted after {} cycles", target.cpu().cycles),
+        Ok(SessionEnd::Killed) => println!("💀 GDB killed the program"),
+        Err(e) => fail(false, format!("GDB session failed: {}", e)),
+    }
+    
+    let end = setun::cpu::Snapshot::capture(target.cpu(), &snapshot.program)
+        .with_layout(snapshot.load_at, snapshot.entry);
+    if let Err(e) = state.save(&end) {
+        fail(false, e);
+    }
+    if let Some(path) = &state.save_state {
+        println!("💾 State saved to {}", path);
+    }
+}
+
+fn assemble_file(source_path: &str, output: Option<String>, boot_tape: bool, json: bool) {
+    use setun::{save_trom, TromFile};
+    use setun::asm::assemble_collecting;
+    
+    let out_path = output.unwrap_or_else(|| {
+        source_path.replace(".asm", if boot_tape { ".tape" } else { ".trom" })
+    });
+    
+    if !json {
+        println!("📝 Assembling: {} → {}", source_path, out_path);
+    }
+    
+    // Read source
+    let source = match std::fs::read_to_string(source_path) {
+        Ok(s) => s,
+        Err(e) => fail(json, format!("Failed to read file: {}", e)),
+    };
+    
+    // Assemble
+    let (assembly, errors) = assemble_collecting(&source);
+    if json && !errors.is_empty() {
+        let errors: Vec<_> = errors.iter()
+            .map(|e| serde_json::json!({ "line": e.line(), "message": e.to_string() }))
+            .collect();
+        let result = serde_json::json!({ "source": source_path, "errors": errors });
+        println!("{}", serde_json::to_string_pretty(&result).expect("diagnostics serialization cannot fail"));
+        std::process::exit(1);
+    }
+    if let Some(e) = errors.first() {
+        fail(json, format!("Assembly error: {}", e));
+    }
+    let instructions = &assembly.program;
+    
+    if !json {
+        println!("✓ Assembled {} instructions", instructions.len());
+    }
+    
+    if boot_tape {
+        let text = boot_tape_text(instructions, assembly.origin, source_path);
+        if let Err(e) = std::fs::write(&out_path, text) {
+            fail(json, format!("Failed to save boot tape: {}", e));
+        }
+    } else {
+        let trom = TromFile {
+            instructions: instructions.clone(),
+            source_lines: instructions.iter().map(|i| format!("{}", i)).collect(),
+        };
+        if let Err(e) = save_trom(&out_path, &trom) {
+            fail(json, format!("Failed to save TROM: {}", e));
+        }
+    }
+    
+    if json {
+        let result = serde_json::json!({
+            "source": source_path,
+            "output": out_path,
+            "words": instructions.len(),
+            "origin": assembly.origin,
+            "symbols": assembly.symbols,
+            "warnings": assembly.warnings,
+        });
+        println!("{}", serde_json::to_string_pretty(&result).expect("assembly serialization cannot fail"));
+    } else {
+        println!("✓ Saved to {}", out_path);
+    }
+}
+
+/// A boot tape for `program` loaded and entered at `origin`: the header
+/// on one line, then one word per line.
+fn boot_tape_text(program: &[setun::Tryte9], origin: i32, name: &str) -> String {
+    let tape = setun::cpu::boot::boot_tape(program, origin, origin);
+    let mut text = format!("# Boot tape for {}: load address, length, entry\n", name);
+    let (header, words) = tape.split_at(setun::cpu::boot::BOOT_HEADER_LEN);
+    let header: Vec<String> = header.iter().map(|w| w.to_i32().to_string()).collect();
+    text.push_str(&header.join(" "));
+    text.push('\n');
+    for word in words {
+        text.push_str(&format!("{}\n", word.to_i32()));
+    }
+    text
+}
+
+/// Link and assemble a project file, writing its image, symbols and listing.
+fn build_project(path: &str, json: bool) {
+    use setun::asm::project::{ImageFormat, Project};
+    use setun::{save_trom, TromFile};
+    
+    let project = Project::load(path).unwrap_or_else(|e| fail(json, e));
+    if !json {
+        println!("📦 Building {} ({} sources)", project.name, project.sources.len());
+    }
+    let build = project.build().unwrap_or_else(|e| fail(json, e));
+    let assembly = &build.assembly;
+    for warning in &assembly.warnings {
+        if !json {
+            let location = build.lines.get(warning.line.saturating_sub(1))
+                .map_or_else(|| warning.line.to_string(), |l| l.to_string());
+            eprintln!("⚠ {}: {}", location, warning.message);
+        }
+    }
+    
+    // Outputs may go to a build directory that does not exist yet
+    let create_parent = |path: &std::path::Path| {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                fail(json, format!("Failed to create {}: {}", dir.display(), e));
+            }
+        }
+    };
+    let write = |path: &std::path::Path, text: String| {
+        create_parent(path);
+        if let Err(e) = std::fs::write(path, text) {
+            fail(json, format!("Failed to write {}: {}", path.display(), e));
+        }
+    };
+    let image = project.image_path();
+    match project.output.format {
+        ImageFormat::Trom => {
+            create_parent(&image);
+            let trom = TromFile {
+                instructions: assembly.program.clone(),
+                source_lines: assembly.program.iter().map(|i| format!("{}", i)).collect(),
+            };
+            if let Err(e) = save_trom(&image, &trom) {
+                fail(json, format!("Failed to save TROM: {}", e));
+            }
+        }
+        ImageFormat::Tape => write(&image, boot_tape_text(&assembly.program, assembly.origin, &project.name)),
+    }
+    let symbols = project.symbols_path();
+    if let Some(path) = &symbols {
+        write(path, build.symbols_text());
+    }
+    let listing = project.listing_path();
+    if let Some(path) = &listing {
+        write(path, build.listing());
+    }
+    
+    if json {
+        let result = serde_json::json!({
+            "project": project.name,
+            "output": image,
+            "symbols_file": symbols,
+            "listing_file": listing,
+            "words": assembly.program.len(),
+            "origin": assembly.origin,
+            "symbols": assembly.symbols,
+            "warnings": assembly.warnings,
+        });
+        println!("{}", serde_json::to_string_pretty(&result).expect("build serialization cannot fail"));
+    } else {
+        println!("✓ Linked {} words at {} → {}", assembly.program.len(), assembly.origin, image.display());
+        for path in symbols.iter().chain(&listing) {
+            println!("✓ Wrote {}", path.display());
+        }
+    }
+}
+
+fn compile_file(source_path: &str, output: Option<String>, json: bool) {
+    use setun::lang::{compile, compile_program};
+
+    let out_path = output.unwrap_or_else(|| {
+        std::path::Path::new(source_path).with_extension("asm").to_string_lossy().into_owned()
+    });
+    let source = match std::fs::read_to_string(source_path) {
+        Ok(s) => s,
+        Err(e) => fail(json, format!("Failed to read file: {}", e)),
+    };
+
+    // Assemble too, so programs that do not fit are reported here
+    let compiled = compile(&source).and_then(|text| compile_program(&source).map(|assembly| (text, assembly)));
+    let (text, assembly) = match compiled {
+        Ok(compiled) => compiled,
+        Err(e) => {
+            if json {
+                let result = serde_json::json!({
+                    "source": source_path,
+                    "errors": [{ "line": e.line(), "message": e.to_string() }],
+                });
+                println!("{}", serde_json::to_string_pretty(&result).expect("diagnostics serialization cannot fail"));
+                std::process::exit(1);
+            }
+            fail(json, format!("Compile error: {}", e));
+        }
+    };
+
+    if out_path == "-" {
+        print!("{}", text);
+        return;
+    }
+    if let Err(e) = std::fs::write(&out_path, &text) {
+        fail(json, format!("Failed to write {}: {}", out_path, e));
+    }
+    if json {
+        let result = serde_json::json!({
+            "source": source_path,
+            "output": out_path,
+            "words": assembly.program.len(),
+            "origin": assembly.origin,
+        });
+        println!("{}", serde_json::to_string_pretty(&result).expect("compile result serialization cannot fail"));
+    } else {
+        println!("📝 Compiled {} → {} ({} words at {})", source_path, out_path, assembly.program.len(), assembly.origin);
+    }
 }
 
-fn main() {
-    let cli = Cli::parse();
+/// The `disasm` options beyond the TROM path and coverage.
+struct DisasmRequest {
+    output: Option<String>,
+    format: DisasmFormat,
+    symbols: Option<String>,
+    labels: bool,
+}
 
-    match cli.command {
-        Some(Commands::Run { program, max_cycles, trace }) => {
-            run_program(&program, max_cycles, trace);
-        }
-        Some(Commands::Debug { program }) => {
-            debug_program(&program);
+fn disassemble_file(trom_path: &str, coverage_cycles: Option<u64>, request: &DisasmRequest, json: bool) {
+    use setun::{load_trom};
+    use setun::asm::disasm::{
+        disassemble, disassemble_instruction, disassemble_lines, disassemble_with_coverage,
+        format_lines, format_lines_with_coverage, DisasmOptions,
+    };
+    
+    if coverage_cycles.is_some() && request.format == DisasmFormat::Asm {
+        fail(json, "--coverage cannot be combined with --format asm".to_string());
+    }
+    if !json && request.output.is_none() {
+        println!("📖 Disassembling: {}", trom_path);
+        println!();
+    }
+    
+    // Load TROM
+    let trom = match load_trom(trom_path) {
+        Ok(t) => t,
+        Err(e) => fail(json, format!("Failed to load TROM: {}", e)),
+    };
+    let symbols = match &request.symbols {
+        Some(path) => load_symbols(path).unwrap_or_else(|e| fail(json, format!("Failed to load symbols: {}", e))),
+        None => Default::default(),
+    };
+    let named = !symbols.is_empty() || request.labels;
+    let lines = disassemble_lines(&trom.instructions, &DisasmOptions { origin: 0, symbols, labels: request.labels });
+    
+    // Coverage of one run from address 0; a failing run still covers what it reached
+    let coverage = coverage_cycles.map(|max_cycles| {
+        let mut cpu = setun::Cpu::new();
+        if let Err(e) = cpu.load_program(&trom.instructions) {
+            fail(json, format!("Failed to load program: {}", e));
         }
-        Some(Commands::Asm { source, output }) => {
-            assemble_file(&source, output);
+        let mut coverage = setun::cpu::Coverage::new();
+        let _ = cpu.run_traced(max_cycles, |record| coverage.record(&record));
+        coverage
+    });
+    
+    if json && request.output.is_none() {
+        let words: Vec<_> = trom.instructions.iter().zip(&lines).enumerate().map(|(index, (&word, line))| {
+            let mut entry = serde_json::json!({
+                "index": index,
+                "trits": word.to_string(),
+                "value": word.to_i32(),
+                "text": disassemble_instruction(word),
+            });
+            if named {
+                entry["source"] = line.text.clone().into();
+                entry["label"] = serde_json::json!(line.label);
+            }
+            if let Some(coverage) = &coverage {
+                entry["executed"] = coverage.is_executed(index as i32).into();
+                entry["branch"] = serde_json::json!(coverage.branches.get(&(index as i32)));
+            }
+            entry
+        }).collect();
+        let mut result = serde_json::json!({ "file": trom_path, "words": words });
+        if let Some(coverage) = &coverage {
+            result["coverage"] = serde_json::json!(coverage.report(0, trom.instructions.len()));
         }
-        Some(Commands::Disasm { trom }) => {
-            disassemble_file(&trom);
+        println!("{}", serde_json::to_string_pretty(&result).expect("disassembly serialization cannot fail"));
+        return;
+    }
+    
+    // Disassemble; the plain listing keeps the decoder's own mnemonics
+    let output = match (&coverage, request.format) {
+        (Some(coverage), _) if named => format_lines_with_coverage(&lines, coverage),
+        (Some(coverage), _) => disassemble_with_coverage(&trom.instructions, 0, coverage),
+        (None, DisasmFormat::Listing) if !named => disassemble(&trom.instructions),
+        (None, format) => format_lines(&lines, format),
+    };
+    match &request.output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, &output) {
+                fail(json, format!("Failed to write {}: {}", path, e));
+            }
+            if json {
+                let result = serde_json::json!({ "file": trom_path, "output": path, "format": request.format.to_string(), "words": lines.len() });
+                println!("{}", serde_json::to_string_pretty(&result).expect("disassembly serialization cannot fail"));
+            } else {
+                println!("✓ Saved {} words to {}", lines.len(), path);
+            }
         }
-        Some(Commands::Test) => {
-            run_self_test();
+        None => println!("{}", output),
+    }
+}
+
+/// Read label addresses from `asm --json` output, a JSON object of name to
+/// address, or `NAME = addr` lines with `;` comments.
+fn load_symbols(path: &str) -> Result<std::collections::BTreeMap<String, i32>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(&text) {
+        let map = value.get("symbols").cloned().unwrap_or(value);
+        return serde_json::from_value(map).map_err(|e| format!("{}: {}", path, e));
+    }
+    let mut symbols = std::collections::BTreeMap::new();
+    for (index, raw) in text.lines().enumerate() {
+        let line = raw.split(';').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
         }
-        None => {
-            println!("Setun Emulator v0.1.0");
-            println!("A balanced ternary computer emulator");
-            println!();
-            println!("Use --help for available commands");
-            println!();
-            demo_ternary_primitives();
+        let parsed = line.split_once('=').and_then(|(name, addr)| {
+            let addr = setun::ternary::parse_number(addr.trim()).ok()?;
+            Some((name.trim().to_string(), i32::try_from(addr).ok()?))
+        });
+        match parsed {
+            Some((name, addr)) if !name.is_empty() => { symbols.insert(name, addr); }
+            _ => return Err(format!("{}:{}: expected NAME = address", path, index + 1)),
         }
     }
+    Ok(symbols)
 }
 
-fn run_program(path: &str, max_cycles: u64, trace: bool) {
-    use setun::{Cpu, Tryte9, load_trom, assemble};
-    use setun::asm::disasm::disassemble_instruction;
+fn convert_values(values: &[String], width: usize) {
+    use setun::ternary::{convert, Width};
+    use std::io::BufRead;
     
-    println!("🔧 Running: {}", path);
-    
-    // Load program (either TROM or ASM)
-    let instructions: Vec<Tryte9> = if path.ends_with(".asm") {
-        // Assemble first
-        let source = match std::fs::read_to_string(path) {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("❌ Failed to read file: {}", e);
-                std::process::exit(1);
-            }
-        };
-        
-        match assemble(&source) {
-            Ok(instrs) => {
-                println!("📝 Assembled {} instructions", instrs.len());
-                instrs
-            }
-            Err(e) => {
-                eprintln!("❌ Assembly error: {}", e);
-                std::process::exit(1);
-            }
+    let width = match Width::from_trits(width) {
+        Some(w) => w,
+        None => {
+            eprintln!("❌ Unsupported width {} (expected 9 or 18)", width);
+            std::process::exit(1);
         }
+    };
+    
+    // Batch mode: one value per line from stdin
+    let inputs: Vec<String> = if values.is_empty() || values == ["-"] {
+        std::io::stdin().lock().lines()
+            .map_while(Result::ok)
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect()
     } else {
-        // Load TROM
-        match load_trom(path) {
-            Ok(trom) => {
-                println!("📂 Loaded {} instructions", trom.len());
-                trom.instructions
-            }
+        values.to_vec()
+    };
+    
+    let mut failed = false;
+    for input in &inputs {
+        match convert(input, width) {
+            Ok(c) => println!("{}", c),
             Err(e) => {
-                eprintln!("❌ Failed to load TROM: {}", e);
-                std::process::exit(1);
+                eprintln!("❌ {}: {}", input, e);
+                failed = true;
             }
         }
-    };
-    
-    if instructions.is_empty() {
-        eprintln!("❌ No instructions to execute");
-        std::process::exit(1);
     }
     
-    // Create CPU and load program
-    let mut cpu = Cpu::new();
-    if let Err(e) = cpu.load_program(&instructions) {
-        eprintln!("❌ Failed to load program: {}", e);
+    if failed {
         std::process::exit(1);
     }
+}
+
+fn explain_arithmetic(values: &[String], html: bool, json: bool) {
+    use setun::ternary::arith::explain;
+    use std::io::BufRead;
     
-    println!();
-    println!("━━━ Execution ━━━");
+    // Arguments form one expression; stdin holds one per line
+    let inputs: Vec<String> = if values.is_empty() || values == ["-"] {
+        std::io::stdin().lock().lines()
+            .map_while(Result::ok)
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect()
+    } else {
+        vec![values.join(" ")]
+    };
     
-    // Run with optional trace
-    let mut cycles = 0u64;
-    while cpu.is_running() && cycles < max_cycles {
-        let pc = cpu.regs.c.to_i32();
-        
-        match cpu.step() {
-            Ok(instr) => {
-                if trace {
-                    let disasm = disassemble_instruction(setun::cpu::decode::encode(&instr));
-                    println!("{:03}: {}  S={} ω={:?}", 
-                        pc, disasm, cpu.regs.s.to_i64(), cpu.regs.omega);
+    let mut failed = false;
+    for (i, input) in inputs.iter().enumerate() {
+        match explain(input) {
+            Ok(e) if json => println!("{}", serde_json::to_string(&e).expect("explanation serialization cannot fail")),
+            Ok(e) if html => print!("{}", e.to_html()),
+            Ok(e) => {
+                if i > 0 {
+                    println!();
                 }
-                cycles += 1;
+                println!("{}", e);
             }
             Err(e) => {
-                eprintln!("❌ CPU error at PC={}: {}", pc, e);
-                std::process::exit(1);
+                eprintln!("❌ {}", e);
+                failed = true;
             }
         }
     }
     
-    println!();
-    println!("━━━ Result ━━━");
-    println!("Cycles: {}", cycles);
-    println!("State: {:?}", cpu.state);
-    println!("S (accumulator): {} ({})", cpu.regs.s, cpu.regs.s.to_i64());
-    println!("R (multiplier):  {} ({})", cpu.regs.r, cpu.regs.r.to_i64());
-    println!("F (index):       {}", cpu.regs.f.to_i32());
-    println!("ω (omega):       {:?}", cpu.regs.omega);
-    
-    if cycles >= max_cycles {
-        println!();
-        println!("⚠️  Reached max cycles limit ({}). Use --max-cycles to increase.", max_cycles);
+    if failed {
+        std::process::exit(1);
     }
 }
 
-fn debug_program(path: &str) {
-    use setun::{Tryte9, load_trom, assemble};
-    use setun::tui::run_debugger;
+fn describe_isa(format: &str, preset: &str, output: Option<&str>, json: bool) {
+    use setun::cpu::{CpuConfig, IsaDescription};
     
-    println!("🔍 Loading: {}", path);
+    let config = CpuConfig::preset(preset)
+        .unwrap_or_else(|| fail(json, format!("Unknown machine preset '{}' (expected simplified or historical)", preset)));
+    let isa = IsaDescription::new(config.isa);
     
-    // Load program (either TROM or ASM)
-    let instructions: Vec<Tryte9> = if path.ends_with(".asm") {
-        let source = match std::fs::read_to_string(path) {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("❌ Failed to read file: {}", e);
-                std::process::exit(1);
-            }
-        };
-        
-        match assemble(&source) {
-            Ok(instrs) => {
-                println!("📝 Assembled {} instructions", instrs.len());
-                instrs
-            }
-            Err(e) => {
-                eprintln!("❌ Assembly error: {}", e);
-                std::process::exit(1);
+    let text = match if json { "json" } else { format } {
+        "json" => isa.to_json() + "\n",
+        "toml" => isa.to_toml(),
+        "text" => {
+            let mut text = format!("{:<7} {:>6}  {:<11}  {:<10} {:>5}  {}\n", "name", "opcode", "encoding", "group", "µs", "semantics");
+            for i in &isa.instructions {
+                let group = serde_json::to_value(i.group).expect("group serialization cannot fail");
+                text += &format!(
+                    "{:<7} {:>6}  {:<11}  {:<10} {:>5}  {}\n",
+                    i.mnemonic, i.opcode, i.encoding, group.as_str().unwrap_or_default(), i.time_us, i.summary
+                );
             }
+            text
         }
-    } else {
-        match load_trom(path) {
-            Ok(trom) => {
-                println!("📂 Loaded {} instructions", trom.len());
-                trom.instructions
+        other => fail(json, format!("Unknown format '{}' (expected text, json or toml)", other)),
+    };
+    
+    match output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, text) {
+                fail(json, format!("Failed to write {}: {}", path, e));
             }
-            Err(e) => {
-                eprintln!("❌ Failed to load TROM: {}", e);
-                std::process::exit(1);
+            if !json {
+                println!("📄 Instruction set written to {}", path);
             }
         }
+        None => print!("{}", text),
+    }
+}
+
+fn run_bench(instructions: u64, workload: Option<&str>, mode: Option<setun::bench::Mode>, json: bool) {
+    use setun::bench::{find, measure, Mode, WORKLOADS};
+    
+    let workloads = match workload {
+        Some(name) => match find(name) {
+            Some(w) => std::slice::from_ref(w),
+            None => fail(json, format!("Unknown workload '{}'", name)),
+        },
+        None => WORKLOADS,
     };
+    let modes = mode.map_or(Mode::ALL.to_vec(), |m| vec![m]);
     
-    if instructions.is_empty() {
-        eprintln!("❌ No instructions to execute");
-        std::process::exit(1);
+    if !json {
+        println!("⏱️  Benchmarking {} instructions per measurement", instructions);
+        if cfg!(debug_assertions) {
+            println!("⚠️  Debug build; use `cargo run --release` for meaningful numbers");
+        }
+        println!();
+        println!("{:<14} {:<10} {:>12} {:>14}", "workload", "mode", "time (ms)", "instr/s");
     }
     
-    println!("🚀 Launching debugger...");
-    println!();
+    let mut results = Vec::new();
+    for workload in workloads {
+        for &mode in &modes {
+            let result = match measure(workload, mode, instructions) {
+                Ok(result) => result,
+                Err(e) => fail(json, format!("{} ({}): {}", workload.name, mode, e)),
+            };
+            if !json {
+                println!("{:<14} {:<10} {:>12.1} {:>14.0}",
+                    workload.name, mode, result.elapsed.as_secs_f64() * 1000.0, result.instructions_per_second());
+            }
+            results.push(result);
+        }
+    }
     
-    if let Err(e) = run_debugger(instructions) {
-        eprintln!("❌ Debugger error: {}", e);
-        std::process::exit(1);
+    if json {
+        let entries: Vec<_> = results.iter().map(|r| serde_json::json!({
+            "workload": r.workload,
+            "mode": r.mode,
+            "instructions": r.instructions,
+            "elapsed_ms": r.elapsed.as_secs_f64() * 1000.0,
+            "instructions_per_second": r.instructions_per_second(),
+        })).collect();
+        println!("{}", serde_json::to_string_pretty(&entries).expect("bench results serialization cannot fail"));
     }
 }
 
-fn assemble_file(source_path: &str, output: Option<String>) {
-    use setun::{assemble, save_trom, TromFile};
-    
-    let out_path = output.unwrap_or_else(|| {
-        source_path.replace(".asm", ".trom")
-    });
+fn dump_snapshot(path: &str, output: Option<&str>, json: bool) {
+    use setun::cpu::{MemInit, Snapshot};
     
-    println!("📝 Assembling: {} → {}", source_path, out_path);
+    let snapshot = match std::fs::read_to_string(path) {
+        Ok(text) => Snapshot::from_json(&text).unwrap_or_else(|e| fail(json, e)),
+        Err(e) => fail(json, format!("Failed to read snapshot: {}", e)),
+    };
+    let mem = &snapshot.cpu.mem;
     
-    // Read source
-    let source = match std::fs::read_to_string(source_path) {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("❌ Failed to read file: {}", e);
-            std::process::exit(1);
+    match output {
+        Some(out) => {
+            if let Err(e) = write_memory_dump(out, mem) {
+                fail(json, e);
+            }
+            if json {
+                println!("{}", serde_json::json!({ "snapshot": path, "output": out, "cells": mem.size() }));
+            } else {
+                println!("💾 Memory image of {} written to {}", path, out);
+            }
         }
+        None if json => {
+            let cells: Vec<_> = MemInit::from_memory(mem).entries.iter()
+                .map(|(addr, value)| serde_json::json!({ "address": addr, "value": value.to_i32(), "trits": value.to_string() }))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&cells).expect("memory serialization cannot fail"));
+        }
+        None => print!("{}", MemInit::from_memory(mem)),
+    }
+}
+
+fn corpus_stats(dir: &str, preset: &str, json: bool) {
+    use setun::asm::stats::CorpusStats;
+    use setun::cpu::CpuConfig;
+    
+    let config = CpuConfig::preset(preset)
+        .unwrap_or_else(|| fail(json, format!("Unknown machine preset '{}' (expected simplified or historical)", preset)));
+    let stats = match CorpusStats::scan(std::path::Path::new(dir), &config) {
+        Ok(stats) => stats,
+        Err(e) => fail(json, format!("Failed to read directory: {}", e)),
     };
     
-    // Assemble
-    let instructions = match assemble(&source) {
-        Ok(instrs) => instrs,
-        Err(e) => {
-            eprintln!("❌ Assembly error: {}", e);
-            std::process::exit(1);
-        }
+    if json {
+        let mut output = serde_json::to_value(&stats).expect("statistics serialization cannot fail");
+        output["sizes"] = serde_json::json!(stats.sizes());
+        println!("{}", serde_json::to_string_pretty(&output).expect("statistics serialization cannot fail"));
+        return;
+    }
+    
+    println!("━━━ {} program(s) in {} ━━━", stats.programs.len(), dir);
+    let Some(sizes) = stats.sizes() else {
+        println!("No .asm or .trom files found");
+        return;
     };
+    println!("Words: {} ({} instructions)", stats.words(), stats.instructions());
+    println!("Program size: {} to {} words, median {}, mean {:.1}", sizes.min, sizes.max, sizes.median, sizes.mean);
+    
+    let share = |count: usize, total: usize| 100.0 * count as f64 / total.max(1) as f64;
+    println!();
+    println!("Opcodes:       count   share");
+    for (mnemonic, count) in stats.ranked_opcodes() {
+        println!("  {:<7} {:>10} {:>6.1}%", mnemonic, count, share(count, stats.instructions()));
+    }
+    
+    let addressed: usize = stats.modes.values().sum();
+    println!();
+    println!("Address modes: count   share");
+    for (mode, count) in &stats.modes {
+        println!("  {:<8} {:>9} {:>6.1}%", mode, count, share(*count, addressed));
+    }
     
-    println!("✓ Assembled {} instructions", instructions.len());
+    if !stats.failures.is_empty() {
+        println!();
+        println!("⚠️  Skipped {} file(s):", stats.failures.len());
+        for (file, reason) in &stats.failures {
+            println!("  {}: {}", file, reason);
+        }
+    }
+}
+
+fn view_image(path: &str, grep: Option<&str>, page: usize, page_size: usize, json: bool) {
+    use setun::asm::view::{format_view, view_rows};
+    use setun::cpu::Snapshot;
     
-    // Save TROM
-    let trom = TromFile {
-        instructions: instructions.clone(),
-        source_lines: instructions.iter().map(|i| format!("{}", i)).collect(),
+    let rows = if path.ends_with(".json") {
+        let snapshot = match std::fs::read_to_string(path) {
+            Ok(text) => Snapshot::from_json(&text).unwrap_or_else(|e| fail(json, e)),
+            Err(e) => fail(json, format!("Failed to read snapshot: {}", e)),
+        };
+        let mem = &snapshot.cpu.mem;
+        view_rows(mem.as_slice(), -(mem.origin() as i32))
+    } else {
+        let (words, origin) = try_load_image(path).unwrap_or_else(|e| fail(json, e));
+        view_rows(&words, origin)
     };
+    let rows: Vec<_> = rows.into_iter().filter(|row| grep.is_none_or(|query| row.matches(query))).collect();
     
-    if let Err(e) = save_trom(&out_path, &trom) {
-        eprintln!("❌ Failed to save TROM: {}", e);
-        std::process::exit(1);
+    let page_size = if page_size == 0 { rows.len().max(1) } else { page_size };
+    let pages = rows.len().div_ceil(page_size).max(1);
+    if page == 0 || page > pages {
+        fail(json, format!("No page {}; {} has {} page(s)", page, path, pages));
     }
+    let shown = &rows[(page - 1) * page_size..(page * page_size).min(rows.len())];
     
-    println!("✓ Saved to {}", out_path);
+    if json {
+        println!("{}", serde_json::json!({ "page": page, "pages": pages, "cells": rows.len(), "rows": shown }));
+        return;
+    }
+    print!("{}", format_view(shown));
+    if pages > 1 {
+        println!("-- page {} of {} ({} cells){}", page, pages, rows.len(),
+            if page < pages { format!("; --page {} for more", page + 1) } else { String::new() });
+    }
 }
 
-fn disassemble_file(trom_path: &str) {
-    use setun::{load_trom};
-    use setun::asm::disasm::disassemble;
+#[cfg(feature = "script")]
+fn run_script(path: &str, program: Option<&str>, json: bool) {
+    use setun::script::ScriptEngine;
+    use setun::EmulatorSession;
     
-    println!("📖 Disassembling: {}", trom_path);
-    println!();
+    let mut session = EmulatorSession::new();
+    if let Some(program) = program {
+        let (instructions, origin) = load_program_file(program, true, json);
+        if let Err(e) = session.load_with_layout(instructions, origin, origin) {
+            fail(json, format!("Failed to load program: {}", e));
+        }
+    }
     
-    // Load TROM
-    let trom = match load_trom(trom_path) {
-        Ok(t) => t,
+    let mut engine = ScriptEngine::new(session, !json);
+    let result = engine.run_file(path);
+    let cpu = engine.session().cpu;
+    
+    if json {
+        println!("{}", serde_json::json!({
+            "script": path,
+            "printed": engine.take_printed(),
+            "error": result.as_ref().err().map(|e| e.to_string()),
+            "cycles": cpu.cycles,
+            "halted": cpu.is_halted(),
+        }));
+        if result.is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Err(e) = result {
+        fail(false, e);
+    }
+}
+
+#[cfg(feature = "serve")]
+fn serve(host: &str, port: u16) {
+    let listener = match std::net::TcpListener::bind((host, port)) {
+        Ok(listener) => listener,
         Err(e) => {
-            eprintln!("❌ Failed to load TROM: {}", e);
+            eprintln!("❌ Failed to bind {}:{}: {}", host, port, e);
             std::process::exit(1);
         }
     };
     
-    // Disassemble
-    let output = disassemble(&trom.instructions);
-    println!("{}", output);
+    println!("🌐 Serving on ws://{0}:{1}/ (HTTP POST to http://{0}:{1}/)", host, port);
+    println!("   Press Ctrl-C to stop");
+    if let Err(e) = setun::server::serve(listener) {
+        eprintln!("❌ Server error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run_repl() {
+    use setun::repl::Repl;
+    use std::io::{BufRead, Write};
+    
+    println!("Setun REPL - type 'help' for commands, 'quit' to leave");
+    
+    let mut repl = Repl::new();
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    
+    while !repl.finished {
+        print!("setun> ");
+        let _ = std::io::stdout().flush();
+        
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        
+        match repl.eval(&line) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{}", output),
+            Err(e) => println!("❌ {}", e),
+        }
+    }
 }
 
 fn demo_ternary_primitives() {
@@ -327,93 +2665,124 @@ fn demo_ternary_primitives() {
     println!("✓ Core ternary primitives working!");
 }
 
-fn run_self_test() {
+fn run_program_tests(dir: &str, json: bool) {
+    use setun::harness::run_test;
+    
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => fail(json, format!("Failed to read directory: {}", e)),
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "asm"))
+        .collect();
+    paths.sort();
+    
+    if !json {
+        println!("━━━ Testing {} program(s) in {} ━━━", paths.len(), dir);
+        println!();
+    }
+    
+    let mut failed = 0;
+    let mut results = Vec::new();
+    for path in &paths {
+        let name = path.display();
+        let outcome = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read file: {}", e))
+            .and_then(|source| run_test(&source).map_err(|e| e.to_string()));
+        
+        if outcome.as_ref().map_or(true, |o| !o.passed()) {
+            failed += 1;
+        }
+        if json {
+            results.push(match &outcome {
+                Ok(outcome) => serde_json::json!({
+                    "file": name.to_string(),
+                    "passed": outcome.passed(),
+                    "outcome": outcome,
+                }),
+                Err(e) => serde_json::json!({ "file": name.to_string(), "passed": false, "error": e }),
+            });
+            continue;
+        }
+        
+        match outcome {
+            Ok(outcome) if outcome.passed() => {
+                println!("  ✓ {} ({} checks, {} cycles)", name, outcome.checked, outcome.cycles);
+            }
+            Ok(outcome) => {
+                println!("  ✗ {}", name);
+                for failure in &outcome.failures {
+                    println!("      {}", failure);
+                }
+            }
+            Err(e) => {
+                println!("  ✗ {}", name);
+                println!("      {}", e);
+            }
+        }
+    }
+    
+    if json {
+        let summary = serde_json::json!({
+            "dir": dir,
+            "passed": paths.len() - failed,
+            "failed": failed,
+            "results": results,
+        });
+        println!("{}", serde_json::to_string_pretty(&summary).expect("test results serialization cannot fail"));
+    } else {
+        println!();
+        println!("Results: {} passed, {} failed", paths.len() - failed, failed);
+    }
+    
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// The built-in self-test checks: a name and `Err(detail)` on failure.
+fn self_test_checks() -> Vec<(&'static str, Result<(), String>)> {
     use setun::{Trit, Tryte9, Word18, Cpu};
     use setun::ternary::arith;
     use setun::cpu::decode::{Instruction, AddrMode, encode};
     
-    println!("━━━ Setun Emulator Self-Test ━━━");
-    println!();
-    
-    let mut passed = 0;
-    let mut failed = 0;
+    let check = |ok: bool| if ok { Ok(()) } else { Err(String::new()) };
+    let expect = |got: i64, expected: i64| {
+        if got == expected { Ok(()) } else { Err(format!("got {}, expected {}", got, expected)) }
+    };
+    let mut checks = Vec::new();
     
     // Test 1: Trit negation involution
-    print!("Trit negation involution... ");
-    let mut ok = true;
-    for t in Trit::ALL {
-        if t.neg().neg() != t {
-            ok = false;
-            break;
-        }
-    }
-    if ok { println!("✓"); passed += 1; } 
-    else { println!("✗"); failed += 1; }
+    checks.push(("Trit negation involution", check(Trit::ALL.iter().all(|t| t.neg().neg() == *t))));
     
     // Test 2: Conversion roundtrip
-    print!("Tryte9 conversion roundtrip... ");
-    ok = true;
-    for val in [-9841, -100, -1, 0, 1, 100, 9841] {
-        if Tryte9::from_i32(val).to_i32() != val {
-            ok = false;
-            break;
-        }
-    }
-    if ok { println!("✓"); passed += 1; } 
-    else { println!("✗"); failed += 1; }
+    checks.push(("Tryte9 conversion roundtrip", check(
+        [-9841, -100, -1, 0, 1, 100, 9841].iter().all(|&val| Tryte9::from_i32(val).to_i32() == val)
+    )));
     
     // Test 3: Additive inverse
-    print!("Additive inverse (a + -a = 0)... ");
-    ok = true;
-    for val in [-1000i64, -1, 0, 1, 1000] {
+    checks.push(("Additive inverse (a + -a = 0)", check([-1000i64, -1, 0, 1, 1000].iter().all(|&val| {
         let a = Word18::from_i64(val);
-        let neg_a = arith::negate(&a);
-        let (result, _) = arith::add(&a, &neg_a);
-        if !result.is_zero() {
-            ok = false;
-            break;
-        }
-    }
-    if ok { println!("✓"); passed += 1; } 
-    else { println!("✗"); failed += 1; }
+        let (result, _) = arith::add(&a, &arith::negate(&a));
+        result.is_zero()
+    }))));
     
     // Test 4: Multiplication correctness
-    print!("Multiplication correctness... ");
     let (prod, _) = arith::multiply(&Word18::from_i64(123), &Word18::from_i64(456));
-    if prod.to_i64() == 56088 {
-        println!("✓");
-        passed += 1;
-    } else {
-        println!("✗ (got {}, expected 56088)", prod.to_i64());
-        failed += 1;
-    }
+    checks.push(("Multiplication correctness", expect(prod.to_i64(), 56088)));
     
     // Test 5: Shift operations
-    print!("Shift left (×3) correctness... ");
     let shifted = arith::shift_left(&Word18::from_i64(1), 3);
-    if shifted.to_i64() == 27 {
-        println!("✓");
-        passed += 1;
-    } else {
-        println!("✗ (got {}, expected 27)", shifted.to_i64());
-        failed += 1;
-    }
+    checks.push(("Shift left (×3) correctness", expect(shifted.to_i64(), 27)));
     
     // Test 6: CPU execution
-    print!("CPU halt instruction... ");
     let mut cpu = Cpu::new();
     cpu.load_program(&[encode(&Instruction::Hlt)]).unwrap();
     let result = cpu.run();
-    if result.is_ok() && cpu.is_halted() {
-        println!("✓");
-        passed += 1;
-    } else {
-        println!("✗");
-        failed += 1;
-    }
+    checks.push(("CPU halt instruction", check(result.is_ok() && cpu.is_halted())));
     
     // Test 7: CPU arithmetic
-    print!("CPU load/add/store... ");
     let mut cpu = Cpu::new();
     cpu.mem.write(91, Tryte9::from_i32(10));
     cpu.mem.write(92, Tryte9::from_i32(5));
@@ -424,21 +2793,44 @@ fn run_self_test() {
     ];
     cpu.load_program(&program).unwrap();
     cpu.run().unwrap();
-    if cpu.regs.s.to_i64() == 15 {
-        println!("✓");
-        passed += 1;
-    } else {
-        println!("✗ (got {}, expected 15)", cpu.regs.s.to_i64());
-        failed += 1;
-    }
+    checks.push(("CPU load/add/store", expect(cpu.regs.s.to_i64(), 15)));
     
-    println!();
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    println!("Results: {} passed, {} failed", passed, failed);
+    checks
+}
+
+fn run_self_test(json: bool) {
+    let checks = self_test_checks();
+    let failed = checks.iter().filter(|(_, result)| result.is_err()).count();
+    let passed = checks.len() - failed;
     
-    if failed == 0 {
-        println!("✓ All tests passed!");
+    if json {
+        let tests: Vec<_> = checks.iter().map(|(name, result)| serde_json::json!({
+            "name": name,
+            "passed": result.is_ok(),
+            "detail": result.as_ref().err().filter(|d| !d.is_empty()),
+        })).collect();
+        let summary = serde_json::json!({ "passed": passed, "failed": failed, "tests": tests });
+        println!("{}", serde_json::to_string_pretty(&summary).expect("test results serialization cannot fail"));
     } else {
+        println!("━━━ Setun Emulator Self-Test ━━━");
+        println!();
+        for (name, result) in &checks {
+            match result {
+                Ok(()) => println!("{}... ✓", name),
+                Err(detail) if detail.is_empty() => println!("{}... ✗", name),
+                Err(detail) => println!("{}... ✗ ({})", name, detail),
+            }
+        }
+        
+        println!();
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        println!("Results: {} passed, {} failed", passed, failed);
+        if failed == 0 {
+            println!("✓ All tests passed!");
+        }
+    }
+    
+    if failed > 0 {
         std::process::exit(1);
     }
 }
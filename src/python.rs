@@ -0,0 +1,318 @@
+//! Python bindings for the Setun emulator.
+//!
+//! Built with the `python` feature (`maturin develop` uses it via
+//! `pyproject.toml`), this module exposes `setun.Cpu`, `setun.Tryte9`,
+//! `setun.Word18`, `setun.assemble` and `setun.disassemble`, so the
+//! emulator can be driven from notebooks:
+//!
+//! ```python
+//! import setun
+//! cpu = setun.Cpu()
+//! cpu.load_asm("LDA 3\nADD 3\nHLT\nDAT 21")
+//! cpu.run()
+//! cpu.s          # 42
+//! ```
+
+use crate::{Cpu, Tryte9, Word18};
+use crate::asm::assembler;
+use crate::asm::disasm::disassemble_instruction;
+use crate::cpu::decode::encode;
+use crate::cpu::Snapshot;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+fn value_error(e: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn tryte(value: i64) -> PyResult<Tryte9> {
+    if (Tryte9::MIN as i64..=Tryte9::MAX as i64).contains(&value) {
+        Ok(Tryte9::from_i32(value as i32))
+    } else {
+        Err(value_error(format!("{} does not fit in 9 trits", value)))
+    }
+}
+
+/// A 9-trit balanced ternary word.
+#[pyclass(name = "Tryte9", module = "setun", frozen, from_py_object)]
+#[derive(Clone, Copy)]
+pub struct PyTryte9(Tryte9);
+
+#[pymethods]
+impl PyTryte9 {
+    /// Create from an integer in [-9841, 9841].
+    #[new]
+    fn new(value: i64) -> PyResult<Self> {
+        tryte(value).map(PyTryte9)
+    }
+
+    /// Parse a trit string such as `"0tPNO"` or `"PNOOOOOOP"`.
+    #[staticmethod]
+    fn parse(text: &str) -> PyResult<Self> {
+        Tryte9::parse(text).map(PyTryte9).map_err(value_error)
+    }
+
+    /// The integer value.
+    #[getter]
+    fn value(&self) -> i32 {
+        self.0.to_i32()
+    }
+
+    /// The trits, least significant first, as -1/0/1.
+    #[getter]
+    fn trits(&self) -> Vec<i8> {
+        self.0.trits().iter().map(|&t| i8::from(t)).collect()
+    }
+
+    fn __int__(&self) -> i32 {
+        self.0.to_i32()
+    }
+
+    fn __neg__(&self) -> Self {
+        PyTryte9(-self.0)
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+
+    fn __hash__(&self) -> i64 {
+        self.0.to_i32() as i64
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Tryte9({})", self.0.to_i32())
+    }
+}
+
+/// An 18-trit balanced ternary word.
+#[pyclass(name = "Word18", module = "setun", frozen, from_py_object)]
+#[derive(Clone, Copy)]
+pub struct PyWord18(Word18);
+
+#[pymethods]
+impl PyWord18 {
+    /// Create from an integer in [-193710244, 193710244].
+    #[new]
+    fn new(value: i64) -> PyResult<Self> {
+        if (Word18::MIN..=Word18::MAX).contains(&value) {
+            Ok(PyWord18(Word18::from_i64(value)))
+        } else {
+            Err(value_error(format!("{} does not fit in 18 trits", value)))
+        }
+    }
+
+    /// Parse an 18-trit string.
+    #[staticmethod]
+    fn parse(text: &str) -> PyResult<Self> {
+        Word18::parse(text).map(PyWord18).map_err(value_error)
+    }
+
+    /// The integer value.
+    #[getter]
+    fn value(&self) -> i64 {
+        self.0.to_i64()
+    }
+
+    /// The low and high 9-trit halves.
+    #[getter]
+    fn halves(&self) -> (PyTryte9, PyTryte9) {
+        (PyTryte9(self.0.low()), PyTryte9(self.0.high()))
+    }
+
+    fn __int__(&self) -> i64 {
+        self.0.to_i64()
+    }
+
+    fn __neg__(&self) -> Self {
+        PyWord18(-self.0)
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+
+    fn __hash__(&self) -> i64 {
+        self.0.to_i64()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Word18({})", self.0.to_i64())
+    }
+}
+
+/// The Setun CPU with its memory, I/O ports enabled.
+#[pyclass(name = "Cpu", module = "setun")]
+pub struct PyCpu {
+    cpu: Cpu,
+    program: Vec<Tryte9>,
+}
+
+#[pymethods]
+impl PyCpu {
+    #[new]
+    fn new() -> Self {
+        let mut cpu = Cpu::new();
+        cpu.io.enable();
+        PyCpu { cpu, program: Vec::new() }
+    }
+
+    /// Assemble `source`, reset, and load it. Returns the number of words.
+    fn load_asm(&mut self, source: &str) -> PyResult<usize> {
+        let program = assembler::assemble(source).map_err(value_error)?;
+        self.load(program.iter().map(|w| w.to_i32() as i64).collect())
+    }
+
+    /// Reset and load words (integers) at address 0. Returns the number of words.
+    fn load(&mut self, words: Vec<i64>) -> PyResult<usize> {
+        let program = words.into_iter().map(tryte).collect::<PyResult<Vec<_>>>()?;
+        self.program = program;
+        self.reset()?;
+        Ok(self.program.len())
+    }
+
+    /// Reset the machine and reload the current program.
+    fn reset(&mut self) -> PyResult<()> {
+        self.cpu = Cpu::with_config(self.cpu.config.clone());
+        self.cpu.io.enable();
+        self.cpu.load_program(&self.program).map_err(value_error)
+    }
+
+    /// Execute one instruction and return its disassembly.
+    fn step(&mut self) -> PyResult<String> {
+        let instr = self.cpu.step().map_err(value_error)?;
+        Ok(disassemble_instruction(encode(&instr)))
+    }
+
+    /// Run until halt or `max_cycles` instructions. Returns the number executed.
+    #[pyo3(signature = (max_cycles = 100_000))]
+    fn run(&mut self, max_cycles: u64) -> PyResult<u64> {
+        self.cpu.run_limited(max_cycles).map_err(value_error)
+    }
+
+    /// Read `count` cells starting at address `addr`.
+    #[pyo3(signature = (addr, count = 1))]
+    fn read_memory(&self, addr: i64, count: usize) -> PyResult<Vec<i32>> {
+        (addr..addr + count as i64)
+            .map(|a| {
+                let word = self.cpu.mem.read_ternary(tryte(a)?).map_err(value_error)?;
+                Ok(word.to_i32())
+            })
+            .collect()
+    }
+
+    /// Write `values` to consecutive cells starting at address `addr`.
+    fn write_memory(&mut self, addr: i64, values: Vec<i64>) -> PyResult<()> {
+        for (offset, value) in values.into_iter().enumerate() {
+            let target = tryte(addr + offset as i64)?;
+            self.cpu.mem.write_ternary(target, tryte(value)?).map_err(value_error)?;
+        }
+        Ok(())
+    }
+
+    /// Append words to the paper tape read at address 120.
+    fn feed_input(&mut self, values: Vec<i64>) -> PyResult<()> {
+        let words = values.into_iter().map(tryte).collect::<PyResult<Vec<_>>>()?;
+        self.cpu.io.feed(words);
+        Ok(())
+    }
+
+    /// Take everything written to the teletype (address 121) so far.
+    fn take_output(&mut self) -> Vec<i32> {
+        self.cpu.io.take_output().iter().map(|w| w.to_i32()).collect()
+    }
+
+    /// Accumulator S.
+    #[getter]
+    fn s(&self) -> i64 {
+        self.cpu.regs.s.to_i64()
+    }
+
+    /// Multiplier register R.
+    #[getter]
+    fn r(&self) -> i64 {
+        self.cpu.regs.r.to_i64()
+    }
+
+    /// Index register F.
+    #[getter]
+    fn f(&self) -> i32 {
+        self.cpu.regs.f.to_i32()
+    }
+
+    /// Program counter C.
+    #[getter]
+    fn pc(&self) -> i32 {
+        self.cpu.regs.c.to_i32()
+    }
+
+    /// Sign register ω (-1, 0 or 1).
+    #[getter]
+    fn omega(&self) -> i8 {
+        i8::from(self.cpu.regs.omega)
+    }
+
+    /// Instructions executed since the last reset.
+    #[getter]
+    fn cycles(&self) -> u64 {
+        self.cpu.cycles
+    }
+
+    /// Whether the CPU has executed HLT.
+    #[getter]
+    fn halted(&self) -> bool {
+        self.cpu.is_halted()
+    }
+
+    /// Serialize the machine and program to a snapshot JSON string.
+    fn save_state(&self) -> String {
+        Snapshot::capture(&self.cpu, &self.program).to_json()
+    }
+
+    /// Restore from a string produced by `save_state`.
+    fn restore_state(&mut self, state: &str) -> PyResult<()> {
+        let snapshot = Snapshot::from_json(state).map_err(value_error)?;
+        self.cpu = snapshot.cpu;
+        self.program = snapshot.program;
+        Ok(())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Cpu(pc={}, s={}, cycles={}, state={:?})",
+            self.pc(), self.s(), self.cpu.cycles, self.cpu.state)
+    }
+}
+
+/// Assemble source text into a list of words.
+#[pyfunction]
+fn assemble(source: &str) -> PyResult<Vec<i32>> {
+    let program = assembler::assemble(source).map_err(value_error)?;
+    Ok(program.iter().map(|w| w.to_i32()).collect())
+}
+
+/// Disassemble words into one instruction string per word.
+#[pyfunction]
+fn disassemble(words: Vec<i64>) -> PyResult<Vec<String>> {
+    words.into_iter()
+        .map(|w| Ok(disassemble_instruction(tryte(w)?)))
+        .collect()
+}
+
+/// The `setun` Python module.
+#[pymodule]
+fn setun(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyCpu>()?;
+    m.add_class::<PyTryte9>()?;
+    m.add_class::<PyWord18>()?;
+    m.add_function(wrap_pyfunction!(assemble, m)?)?;
+    m.add_function(wrap_pyfunction!(disassemble, m)?)?;
+    Ok(())
+}
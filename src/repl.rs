@@ -0,0 +1,420 @@
+//! Line-based interactive environment.
+//!
+//! The REPL evaluates one line at a time against a live [`Cpu`]:
+//!
+//! - arithmetic expressions (`42 * 0tPN - S`) are evaluated and shown in
+//!   decimal and balanced ternary
+//! - `asm <instr>` assembles a single instruction and shows its encoding
+//! - `peek`, `poke` and `set` inspect and modify memory and registers
+//! - `step`, `run` and `reset` drive execution
+//!
+//! The frontend only has to read lines, call [`Repl::eval`] and print the
+//! result; see `help` for the full command list.
+
+use crate::asm::{assemble, disasm::{disassemble_instruction, disassemble_instruction_for}};
+use crate::cpu::registers::Tryte5;
+use crate::cpu::Cpu;
+use crate::ternary::{convert, parse_number, Trit, Tryte9, Word18, Width};
+use thiserror::Error;
+
+/// Help text printed by the `help` command.
+pub const HELP: &str = "\
+Expressions:
+  42 + 0tPN * (3 - S)    evaluate (+ - * /, parentheses, registers S R F C)
+Commands:
+  asm <instr>            assemble one instruction and show its encoding
+  peek <addr> [count]    show memory cells
+  poke <addr> <value>    write a number or instruction to memory
+  set <reg> <value>      set register S, R, F, C or W (omega)
+  regs                   show registers
+  step [n]               execute n instructions (default 1)
+  run [max]              run until halt (default 10000 instructions)
+  reset                  clear registers and memory
+  help                   show this text
+  quit                   leave the REPL";
+
+/// Default instruction limit for `run`.
+const DEFAULT_RUN_LIMIT: u64 = 10_000;
+
+/// Interactive evaluation state.
+pub struct Repl {
+    /// The machine commands operate on.
+    pub cpu: Cpu,
+    /// Set once `quit` has been entered.
+    pub finished: bool,
+}
+
+impl Repl {
+    /// Create a REPL with a fresh CPU.
+    pub fn new() -> Self {
+        Self::with_cpu(Cpu::new())
+    }
+
+    /// Create a REPL around an existing CPU.
+    pub fn with_cpu(cpu: Cpu) -> Self {
+        Self { cpu, finished: false }
+    }
+
+    /// Evaluate one line of input and return the text to display.
+    pub fn eval(&mut self, line: &str) -> Result<String, ReplError> {
+        let line = line.trim();
+        let (command, rest) = match line.split_once(char::is_whitespace) {
+            Some((cmd, rest)) => (cmd, rest.trim()),
+            None => (line, ""),
+        };
+
+        match command.to_ascii_lowercase().as_str() {
+            "" => Ok(String::new()),
+            "help" | "?" => Ok(HELP.to_string()),
+            "quit" | "exit" => {
+                self.finished = true;
+                Ok(String::new())
+            }
+            "asm" => self.cmd_asm(rest),
+            "peek" => self.cmd_peek(rest),
+            "poke" => self.cmd_poke(rest),
+            "set" => self.cmd_set(rest),
+            "regs" => Ok(self.format_registers()),
+            "step" => self.cmd_step(rest),
+            "run" => self.cmd_run(rest),
+            "reset" => {
+                self.cpu.reset();
+                Ok("CPU reset".into())
+            }
+            _ => {
+                let value = self.eval_expr(line)?;
+                let c = convert(&value.to_string(), Width::Word18)
+                    .map_err(|e| ReplError::Eval(e.to_string()))?;
+                Ok(format!("{} = {}", c.decimal, c.ternary))
+            }
+        }
+    }
+
+    /// Evaluate an arithmetic expression against the current registers.
+    pub fn eval_expr(&self, expr: &str) -> Result<i64, ReplError> {
+        let tokens = tokenize(expr)?;
+        let mut parser = ExprParser { tokens: &tokens, pos: 0, cpu: &self.cpu };
+        let value = parser.expr()?;
+        if parser.pos != tokens.len() {
+            return Err(ReplError::Eval(format!("unexpected '{}'", tokens[parser.pos])));
+        }
+        Ok(value)
+    }
+
+    fn cmd_asm(&self, instr: &str) -> Result<String, ReplError> {
+        let word = assemble_one(instr)?;
+        Ok(format!("{} = {} ({})", disassemble_instruction(word), word, word.to_i32()))
+    }
+
+    fn cmd_peek(&self, args: &str) -> Result<String, ReplError> {
+        let (addr, count) = match args.split_once(char::is_whitespace) {
+            Some((addr, count)) => (addr, self.eval_expr(count)?),
+            None => (args, 1),
+        };
+        let addr = self.eval_expr(addr)?;
+
+        let end = addr.checked_add(count.max(1)).ok_or_else(overflow)?;
+        let mut lines = Vec::new();
+        for a in addr..end {
+            let word = self.read_cell(a)?;
+            lines.push(format!("{:+4}: {} {:+6}  {}", a, word, word.to_i32(), disassemble_instruction_for(word, &self.cpu.config)));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn cmd_poke(&mut self, args: &str) -> Result<String, ReplError> {
+        let (addr, value) = args.split_once(char::is_whitespace)
+            .ok_or(ReplError::Usage("poke <addr> <value>"))?;
+        let addr = self.eval_expr(addr)?;
+        let value = value.trim();
+
+        // Numbers first, then fall back to an instruction
+        let word = match self.eval_expr(value) {
+            Ok(v) => to_tryte(v)?,
+            Err(_) => assemble_one(value)?,
+        };
+
+        let index = self.cell_index(addr)?;
+        self.cpu.mem.write(index, word);
+        Ok(format!("{:+4}: {} {:+6}", addr, word, word.to_i32()))
+    }
+
+    fn cmd_set(&mut self, args: &str) -> Result<String, ReplError> {
+        let (reg, value) = args.split_once(char::is_whitespace)
+            .ok_or(ReplError::Usage("set <reg> <value>"))?;
+        let value = self.eval_expr(value)?;
+        let out_of_range = || ReplError::Eval(format!("{} out of range for register {}", value, reg));
+
+        match reg.to_ascii_uppercase().as_str() {
+            "S" | "R" => {
+                if value.unsigned_abs() > Word18::MAX as u64 {
+                    return Err(out_of_range());
+                }
+                let word = Word18::from_i64(value);
+                if reg.eq_ignore_ascii_case("S") {
+                    self.cpu.regs.s = word;
+                } else {
+                    self.cpu.regs.r = word;
+                }
+            }
+            "F" => {
+                if value.unsigned_abs() > Tryte5::MAX as u64 {
+                    return Err(out_of_range());
+                }
+                self.cpu.regs.f = Tryte5::from_i32(value as i32);
+            }
+            "C" => self.cpu.regs.c = to_tryte(value)?,
+            "W" | "OMEGA" | "Ω" | "ω" => {
+                if value.unsigned_abs() > 1 {
+                    return Err(out_of_range());
+                }
+                self.cpu.regs.omega = Trit::from_i8(value as i8);
+            }
+            _ => return Err(ReplError::Eval(format!("unknown register {}", reg))),
+        }
+        Ok(self.format_registers())
+    }
+
+    fn cmd_step(&mut self, args: &str) -> Result<String, ReplError> {
+        let count = if args.is_empty() { 1 } else { self.eval_expr(args)? };
+
+        let mut lines = Vec::new();
+        for _ in 0..count.max(1) {
+            if !self.cpu.is_running() {
+                lines.push(format!("CPU {:?}", self.cpu.state));
+                break;
+            }
+            let record = self.cpu.step_traced()?;
+            lines.push(format!("{:+4}: {}", record.pc, disassemble_instruction_for(record.raw, &self.cpu.config)));
+        }
+        lines.push(self.format_registers());
+        Ok(lines.join("\n"))
+    }
+
+    fn cmd_run(&mut self, args: &str) -> Result<String, ReplError> {
+        let limit = if args.is_empty() { DEFAULT_RUN_LIMIT as i64 } else { self.eval_expr(args)? };
+        let executed = self.cpu.run_limited(limit.max(0) as u64)?;
+        Ok(format!("executed {} instructions, CPU {:?}\n{}",
+            executed, self.cpu.state, self.format_registers()))
+    }
+
+    fn format_registers(&self) -> String {
+        let regs = &self.cpu.regs;
+        format!(
+            "S={} ({})  R={} ({})  F={}  C={}  ω={:?}",
+            regs.s, regs.s.to_i64(), regs.r, regs.r.to_i64(),
+            regs.f.to_i32(), regs.c.to_i32(), regs.omega
+        )
+    }
+
+    fn cell_index(&self, addr: i64) -> Result<usize, ReplError> {
+        let addr = to_tryte(addr)?;
+        Ok(self.cpu.mem.addr_to_index(addr)?)
+    }
+
+    fn read_cell(&self, addr: i64) -> Result<Tryte9, ReplError> {
+        Ok(self.cpu.mem.read(self.cell_index(addr)?))
+    }
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Assemble a single instruction line into one word.
+fn assemble_one(instr: &str) -> Result<Tryte9, ReplError> {
+    let words = assemble(instr).map_err(|e| ReplError::Eval(e.to_string()))?;
+    match words.as_slice() {
+        [word] => Ok(*word),
+        _ => Err(ReplError::Eval(format!("expected one instruction, got {}", words.len()))),
+    }
+}
+
+fn to_tryte(value: i64) -> Result<Tryte9, ReplError> {
+    if value.unsigned_abs() > Tryte9::MAX as u64 {
+        return Err(ReplError::Eval(format!("{} does not fit in 9 trits", value)));
+    }
+    Ok(Tryte9::from_i32(value as i32))
+}
+
+// ============================================================================
+// Expression evaluation
+
+/// Split an expression into number, register, operator and parenthesis tokens.
+///
+/// Ternary literals in expressions use N/O/P digits only, so `-` is
+/// always an operator.
+fn tokenize(expr: &str) -> Result<Vec<String>, ReplError> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if "+-*/()".contains(c) {
+            tokens.push(c.to_string());
+            chars.next();
+        } else if c.is_ascii_alphanumeric() {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek() {
+                if !c.is_ascii_alphanumeric() {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        } else {
+            return Err(ReplError::Eval(format!("unexpected character '{}'", c)));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive-descent parser over expression tokens.
+struct ExprParser<'a> {
+    tokens: &'a [String],
+    pos: usize,
+    cpu: &'a Cpu,
+}
+
+impl ExprParser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn expr(&mut self) -> Result<i64, ReplError> {
+        let mut value = self.term()?;
+        while let Some(op @ ("+" | "-")) = self.peek() {
+            let op = op.to_string();
+            self.pos += 1;
+            let rhs = self.term()?;
+            value = if op == "+" { value.checked_add(rhs) } else { value.checked_sub(rhs) }
+                .ok_or_else(overflow)?;
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<i64, ReplError> {
+        let mut value = self.unary()?;
+        while let Some(op @ ("*" | "/")) = self.peek() {
+            let op = op.to_string();
+            self.pos += 1;
+            let rhs = self.unary()?;
+            value = if op == "*" {
+                value.checked_mul(rhs).ok_or_else(overflow)?
+            } else if rhs == 0 {
+                return Err(ReplError::Eval("division by zero".into()));
+            } else {
+                value.checked_div(rhs).ok_or_else(overflow)?
+            };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<i64, ReplError> {
+        if self.peek() == Some("-") {
+            self.pos += 1;
+            return self.unary()?.checked_neg().ok_or_else(overflow);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<i64, ReplError> {
+        let token = self.peek()
+            .ok_or_else(|| ReplError::Eval("unexpected end of expression".into()))?
+            .to_string();
+        self.pos += 1;
+
+        if token == "(" {
+            let value = self.expr()?;
+            if self.peek() != Some(")") {
+                return Err(ReplError::Eval("missing ')'".into()));
+            }
+            self.pos += 1;
+            return Ok(value);
+        }
+
+        let regs = &self.cpu.regs;
+        match token.to_ascii_uppercase().as_str() {
+            "S" => Ok(regs.s.to_i64()),
+            "R" => Ok(regs.r.to_i64()),
+            "F" => Ok(regs.f.to_i32() as i64),
+            "C" => Ok(regs.c.to_i32() as i64),
+            _ => parse_number(&token).map_err(|e| ReplError::Eval(e.to_string())),
+        }
+    }
+}
+
+fn overflow() -> ReplError {
+    ReplError::Eval("arithmetic overflow".into())
+}
+
+/// Errors reported by the REPL.
+#[derive(Debug, Error)]
+pub enum ReplError {
+    #[error("{0}")]
+    Eval(String),
+
+    #[error("usage: {0}")]
+    Usage(&'static str),
+
+    #[error("{0}")]
+    Cpu(#[from] crate::cpu::CpuError),
+
+    #[error("{0}")]
+    Memory(#[from] crate::cpu::memory::MemoryError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expressions() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.eval_expr("1 + 2 * 3").unwrap(), 7);
+        assert_eq!(repl.eval_expr("-(0tPN - 10) / 2").unwrap(), 4);
+        assert_eq!(repl.eval("0tPNNNO").unwrap(), "42 = 0tOOOOOOOOOOOOOPNNNO");
+        assert!(repl.eval_expr("1 / 0").is_err());
+        assert!(repl.eval_expr("(1 + 2").is_err());
+
+        // Results that do not fit in an i64 are errors, not panics
+        assert!(repl.eval_expr("-(-9223372036854775807 - 1)").is_err());
+        assert!(repl.eval_expr("(-9223372036854775807 - 1) / -1").is_err());
+        assert!(repl.eval("peek 9223372036854775807 1").is_err());
+        assert!(repl.eval("set S -9223372036854775807 - 1").is_err());
+    }
+
+    #[test]
+    fn test_poke_step_and_registers() {
+        let mut repl = Repl::new();
+        repl.eval("poke 10 7").unwrap();
+        repl.eval("poke 0 LDA 10").unwrap();
+        repl.eval("poke 1 ADD 10").unwrap();
+        repl.eval("poke 2 HLT").unwrap();
+
+        repl.eval("step 2").unwrap();
+        assert_eq!(repl.eval_expr("S").unwrap(), 14);
+
+        repl.eval("set R S * 2").unwrap();
+        assert_eq!(repl.cpu.regs.r.to_i64(), 28);
+        assert!(repl.eval("peek 10").unwrap().contains("+7"));
+
+        repl.eval("run").unwrap();
+        assert!(repl.cpu.is_halted());
+    }
+
+    #[test]
+    fn test_quit_and_errors() {
+        let mut repl = Repl::new();
+        assert!(repl.eval("asm LDA 10").unwrap().starts_with("LDA"));
+        assert!(matches!(repl.eval("poke 1"), Err(ReplError::Usage(_))));
+        assert!(repl.eval("poke 500 1").is_err());
+        repl.eval("quit").unwrap();
+        assert!(repl.finished);
+    }
+}
@@ -0,0 +1,373 @@
+//! Memory access patterns over time.
+//!
+//! An [`AccessPattern`] turns trace records into a flat list of memory
+//! events — instruction fetches, data reads and data writes — and exports
+//! them for plotting:
+//!
+//! - **SVG**: an address-versus-cycle scatter plot, binned into a heatmap
+//!   when the run is longer than the plot is wide
+//! - **CSV**: one row per event, for spreadsheets and plotting scripts
+//! - **DOT**: a Graphviz graph from each instruction address to the data
+//!   addresses it touches
+//!
+//! Writes to an address that is also fetched as an instruction are
+//! flagged as code writes in every format, which makes self-modifying
+//! code stand out.
+
+use crate::cpu::{AccessKind, Memory, TraceRecord};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// What kind of memory access an [`AccessEvent`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// Instruction fetch.
+    Fetch,
+    /// Data read.
+    Read,
+    /// Data write.
+    Write,
+}
+
+impl EventKind {
+    fn name(self) -> &'static str {
+        match self {
+            EventKind::Fetch => "fetch",
+            EventKind::Read => "read",
+            EventKind::Write => "write",
+        }
+    }
+}
+
+/// One memory access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessEvent {
+    /// Cycle of the instruction making the access.
+    pub cycle: u64,
+    /// Address of that instruction.
+    pub pc: i32,
+    /// Fetch, read or write.
+    pub kind: EventKind,
+    /// Address accessed.
+    pub addr: i32,
+    /// Word fetched, read or written.
+    pub value: i32,
+}
+
+/// Export format for [`AccessPattern::write`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccessFormat {
+    /// Address-versus-cycle plot.
+    #[default]
+    Svg,
+    /// One row per event.
+    Csv,
+    /// Graphviz graph of instruction-to-data accesses.
+    Dot,
+}
+
+impl AccessFormat {
+    /// Pick a format from a file name's extension.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?;
+        extension.parse().ok()
+    }
+}
+
+impl FromStr for AccessFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "svg" => Ok(AccessFormat::Svg),
+            "csv" => Ok(AccessFormat::Csv),
+            "dot" | "gv" => Ok(AccessFormat::Dot),
+            _ => Err(format!("unknown access map format '{}' (expected svg, csv or dot)", s)),
+        }
+    }
+}
+
+impl fmt::Display for AccessFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessFormat::Svg => write!(f, "svg"),
+            AccessFormat::Csv => write!(f, "csv"),
+            AccessFormat::Dot => write!(f, "dot"),
+        }
+    }
+}
+
+/// Plot area of the SVG, in pixels.
+const PLOT_WIDTH: u64 = 800;
+/// Height of one address row in the SVG.
+const ROW_HEIGHT: i32 = 3;
+/// Margins around the SVG plot area: left, top, right, bottom.
+const MARGIN: (i32, i32, i32, i32) = (50, 30, 20, 40);
+
+/// Memory events collected from a run.
+#[derive(Debug, Clone)]
+pub struct AccessPattern {
+    events: Vec<AccessEvent>,
+    addr_range: (i32, i32),
+}
+
+impl Default for AccessPattern {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AccessPattern {
+    /// Start an empty pattern for the default memory size.
+    pub fn new() -> Self {
+        let mem = Memory::new();
+        Self { events: Vec::new(), addr_range: (mem.min_addr(), mem.max_addr()) }
+    }
+
+    /// Set the address range plotted in the SVG.
+    pub fn with_memory(mut self, mem: &Memory) -> Self {
+        self.addr_range = (mem.min_addr(), mem.max_addr());
+        self
+    }
+
+    /// Add the fetch and data accesses of one executed instruction.
+    pub fn record(&mut self, record: &TraceRecord) {
+        let event = |kind, addr, value| AccessEvent { cycle: record.cycle, pc: record.pc, kind, addr, value };
+        self.events.push(event(EventKind::Fetch, record.pc, record.raw.to_i32()));
+        for access in &record.accesses {
+            let kind = match access.kind {
+                AccessKind::Read => EventKind::Read,
+                AccessKind::Write => EventKind::Write,
+            };
+            self.events.push(event(kind, access.addr, access.value));
+        }
+    }
+
+    /// All events, in execution order.
+    pub fn events(&self) -> &[AccessEvent] {
+        &self.events
+    }
+
+    /// Addresses fetched as instructions at least once.
+    pub fn code_addresses(&self) -> BTreeSet<i32> {
+        self.events.iter()
+            .filter(|e| e.kind == EventKind::Fetch)
+            .map(|e| e.addr)
+            .collect()
+    }
+
+    /// Writes to addresses that are also executed: self-modifying code.
+    pub fn code_writes(&self) -> Vec<AccessEvent> {
+        let code = self.code_addresses();
+        self.events.iter()
+            .filter(|e| e.kind == EventKind::Write && code.contains(&e.addr))
+            .copied()
+            .collect()
+    }
+
+    /// Export in `format`.
+    pub fn write(&self, out: impl Write, format: AccessFormat) -> io::Result<()> {
+        match format {
+            AccessFormat::Svg => self.write_svg(out),
+            AccessFormat::Csv => self.write_csv(out),
+            AccessFormat::Dot => self.write_dot(out),
+        }
+    }
+
+    /// Write one CSV row per event; `code_write` is 1 for writes to executed addresses.
+    pub fn write_csv(&self, mut out: impl Write) -> io::Result<()> {
+        let code = self.code_addresses();
+        writeln!(out, "cycle,pc,kind,addr,value,code_write")?;
+        for e in &self.events {
+            let code_write = e.kind == EventKind::Write && code.contains(&e.addr);
+            writeln!(out, "{},{},{},{},{},{}", e.cycle, e.pc, e.kind.name(), e.addr, e.value, code_write as u8)?;
+        }
+        Ok(())
+    }
+
+    /// Write a Graphviz digraph with an edge from each instruction address
+    /// to every data address it read or wrote, labelled with the counts.
+    pub fn write_dot(&self, mut out: impl Write) -> io::Result<()> {
+        let code = self.code_addresses();
+        let mut edges: BTreeMap<(i32, i32), (u64, u64)> = BTreeMap::new();
+        for e in self.events.iter().filter(|e| e.kind != EventKind::Fetch) {
+            let counts = edges.entry((e.pc, e.addr)).or_default();
+            if e.kind == EventKind::Read {
+                counts.0 += 1;
+            } else {
+                counts.1 += 1;
+            }
+        }
+        let data: BTreeSet<i32> = edges.keys().map(|&(_, addr)| addr).collect();
+
+        writeln!(out, "digraph memory {{")?;
+        writeln!(out, "    rankdir=LR;")?;
+        writeln!(out, "    node [fontname=\"monospace\"];")?;
+        for &addr in code.union(&data) {
+            let style = match (code.contains(&addr), data.contains(&addr)) {
+                (true, true) => "shape=box, style=filled, fillcolor=\"#f5b7b1\"",
+                (true, false) => "shape=box",
+                _ => "shape=ellipse",
+            };
+            writeln!(out, "    \"{}\" [{}];", addr, style)?;
+        }
+        for (&(pc, addr), &(reads, writes)) in &edges {
+            let label = match (reads, writes) {
+                (0, w) => format!("W×{}", w),
+                (r, 0) => format!("R×{}", r),
+                (r, w) => format!("R×{} W×{}", r, w),
+            };
+            let color = if writes > 0 && code.contains(&addr) {
+                "red"
+            } else if writes > 0 {
+                "\"#c0392b\""
+            } else {
+                "\"#2471a3\""
+            };
+            writeln!(out, "    \"{}\" -> \"{}\" [label=\"{}\", color={}];", pc, addr, label, color)?;
+        }
+        writeln!(out, "}}")
+    }
+
+    /// Write an SVG plot of address (vertical) against cycle (horizontal).
+    ///
+    /// Fetches are grey, reads blue and writes red; writes to executed
+    /// addresses are outlined in black. Runs longer than the plot width
+    /// are binned, with darker marks for more accesses per bin.
+    pub fn write_svg(&self, mut out: impl Write) -> io::Result<()> {
+        let (min_addr, max_addr) = self.addr_range;
+        let first = self.events.first().map_or(0, |e| e.cycle);
+        let last = self.events.last().map_or(0, |e| e.cycle);
+        let cycles = last - first + 1;
+        let bin = cycles.div_ceil(PLOT_WIDTH).max(1);
+        let columns = cycles.div_ceil(bin);
+        let cell_width = (PLOT_WIDTH as f64 / columns as f64).min(8.0);
+        let plot_width = cell_width * columns as f64;
+        let plot_height = (max_addr - min_addr + 1) * ROW_HEIGHT;
+        let (left, top, right, bottom) = MARGIN;
+        let width = left as f64 + plot_width + right as f64;
+        let height = top + plot_height + bottom;
+
+        let code = self.code_addresses();
+        let mut cells: BTreeMap<(EventKind, u64, i32), u64> = BTreeMap::new();
+        for e in self.events.iter().filter(|e| (min_addr..=max_addr).contains(&e.addr)) {
+            *cells.entry((e.kind, (e.cycle - first) / bin, e.addr)).or_default() += 1;
+        }
+        let densest = cells.values().copied().max().unwrap_or(1);
+
+        writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{}" font-family="monospace" font-size="10">"#, width, height)?;
+        writeln!(out, r##"<rect width="100%" height="100%" fill="#fff"/>"##)?;
+        writeln!(out, r##"<rect x="{}" y="{}" width="{:.1}" height="{}" fill="#fafafa" stroke="#ccc"/>"##, left, top, plot_width, plot_height)?;
+
+        // Address axis, a gridline every 27 words
+        let y = |addr: i32| top + (max_addr - addr) * ROW_HEIGHT;
+        for addr in (min_addr..=max_addr).filter(|a| a % 27 == 0) {
+            writeln!(out, r##"<line x1="{}" x2="{:.1}" y1="{}" y2="{}" stroke="#eee"/>"##, left, left as f64 + plot_width, y(addr), y(addr))?;
+            writeln!(out, r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#, left - 4, y(addr) + 3, addr)?;
+        }
+
+        // Cycle axis, five ticks
+        for i in 0..5u64 {
+            let column = columns.saturating_sub(1) * i / 4;
+            let x = left as f64 + (column as f64 + 0.5) * cell_width;
+            writeln!(out, r#"<text x="{:.1}" y="{}" text-anchor="middle">{}</text>"#, x, top + plot_height + 14, first + column * bin)?;
+        }
+        writeln!(out, r#"<text x="{:.1}" y="{}" text-anchor="middle">cycle{}</text>"#,
+            left as f64 + plot_width / 2.0, height - 6,
+            if bin > 1 { format!(" ({} per column)", bin) } else { String::new() })?;
+        writeln!(out, r#"<text x="{}" y="{}" transform="rotate(-90 12 {})" text-anchor="middle">address</text>"#,
+            12, top + plot_height / 2, top + plot_height / 2)?;
+
+        // Accesses, drawn fetches first so data accesses stay visible on top
+        for (&(kind, column, addr), &count) in &cells {
+            let fill = match kind {
+                EventKind::Fetch => "#999",
+                EventKind::Read => "#2471a3",
+                EventKind::Write => "#c0392b",
+            };
+            let opacity = 0.35 + 0.65 * (count as f64).ln_1p() / (densest as f64).ln_1p();
+            let stroke = if kind == EventKind::Write && code.contains(&addr) { r##" stroke="#000""## } else { "" };
+            writeln!(out, r#"<rect x="{:.1}" y="{}" width="{:.1}" height="{}" fill="{}" fill-opacity="{:.2}"{}><title>{} {} ×{} @ cycle {}</title></rect>"#,
+                left as f64 + column as f64 * cell_width, y(addr) - ROW_HEIGHT / 2, cell_width, ROW_HEIGHT,
+                fill, opacity, stroke, kind.name(), addr, count, first + column * bin)?;
+        }
+
+        // Legend
+        let legend = [("fetch", "#999"), ("read", "#2471a3"), ("write", "#c0392b")];
+        for (i, (name, color)) in legend.iter().enumerate() {
+            let x = left + i as i32 * 70;
+            writeln!(out, r#"<rect x="{}" y="10" width="10" height="10" fill="{}"/><text x="{}" y="19">{}</text>"#, x, color, x + 14, name)?;
+        }
+        writeln!(out, r##"<rect x="{}" y="10" width="10" height="10" fill="#c0392b" stroke="#000"/><text x="{}" y="19">write to code</text>"##,
+            left + 210, left + 224)?;
+        writeln!(out, "</svg>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::Cpu;
+
+    /// Runs a program that patches its own HLT into a NOP once.
+    fn self_modifying() -> AccessPattern {
+        let program = assemble("
+            LDA patch
+            STA target
+        target:
+            HLT
+            HLT
+        patch:
+            NOP
+        ").unwrap();
+        let mut cpu = Cpu::new();
+        cpu.load_program(&program).unwrap();
+        let mut pattern = AccessPattern::new().with_memory(&cpu.mem);
+        cpu.run_traced(100, |record| pattern.record(&record)).unwrap();
+        pattern
+    }
+
+    #[test]
+    fn test_events_and_code_writes() {
+        let pattern = self_modifying();
+        let kinds: Vec<_> = pattern.events().iter().map(|e| (e.kind, e.addr)).collect();
+        assert_eq!(kinds, vec![
+            (EventKind::Fetch, 0), (EventKind::Read, 4),
+            (EventKind::Fetch, 1), (EventKind::Write, 2),
+            (EventKind::Fetch, 2), (EventKind::Fetch, 3),
+        ]);
+        assert_eq!(pattern.code_writes().len(), 1);
+        assert_eq!(pattern.code_writes()[0].pc, 1);
+    }
+
+    #[test]
+    fn test_exports() {
+        let pattern = self_modifying();
+        let mut csv = Vec::new();
+        pattern.write(&mut csv, AccessFormat::Csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 7);
+        assert!(csv.contains("\n2,1,write,2,"));
+        assert!(csv.lines().nth(4).unwrap().ends_with(",1"));
+
+        let mut dot = Vec::new();
+        pattern.write(&mut dot, AccessFormat::Dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.contains("\"1\" -> \"2\" [label=\"W×1\", color=red];"));
+        assert!(dot.contains("\"0\" -> \"4\" [label=\"R×1\""));
+
+        let mut svg = Vec::new();
+        pattern.write(&mut svg, AccessFormat::Svg).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<title>write 2").count(), 1);
+
+        assert_eq!(AccessFormat::from_path("out/map.DOT"), Some(AccessFormat::Dot));
+        assert_eq!(AccessFormat::from_path("map"), None);
+    }
+}
@@ -0,0 +1,228 @@
+//! Standalone HTML trace reports.
+//!
+//! An [`HtmlReport`] collects the [`TraceRecord`]s of a run and renders
+//! them, with the [`Profile`] built from the same records, as a single
+//! self-contained HTML file: a timeline scrubber, plots of S, R, F and C,
+//! a memory heatmap of executions, reads and writes, and a disassembly
+//! listing linked to the timeline. The file needs no server or network
+//! access, so it can be attached to an assignment or bug report as is.
+//!
+//! [`access`] exports the memory accesses of a run on their own, as an
+//! address-versus-cycle plot or a dataset for other tools.
+//!
+//! ```
+//! use setun::{assemble, Cpu};
+//! use setun::report::HtmlReport;
+//!
+//! let program = assemble("LDA 3\nADD 3\nHLT\nDAT 21").unwrap();
+//! let mut cpu = Cpu::new();
+//! cpu.load_program(&program).unwrap();
+//!
+//! let mut report = HtmlReport::new("double").with_program(0, &program);
+//! cpu.run_traced(100, |record| report.record(record)).unwrap();
+//! assert!(report.render().contains("<title>double</title>"));
+//! ```
+
+pub mod access;
+
+pub use access::{AccessEvent, AccessFormat, AccessPattern, EventKind};
+
+use crate::asm::disasm::disassemble_instruction;
+use crate::cpu::{AccessKind, ExecutionReport, Memory, Profile, TraceRecord};
+use crate::ternary::Tryte9;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Page template; `{{TITLE}}` and `{{DATA}}` are filled in by [`HtmlReport::render`].
+const TEMPLATE: &str = include_str!("template.html");
+
+/// Per-address activity shown in the heatmap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Heat {
+    exec: u64,
+    reads: u64,
+    writes: u64,
+}
+
+/// Builder for an HTML trace report.
+#[derive(Debug, Clone)]
+pub struct HtmlReport {
+    title: String,
+    records: Vec<TraceRecord>,
+    profile: Profile,
+    stats: ExecutionReport,
+    program: BTreeMap<i32, Tryte9>,
+    addr_range: (i32, i32),
+}
+
+impl HtmlReport {
+    /// Start an empty report for the default memory size.
+    pub fn new(title: impl Into<String>) -> Self {
+        let mem = Memory::new();
+        Self {
+            title: title.into(),
+            records: Vec::new(),
+            profile: Profile::new(),
+            stats: ExecutionReport::new(),
+            program: BTreeMap::new(),
+            addr_range: (mem.min_addr(), mem.max_addr()),
+        }
+    }
+
+    /// Build a report from already recorded trace records (e.g. a JSON Lines trace file).
+    pub fn from_records(title: impl Into<String>, records: impl IntoIterator<Item = TraceRecord>) -> Self {
+        let mut report = Self::new(title);
+        for record in records {
+            report.record(record);
+        }
+        report
+    }
+
+    /// List the program loaded at `origin` in the disassembly, including
+    /// words that never execute. Without it only executed addresses are listed.
+    pub fn with_program(mut self, origin: i32, words: &[Tryte9]) -> Self {
+        self.program.extend(words.iter().enumerate().map(|(i, &word)| (origin + i as i32, word)));
+        self
+    }
+
+    /// Set the address range drawn in the memory heatmap.
+    pub fn with_memory(mut self, mem: &Memory) -> Self {
+        self.addr_range = (mem.min_addr(), mem.max_addr());
+        self
+    }
+
+    /// Add one executed instruction.
+    pub fn record(&mut self, record: TraceRecord) {
+        self.profile.record(&record);
+        self.stats.record(&record);
+        self.records.push(record);
+    }
+
+    /// The profile of the recorded instructions.
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Per-opcode statistics of the recorded instructions.
+    pub fn execution_report(&self) -> &ExecutionReport {
+        &self.stats
+    }
+
+    /// Number of recorded instructions.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether no instructions have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Render the report as a standalone HTML document.
+    pub fn render(&self) -> String {
+        // Embedded in a <script> element, so `</` must not appear literally
+        let data = self.data().to_string().replace("</", "<\\/");
+        TEMPLATE
+            .replace("{{TITLE}}", &escape_html(&self.title))
+            .replace("{{DATA}}", &data)
+    }
+
+    /// Everything the page script needs, as one JSON object.
+    fn data(&self) -> serde_json::Value {
+        let mut listing = self.program.clone();
+        for record in &self.records {
+            listing.entry(record.pc).or_insert(record.raw);
+        }
+
+        let (min_addr, max_addr) = self.addr_range;
+        let mut heat: BTreeMap<i32, Heat> = BTreeMap::new();
+        for (&addr, &count) in &self.profile.address_counts {
+            heat.entry(addr).or_default().exec = count;
+        }
+        for access in self.records.iter().flat_map(|r| &r.accesses) {
+            let cell = heat.entry(access.addr).or_default();
+            match access.kind {
+                AccessKind::Read => cell.reads += 1,
+                AccessKind::Write => cell.writes += 1,
+            }
+        }
+
+        let steps: Vec<_> = self.records.iter().map(|r| json!({
+            "cycle": r.cycle,
+            "pc": r.pc,
+            "text": disassemble_instruction(r.raw),
+            "s": r.regs.s,
+            "r": r.regs.r,
+            "f": r.regs.f,
+            "c": r.regs.c,
+            "omega": r.regs.omega.to_i8(),
+            "access": r.accesses.iter()
+                .map(|a| json!([if a.kind == AccessKind::Read { "R" } else { "W" }, a.addr, a.value]))
+                .collect::<Vec<_>>(),
+        })).collect();
+
+        json!({
+            "title": self.title,
+            "min_addr": min_addr,
+            "max_addr": max_addr,
+            "steps": steps,
+            "listing": listing.iter().map(|(&addr, &word)| json!({
+                "addr": addr,
+                "word": word.to_i32(),
+                "text": disassemble_instruction(word),
+                "count": self.profile.address_counts.get(&addr).copied().unwrap_or(0),
+            })).collect::<Vec<_>>(),
+            "heat": heat.iter().map(|(&addr, h)| json!([addr, h.exec, h.reads, h.writes])).collect::<Vec<_>>(),
+            "profile": self.profile,
+            "stats": self.stats,
+        })
+    }
+}
+
+/// Escape text for use in HTML element content.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::Cpu;
+
+    fn report_for(source: &str) -> HtmlReport {
+        let program = assemble(source).unwrap();
+        let mut cpu = Cpu::new();
+        cpu.load_program(&program).unwrap();
+        let mut report = HtmlReport::new("test").with_program(0, &program).with_memory(&cpu.mem);
+        cpu.run_traced(100, |record| report.record(record)).unwrap();
+        report
+    }
+
+    #[test]
+    fn test_report_data() {
+        let report = report_for("LDA 4\nSTA 5\nHLT\nJMP 0\nDAT 7");
+        assert_eq!(report.len(), 3);
+        assert_eq!(report.profile().instructions, 3);
+
+        let data = report.data();
+        assert_eq!(data["steps"][0]["s"], 7);
+        assert_eq!(data["steps"][1]["access"][0], json!(["W", 5, 7]));
+        // The never-executed JMP is still listed
+        assert_eq!(data["listing"][3]["text"], "JMP 0");
+        assert_eq!(data["listing"][3]["count"], 0);
+        assert!(data["heat"].as_array().unwrap().contains(&json!([4, 0, 1, 0])));
+        assert_eq!(data["min_addr"], -81);
+        assert_eq!(data["stats"]["opcodes"]["STA"]["writes"], 1);
+    }
+
+    #[test]
+    fn test_render_escapes_title_and_script() {
+        let report = HtmlReport::from_records("a </script> & b", report_for("HLT").records.clone());
+        let html = report.render();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>a &lt;/script&gt; &amp; b</title>"));
+        assert!(!html.contains("{{DATA}}"));
+        assert_eq!(html.matches("</script>").count(), TEMPLATE.matches("</script>").count());
+    }
+}
@@ -0,0 +1,320 @@
+//! Rhai scripting for experiments and automation.
+//!
+//! Scripts see the machine as a global `cpu` object, plus a few free
+//! functions, so setups such as "sweep these inputs and collect the
+//! results" need no Rust harness:
+//!
+//! ```text
+//! cpu.load("LDA 120\nADD 120\nSTA 121\nHLT");
+//! for x in 1..=5 {
+//!     cpu.reset();
+//!     cpu.feed([x, x]);
+//!     cpu.run();
+//!     print(`${x}: ${cpu.output()}`);
+//! }
+//! ```
+//!
+//! `cpu` members:
+//!
+//! | member | meaning |
+//! |--------|---------|
+//! | `s`, `r`, `f`, `pc` | registers (read/write) |
+//! | `omega`, `cycles`, `halted` | sign register, instruction count, halt flag |
+//! | `load(source)`, `load(words)`, `load_file(path)` | load a program, resetting the machine |
+//! | `reset()` | reset and reload the program |
+//! | `step()` | execute one instruction, returning its disassembly |
+//! | `run()`, `run(max)` | run until halt, a breakpoint, or `max` instructions |
+//! | `peek(addr)`, `poke(addr, value)` | read and write memory |
+//! | `break_at(addr)`, `clear_break(addr)`, `breakpoints()` | breakpoints |
+//! | `feed(words)`, `output()` | paper tape input and teletype output |
+//!
+//! Free functions: `assemble(source)` returns the words of a program and
+//! `disassemble(word)` decodes one word. `print` output is collected (and
+//! optionally echoed), since the TUI cannot show it directly.
+
+use crate::{Tryte9, Word18};
+use crate::asm::assembler::assemble;
+use crate::asm::disasm::disassemble_instruction;
+use crate::asm::trom::load_trom;
+use crate::cpu::decode::encode;
+use crate::cpu::StopReason;
+use crate::cpu::registers::Tryte5;
+use crate::session::EmulatorSession;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Scope, INT};
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+use thiserror::Error;
+
+/// Cycle limit for `cpu.run()` without an argument.
+pub const DEFAULT_RUN_CYCLES: u64 = 100_000;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Handle to the shared machine, registered with Rhai as `Cpu`.
+#[derive(Clone)]
+struct CpuHandle(Rc<RefCell<EmulatorSession>>);
+
+fn error(e: impl ToString) -> Box<EvalAltResult> {
+    e.to_string().into()
+}
+
+fn tryte(value: INT) -> ScriptResult<Tryte9> {
+    if (Tryte9::MIN as INT..=Tryte9::MAX as INT).contains(&value) {
+        Ok(Tryte9::from_i32(value as i32))
+    } else {
+        Err(error(format!("{} does not fit in 9 trits", value)))
+    }
+}
+
+fn words(array: Array) -> ScriptResult<Vec<Tryte9>> {
+    array.into_iter()
+        .map(|item| item.as_int().map_err(|ty| error(format!("expected an integer, found {}", ty))).and_then(tryte))
+        .collect()
+}
+
+fn word18(value: INT) -> ScriptResult<Word18> {
+    if (Word18::MIN..=Word18::MAX).contains(&value) {
+        Ok(Word18::from_i64(value))
+    } else {
+        Err(error(format!("{} does not fit in 18 trits", value)))
+    }
+}
+
+/// Load a `.trom` image or assemble any other file.
+fn read_program(path: &str) -> ScriptResult<Vec<Tryte9>> {
+    if Path::new(path).extension().is_some_and(|ext| ext == "trom") {
+        return load_trom(path).map(|trom| trom.instructions).map_err(error);
+    }
+    let source = std::fs::read_to_string(path).map_err(|e| error(format!("{}: {}", path, e)))?;
+    assemble(&source).map_err(error)
+}
+
+/// Load a program at address 0, resetting the machine. Returns its length.
+fn load(h: &mut CpuHandle, program: Vec<Tryte9>) -> ScriptResult<INT> {
+    let len = program.len() as INT;
+    h.0.borrow_mut().load(program).map_err(error)?;
+    Ok(len)
+}
+
+/// Run until halt, a breakpoint, or `max_cycles` instructions. Returns the number executed.
+fn run(h: &mut CpuHandle, max_cycles: INT) -> ScriptResult<INT> {
+    let summary = h.0.borrow_mut().run(max_cycles.max(0) as u64);
+    match summary.error {
+        Some(e) if summary.stop_reason == StopReason::Error => Err(error(e)),
+        _ => Ok(summary.executed as INT),
+    }
+}
+
+fn register_api(engine: &mut Engine) {
+    engine.register_type_with_name::<CpuHandle>("Cpu");
+
+    engine.register_get("s", |h: &mut CpuHandle| h.0.borrow().cpu.regs.s.to_i64());
+    engine.register_set("s", |h: &mut CpuHandle, v: INT| -> ScriptResult<()> {
+        h.0.borrow_mut().cpu.regs.s = word18(v)?;
+        Ok(())
+    });
+    engine.register_get("r", |h: &mut CpuHandle| h.0.borrow().cpu.regs.r.to_i64());
+    engine.register_set("r", |h: &mut CpuHandle, v: INT| -> ScriptResult<()> {
+        h.0.borrow_mut().cpu.regs.r = word18(v)?;
+        Ok(())
+    });
+    engine.register_get("f", |h: &mut CpuHandle| h.0.borrow().cpu.regs.f.to_i32() as INT);
+    engine.register_set("f", |h: &mut CpuHandle, v: INT| -> ScriptResult<()> {
+        if !(Tryte5::MIN as INT..=Tryte5::MAX as INT).contains(&v) {
+            return Err(error(format!("{} does not fit in 5 trits", v)));
+        }
+        h.0.borrow_mut().cpu.regs.f = Tryte5::from_i32(v as i32);
+        Ok(())
+    });
+    engine.register_get("pc", |h: &mut CpuHandle| h.0.borrow().cpu.regs.c.to_i32() as INT);
+    engine.register_set("pc", |h: &mut CpuHandle, v: INT| -> ScriptResult<()> {
+        let addr = tryte(v)?;
+        let mut machine = h.0.borrow_mut();
+        machine.cpu.mem.addr_to_index(addr).map_err(error)?;
+        machine.cpu.regs.c = addr;
+        Ok(())
+    });
+    engine.register_get("omega", |h: &mut CpuHandle| i8::from(h.0.borrow().cpu.regs.omega) as INT);
+    engine.register_get("cycles", |h: &mut CpuHandle| h.0.borrow().cpu.cycles as INT);
+    engine.register_get("halted", |h: &mut CpuHandle| h.0.borrow().cpu.is_halted());
+
+    engine.register_fn("load", |h: &mut CpuHandle, source: &str| -> ScriptResult<INT> {
+        let program = assemble(source).map_err(error)?;
+        load(h, program)
+    });
+    engine.register_fn("load", |h: &mut CpuHandle, array: Array| -> ScriptResult<INT> {
+        let program = words(array)?;
+        load(h, program)
+    });
+    engine.register_fn("load_file", |h: &mut CpuHandle, path: &str| -> ScriptResult<INT> {
+        let program = read_program(path)?;
+        load(h, program)
+    });
+    engine.register_fn("reset", |h: &mut CpuHandle| h.0.borrow_mut().reset().map_err(error));
+    engine.register_fn("step", |h: &mut CpuHandle| -> ScriptResult<String> {
+        let instr = h.0.borrow_mut().cpu.step().map_err(error)?;
+        Ok(disassemble_instruction(encode(&instr)))
+    });
+    engine.register_fn("run", |h: &mut CpuHandle| run(h, DEFAULT_RUN_CYCLES as INT));
+    engine.register_fn("run", run);
+
+    engine.register_fn("peek", |h: &mut CpuHandle, addr: INT| -> ScriptResult<INT> {
+        let word = h.0.borrow().cpu.mem.read_ternary(tryte(addr)?).map_err(error)?;
+        Ok(word.to_i32() as INT)
+    });
+    engine.register_fn("poke", |h: &mut CpuHandle, addr: INT, value: INT| -> ScriptResult<()> {
+        let (addr, value) = (tryte(addr)?, tryte(value)?);
+        h.0.borrow_mut().cpu.mem.write_ternary(addr, value).map_err(error)
+    });
+
+    engine.register_fn("break_at", |h: &mut CpuHandle, addr: INT| {
+        h.0.borrow_mut().set_breakpoint(addr as i32);
+    });
+    engine.register_fn("clear_break", |h: &mut CpuHandle, addr: INT| {
+        h.0.borrow_mut().clear_breakpoint(addr as i32);
+    });
+    engine.register_fn("breakpoints", |h: &mut CpuHandle| -> Array {
+        h.0.borrow().breakpoints().iter().map(|&addr| Dynamic::from(addr as INT)).collect()
+    });
+
+    engine.register_fn("feed", |h: &mut CpuHandle, array: Array| -> ScriptResult<()> {
+        let input = words(array)?;
+        h.0.borrow_mut().cpu.io.feed(input);
+        Ok(())
+    });
+    engine.register_fn("output", |h: &mut CpuHandle| -> Array {
+        h.0.borrow_mut().cpu.io.take_output().iter().map(|w| Dynamic::from(w.to_i32() as INT)).collect()
+    });
+
+    engine.register_fn("assemble", |source: &str| -> ScriptResult<Array> {
+        let program = assemble(source).map_err(error)?;
+        Ok(program.iter().map(|w| Dynamic::from(w.to_i32() as INT)).collect())
+    });
+    engine.register_fn("disassemble", |word: INT| -> ScriptResult<String> {
+        Ok(disassemble_instruction(tryte(word)?))
+    });
+}
+
+/// Runs scripts against an [`EmulatorSession`].
+pub struct ScriptEngine {
+    engine: Engine,
+    session: Rc<RefCell<EmulatorSession>>,
+    printed: Rc<RefCell<Vec<String>>>,
+}
+
+impl ScriptEngine {
+    /// Script `session`, with its I/O ports enabled. With `echo`, `print`
+    /// output also goes to stdout as it happens.
+    pub fn new(mut session: EmulatorSession, echo: bool) -> Self {
+        session.cpu.io.enable();
+        let printed = Rc::new(RefCell::new(Vec::new()));
+        let mut engine = Engine::new();
+        register_api(&mut engine);
+
+        let sink = Rc::clone(&printed);
+        engine.on_print(move |text| {
+            if echo {
+                println!("{}", text);
+            }
+            sink.borrow_mut().push(text.to_string());
+        });
+        let sink = Rc::clone(&printed);
+        engine.on_debug(move |text, _, pos| {
+            let line = format!("[{}] {}", pos, text);
+            if echo {
+                println!("{}", line);
+            }
+            sink.borrow_mut().push(line);
+        });
+
+        ScriptEngine { engine, session: Rc::new(RefCell::new(session)), printed }
+    }
+
+    /// Run a script. The session keeps whatever state the script left,
+    /// even when it fails part-way.
+    pub fn run(&mut self, source: &str) -> Result<(), ScriptError> {
+        let mut scope = Scope::new();
+        scope.push("cpu", CpuHandle(Rc::clone(&self.session)));
+        self.engine.run_with_scope(&mut scope, source)
+            .map_err(|e| ScriptError::Eval(e.to_string()))
+    }
+
+    /// Read and run a script file.
+    pub fn run_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), ScriptError> {
+        let source = std::fs::read_to_string(path.as_ref()).map_err(|e| ScriptError::Io(e.to_string()))?;
+        self.run(&source)
+    }
+
+    /// Remove and return the lines printed so far.
+    pub fn take_printed(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.printed.borrow_mut())
+    }
+
+    /// The session in its current state.
+    pub fn session(&self) -> EmulatorSession {
+        self.session.borrow().clone()
+    }
+}
+
+/// Errors from running a script.
+#[derive(Debug, Clone, Error)]
+pub enum ScriptError {
+    #[error("I/O error: {0}")]
+    Io(String),
+
+    #[error("script error: {0}")]
+    Eval(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str) -> (ScriptEngine, Result<(), ScriptError>) {
+        let mut engine = ScriptEngine::new(EmulatorSession::new(), false);
+        let result = engine.run(source);
+        (engine, result)
+    }
+
+    #[test]
+    fn test_sweep_inputs() {
+        let (mut engine, result) = run(r#"
+            cpu.load("LDA 120\nADD 120\nSTA 121\nHLT");
+            for x in 1..=3 {
+                cpu.reset();
+                cpu.feed([x, x + 1]);
+                cpu.run();
+                print(cpu.output());
+            }
+        "#);
+        result.unwrap();
+        assert_eq!(engine.take_printed(), vec!["[3]", "[5]", "[7]"]);
+        assert!(engine.session().cpu.is_halted());
+    }
+
+    #[test]
+    fn test_registers_memory_and_breakpoints() {
+        let (engine, result) = run(r#"
+            cpu.load("ADD 4\nADD 4\nADD 4\nHLT\nDAT 5");
+            cpu.break_at(2);
+            let executed = cpu.run();
+            if executed != 2 || cpu.s != 10 { throw "bad run"; }
+            cpu.poke(10, if disassemble(assemble("HLT")[0]) == "HLT" { 7 } else { 0 });
+            cpu.f = 3;
+        "#);
+        result.unwrap();
+        let session = engine.session();
+        assert_eq!(session.cpu.regs.c.to_i32(), 2);
+        assert_eq!(session.cpu.regs.f.to_i32(), 3);
+        assert_eq!(session.cpu.mem.read_ternary(Tryte9::from_i32(10)).unwrap().to_i32(), 7);
+        assert_eq!(session.breakpoints().iter().copied().collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(run("cpu.load(\"FOO\")").1, Err(ScriptError::Eval(_))));
+        assert!(matches!(run("cpu.poke(500, 1)").1, Err(ScriptError::Eval(_))));
+        assert!(matches!(run("cpu.f = 1000").1, Err(ScriptError::Eval(_))));
+    }
+}
@@ -0,0 +1,536 @@
+//! Remote control of the emulator over WebSocket and HTTP.
+//!
+//! `setun-emu serve` exposes the emulator through a JSON protocol so
+//! external GUIs and notebooks can drive it without linking the crate.
+//! Each WebSocket connection owns its own [`Session`]: the client sends
+//! [`ServerCommand`]s as text frames and receives [`ServerMessage`]s.
+//! An optional `id` on a command is echoed on its reply.
+//!
+//! ```text
+//! → {"id": 1, "cmd": "load", "source": "LDA 3\nADD 3\nHLT\nDAT 21"}
+//! ← {"id": 1, "type": "loaded", "words": 4}
+//! → {"cmd": "subscribe", "events": ["trace", "output"]}
+//! ← {"type": "ok"}
+//! → {"id": 2, "cmd": "run"}
+//! ← {"type": "trace", "record": {...}}        (one per instruction)
+//! ← {"id": 2, "type": "stopped", "reason": "halted", ...}
+//! ```
+//!
+//! Plain HTTP is supported for one-shot use: `POST /` with a command or an
+//! array of commands runs them against a fresh session and answers with
+//! every message as a JSON array.
+
+use crate::{Cpu, CpuState, Tryte9};
+use crate::cpu::{RegisterState, StopReason, TraceRecord};
+use crate::session::EmulatorSession;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use tungstenite::Message;
+
+/// Cycle limit for `run` when the command does not give one.
+pub const DEFAULT_RUN_CYCLES: u64 = 100_000;
+
+/// A request sent by a client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum ServerCommand {
+    /// Assemble and load a program, resetting the CPU.
+    Load { source: String },
+    /// Load already-assembled words (decimal values), resetting the CPU.
+    LoadWords { words: Vec<i32> },
+    /// Execute `count` instructions, ignoring breakpoints.
+    Step {
+        #[serde(default = "one")]
+        count: u64,
+    },
+    /// Run until halt, error, a breakpoint, or `max_cycles` instructions.
+    Run {
+        #[serde(default = "default_run_cycles")]
+        max_cycles: u64,
+    },
+    /// Reset the CPU and reload the current program.
+    Reset,
+    /// Report the register file.
+    Registers,
+    /// Read `count` memory cells starting at address `addr`.
+    ReadMemory {
+        addr: i32,
+        #[serde(default = "one_cell")]
+        count: usize,
+    },
+    /// Write `values` to consecutive cells starting at address `addr`.
+    WriteMemory { addr: i32, values: Vec<i32> },
+    /// Stop `run` before executing the instruction at `addr`.
+    SetBreakpoint { addr: i32 },
+    /// Remove a breakpoint.
+    ClearBreakpoint { addr: i32 },
+    /// List all breakpoints.
+    Breakpoints,
+    /// Append words to the paper tape read at address 120.
+    Input { values: Vec<i32> },
+    /// Choose which events are pushed to this client (replaces the previous set).
+    Subscribe { events: Vec<EventKind> },
+}
+
+fn one() -> u64 {
+    1
+}
+
+fn one_cell() -> usize {
+    1
+}
+
+fn default_run_cycles() -> u64 {
+    DEFAULT_RUN_CYCLES
+}
+
+/// Events a client can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// A `trace` message for every executed instruction.
+    Trace,
+    /// An `output` message whenever the program prints on the teletype.
+    Output,
+}
+
+/// Why `step` or `run` stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopCause {
+    /// The CPU halted (or was already stopped).
+    Halted,
+    /// The requested number of instructions was executed.
+    CycleLimit,
+    /// The next instruction is at a breakpoint.
+    Breakpoint,
+    /// A caller-supplied stop condition held.
+    Condition,
+    /// An instruction raised an error.
+    Error,
+}
+
+impl From<StopReason> for StopCause {
+    fn from(reason: StopReason) -> Self {
+        match reason {
+            StopReason::Halted => StopCause::Halted,
+            StopReason::CycleLimit => StopCause::CycleLimit,
+            StopReason::Breakpoint => StopCause::Breakpoint,
+            StopReason::Condition => StopCause::Condition,
+            StopReason::Error => StopCause::Error,
+        }
+    }
+}
+
+/// A message sent to a client: a reply to a command, or a pushed event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// A program was loaded.
+    Loaded { words: usize },
+    /// Execution stopped after `step` or `run`.
+    Stopped {
+        executed: u64,
+        reason: StopCause,
+        error: Option<String>,
+        state: CpuState,
+        regs: RegisterState,
+        cycles: u64,
+    },
+    /// Current register file.
+    Registers { regs: RegisterState, state: CpuState, cycles: u64 },
+    /// A block of memory cells (decimal values) starting at `addr`.
+    Memory { addr: i32, values: Vec<i32> },
+    /// Current breakpoints, in ascending order.
+    Breakpoints { addrs: Vec<i32> },
+    /// The command succeeded with nothing to report.
+    Ok,
+    /// The command failed.
+    Error { message: String },
+    /// Event: one instruction was executed.
+    Trace { record: TraceRecord },
+    /// Event: the program printed on the teletype.
+    Output { values: Vec<i32> },
+}
+
+/// A message tagged with the id of the command it answers.
+#[derive(Serialize)]
+struct Envelope<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<&'a serde_json::Value>,
+    #[serde(flatten)]
+    message: &'a ServerMessage,
+}
+
+/// A command tagged with an optional client-chosen id.
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    command: ServerCommand,
+}
+
+/// Protocol handler owning one client's emulator.
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    emulator: EmulatorSession,
+    events: BTreeSet<EventKind>,
+}
+
+impl Session {
+    /// Create a session with an empty machine and I/O ports enabled.
+    pub fn new() -> Self {
+        let mut session = Self::default();
+        session.emulator.cpu.io.enable();
+        session
+    }
+
+    /// The emulated CPU.
+    pub fn cpu(&self) -> &Cpu {
+        &self.emulator.cpu
+    }
+
+    /// Handle one command. The reply is the last message; any events
+    /// produced along the way come before it.
+    pub fn handle(&mut self, cmd: ServerCommand) -> Vec<ServerMessage> {
+        match cmd {
+            ServerCommand::Load { source } => match self.emulator.load_source(&source) {
+                Ok(words) => vec![ServerMessage::Loaded { words }],
+                Err(e) => vec![error(e)],
+            },
+            ServerCommand::LoadWords { words } => {
+                if let Some(bad) = words.iter().find(|w| w.unsigned_abs() > Tryte9::MAX as u32) {
+                    return vec![error(format!("word {} out of range", bad))];
+                }
+                let program: Vec<Tryte9> = words.into_iter().map(Tryte9::from_i32).collect();
+                let words = program.len();
+                match self.emulator.load(program) {
+                    Ok(()) => vec![ServerMessage::Loaded { words }],
+                    Err(e) => vec![error(e)],
+                }
+            }
+            ServerCommand::Step { count } => self.execute(count, false),
+            ServerCommand::Run { max_cycles } => self.execute(max_cycles, true),
+            ServerCommand::Reset => match self.emulator.reset() {
+                Ok(()) => vec![ServerMessage::Ok],
+                Err(e) => vec![error(e)],
+            },
+            ServerCommand::Registers => vec![ServerMessage::Registers {
+                regs: RegisterState::capture(&self.cpu().regs),
+                state: self.cpu().state,
+                cycles: self.cpu().cycles,
+            }],
+            ServerCommand::ReadMemory { addr, count } => {
+                let values = (0..count as i32)
+                    .map(|offset| self.memory_index(addr + offset).map(|i| self.cpu().mem.read(i).to_i32()))
+                    .collect::<Result<Vec<_>, _>>();
+                match values {
+                    Ok(values) => vec![ServerMessage::Memory { addr, values }],
+                    Err(message) => vec![error(message)],
+                }
+            }
+            ServerCommand::WriteMemory { addr, values } => {
+                let mut writes = Vec::with_capacity(values.len());
+                for (offset, &value) in values.iter().enumerate() {
+                    if value.unsigned_abs() > Tryte9::MAX as u32 {
+                        return vec![error(format!("word {} out of range", value))];
+                    }
+                    match self.memory_index(addr + offset as i32) {
+                        Ok(index) => writes.push((index, Tryte9::from_i32(value))),
+                        Err(message) => return vec![error(message)],
+                    }
+                }
+                for (index, value) in writes {
+                    self.emulator.cpu.mem.write(index, value);
+                }
+                vec![ServerMessage::Ok]
+            }
+            ServerCommand::SetBreakpoint { addr } => {
+                self.emulator.set_breakpoint(addr);
+                vec![self.breakpoint_list()]
+            }
+            ServerCommand::ClearBreakpoint { addr } => {
+                self.emulator.clear_breakpoint(addr);
+                vec![self.breakpoint_list()]
+            }
+            ServerCommand::Breakpoints => vec![self.breakpoint_list()],
+            ServerCommand::Input { values } => {
+                if let Some(bad) = values.iter().find(|w| w.unsigned_abs() > Tryte9::MAX as u32) {
+                    return vec![error(format!("word {} out of range", bad))];
+                }
+                self.emulator.cpu.io.feed(values.into_iter().map(Tryte9::from_i32));
+                vec![ServerMessage::Ok]
+            }
+            ServerCommand::Subscribe { events } => {
+                self.events = events.into_iter().collect();
+                vec![ServerMessage::Ok]
+            }
+        }
+    }
+
+    /// Handle one JSON-encoded command, returning JSON-encoded messages.
+    pub fn handle_json(&mut self, message: &str) -> Vec<String> {
+        let (id, messages) = match serde_json::from_str::<Request>(message) {
+            Ok(request) => (request.id, self.handle(request.command)),
+            Err(e) => (None, vec![error(format!("invalid command: {}", e))]),
+        };
+        let last = messages.len() - 1;
+        messages.iter().enumerate()
+            .map(|(i, message)| {
+                let envelope = Envelope { id: id.as_ref().filter(|_| i == last), message };
+                serde_json::to_string(&envelope).expect("server message serialization cannot fail")
+            })
+            .collect()
+    }
+
+    /// Run up to `limit` instructions, emitting subscribed events.
+    fn execute(&mut self, limit: u64, stop_at_breakpoints: bool) -> Vec<ServerMessage> {
+        let mut messages = Vec::new();
+        let events = &self.events;
+        let summary = self.emulator.execute(limit, stop_at_breakpoints, |cpu, record| {
+            if events.contains(&EventKind::Trace) {
+                messages.push(ServerMessage::Trace { record });
+            }
+            let output = cpu.io.take_output();
+            if !output.is_empty() && events.contains(&EventKind::Output) {
+                messages.push(ServerMessage::Output {
+                    values: output.iter().map(|w| w.to_i32()).collect(),
+                });
+            }
+        });
+
+        let cpu = self.cpu();
+        messages.push(ServerMessage::Stopped {
+            executed: summary.executed,
+            reason: summary.stop_reason.into(),
+            error: summary.error.map(|e| e.to_string()),
+            state: cpu.state,
+            regs: RegisterState::capture(&cpu.regs),
+            cycles: cpu.cycles,
+        });
+        messages
+    }
+
+    fn memory_index(&self, addr: i32) -> Result<usize, String> {
+        let mem = &self.cpu().mem;
+        if addr < mem.min_addr() || addr > mem.max_addr() {
+            return Err(format!("address {} out of range", addr));
+        }
+        mem.addr_to_index(Tryte9::from_i32(addr)).map_err(|e| e.to_string())
+    }
+
+    fn breakpoint_list(&self) -> ServerMessage {
+        ServerMessage::Breakpoints { addrs: self.emulator.breakpoints().iter().copied().collect() }
+    }
+}
+
+fn error(e: impl std::fmt::Display) -> ServerMessage {
+    ServerMessage::Error { message: e.to_string() }
+}
+
+/// Accept connections forever, serving each on its own thread.
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        std::thread::spawn(move || {
+            if let Err(e) = handle_connection(stream) {
+                eprintln!("connection error: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Serve one connection as WebSocket or plain HTTP, depending on its headers.
+fn handle_connection(stream: TcpStream) -> io::Result<()> {
+    let mut head = [0u8; 4096];
+    let mut len = 0;
+    // Peek until the header block is complete so the request is not consumed.
+    while len < head.len() {
+        let n = stream.peek(&mut head)?;
+        if n == 0 || n == len || head[..n].windows(4).any(|w| w == b"\r\n\r\n") {
+            len = n;
+            break;
+        }
+        len = n;
+    }
+    let headers = String::from_utf8_lossy(&head[..len]).to_ascii_lowercase();
+
+    if headers.contains("upgrade: websocket") {
+        serve_websocket(stream)
+    } else {
+        serve_http(stream)
+    }
+}
+
+fn serve_websocket(stream: TcpStream) -> io::Result<()> {
+    let mut socket = tungstenite::accept(stream).map_err(io::Error::other)?;
+    let mut session = Session::new();
+
+    loop {
+        let text = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => continue,
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => return Ok(()),
+            Err(e) => return Err(io::Error::other(e)),
+        };
+        for reply in session.handle_json(&text) {
+            socket.send(Message::Text(reply)).map_err(io::Error::other)?;
+        }
+    }
+}
+
+const HTTP_USAGE: &str = "Setun emulator server\n\n\
+    Connect with a WebSocket to drive a persistent session, or POST a JSON\n\
+    command (or array of commands) to / for a one-shot session.\n";
+
+/// Largest POST body accepted, checked against Content-Length before the
+/// body is read.
+const MAX_BODY: usize = 1 << 20;
+
+fn serve_http(stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    let method = request_line.split_whitespace().next().unwrap_or("");
+    let (status, content_type, body) = match method {
+        "GET" => ("200 OK", "text/plain", HTTP_USAGE.to_string()),
+        "POST" if content_length > MAX_BODY => {
+            ("413 Payload Too Large", "text/plain", format!("request body over {} bytes\n", MAX_BODY))
+        }
+        "POST" => {
+            let mut body = vec![0u8; content_length];
+            reader.read_exact(&mut body)?;
+            ("200 OK", "application/json", run_one_shot(&String::from_utf8_lossy(&body)))
+        }
+        _ => ("405 Method Not Allowed", "text/plain", HTTP_USAGE.to_string()),
+    };
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body
+    )?;
+    stream.flush()
+}
+
+/// Run a command or array of commands against a fresh session.
+fn run_one_shot(body: &str) -> String {
+    let commands = match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Array(commands)) => commands,
+        Ok(command) => vec![command],
+        Err(e) => vec![serde_json::Value::String(format!("invalid JSON: {}", e))],
+    };
+
+    let mut session = Session::new();
+    let replies: Vec<String> = commands.iter()
+        .flat_map(|command| session.handle_json(&command.to_string()))
+        .collect();
+    format!("[{}]", replies.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "LDA 120\nADD 5\nSTA 121\nSTA 6\nHLT\nDAT 10\nDAT 0";
+
+    fn reply(session: &mut Session, cmd: &str) -> serde_json::Value {
+        let messages = session.handle_json(cmd);
+        serde_json::from_str(messages.last().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_session_protocol() {
+        let mut session = Session::new();
+        let cmd = serde_json::json!({ "id": 7, "cmd": "load", "source": PROGRAM }).to_string();
+        let loaded = reply(&mut session, &cmd);
+        assert_eq!(loaded["type"], "loaded");
+        assert_eq!(loaded["id"], 7);
+        assert_eq!(loaded["words"], 7);
+
+        assert_eq!(reply(&mut session, r#"{"cmd":"input","values":[32]}"#)["type"], "ok");
+        assert_eq!(reply(&mut session, r#"{"cmd":"subscribe","events":["output"]}"#)["type"], "ok");
+        assert_eq!(reply(&mut session, r#"{"cmd":"set_breakpoint","addr":3}"#)["addrs"], serde_json::json!([3]));
+
+        let messages = session.handle_json(r#"{"cmd":"run"}"#);
+        let output: serde_json::Value = serde_json::from_str(&messages[0]).unwrap();
+        assert_eq!(output["type"], "output");
+        assert_eq!(output["values"], serde_json::json!([42]));
+        let stopped: serde_json::Value = serde_json::from_str(&messages[1]).unwrap();
+        assert_eq!(stopped["reason"], "breakpoint");
+        assert_eq!(stopped["executed"], 3);
+
+        let stopped = reply(&mut session, r#"{"cmd":"run"}"#);
+        assert_eq!(stopped["reason"], "halted");
+        assert_eq!(reply(&mut session, r#"{"cmd":"read_memory","addr":6}"#)["values"], serde_json::json!([42]));
+    }
+
+    #[test]
+    fn test_session_errors() {
+        let mut session = Session::new();
+        assert_eq!(reply(&mut session, r#"{"cmd":"fly"}"#)["type"], "error");
+        assert_eq!(reply(&mut session, r#"{"cmd":"load","source":"BOGUS 1"}"#)["type"], "error");
+        assert_eq!(reply(&mut session, r#"{"cmd":"read_memory","addr":500}"#)["type"], "error");
+        assert_eq!(reply(&mut session, r#"{"cmd":"write_memory","addr":0,"values":[99999]}"#)["type"], "error");
+        for cmd in [
+            r#"{"cmd":"load_words","words":[-2147483648]}"#,
+            r#"{"cmd":"write_memory","addr":0,"values":[-2147483648]}"#,
+            r#"{"cmd":"input","values":[-2147483648]}"#,
+        ] {
+            let message = reply(&mut session, cmd);
+            assert_eq!(message["message"], "word -2147483648 out of range", "{}", cmd);
+        }
+    }
+
+    #[test]
+    fn test_websocket_and_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || serve(listener));
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let (mut socket, _) = tungstenite::client(format!("ws://{}/", addr), stream).unwrap();
+        socket.send(Message::Text(r#"{"id":"a","cmd":"registers"}"#.into())).unwrap();
+        let text = socket.read().unwrap().into_text().unwrap();
+        let registers: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(registers["id"], "a");
+        assert_eq!(registers["type"], "registers");
+
+        let body = r#"[{"cmd":"load_words","words":[0]},{"cmd":"step"}]"#;
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        let json: serde_json::Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(json[0]["type"], "loaded");
+        assert_eq!(json[1]["reason"], "halted");
+
+        // An oversized body is refused without reading it
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", usize::MAX).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large"));
+    }
+}
@@ -0,0 +1,363 @@
+//! Shared driver layer for emulator frontends.
+//!
+//! Every frontend — the CLI, the TUI debugger, the WebSocket server, the
+//! GDB stub, scripts and the WASM bindings — needs the same plumbing around
+//! a [`Cpu`]: remember which program was loaded where so it can be reset,
+//! keep a set of breakpoints, run until something stops execution and say
+//! why, and capture or restore the whole thing as a [`Snapshot`].
+//! [`EmulatorSession`] is that plumbing, so a new frontend only has to
+//! translate its own protocol.
+//!
+//! ```
+//! use setun::session::EmulatorSession;
+//! use setun::cpu::StopReason;
+//!
+//! let mut session = EmulatorSession::new();
+//! session.load_source("LDA 4\nADD 4\nSTA 5\nHLT\nDAT 21").unwrap();
+//! session.set_breakpoint(2);
+//!
+//! let summary = session.run(100);
+//! assert_eq!(summary.stop_reason, StopReason::Breakpoint);
+//! assert_eq!(session.cpu.regs.s.to_i64(), 42);
+//!
+//! assert_eq!(session.run(100).stop_reason, StopReason::Halted);
+//! session.reset().unwrap();
+//! assert_eq!(session.cpu.cycles, 0);
+//! ```
+
+use crate::asm::{assemble, AssemblerError};
+use crate::cpu::{Cpu, CpuConfig, CpuError, Instruction, IoPorts, Snapshot, StopReason, TraceRecord};
+use crate::cpu::memory::MemoryError;
+use crate::ternary::Tryte9;
+use std::collections::BTreeSet;
+use thiserror::Error;
+
+/// Errors from loading or resetting a session.
+#[derive(Debug, Clone, Error)]
+pub enum SessionError {
+    #[error("assembly failed: {0}")]
+    Assembly(#[from] AssemblerError),
+
+    #[error("failed to load program at {addr}: {source}")]
+    Load { addr: i32, source: MemoryError },
+
+    #[error("invalid entry point {addr}: {source}")]
+    Entry { addr: i32, source: MemoryError },
+}
+
+/// Result of [`EmulatorSession::run`] and friends.
+#[derive(Debug, Clone)]
+pub struct RunSummary {
+    /// Number of instructions executed.
+    pub executed: u64,
+    /// Why execution stopped.
+    pub stop_reason: StopReason,
+    /// The error, when `stop_reason` is `Error`.
+    pub error: Option<CpuError>,
+}
+
+/// A CPU together with its program, load layout and breakpoints.
+#[derive(Debug, Clone, Default)]
+pub struct EmulatorSession {
+    /// The emulated machine.
+    pub cpu: Cpu,
+    program: Vec<Tryte9>,
+    load_at: i32,
+    entry: i32,
+    breakpoints: BTreeSet<i32>,
+}
+
+impl EmulatorSession {
+    /// An empty machine with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty machine with `config`.
+    pub fn with_config(config: CpuConfig) -> Self {
+        Self { cpu: Cpu::with_config(config), ..Self::default() }
+    }
+
+    /// Wrap a CPU that already holds `program`, loaded at `load_at` with
+    /// its entry point at `entry`. The CPU is used as is.
+    pub fn from_cpu(cpu: Cpu, program: Vec<Tryte9>, load_at: i32, entry: i32) -> Self {
+        Self { cpu, program, load_at, entry, breakpoints: BTreeSet::new() }
+    }
+
+    /// Resume a saved session. Every cell counts as written, since all of
+    /// memory may differ from what a delta consumer saw last.
+    pub fn from_snapshot(snapshot: Snapshot) -> Self {
+        let mut cpu = snapshot.cpu;
+        cpu.mem.mark_all_written();
+        Self::from_cpu(cpu, snapshot.program, snapshot.load_at, snapshot.entry)
+    }
+
+    /// Capture the machine, program and layout.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::capture(&self.cpu, &self.program).with_layout(self.load_at, self.entry)
+    }
+
+    /// The loaded program, as reloaded by [`reset`](Self::reset).
+    pub fn program(&self) -> &[Tryte9] {
+        &self.program
+    }
+
+    /// Address the program is loaded at.
+    pub fn load_address(&self) -> i32 {
+        self.load_at
+    }
+
+    /// Address execution starts at after a reset.
+    pub fn entry(&self) -> i32 {
+        self.entry
+    }
+
+    /// Load `program` at address 0 and start there.
+    pub fn load(&mut self, program: Vec<Tryte9>) -> Result<(), SessionError> {
+        self.load_with_layout(program, 0, 0)
+    }
+
+    /// Load `program` at `load_at` with the PC at `entry`, on a freshly
+    /// reset machine with empty I/O buffers. The configuration, whether
+    /// the I/O ports are mapped and the attached devices carry over. Nothing changes if the layout
+    /// does not fit.
+    pub fn load_with_layout(&mut self, program: Vec<Tryte9>, load_at: i32, entry: i32) -> Result<(), SessionError> {
+        let mut io = IoPorts::default();
+        if self.cpu.io.enabled {
+            io.enable();
+        }
+        let cpu = self.fresh_cpu(io, &program, load_at, entry)?;
+        self.cpu = cpu;
+        self.program = program;
+        self.load_at = load_at;
+        self.entry = entry;
+        Ok(())
+    }
+
+    /// Assemble `source` and load it at address 0. Returns the number of words.
+    pub fn load_source(&mut self, source: &str) -> Result<usize, SessionError> {
+        let program = assemble(source)?;
+        let len = program.len();
+        self.load(program)?;
+        Ok(len)
+    }
+
+    /// Reset the machine and reload the program at its layout.
+    ///
+    /// The configuration, breakpoints, I/O ports (including unread
+    /// tape input) and attached devices are kept; devices are reset.
+    pub fn reset(&mut self) -> Result<(), SessionError> {
+        self.cpu = self.fresh_cpu(self.cpu.io.clone(), &self.program, self.load_at, self.entry)?;
+        Ok(())
+    }
+
+    fn fresh_cpu(&self, io: IoPorts, program: &[Tryte9], load_at: i32, entry: i32) -> Result<Cpu, SessionError> {
+        let mut cpu = Cpu::with_config(self.cpu.config.clone());
+        cpu.io = io;
+        cpu.devices = self.cpu.devices.clone();
+        cpu.devices.reset();
+        cpu.load_program_at(load_at, program)
+            .map_err(|source| SessionError::Load { addr: load_at, source })?;
+        cpu.set_entry(entry)
+            .map_err(|source| SessionError::Entry { addr: entry, source })?;
+        Ok(cpu)
+    }
+
+    /// Breakpoint addresses, in ascending order.
+    pub fn breakpoints(&self) -> &BTreeSet<i32> {
+        &self.breakpoints
+    }
+
+    /// Stop [`run`](Self::run) before the instruction at `addr`. Returns
+    /// `false` if it was already set.
+    pub fn set_breakpoint(&mut self, addr: i32) -> bool {
+        self.breakpoints.insert(addr)
+    }
+
+    /// Remove a breakpoint. Returns `false` if none was set at `addr`.
+    pub fn clear_breakpoint(&mut self, addr: i32) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    /// Set or remove the breakpoint at `addr`; returns whether it is now set.
+    pub fn toggle_breakpoint(&mut self, addr: i32) -> bool {
+        if !self.breakpoints.remove(&addr) {
+            self.breakpoints.insert(addr);
+            return true;
+        }
+        false
+    }
+
+    /// Remove every breakpoint.
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Is the next instruction at a breakpoint?
+    pub fn at_breakpoint(&self) -> bool {
+        self.breakpoints.contains(&self.cpu.regs.c.to_i32())
+    }
+
+    /// Execute one instruction, ignoring breakpoints.
+    pub fn step(&mut self) -> Result<Instruction, CpuError> {
+        self.cpu.step()
+    }
+
+    /// Run until the CPU stops, the next instruction is at a breakpoint, or
+    /// `max_cycles` instructions have executed. The instruction at the
+    /// starting PC always executes, so a run can resume from a breakpoint.
+    pub fn run(&mut self, max_cycles: u64) -> RunSummary {
+        self.drive(max_cycles, true, |cpu| cpu.step().map(drop))
+    }
+
+    /// Like [`run`](Self::run), passing the trace record of every executed
+    /// instruction to `on_step` along with the CPU (e.g. to collect teletype
+    /// output as it is printed). With `stop_at_breakpoints` false only a
+    /// stopped CPU, an error or the limit end execution.
+    pub fn execute<F>(&mut self, limit: u64, stop_at_breakpoints: bool, mut on_step: F) -> RunSummary
+    where
+        F: FnMut(&mut Cpu, TraceRecord),
+    {
+        self.drive(limit, stop_at_breakpoints, |cpu| {
+            let record = cpu.step_traced()?;
+            on_step(cpu, record);
+            Ok(())
+        })
+    }
+
+    fn drive<F>(&mut self, limit: u64, stop_at_breakpoints: bool, mut step: F) -> RunSummary
+    where
+        F: FnMut(&mut Cpu) -> Result<(), CpuError>,
+    {
+        let mut executed = 0;
+        let mut stop_reason = StopReason::CycleLimit;
+        let mut error = None;
+
+        while executed < limit {
+            if !self.cpu.is_running() {
+                stop_reason = StopReason::Halted;
+                break;
+            }
+            if let Err(e) = step(&mut self.cpu) {
+                stop_reason = StopReason::Error;
+                error = Some(e);
+                break;
+            }
+            executed += 1;
+            if stop_at_breakpoints && self.cpu.is_running() && self.at_breakpoint() {
+                stop_reason = StopReason::Breakpoint;
+                break;
+            }
+        }
+        if stop_reason == StopReason::CycleLimit && self.cpu.is_halted() {
+            stop_reason = StopReason::Halted;
+        }
+
+        RunSummary { executed, stop_reason, error }
+    }
+}
+
+impl From<Cpu> for EmulatorSession {
+    /// Wrap a CPU with no recorded program; [`reset`](EmulatorSession::reset)
+    /// then gives an empty machine.
+    fn from(cpu: Cpu) -> Self {
+        Self::from_cpu(cpu, Vec::new(), 0, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOOP: &str = "
+        LDA 20
+    loop:
+        JZ done
+        SUB 21
+        JMP loop
+    done:
+        HLT
+    ";
+
+    fn loop_session() -> EmulatorSession {
+        let mut session = EmulatorSession::new();
+        session.load_with_layout(assemble(LOOP).unwrap(), 0, 0).unwrap();
+        session.cpu.mem.write_ternary(Tryte9::from_i32(20), Tryte9::from_i32(3)).unwrap();
+        session.cpu.mem.write_ternary(Tryte9::from_i32(21), Tryte9::from_i32(1)).unwrap();
+        session
+    }
+
+    #[test]
+    fn test_breakpoints_stop_run() {
+        let mut session = loop_session();
+        assert!(session.toggle_breakpoint(2));
+
+        // Each pass of the loop stops before SUB, including when resuming there
+        for _ in 0..3 {
+            let summary = session.run(1000);
+            assert_eq!(summary.stop_reason, StopReason::Breakpoint);
+            assert!(session.at_breakpoint());
+        }
+        let summary = session.run(1000);
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(summary.executed, 4);
+
+        // Breakpoints take precedence over a limit reached at the same time
+        session = loop_session();
+        session.set_breakpoint(2);
+        assert_eq!(session.run(2).stop_reason, StopReason::Breakpoint);
+        assert_eq!(session.run(1).stop_reason, StopReason::CycleLimit);
+        assert!(!session.toggle_breakpoint(2));
+        assert_eq!(session.run(1000).stop_reason, StopReason::Halted);
+    }
+
+    #[test]
+    fn test_execute_traces_and_ignores_breakpoints() {
+        let mut session = loop_session();
+        session.set_breakpoint(2);
+        let mut pcs = Vec::new();
+        let summary = session.execute(1000, false, |_, record| pcs.push(record.pc));
+        assert_eq!(summary.stop_reason, StopReason::Halted);
+        assert_eq!(pcs.len() as u64, summary.executed);
+        assert_eq!(pcs.iter().filter(|&&pc| pc == 2).count(), 3);
+    }
+
+    #[test]
+    fn test_layout_reset_and_snapshot() {
+        let mut session = EmulatorSession::new();
+        session.cpu.io.enable();
+        session.cpu.io.feed([Tryte9::from_i32(8)]);
+        session.load_with_layout(assemble("NOP\nHLT").unwrap(), -10, -9).unwrap();
+        assert!(session.cpu.io.enabled);
+        assert_eq!(session.cpu.io.pending_input(), 0);
+        session.cpu.io.feed([Tryte9::from_i32(9)]);
+        assert_eq!(session.cpu.regs.c.to_i32(), -9);
+        assert_eq!(session.run(10).executed, 1);
+
+        session.reset().unwrap();
+        assert_eq!(session.cpu.regs.c.to_i32(), -9);
+        assert_eq!(session.cpu.io.pending_input(), 1);
+
+        session.cpu.mem.take_changes();
+        let mut restored = EmulatorSession::from_snapshot(session.snapshot());
+        assert_eq!((restored.load_address(), restored.entry()), (-10, -9));
+        assert_eq!(restored.program(), session.program());
+        assert_eq!(restored.cpu.mem.take_changes().len(), restored.cpu.mem.size());
+
+        // A bad layout leaves the session as it was
+        assert!(matches!(session.load_with_layout(vec![Tryte9::zero(); 3], 79, 79), Err(SessionError::Load { addr: 79, .. })));
+        assert!(matches!(session.load_with_layout(vec![], 0, 500), Err(SessionError::Entry { addr: 500, .. })));
+        assert!(matches!(session.load_source("BOGUS"), Err(SessionError::Assembly(_))));
+        assert_eq!(session.load_address(), -10);
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        let mut session = EmulatorSession::new();
+        session.load_source("DIV 2\nHLT\nDAT 0").unwrap();
+        let summary = session.run(10);
+        assert_eq!(summary.stop_reason, StopReason::Error);
+        assert!(matches!(summary.error, Some(CpuError::DivisionByZero)));
+        assert_eq!(session.run(10).stop_reason, StopReason::Halted);
+    }
+}
@@ -0,0 +1,221 @@
+//! Monte Carlo runs over randomized inputs.
+//!
+//! Where [`equiv`](crate::equiv) enumerates every input combination,
+//! [`simulate`] samples: each trial sets the input cells to random values
+//! from their ranges, runs the program on a fresh machine and records how
+//! it ended. The values come from a seeded generator, so a report can be
+//! reproduced exactly, and a failing trial can be replayed from the inputs
+//! it lists:
+//!
+//! ```
+//! use setun::asm::assemble;
+//! use setun::simulate::{simulate, SimOptions};
+//!
+//! // Absolute value of the cell at 20
+//! let program = assemble("LDA 20\nJGT DONE\nLDA ZERO\nSUB 20\nDONE: HLT\nZERO: DAT 0").unwrap();
+//! let options = SimOptions { inputs: vec!["20=-10..10".parse().unwrap()], trials: 200, ..Default::default() };
+//!
+//! let report = simulate(&program, 0, &options).unwrap();
+//! assert_eq!(report.halted, 200);
+//! assert!(report.s.unwrap().min >= 0);
+//! ```
+
+use crate::cpu::random::{Xorshift, DEFAULT_SEED};
+use crate::cpu::{Cpu, CpuState};
+use crate::equiv::InputCell;
+use crate::ternary::Tryte9;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Cycle limit for each trial when none is given.
+pub const DEFAULT_MAX_CYCLES: u64 = 10_000;
+
+/// Number of trials when none is given.
+pub const DEFAULT_TRIALS: u64 = 1000;
+
+/// What to randomize and how often.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimOptions {
+    /// Cells set to a random value from their range before each trial.
+    pub inputs: Vec<InputCell>,
+    /// Number of trials.
+    pub trials: u64,
+    /// Seed for the input values.
+    pub seed: u64,
+    /// Cycle limit for each trial.
+    pub max_cycles: u64,
+}
+
+impl Default for SimOptions {
+    fn default() -> Self {
+        Self {
+            inputs: Vec::new(),
+            trials: DEFAULT_TRIALS,
+            seed: DEFAULT_SEED,
+            max_cycles: DEFAULT_MAX_CYCLES,
+        }
+    }
+}
+
+/// Smallest, largest and mean of a sample.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    /// Smallest value.
+    pub min: i64,
+    /// Largest value.
+    pub max: i64,
+    /// Arithmetic mean.
+    pub mean: f64,
+}
+
+impl Summary {
+    /// Summarize `values`, or `None` if there are none.
+    fn of(values: impl IntoIterator<Item = i64>) -> Option<Self> {
+        let mut iter = values.into_iter();
+        let first = iter.next()?;
+        let (mut min, mut max, mut sum, mut count) = (first, first, first as f64, 1u64);
+        for value in iter {
+            min = min.min(value);
+            max = max.max(value);
+            sum += value as f64;
+            count += 1;
+        }
+        Some(Self { min, max, mean: sum / count as f64 })
+    }
+}
+
+/// A trial that did not halt normally.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Failure {
+    /// Trial number, from 0.
+    pub trial: u64,
+    /// Address and value of each input cell.
+    pub inputs: Vec<(i32, i32)>,
+    /// The error, or that the cycle limit was reached.
+    pub reason: String,
+}
+
+/// Result of [`simulate`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimReport {
+    /// Trials run.
+    pub trials: u64,
+    /// Seed the inputs were drawn from.
+    pub seed: u64,
+    /// Trials that halted.
+    pub halted: u64,
+    /// Final S of the halted trials, if any halted.
+    pub s: Option<Summary>,
+    /// How many halted trials ended with each value of S.
+    pub s_counts: BTreeMap<i64, u64>,
+    /// Cycles taken by every trial.
+    pub cycles: Option<Summary>,
+    /// Number of failed trials for each reason.
+    pub failures: BTreeMap<String, u64>,
+    /// The first failed trial, to replay.
+    pub first_failure: Option<Failure>,
+}
+
+impl SimReport {
+    /// Number of trials that did not halt normally.
+    pub fn failed(&self) -> u64 {
+        self.trials - self.halted
+    }
+}
+
+/// Errors that prevent a simulation from running.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SimError {
+    #[error("cell {0} is outside memory")]
+    InvalidCell(i32),
+
+    #[error("program does not fit in memory at {0}")]
+    ProgramTooLarge(i32),
+}
+
+/// Run `program`, loaded at `origin`, once per trial with random inputs.
+pub fn simulate(program: &[Tryte9], origin: i32, options: &SimOptions) -> Result<SimReport, SimError> {
+    let template = {
+        let mut cpu = Cpu::new();
+        cpu.load_program_at(origin, program).map_err(|_| SimError::ProgramTooLarge(origin))?;
+        cpu.set_entry(origin).map_err(|_| SimError::ProgramTooLarge(origin))?;
+        cpu
+    };
+    for cell in &options.inputs {
+        if cell.addr < template.mem.min_addr() || cell.addr > template.mem.max_addr() {
+            return Err(SimError::InvalidCell(cell.addr));
+        }
+    }
+
+    let mut rng = Xorshift::new(options.seed);
+    let mut final_s = Vec::new();
+    let mut cycles = Vec::new();
+    let mut failures = BTreeMap::new();
+    let mut first_failure = None;
+    for trial in 0..options.trials {
+        let mut cpu = template.clone();
+        let inputs: Vec<(i32, i32)> = options.inputs
+            .iter()
+            .map(|cell| (cell.addr, rng.between(cell.min as i64, cell.max as i64) as i32))
+            .collect();
+        for &(addr, value) in &inputs {
+            cpu.mem.write_ternary(Tryte9::from_i32(addr), Tryte9::from_i32(value)).expect("input cell checked");
+        }
+
+        let reason = match cpu.run_limited(options.max_cycles) {
+            Err(e) => Some(e.to_string()),
+            Ok(_) if cpu.state == CpuState::Running => Some(format!("no halt within {} cycles", options.max_cycles)),
+            Ok(_) => None,
+        };
+        cycles.push(cpu.cycles as i64);
+        match reason {
+            None => final_s.push(cpu.regs.s.to_i64()),
+            Some(reason) => {
+                *failures.entry(reason.clone()).or_insert(0) += 1;
+                first_failure.get_or_insert(Failure { trial, inputs, reason });
+            }
+        }
+    }
+
+    let mut s_counts = BTreeMap::new();
+    for &s in &final_s {
+        *s_counts.entry(s).or_insert(0) += 1;
+    }
+    Ok(SimReport {
+        trials: options.trials,
+        seed: options.seed,
+        halted: final_s.len() as u64,
+        s: Summary::of(final_s),
+        s_counts,
+        cycles: Summary::of(cycles),
+        failures,
+        first_failure,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    #[test]
+    fn test_statistics_and_failures() {
+        // Loops forever when the input is negative
+        let program = assemble("LOOP: LDA 20\nJLT LOOP\nHLT").unwrap();
+        let options = SimOptions { inputs: vec!["20=-1..1".parse().unwrap()], trials: 300, max_cycles: 50, ..Default::default() };
+        let report = simulate(&program, 0, &options).unwrap();
+
+        assert_eq!(report.halted + report.failed(), 300);
+        assert!(report.failed() > 50 && report.halted > 150);
+        assert_eq!(report.s_counts.keys().copied().collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(report.failures.get("no halt within 50 cycles"), Some(&report.failed()));
+        assert_eq!(report.first_failure.as_ref().unwrap().inputs, vec![(20, -1)]);
+        assert_eq!(report.cycles.unwrap().max, 50);
+
+        // Same seed, same report
+        assert_eq!(simulate(&program, 0, &options).unwrap(), report);
+        let options = SimOptions { inputs: vec!["200".parse().unwrap()], ..Default::default() };
+        assert_eq!(simulate(&program, 0, &options), Err(SimError::InvalidCell(200)));
+    }
+}
@@ -1,9 +1,22 @@
 //! Multi-trit arithmetic operations.
 //!
-//! Provides addition, subtraction, multiplication, and negation
-//! for balanced ternary words using ripple-carry algorithms.
+//! Provides addition, subtraction, multiplication, division, shifts and
+//! negation for balanced ternary words using ripple-carry algorithms. Each
+//! operation exists for 18-trit [`Word18`]s and, with a `_tryte9` suffix,
+//! for 9-trit [`Tryte9`] memory cells, so cell values need not be widened
+//! and narrowed back. [`mul_const`] multiplies by a known constant with
+//! a shift/add/subtract chain ([`MulChain`]) instead of a full schoolbook
+//! pass. Division works
+//! trit by trit on the words themselves, for 18-trit and, with
+//! [`divide_wide`], 36-trit dividends, and [`isqrt`] finds square roots
+//! the same way.
+//! [`explain`] shows the same algorithms one trit at a time.
 
-use crate::ternary::{Trit, Tryte9, Word18};
+pub mod explain;
+
+pub use explain::{explain, explain_add, explain_mul, explain_sub, Explanation};
+
+use crate::ternary::{Trit, TryteN, Tryte9, Word18, Word36};
 
 /// Negate a 9-trit word.
 #[inline]
@@ -18,49 +31,34 @@ pub fn negate(a: &Word18) -> Word18 {
 }
 
 /// Add two 9-trit words, returning (result, carry_out).
+#[inline]
 pub fn add_tryte9(a: &Tryte9, b: &Tryte9) -> (Tryte9, Trit) {
-    let mut result = Tryte9::zero();
-    let mut carry = Trit::O;
-    
-    for i in 0..9 {
-        let (sum, new_carry) = a.get(i).full_add(b.get(i), carry);
-        result.set(i, sum);
-        carry = new_carry;
-    }
-    
-    (result, carry)
+    a.add_with_carry(b)
 }
 
 /// Add two 18-trit words, returning (result, carry_out).
+#[inline]
 pub fn add(a: &Word18, b: &Word18) -> (Word18, Trit) {
-    let mut result = Word18::zero();
-    let mut carry = Trit::O;
-    
-    for i in 0..18 {
-        let (sum, new_carry) = a.get(i).full_add(b.get(i), carry);
-        result.set(i, sum);
-        carry = new_carry;
-    }
-    
-    (result, carry)
+    a.add_with_carry(b)
 }
 
 /// Subtract two 9-trit words (a - b), returning (result, borrow_out).
 #[inline]
 pub fn subtract_tryte9(a: &Tryte9, b: &Tryte9) -> (Tryte9, Trit) {
-    add_tryte9(a, &b.neg())
+    a.sub_with_borrow(b)
 }
 
 /// Subtract two 18-trit words (a - b), returning (result, borrow_out).
 #[inline]
 pub fn subtract(a: &Word18, b: &Word18) -> (Word18, Trit) {
-    add(a, &b.neg())
+    a.sub_with_borrow(b)
 }
 
 /// Multiply two 18-trit words, returning a 36-trit result as (low, high).
 ///
 /// Uses the schoolbook multiplication algorithm adapted for balanced ternary.
 /// Note: Single-trit multiplication never carries, which simplifies partial products.
+/// There is one pass per non-zero trit of `a`, so pass the sparser word first.
 pub fn multiply(a: &Word18, b: &Word18) -> (Word18, Word18) {
     // We need 36 trits to hold the full product
     let mut product = [Trit::O; 36];
@@ -70,22 +68,220 @@ pub fn multiply(a: &Word18, b: &Word18) -> (Word18, Word18) {
         if a.get(i).is_zero() {
             continue; // Multiplying by zero contributes nothing
         }
+        add_shifted(&mut product, b, i, a.get(i));
+    }
+    
+    split_product(&product)
+}
+
+/// Multiply an 18-trit word by a constant, returning the same (low, high)
+/// product as [`multiply`], by way of the [`MulChain`] for `k`.
+///
+/// Planning the chain costs more than it saves for a single product, so
+/// plan once with [`MulChain::new`] when multiplying many words by `k`.
+///
+/// # Panics
+/// Panics if `k` does not fit in 18 trits.
+pub fn mul_const(a: &Word18, k: i64) -> (Word18, Word18) {
+    assert!(k.unsigned_abs() <= Word18::MAX as u64, "constant {} does not fit in 18 trits", k);
+    match MulChain::new(k) {
+        Some(chain) => chain.apply(a),
+        None => (Word18::zero(), Word18::zero()),
+    }
+}
+
+/// What a [`MulStep`] adds to the shifted running product.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainTerm {
+    /// Nothing: the step is a plain shift.
+    None,
+    /// The multiplicand.
+    Multiplicand,
+    /// The running product as it was before the shift.
+    Running,
+}
+
+/// One step of a [`MulChain`]: the running product `x` becomes
+/// `x * 3^shift + sign * term`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MulStep {
+    pub shift: u32,
+    pub sign: Trit,
+    pub term: ChainTerm,
+}
+
+/// A shift/add/subtract chain multiplying by a constant.
+///
+/// The running product starts as the multiplicand, goes through the
+/// steps, and is negated at the end for a negative constant. Negation is
+/// free in balanced ternary, so subtracting costs the same as adding:
+/// ×2 is `3a - a`, ×10 is `9a + a` and ×16 is `(3x + x)` twice over
+/// `x = 3a + a`, where the trits of 16 (`PNNP`) would take three steps.
+///
+/// [`MulChain::new`] finds the fewest additions and subtractions among
+/// chains of this form, in which every step combines the running product
+/// with the multiplicand or with itself, as Bernstein's method does in
+/// binary. Chains free to combine any two earlier values can occasionally
+/// be shorter still.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MulChain {
+    pub steps: Vec<MulStep>,
+    pub negate: bool,
+}
+
+impl MulChain {
+    /// The cheapest chain for `k`, or `None` for 0, whose product needs no
+    /// chain, and for a `k` wider than 18 trits, whose product [`apply`]
+    /// could not hold.
+    ///
+    /// [`apply`]: MulChain::apply
+    pub fn new(k: i64) -> Option<Self> {
+        if k == 0 || k.unsigned_abs() > Word18::MAX as u64 {
+            return None;
+        }
+        let steps = plan_chain(k.unsigned_abs(), &mut std::collections::HashMap::new());
+        Some(MulChain { steps, negate: k < 0 })
+    }
+
+    /// Additions and subtractions in the chain.
+    pub fn adds(&self) -> usize {
+        self.steps.iter().filter(|step| step.term != ChainTerm::None).count()
+    }
+
+    /// `a` times the chain's constant, as (low, high) like [`multiply`].
+    pub fn apply(&self, a: &Word18) -> (Word18, Word18) {
+        let multiplicand: Word36 = a.resize();
+        let mut x = multiplicand;
+        for step in &self.steps {
+            let before = x;
+            let mut trits = [Trit::O; 36];
+            let shift = step.shift as usize;
+            trits[shift..].copy_from_slice(&x.trits()[..36 - shift]);
+            x = Word36::from_trits(trits);
+            let term = match step.term {
+                ChainTerm::None => continue,
+                ChainTerm::Multiplicand => multiplicand,
+                ChainTerm::Running => before,
+            };
+            // An 18 by 18 trit product always fits, so nothing carries out
+            x = if step.sign == Trit::P { x.add_with_carry(&term).0 } else { x.sub_with_borrow(&term).0 };
+        }
+        if self.negate {
+            x = x.neg();
+        }
+        (x.low(), x.high())
+    }
+}
+
+/// Cheapest steps taking `a` to `n * a`, for `n >= 1`, memoized in `memo`.
+fn plan_chain(n: u64, memo: &mut std::collections::HashMap<u64, Vec<MulStep>>) -> Vec<MulStep> {
+    if n == 1 {
+        return Vec::new();
+    }
+    if let Some(steps) = memo.get(&n) {
+        return steps.clone();
+    }
+
+    // (fewest adds, fewest steps, chain) over every way to finish n
+    let mut best: Option<(usize, usize, Vec<MulStep>)> = None;
+    let mut consider = |mut steps: Vec<MulStep>, step: MulStep| {
+        // A shift followed by a step using the multiplicand folds into it
+        match steps.last_mut() {
+            Some(last) if last.term == ChainTerm::None && step.term != ChainTerm::Running => {
+                last.shift += step.shift;
+                last.sign = step.sign;
+                last.term = step.term;
+            }
+            _ => steps.push(step),
+        }
+        let adds = steps.iter().filter(|s| s.term != ChainTerm::None).count();
+        if best.as_ref().is_none_or(|(a, len, _)| (adds, steps.len()) < (*a, *len)) {
+            best = Some((adds, steps.len(), steps));
+        }
+    };
+
+    // n = 3q + d: shift, then add or subtract the multiplicand
+    let (q, d) = match n % 3 {
+        0 => (n / 3, Trit::O),
+        1 => (n / 3, Trit::P),
+        _ => (n / 3 + 1, Trit::N),
+    };
+    let term = if d == Trit::O { ChainTerm::None } else { ChainTerm::Multiplicand };
+    consider(plan_chain(q, memo), MulStep { shift: 1, sign: d, term });
+
+    // n = m (3^s ± 1): shift, then add or subtract the running product
+    let mut power = 3u64;
+    for shift in 1.. {
+        if power - 1 > n {
+            break;
+        }
+        for (factor, sign) in [(power - 1, Trit::N), (power + 1, Trit::P)] {
+            if factor > 1 && n.is_multiple_of(factor) {
+                consider(plan_chain(n / factor, memo), MulStep { shift, sign, term: ChainTerm::Running });
+            }
+        }
+        power *= 3;
+    }
+
+    let steps = best.map(|(_, _, steps)| steps).unwrap_or_default();
+    memo.insert(n, steps.clone());
+    steps
+}
+
+/// Add `sign * b * 3^shift` into a 36-trit product.
+#[inline]
+fn add_shifted(product: &mut [Trit; 36], b: &Word18, shift: usize, sign: Trit) {
+    let mut carry = Trit::O;
+    for j in 0..18 {
+        // Single-trit multiply (never carries)
+        let partial = sign.mul(b.get(j));
+        
+        // Add to accumulator with carry
+        let (sum, new_carry) = product[shift + j].full_add(partial, carry);
+        product[shift + j] = sum;
+        carry = new_carry;
+    }
+    
+    // Propagate any remaining carry
+    let mut k = shift + 18;
+    while !carry.is_zero() && k < 36 {
+        let (sum, new_carry) = product[k].full_add(carry, Trit::O);
+        product[k] = sum;
+        carry = new_carry;
+        k += 1;
+    }
+}
+
+/// Split a 36-trit product into low and high 18-trit words.
+fn split_product(product: &[Trit; 36]) -> (Word18, Word18) {
+    let mut low_trits = [Trit::O; 18];
+    let mut high_trits = [Trit::O; 18];
+    
+    low_trits.copy_from_slice(&product[..18]);
+    high_trits.copy_from_slice(&product[18..]);
+    
+    (Word18::from_trits(low_trits), Word18::from_trits(high_trits))
+}
+
+/// Multiply two 9-trit words, returning an 18-trit result as (low, high).
+pub fn multiply_tryte9(a: &Tryte9, b: &Tryte9) -> (Tryte9, Tryte9) {
+    let mut product = [Trit::O; 18];
+    
+    for i in 0..9 {
+        if a.get(i).is_zero() {
+            continue;
+        }
         
         let mut carry = Trit::O;
-        for j in 0..18 {
-            // Single-trit multiply (never carries)
+        for j in 0..9 {
             let partial = a.get(i).mul(b.get(j));
-            
-            // Add to accumulator with carry
-            let (sum1, c1) = product[i + j].full_add(partial, Trit::O);
-            let (sum2, c2) = sum1.full_add(carry, Trit::O);
-            product[i + j] = sum2;
-            carry = c1.any(c2);
+            let (sum, new_carry) = product[i + j].full_add(partial, carry);
+            product[i + j] = sum;
+            carry = new_carry;
         }
         
-        // Propagate any remaining carry
-        let mut k = i + 18;
-        while !carry.is_zero() && k < 36 {
+        let mut k = i + 9;
+        while !carry.is_zero() && k < 18 {
             let (sum, new_carry) = product[k].full_add(carry, Trit::O);
             product[k] = sum;
             carry = new_carry;
@@ -93,34 +289,231 @@ pub fn multiply(a: &Word18, b: &Word18) -> (Word18, Word18) {
         }
     }
     
-    // Split into low and high 18-trit words
-    let mut low_trits = [Trit::O; 18];
-    let mut high_trits = [Trit::O; 18];
+    let mut low_trits = [Trit::O; 9];
+    let mut high_trits = [Trit::O; 9];
+    low_trits.copy_from_slice(&product[..9]);
+    high_trits.copy_from_slice(&product[9..]);
     
-    for i in 0..18 {
-        low_trits[i] = product[i];
-        high_trits[i] = product[i + 18];
+    (Tryte9::from_trits(low_trits), Tryte9::from_trits(high_trits))
+}
+
+/// Divide two 9-trit words, returning (quotient, remainder), or `None`
+/// when `b` is zero. Rounds like [`divide`]: the quotient is the nearest
+/// integer and |remainder| <= |b|/2. Both always fit in 9 trits.
+pub fn divmod_tryte9(a: &Tryte9, b: &Tryte9) -> Option<(Tryte9, Tryte9)> {
+    let (quotient, remainder) = divide_trits(&a.resize(), &b.resize(), Tryte9::WIDTH)?;
+    Some((quotient.resize(), remainder.resize()))
+}
+
+/// Divide `a` by `b` one quotient trit at a time, from the top, returning
+/// (quotient, remainder), or `None` when `b` is zero.
+///
+/// Each step picks the trit that brings the partial remainder closest to
+/// zero, so the quotient comes out rounded to nearest rather than
+/// truncated and the remainder `a - q*b` satisfies |r| <= |b|/2. An
+/// exact half can round either way, as the trits happen to fall: 5/2
+/// gives 3 rem -1 but 7/2 gives 3 rem 1, and 1/2 gives 0 rem 1. The
+/// choice is made the way the hardware would, from the sign of
+/// `2|r| - |b|*3^i`, with no integer conversion and no restoring step.
+pub fn divide(a: &Word18, b: &Word18) -> Option<(Word18, Word18)> {
+    let (quotient, remainder) = divide_trits(&a.resize(), &b.resize(), Word18::WIDTH)?;
+    Some((quotient.resize(), remainder.resize()))
+}
+
+/// Divide like Rust's `/` and `%`: the quotient truncated toward zero and
+/// a remainder with the sign of `a`, or `None` when `b` is zero.
+///
+/// This is [`divide`] followed by one correction step, taken when the
+/// rounded remainder has the wrong sign.
+pub fn divide_toward_zero(a: &Word18, b: &Word18) -> Option<(Word18, Word18)> {
+    let (quotient, remainder) = divide(a, b)?;
+    if remainder.is_zero() || remainder.sign() == a.sign() {
+        return Some((quotient, remainder));
     }
-    
-    (Word18::from_trits(low_trits), Word18::from_trits(high_trits))
+    // a = (q - s)b + (r + sb), and r + sb has the sign of a
+    let step = a.sign().mul(b.sign());
+    let (quotient, _) = quotient.sub_with_borrow(&Word18::from_i64(step.to_i8() as i64));
+    let (remainder, _) = remainder.add_with_carry(&if step == Trit::N { b.neg() } else { *b });
+    Some((quotient, remainder))
+}
+
+/// Divide a double-length dividend, such as the (low, high) product of
+/// [`multiply`] joined with [`Word36::from_halves`], by `b`. Rounds like
+/// [`divide`]; the quotient can be up to 36 trits wide.
+pub fn divide_wide(a: &Word36, b: &Word18) -> Option<(Word36, Word18)> {
+    let (quotient, remainder) = divide_trits(&a.resize(), &b.resize(), Word36::WIDTH)?;
+    Some((quotient.resize(), remainder.resize()))
+}
+
+/// Width of the division registers: a [`Word36`] dividend plus room to
+/// double the partial remainder.
+const DIV_WIDTH: usize = 38;
+
+type DivRegister = TryteN<DIV_WIDTH>;
+
+/// Round-to-nearest division of an `a` of `width` trits, the shared core
+/// of [`divide`], [`divmod_tryte9`] and [`divide_wide`].
+fn divide_trits(a: &DivRegister, b: &DivRegister, width: usize) -> Option<(DivRegister, DivRegister)> {
+    if b.is_zero() {
+        return None;
+    }
+    let divisor = magnitude(b);
+    let mut quotient = DivRegister::zero();
+    let mut remainder = *a;
+    for i in (0..width).rev() {
+        if remainder.is_zero() {
+            break;
+        }
+        // A shifted divisor too wide for the register is more than twice
+        // any remainder, so the trit is O
+        let Some(step) = shifted(&divisor, i) else {
+            continue;
+        };
+        // Subtracting |b|*3^i from |r| shrinks it only if 2|r| > |b|*3^i
+        let r = magnitude(&remainder);
+        let (twice, _) = r.add_with_carry(&r);
+        if twice.sub_with_borrow(&step).0.sign() != Trit::P {
+            continue;
+        }
+        let sign = remainder.sign();
+        let (reduced, _) = r.sub_with_borrow(&step);
+        remainder = if sign == Trit::N { reduced.neg() } else { reduced };
+        quotient.set(i, sign.mul(b.sign()));
+    }
+    Some((quotient, remainder))
+}
+
+/// |a|, by negating when the leading trit is N.
+fn magnitude(a: &DivRegister) -> DivRegister {
+    if a.sign() == Trit::N { a.neg() } else { *a }
+}
+
+/// `a` shifted left `n` trits, or `None` if a non-zero trit would drop off.
+fn shifted(a: &DivRegister, n: usize) -> Option<DivRegister> {
+    let trits = a.trits();
+    if trits[DIV_WIDTH - n..].iter().any(|t| !t.is_zero()) {
+        return None;
+    }
+    let mut result = [Trit::O; DIV_WIDTH];
+    result[n..].copy_from_slice(&trits[..DIV_WIDTH - n]);
+    Some(DivRegister::from_trits(result))
+}
+
+/// Integer square root: the largest `r` with `r*r <= a`.
+///
+/// # Panics
+/// Panics if `a` is negative; [`isqrt_rem`] returns `None` instead.
+pub fn isqrt(a: &Word18) -> Word18 {
+    isqrt_rem(a).expect("square root of a negative word").0
+}
+
+/// Integer square root with remainder: `(r, a - r*r)` for the largest
+/// `r` with `r*r <= a`, so the remainder is between 0 and `2r`, or `None`
+/// if `a` is negative.
+///
+/// The root is found one balanced trit at a time from the top. With the
+/// root `r` chosen so far and `h` the largest value the remaining trits can
+/// add (all P), the next trit is P if `a >= (r + h + 1)^2`, N if
+/// `a < (r - h)^2`, and O otherwise. Both tests compare the running
+/// remainder `a - r*r` with bounds built from shifts and additions, so no
+/// multiplication or floating point is needed.
+pub fn isqrt_rem(a: &Word18) -> Option<(Word18, Word18)> {
+    if a.sign() == Trit::N {
+        return None;
+    }
+    // h_i = (3^i - 1) / 2 and its square, from h_0 = 0 by h_{i+1} = 3h_i + 1
+    let mut halves = vec![(SqrtRegister::zero(), SqrtRegister::zero())];
+    for i in 0..ROOT_TRITS - 1 {
+        let (h, square) = halves[i];
+        let next = add_all(&[times3(&h), one()]);
+        // (3h + 1)^2 = 9h^2 + 6h + 1
+        let next_square = add_all(&[times3(&times3(&square)), times3(&h), times3(&h), one()]);
+        halves.push((next, next_square));
+    }
+
+    let mut root = SqrtRegister::zero();
+    let mut remainder: SqrtRegister = a.resize();
+    for i in (0..ROOT_TRITS).rev() {
+        let (h, h_square) = halves[i];
+        let place = shifted_by(&one(), i);
+        let root_place = shifted_by(&root, i);
+        // (r + h + 1)^2 - r^2 = r*3^i + r + (h + 1)^2
+        let h1_square = add_all(&[h_square, h, h, one()]);
+        let up = add_all(&[root_place, root, h1_square]);
+        if remainder.sub_with_borrow(&up).0.sign() != Trit::N {
+            // a - (r + 3^i)^2 = a - r^2 - 2r*3^i - 9^i
+            remainder = add_all(&[remainder, root_place.neg(), root_place.neg(), shifted_by(&place, i).neg()]);
+            root = root.add_with_carry(&place).0;
+            continue;
+        }
+        // (r - h)^2 - r^2 = h^2 - r*3^i + r, meaningful only while r > h
+        let down = add_all(&[h_square, root_place.neg(), root]);
+        if !root.is_zero() && remainder.sub_with_borrow(&down).0.sign() == Trit::N {
+            remainder = add_all(&[remainder, root_place, root_place, shifted_by(&place, i).neg()]);
+            root = root.sub_with_borrow(&place).0;
+        }
+    }
+    Some((root.resize(), remainder.resize()))
+}
+
+/// Trits in the root of a [`Word18`]: sqrt(193,710,244) < 13,918.
+const ROOT_TRITS: usize = 10;
+
+/// Working register of [`isqrt_rem`], wide enough for `r*3^i` at every step.
+type SqrtRegister = TryteN<24>;
+
+fn one() -> SqrtRegister {
+    SqrtRegister::from_i64_const(1)
+}
+
+fn times3(a: &SqrtRegister) -> SqrtRegister {
+    shifted_by(a, 1)
+}
+
+/// `a * 3^n`; the register is wide enough that nothing drops off.
+fn shifted_by(a: &SqrtRegister, n: usize) -> SqrtRegister {
+    let mut trits = [Trit::O; 24];
+    trits[n..].copy_from_slice(&a.trits()[..24 - n]);
+    SqrtRegister::from_trits(trits)
+}
+
+fn add_all(terms: &[SqrtRegister]) -> SqrtRegister {
+    terms.iter().fold(SqrtRegister::zero(), |sum, term| sum.add_with_carry(term).0)
 }
 
 /// Shift a word left by n trit positions (multiply by 3^n).
-/// Fills vacated positions with zeros. Trits shifted out are lost.
+/// Fills vacated positions with zeros. Trits shifted out are lost; use
+/// [`shift_left_checked`] to find out whether any were significant.
+#[inline]
 pub fn shift_left(a: &Word18, n: usize) -> Word18 {
+    shift_left_checked(a, n).0
+}
+
+/// Shift a word left by n trit positions, returning (result, lost).
+///
+/// `lost` holds the trits shifted out of the top, lowest first (all of
+/// `a` once n reaches 18), so it is zero exactly when the result equals
+/// a × 3^n and otherwise has the sign of the part that did not fit.
+#[inline]
+pub fn shift_left_checked(a: &Word18, n: usize) -> (Word18, Word18) {
     if n >= 18 {
-        return Word18::zero();
+        return (Word18::zero(), *a);
     }
     
     let mut result = Word18::zero();
     for i in 0..(18 - n) {
         result.set(i + n, a.get(i));
     }
-    result
+    (result, shift_right(a, 18 - n))
 }
 
-/// Shift a word right by n trit positions (divide by 3^n, truncated).
-/// In balanced ternary, truncation equals rounding!
+/// Shift a word right by n trit positions (divide by 3^n).
+///
+/// Dropping the low trits of a balanced ternary number rounds the quotient
+/// to nearest, and the result keeps the sign of `a` or becomes zero, so
+/// unlike a binary arithmetic shift no sign extension is needed and
+/// negative values round symmetrically with positive ones.
+#[inline]
 pub fn shift_right(a: &Word18, n: usize) -> Word18 {
     if n >= 18 {
         return Word18::zero();
@@ -133,6 +526,35 @@ pub fn shift_right(a: &Word18, n: usize) -> Word18 {
     result
 }
 
+/// Shift a 9-trit word left by n trit positions (multiply by 3^n).
+/// Fills vacated positions with zeros. Trits shifted out are lost.
+#[inline]
+pub fn shift_left_tryte9(a: &Tryte9, n: usize) -> Tryte9 {
+    shift_left_checked_tryte9(a, n).0
+}
+
+/// Shift a 9-trit word left by n trit positions, returning (result, lost)
+/// as [`shift_left_checked`] does.
+#[inline]
+pub fn shift_left_checked_tryte9(a: &Tryte9, n: usize) -> (Tryte9, Tryte9) {
+    let mut result = Tryte9::zero();
+    for i in 0..9usize.saturating_sub(n) {
+        result.set(i + n, a.get(i));
+    }
+    (result, shift_right_tryte9(a, 9usize.saturating_sub(n)))
+}
+
+/// Shift a 9-trit word right by n trit positions (divide by 3^n, rounded
+/// to nearest, as [`shift_right`]).
+#[inline]
+pub fn shift_right_tryte9(a: &Tryte9, n: usize) -> Tryte9 {
+    let mut result = Tryte9::zero();
+    for i in n.min(9)..9 {
+        result.set(i - n, a.get(i));
+    }
+    result
+}
+
 /// Compare two words, returning their relationship.
 pub fn compare(a: &Word18, b: &Word18) -> std::cmp::Ordering {
     let a_val = a.to_i64();
@@ -212,6 +634,141 @@ mod tests {
         assert!(high.is_zero());
     }
     
+    #[test]
+    fn test_mul_const_matches_multiply() {
+        for a in [0, 1, -7, 12345, -98765, Word18::MAX] {
+            let word = Word18::from_i64(a);
+            for k in [0, 1, -1, 2, 5, 10, -81, 9841, Word18::MIN] {
+                assert_eq!(mul_const(&word, k), multiply(&word, &Word18::from_i64(k)), "{} * {}", a, k);
+            }
+        }
+        assert_eq!(mul_const(&Word18::from_i64(-4), 10).0.to_i64(), -40);
+        
+        // Every chain agrees with its constant
+        let word = Word18::from_i64(-12345);
+        for k in -400..=400 {
+            assert_eq!(mul_const(&word, k), multiply(&word, &Word18::from_i64(k)), "{}", k);
+        }
+    }
+    
+    #[test]
+    fn test_mul_chain_lengths() {
+        let adds = |k| MulChain::new(k).unwrap().adds();
+        assert_eq!(MulChain::new(0), None);
+        assert_eq!([1, 3, 81].map(adds), [0, 0, 0]);
+        assert_eq!([2, 4, 8, 10, -10, 18].map(adds), [1, 1, 1, 1, 1, 1]);
+        assert_eq!(adds(5), 2);
+        
+        // Factoring beats one step per trit: 16 = 4 × 4 and 20 = 2 × 10
+        assert_eq!((adds(16), adds(20)), (2, 2));
+        
+        // The widest constants still plan; one more does not fit
+        let word = Word18::from_i64(-12345);
+        for k in [Word18::MAX, Word18::MIN] {
+            assert_eq!(MulChain::new(k).unwrap().apply(&word), multiply(&word, &Word18::from_i64(k)), "{}", k);
+        }
+        assert_eq!(MulChain::new(Word18::MAX + 1), None);
+        assert_eq!(MulChain::new(Word18::MIN - 1), None);
+        assert!(std::panic::catch_unwind(|| mul_const(&word, Word18::MAX + 1)).is_err());
+        
+        let ten = MulChain::new(10).unwrap();
+        assert_eq!(ten.steps, [MulStep { shift: 2, sign: Trit::P, term: ChainTerm::Multiplicand }]);
+    }
+    
+    #[test]
+    fn test_tryte9_arithmetic() {
+        let t = Tryte9::from_i32;
+        let (low, high) = multiply_tryte9(&t(-97), &t(203));
+        assert_eq!(high.to_i32() * 19683 + low.to_i32(), -97 * 203);
+        let (low, high) = multiply_tryte9(&t(Tryte9::MAX), &t(Tryte9::MAX));
+        assert_eq!(high.to_i32() as i64 * 19683 + low.to_i32() as i64, 9841 * 9841);
+        
+        assert_eq!(divmod_tryte9(&t(8), &t(3)), Some((t(3), t(-1))));
+        assert_eq!(divmod_tryte9(&t(-9841), &t(-1)), Some((t(9841), t(0))));
+        assert_eq!(divmod_tryte9(&t(5), &t(0)), None);
+        
+        assert_eq!(shift_left_tryte9(&t(-5), 2), t(-45));
+        assert_eq!(shift_left_tryte9(&t(5), 9), Tryte9::zero());
+        assert_eq!(shift_right_tryte9(&t(-45), 2), t(-5));
+        assert_eq!(shift_right_tryte9(&t(4), 1), t(1));
+        assert_eq!(shift_right_tryte9(&t(4), 12), Tryte9::zero());
+    }
+    
+    #[test]
+    fn test_divide_rounds_to_nearest() {
+        let div = |a: i64, b: i64| {
+            divide(&Word18::from_i64(a), &Word18::from_i64(b)).map(|(q, r)| (q.to_i64(), r.to_i64()))
+        };
+        assert_eq!(div(7, 2), Some((3, 1)));
+        assert_eq!(div(5, 2), Some((3, -1)));
+        // Exact halves go whichever way the top trits lead
+        assert_eq!(div(1, 2), Some((0, 1)));
+        assert_eq!(div(-1, 2), Some((0, -1)));
+        assert_eq!(div(3, 2), Some((1, 1)));
+        assert_eq!(div(-5, 2), Some((-3, 1)));
+        assert_eq!(div(-7, 2), Some((-3, -1)));
+        assert_eq!(div(15, 6), Some((3, -3)));
+        assert_eq!(div(8, 3), Some((3, -1)));
+        assert_eq!(div(-8, 3), Some((-3, 1)));
+        assert_eq!(div(100, -7), Some((-14, 2)));
+        assert_eq!(div(Word18::MAX, 1), Some((Word18::MAX, 0)));
+        assert_eq!(div(5, 0), None);
+    }
+    
+    /// The rounding division of [`divide`], in integers.
+    fn reference_divide(a: i64, b: i64) -> (i64, i64) {
+        let (mut q, mut r) = (0, a);
+        for i in (0..18).rev() {
+            let step = b * 3i64.pow(i);
+            let t = [-1, 1].into_iter().find(|t| (r - t * step).abs() < r.abs()).unwrap_or(0);
+            r -= t * step;
+            q += t * 3i64.pow(i);
+        }
+        (q, r)
+    }
+    
+    #[test]
+    fn test_division_matches_integers() {
+        let w = Word18::from_i64;
+        for a in (-250..=250).chain([Word18::MIN, Word18::MAX, 193_000_001, -87_654_321]) {
+            for b in (-15..=15).chain([Word18::MIN, Word18::MAX, 9_841, -19_683]).filter(|&b| b != 0) {
+                let (q, r) = divide(&w(a), &w(b)).unwrap();
+                assert_eq!(q.to_i64() * b + r.to_i64(), a);
+                assert!(2 * r.to_i64().abs() <= b.abs(), "{} / {}", a, b);
+                assert_eq!((q.to_i64(), r.to_i64()), reference_divide(a, b), "{} / {}", a, b);
+
+                let (q, r) = divide_toward_zero(&w(a), &w(b)).unwrap();
+                assert_eq!((q.to_i64(), r.to_i64()), (a / b, a % b));
+            }
+        }
+
+        // The full product of two words divides back
+        for (x, y) in [(Word18::MAX, Word18::MAX), (-123_456_789, 98_765), (7, -1)] {
+            let (low, high) = multiply(&w(x), &w(y));
+            let (q, r) = divide_wide(&Word36::from_halves(low, high), &w(y)).unwrap();
+            assert_eq!((q.to_i64(), r.to_i64()), (x, 0));
+        }
+        let a = Word36::from_i64(Word36::MAX_VALUE);
+        let (q, r) = divide_wide(&a, &w(-2)).unwrap();
+        assert_eq!(q.to_i64() * -2 + r.to_i64(), Word36::MAX_VALUE);
+        assert_eq!(divide_wide(&a, &Word18::zero()), None);
+    }
+    
+    #[test]
+    fn test_isqrt() {
+        let check = |a: i64| {
+            let (root, remainder) = isqrt_rem(&Word18::from_i64(a)).unwrap();
+            let (root, remainder) = (root.to_i64(), remainder.to_i64());
+            assert_eq!(root * root + remainder, a);
+            assert!((0..=2 * root).contains(&remainder), "isqrt({}) = {} rem {}", a, root, remainder);
+        };
+        (0..3000).chain([Word18::MAX, 13_917 * 13_917, 13_917 * 13_917 - 1, 9_841 * 9_841, 193_000_000]).for_each(check);
+        (0..Word18::MAX).step_by(999_983).for_each(check);
+
+        assert_eq!(isqrt(&Word18::from_i64(1_000_000)).to_i64(), 1000);
+        assert_eq!(isqrt_rem(&Word18::from_i64(-4)), None);
+    }
+    
     #[test]
     fn test_shift_left() {
         let a = Word18::from_i64(1);
@@ -225,6 +782,26 @@ mod tests {
         assert_eq!(shifted2.to_i64(), 9);
     }
     
+    #[test]
+    fn test_shift_left_checked() {
+        // A value that fits loses nothing
+        let (shifted, lost) = shift_left_checked(&Word18::from_i64(-1000), 5);
+        assert_eq!(shifted.to_i64(), -243_000);
+        assert!(lost.is_zero());
+        
+        // Otherwise result + lost * 3^18 is the true product
+        for (value, n) in [(Word18::MAX, 1), (-5000, 12), (7, 17), (1, 18)] {
+            let (shifted, lost) = shift_left_checked(&Word18::from_i64(value), n);
+            assert_eq!(shifted.to_i64() + lost.to_i64() * 3i64.pow(18), value * 3i64.pow(n as u32));
+            assert_eq!(lost.sign(), Word18::from_i64(value).sign());
+        }
+        assert_eq!(shift_left_checked(&Word18::from_i64(4), 40).1.to_i64(), 4);
+        
+        let (shifted, lost) = shift_left_checked_tryte9(&Tryte9::from_i32(-400), 3);
+        assert_eq!(shifted.to_i32() + lost.to_i32() * 19683, -10800);
+        assert!(!lost.is_zero());
+    }
+    
     #[test]
     fn test_shift_right() {
         let a = Word18::from_i64(27);
@@ -0,0 +1,418 @@
+//! Step-by-step explanations of balanced ternary arithmetic.
+//!
+//! [`explain_add`], [`explain_sub`] and [`explain_mul`] redo an operation
+//! the way [`add`](super::add) and [`multiply`](super::multiply) compute it
+//! and record every trit position along the way: the two operand trits,
+//! the carry coming in, the sum trit and the carry going out. An
+//! [`Explanation`] renders as a plain-text table through `Display` or as
+//! an HTML fragment through [`Explanation::to_html`].
+//!
+//! ```
+//! use setun::ternary::arith::explain;
+//!
+//! let steps = explain("5 + 7").unwrap();
+//! assert_eq!(steps.value(), 12);
+//! // Trit 1: - plus - is -2, written as + with a carry of -
+//! let t1 = &steps.passes[0].steps[1];
+//! assert_eq!((t1.sum.to_i8(), t1.carry_out.to_i8()), (1, -1));
+//! ```
+
+use crate::ternary::convert::{parse_number, ConvertError};
+use crate::ternary::{Trit, Word18};
+use serde::Serialize;
+use std::fmt;
+use thiserror::Error;
+
+/// Error parsing an arithmetic expression to explain.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ExplainError {
+    /// The text is not `<a> <op> <b>`.
+    #[error("expected '<a> + <b>', '<a> - <b>' or '<a> * <b>', got '{0}'")]
+    Syntax(String),
+    /// An operand is not a valid 18-trit number.
+    #[error(transparent)]
+    Number(#[from] ConvertError),
+}
+
+/// The operation being explained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArithOp {
+    /// `a + b`
+    Add,
+    /// `a - b`, computed as `a + (-b)`
+    Subtract,
+    /// `a × b`, computed by adding shifted partial products
+    Multiply,
+}
+
+impl ArithOp {
+    /// Operator symbol.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            ArithOp::Add => "+",
+            ArithOp::Subtract => "-",
+            ArithOp::Multiply => "×",
+        }
+    }
+}
+
+impl fmt::Display for ArithOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+/// One trit position of a ripple-carry addition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TritStep {
+    /// Trit position, 0 being least significant.
+    pub position: usize,
+    /// Trit of the first addend (for multiplication, the running total).
+    pub a: Trit,
+    /// Trit of the second addend (for multiplication, the partial product).
+    pub b: Trit,
+    /// Carry from the position below.
+    pub carry_in: Trit,
+    /// Resulting trit.
+    pub sum: Trit,
+    /// Carry into the position above.
+    pub carry_out: Trit,
+}
+
+/// One ripple-carry addition.
+///
+/// Addition and subtraction have a single pass. Multiplication has one
+/// per non-zero trit of `a`, adding that trit times `b`, shifted into place.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Pass {
+    /// Trit of `a` selecting this partial product (P for addition).
+    pub multiplier: Trit,
+    /// How many positions the partial product is shifted left.
+    pub shift: usize,
+    /// The trits added in this pass, least significant first.
+    pub addend: Vec<Trit>,
+    /// Every trit position the addition visited.
+    pub steps: Vec<TritStep>,
+    /// The accumulated result after the pass, least significant first.
+    pub total: Vec<Trit>,
+}
+
+/// A worked arithmetic operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Explanation {
+    /// The operation.
+    pub op: ArithOp,
+    /// First operand.
+    pub a: Word18,
+    /// Second operand.
+    pub b: Word18,
+    /// The additions performed, in order.
+    pub passes: Vec<Pass>,
+    /// Result trits, least significant first: 18 for addition and
+    /// subtraction, 36 for multiplication.
+    pub result: Vec<Trit>,
+    /// Carry out of the top trit; non-zero means an 18-trit sum overflowed.
+    pub carry: Trit,
+}
+
+/// Explain `a + b`.
+pub fn explain_add(a: &Word18, b: &Word18) -> Explanation {
+    explain_sum(ArithOp::Add, a, b, b)
+}
+
+/// Explain `a - b`: `b` is negated by flipping every trit, then added.
+pub fn explain_sub(a: &Word18, b: &Word18) -> Explanation {
+    explain_sum(ArithOp::Subtract, a, b, &b.neg())
+}
+
+fn explain_sum(op: ArithOp, a: &Word18, b: &Word18, addend: &Word18) -> Explanation {
+    let mut total = a.trits().to_vec();
+    let (steps, carry) = ripple(&mut total, addend.trits(), 0);
+    let pass = Pass {
+        multiplier: Trit::P,
+        shift: 0,
+        addend: addend.trits().to_vec(),
+        steps,
+        total: total.clone(),
+    };
+    Explanation { op, a: *a, b: *b, passes: vec![pass], result: total, carry }
+}
+
+/// Explain `a × b` as the schoolbook method of [`multiply`](super::multiply).
+///
+/// Each trit of `a` multiplies all of `b` without carries (a single-trit
+/// product is -1, 0 or +1), so the partial products are `b`, `-b` or
+/// nothing, and only their additions carry.
+pub fn explain_mul(a: &Word18, b: &Word18) -> Explanation {
+    let mut total = vec![Trit::O; 36];
+    let mut passes = Vec::new();
+    for shift in 0..18 {
+        let multiplier = a.get(shift);
+        if multiplier.is_zero() {
+            continue;
+        }
+        let mut addend = vec![Trit::O; 36];
+        for (j, &t) in b.trits().iter().enumerate() {
+            addend[shift + j] = multiplier.mul(t);
+        }
+        let (steps, _) = ripple(&mut total, &addend[shift..shift + 18], shift);
+        passes.push(Pass { multiplier, shift, addend, steps, total: total.clone() });
+    }
+    Explanation { op: ArithOp::Multiply, a: *a, b: *b, passes, result: total, carry: Trit::O }
+}
+
+/// Add `addend` into `acc` starting at `offset`, propagating any carry
+/// up to the top of `acc`. Returns the visited positions and the final carry.
+fn ripple(acc: &mut [Trit], addend: &[Trit], offset: usize) -> (Vec<TritStep>, Trit) {
+    let mut steps = Vec::new();
+    let mut carry = Trit::O;
+    for (position, slot) in acc.iter_mut().enumerate().skip(offset) {
+        let b = addend.get(position - offset).copied().unwrap_or(Trit::O);
+        if position >= offset + addend.len() && carry.is_zero() {
+            break;
+        }
+        let a = *slot;
+        let (sum, carry_out) = a.full_add(b, carry);
+        steps.push(TritStep { position, a, b, carry_in: carry, sum, carry_out });
+        *slot = sum;
+        carry = carry_out;
+    }
+    (steps, carry)
+}
+
+/// Parse and explain an expression such as `5 + 7`, `0t+- * -3` or `100 - 0n1Z`.
+///
+/// Operands may be written in any notation [`parse_number`] accepts and
+/// must fit in 18 trits.
+pub fn explain(expr: &str) -> Result<Explanation, ExplainError> {
+    let text: String = expr.split_whitespace().collect();
+
+    // `-` and `+` also appear in signs and trit patterns, so take the
+    // first operator position where both sides parse as numbers
+    for (i, c) in text.char_indices().skip(1) {
+        let op = match c {
+            '+' => ArithOp::Add,
+            '-' => ArithOp::Subtract,
+            '*' | '×' => ArithOp::Multiply,
+            _ => continue,
+        };
+        let (left, right) = (&text[..i], &text[i + c.len_utf8()..]);
+        let (a, b) = match (parse_number(left), parse_number(right)) {
+            (Ok(a), Ok(b)) => (a, b),
+            _ => continue,
+        };
+        let (a, b) = (operand(a)?, operand(b)?);
+        return Ok(match op {
+            ArithOp::Add => explain_add(&a, &b),
+            ArithOp::Subtract => explain_sub(&a, &b),
+            ArithOp::Multiply => explain_mul(&a, &b),
+        });
+    }
+    Err(ExplainError::Syntax(expr.trim().to_string()))
+}
+
+fn operand(value: i64) -> Result<Word18, ConvertError> {
+    if value.abs() > Word18::MAX {
+        return Err(ConvertError::OutOfRange { value, trits: Word18::WIDTH });
+    }
+    Ok(Word18::from_i64(value))
+}
+
+/// Value of balanced ternary trits, least significant first.
+fn trits_value(trits: &[Trit]) -> i64 {
+    trits.iter().rev().fold(0, |acc, &t| acc * 3 + t.to_i8() as i64)
+}
+
+/// Trits, most significant first, without leading zeros.
+fn trit_pattern(trits: &[Trit]) -> String {
+    let pattern: String = trits.iter().rev()
+        .skip_while(|t| t.is_zero())
+        .map(|t| t.to_string())
+        .collect();
+    if pattern.is_empty() { "0".into() } else { pattern }
+}
+
+impl Explanation {
+    /// The exact result, including any carry out of the top trit.
+    pub fn value(&self) -> i64 {
+        trits_value(&self.result) + self.carry.to_i8() as i64 * 3i64.pow(self.result.len() as u32)
+    }
+
+    /// Number of low positions worth showing: up to the highest non-zero
+    /// trit or carry anywhere in the explanation.
+    fn shown_width(&self) -> usize {
+        let mut width = 1;
+        let operands = [self.a.trits(), self.b.trits()];
+        for trits in operands.iter().map(|t| &t[..]).chain([&self.result[..]]) {
+            if let Some(top) = trits.iter().rposition(|t| !t.is_zero()) {
+                width = width.max(top + 1);
+            }
+        }
+        for step in self.passes.iter().flat_map(|p| &p.steps) {
+            if !step.carry_out.is_zero() {
+                width = width.max(step.position + 2);
+            }
+        }
+        width
+    }
+
+    /// Render as an HTML fragment: a summary table of the operands and
+    /// result, then one table per addition pass.
+    pub fn to_html(&self) -> String {
+        let width = self.shown_width().min(self.result.len());
+        let mut html = String::from("<div class=\"setun-explain\">\n");
+        html.push_str(&format!(
+            "<p class=\"equation\">{} {} {} = {}</p>\n",
+            self.a.to_i64(), escape(self.op.symbol()), self.b.to_i64(), self.value()
+        ));
+        for pass in &self.passes {
+            let caption = match self.op {
+                ArithOp::Add => "a + b".to_string(),
+                ArithOp::Subtract => format!("a + (-b), -b = {}", trit_pattern(&pass.addend)),
+                ArithOp::Multiply => format!(
+                    "trit {} of a is {}: add {}b shifted {} place(s)",
+                    pass.shift, pass.multiplier, if pass.multiplier == Trit::P { "" } else { "-" }, pass.shift
+                ),
+            };
+            html.push_str(&format!("<table class=\"pass\">\n<caption>{}</caption>\n", escape(&caption)));
+            html.push_str("<tr><th>trit</th><th>a</th><th>b</th><th>carry in</th><th>sum</th><th>carry out</th></tr>\n");
+            for step in pass.steps.iter().rev().filter(|s| s.position < width || !s.carry_in.is_zero()) {
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    step.position, step.a, step.b, step.carry_in, step.sum, step.carry_out
+                ));
+            }
+            html.push_str(&format!(
+                "<tr class=\"total\"><td colspan=\"4\">total</td><td colspan=\"2\">{} ({})</td></tr>\n</table>\n",
+                trit_pattern(&pass.total), trits_value(&pass.total)
+            ));
+        }
+        if !self.carry.is_zero() {
+            html.push_str(&format!("<p class=\"overflow\">carry {} out of trit 17: the 18-trit result overflows</p>\n", self.carry));
+        }
+        html.push_str("</div>\n");
+        html
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.shown_width();
+        writeln!(f, "{} {} {} = {}", self.a.to_i64(), self.op, self.b.to_i64(), self.value())?;
+        writeln!(f, "  a = {:>w$}  ({})", trit_pattern(self.a.trits()), self.a.to_i64(), w = width)?;
+        writeln!(f, "  b = {:>w$}  ({})", trit_pattern(self.b.trits()), self.b.to_i64(), w = width)?;
+
+        match self.op {
+            ArithOp::Add | ArithOp::Subtract => {
+                let pass = &self.passes[0];
+                if self.op == ArithOp::Subtract {
+                    writeln!(f, " -b = {:>w$}  (every trit flipped)", trit_pattern(&pass.addend), w = width)?;
+                }
+                writeln!(f)?;
+                writeln!(f, "  trit   a  {}  carry in  sum  carry out", if self.op == ArithOp::Add { "b " } else { "-b" })?;
+                for step in pass.steps.iter().rev().filter(|s| s.position < width) {
+                    writeln!(
+                        f, "  {:>4}   {}  {}      {}       {}       {}",
+                        step.position, step.a, step.b, step.carry_in, step.sum, step.carry_out
+                    )?;
+                }
+            }
+            ArithOp::Multiply => {
+                writeln!(f)?;
+                writeln!(f, "  Each trit of a multiplies b without carrying, giving b, -b or 0;")?;
+                writeln!(f, "  the shifted partial products are then added with carries.")?;
+                writeln!(f)?;
+                let partials: Vec<String> = self.passes.iter().map(|p| trit_pattern(&p.addend)).collect();
+                let col = partials.iter().map(String::len).max().unwrap_or(0).max("partial product".len());
+                writeln!(f, "  trit  a  {:>col$}  running total", "partial product", col = col)?;
+                for (pass, partial) in self.passes.iter().zip(&partials) {
+                    writeln!(
+                        f, "  {:>4}  {}  {:>col$}  {} ({})",
+                        pass.shift, pass.multiplier, partial,
+                        trit_pattern(&pass.total), trits_value(&pass.total), col = col
+                    )?;
+                }
+                if self.passes.is_empty() {
+                    writeln!(f, "  (a is zero, so nothing is added)")?;
+                }
+            }
+        }
+
+        writeln!(f)?;
+        write!(f, "  = {}  ({})", trit_pattern(&self.result), trits_value(&self.result))?;
+        if !self.carry.is_zero() {
+            write!(f, " with carry {} out of trit {}: overflow", self.carry, self.result.len() - 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Escape text for HTML element content.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ternary::arith::{add, multiply, subtract};
+
+    #[test]
+    fn test_explanations_match_arithmetic() {
+        for (x, y) in [(5, 7), (-13, 40), (9841, -1), (0, 0), (Word18::MAX, 1), (-1234, -5678)] {
+            let (a, b) = (Word18::from_i64(x), Word18::from_i64(y));
+
+            let sum = explain_add(&a, &b);
+            assert_eq!(sum.result, add(&a, &b).0.trits().to_vec());
+            assert_eq!(sum.carry, add(&a, &b).1);
+            assert_eq!(sum.value(), x + y);
+
+            let diff = explain_sub(&a, &b);
+            assert_eq!(diff.result, subtract(&a, &b).0.trits().to_vec());
+            assert_eq!(diff.value(), x - y);
+
+            let product = explain_mul(&a, &b);
+            let (low, high) = multiply(&a, &b);
+            assert_eq!(product.result[..18], low.trits()[..]);
+            assert_eq!(product.result[18..], high.trits()[..]);
+            assert_eq!(product.value(), x * y);
+            assert_eq!(product.passes.len(), a.trits().iter().filter(|t| !t.is_zero()).count());
+        }
+    }
+
+    #[test]
+    fn test_carry_steps() {
+        // 4 + 5: ++ + +-- → trit 0: + + - = 0, trit 1: + + - = 0, trit 2: 0 + + = +
+        let steps = &explain_add(&Word18::from_i64(4), &Word18::from_i64(5)).passes[0].steps;
+        assert_eq!(steps.len(), 18);
+        let t1 = steps[1];
+        assert_eq!((t1.a, t1.b, t1.carry_in, t1.sum, t1.carry_out), (Trit::P, Trit::N, Trit::O, Trit::O, Trit::O));
+
+        // Partial products start at their shift and stop once the carry dies out
+        let product = explain_mul(&Word18::from_i64(3), &Word18::from_i64(1));
+        assert_eq!(product.passes[0].shift, 1);
+        assert_eq!(product.passes[0].steps.first().unwrap().position, 1);
+        assert_eq!(product.passes[0].steps.len(), 18);
+    }
+
+    #[test]
+    fn test_parse_and_render() {
+        assert_eq!(explain("5--3").unwrap().value(), 8);
+        assert_eq!(explain("-5 - 3").unwrap().value(), -8);
+        assert_eq!(explain("0t+- * 0t-").unwrap().value(), -2);
+        assert_eq!(explain("6 × 7").unwrap().op, ArithOp::Multiply);
+        assert!(matches!(explain("5"), Err(ExplainError::Syntax(_))));
+        assert!(matches!(explain("1 + 999999999"), Err(ExplainError::Number(ConvertError::OutOfRange { .. }))));
+
+        let text = explain("5 + 7").unwrap().to_string();
+        assert!(text.starts_with("5 + 7 = 12\n"));
+        assert!(text.contains("     1   -  -      0       +       -\n"));
+        assert!(text.ends_with("= ++0  (12)"));
+        assert!(explain("5 - 7").unwrap().to_string().contains(" -b = -+-  (every trit flipped)"));
+
+        let html = explain("7 * 6").unwrap().to_html();
+        assert!(html.contains("<p class=\"equation\">7 × 6 = 42</p>"));
+        assert_eq!(html.matches("<table").count(), 3);
+    }
+}
@@ -0,0 +1,432 @@
+//! Arbitrary-precision balanced ternary integers.
+//!
+//! A [`BigTrit`] holds as many trits as its value needs, so algorithms can
+//! be tried beyond 18 trits and the machine's fixed-width results checked
+//! against an exact reference:
+//!
+//! ```
+//! use setun::ternary::{arith, BigTrit};
+//! use setun::Word18;
+//!
+//! let (a, b) = (Word18::from_i64(-98_765_432), Word18::from_i64(123_456));
+//! let (low, high) = arith::multiply(&a, &b);
+//!
+//! let exact = BigTrit::from(a) * BigTrit::from(b);
+//! let split = BigTrit::from(high).mul_pow3(18) + BigTrit::from(low);
+//! assert_eq!(split, exact);
+//! assert_eq!(exact.to_string(), "-12193185172992");
+//!
+//! let big: BigTrit = "1000000000000000000000000000000000000000".parse().unwrap();
+//! assert_eq!(big.to_i128(), None);
+//! assert_eq!((&big / &BigTrit::from(10i128.pow(30))).to_i64(), Some(1_000_000_000));
+//! ```
+//!
+//! Values are kept without leading zero trits, so equal values have equal
+//! trits and zero has none. Division truncates toward zero like Rust's
+//! integers; [`BigTrit::div_rem_nearest`] rounds to nearest.
+
+use crate::ternary::{ConvertError, Trit, TritVec, TryteN};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// A balanced ternary integer of any size.
+#[derive(Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigTrit {
+    /// Trits from least significant, with no zero at the top.
+    trits: Vec<Trit>,
+}
+
+impl BigTrit {
+    /// Zero.
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    /// Create from trits, least significant first.
+    pub fn from_trits(mut trits: Vec<Trit>) -> Self {
+        let len = trits.iter().rposition(|t| !t.is_zero()).map_or(0, |i| i + 1);
+        trits.truncate(len);
+        Self { trits }
+    }
+
+    /// The trits, least significant first, without leading zeros.
+    pub fn trits(&self) -> &[Trit] {
+        &self.trits
+    }
+
+    /// Number of trits the value needs (0 for zero).
+    pub fn trit_len(&self) -> usize {
+        self.trits.len()
+    }
+
+    /// Whether the value is zero.
+    pub fn is_zero(&self) -> bool {
+        self.trits.is_empty()
+    }
+
+    /// The sign of the value (its leading trit).
+    pub fn sign(&self) -> Trit {
+        self.trits.last().copied().unwrap_or(Trit::O)
+    }
+
+    /// Negate every trit.
+    pub fn neg(&self) -> Self {
+        Self { trits: self.trits.iter().map(|t| t.neg()).collect() }
+    }
+
+    /// The magnitude.
+    pub fn abs(&self) -> Self {
+        if self.sign() == Trit::N { self.neg() } else { self.clone() }
+    }
+
+    /// Encode an integer.
+    pub fn from_i128(value: i128) -> Self {
+        let mut trits = Vec::new();
+        let mut rest = value;
+        while rest != 0 {
+            // Divide before correcting the digit, so i128::MIN cannot overflow
+            let (trit, carry) = match rest.rem_euclid(3) {
+                0 => (Trit::O, 0),
+                1 => (Trit::P, 0),
+                _ => (Trit::N, 1),
+            };
+            rest = rest.div_euclid(3) + carry;
+            trits.push(trit);
+        }
+        Self { trits }
+    }
+
+    /// Decode the value, or `None` if it does not fit in an `i128`.
+    pub fn to_i128(&self) -> Option<i128> {
+        // 3 * acc + t, adding the trit first so i128::MIN has no
+        // intermediate overflow
+        self.trits.iter().rev().try_fold(0i128, |acc, t| {
+            acc.checked_add(t.to_i8() as i128)?.checked_add(acc)?.checked_add(acc)
+        })
+    }
+
+    /// Decode the value, or `None` if it does not fit in an `i64`.
+    pub fn to_i64(&self) -> Option<i64> {
+        self.to_i128().and_then(|v| i64::try_from(v).ok())
+    }
+
+    /// Convert to an `N`-trit word, or `None` if the value does not fit.
+    pub fn to_tryte<const N: usize>(&self) -> Option<TryteN<N>> {
+        if self.trits.len() > N {
+            return None;
+        }
+        let mut trits = [Trit::O; N];
+        trits[..self.trits.len()].copy_from_slice(&self.trits);
+        Some(TryteN::from_trits(trits))
+    }
+
+    /// Multiply by 3^n, shifting the trits up.
+    pub fn mul_pow3(&self, n: usize) -> Self {
+        if self.is_zero() {
+            return Self::zero();
+        }
+        let mut trits = vec![Trit::O; n];
+        trits.extend_from_slice(&self.trits);
+        Self { trits }
+    }
+
+    /// Sum, with a ripple carry.
+    pub fn add(&self, other: &Self) -> Self {
+        let len = self.trits.len().max(other.trits.len());
+        let mut trits = Vec::with_capacity(len + 1);
+        let mut carry = Trit::O;
+        for i in 0..len {
+            let a = self.trits.get(i).copied().unwrap_or(Trit::O);
+            let b = other.trits.get(i).copied().unwrap_or(Trit::O);
+            let (sum, carry_out) = a.full_add(b, carry);
+            trits.push(sum);
+            carry = carry_out;
+        }
+        trits.push(carry);
+        Self::from_trits(trits)
+    }
+
+    /// Difference `self - other`.
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    /// Product, by the schoolbook method: one shifted copy of `self` per
+    /// non-zero trit of `other`, added or subtracted by that trit's sign.
+    pub fn mul(&self, other: &Self) -> Self {
+        let negated = self.neg();
+        let mut product = Self::zero();
+        for (shift, &trit) in other.trits.iter().enumerate() {
+            match trit {
+                Trit::P => product = product.add(&self.mul_pow3(shift)),
+                Trit::N => product = product.add(&negated.mul_pow3(shift)),
+                Trit::O => {}
+            }
+        }
+        product
+    }
+
+    /// Quotient truncated toward zero and remainder with the sign of
+    /// `self`, like Rust's `/` and `%`; `None` when `other` is zero.
+    pub fn div_rem(&self, other: &Self) -> Option<(Self, Self)> {
+        if other.is_zero() {
+            return None;
+        }
+        let divisor = other.abs();
+        let (mut quotient, mut remainder) = (Self::zero(), Self::zero());
+        let one = Self::from_i128(1);
+        // Long division of the magnitudes, bringing down one trit at a time.
+        // Trits may be negative, so the partial remainder is corrected in
+        // both directions; it stays in 0..divisor.
+        for &trit in self.abs().trits.iter().rev() {
+            remainder = remainder.mul_pow3(1).add(&Self::from_trits(vec![trit]));
+            quotient = quotient.mul_pow3(1);
+            while remainder >= divisor {
+                remainder = remainder.sub(&divisor);
+                quotient = quotient.add(&one);
+            }
+            while remainder.sign() == Trit::N {
+                remainder = remainder.add(&divisor);
+                quotient = quotient.sub(&one);
+            }
+        }
+        if self.sign() != other.sign() {
+            quotient = quotient.neg();
+        }
+        if self.sign() == Trit::N {
+            remainder = remainder.neg();
+        }
+        Some((quotient, remainder))
+    }
+
+    /// Quotient rounded to nearest, exact halves toward zero, and the
+    /// remainder `self - q*other`; `None` when `other` is zero. This agrees
+    /// with [`arith::divide`](super::arith::divide) except on exact halves,
+    /// which that can round either way.
+    pub fn div_rem_nearest(&self, other: &Self) -> Option<(Self, Self)> {
+        let (mut quotient, mut remainder) = self.div_rem(other)?;
+        if remainder.add(&remainder).abs() > other.abs() {
+            let one = Self::from_i128(1);
+            if remainder.sign() == other.sign() {
+                quotient = quotient.add(&one);
+                remainder = remainder.sub(other);
+            } else {
+                quotient = quotient.sub(&one);
+                remainder = remainder.add(other);
+            }
+        }
+        Some((quotient, remainder))
+    }
+
+    /// Parse a decimal integer of any length or a `0t` trit string with
+    /// N/O/P or -/0/+ digits.
+    pub fn parse(input: &str) -> Result<Self, ConvertError> {
+        let s = input.trim();
+        let invalid = || ConvertError::InvalidNumber(s.to_string());
+
+        if let Some(digits) = s.strip_prefix("0t").or_else(|| s.strip_prefix("0T")) {
+            let trits = digits.chars().rev().map(|c| match c {
+                'N' | 'n' | '-' => Ok(Trit::N),
+                'O' | 'o' | '0' => Ok(Trit::O),
+                'P' | 'p' | '+' => Ok(Trit::P),
+                _ => Err(invalid()),
+            }).collect::<Result<Vec<_>, _>>()?;
+            if trits.is_empty() {
+                return Err(invalid());
+            }
+            return Ok(Self::from_trits(trits));
+        }
+
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        if digits.is_empty() {
+            return Err(invalid());
+        }
+        let ten = Self::from_i128(10);
+        let value = digits.chars().try_fold(Self::zero(), |acc, c| {
+            let digit = c.to_digit(10).ok_or_else(invalid)?;
+            Ok(acc.mul(&ten).add(&Self::from_i128(digit as i128)))
+        })?;
+        Ok(if negative { value.neg() } else { value })
+    }
+
+    /// Trits most significant first, like `0t+0-` (`0t0` for zero).
+    pub fn to_trit_string(&self) -> String {
+        if self.is_zero() {
+            return "0t0".to_string();
+        }
+        let trits: String = self.trits.iter().rev().map(|t| t.to_string()).collect();
+        format!("0t{}", trits)
+    }
+}
+
+impl Ord for BigTrit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.sub(other).sign() {
+            Trit::N => Ordering::Less,
+            Trit::O => Ordering::Equal,
+            Trit::P => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for BigTrit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<i64> for BigTrit {
+    fn from(value: i64) -> Self {
+        Self::from_i128(value as i128)
+    }
+}
+
+impl From<i128> for BigTrit {
+    fn from(value: i128) -> Self {
+        Self::from_i128(value)
+    }
+}
+
+impl<const N: usize> From<TryteN<N>> for BigTrit {
+    fn from(word: TryteN<N>) -> Self {
+        Self::from_trits(word.trits().to_vec())
+    }
+}
+
+impl From<TritVec> for BigTrit {
+    fn from(trits: TritVec) -> Self {
+        Self::from_trits(trits.trits().to_vec())
+    }
+}
+
+impl FromStr for BigTrit {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for BigTrit {
+    /// The value in decimal.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(value) = self.to_i128() {
+            return write!(f, "{}", value);
+        }
+        // Peel off 18 decimal digits at a time
+        let chunk = Self::from_i128(10i128.pow(18));
+        let mut chunks = Vec::new();
+        let mut rest = self.abs();
+        while !rest.is_zero() {
+            let (quotient, remainder) = rest.div_rem(&chunk).expect("chunk is non-zero");
+            chunks.push(remainder.to_i64().expect("remainder is below 10^18"));
+            rest = quotient;
+        }
+        if self.sign() == Trit::N {
+            write!(f, "-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        write!(f, "{}", chunks.next().expect("value is non-zero"))?;
+        chunks.try_for_each(|chunk| write!(f, "{:018}", chunk))
+    }
+}
+
+impl fmt::Debug for BigTrit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BigTrit({} = {})", self.to_trit_string(), self)
+    }
+}
+
+impl std::ops::Neg for BigTrit {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        BigTrit::neg(&self)
+    }
+}
+
+// Operators for owned values and references, panicking on division by
+// zero like the integer types
+macro_rules! impl_big_op {
+    ($trait:ident, $method:ident, $body:expr) => {
+        impl std::ops::$trait<&BigTrit> for &BigTrit {
+            type Output = BigTrit;
+
+            fn $method(self, other: &BigTrit) -> BigTrit {
+                let f: fn(&BigTrit, &BigTrit) -> BigTrit = $body;
+                f(self, other)
+            }
+        }
+
+        impl std::ops::$trait for BigTrit {
+            type Output = BigTrit;
+
+            fn $method(self, other: BigTrit) -> BigTrit {
+                std::ops::$trait::$method(&self, &other)
+            }
+        }
+    };
+}
+
+impl_big_op!(Add, add, |a, b| a.add(b));
+impl_big_op!(Sub, sub, |a, b| a.sub(b));
+impl_big_op!(Mul, mul, |a, b| a.mul(b));
+impl_big_op!(Div, div, |a, b| a.div_rem(b).expect("division by zero").0);
+impl_big_op!(Rem, rem, |a, b| a.div_rem(b).expect("division by zero").1);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ternary::arith::divide;
+    use crate::Word18;
+
+    #[test]
+    fn test_matches_i128() {
+        let values = [0, 1, -1, 2, -5, 13, 100, -9_841, 193_710_244, -987_654_321_987, i64::MAX as i128];
+        for &a in &values {
+            let big_a = BigTrit::from_i128(a);
+            assert_eq!(big_a.to_i128(), Some(a));
+            assert_eq!(BigTrit::parse(&a.to_string()), Ok(big_a.clone()));
+            assert_eq!(BigTrit::parse(&big_a.to_trit_string()), Ok(big_a.clone()));
+            for &b in &values {
+                let big_b = BigTrit::from_i128(b);
+                assert_eq!((&big_a + &big_b).to_i128(), Some(a + b));
+                assert_eq!((&big_a - &big_b).to_i128(), Some(a - b));
+                assert_eq!((&big_a * &big_b).to_i128(), Some(a * b));
+                assert_eq!(big_a.cmp(&big_b), a.cmp(&b));
+                if b != 0 {
+                    let (q, r) = big_a.div_rem(&big_b).unwrap();
+                    assert_eq!((q.to_i128(), r.to_i128()), (Some(a / b), Some(a % b)), "{} / {}", a, b);
+                }
+            }
+        }
+        for value in [i128::MIN, i128::MAX] {
+            assert_eq!(BigTrit::from_i128(value).to_i128(), Some(value));
+            assert_eq!(BigTrit::from_i128(value).to_string(), value.to_string());
+        }
+        assert!(BigTrit::from_i128(1).div_rem(&BigTrit::zero()).is_none());
+        assert!(BigTrit::parse("12a").is_err());
+    }
+
+    #[test]
+    fn test_beyond_i128_and_word18_reference() {
+        let text = "-123456789012345678901234567890123456789012345678901234567890";
+        let big = BigTrit::parse(text).unwrap();
+        assert_eq!(big.to_string(), text);
+        assert_eq!(big.to_i128(), None);
+        assert_eq!(&(&big * &big) / &big, big);
+        assert_eq!(big.to_tryte::<18>(), None);
+        assert_eq!(BigTrit::from(-42i64).to_tryte::<9>(), Some(crate::Tryte9::from_i32(-42)));
+
+        // Word18 division agrees with the exact reference
+        for (a, b) in [(100, 7), (-100, 7), (3, 2), (-3, 2), (193_710_244, -3)] {
+            let (q, r) = divide(&Word18::from_i64(a), &Word18::from_i64(b)).unwrap();
+            let (exact_q, exact_r) = BigTrit::from(a).div_rem_nearest(&BigTrit::from(b)).unwrap();
+            assert_eq!((BigTrit::from(q), BigTrit::from(r)), (exact_q, exact_r), "{} / {}", a, b);
+        }
+    }
+}
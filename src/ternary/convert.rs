@@ -0,0 +1,450 @@
+//! Conversions between number notations.
+//!
+//! A value can be written in several ways when working with the Setun:
+//! - decimal (`42`, `-17`)
+//! - balanced ternary with N/O/P digits (`0tPNNNO`)
+//! - a trit pattern with `+`/`0`/`-` digits (`0t+---0`)
+//! - balanced base 9 (`0n1ZZ`), pairing trits into digits -4..+4
+//! - balanced base 27 (`0k1DZ`), grouping three trits into digits -13..+13
+//!
+//! Balanced base-9 digits -1..-4 are written `Z`, `Y`, `X`, `W`. Base 27,
+//! in which Setun operators wrote words with letter codes, continues the
+//! same way: digits 1..9 are themselves, 10..13 are `A` to `D`, and
+//! -1..-13 run backwards from `Z` to `N`, so a base-9 digit keeps its
+//! letter.
+//!
+//! A [`Word18`] can also be read as a fixed-point number with some of its
+//! low trits after the radix point, i.e. the integer value divided by
+//! 3^k. [`format_fixed`] writes such a value as an exact decimal; since
+//! no power of 3 divides a power of 10, the fraction always repeats, and
+//! the repeating block is written in parentheses (`1/3` is `0.(3)`).
+//! [`parse_fixed`] reads that form back, as well as ordinary decimals.
+
+use crate::ternary::{Trit, Tryte9, Word18};
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use thiserror::Error;
+
+/// Word width to convert for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Width {
+    /// 9 trits (memory cells, instructions).
+    Tryte9,
+    /// 18 trits (accumulator and R register).
+    Word18,
+}
+
+impl Width {
+    /// Number of trits.
+    pub fn trits(self) -> usize {
+        match self {
+            Width::Tryte9 => Tryte9::WIDTH,
+            Width::Word18 => Word18::WIDTH,
+        }
+    }
+    
+    /// Largest representable magnitude.
+    pub fn max(self) -> i64 {
+        match self {
+            Width::Tryte9 => Tryte9::MAX as i64,
+            Width::Word18 => Word18::MAX,
+        }
+    }
+    
+    /// Look up a width by trit count (9 or 18).
+    pub fn from_trits(trits: usize) -> Option<Self> {
+        match trits {
+            9 => Some(Width::Tryte9),
+            18 => Some(Width::Word18),
+            _ => None,
+        }
+    }
+}
+
+/// One value rendered in every supported notation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conversion {
+    /// Width the value was rendered for.
+    pub width: Width,
+    /// Decimal value.
+    pub decimal: i64,
+    /// Balanced ternary with N/O/P digits, `0t`-prefixed.
+    pub ternary: String,
+    /// Trit pattern with +/0/- digits.
+    pub trits: String,
+    /// Balanced base 9, `0n`-prefixed.
+    pub base9: String,
+    /// Balanced base 27, `0k`-prefixed.
+    pub base27: String,
+}
+
+impl fmt::Display for Conversion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.decimal)?;
+        writeln!(f, "  ternary: {}", self.ternary)?;
+        writeln!(f, "  trits:   {}", self.trits)?;
+        writeln!(f, "  base-9:  {}", self.base9)?;
+        write!(f, "  base-27: {}", self.base27)
+    }
+}
+
+/// Parse a value in any supported notation and render it for `width`.
+pub fn convert(input: &str, width: Width) -> Result<Conversion, ConvertError> {
+    let value = parse_number(input)?;
+    if value.unsigned_abs() > width.max() as u64 {
+        return Err(ConvertError::OutOfRange { value, trits: width.trits() });
+    }
+    
+    let trits = to_trits(value, width.trits());
+    Ok(Conversion {
+        width,
+        decimal: value,
+        ternary: format!("0t{}", trits.iter().rev().map(|t| format!("{:?}", t)).collect::<String>()),
+        trits: trits.iter().rev().map(|t| t.to_string()).collect(),
+        base9: format_base9(value, width.trits()),
+        base27: format_base27(value, width.trits()),
+    })
+}
+
+/// Parse a number written in decimal, `0t` ternary, `0n` balanced base 9
+/// or `0k` balanced base 27.
+///
+/// `i64::MIN` is rejected, so every value returned can be negated.
+pub fn parse_number(input: &str) -> Result<i64, ConvertError> {
+    let s = input.trim();
+    let invalid = || ConvertError::InvalidNumber(s.to_string());
+    
+    if let Some(digits) = s.strip_prefix("0t").or_else(|| s.strip_prefix("0T")) {
+        if digits.is_empty() || digits.len() > 40 {
+            return Err(invalid());
+        }
+        return digits.chars().try_fold(0i64, |acc, c| {
+            let trit = match c {
+                'N' | 'n' | '-' => Trit::N,
+                'O' | 'o' | '0' => Trit::O,
+                'P' | 'p' | '+' => Trit::P,
+                _ => return Err(invalid()),
+            };
+            acc.checked_mul(3).and_then(|v| v.checked_add(trit.to_i8() as i64)).ok_or_else(invalid)
+        });
+    }
+    
+    if let Some(digits) = s.strip_prefix("0n").or_else(|| s.strip_prefix("0N")) {
+        if digits.is_empty() || digits.len() > 20 {
+            return Err(invalid());
+        }
+        return digits.chars().try_fold(0i64, |acc, c| {
+            let digit = base9_digit_value(c).ok_or_else(invalid)?;
+            acc.checked_mul(9).and_then(|v| v.checked_add(digit)).ok_or_else(invalid)
+        });
+    }
+    
+    if let Some(digits) = s.strip_prefix("0k").or_else(|| s.strip_prefix("0K")) {
+        return parse_base27_digits(digits).ok_or_else(invalid);
+    }
+    
+    s.parse::<i64>().ok().filter(|&value| value != i64::MIN).ok_or_else(invalid)
+}
+
+/// Balanced ternary digits of `value`, least significant first.
+///
+/// Digits beyond `width` are dropped; check the range first.
+pub fn to_trits(mut value: i64, width: usize) -> Vec<Trit> {
+    let mut trits = Vec::with_capacity(width);
+    for _ in 0..width {
+        let trit = match value.rem_euclid(3) {
+            0 => Trit::O,
+            1 => Trit::P,
+            _ => Trit::N,
+        };
+        value = (value - trit.to_i8() as i64) / 3;
+        trits.push(trit);
+    }
+    trits
+}
+
+/// Render `value` in balanced base 9 using `width` trits (rounded up to pairs).
+pub fn format_base9(value: i64, width: usize) -> String {
+    let trits = to_trits(value, width + width % 2);
+    let digits: String = trits.chunks(2).rev()
+        .map(|pair| base9_digit_char(pair[0].to_i8() + 3 * pair[1].to_i8()))
+        .collect();
+    format!("0n{}", digits)
+}
+
+/// Render `value` in balanced base 27 using `width` trits (rounded up to triples).
+pub fn format_base27(value: i64, width: usize) -> String {
+    format!("0k{}", base27_digits(&to_trits(value, width)))
+}
+
+/// Base-27 digits of `trits` (least significant first), most significant
+/// first, padding the top digit with zero trits.
+pub(crate) fn base27_digits(trits: &[Trit]) -> String {
+    trits.chunks(3).rev()
+        .map(|group| {
+            let digit = group.iter().rev().fold(0, |acc, t| acc * 3 + t.to_i8());
+            base27_digit_char(digit)
+        })
+        .collect()
+}
+
+/// The value of base-27 `digits` without a prefix, or `None` if they are
+/// empty, contain something other than a digit or overflow an `i64`.
+pub(crate) fn parse_base27_digits(digits: &str) -> Option<i64> {
+    if digits.is_empty() || digits.len() > 13 {
+        return None;
+    }
+    digits.chars().try_fold(0i64, |acc, c| acc.checked_mul(27)?.checked_add(base27_digit_value(c)?))
+}
+
+fn base27_digit_char(digit: i8) -> char {
+    match digit {
+        -13..=-1 => (b'Z' - (-1 - digit) as u8) as char,
+        0..=9 => (b'0' + digit as u8) as char,
+        10..=13 => (b'A' + (digit - 10) as u8) as char,
+        _ => unreachable!(),
+    }
+}
+
+fn base27_digit_value(c: char) -> Option<i64> {
+    match c.to_ascii_uppercase() {
+        c @ 'N'..='Z' => Some(c as i64 - 'Z' as i64 - 1),
+        c @ '0'..='9' => Some(c as i64 - '0' as i64),
+        c @ 'A'..='D' => Some(c as i64 - 'A' as i64 + 10),
+        _ => None,
+    }
+}
+
+fn base9_digit_char(digit: i8) -> char {
+    match digit {
+        -4 => 'W',
+        -3 => 'X',
+        -2 => 'Y',
+        -1 => 'Z',
+        0..=4 => (b'0' + digit as u8) as char,
+        _ => unreachable!(),
+    }
+}
+
+fn base9_digit_value(c: char) -> Option<i64> {
+    match c.to_ascii_uppercase() {
+        'W' => Some(-4),
+        'X' => Some(-3),
+        'Y' => Some(-2),
+        'Z' => Some(-1),
+        '0'..='4' => Some(c as i64 - '0' as i64),
+        _ => None,
+    }
+}
+
+/// Render `word` with its `frac_trits` low trits after the radix point as
+/// an exact decimal, writing the repeating digits in parentheses:
+/// `-4` with two fraction trits is `-0.(4)`, i.e. -4/9.
+///
+/// The repeating block of 1/3^k has 3^(k-2) digits, so this is meant for
+/// short fractions; use [`format_fixed_digits`] for a bounded length.
+///
+/// # Panics
+/// Panics if `frac_trits` is more than 18.
+pub fn format_fixed(word: &Word18, frac_trits: usize) -> String {
+    let (negative, whole, remainder, scale) = split_fixed(word, frac_trits);
+    let mut out = format!("{}{}", if negative { "-" } else { "" }, whole);
+    if remainder != 0 {
+        // 10 is invertible mod 3^k, so the expansion is purely periodic:
+        // it repeats as soon as the remainder comes back around
+        out.push_str(".(");
+        let mut r = remainder;
+        loop {
+            r *= 10;
+            out.push((b'0' + (r / scale) as u8) as char);
+            r %= scale;
+            if r == remainder {
+                break;
+            }
+        }
+        out.push(')');
+    }
+    out
+}
+
+/// Render `word` with `frac_trits` fraction trits rounded to `digits`
+/// decimal places (halves away from zero).
+///
+/// # Panics
+/// Panics if `frac_trits` is more than 18.
+pub fn format_fixed_digits(word: &Word18, frac_trits: usize, digits: usize) -> String {
+    let (negative, mut whole, remainder, scale) = split_fixed(word, frac_trits);
+    let mut fraction: Vec<u8> = Vec::with_capacity(digits);
+    let mut r = remainder;
+    for _ in 0..digits {
+        r *= 10;
+        fraction.push((r / scale) as u8);
+        r %= scale;
+    }
+    if 2 * r >= scale {
+        // Propagate the rounding carry through the digits into the whole part
+        let mut carry = true;
+        for digit in fraction.iter_mut().rev() {
+            *digit += 1;
+            carry = *digit == 10;
+            if !carry {
+                break;
+            }
+            *digit = 0;
+        }
+        whole += carry as u64;
+    }
+    let negative = negative && (whole != 0 || fraction.iter().any(|&d| d != 0));
+    let mut out = format!("{}{}", if negative { "-" } else { "" }, whole);
+    if digits > 0 {
+        out.push('.');
+        out.extend(fraction.iter().map(|&d| (b'0' + d) as char));
+    }
+    out
+}
+
+/// Sign, whole part, fraction numerator and denominator 3^k of a fixed-point word.
+fn split_fixed(word: &Word18, frac_trits: usize) -> (bool, u64, u64, u64) {
+    assert!(frac_trits <= Word18::WIDTH, "a Word18 has only {} trits", Word18::WIDTH);
+    let value = word.to_i64();
+    let scale = 3u64.pow(frac_trits as u32);
+    let magnitude = value.unsigned_abs();
+    (value < 0, magnitude / scale, magnitude % scale, scale)
+}
+
+/// Parse a decimal such as `-2.75` or `0.(4)` (repeating digits in
+/// parentheses) into a `Word18` with `frac_trits` fraction trits, rounding
+/// to the nearest representable value. Exact renderings from
+/// [`format_fixed`] parse back to the same word.
+///
+/// # Panics
+/// Panics if `frac_trits` is more than 18.
+pub fn parse_fixed(input: &str, frac_trits: usize) -> Result<Word18, ConvertError> {
+    assert!(frac_trits <= Word18::WIDTH, "a Word18 has only {} trits", Word18::WIDTH);
+    let s = input.trim();
+    let invalid = || ConvertError::InvalidNumber(s.to_string());
+    let (negative, body) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (whole, fraction) = body.split_once('.').unwrap_or((body, ""));
+    let (fixed, repeating) = match fraction.split_once('(') {
+        Some((fixed, rest)) => (fixed, rest.strip_suffix(')').filter(|r| !r.is_empty()).ok_or_else(invalid)?),
+        None => (fraction, ""),
+    };
+    let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if whole.is_empty() || whole.len() > 9 || !all_digits(whole) || !all_digits(fixed) || !all_digits(repeating) {
+        return Err(invalid());
+    }
+    
+    // Enough fraction digits to round correctly at 3^-18 resolution, while
+    // whole * 10^FRACTION_DIGITS * 3^18 still fits in a u128
+    const FRACTION_DIGITS: usize = 20;
+    let digits = fixed.bytes().chain(repeating.bytes().cycle().take(if repeating.is_empty() { 0 } else { FRACTION_DIGITS }));
+    let mut numerator: u128 = whole.parse().map_err(|_| invalid())?;
+    for digit in digits.chain(std::iter::repeat(b'0')).take(FRACTION_DIGITS) {
+        numerator = numerator * 10 + (digit - b'0') as u128;
+    }
+    let denominator = 10u128.pow(FRACTION_DIGITS as u32);
+    let scaled = numerator * 3u128.pow(frac_trits as u32);
+    let magnitude = (scaled + denominator / 2) / denominator;
+    
+    let value = i64::try_from(magnitude).unwrap_or(i64::MAX);
+    let value = if negative { -value } else { value };
+    if value.abs() > Word18::MAX {
+        return Err(ConvertError::OutOfRange { value, trits: Word18::WIDTH });
+    }
+    Ok(Word18::from_i64(value))
+}
+
+/// Errors that can occur when converting numbers.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConvertError {
+    #[error("invalid number: {0}")]
+    InvalidNumber(String),
+    
+    #[error("value {value} does not fit in {trits} trits")]
+    OutOfRange { value: i64, trits: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_convert_matches_word_types() {
+        let c = convert("42", Width::Tryte9).unwrap();
+        assert_eq!(c.ternary, Tryte9::from_i32(42).to_string());
+        assert_eq!(c.trits, "0000+---0");
+        
+        let c = convert("-123456", Width::Word18).unwrap();
+        assert_eq!(c.ternary, Word18::from_i64(-123456).to_string());
+    }
+    
+    #[test]
+    fn test_parse_notations_roundtrip() {
+        for value in [-9841i64, -100, -1, 0, 1, 42, 9841] {
+            let c = convert(&value.to_string(), Width::Tryte9).unwrap();
+            assert_eq!(parse_number(&c.ternary).unwrap(), value);
+            assert_eq!(parse_number(&format!("0t{}", c.trits)).unwrap(), value);
+            assert_eq!(parse_number(&c.base9).unwrap(), value);
+        }
+        
+        assert_eq!(parse_number("-9223372036854775807"), Ok(-i64::MAX));
+        assert!(parse_number("-9223372036854775808").is_err());
+        assert!(convert("9223372036854775807", Width::Word18).is_err());
+    }
+    
+    #[test]
+    fn test_base9_digits() {
+        assert_eq!(format_base9(4, 2), "0n4");
+        assert_eq!(format_base9(-4, 2), "0nW");
+        assert_eq!(format_base9(5, 4), "0n1W");
+        assert_eq!(parse_number("0n1Y").unwrap(), 7);
+        
+        assert_eq!(format_base27(13, 9), "0k00D");
+        assert_eq!(format_base27(-13, 9), "0k00N");
+        assert_eq!(format_base27(-1, 4), "0k0Z");
+        assert_eq!(format_base27(40, 9), "0k01D");
+        for value in [0, 1, -4, 9, 10, 14, -9841, 9841] {
+            assert_eq!(parse_number(&format_base27(value, 9)), Ok(value));
+        }
+        assert_eq!(parse_number("0kw"), Ok(-4));
+        assert!(parse_number("0kE").is_err());
+        assert!(parse_number("0k").is_err());
+    }
+    
+    #[test]
+    fn test_convert_errors() {
+        assert!(matches!(convert("9842", Width::Tryte9), Err(ConvertError::OutOfRange { .. })));
+        assert!(convert("9842", Width::Word18).is_ok());
+        assert!(matches!(parse_number("0tPQ"), Err(ConvertError::InvalidNumber(_))));
+        assert!(parse_number("abc").is_err());
+    }
+    
+    #[test]
+    fn test_fixed_point_decimals() {
+        let word = |v: i64| Word18::from_i64(v);
+        assert_eq!(format_fixed(&word(1), 1), "0.(3)");
+        assert_eq!(format_fixed(&word(-4), 2), "-0.(4)");
+        assert_eq!(format_fixed(&word(28), 3), "1.(037)");
+        assert_eq!(format_fixed(&word(-54), 3), "-2");
+        assert_eq!(format_fixed_digits(&word(2), 1, 3), "0.667");
+        assert_eq!(format_fixed_digits(&word(-1), 4, 2), "-0.01");
+        assert_eq!(format_fixed_digits(&word(-1), 9, 2), "0.00");
+        assert_eq!(format_fixed_digits(&word(26), 3, 2), "0.96");
+        assert_eq!(format_fixed_digits(&word(80), 4, 1), "1.0");
+        
+        for (value, frac_trits) in [(1, 1), (-4, 2), (28, 3), (12345, 6), (-9841, 9), (Word18::MAX, 18)] {
+            let text = format_fixed_digits(&word(value), frac_trits, 12);
+            assert_eq!(parse_fixed(&text, frac_trits), Ok(word(value)), "{}", text);
+        }
+        for (value, frac_trits) in [(1, 1), (-4, 2), (28, 3), (-9841, 9)] {
+            assert_eq!(parse_fixed(&format_fixed(&word(value), frac_trits), frac_trits), Ok(word(value)));
+        }
+        assert_eq!(parse_fixed("2.75", 2), Ok(word(25)));
+        assert!(matches!(parse_fixed("1.(", 2), Err(ConvertError::InvalidNumber(_))));
+        assert!(matches!(parse_fixed("1e5", 2), Err(ConvertError::InvalidNumber(_))));
+        assert!(matches!(parse_fixed("193710245", 0), Err(ConvertError::OutOfRange { .. })));
+    }
+}
@@ -0,0 +1,176 @@
+//! Words as the Setun's fixed-point fractions.
+//!
+//! The original machine read an 18-trit word as a fraction with the point
+//! right after the top trit: the value is the integer divided by 3^17, so
+//! it lies within about ±1.5 and steps by 3^-17 (about 7.7e-9). [`Fixed18`]
+//! gives a [`Word18`] that reading. Addition and subtraction are the
+//! integer ones, while [`Fixed18::mul`] and [`Fixed18::div`] rescale their
+//! result, rounding to the nearest step as dropping balanced trits does:
+//!
+//! ```
+//! use setun::ternary::Fixed18;
+//!
+//! let third = Fixed18::from_f64(1.0 / 3.0).unwrap();
+//! let ninth = third.mul(&third).unwrap();
+//! assert_eq!(format!("{:.6}", ninth), "0.111111");
+//! assert_eq!(ninth.div(&third), Some(third));
+//!
+//! // 1/2 has no finite ternary expansion, so it rounds to the nearest step
+//! let half: Fixed18 = "0.5".parse().unwrap();
+//! assert_eq!(half.to_string(), "0.500000004");
+//! assert_eq!(half.mul(&Fixed18::ONE), Some(half));
+//! ```
+
+use crate::ternary::arith::{divide_wide, multiply};
+use crate::ternary::convert::{format_fixed_digits, parse_fixed};
+use crate::ternary::{ConvertError, Trit, Word18, Word36};
+use std::fmt;
+use std::str::FromStr;
+
+/// A [`Word18`] read as a fraction with 17 trits after the point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Fixed18(Word18);
+
+impl Fixed18 {
+    /// Trits after the point.
+    pub const FRAC_TRITS: usize = 17;
+
+    /// 0.
+    pub const ZERO: Fixed18 = Fixed18(Word18::zero());
+
+    /// 1: the top trit P and the rest O.
+    pub const ONE: Fixed18 = Fixed18(Word18::from_i64_const(3i64.pow(Self::FRAC_TRITS as u32)));
+
+    /// The word `word` read as a fraction.
+    pub const fn from_word(word: Word18) -> Self {
+        Fixed18(word)
+    }
+
+    /// The underlying word.
+    pub const fn word(&self) -> Word18 {
+        self.0
+    }
+
+    /// The nearest fraction to `x`, or `None` if `x` is not finite or is
+    /// outside the range.
+    pub fn from_f64(x: f64) -> Option<Self> {
+        Word18::from_f64_scaled(x, Self::FRAC_TRITS).map(Fixed18)
+    }
+
+    /// The value as an `f64`.
+    pub fn to_f64(&self) -> f64 {
+        self.0.to_f64_scaled(Self::FRAC_TRITS)
+    }
+
+    /// Negate.
+    pub fn neg(&self) -> Self {
+        Fixed18(self.0.neg())
+    }
+
+    /// `self + other`, or `None` on overflow.
+    pub fn add(&self, other: &Self) -> Option<Self> {
+        let (sum, carry) = self.0.add_with_carry(&other.0);
+        carry.is_zero().then_some(Fixed18(sum))
+    }
+
+    /// `self - other`, or `None` on overflow.
+    pub fn sub(&self, other: &Self) -> Option<Self> {
+        self.add(&other.neg())
+    }
+
+    /// `self * other`, rounded to the nearest step, or `None` on overflow.
+    ///
+    /// The 36-trit product has 34 trits after the point; dropping the low
+    /// 17 leaves the result, and any non-zero trit above those is overflow.
+    pub fn mul(&self, other: &Self) -> Option<Self> {
+        let (low, high) = multiply(&self.0, &other.0);
+        let product = Word36::from_halves(low, high);
+        let trits = product.trits();
+        let top = Self::FRAC_TRITS + Word18::WIDTH;
+        if trits[top..].iter().any(|t| *t != Trit::O) {
+            return None;
+        }
+        let mut result = [Trit::O; 18];
+        result.copy_from_slice(&trits[Self::FRAC_TRITS..top]);
+        Some(Fixed18(Word18::from_trits(result)))
+    }
+
+    /// `self / other`, rounded to the nearest step, or `None` if `other`
+    /// is zero or the quotient is out of range.
+    ///
+    /// The dividend is widened with 17 more fraction trits, so the
+    /// quotient of [`divide_wide`] comes out at the right scale.
+    pub fn div(&self, other: &Self) -> Option<Self> {
+        let mut trits = [Trit::O; 36];
+        trits[Self::FRAC_TRITS..Self::FRAC_TRITS + Word18::WIDTH].copy_from_slice(self.0.trits());
+        let (quotient, _) = divide_wide(&Word36::from_trits(trits), &other.0)?;
+        (quotient.high().is_zero() && quotient.to_i64().abs() <= Word18::MAX).then(|| Fixed18(quotient.low()))
+    }
+}
+
+impl From<Word18> for Fixed18 {
+    fn from(word: Word18) -> Self {
+        Fixed18(word)
+    }
+}
+
+impl From<Fixed18> for Word18 {
+    fn from(value: Fixed18) -> Self {
+        value.0
+    }
+}
+
+/// Decimal, with 9 places unless a precision is given: enough to tell
+/// every pair of fractions apart and to parse back to the same word.
+impl fmt::Display for Fixed18 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Not `pad`, which would cut the digits to the precision
+        let text = format_fixed_digits(&self.0, Self::FRAC_TRITS, f.precision().unwrap_or(9));
+        match f.width() {
+            Some(width) => write!(f, "{:>width$}", text),
+            None => f.write_str(&text),
+        }
+    }
+}
+
+/// Parse a decimal such as `-0.25` or `0.(3)`, rounding to the nearest step.
+impl FromStr for Fixed18 {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_fixed(s, Self::FRAC_TRITS).map(Fixed18)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fraction_arithmetic() {
+        let f = |x: f64| Fixed18::from_f64(x).unwrap();
+        let step = 1.0 / 3f64.powi(17);
+
+        assert_eq!(Fixed18::ONE.to_f64(), 1.0);
+        assert_eq!(Fixed18::from_word(Word18::from_i64(Word18::MAX)).to_string(), "1.499999996");
+        assert_eq!(Fixed18::from_f64(1.6), None);
+
+        for (x, y) in [(0.5, 0.5), (-0.6, 0.75), (1.0 / 9.0, -1.0 / 3.0), (1.4, -1.0), (0.001, 0.002)] {
+            let product = f(x).mul(&f(y)).unwrap().to_f64();
+            assert!((product - x * y).abs() <= 2.0 * step, "{} * {}", x, y);
+            let quotient = f(x).div(&f(y)).unwrap().to_f64();
+            assert!((quotient - x / y).abs() <= quotient.abs() * 1e-7 + step, "{} / {}", x, y);
+        }
+        assert_eq!(f(1.2).mul(&f(1.3)), None);
+        assert_eq!(f(1.2).div(&f(0.5)), None);
+        assert_eq!(f(1.2).div(&Fixed18::ZERO), None);
+        assert_eq!(f(1.2).add(&f(0.5)), None);
+
+        // Nine places parse back to the same word
+        for word in [1, -1, 12_345_678, Word18::MAX, Word18::MIN] {
+            let value = Fixed18::from_word(Word18::from_i64(word));
+            assert_eq!(value.to_string().parse::<Fixed18>(), Ok(value));
+        }
+        assert_eq!(format!("{:>8.3}", f(-0.25)), "  -0.250");
+    }
+}
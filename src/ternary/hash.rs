@@ -0,0 +1,137 @@
+//! Ternary checksums and digests.
+//!
+//! Two functions for telling ternary data apart without going through
+//! bytes:
+//!
+//! - [`checksum`] folds words into one with `h := 10h + w`, keeping the
+//!   low nine trits. Multiplying by 10 (`0t+0+`) is a shift and an add,
+//!   so a Setun program computes the same value in four instructions per
+//!   word (the bundled `checksum` example does). It catches any single
+//!   changed word and most swaps, but it is not meant to resist
+//!   deliberate collisions.
+//! - [`digest`] mixes every trit into an 18-trit state FNV-style: the
+//!   input trit is summed into the lowest state trit without carry, then
+//!   the state is multiplied by a large constant, keeping 18 trits. Small
+//!   input changes spread over the whole word, which makes it suitable
+//!   for comparing machine states and images.
+//!
+//! ```
+//! use setun::ternary::hash::{checksum, digest_words};
+//! use setun::Tryte9;
+//!
+//! let words = [Tryte9::from_i32(1), Tryte9::from_i32(2), Tryte9::from_i32(3)];
+//! assert_eq!(checksum(&words).to_i32(), 123);
+//! assert_ne!(digest_words(&words), digest_words(&words[..2]));
+//! ```
+
+use crate::ternary::arith::{add_tryte9, multiply, shift_left_tryte9};
+use crate::ternary::{Trit, Tryte9, Word18};
+
+/// Starting state of [`Digest`].
+pub const DIGEST_OFFSET: i64 = 123_456_789;
+
+/// Multiplier applied after each trit in [`Digest`]; not a multiple of 3,
+/// so no state trit is ever shifted out for good.
+pub const DIGEST_PRIME: i64 = 16_777_619;
+
+/// [`DIGEST_PRIME`] as a word.
+const PRIME: Word18 = Word18::from_i64_const(DIGEST_PRIME);
+
+/// Fold `words` into one word: `h := 10h + w` from `h = 0`, keeping the
+/// low nine trits.
+pub fn checksum(words: &[Tryte9]) -> Tryte9 {
+    words.iter().fold(Tryte9::zero(), |h, w| {
+        let (tenfold, _) = add_tryte9(&shift_left_tryte9(&h, 2), &h);
+        add_tryte9(&tenfold, w).0
+    })
+}
+
+/// Incremental 18-trit digest of a trit sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Digest {
+    state: Word18,
+}
+
+impl Digest {
+    /// Start a digest.
+    pub fn new() -> Self {
+        Self { state: Word18::from_i64(DIGEST_OFFSET) }
+    }
+
+    /// Mix in one trit.
+    pub fn update_trit(&mut self, trit: Trit) {
+        self.state.set(0, self.state.get(0).sum(trit));
+        self.state = multiply(&self.state, &PRIME).0;
+    }
+
+    /// Mix in trits, least significant first.
+    pub fn update(&mut self, trits: &[Trit]) {
+        for &trit in trits {
+            self.update_trit(trit);
+        }
+    }
+
+    /// Mix in every trit of each word.
+    pub fn update_words(&mut self, words: &[Tryte9]) {
+        for word in words {
+            self.update(word.trits());
+        }
+    }
+
+    /// The digest of everything mixed in so far.
+    pub fn finish(&self) -> Word18 {
+        self.state
+    }
+}
+
+impl Default for Digest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Digest of a trit sequence.
+pub fn digest(trits: &[Trit]) -> Word18 {
+    let mut digest = Digest::new();
+    digest.update(trits);
+    digest.finish()
+}
+
+/// Digest of the trits of `words`.
+pub fn digest_words(words: &[Tryte9]) -> Word18 {
+    let mut digest = Digest::new();
+    digest.update_words(words);
+    digest.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_wraps_like_the_machine() {
+        let words: Vec<Tryte9> = [9841, -9841, 5000, 17].iter().map(|&v| Tryte9::from_i32(v)).collect();
+        // Reference: the same recurrence in integers, wrapped to ±9841
+        let expected = [9841i64, -9841, 5000, 17].iter().fold(0i64, |h, w| {
+            (10 * h + w + 9841).rem_euclid(19683) - 9841
+        });
+        assert_eq!(checksum(&words).to_i32() as i64, expected);
+        assert_eq!(checksum(&[]), Tryte9::zero());
+    }
+
+    #[test]
+    fn test_digest_separates_near_inputs() {
+        let base: Vec<Tryte9> = (0..20).map(Tryte9::from_i32).collect();
+        let reference = digest_words(&base);
+        for i in 0..base.len() {
+            let mut changed = base.clone();
+            changed[i] = Tryte9::from_i32(changed[i].to_i32() + 1);
+            assert_ne!(digest_words(&changed), reference, "word {}", i);
+        }
+        let mut swapped = base.clone();
+        swapped.swap(3, 4);
+        assert_ne!(digest_words(&swapped), reference);
+        // Trailing zeros still count
+        assert_ne!(digest(&[Trit::P]), digest(&[Trit::P, Trit::O]));
+    }
+}
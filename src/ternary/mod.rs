@@ -4,13 +4,29 @@
 //! - [`Trit`] - A single balanced ternary digit (-1, 0, +1)
 //! - [`Tryte9`] - A 9-trit word (used for memory cells and instructions)
 //! - [`Word18`] - An 18-trit word (used for the accumulator and computation)
+//! - [`TryteN`] - The fixed-width word all of these are built on, for any width
+//! - [`TritVec`] - A growable trit sequence for any other length
+//! - [`BigTrit`] - An integer of any size, for exact reference results
+//! - [`Fixed18`] - A word read as the Setun's fixed-point fraction
 
 mod trit;
 mod word;
+mod tryte;
 mod ops;
+mod vec;
+mod big;
+mod fixed;
+pub(crate) mod serial;
 pub mod arith;
+pub mod convert;
+pub mod hash;
 
 pub use trit::Trit;
-pub use word::{Tryte9, Word18};
+pub use word::{Tryte5, Tryte9, Word18, Word36};
+pub use tryte::TryteN;
 pub use ops::TritOps;
+pub use vec::TritVec;
+pub use big::BigTrit;
+pub use fixed::Fixed18;
 pub use arith::{add, subtract, multiply, negate};
+pub use convert::{convert, parse_number, Conversion, ConvertError, Width};
@@ -1,9 +1,12 @@
 //! Tritwise operations trait.
 //!
 //! Defines common operations that can be applied to all ternary types:
-//! trits, trytes, and words.
+//! trits, trytes, and words. Binary operations apply trit by trit; shifts
+//! and rotations move trits towards the most significant end (left) or
+//! the least significant end (right). A single trit shifts to O and
+//! rotates to itself.
 
-use crate::ternary::Trit;
+use crate::ternary::{Trit, TryteN};
 
 /// Trait for types that support tritwise operations.
 pub trait TritOps {
@@ -21,6 +24,27 @@ pub trait TritOps {
     
     /// Tritwise consensus.
     fn ternary_consensus(&self, other: &Self) -> Self::Output;
+    
+    /// Tritwise Kleene implication, max(¬a, b).
+    fn ternary_imp(&self, other: &Self) -> Self::Output;
+    
+    /// Tritwise equivalence (XNOR analogue), see [`Trit::equiv`].
+    fn ternary_equiv(&self, other: &Self) -> Self::Output;
+    
+    /// Tritwise exclusive max (XOR analogue), see [`Trit::xmax`].
+    fn ternary_xmax(&self, other: &Self) -> Self::Output;
+    
+    /// Shift `n` trits left, filling with O.
+    fn ternary_shl(&self, n: usize) -> Self::Output;
+    
+    /// Shift `n` trits right, filling with O.
+    fn ternary_shr(&self, n: usize) -> Self::Output;
+    
+    /// Rotate `n` trits left; trits leaving the top re-enter at the bottom.
+    fn ternary_rotl(&self, n: usize) -> Self::Output;
+    
+    /// Rotate `n` trits right; trits leaving the bottom re-enter at the top.
+    fn ternary_rotr(&self, n: usize) -> Self::Output;
 }
 
 impl TritOps for Trit {
@@ -45,49 +69,128 @@ impl TritOps for Trit {
     fn ternary_consensus(&self, other: &Self) -> Trit {
         self.consensus(*other)
     }
+    
+    #[inline]
+    fn ternary_imp(&self, other: &Self) -> Trit {
+        self.implies(*other)
+    }
+    
+    #[inline]
+    fn ternary_equiv(&self, other: &Self) -> Trit {
+        self.equiv(*other)
+    }
+    
+    #[inline]
+    fn ternary_xmax(&self, other: &Self) -> Trit {
+        self.xmax(*other)
+    }
+    
+    #[inline]
+    fn ternary_shl(&self, n: usize) -> Trit {
+        if n == 0 { *self } else { Trit::O }
+    }
+    
+    #[inline]
+    fn ternary_shr(&self, n: usize) -> Trit {
+        self.ternary_shl(n)
+    }
+    
+    #[inline]
+    fn ternary_rotl(&self, _n: usize) -> Trit {
+        *self
+    }
+    
+    #[inline]
+    fn ternary_rotr(&self, _n: usize) -> Trit {
+        *self
+    }
 }
 
-// Implement TritOps for Tryte9 and Word18 using a macro
-macro_rules! impl_trit_ops {
-    ($type:ty, $width:expr) => {
-        impl TritOps for $type {
-            type Output = Self;
-            
-            fn ternary_neg(&self) -> Self {
-                self.neg()
-            }
-            
-            fn ternary_min(&self, other: &Self) -> Self {
-                let mut result = *self;
-                for i in 0..$width {
-                    result.trits_mut()[i] = self.get(i).min(other.get(i));
-                }
-                result
-            }
-            
-            fn ternary_max(&self, other: &Self) -> Self {
-                let mut result = *self;
-                for i in 0..$width {
-                    result.trits_mut()[i] = self.get(i).max(other.get(i));
-                }
-                result
-            }
-            
-            fn ternary_consensus(&self, other: &Self) -> Self {
-                let mut result = *self;
-                for i in 0..$width {
-                    result.trits_mut()[i] = self.get(i).consensus(other.get(i));
-                }
-                result
-            }
+// Tritwise operations on every fixed-width word, Tryte9 and Word18 included
+impl<const N: usize> TritOps for TryteN<N> {
+    type Output = Self;
+    
+    fn ternary_neg(&self) -> Self {
+        self.neg()
+    }
+    
+    fn ternary_min(&self, other: &Self) -> Self {
+        let mut result = *self;
+        for i in 0..N {
+            result.trits_mut()[i] = self.get(i).min(other.get(i));
         }
-    };
+        result
+    }
+    
+    fn ternary_max(&self, other: &Self) -> Self {
+        let mut result = *self;
+        for i in 0..N {
+            result.trits_mut()[i] = self.get(i).max(other.get(i));
+        }
+        result
+    }
+    
+    fn ternary_consensus(&self, other: &Self) -> Self {
+        let mut result = *self;
+        for i in 0..N {
+            result.trits_mut()[i] = self.get(i).consensus(other.get(i));
+        }
+        result
+    }
+    
+    fn ternary_imp(&self, other: &Self) -> Self {
+        let mut result = *self;
+        for i in 0..N {
+            result.trits_mut()[i] = self.get(i).implies(other.get(i));
+        }
+        result
+    }
+    
+    fn ternary_equiv(&self, other: &Self) -> Self {
+        let mut result = *self;
+        for i in 0..N {
+            result.trits_mut()[i] = self.get(i).equiv(other.get(i));
+        }
+        result
+    }
+    
+    fn ternary_xmax(&self, other: &Self) -> Self {
+        let mut result = *self;
+        for i in 0..N {
+            result.trits_mut()[i] = self.get(i).xmax(other.get(i));
+        }
+        result
+    }
+    
+    fn ternary_shl(&self, n: usize) -> Self {
+        let mut result = *self;
+        for i in 0..N {
+            result.trits_mut()[i] = if i >= n { self.get(i - n) } else { Trit::O };
+        }
+        result
+    }
+    
+    fn ternary_shr(&self, n: usize) -> Self {
+        let mut result = *self;
+        for i in 0..N {
+            result.trits_mut()[i] = if n < N - i { self.get(i + n) } else { Trit::O };
+        }
+        result
+    }
+    
+    fn ternary_rotl(&self, n: usize) -> Self {
+        let mut result = *self;
+        result.trits_mut().rotate_right(n % N);
+        result
+    }
+    
+    fn ternary_rotr(&self, n: usize) -> Self {
+        let mut result = *self;
+        result.trits_mut().rotate_left(n % N);
+        result
+    }
 }
 
-use crate::ternary::{Tryte9, Word18};
-impl_trit_ops!(Tryte9, 9);
-impl_trit_ops!(Word18, 18);
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -127,4 +230,39 @@ mod tests {
         let _max = a.ternary_max(&b);
         let _cons = a.ternary_consensus(&b);
     }
+    
+    #[test]
+    fn test_logic_identities() {
+        let trits = [Trit::N, Trit::O, Trit::P];
+        for a in trits {
+            for b in trits {
+                // a → b equals ¬a ∨ b; equivalence is symmetric; xmax of equals is N
+                assert_eq!(a.ternary_imp(&b), a.ternary_neg().ternary_max(&b));
+                assert_eq!(a.ternary_equiv(&b), b.ternary_equiv(&a));
+                assert_eq!(a.ternary_xmax(&a), Trit::N);
+            }
+        }
+        assert_eq!(Trit::P.ternary_xmax(&Trit::N), Trit::P);
+        assert_eq!(Trit::O.ternary_imp(&Trit::O), Trit::O);
+    }
+    
+    #[test]
+    fn test_word_shifts_and_rotations() {
+        let a = Tryte9::from_i32(-5);
+        assert_eq!(a.ternary_shl(2).to_i32(), -45);
+        assert_eq!(a.ternary_shl(2).ternary_shr(2), a);
+        assert_eq!(a.ternary_shr(9), Tryte9::zero());
+        assert_eq!(a.ternary_shr(usize::MAX), Tryte9::zero());
+        assert_eq!(a.ternary_shl(usize::MAX), Tryte9::zero());
+        
+        // The top trit wraps around to the bottom
+        let top = Tryte9::from_i32(6561);
+        assert_eq!(top.ternary_rotl(1).to_i32(), 1);
+        assert_eq!(top.ternary_rotl(1).ternary_rotr(1), top);
+        assert_eq!(top.ternary_rotl(9), top);
+        
+        let w = Word18::from_i64(-123456);
+        assert_eq!(w.ternary_rotr(5).ternary_rotl(5), w);
+        assert_eq!(w.ternary_shl(1).to_i64(), -370368);
+    }
 }
@@ -0,0 +1,206 @@
+//! Serde representation of ternary words.
+//!
+//! In human-readable formats (JSON, TOML) a word is written as its trit
+//! string, `"0tOOOOPNNNO"`, rather than an array of trit variants. Binary
+//! formats get the packed integer value instead. Reading a human-readable
+//! format also accepts a plain integer and the older `{"trits": [...]}`
+//! form, so files written before the change still load:
+//!
+//! ```
+//! use setun::Tryte9;
+//!
+//! let word = Tryte9::from_i32(42);
+//! assert_eq!(serde_json::to_string(&word).unwrap(), r#""0tOOOOPNNNO""#);
+//! assert_eq!(serde_json::from_str::<Tryte9>("42").unwrap(), word);
+//! assert_eq!(serde_json::from_str::<Tryte9>(r#""0t+---0""#).unwrap(), word);
+//! ```
+//!
+//! A trit string may be shorter than the word; missing high trits are 0.
+
+use crate::ternary::{Trit, Tryte5, Tryte9, Word18};
+use serde::de::{self, DeserializeOwned, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A fixed-width word with the string/packed serde representation.
+pub(crate) trait SerdeWord: Sized {
+    /// Type name used in error messages.
+    const NAME: &'static str;
+    /// Number of trits.
+    const WIDTH: usize;
+    /// Integer type used by binary formats.
+    type Packed: Serialize + DeserializeOwned + TryFrom<i64> + Into<i64>;
+
+    /// The trits, least significant first.
+    fn trit_slice(&self) -> &[Trit];
+    /// Build from exactly the word's number of trits.
+    fn from_trit_slice(trits: &[Trit]) -> Self;
+    /// The word's value.
+    fn value(&self) -> i64;
+    /// Build from a value in range.
+    fn from_value(value: i64) -> Option<Self>;
+}
+
+/// Serialize `word` as a trit string or a packed integer.
+pub(crate) fn serialize<W: SerdeWord, S: Serializer>(word: &W, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        let digits: String = word.trit_slice().iter().rev().map(|t| format!("{:?}", t)).collect();
+        serializer.serialize_str(&format!("0t{}", digits))
+    } else {
+        let packed = W::Packed::try_from(word.value()).unwrap_or_else(|_| unreachable!("word value fits its packed type"));
+        packed.serialize(serializer)
+    }
+}
+
+/// Deserialize a word written by [`serialize`], or in the legacy form.
+pub(crate) fn deserialize<'de, W: SerdeWord, D: Deserializer<'de>>(deserializer: D) -> Result<W, D::Error> {
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(WordVisitor(PhantomData))
+    } else {
+        let value: i64 = W::Packed::deserialize(deserializer)?.into();
+        W::from_value(value).ok_or_else(|| de::Error::custom(format!("value {} out of range for {}", value, W::NAME)))
+    }
+}
+
+struct WordVisitor<W>(PhantomData<W>);
+
+impl<W: SerdeWord> WordVisitor<W> {
+    fn from_value<E: de::Error>(value: i64) -> Result<W, E> {
+        W::from_value(value).ok_or_else(|| E::custom(format!("value {} out of range for {}", value, W::NAME)))
+    }
+
+    fn from_trits<E: de::Error>(mut trits: Vec<Trit>) -> Result<W, E> {
+        if trits.len() > W::WIDTH {
+            return Err(E::custom(format!("{} trits is too many for {}", trits.len(), W::NAME)));
+        }
+        trits.resize(W::WIDTH, Trit::O);
+        Ok(W::from_trit_slice(&trits))
+    }
+}
+
+impl<'de, W: SerdeWord> Visitor<'de> for WordVisitor<W> {
+    type Value = W;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a {} as a trit string like \"0tPON\" or an integer", W::NAME)
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<W, E> {
+        let digits = s.strip_prefix("0t").unwrap_or(s);
+        if digits.is_empty() {
+            return Err(E::custom("empty trit string"));
+        }
+        let trits = digits
+            .chars()
+            .rev()
+            .map(|c| match c {
+                'N' | 'n' | '-' => Ok(Trit::N),
+                'O' | 'o' | '0' => Ok(Trit::O),
+                'P' | 'p' | '+' => Ok(Trit::P),
+                _ => Err(E::custom(format!("invalid trit character: '{}' (expected N/O/P)", c))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_trits(trits)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<W, E> {
+        Self::from_value(value)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<W, E> {
+        Self::from_value(i64::try_from(value).unwrap_or(i64::MAX))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<W, A::Error> {
+        // Legacy form: {"trits": ["N", "O", ...]}, least significant first
+        let mut trits = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "trits" {
+                trits = Some(map.next_value::<Vec<Trit>>()?);
+            } else {
+                map.next_value::<de::IgnoredAny>()?;
+            }
+        }
+        Self::from_trits(trits.ok_or_else(|| de::Error::missing_field("trits"))?)
+    }
+}
+
+/// Implement the representation for a word type with `WIDTH`, `MAX`,
+/// `from_trits`, `trits` and integer conversions.
+macro_rules! impl_serde_word {
+    ($type:ty, $packed:ty, $to:ident, $from:ident, $int:ty) => {
+        impl $crate::ternary::serial::SerdeWord for $type {
+            const NAME: &'static str = stringify!($type);
+            const WIDTH: usize = <$type>::WIDTH;
+            type Packed = $packed;
+
+            fn trit_slice(&self) -> &[$crate::ternary::Trit] {
+                self.trits()
+            }
+
+            fn from_trit_slice(trits: &[$crate::ternary::Trit]) -> Self {
+                let mut array = [$crate::ternary::Trit::O; <$type>::WIDTH];
+                array.copy_from_slice(trits);
+                Self::from_trits(array)
+            }
+
+            fn value(&self) -> i64 {
+                self.$to() as i64
+            }
+
+            fn from_value(value: i64) -> Option<Self> {
+                (-<$type>::MAX as i64..=<$type>::MAX as i64).contains(&value).then(|| Self::$from(value as $int))
+            }
+        }
+
+        impl serde::Serialize for $type {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                $crate::ternary::serial::serialize(self, serializer)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $type {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                $crate::ternary::serial::deserialize(deserializer)
+            }
+        }
+    };
+}
+
+impl_serde_word!(Tryte9, i16, to_i32, from_i32, i32);
+impl_serde_word!(Word18, i32, to_i64, from_i64, i64);
+impl_serde_word!(Tryte5, i8, to_i32, from_i32, i32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_forms() {
+        let word = Word18::from_i64(-123_456);
+        let json = serde_json::to_string(&word).unwrap();
+        assert_eq!(json, format!("\"{}\"", word));
+        assert_eq!(serde_json::from_str::<Word18>(&json).unwrap(), word);
+
+        // The pre-string form still loads
+        let legacy = format!(r#"{{"trits": {}}}"#, serde_json::to_string(&word.trits().to_vec()).unwrap());
+        assert_eq!(serde_json::from_str::<Word18>(&legacy).unwrap(), word);
+
+        assert!(serde_json::from_str::<Tryte9>("9842").is_err());
+        assert!(serde_json::from_str::<Tryte9>(r#""0tPPPPPPPPPP""#).is_err());
+        assert!(serde_json::from_str::<Tryte9>(r#""0tPQ""#).is_err());
+    }
+
+    #[test]
+    fn test_toml_field() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Cell {
+            value: Tryte9,
+        }
+        let cell = Cell { value: Tryte9::from_i32(-5) };
+        let text = toml::to_string(&cell).unwrap();
+        assert_eq!(text.trim(), r#"value = "0tOOOOOONPP""#);
+        assert_eq!(toml::from_str::<Cell>(&text).unwrap(), cell);
+    }
+}
@@ -9,17 +9,19 @@
 
 use std::fmt;
 use serde::{Serialize, Deserialize};
+use crate::ternary::ConvertError;
 
 /// A single balanced ternary digit.
 ///
 /// Represented internally using 2-bit BCT encoding for efficient
 /// bitwise operations while maintaining the balanced ternary semantics.
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum Trit {
     /// Negative (-1)
     N = 0b10,
     /// Zero (0)  
+    #[default]
     O = 0b00,
     /// Positive (+1)
     P = 0b01,
@@ -68,6 +70,18 @@ impl Trit {
         }
     }
     
+    /// Create a trit from an integer value, or an error if it is not
+    /// -1, 0 or 1.
+    #[inline]
+    pub fn try_from_i32(value: i32) -> Result<Self, ConvertError> {
+        match value {
+            -1 => Ok(Trit::N),
+            0 => Ok(Trit::O),
+            1 => Ok(Trit::P),
+            _ => Err(ConvertError::OutOfRange { value: value as i64, trits: 1 }),
+        }
+    }
+    
     /// Convert to integer value.
     #[inline]
     pub const fn to_i8(self) -> i8 {
@@ -116,6 +130,30 @@ impl Trit {
         }
     }
     
+    /// Kleene implication: max(¬self, other).
+    #[inline]
+    pub const fn implies(self, other: Self) -> Self {
+        self.neg().max(other)
+    }
+    
+    /// Equivalence (XNOR analogue): P when both agree, N when they are
+    /// opposite, O when either is unknown. The same as [`mul`](Self::mul).
+    #[inline]
+    pub const fn equiv(self, other: Self) -> Self {
+        self.mul(other)
+    }
+    
+    /// Exclusive max (XOR analogue): the greater input when they differ,
+    /// N when they are equal.
+    #[inline]
+    pub const fn xmax(self, other: Self) -> Self {
+        if self.to_i8() == other.to_i8() {
+            Trit::N
+        } else {
+            self.max(other)
+        }
+    }
+    
     /// Any (gullibility) - accepts any non-zero input, prefers first.
     /// Used in carry chain combination.
     #[inline]
@@ -152,20 +190,16 @@ impl Trit {
     }
     
     /// Full adder: adds three trits (a, b, c_in), returns (sum, carry_out).
+    ///
+    /// Equivalent to chaining two half-adders, but looks the result up by
+    /// the integer total (-3..=3), which keeps the ripple-carry loops in
+    /// `arith` branch-free.
     #[inline]
     pub const fn full_add(self, other: Self, carry_in: Self) -> (Self, Self) {
-        // First half-adder: a + b
-        let s1 = self.sum(other);
-        let c1 = self.carry(other);
-        
-        // Second half-adder: s1 + carry_in
-        let sum = s1.sum(carry_in);
-        let c2 = s1.carry(carry_in);
-        
-        // Combine carries (they can't both be non-zero)
-        let carry_out = c1.any(c2);
-        
-        (sum, carry_out)
+        const SUM: [Trit; 7] = [Trit::O, Trit::P, Trit::N, Trit::O, Trit::P, Trit::N, Trit::O];
+        const CARRY: [Trit; 7] = [Trit::N, Trit::N, Trit::O, Trit::O, Trit::O, Trit::P, Trit::P];
+        let total = (self.to_i8() + other.to_i8() + carry_in.to_i8() + 3) as usize;
+        (SUM[total], CARRY[total])
     }
     
     /// Single-trit multiplication (never carries).
@@ -197,12 +231,6 @@ impl Trit {
     }
 }
 
-impl Default for Trit {
-    fn default() -> Self {
-        Trit::O
-    }
-}
-
 impl fmt::Debug for Trit {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -237,6 +265,14 @@ impl From<i8> for Trit {
     }
 }
 
+impl TryFrom<i32> for Trit {
+    type Error = ConvertError;
+    
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        Trit::try_from_i32(value)
+    }
+}
+
 impl From<Trit> for i8 {
     fn from(trit: Trit) -> Self {
         trit.to_i8()
@@ -294,6 +330,20 @@ mod tests {
         
         // -1 + -1 + -1 = 0, carry -1 (-3 = 0 - 3)
         assert_eq!(Trit::N.full_add(Trit::N, Trit::N), (Trit::O, Trit::N));
+        
+        // 1 + 1 - 1 = 1, carry 0 (half-adder carries cancel)
+        assert_eq!(Trit::P.full_add(Trit::P, Trit::N), (Trit::P, Trit::O));
+        assert_eq!(Trit::N.full_add(Trit::N, Trit::P), (Trit::N, Trit::O));
+        
+        // Exhaustive: sum + 3 * carry equals the arithmetic total
+        for a in Trit::ALL {
+            for b in Trit::ALL {
+                for c in Trit::ALL {
+                    let (sum, carry) = a.full_add(b, c);
+                    assert_eq!(sum.to_i8() + 3 * carry.to_i8(), a.to_i8() + b.to_i8() + c.to_i8());
+                }
+            }
+        }
     }
     
     #[test]
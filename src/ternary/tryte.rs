@@ -0,0 +1,332 @@
+//! Balanced ternary words of any fixed width.
+//!
+//! [`TryteN<N>`] holds `N` trits and carries everything that does not
+//! depend on the width: integer conversion, parsing, formatting, negation,
+//! sign and ripple-carry addition. The machine's words are aliases of it,
+//! [`Tryte9`](crate::Tryte9) for memory cells, [`Word18`](crate::Word18)
+//! for the accumulator and [`Tryte5`](crate::ternary::Tryte5) for the index
+//! register, each adding the extras of its role, so other widths come for
+//! free:
+//!
+//! ```
+//! use setun::ternary::TryteN;
+//!
+//! type Tryte6 = TryteN<6>;
+//! assert_eq!(Tryte6::MAX_VALUE, 364);
+//!
+//! let a = Tryte6::from_i64(300);
+//! let (sum, carry) = a.add_with_carry(&Tryte6::from_i64(100));
+//! assert_eq!((sum.to_i64(), carry.to_i8()), (-329, 1));
+//! assert_eq!(Tryte6::parse("0tPONOOP").unwrap().to_i64(), 217);
+//! assert_eq!(a.resize::<12>().to_i64(), 300);
+//! ```
+//!
+//! Widths up to 39 trits fit in an `i64`.
+
+use crate::ternary::convert::{base27_digits, parse_base27_digits};
+use crate::ternary::word::ParseError;
+use crate::ternary::{ConvertError, Trit};
+use std::fmt;
+
+/// An `N`-trit balanced ternary word.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TryteN<const N: usize> {
+    /// Trits stored from least significant (index 0) to most significant (index N-1)
+    trits: [Trit; N],
+}
+
+impl<const N: usize> TryteN<N> {
+    /// Number of trits.
+    pub const WIDTH: usize = N;
+
+    /// Largest value, (3^N - 1) / 2: every trit P.
+    pub const MAX_VALUE: i64 = {
+        assert!(N <= 39, "a TryteN holds at most 39 trits");
+        let mut max = 0;
+        let mut i = 0;
+        while i < N {
+            max = max * 3 + 1;
+            i += 1;
+        }
+        max
+    };
+
+    /// Smallest value: every trit N.
+    pub const MIN_VALUE: i64 = -Self::MAX_VALUE;
+
+    /// A word with all zeros.
+    #[inline]
+    pub const fn zero() -> Self {
+        Self { trits: [Trit::O; N] }
+    }
+
+    /// Create from an array of trits (LSB first).
+    #[inline]
+    pub const fn from_trits(trits: [Trit; N]) -> Self {
+        Self { trits }
+    }
+
+    /// Get the underlying trit array.
+    #[inline]
+    pub const fn trits(&self) -> &[Trit; N] {
+        &self.trits
+    }
+
+    /// Get a mutable reference to the trit array.
+    #[inline]
+    pub fn trits_mut(&mut self) -> &mut [Trit; N] {
+        &mut self.trits
+    }
+
+    /// Get a single trit by index (0 = LSB).
+    #[inline]
+    pub const fn get(&self, index: usize) -> Trit {
+        self.trits[index]
+    }
+
+    /// Set a single trit by index (0 = LSB).
+    #[inline]
+    pub fn set(&mut self, index: usize, trit: Trit) {
+        self.trits[index] = trit;
+    }
+
+    /// Create from a decimal integer.
+    ///
+    /// # Panics
+    /// Panics if value is outside [`MIN_VALUE`](Self::MIN_VALUE)..=[`MAX_VALUE`](Self::MAX_VALUE).
+    #[inline]
+    pub fn from_i64(value: i64) -> Self {
+        assert!(
+            (Self::MIN_VALUE..=Self::MAX_VALUE).contains(&value),
+            "Value {} out of range for {} [{}, {}]",
+            value, type_name(N), Self::MIN_VALUE, Self::MAX_VALUE
+        );
+
+        Self::from_i64_const(value)
+    }
+
+    /// Create from a decimal integer, or an error if it does not fit.
+    #[inline]
+    pub fn try_from_i64(value: i64) -> Result<Self, ConvertError> {
+        if !(Self::MIN_VALUE..=Self::MAX_VALUE).contains(&value) {
+            return Err(ConvertError::OutOfRange { value, trits: N });
+        }
+        Ok(Self::from_i64_const(value))
+    }
+
+    /// Create from a decimal integer in a const context.
+    ///
+    /// # Panics
+    /// Panics (or fails to compile, in a constant) if value does not fit.
+    pub const fn from_i64_const(value: i64) -> Self {
+        assert!(value >= Self::MIN_VALUE && value <= Self::MAX_VALUE, "value out of range for the word");
+
+        // Offsetting by MAX turns every trit into an ordinary base-3 digit
+        // (0, 1, 2), so no sign handling or carry is needed
+        let mut digits = (value + Self::MAX_VALUE) as u64;
+        let mut trits = [Trit::O; N];
+        let mut i = 0;
+        while i < N {
+            trits[i] = Trit::ALL[(digits % 3) as usize];
+            digits /= 3;
+            i += 1;
+        }
+        Self { trits }
+    }
+
+    /// Convert to a decimal integer.
+    #[inline]
+    pub const fn to_i64(&self) -> i64 {
+        let mut result: i64 = 0;
+        let mut i = N;
+        while i > 0 {
+            i -= 1;
+            result = result * 3 + self.trits[i].to_i8() as i64;
+        }
+        result
+    }
+
+    /// Negate all trits.
+    #[inline]
+    pub fn neg(&self) -> Self {
+        Self { trits: self.trits.map(Trit::neg) }
+    }
+
+    /// Check if this word is zero.
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        self.trits.iter().all(|t| t.is_zero())
+    }
+
+    /// Get the sign of this word (the leading non-zero trit).
+    pub fn sign(&self) -> Trit {
+        self.trits.iter().rev().copied().find(|t| !t.is_zero()).unwrap_or(Trit::O)
+    }
+
+    /// Add with a ripple carry, returning (result, carry_out).
+    pub fn add_with_carry(&self, other: &Self) -> (Self, Trit) {
+        let mut result = Self::zero();
+        let mut carry = Trit::O;
+        for i in 0..N {
+            let (sum, new_carry) = self.trits[i].full_add(other.trits[i], carry);
+            result.trits[i] = sum;
+            carry = new_carry;
+        }
+        (result, carry)
+    }
+
+    /// Subtract `other`, returning (result, borrow_out).
+    pub fn sub_with_borrow(&self, other: &Self) -> (Self, Trit) {
+        self.add_with_carry(&other.neg())
+    }
+
+    /// Convert to another width: zero-extended when wider, which keeps
+    /// the value, and cut to the low `M` trits when narrower.
+    pub fn resize<const M: usize>(&self) -> TryteN<M> {
+        let mut trits = [Trit::O; M];
+        let n = N.min(M);
+        trits[..n].copy_from_slice(&self.trits[..n]);
+        TryteN { trits }
+    }
+
+    /// Parse from a string of exactly `N` trits, like "0tPON" for N = 3.
+    pub fn parse(s: &str) -> Result<Self, ParseError> {
+        let s = s.trim();
+        let s = s.strip_prefix("0t").unwrap_or(s);
+
+        if s.len() != N {
+            return Err(ParseError::WrongLength { expected: N, got: s.len() });
+        }
+
+        let mut trits = [Trit::O; N];
+        for (i, c) in s.chars().rev().enumerate() {
+            trits[i] = match c {
+                'N' | 'n' | '-' => Trit::N,
+                'O' | 'o' | '0' => Trit::O,
+                'P' | 'p' | '+' => Trit::P,
+                _ => return Err(ParseError::InvalidChar(c)),
+            };
+        }
+
+        Ok(Self { trits })
+    }
+
+    /// The word in balanced base 27, one letter code per three trits and
+    /// no prefix, like "1OW" for a [`Tryte9`](crate::Tryte9) holding 401.
+    /// The `{:#}` format gives the same with a `0k` prefix.
+    pub fn to_base27(&self) -> String {
+        base27_digits(&self.trits)
+    }
+
+    /// Parse base-27 letter codes, with or without the `0k` prefix.
+    pub fn from_base27(s: &str) -> Result<Self, ConvertError> {
+        let s = s.trim();
+        let digits = s.strip_prefix("0k").or_else(|| s.strip_prefix("0K")).unwrap_or(s);
+        let value = parse_base27_digits(digits).ok_or_else(|| ConvertError::InvalidNumber(s.to_string()))?;
+        Self::try_from_i64(value)
+    }
+}
+
+/// Name of the `N`-trit word in messages.
+fn type_name(n: usize) -> String {
+    match n {
+        5 => "Tryte5".into(),
+        9 => "Tryte9".into(),
+        18 => "Word18".into(),
+        36 => "Word36".into(),
+        _ => format!("TryteN<{}>", n),
+    }
+}
+
+impl<const N: usize> Default for TryteN<N> {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl<const N: usize> fmt::Debug for TryteN<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(0t", type_name(N))?;
+        for i in (0..N).rev() {
+            write!(f, "{:?}", self.trits[i])?;
+            if N == 18 && i == 9 {
+                write!(f, " ")?; // Visual separator between halves
+            }
+        }
+        write!(f, " = {})", self.to_i64())
+    }
+}
+
+/// `0t` and the trits, or with `{:#}`, `0k` and the base-27 digits.
+impl<const N: usize> fmt::Display for TryteN<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return write!(f, "0k{}", self.to_base27());
+        }
+        write!(f, "0t")?;
+        for i in (0..N).rev() {
+            write!(f, "{:?}", self.trits[i])?;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> std::ops::Neg for TryteN<N> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        TryteN::neg(&self)
+    }
+}
+
+impl<const N: usize> TryFrom<i64> for TryteN<N> {
+    type Error = ConvertError;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        Self::try_from_i64(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_width() {
+        assert_eq!(TryteN::<1>::MAX_VALUE, 1);
+        assert_eq!(TryteN::<12>::MAX_VALUE, 265_720);
+        assert_eq!(TryteN::<39>::MAX_VALUE, (3i64.pow(39) - 1) / 2);
+
+        for value in -364..=364 {
+            let word = TryteN::<6>::from_i64(value);
+            assert_eq!(word.to_i64(), value);
+            assert_eq!(TryteN::<6>::parse(&word.to_string()), Ok(word));
+            assert_eq!(word.neg().to_i64(), -value);
+            assert_eq!(word.sign().to_i8() as i64, value.signum());
+        }
+        assert!(TryteN::<6>::try_from_i64(365).is_err());
+        assert_eq!(format!("{:?}", TryteN::<2>::from_i64(-4)), "TryteN<2>(0tNN = -4)");
+
+        // Narrowing keeps the low trits
+        let (diff, borrow) = TryteN::<3>::from_i64(-13).sub_with_borrow(&TryteN::<3>::from_i64(1));
+        assert_eq!((diff.to_i64(), borrow), (13, Trit::N));
+        assert_eq!(TryteN::<6>::from_i64(100).resize::<3>().to_i64(), -8);
+    }
+
+    #[test]
+    fn test_base27() {
+        let word = TryteN::<9>::from_i64(401);
+        assert_eq!(word.to_base27(), "1OW");
+        assert_eq!(format!("{:#}", word), "0k1OW");
+        assert_eq!(TryteN::<18>::from_i64(-1).to_base27(), "00000Z");
+        assert_eq!(TryteN::<5>::from_i64(-121).to_base27(), "WN");
+
+        for value in -364..=364 {
+            let word = TryteN::<6>::from_i64(value);
+            assert_eq!(TryteN::<6>::from_base27(&format!("{:#}", word)), Ok(word));
+        }
+        assert_eq!(TryteN::<9>::from_base27("1ow"), Ok(word));
+        assert_eq!(TryteN::<9>::from_base27("1000"), Err(ConvertError::OutOfRange { value: 19683, trits: 9 }));
+        assert!(TryteN::<9>::from_base27("0kE").is_err());
+    }
+}
@@ -0,0 +1,305 @@
+//! Growable trit sequences.
+//!
+//! [`Tryte9`] and [`Word18`] have the machine's fixed widths, but tape
+//! rows, character groups and intermediate results come in any length.
+//! A [`TritVec`] holds such a run of trits, least significant first like
+//! the fixed words, and converts to a fixed width when the value fits:
+//!
+//! ```
+//! use setun::ternary::TritVec;
+//! use setun::{Trit, Tryte9};
+//!
+//! let mut row = TritVec::from_i64(-4);
+//! assert_eq!(row.to_string(), "0t--");
+//! row.push(Trit::P);
+//! assert_eq!(row.to_i64(), Some(5));
+//!
+//! let sum = row.add(&TritVec::from_i64(100));
+//! assert_eq!(sum.to_tryte9(), Some(Tryte9::from_i32(105)));
+//! assert_eq!(TritVec::from_i64(20_000).to_tryte9(), None);
+//! ```
+
+use std::fmt;
+use std::ops::{Index, Range};
+use serde::{Serialize, Deserialize};
+use crate::ternary::{Trit, TryteN, Tryte9, Word18};
+
+/// A variable-length sequence of trits, least significant first.
+///
+/// Equality compares the trits, so `0t+` and `0t0+` differ even though
+/// they hold the same value; compare [`trimmed`](Self::trimmed) copies to
+/// compare values.
+#[derive(Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct TritVec {
+    trits: Vec<Trit>,
+}
+
+impl TritVec {
+    /// Create an empty sequence.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create `len` zero trits.
+    pub fn zeros(len: usize) -> Self {
+        Self { trits: vec![Trit::O; len] }
+    }
+
+    /// Create from trits, least significant first.
+    pub fn from_trits(trits: Vec<Trit>) -> Self {
+        Self { trits }
+    }
+
+    /// The trits, least significant first.
+    pub fn trits(&self) -> &[Trit] {
+        &self.trits
+    }
+
+    /// Number of trits.
+    pub fn len(&self) -> usize {
+        self.trits.len()
+    }
+
+    /// Whether the sequence has no trits.
+    pub fn is_empty(&self) -> bool {
+        self.trits.is_empty()
+    }
+
+    /// Get a trit by index (0 = LSB), or `None` past the end.
+    pub fn get(&self, index: usize) -> Option<Trit> {
+        self.trits.get(index).copied()
+    }
+
+    /// Set a trit by index (0 = LSB), growing with zeros if needed.
+    pub fn set(&mut self, index: usize, trit: Trit) {
+        if index >= self.trits.len() {
+            self.trits.resize(index + 1, Trit::O);
+        }
+        self.trits[index] = trit;
+    }
+
+    /// Append a trit above the current most significant one.
+    pub fn push(&mut self, trit: Trit) {
+        self.trits.push(trit);
+    }
+
+    /// Remove and return the most significant trit.
+    pub fn pop(&mut self) -> Option<Trit> {
+        self.trits.pop()
+    }
+
+    /// Append `other` above the current trits.
+    pub fn extend_from(&mut self, other: &TritVec) {
+        self.trits.extend_from_slice(&other.trits);
+    }
+
+    /// Copy the trits in `range`.
+    ///
+    /// # Panics
+    /// Panics if `range` is out of bounds.
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        Self { trits: self.trits[range].to_vec() }
+    }
+
+    /// Split into chunks of `width` trits, starting from the least
+    /// significant end; the last chunk may be shorter.
+    ///
+    /// # Panics
+    /// Panics if `width` is 0.
+    pub fn chunks(&self, width: usize) -> impl Iterator<Item = TritVec> + '_ {
+        self.trits.chunks(width).map(|chunk| Self { trits: chunk.to_vec() })
+    }
+
+    /// Drop leading zero trits; zero becomes the empty sequence.
+    pub fn trimmed(&self) -> Self {
+        let len = self.trits.iter().rposition(|t| !t.is_zero()).map_or(0, |i| i + 1);
+        Self { trits: self.trits[..len].to_vec() }
+    }
+
+    /// Encode `value` in the fewest trits (none for zero).
+    pub fn from_i64(value: i64) -> Self {
+        let mut trits = Vec::new();
+        let mut rest = value as i128;
+        while rest != 0 {
+            let trit = match rest.rem_euclid(3) {
+                0 => Trit::O,
+                1 => Trit::P,
+                _ => Trit::N,
+            };
+            rest = (rest - trit.to_i8() as i128) / 3;
+            trits.push(trit);
+        }
+        Self { trits }
+    }
+
+    /// Decode the value, or `None` if it does not fit in an `i64`.
+    pub fn to_i64(&self) -> Option<i64> {
+        self.trits.iter().rev().try_fold(0i64, |acc, t| {
+            acc.checked_mul(3)?.checked_add(t.to_i8() as i64)
+        })
+    }
+
+    /// Whether every trit is zero.
+    pub fn is_zero(&self) -> bool {
+        self.trits.iter().all(|t| t.is_zero())
+    }
+
+    /// The sign of the value (the leading non-zero trit).
+    pub fn sign(&self) -> Trit {
+        self.trits.iter().rev().find(|t| !t.is_zero()).copied().unwrap_or(Trit::O)
+    }
+
+    /// Negate every trit.
+    pub fn neg(&self) -> Self {
+        Self { trits: self.trits.iter().map(|t| t.neg()).collect() }
+    }
+
+    /// Sum of two sequences, one trit longer than the longer operand so
+    /// nothing is lost to overflow.
+    pub fn add(&self, other: &TritVec) -> Self {
+        let len = self.len().max(other.len());
+        let mut trits = Vec::with_capacity(len + 1);
+        let mut carry = Trit::O;
+        for i in 0..len {
+            let a = self.get(i).unwrap_or(Trit::O);
+            let b = other.get(i).unwrap_or(Trit::O);
+            let (sum, carry_out) = a.full_add(b, carry);
+            trits.push(sum);
+            carry = carry_out;
+        }
+        trits.push(carry);
+        Self { trits }
+    }
+
+    /// Difference `self - other`, sized like [`add`](Self::add).
+    pub fn sub(&self, other: &TritVec) -> Self {
+        self.add(&other.neg())
+    }
+
+    /// Product of two sequences, as long as both operands together.
+    pub fn mul(&self, other: &TritVec) -> Self {
+        let mut product = Self::zeros(self.len() + other.len());
+        for (shift, &b) in other.trits.iter().enumerate() {
+            if b.is_zero() {
+                continue;
+            }
+            let mut partial = Self::zeros(shift);
+            partial.trits.extend(self.trits.iter().map(|a| a.mul(b)));
+            product = product.add(&partial);
+        }
+        product.trits.truncate(self.len() + other.len());
+        product
+    }
+
+    /// Convert to a fixed width, zero-extending shorter sequences. `None`
+    /// if a non-zero trit lies beyond `N`.
+    fn to_fixed<const N: usize>(&self) -> Option<[Trit; N]> {
+        if self.trits.iter().skip(N).any(|t| !t.is_zero()) {
+            return None;
+        }
+        let mut trits = [Trit::O; N];
+        let len = self.len().min(N);
+        trits[..len].copy_from_slice(&self.trits[..len]);
+        Some(trits)
+    }
+
+    /// Convert to a [`Tryte9`], or `None` if the value does not fit.
+    pub fn to_tryte9(&self) -> Option<Tryte9> {
+        self.to_fixed().map(Tryte9::from_trits)
+    }
+
+    /// Convert to a [`Word18`], or `None` if the value does not fit.
+    pub fn to_word18(&self) -> Option<Word18> {
+        self.to_fixed().map(Word18::from_trits)
+    }
+}
+
+impl Index<usize> for TritVec {
+    type Output = Trit;
+
+    fn index(&self, index: usize) -> &Trit {
+        &self.trits[index]
+    }
+}
+
+impl<const N: usize> From<TryteN<N>> for TritVec {
+    fn from(word: TryteN<N>) -> Self {
+        Self { trits: word.trits().to_vec() }
+    }
+}
+
+impl From<Vec<Trit>> for TritVec {
+    fn from(trits: Vec<Trit>) -> Self {
+        Self { trits }
+    }
+}
+
+impl FromIterator<Trit> for TritVec {
+    fn from_iter<I: IntoIterator<Item = Trit>>(iter: I) -> Self {
+        Self { trits: iter.into_iter().collect() }
+    }
+}
+
+impl fmt::Debug for TritVec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TritVec({}", self)?;
+        match self.to_i64() {
+            Some(value) => write!(f, " = {})", value),
+            None => write!(f, ")"),
+        }
+    }
+}
+
+impl fmt::Display for TritVec {
+    /// Most significant trit first, like `0t+0-`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0t")?;
+        for t in self.trits.iter().rev() {
+            write!(f, "{}", t)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::ops::Neg for TritVec {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        TritVec::neg(&self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_round_trip() {
+        for value in [-1_000_000, -13, -1, 0, 1, 40, 9_842, i64::MAX, i64::MIN + 1] {
+            assert_eq!(TritVec::from_i64(value).to_i64(), Some(value), "value {}", value);
+        }
+        assert!(TritVec::from_i64(0).is_empty());
+        let mut long = TritVec::from_i64(i64::MAX);
+        long.push(Trit::P);
+        assert_eq!(long.to_i64(), None);
+    }
+
+    #[test]
+    fn test_arithmetic_and_fixed_widths() {
+        let a = TritVec::from_i64(-1234);
+        let b = TritVec::from_i64(567);
+        assert_eq!(a.add(&b).to_i64(), Some(-667));
+        assert_eq!(a.sub(&b).to_i64(), Some(-1801));
+        assert_eq!(a.mul(&b).to_i64(), Some(-1234 * 567));
+        assert_eq!(a.add(&a.neg()).trimmed(), TritVec::new());
+
+        let word = Word18::from_i64(-98_765);
+        let vec = TritVec::from(word);
+        assert_eq!(vec.len(), 18);
+        assert_eq!(vec.to_word18(), Some(word));
+        assert_eq!(vec.to_tryte9(), None);
+        assert_eq!(vec.slice(0..9).to_tryte9(), Some(word.low()));
+        let halves: Vec<TritVec> = vec.chunks(9).collect();
+        assert_eq!(halves[1].to_tryte9(), Some(word.high()));
+    }
+}
@@ -1,26 +1,111 @@
 //! Fixed-width balanced ternary words.
 //!
-//! This module provides the two word sizes used in the Setun:
+//! This module provides the word sizes used in the Setun, all built on
+//! [`TryteN`]:
 //! - `Tryte9`: 9-trit "nitrit" for instructions and memory cells
 //! - `Word18`: 18-trit full word for accumulator and computation
+//! - `Tryte5`: 5-trit index register
+//!
+//! Both can be built in const contexts with [`Tryte9::from_i32_const`] and
+//! [`Word18::from_i64_const`], or from literals with the [`tryte!`] and
+//! [`word!`] macros, which are checked at compile time:
+//!
+//! ```
+//! use setun::{tryte, word, Tryte9, Word18};
+//!
+//! const LIMIT: Tryte9 = tryte!(42);
+//! const PATTERN: Tryte9 = tryte!("PONOONNOP");
+//! const BIG: Word18 = word!(-1_000_000);
+//!
+//! assert_eq!(LIMIT, Tryte9::from_i32(42));
+//! assert_eq!(PATTERN, Tryte9::parse("PONOONNOP").unwrap());
+//! assert_eq!(BIG.to_i64(), -1_000_000);
+//! ```
+//!
+//! A trit-string literal is written most significant trit first, with
+//! N/O/P or -/0/+ digits and an optional `0t` prefix, and may be shorter
+//! than the word.
 
 use std::fmt;
-use serde::{Serialize, Deserialize};
-use crate::ternary::Trit;
+use crate::ternary::{ConvertError, Trit, TryteN};
+
+/// Build a [`Tryte9`](crate::Tryte9) constant from a decimal or
+/// trit-string literal at compile time. Out-of-range values and bad trit
+/// characters are compile errors:
+///
+/// ```compile_fail
+/// const TOO_BIG: setun::Tryte9 = setun::tryte!(10_000);
+/// ```
+#[macro_export]
+macro_rules! tryte {
+    ($lit:literal) => {
+        const { $crate::ternary::Tryte9::from_literal(stringify!($lit)) }
+    };
+}
+
+/// Build a [`Word18`](crate::Word18) constant from a decimal or
+/// trit-string literal at compile time, like [`tryte!`].
+#[macro_export]
+macro_rules! word {
+    ($lit:literal) => {
+        const { $crate::ternary::Word18::from_literal(stringify!($lit)) }
+    };
+}
+
+/// Value of a literal as `stringify!` writes it: a quoted trit string
+/// (most significant first, optional `0t`) or a decimal integer.
+/// Panics, at compile time when used in a constant, if it is malformed or
+/// has more than `width` trits.
+const fn literal_value(s: &str, width: usize) -> i64 {
+    let bytes = s.as_bytes();
+    let mut value: i64 = 0;
+    if !bytes.is_empty() && bytes[0] == b'"' {
+        let end = bytes.len() - 1;
+        let mut i = 1;
+        if end >= 3 && bytes[1] == b'0' && (bytes[2] == b't' || bytes[2] == b'T') {
+            i = 3;
+        }
+        assert!(end > i && end - i <= width, "trit literal is empty or longer than the word");
+        while i < end {
+            let trit = match bytes[i] {
+                b'N' | b'n' | b'-' => -1,
+                b'O' | b'o' | b'0' => 0,
+                b'P' | b'p' | b'+' => 1,
+                _ => panic!("invalid character in trit literal (expected N/O/P or -/0/+)"),
+            };
+            value = value * 3 + trit;
+            i += 1;
+        }
+        value
+    } else {
+        let mut i = 0;
+        let negative = !bytes.is_empty() && bytes[0] == b'-';
+        if negative {
+            i = 1;
+        }
+        assert!(i < bytes.len(), "empty literal");
+        while i < bytes.len() {
+            match bytes[i] {
+                b'0'..=b'9' => value = value * 10 + (bytes[i] - b'0') as i64,
+                b'_' | b' ' => {}
+                _ => panic!("invalid decimal literal"),
+            }
+            assert!(value <= Word18::MAX, "literal out of range");
+            i += 1;
+        }
+        if negative { -value } else { value }
+    }
+}
 
 /// A 9-trit word (nitrit).
 ///
 /// Used for:
 /// - Memory cells (the Setun had 162 of these)
 /// - Individual instructions (two fit in an 18-trit word)
-/// - The index register F (only uses 5 trits, but stored as 9)
+/// - The program counter C
 ///
 /// Value range: -9,841 to +9,841
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
-pub struct Tryte9 {
-    /// Trits stored from least significant (index 0) to most significant (index 8)
-    trits: [Trit; 9],
-}
+pub type Tryte9 = TryteN<9>;
 
 /// An 18-trit word.
 ///
@@ -30,134 +115,66 @@ pub struct Tryte9 {
 /// - Full-precision arithmetic
 ///
 /// Value range: -193,710,244 to +193,710,244
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
-pub struct Word18 {
-    /// Trits stored from least significant (index 0) to most significant (index 17)
-    trits: [Trit; 18],
-}
+pub type Word18 = TryteN<18>;
+
+/// A 36-trit double word: the full product of two [`Word18`]s and the
+/// dividend of [`divide_wide`](crate::ternary::arith::divide_wide).
+pub type Word36 = TryteN<36>;
+
+/// A 5-trit word, the width of the index register F.
+///
+/// Value range: -121 to +121
+pub type Tryte5 = TryteN<5>;
 
 // ============================================================================
 // Tryte9 Implementation
 // ============================================================================
 
 impl Tryte9 {
-    /// Number of trits in a Tryte9.
-    pub const WIDTH: usize = 9;
-    
     /// Maximum positive value: +9,841 (all P's: PPP PPP PPP)
-    pub const MAX: i32 = 9_841;
+    pub const MAX: i32 = Self::MAX_VALUE as i32;
     
     /// Minimum negative value: -9,841 (all N's: NNN NNN NNN)
-    pub const MIN: i32 = -9_841;
-    
-    /// Create a new Tryte9 with all zeros.
-    #[inline]
-    pub const fn zero() -> Self {
-        Self { trits: [Trit::O; 9] }
-    }
-    
-    /// Create a Tryte9 from an array of trits (LSB first).
-    #[inline]
-    pub const fn from_trits(trits: [Trit; 9]) -> Self {
-        Self { trits }
-    }
-    
-    /// Get the underlying trit array.
-    #[inline]
-    pub const fn trits(&self) -> &[Trit; 9] {
-        &self.trits
-    }
-    
-    /// Get a mutable reference to the trit array.
-    #[inline]
-    pub fn trits_mut(&mut self) -> &mut [Trit; 9] {
-        &mut self.trits
-    }
+    pub const MIN: i32 = Self::MIN_VALUE as i32;
     
-    /// Get a single trit by index (0 = LSB).
+    /// Create from a decimal integer.
+    ///
+    /// # Panics
+    /// Panics if value is outside the range [-9841, +9841].
     #[inline]
-    pub const fn get(&self, index: usize) -> Trit {
-        self.trits[index]
+    pub fn from_i32(value: i32) -> Self {
+        Self::from_i64(value as i64)
     }
     
-    /// Set a single trit by index (0 = LSB).
+    /// Create from a decimal integer, or an error if it is outside the
+    /// range [-9841, +9841].
     #[inline]
-    pub fn set(&mut self, index: usize, trit: Trit) {
-        self.trits[index] = trit;
+    pub fn try_from_i32(value: i32) -> Result<Self, ConvertError> {
+        Self::try_from_i64(value as i64)
     }
     
-    /// Create from a decimal integer.
+    /// Create from a decimal integer in a const context.
     ///
     /// # Panics
-    /// Panics if value is outside the range [-9841, +9841].
-    pub fn from_i32(mut value: i32) -> Self {
-        assert!(
-            value >= Self::MIN && value <= Self::MAX,
-            "Value {} out of range for Tryte9 [{}, {}]",
-            value, Self::MIN, Self::MAX
-        );
-        
-        let mut trits = [Trit::O; 9];
-        let negative = value < 0;
-        if negative {
-            value = -value;
-        }
-        
-        for i in 0..9 {
-            let remainder = ((value % 3) + 1) as i8; // 0, 1, 2 -> 1, 2, 3
-            let (trit, carry) = match remainder {
-                1 => (Trit::O, 0),
-                2 => (Trit::P, 0),
-                3 => (Trit::N, 1), // 3 mod 3 = 0, but we need -1 + carry
-                _ => unreachable!(),
-            };
-            trits[i] = trit;
-            value = value / 3 + carry;
-        }
-        
-        let mut result = Self { trits };
-        if negative {
-            result = result.neg();
-        }
-        result
+    /// Panics (or fails to compile, in a constant) if value is outside the
+    /// range [-9841, +9841].
+    pub const fn from_i32_const(value: i32) -> Self {
+        assert!(value >= Self::MIN && value <= Self::MAX, "value out of range for Tryte9");
+        Self::from_i64_const(value as i64)
     }
     
-    /// Convert to a decimal integer.
-    pub fn to_i32(&self) -> i32 {
-        let mut result: i32 = 0;
-        let mut power: i32 = 1;
-        
-        for i in 0..9 {
-            result += self.trits[i].to_i8() as i32 * power;
-            power *= 3;
-        }
-        
-        result
+    /// Parse a [`tryte!`] literal; not meant to be called directly.
+    #[doc(hidden)]
+    pub const fn from_literal(s: &str) -> Self {
+        let value = literal_value(s, Self::WIDTH);
+        assert!(value >= Self::MIN as i64 && value <= Self::MAX as i64, "value out of range for Tryte9");
+        Self::from_i32_const(value as i32)
     }
     
-    /// Negate all trits.
+    /// Convert to a decimal integer.
     #[inline]
-    pub fn neg(&self) -> Self {
-        let mut trits = [Trit::O; 9];
-        for i in 0..9 {
-            trits[i] = self.trits[i].neg();
-        }
-        Self { trits }
-    }
-    
-    /// Check if this word is zero.
-    pub fn is_zero(&self) -> bool {
-        self.trits.iter().all(|t| t.is_zero())
-    }
-    
-    /// Get the sign of this word (the leading non-zero trit).
-    pub fn sign(&self) -> Trit {
-        for i in (0..9).rev() {
-            if !self.trits[i].is_zero() {
-                return self.trits[i];
-            }
-        }
-        Trit::O
+    pub const fn to_i32(&self) -> i32 {
+        self.to_i64() as i32
     }
     
     /// Extend to an 18-trit word (zero-extended).
@@ -165,61 +182,7 @@ impl Tryte9 {
     /// Note: In balanced ternary, zero-extension preserves the value.
     /// Sign extension would change the value (unlike in two's complement).
     pub fn to_word18(&self) -> Word18 {
-        let mut trits = [Trit::O; 18];
-        for i in 0..9 {
-            trits[i] = self.trits[i];
-        }
-        Word18 { trits }
-    }
-    
-    /// Parse from a string like "0tPON" or "PONOOOOOO".
-    pub fn parse(s: &str) -> Result<Self, ParseError> {
-        let s = s.trim();
-        let s = s.strip_prefix("0t").unwrap_or(s);
-        
-        if s.len() != 9 {
-            return Err(ParseError::WrongLength { expected: 9, got: s.len() });
-        }
-        
-        let mut trits = [Trit::O; 9];
-        for (i, c) in s.chars().rev().enumerate() {
-            trits[i] = match c {
-                'N' | 'n' | '-' => Trit::N,
-                'O' | 'o' | '0' => Trit::O,
-                'P' | 'p' | '+' => Trit::P,
-                _ => return Err(ParseError::InvalidChar(c)),
-            };
-        }
-        
-        Ok(Self { trits })
-    }
-}
-
-impl fmt::Debug for Tryte9 {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Tryte9(0t")?;
-        for i in (0..9).rev() {
-            write!(f, "{:?}", self.trits[i])?;
-        }
-        write!(f, " = {})", self.to_i32())
-    }
-}
-
-impl fmt::Display for Tryte9 {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "0t")?;
-        for i in (0..9).rev() {
-            write!(f, "{:?}", self.trits[i])?;
-        }
-        Ok(())
-    }
-}
-
-impl std::ops::Neg for Tryte9 {
-    type Output = Self;
-    
-    fn neg(self) -> Self::Output {
-        Tryte9::neg(&self)
+        self.resize()
     }
 }
 
@@ -228,210 +191,146 @@ impl std::ops::Neg for Tryte9 {
 // ============================================================================
 
 impl Word18 {
-    /// Number of trits in a Word18.
-    pub const WIDTH: usize = 18;
-    
     /// Maximum positive value: +193,710,244
-    pub const MAX: i64 = 193_710_244;
+    pub const MAX: i64 = Self::MAX_VALUE;
     
     /// Minimum negative value: -193,710,244
-    pub const MIN: i64 = -193_710_244;
-    
-    /// Create a new Word18 with all zeros.
-    #[inline]
-    pub const fn zero() -> Self {
-        Self { trits: [Trit::O; 18] }
-    }
+    pub const MIN: i64 = Self::MIN_VALUE;
     
-    /// Create a Word18 from an array of trits (LSB first).
-    #[inline]
-    pub const fn from_trits(trits: [Trit; 18]) -> Self {
-        Self { trits }
+    /// Parse a [`word!`] literal; not meant to be called directly.
+    #[doc(hidden)]
+    pub const fn from_literal(s: &str) -> Self {
+        Self::from_i64_const(literal_value(s, Self::WIDTH))
     }
     
-    /// Get the underlying trit array.
-    #[inline]
-    pub const fn trits(&self) -> &[Trit; 18] {
-        &self.trits
-    }
-    
-    /// Get a mutable reference to the trit array.
-    #[inline]
-    pub fn trits_mut(&mut self) -> &mut [Trit; 18] {
-        &mut self.trits
-    }
-    
-    /// Get a single trit by index (0 = LSB).
-    #[inline]
-    pub const fn get(&self, index: usize) -> Trit {
-        self.trits[index]
-    }
-    
-    /// Set a single trit by index (0 = LSB).
-    #[inline]
-    pub fn set(&mut self, index: usize, trit: Trit) {
-        self.trits[index] = trit;
-    }
-    
-    /// Create from a decimal integer.
+    /// Encode `x` as a fixed-point word with `frac_trits` trits after the
+    /// radix point, i.e. `x * 3^frac_trits` rounded to the nearest integer
+    /// (halves away from zero, like [`f64::round`]). The rounding error is
+    /// at most half of 3^-frac_trits.
+    ///
+    /// Returns `None` if `x` is not finite or the scaled value does not fit.
     ///
     /// # Panics
-    /// Panics if value is outside the valid range.
-    pub fn from_i64(mut value: i64) -> Self {
-        assert!(
-            value >= Self::MIN && value <= Self::MAX,
-            "Value {} out of range for Word18 [{}, {}]",
-            value, Self::MIN, Self::MAX
-        );
-        
-        let mut trits = [Trit::O; 18];
-        let negative = value < 0;
-        if negative {
-            value = -value;
-        }
-        
-        for i in 0..18 {
-            let remainder = ((value % 3) + 1) as i8;
-            let (trit, carry) = match remainder {
-                1 => (Trit::O, 0),
-                2 => (Trit::P, 0),
-                3 => (Trit::N, 1),
-                _ => unreachable!(),
-            };
-            trits[i] = trit;
-            value = value / 3 + carry;
-        }
-        
-        let mut result = Self { trits };
-        if negative {
-            result = result.neg();
-        }
-        result
+    /// Panics if `frac_trits` is more than 18.
+    pub fn from_f64_scaled(x: f64, frac_trits: usize) -> Option<Self> {
+        assert!(frac_trits <= Self::WIDTH, "a Word18 has only {} trits", Self::WIDTH);
+        let scaled = (x * 3f64.powi(frac_trits as i32)).round();
+        (scaled.is_finite() && scaled.abs() <= Self::MAX as f64).then(|| Self::from_i64(scaled as i64))
     }
     
-    /// Convert to a decimal integer.
-    pub fn to_i64(&self) -> i64 {
-        let mut result: i64 = 0;
-        let mut power: i64 = 1;
-        
-        for i in 0..18 {
-            result += self.trits[i].to_i8() as i64 * power;
-            power *= 3;
-        }
-        
-        result
-    }
-    
-    /// Negate all trits.
-    #[inline]
-    pub fn neg(&self) -> Self {
-        let mut trits = [Trit::O; 18];
-        for i in 0..18 {
-            trits[i] = self.trits[i].neg();
-        }
-        Self { trits }
-    }
-    
-    /// Check if this word is zero.
-    pub fn is_zero(&self) -> bool {
-        self.trits.iter().all(|t| t.is_zero())
-    }
-    
-    /// Get the sign of this word (the leading non-zero trit).
-    pub fn sign(&self) -> Trit {
-        for i in (0..18).rev() {
-            if !self.trits[i].is_zero() {
-                return self.trits[i];
-            }
-        }
-        Trit::O
+    /// Decode a fixed-point word with `frac_trits` fraction trits, i.e.
+    /// the value divided by 3^frac_trits. Every word converts exactly up
+    /// to f64 rounding of that division, so the result maps back to the
+    /// same word through [`from_f64_scaled`](Self::from_f64_scaled).
+    ///
+    /// # Panics
+    /// Panics if `frac_trits` is more than 18.
+    pub fn to_f64_scaled(&self, frac_trits: usize) -> f64 {
+        assert!(frac_trits <= Self::WIDTH, "a Word18 has only {} trits", Self::WIDTH);
+        self.to_i64() as f64 / 3f64.powi(frac_trits as i32)
     }
     
     /// Extract the low 9-trit half.
     pub fn low(&self) -> Tryte9 {
-        let mut trits = [Trit::O; 9];
-        for i in 0..9 {
-            trits[i] = self.trits[i];
-        }
-        Tryte9 { trits }
+        self.resize()
     }
     
     /// Extract the high 9-trit half.
     pub fn high(&self) -> Tryte9 {
         let mut trits = [Trit::O; 9];
-        for i in 0..9 {
-            trits[i] = self.trits[i + 9];
-        }
-        Tryte9 { trits }
+        trits.copy_from_slice(&self.trits()[9..]);
+        Tryte9::from_trits(trits)
     }
     
     /// Create from two 9-trit halves.
     pub fn from_halves(low: Tryte9, high: Tryte9) -> Self {
         let mut trits = [Trit::O; 18];
-        for i in 0..9 {
-            trits[i] = low.trits[i];
-            trits[i + 9] = high.trits[i];
-        }
-        Self { trits }
+        trits[..9].copy_from_slice(low.trits());
+        trits[9..].copy_from_slice(high.trits());
+        Self::from_trits(trits)
+    }
+}
+
+// ============================================================================
+// Word36 Implementation
+// ============================================================================
+
+impl Word36 {
+    /// The low 18-trit half.
+    pub fn low(&self) -> Word18 {
+        self.resize()
     }
     
-    /// Parse from a string like "0tPONOOOOOOOOOOOOOOO".
-    pub fn parse(s: &str) -> Result<Self, ParseError> {
-        let s = s.trim();
-        let s = s.strip_prefix("0t").unwrap_or(s);
-        
-        if s.len() != 18 {
-            return Err(ParseError::WrongLength { expected: 18, got: s.len() });
-        }
-        
+    /// The high 18-trit half.
+    pub fn high(&self) -> Word18 {
         let mut trits = [Trit::O; 18];
-        for (i, c) in s.chars().rev().enumerate() {
-            trits[i] = match c {
-                'N' | 'n' | '-' => Trit::N,
-                'O' | 'o' | '0' => Trit::O,
-                'P' | 'p' | '+' => Trit::P,
-                _ => return Err(ParseError::InvalidChar(c)),
-            };
-        }
-        
-        Ok(Self { trits })
+        trits.copy_from_slice(&self.trits()[18..]);
+        Word18::from_trits(trits)
+    }
+    
+    /// Create from two 18-trit halves, e.g. the (low, high) result of
+    /// [`multiply`](crate::ternary::arith::multiply).
+    pub fn from_halves(low: Word18, high: Word18) -> Self {
+        let mut trits = [Trit::O; 36];
+        trits[..18].copy_from_slice(low.trits());
+        trits[18..].copy_from_slice(high.trits());
+        Self::from_trits(trits)
     }
 }
 
-impl fmt::Debug for Word18 {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Word18(0t")?;
-        for i in (0..18).rev() {
-            write!(f, "{:?}", self.trits[i])?;
-            if i == 9 {
-                write!(f, " ")?; // Visual separator between halves
-            }
-        }
-        write!(f, " = {})", self.to_i64())
+// ============================================================================
+// Tryte5 Implementation
+// ============================================================================
+
+impl Tryte5 {
+    /// Maximum value: 121 (PPPPP)
+    pub const MAX: i32 = Self::MAX_VALUE as i32;
+    /// Minimum value: -121 (NNNNN)
+    pub const MIN: i32 = Self::MIN_VALUE as i32;
+    
+    /// Create from an integer, or an error if it is outside [-121, +121].
+    pub fn try_from_i32(value: i32) -> Result<Self, ConvertError> {
+        Self::try_from_i64(value as i64)
+    }
+    
+    /// Create from an integer.
+    ///
+    /// # Panics
+    /// Panics if value is outside [-121, +121].
+    pub fn from_i32(value: i32) -> Self {
+        Self::from_i64(value as i64)
+    }
+    
+    /// Convert to integer.
+    pub const fn to_i32(&self) -> i32 {
+        self.to_i64() as i32
+    }
+    
+    /// Extend to 9-trit Tryte9 (zero-extended).
+    pub fn to_tryte9(&self) -> Tryte9 {
+        self.resize()
     }
 }
 
-impl fmt::Display for Word18 {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "0t")?;
-        for i in (0..18).rev() {
-            write!(f, "{:?}", self.trits[i])?;
-        }
-        Ok(())
+impl From<Tryte9> for Word18 {
+    fn from(tryte: Tryte9) -> Self {
+        tryte.to_word18()
     }
 }
 
-impl std::ops::Neg for Word18 {
-    type Output = Self;
+impl TryFrom<i32> for Tryte9 {
+    type Error = ConvertError;
     
-    fn neg(self) -> Self::Output {
-        Word18::neg(&self)
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        Tryte9::try_from_i32(value)
     }
 }
 
-impl From<Tryte9> for Word18 {
-    fn from(tryte: Tryte9) -> Self {
-        tryte.to_word18()
+impl TryFrom<i32> for Tryte5 {
+    type Error = ConvertError;
+    
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        Tryte5::try_from_i32(value)
     }
 }
 
@@ -490,6 +389,17 @@ mod tests {
         assert_eq!(Tryte9::from_i32(-9841).to_i32(), -9841);
     }
     
+    #[test]
+    fn test_fallible_conversions() {
+        assert_eq!(Tryte9::try_from(-9841), Ok(Tryte9::from_i32(-9841)));
+        assert_eq!(Tryte9::try_from(9842i64), Err(ConvertError::OutOfRange { value: 9842, trits: 9 }));
+        assert_eq!(Tryte9::try_from_i64(i64::MIN).ok(), None);
+        assert_eq!(Word18::try_from(Word18::MAX), Ok(Word18::from_i64(Word18::MAX)));
+        assert!(Word18::try_from_i64(Word18::MIN - 1).is_err());
+        assert_eq!(Trit::try_from(-1), Ok(Trit::N));
+        assert_eq!(Trit::try_from_i32(2), Err(ConvertError::OutOfRange { value: 2, trits: 1 }));
+    }
+    
     #[test]
     fn test_tryte9_negation() {
         let value = Tryte9::from_i32(42);
@@ -554,6 +464,40 @@ mod tests {
         assert_eq!(combined.high().to_i32(), 100);
     }
     
+    #[test]
+    fn test_f64_scaled() {
+        let word = Word18::from_f64_scaled(0.5, 4).unwrap();
+        // 0.5 * 81 = 40.5 rounds away from zero
+        assert_eq!(word.to_i64(), 41);
+        assert_eq!(Word18::from_f64_scaled(-0.5, 4).unwrap().to_i64(), -41);
+        assert!((word.to_f64_scaled(4) - 0.5).abs() <= 0.5 / 81.0 + f64::EPSILON);
+        assert_eq!(Word18::from_f64_scaled(-2.25, 0).unwrap().to_i64(), -2);
+        
+        for value in [0, 1, -1, 12345, Word18::MIN, Word18::MAX] {
+            let w = Word18::from_i64(value);
+            for frac_trits in [0, 5, 18] {
+                assert_eq!(Word18::from_f64_scaled(w.to_f64_scaled(frac_trits), frac_trits), Some(w));
+            }
+        }
+        assert_eq!(Word18::from_f64_scaled(f64::NAN, 3), None);
+        assert_eq!(Word18::from_f64_scaled(1e9, 0), None);
+        assert_eq!(Word18::from_f64_scaled(1.0, 18), None);
+    }
+    
+    #[test]
+    fn test_const_literals() {
+        const TABLE: [Tryte9; 3] = [tryte!(-9841), tryte!("0t+0-"), tryte!("PONOONNOP")];
+        assert_eq!(TABLE[0], Tryte9::from_i32(-9841));
+        assert_eq!(TABLE[1].to_i32(), 8);
+        assert_eq!(TABLE[2], Tryte9::parse("PONOONNOP").unwrap());
+        assert_eq!(word!(193_710_244), Word18::from_i64(Word18::MAX));
+        assert_eq!(word!("NNNNNNNNNNNNNNNNNN").to_i64(), Word18::MIN);
+        
+        const ANSWER: i32 = Tryte9::from_i32_const(42).to_i32();
+        assert_eq!(ANSWER, 42);
+        assert_eq!(Word18::from_i64_const(-5), Word18::from_i64(-5));
+    }
+    
     #[test]
     fn test_tryte9_to_word18() {
         let positive = Tryte9::from_i32(42);
@@ -0,0 +1,519 @@
+//! Differential testing against a golden model.
+//!
+//! [`Reference`] is a deliberately naive interpreter of the same
+//! instruction set: registers and memory are plain `i64`s, words are
+//! decoded with integer division, and every trit-level operation is
+//! replaced by ordinary arithmetic followed by a balanced wrap. It shares
+//! no code with the emulator beyond the instruction encoding, so the two
+//! only agree if the trit-level implementation is right.
+//!
+//! [`verify`] runs a prepared [`Cpu`] and the reference side by side and
+//! compares registers, state, written memory and teletype output after
+//! every instruction, stopping at the first [`Divergence`]:
+//!
+//! ```
+//! use setun::{assemble, Cpu};
+//! use setun::testing::verify;
+//!
+//! let mut cpu = Cpu::new();
+//! cpu.load_program(&assemble("LDA 3\nMUL 3\nHLT\nDAT 99").unwrap()).unwrap();
+//! let report = verify(cpu, 100);
+//! assert!(report.passed());
+//! ```
+//!
+//! Attached devices and the timing model are not part of the reference.
+
+use crate::asm::disasm::disassemble_instruction;
+use crate::cpu::io::{TAPE_READER_ADDR, TELETYPE_ADDR};
+use crate::cpu::{Cpu, CpuState, OverflowPolicy, StopReason};
+use crate::ternary::Tryte9;
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+use thiserror::Error;
+
+/// 3^5: values of the 5-trit address field and F register.
+const W5: i64 = 243;
+/// 3^6: the low six trits (mode and address) of an instruction.
+const W6: i64 = 729;
+/// 3^9: values of a memory cell.
+const W9: i64 = 19_683;
+/// 3^18: values of the S and R registers.
+const W18: i64 = 387_420_489;
+
+/// Wrap `value` into the balanced range of `modulus` (a power of three).
+fn wrap(value: i64, modulus: i64) -> i64 {
+    let half = (modulus - 1) / 2;
+    (value + half).rem_euclid(modulus) - half
+}
+
+/// `value / divisor` rounded to the nearest integer (`divisor` is odd).
+fn round_div(value: i64, divisor: i64) -> i64 {
+    (value + (divisor - 1) / 2).div_euclid(divisor)
+}
+
+/// Opcode numbers (the top three trits of an instruction).
+mod op {
+    pub const HLT: i64 = 0;
+    pub const ADD: i64 = 1;
+    pub const SUB: i64 = -1;
+    pub const MUL: i64 = 2;
+    pub const DIV: i64 = -2;
+    pub const LDA: i64 = 3;
+    pub const STA: i64 = -3;
+    pub const LDF: i64 = 4;
+    pub const STF: i64 = -4;
+    pub const JMP: i64 = 5;
+    pub const LDAU: i64 = -5;
+    pub const JZ: i64 = 6;
+    pub const JP: i64 = 7;
+    pub const JN: i64 = -7;
+    pub const NOP: i64 = 8;
+    pub const SHL: i64 = 9;
+    pub const SHR: i64 = -9;
+    pub const LDR: i64 = 10;
+    pub const STR: i64 = -10;
+    pub const ADDABS: i64 = 11;
+    pub const SUBABS: i64 = -11;
+    pub const XCHG: i64 = 12;
+    pub const JOP: i64 = 13;
+    pub const JON: i64 = -13;
+}
+
+/// Why the reference interpreter stopped.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ReferenceError {
+    #[error("address {0} is outside memory")]
+    BadAddress(i64),
+
+    #[error("invalid opcode {0}")]
+    InvalidOpcode(i64),
+
+    #[error("arithmetic overflow")]
+    Overflow,
+
+    #[error("division by zero")]
+    DivisionByZero,
+
+    #[error("input tape exhausted")]
+    InputExhausted,
+}
+
+/// The golden model: the Setun as plain integers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub s: i64,
+    pub r: i64,
+    pub f: i64,
+    pub c: i64,
+    pub omega: i64,
+    /// Memory cells, lowest address first.
+    pub mem: Vec<i64>,
+    pub halted: bool,
+    /// Words written to the teletype since [`verify`] last took them.
+    pub output: Vec<i64>,
+    input: VecDeque<i64>,
+    io: bool,
+    trap_overflow: bool,
+    strict_shifts: bool,
+    /// Memory addresses written by the last step.
+    written: Vec<i64>,
+}
+
+impl Reference {
+    /// Copy the architectural state of `cpu`.
+    pub fn from_cpu(cpu: &Cpu) -> Self {
+        Reference {
+            s: cpu.regs.s.to_i64(),
+            r: cpu.regs.r.to_i64(),
+            f: cpu.regs.f.to_i32() as i64,
+            c: cpu.regs.c.to_i32() as i64,
+            omega: i8::from(cpu.regs.omega) as i64,
+            mem: cpu.mem.as_slice().iter().map(|cell| cell.to_i32() as i64).collect(),
+            halted: cpu.state != CpuState::Running,
+            output: Vec::new(),
+            input: cpu.io.input_tape().map(|w| w.to_i32() as i64).collect(),
+            io: cpu.io.enabled,
+            trap_overflow: cpu.config.overflow == OverflowPolicy::Trap,
+            strict_shifts: cpu.config.strict_shifts,
+            written: Vec::new(),
+        }
+    }
+
+    fn index(&self, addr: i64) -> Result<usize, ReferenceError> {
+        let index = addr + (self.mem.len() / 2) as i64;
+        if (0..self.mem.len() as i64).contains(&index) {
+            Ok(index as usize)
+        } else {
+            Err(ReferenceError::BadAddress(addr))
+        }
+    }
+
+    /// The cell at `addr`.
+    pub fn peek(&self, addr: i64) -> Result<i64, ReferenceError> {
+        Ok(self.mem[self.index(addr)?])
+    }
+
+    fn is_port(&self, addr: i64) -> bool {
+        self.io && (addr == TAPE_READER_ADDR as i64 || addr == TELETYPE_ADDR as i64)
+    }
+
+    fn load(&mut self, addr: i64) -> Result<i64, ReferenceError> {
+        if self.is_port(addr) {
+            return if addr == TAPE_READER_ADDR as i64 {
+                self.input.pop_front().ok_or(ReferenceError::InputExhausted)
+            } else {
+                Ok(0)
+            };
+        }
+        self.peek(addr)
+    }
+
+    fn store(&mut self, addr: i64, value: i64) -> Result<(), ReferenceError> {
+        if self.is_port(addr) {
+            if addr == TELETYPE_ADDR as i64 {
+                self.output.push(value);
+            }
+            return Ok(());
+        }
+        let index = self.index(addr)?;
+        self.mem[index] = wrap(value, W9);
+        self.written.push(addr);
+        Ok(())
+    }
+
+    /// Set S, trapping or wrapping on overflow, and ω from its sign.
+    fn set_sum(&mut self, sum: i64) -> Result<(), ReferenceError> {
+        if self.trap_overflow && wrap(sum, W18) != sum {
+            return Err(ReferenceError::Overflow);
+        }
+        self.s = wrap(sum, W18);
+        self.omega = self.s.signum();
+        Ok(())
+    }
+
+    /// Execute one instruction.
+    pub fn step(&mut self) -> Result<(), ReferenceError> {
+        self.written.clear();
+        let word = self.peek(self.c)?;
+        self.c += 1;
+
+        let opcode = round_div(word, W6);
+        let mode = round_div(wrap(word, W6), W5);
+        let field = wrap(word, W5);
+        let addr = field + mode * self.f;
+
+        match opcode {
+            op::ADD => {
+                let value = self.load(addr)?;
+                self.set_sum(self.s + value)?;
+            }
+            op::SUB => {
+                let value = self.load(addr)?;
+                self.set_sum(self.s - value)?;
+            }
+            op::ADDABS => {
+                let value = self.load(addr)?;
+                self.set_sum(self.s + value.abs())?;
+            }
+            op::SUBABS => {
+                let value = self.load(addr)?;
+                self.set_sum(self.s - value.abs())?;
+            }
+            op::MUL => {
+                let product = self.s * self.load(addr)?;
+                self.r = wrap(product, W18);
+                self.s = (product - self.r) / W18;
+                self.omega = self.s.signum();
+            }
+            op::DIV => {
+                let divisor = self.load(addr)?;
+                if divisor == 0 {
+                    return Err(ReferenceError::DivisionByZero);
+                }
+                self.r = self.s % divisor;
+                self.s /= divisor;
+                self.omega = self.s.signum();
+            }
+            op::LDA | op::LDAU => {
+                self.s = self.load(addr)?;
+                self.omega = self.s.signum();
+            }
+            op::STA => self.store(addr, self.s)?,
+            op::LDF => self.f = wrap(self.load(addr)?, W5),
+            op::STF => self.store(addr, self.f)?,
+            op::LDR => self.r = self.load(addr)?,
+            op::STR => self.store(addr, self.r)?,
+            op::XCHG => {
+                let value = self.load(addr)?;
+                self.store(addr, self.s)?;
+                self.s = value;
+                self.omega = self.s.signum();
+            }
+            op::JMP => self.c = addr,
+            op::JZ if self.s == 0 => self.c = addr,
+            op::JP if self.s > 0 => self.c = addr,
+            op::JN if self.s < 0 => self.c = addr,
+            op::JOP if self.omega > 0 => self.c = addr,
+            op::JON if self.omega < 0 => self.c = addr,
+            op::JZ | op::JP | op::JN | op::JOP | op::JON | op::NOP => {}
+            op::HLT => self.halted = true,
+            op::SHL => {
+                let (shifted, lost) = match field {
+                    0..=17 => {
+                        let product = self.s * 3i64.pow(field as u32);
+                        (wrap(product, W18), product - wrap(product, W18))
+                    }
+                    _ => (0, self.s),
+                };
+                self.s = shifted;
+                self.omega = if self.strict_shifts { lost.signum() } else { shifted.signum() };
+            }
+            op::SHR => {
+                self.s = match field {
+                    0..=17 => round_div(self.s, 3i64.pow(field as u32)),
+                    _ => 0,
+                };
+                self.omega = self.s.signum();
+            }
+            _ => return Err(ReferenceError::InvalidOpcode(opcode)),
+        }
+        Ok(())
+    }
+}
+
+/// The first point where the emulator and the reference disagree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Divergence {
+    /// Zero-based number of the instruction that diverged.
+    pub step: u64,
+    /// Address of that instruction.
+    pub pc: i32,
+    /// Its disassembly.
+    pub instruction: String,
+    /// What differs: a register (`S`, `R`, `F`, `C`, `W`), `[addr]`,
+    /// `state`, `output` or `error`.
+    pub field: String,
+    /// The emulator's value.
+    pub emulator: String,
+    /// The reference's value.
+    pub reference: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step {} at {:+} ({}): {} is {} in the emulator but {} in the reference",
+            self.step, self.pc, self.instruction, self.field, self.emulator, self.reference)
+    }
+}
+
+/// Outcome of [`verify`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyReport {
+    /// Instructions both sides executed and agreed on.
+    pub steps: u64,
+    /// Why the run stopped (`Error` covers errors both sides raised).
+    pub stop_reason: StopReason,
+    /// The error both sides stopped with, as the emulator reported it.
+    pub error: Option<String>,
+    /// The first disagreement, if any.
+    pub divergence: Option<Divergence>,
+}
+
+impl VerifyReport {
+    /// Whether the emulator matched the reference throughout.
+    pub fn passed(&self) -> bool {
+        self.divergence.is_none()
+    }
+}
+
+/// Run `cpu` for at most `max_steps` instructions, checking every step
+/// against the [`Reference`] model started from the same state.
+pub fn verify(mut cpu: Cpu, max_steps: u64) -> VerifyReport {
+    let mut reference = Reference::from_cpu(&cpu);
+    cpu.mem.take_changes();
+    let mut report = VerifyReport {
+        steps: 0,
+        stop_reason: StopReason::CycleLimit,
+        error: None,
+        divergence: None,
+    };
+
+    while report.steps < max_steps {
+        if !cpu.is_running() {
+            report.stop_reason = StopReason::Halted;
+            break;
+        }
+        let pc = cpu.regs.c.to_i32();
+        let word = cpu.mem.read_ternary(cpu.regs.c).unwrap_or_default();
+        let diverged = |field: &str, emulator: String, reference: String| Divergence {
+            step: report.steps,
+            pc,
+            instruction: disassemble_instruction(word),
+            field: field.to_string(),
+            emulator,
+            reference,
+        };
+
+        match (cpu.step(), reference.step()) {
+            (Ok(_), Ok(())) => {}
+            (Err(e), Err(_)) => {
+                report.stop_reason = StopReason::Error;
+                report.error = Some(e.to_string());
+                break;
+            }
+            (emulated, expected) => {
+                let describe = |error: Option<String>| error.unwrap_or_else(|| "ok".into());
+                report.divergence = Some(diverged(
+                    "error",
+                    describe(emulated.err().map(|e| e.to_string())),
+                    describe(expected.err().map(|e| e.to_string())),
+                ));
+                break;
+            }
+        }
+
+        if let Some((field, emulator, expected)) = compare(&mut cpu, &mut reference) {
+            report.divergence = Some(diverged(&field, emulator, expected));
+            break;
+        }
+        report.steps += 1;
+    }
+
+    if report.passed() {
+        if let Some((field, emulator, expected)) = compare_memory(&cpu, &reference) {
+            report.divergence = Some(Divergence {
+                step: report.steps,
+                pc: cpu.regs.c.to_i32(),
+                instruction: String::from("(final memory)"),
+                field,
+                emulator,
+                reference: expected,
+            });
+        }
+    }
+    report
+}
+
+/// The first mismatch after a step, as (field, emulator, reference).
+fn compare(cpu: &mut Cpu, reference: &mut Reference) -> Option<(String, String, String)> {
+    let registers = [
+        ("S", cpu.regs.s.to_i64(), reference.s),
+        ("R", cpu.regs.r.to_i64(), reference.r),
+        ("F", cpu.regs.f.to_i32() as i64, reference.f),
+        ("C", cpu.regs.c.to_i32() as i64, reference.c),
+        ("W", i8::from(cpu.regs.omega) as i64, reference.omega),
+    ];
+    for (name, emulated, expected) in registers {
+        if emulated != expected {
+            return Some((name.to_string(), emulated.to_string(), expected.to_string()));
+        }
+    }
+
+    let halted = cpu.state != CpuState::Running;
+    if halted != reference.halted {
+        return Some(("state".into(), format!("{:?}", cpu.state), if reference.halted { "Halted" } else { "Running" }.into()));
+    }
+
+    // Cells either side wrote this step
+    let mut written: BTreeSet<i64> = reference.written.iter().copied().collect();
+    written.extend(cpu.mem.take_changes().into_iter().map(|i| cpu.mem.index_to_addr(i).to_i32() as i64));
+    for addr in written {
+        let emulated = cpu.mem.read_ternary(Tryte9::from_i32(addr as i32)).map(|w| w.to_i32() as i64).ok();
+        let expected = reference.peek(addr).ok();
+        if emulated != expected {
+            return Some((format!("[{}]", addr), format!("{:?}", emulated), format!("{:?}", expected)));
+        }
+    }
+
+    let output: Vec<i64> = cpu.io.take_output().iter().map(|w| w.to_i32() as i64).collect();
+    let expected = std::mem::take(&mut reference.output);
+    if output != expected {
+        return Some(("output".into(), format!("{:?}", output), format!("{:?}", expected)));
+    }
+    None
+}
+
+/// Compare every memory cell.
+fn compare_memory(cpu: &Cpu, reference: &Reference) -> Option<(String, String, String)> {
+    (0..cpu.mem.size()).find_map(|i| {
+        let emulated = cpu.mem.read(i).to_i32() as i64;
+        (emulated != reference.mem[i]).then(|| {
+            let addr = cpu.mem.index_to_addr(i).to_i32();
+            (format!("[{}]", addr), emulated.to_string(), reference.mem[i].to_string())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::cpu::CpuConfig;
+    use crate::ternary::Word18;
+    use proptest::prelude::*;
+
+    fn loaded(source: &str) -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.load_program(&assemble(source).unwrap()).unwrap();
+        cpu
+    }
+
+    #[test]
+    fn test_examples_agree_with_reference() {
+        for example in crate::examples::EXAMPLES {
+            let mut cpu = loaded(example.source);
+            cpu.io.enable();
+            cpu.io.feed((1..=20).map(Tryte9::from_i32));
+            let report = verify(cpu, 5_000);
+            assert!(report.passed(), "{}: {}", example.name, report.divergence.unwrap());
+        }
+    }
+
+    #[test]
+    fn test_errors_must_match() {
+        let report = verify(loaded("DIV 2\nHLT\nDAT 0"), 10);
+        assert!(report.passed());
+        assert_eq!(report.stop_reason, StopReason::Error);
+
+        let mut cpu = Cpu::with_config(CpuConfig { overflow: OverflowPolicy::Trap, ..CpuConfig::default() });
+        cpu.load_program(&assemble("ADD 2\nHLT\nDAT 1").unwrap()).unwrap();
+        cpu.regs.s = Word18::from_i64(Word18::MAX);
+        let report = verify(cpu, 10);
+        assert!(report.passed());
+        assert_eq!(report.error.as_deref(), Some("arithmetic overflow"));
+    }
+
+    #[test]
+    fn test_divergence_is_reported() {
+        let mut cpu = loaded("LDR 2\nHLT\nDAT 7");
+        let mut reference = Reference::from_cpu(&cpu);
+        reference.mem[cpu.mem.origin() + 2] = 8;
+        cpu.step().unwrap();
+        reference.step().unwrap();
+        assert_eq!(compare(&mut cpu, &mut reference), Some(("R".into(), "7".into(), "8".into())));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_arithmetic_agrees(a in -9841i32..=9841, b in -9841i32..=9841, s in Word18::MIN..=Word18::MAX, shift in 0i32..20) {
+            let source = format!(
+                "ADD 9\nMUL 10\nSUB 9\nADDABS 10\nSHR {}\nSUBABS 9\nSHL {}\nDIV 10\nHLT\nDAT {}\nDAT {}",
+                shift, shift, a, if b == 0 { 1 } else { b }
+            );
+            let mut cpu = loaded(&source);
+            cpu.regs.s = Word18::from_i64(s);
+            let report = verify(cpu, 20);
+            prop_assert!(report.passed(), "{}", report.divergence.unwrap());
+        }
+
+        #[test]
+        fn prop_strict_shift_agrees(s in Word18::MIN..=Word18::MAX, shift in 0i32..20) {
+            let mut cpu = Cpu::with_config(CpuConfig { strict_shifts: true, ..CpuConfig::default() });
+            cpu.load_program(&assemble(&format!("SHL {}\nHLT", shift)).unwrap()).unwrap();
+            cpu.regs.s = Word18::from_i64(s);
+            let report = verify(cpu, 5);
+            prop_assert!(report.passed(), "{}", report.divergence.unwrap());
+        }
+    }
+}
@@ -1,18 +1,25 @@
 //! Debugger application state and logic.
 
-use crate::{Cpu, Tryte9, Instruction};
-use crate::asm::disasm::disassemble_instruction;
+use crate::Tryte9;
+use crate::asm::disasm::{disassemble_instruction, disassemble_instruction_for};
 use crate::cpu::decode::encode;
-use std::collections::HashSet;
+use crate::cpu::{ConsoleDevice, Coverage, CpuError, DeviceError, DeviceId, Snapshot};
+use crate::ternary::arith::explain;
+use crate::ternary::parse_number;
+use crate::harness::Condition;
+use crate::session::EmulatorSession;
+use super::transcript::{Transcript, TranscriptFormat};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How long each tick of `:until` may run before the screen is redrawn.
+const UNTIL_SLICE: Duration = Duration::from_millis(40);
 
 /// Debugger application state.
 pub struct DebuggerApp {
-    /// The CPU being debugged.
-    pub cpu: Cpu,
-    /// Original program for reference.
-    pub program: Vec<Tryte9>,
-    /// Breakpoints (by address).
-    pub breakpoints: HashSet<i32>,
+    /// The machine being debugged, with its program and breakpoints.
+    pub session: EmulatorSession,
     /// Is the debugger running continuously?
     pub running: bool,
     /// Should we quit?
@@ -23,108 +30,411 @@ pub struct DebuggerApp {
     pub mem_scroll: usize,
     /// Selected memory address.
     pub selected_addr: usize,
+    /// Command line being typed after `:`, if any.
+    pub command: Option<String>,
+    /// Arithmetic explanation shown over the debugger until a key is pressed.
+    pub explanation: Option<String>,
+    /// The attached console device, if it could be mapped.
+    console: Option<DeviceId>,
+    /// Everything the program has printed on the console.
+    pub console_text: String,
+    /// Cells and branch directions executed so far, kept across resets.
+    pub coverage: Coverage,
+    /// Cells shown in the variables panel, with their names (saved in
+    /// snapshots).
+    pub pins: BTreeMap<i32, String>,
+    /// Labels of the program, for naming and finding cells.
+    symbols: BTreeMap<String, i32>,
+    /// The `:until` condition being run to, with its text and the cycle
+    /// count it started at.
+    until: Option<(String, Condition, u64)>,
+    /// Everything done in the debugger, for `:export`.
+    pub transcript: Transcript,
+    /// The action that started the current continuous run, recorded in
+    /// the transcript when the run stops.
+    active: Option<String>,
 }
 
 impl DebuggerApp {
     /// Create a new debugger with a loaded program.
     pub fn new(program: Vec<Tryte9>) -> Self {
-        let mut cpu = Cpu::new();
-        let _ = cpu.load_program(&program);
-        
+        Self::with_layout(program, 0, 0)
+    }
+    
+    /// Create a debugger with the program loaded at `load_at` and the PC at `entry`.
+    pub fn with_layout(program: Vec<Tryte9>, load_at: i32, entry: i32) -> Self {
+        let mut session = EmulatorSession::new();
+        let status = match session.load_with_layout(program, load_at, entry) {
+            Ok(()) => "Ready. Press 's' to step, 'r' to run, 'q' to quit.".into(),
+            Err(e) => format!("Error: {}", e),
+        };
+        Self::with_session(session, status)
+    }
+    
+    /// Resume a debugging session from a snapshot.
+    pub fn from_snapshot(mut snapshot: Snapshot) -> Self {
+        let pins = std::mem::take(&mut snapshot.pins);
+        let session = EmulatorSession::from_snapshot(snapshot);
+        let status = format!("Restored at cycle {}. Press 's' to step, 'r' to run, 'q' to quit.", session.cpu.cycles);
+        Self { pins, ..Self::with_session(session, status) }
+    }
+    
+    /// Use the program's labels to name pinned cells and to pin by label.
+    pub fn with_symbols(mut self, symbols: BTreeMap<String, i32>) -> Self {
+        self.symbols = symbols;
+        self
+    }
+    
+    fn with_session(mut session: EmulatorSession, status: String) -> Self {
+        let console = session.cpu.devices.attach(ConsoleDevice::new()).ok();
         Self {
-            cpu,
-            program,
-            breakpoints: HashSet::new(),
+            session,
             running: false,
             should_quit: false,
-            status: "Ready. Press 's' to step, 'r' to run, 'q' to quit.".into(),
+            status,
             mem_scroll: 0,
             selected_addr: 81, // Address 0 (middle of memory)
+            command: None,
+            explanation: None,
+            console,
+            console_text: String::new(),
+            coverage: Coverage::new(),
+            pins: BTreeMap::new(),
+            symbols: BTreeMap::new(),
+            until: None,
+            transcript: Transcript::new(),
+            active: None,
         }
     }
     
+    /// Capture the current machine, program, layout and pinned cells.
+    pub fn snapshot(&self) -> Snapshot {
+        self.session.snapshot().with_pins(self.pins.clone())
+    }
+    
     /// Step one instruction.
     pub fn step(&mut self) {
-        if !self.cpu.is_running() {
-            self.status = format!("CPU halted: {:?}", self.cpu.state);
+        if !self.session.cpu.is_running() {
+            self.status = format!("CPU halted: {:?}", self.session.cpu.state);
             self.running = false;
             return;
         }
         
-        let pc = self.cpu.regs.c.to_i32();
-        match self.cpu.step() {
+        let pc = self.session.cpu.regs.c.to_i32();
+        let result = self.session.step();
+        self.drain_console();
+        match result {
             Ok(instr) => {
+                self.coverage.record_step(pc, &instr, self.session.cpu.regs.c.to_i32());
                 let disasm = disassemble_instruction(encode(&instr));
                 self.status = format!("PC={:03}: {}", pc, disasm);
             }
+            Err(CpuError::Device(DeviceError::Failed { device, .. })) if device == "console" => {
+                // Retry the read once something has been typed
+                self.session.cpu.regs.c = Tryte9::from_i32(pc);
+                self.status = "Waiting for console input: type :type <text>".into();
+                self.running = false;
+            }
             Err(e) => {
                 self.status = format!("Error: {}", e);
                 self.running = false;
             }
         }
+        if self.active.is_none() {
+            self.record("step");
+        }
+    }
+    
+    /// Add `action` to the transcript, with the current status and registers.
+    fn record(&mut self, action: &str) {
+        self.transcript.record(action, self.status.clone(), &self.session.cpu);
+    }
+    
+    /// Move console output into the console panel.
+    fn drain_console(&mut self) {
+        if let Some(device) = self.console.and_then(|id| self.session.cpu.devices.get_mut::<ConsoleDevice>(id)) {
+            self.console_text.push_str(&device.take_output());
+        }
+    }
+    
+    /// Type a line on the console for the program to read.
+    pub fn type_on_console(&mut self, text: &str) {
+        match self.console.and_then(|id| self.session.cpu.devices.get_mut::<ConsoleDevice>(id)) {
+            Some(device) => {
+                device.feed(text);
+                device.feed("\n");
+                self.status = format!("Typed {} characters", text.chars().count() + 1);
+            }
+            None => self.status = "No console attached".into(),
+        }
     }
     
     /// Run until halt, breakpoint, or error.
     pub fn run(&mut self) {
         self.running = true;
+        self.active = Some("run".into());
         self.status = "Running...".into();
     }
     
+    /// Run until `expr` (see [`Condition`]) holds, a breakpoint is
+    /// reached or the machine stops. Nothing runs if it already holds.
+    pub fn run_until(&mut self, expr: &str) {
+        match expr.parse::<Condition>() {
+            Ok(condition) if condition.holds(&self.session.cpu) => {
+                self.status = format!("{} already holds", expr);
+            }
+            Ok(condition) => {
+                self.until = Some((expr.to_string(), condition, self.session.cpu.cycles));
+                self.running = true;
+                self.active = Some(format!(":until {}", expr));
+                self.status = format!("Running until {}... (p to cancel)", expr);
+            }
+            Err(e) => self.status = format!("Error: {}", e),
+        }
+    }
+    
+    /// Stop continuous execution, abandoning any `:until`.
+    pub fn pause(&mut self) {
+        self.running = false;
+        self.status = match self.until.take() {
+            Some((expr, _, start)) => format!(
+                "Cancelled :until {} after {} cycles", expr, self.session.cpu.cycles - start
+            ),
+            None => "Paused.".into(),
+        };
+        let action = self.active.take().unwrap_or_else(|| "pause".into());
+        self.record(&action);
+    }
+    
     /// Run one iteration of continuous execution.
     pub fn tick(&mut self) {
         if !self.running {
+            self.until = None;
             return;
         }
-        
-        if !self.cpu.is_running() {
+        if self.until.is_some() {
+            self.tick_until();
+        } else if !self.session.cpu.is_running() {
             self.running = false;
-            self.status = format!("Halted after {} cycles", self.cpu.cycles);
-            return;
+            self.status = format!("Halted after {} cycles", self.session.cpu.cycles);
+        } else {
+            self.step();
+            if self.running && self.session.cpu.is_running() && self.session.at_breakpoint() {
+                self.running = false;
+                self.status = format!("Breakpoint at PC={}", self.session.cpu.regs.c.to_i32());
+            }
         }
-        
-        // Check for breakpoint
-        let pc = self.cpu.regs.c.to_i32();
-        if self.breakpoints.contains(&pc) {
-            self.running = false;
-            self.status = format!("Breakpoint at PC={}", pc);
+        if !self.running {
+            if let Some(action) = self.active.take() {
+                self.record(&action);
+            }
+        }
+    }
+    
+    /// Run a slice of a `:until`, stepping one instruction at a time so
+    /// coverage, the console and breakpoints work as in a normal run.
+    fn tick_until(&mut self) {
+        let Some((expr, condition, start)) = self.until.take() else {
             return;
+        };
+        let deadline = Instant::now() + UNTIL_SLICE;
+        while Instant::now() < deadline {
+            self.step();
+            let cpu = &self.session.cpu;
+            if !self.running {
+                // `step` has reported the error
+                return;
+            }
+            if !cpu.is_running() {
+                self.running = false;
+                self.status = format!("Halted after {} cycles; {} never held", cpu.cycles - start, expr);
+                return;
+            }
+            if condition.holds(cpu) {
+                self.running = false;
+                self.status = format!(
+                    "{} holds at PC={} after {} cycles", expr, cpu.regs.c.to_i32(), cpu.cycles - start
+                );
+                return;
+            }
+            if self.session.at_breakpoint() {
+                self.running = false;
+                self.status = format!("Breakpoint at PC={} before {} held", cpu.regs.c.to_i32(), expr);
+                return;
+            }
         }
-        
-        self.step();
+        self.status = format!(
+            "Running until {}... {} cycles (p to cancel)", expr, self.session.cpu.cycles - start
+        );
+        self.until = Some((expr, condition, start));
     }
     
     /// Toggle breakpoint at current PC or selected address.
     pub fn toggle_breakpoint(&mut self) {
-        let pc = self.cpu.regs.c.to_i32();
-        if self.breakpoints.contains(&pc) {
-            self.breakpoints.remove(&pc);
-            self.status = format!("Removed breakpoint at PC={}", pc);
+        let pc = self.session.cpu.regs.c.to_i32();
+        self.status = if self.session.toggle_breakpoint(pc) {
+            format!("Set breakpoint at PC={}", pc)
         } else {
-            self.breakpoints.insert(pc);
-            self.status = format!("Set breakpoint at PC={}", pc);
-        }
+            format!("Removed breakpoint at PC={}", pc)
+        };
+        self.record("breakpoint");
     }
     
     /// Reset CPU to initial state.
     pub fn reset(&mut self) {
-        self.cpu = Cpu::new();
-        let _ = self.cpu.load_program(&self.program);
         self.running = false;
-        self.status = "Reset. Ready.".into();
+        self.active = None;
+        self.console_text.clear();
+        self.status = match self.session.reset() {
+            Ok(()) => "Reset. Ready.".into(),
+            Err(e) => format!("Error: {}", e),
+        };
+        self.record("reset");
+    }
+    
+    /// Execute a `:` command line.
+    pub fn execute_command(&mut self, line: &str) {
+        let line = line.trim();
+        let (name, arg) = line.split_once(' ').unwrap_or((line, ""));
+        match name {
+            "" => return,
+            "export" if arg.trim().is_empty() => self.status = "Usage: :export <file.md|file.html>".into(),
+            "export" => {
+                self.export(arg.trim());
+                return;
+            }
+            "script" if arg.trim().is_empty() => self.status = "Usage: :script <file.rhai>".into(),
+            "type" => self.type_on_console(arg),
+            "coverage" if arg.trim() == "clear" => {
+                self.coverage = Coverage::new();
+                self.status = "Coverage cleared".into();
+            }
+            "coverage" => {
+                let report = self.coverage.report(self.session.load_address(), self.session.program().len());
+                self.status = format!("Coverage: {}", report);
+            }
+            "explain" if arg.trim().is_empty() => self.status = "Usage: :explain <a> +|-|* <b>".into(),
+            "explain" => match explain(arg) {
+                Ok(e) => self.explanation = Some(e.to_string()),
+                Err(e) => self.status = format!("Error: {}", e),
+            },
+            "until" if arg.trim().is_empty() => self.status = "Usage: :until <condition>, e.g. :until S < 0".into(),
+            "until" => self.run_until(arg.trim()),
+            "pin" if arg.trim().is_empty() => self.status = "Usage: :pin <addr|label> [name]".into(),
+            "pin" => self.pin(arg),
+            "unpin" => self.unpin(arg.trim()),
+            #[cfg(feature = "script")]
+            "script" => self.run_script(arg.trim()),
+            _ => self.status = format!("Unknown command: {}", name),
+        }
+        // A command that started a run is recorded when the run stops
+        if self.active.is_none() {
+            self.record(&format!(":{}", line));
+        }
+    }
+    
+    /// The transcript of this session so far, with the final state, as
+    /// `format`.
+    pub fn render_transcript(&self, format: TranscriptFormat) -> String {
+        let coverage = self.coverage.report(self.session.load_address(), self.session.program().len());
+        self.transcript.render(format, &self.session, &self.console_text, &coverage.to_string())
+    }
+    
+    /// Write the transcript to `path`: HTML for a `.html` file, otherwise
+    /// Markdown.
+    pub fn export(&mut self, path: &str) {
+        let text = self.render_transcript(TranscriptFormat::for_path(Path::new(path)));
+        self.status = match std::fs::write(path, text) {
+            Ok(()) => format!("Exported {} actions to {}", self.transcript.entries().len(), path),
+            Err(e) => format!("Error: cannot write {}: {}", path, e),
+        };
+    }
+    
+    /// Pin the cell named by `arg` (`<addr|label> [name]`) to the
+    /// variables panel. Without a name, a label at the address is used.
+    pub fn pin(&mut self, arg: &str) {
+        let (cell, name) = arg.trim().split_once(' ').unwrap_or((arg.trim(), ""));
+        let Some(addr) = self.resolve_cell(cell) else {
+            self.status = format!("No cell {}", cell);
+            return;
+        };
+        let name = match name.trim() {
+            "" => self.symbols.iter().find(|(_, &a)| a == addr).map(|(label, _)| label.clone()).unwrap_or_default(),
+            name => name.to_string(),
+        };
+        self.status = format!("Pinned {}", pin_label(addr, &name));
+        self.pins.insert(addr, name);
+    }
+    
+    /// Unpin the cell named by `arg`, by address, label or pin name;
+    /// with no argument, unpin everything.
+    pub fn unpin(&mut self, arg: &str) {
+        if arg.is_empty() {
+            self.pins.clear();
+            self.status = "Unpinned all cells".into();
+            return;
+        }
+        let addr = self.pins.iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(arg))
+            .map(|(&addr, _)| addr)
+            .or_else(|| self.resolve_cell(arg));
+        self.status = match addr.and_then(|addr| self.pins.remove(&addr).map(|name| pin_label(addr, &name))) {
+            Some(label) => format!("Unpinned {}", label),
+            None => format!("{} is not pinned", arg),
+        };
+    }
+    
+    /// The address of a cell given as a number or a label, if in memory.
+    fn resolve_cell(&self, cell: &str) -> Option<i32> {
+        let addr = match self.symbols.get(&cell.to_uppercase()) {
+            Some(&addr) => addr,
+            None => i32::try_from(parse_number(cell).ok()?).ok()?,
+        };
+        self.session.cpu.mem.contains(addr as i64).then_some(addr)
+    }
+    
+    /// Pinned cells as `(address, label, value)`, in address order.
+    pub fn pinned_cells(&self) -> Vec<(i32, String, Tryte9)> {
+        self.pins.iter()
+            .filter_map(|(&addr, name)| {
+                let value = self.session.cpu.mem.read_ternary(Tryte9::from_i32(addr)).ok()?;
+                Some((addr, pin_label(addr, name), value))
+            })
+            .collect()
+    }
+    
+    /// Run a Rhai script against the debugged machine.
+    #[cfg(feature = "script")]
+    fn run_script(&mut self, path: &str) {
+        use crate::script::ScriptEngine;
+        
+        let mut engine = ScriptEngine::new(self.session.clone(), false);
+        let result = engine.run_file(path);
+        self.session = engine.session();
+        self.running = false;
+        
+        // The TUI has room for one line of script output
+        self.status = match (result, engine.take_printed().pop()) {
+            (Err(e), _) => format!("Script error: {}", e),
+            (Ok(()), Some(line)) => line,
+            (Ok(()), None) => format!("Script {} finished", path),
+        };
     }
     
     /// Get disassembly around current PC.
     pub fn get_disassembly(&self, lines: usize) -> Vec<(i32, String, bool)> {
-        let pc = self.cpu.regs.c.to_i32();
-        let start = (pc - (lines as i32 / 2)).max(-81);
+        let cpu = &self.session.cpu;
+        let pc = cpu.regs.c.to_i32();
+        let origin = cpu.mem.origin() as i32;
+        let start = (pc - (lines as i32 / 2)).max(-origin);
         
         (0..lines as i32)
             .filter_map(|i| {
                 let addr = start + i;
-                let idx = (addr + 81) as usize;
-                if idx < 162 {
-                    let instr = self.cpu.mem.read(idx);
-                    let disasm = disassemble_instruction(instr);
+                let idx = (addr + origin) as usize;
+                if idx < cpu.mem.size() {
+                    let instr = cpu.mem.read(idx);
+                    let disasm = disassemble_instruction_for(instr, &cpu.config);
                     let is_current = addr == pc;
                     Some((addr, disasm, is_current))
                 } else {
@@ -135,8 +445,27 @@ impl DebuggerApp {
     }
 }
 
+/// How a pinned cell is shown: its name and address, or just the address.
+fn pin_label(addr: i32, name: &str) -> String {
+    if name.is_empty() {
+        format!("[{}]", addr)
+    } else {
+        format!("{} [{}]", name, addr)
+    }
+}
+
 /// Run the debugger with a program.
 pub fn run_debugger(program: Vec<Tryte9>) -> std::io::Result<()> {
+    run_debugger_at(program, 0, 0)
+}
+
+/// Run the debugger with a program loaded at `load_at`, starting at `entry`.
+pub fn run_debugger_at(program: Vec<Tryte9>, load_at: i32, entry: i32) -> std::io::Result<()> {
+    run_debugger_app(DebuggerApp::with_layout(program, load_at, entry)).map(|_| ())
+}
+
+/// Run the debugger on a prepared app, returning its final state on quit.
+pub fn run_debugger_app(mut app: DebuggerApp) -> std::io::Result<DebuggerApp> {
     use crossterm::{
         event::{self, Event, KeyCode, KeyEventKind},
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
@@ -151,9 +480,6 @@ pub fn run_debugger(program: Vec<Tryte9>) -> std::io::Result<()> {
     stdout().execute(EnterAlternateScreen)?;
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
     
-    // Create app
-    let mut app = DebuggerApp::new(program);
-    
     // Main loop
     loop {
         // Draw
@@ -165,6 +491,25 @@ pub fn run_debugger(program: Vec<Tryte9>) -> std::io::Result<()> {
         if event::poll(Duration::from_millis(50))? {
             if let Event::Key(key) = event::read()? {
                 if key.kind == KeyEventKind::Press {
+                    if app.explanation.is_some() {
+                        app.explanation = None;
+                        continue;
+                    }
+                    if let Some(command) = app.command.as_mut() {
+                        match key.code {
+                            KeyCode::Enter => {
+                                let line = app.command.take().unwrap_or_default();
+                                app.execute_command(&line);
+                            }
+                            KeyCode::Esc => app.command = None,
+                            KeyCode::Backspace => {
+                                command.pop();
+                            }
+                            KeyCode::Char(c) => command.push(c),
+                            _ => {}
+                        }
+                        continue;
+                    }
                     match key.code {
                         KeyCode::Char('q') => app.should_quit = true,
                         KeyCode::Char('s') => {
@@ -172,21 +517,18 @@ pub fn run_debugger(program: Vec<Tryte9>) -> std::io::Result<()> {
                             app.step();
                         }
                         KeyCode::Char('r') => app.run(),
-                        KeyCode::Char('p') => {
-                            app.running = false;
-                            app.status = "Paused.".into();
-                        }
+                        KeyCode::Char('p') => app.pause(),
                         KeyCode::Char('b') => app.toggle_breakpoint(),
                         KeyCode::Char('x') => app.reset(),
-                        KeyCode::Up => {
-                            if app.mem_scroll > 0 {
-                                app.mem_scroll -= 1;
-                            }
+                        KeyCode::Char(':') => {
+                            app.running = false;
+                            app.command = Some(String::new());
                         }
-                        KeyCode::Down => {
-                            if app.mem_scroll < 150 {
-                                app.mem_scroll += 1;
-                            }
+                        KeyCode::Up if app.mem_scroll > 0 => {
+                            app.mem_scroll -= 1;
+                        }
+                        KeyCode::Down if app.mem_scroll < 150 => {
+                            app.mem_scroll += 1;
                         }
                         _ => {}
                     }
@@ -208,5 +550,5 @@ pub fn run_debugger(program: Vec<Tryte9>) -> std::io::Result<()> {
     disable_raw_mode()?;
     stdout().execute(LeaveAlternateScreen)?;
     
-    Ok(())
+    Ok(app)
 }
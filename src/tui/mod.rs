@@ -5,8 +5,11 @@
 //! - Memory view with trit coloring
 //! - Step/run/breakpoint controls
 //! - Disassembly view
+//! - Session transcripts exported as Markdown or HTML
 
 mod app;
+mod transcript;
 mod ui;
 
-pub use app::{DebuggerApp, run_debugger};
+pub use app::{DebuggerApp, run_debugger, run_debugger_at, run_debugger_app};
+pub use transcript::{RegisterRow, Transcript, TranscriptEntry, TranscriptFormat};
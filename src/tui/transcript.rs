@@ -0,0 +1,226 @@
+//! Transcripts of debugging sessions.
+//!
+//! The debugger records every action taken in it — keys, `:` commands,
+//! breakpoints set and cleared, steps and runs — with the registers after
+//! each one. `:export <file>` writes the record, followed by the final
+//! state of the machine, as Markdown or, for a `.html` file, a standalone
+//! HTML page that can be handed in as evidence of the debugging process.
+
+use crate::cpu::{Cpu, CpuState};
+use crate::session::EmulatorSession;
+use crate::ternary::Trit;
+use std::fmt::Write;
+use std::path::Path;
+
+/// The registers after an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterRow {
+    pub cycles: u64,
+    pub c: i32,
+    pub s: i64,
+    pub r: i64,
+    pub f: i32,
+    pub omega: Trit,
+}
+
+impl RegisterRow {
+    fn of(cpu: &Cpu) -> Self {
+        Self {
+            cycles: cpu.cycles,
+            c: cpu.regs.c.to_i32(),
+            s: cpu.regs.s.to_i64(),
+            r: cpu.regs.r.to_i64(),
+            f: cpu.regs.f.to_i32(),
+            omega: cpu.regs.omega,
+        }
+    }
+}
+
+/// One recorded action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptEntry {
+    /// What was done, e.g. "step" or ":pin 20".
+    pub action: String,
+    /// What the debugger reported.
+    pub outcome: String,
+    /// The registers afterwards.
+    pub regs: RegisterRow,
+}
+
+/// Output format of an exported transcript.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    Markdown,
+    Html,
+}
+
+impl TranscriptFormat {
+    /// HTML for `.html` and `.htm` files, Markdown otherwise.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("html" | "htm") => TranscriptFormat::Html,
+            _ => TranscriptFormat::Markdown,
+        }
+    }
+}
+
+/// The actions of a debugging session, in order.
+#[derive(Debug, Clone, Default)]
+pub struct Transcript {
+    entries: Vec<TranscriptEntry>,
+}
+
+impl Transcript {
+    /// An empty transcript.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `action`, its outcome and the registers of `cpu` after it.
+    pub fn record(&mut self, action: impl Into<String>, outcome: impl Into<String>, cpu: &Cpu) {
+        self.entries.push(TranscriptEntry { action: action.into(), outcome: outcome.into(), regs: RegisterRow::of(cpu) });
+    }
+
+    /// The recorded actions.
+    pub fn entries(&self) -> &[TranscriptEntry] {
+        &self.entries
+    }
+
+    /// Render the transcript, followed by the final state of `session`
+    /// and the console output, as `format`.
+    pub fn render(&self, format: TranscriptFormat, session: &EmulatorSession, console: &str, coverage: &str) -> String {
+        let cpu = &session.cpu;
+        let history = Table {
+            headers: &["#", "Action", "Outcome", "Cycles", "C", "S", "R", "F", "ω"],
+            rows: self.entries.iter().enumerate().map(|(i, entry)| {
+                let regs = entry.regs;
+                vec![
+                    (i + 1).to_string(), entry.action.clone(), entry.outcome.clone(), regs.cycles.to_string(),
+                    regs.c.to_string(), regs.s.to_string(), regs.r.to_string(), regs.f.to_string(), regs.omega.to_string(),
+                ]
+            }).collect(),
+        };
+        let state = match cpu.state {
+            CpuState::Running => "running",
+            CpuState::Halted => "halted",
+            CpuState::Error => "stopped on an error",
+        };
+        let breakpoints = if session.breakpoints().is_empty() {
+            "none".to_string()
+        } else {
+            session.breakpoints().iter().map(i32::to_string).collect::<Vec<_>>().join(", ")
+        };
+        let final_state = Table {
+            headers: &["", "Value"],
+            rows: vec![
+                vec!["State".into(), state.into()],
+                vec!["Cycles".into(), cpu.cycles.to_string()],
+                vec!["C".into(), cpu.regs.c.to_i32().to_string()],
+                vec!["S".into(), format!("{} ({})", cpu.regs.s.to_i64(), cpu.regs.s)],
+                vec!["R".into(), format!("{} ({})", cpu.regs.r.to_i64(), cpu.regs.r)],
+                vec!["F".into(), format!("{} ({})", cpu.regs.f.to_i32(), cpu.regs.f)],
+                vec!["ω".into(), cpu.regs.omega.to_string()],
+                vec!["Breakpoints".into(), breakpoints],
+                vec!["Coverage".into(), coverage.into()],
+            ],
+        };
+
+        let mut out = String::new();
+        match format {
+            TranscriptFormat::Markdown => {
+                let _ = writeln!(out, "# Debugging session\n\n## Actions\n");
+                history.markdown(&mut out);
+                let _ = writeln!(out, "\n## Final state\n");
+                final_state.markdown(&mut out);
+                if !console.is_empty() {
+                    let _ = writeln!(out, "\n## Console\n\n```\n{}\n```", console.trim_end());
+                }
+            }
+            TranscriptFormat::Html => {
+                out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Debugging session</title>\n");
+                out.push_str("<style>body{font-family:sans-serif}table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:2px 6px;text-align:left}</style>\n");
+                out.push_str("</head>\n<body>\n<h1>Debugging session</h1>\n<h2>Actions</h2>\n");
+                history.html(&mut out);
+                out.push_str("<h2>Final state</h2>\n");
+                final_state.html(&mut out);
+                if !console.is_empty() {
+                    let _ = writeln!(out, "<h2>Console</h2>\n<pre>{}</pre>", escape_html(console.trim_end()));
+                }
+                out.push_str("</body>\n</html>\n");
+            }
+        }
+        out
+    }
+}
+
+/// A table rendered into either format.
+struct Table {
+    headers: &'static [&'static str],
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn markdown(&self, out: &mut String) {
+        let _ = writeln!(out, "| {} |", self.headers.join(" | "));
+        let _ = writeln!(out, "|{}", "---|".repeat(self.headers.len()));
+        for row in &self.rows {
+            let cells: Vec<String> = row.iter().map(|cell| cell.replace('|', "\\|")).collect();
+            let _ = writeln!(out, "| {} |", cells.join(" | "));
+        }
+    }
+
+    fn html(&self, out: &mut String) {
+        out.push_str("<table>\n<tr>");
+        for header in self.headers {
+            let _ = write!(out, "<th>{}</th>", escape_html(header));
+        }
+        out.push_str("</tr>\n");
+        for row in &self.rows {
+            out.push_str("<tr>");
+            for cell in row {
+                let _ = write!(out, "<td>{}</td>", escape_html(cell));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+    }
+}
+
+/// Escape text for HTML element content.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::tui::DebuggerApp;
+
+    #[test]
+    fn test_transcript_records_session() {
+        let mut app = DebuggerApp::new(assemble("LDA 4\nADD 4\nSTA 5\nHLT\nDAT 21").unwrap());
+        app.step();
+        app.execute_command("pin 5 total");
+        app.toggle_breakpoint();
+        app.run();
+        while app.running {
+            app.tick();
+        }
+
+        let actions: Vec<&str> = app.transcript.entries().iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, ["step", ":pin 5 total", "breakpoint", "run"]);
+        assert_eq!(app.transcript.entries()[0].regs.s, 21);
+        assert_eq!(app.transcript.entries()[3].regs.s, 42);
+
+        let markdown = app.render_transcript(TranscriptFormat::Markdown);
+        assert!(markdown.contains("| 2 | :pin 5 total | Pinned total [5] |"));
+        assert!(markdown.contains("| State | halted |"));
+        assert!(markdown.contains("| Breakpoints | 1 |"));
+
+        let html = app.render_transcript(TranscriptFormat::Html);
+        assert!(html.contains("<td>run</td><td>Halted after 4 cycles</td>"));
+        assert_eq!(TranscriptFormat::for_path(Path::new("session.HTML")), TranscriptFormat::Html);
+        assert_eq!(TranscriptFormat::for_path(Path::new("session.md")), TranscriptFormat::Markdown);
+    }
+}
@@ -2,7 +2,7 @@
 
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Paragraph, List, ListItem},
+    widgets::{Block, Borders, Clear, Paragraph, List, ListItem},
     style::{Color, Style, Modifier},
 };
 use crate::Trit;
@@ -32,17 +32,28 @@ pub fn draw(frame: &mut Frame, app: &DebuggerApp) {
     draw_registers(frame, left_chunks[1], app);
     draw_status(frame, left_chunks[2], app);
     
-    // Right side: memory and help
+    // Right side: memory, pinned variables, console and help
+    let pinned = app.pinned_cells();
     let right_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Min(10),
+            Constraint::Length(if pinned.is_empty() { 0 } else { pinned.len() as u16 + 2 }),
             Constraint::Length(6),
+            Constraint::Length(11),
         ])
         .split(chunks[1]);
     
     draw_memory(frame, right_chunks[0], app);
-    draw_help(frame, right_chunks[1]);
+    if !pinned.is_empty() {
+        draw_variables(frame, right_chunks[1], &pinned);
+    }
+    draw_console(frame, right_chunks[2], app);
+    draw_help(frame, right_chunks[3]);
+    
+    if let Some(text) = &app.explanation {
+        draw_explanation(frame, text);
+    }
 }
 
 /// Draw disassembly view with colored trits.
@@ -53,15 +64,19 @@ fn draw_disassembly(frame: &mut Frame, area: Rect, app: &DebuggerApp) {
         .iter()
         .map(|(addr, instr, is_current)| {
             let prefix = if *is_current { "▶ " } else { "  " };
-            let bp = if app.breakpoints.contains(addr) { "●" } else { " " };
+            let bp = if app.session.breakpoints().contains(addr) { "●" } else { " " };
             let text = format!("{}{:03}: {}", prefix, addr, instr);
             
             let style = if *is_current {
                 Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-            } else if app.breakpoints.contains(addr) {
+            } else if app.session.breakpoints().contains(addr) {
                 Style::default().fg(Color::Red)
-            } else {
+            } else if app.coverage.executed.is_empty() {
                 Style::default()
+            } else if app.coverage.is_executed(*addr) {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default().fg(Color::DarkGray)
             };
             
             ListItem::new(format!("{} {}", bp, text)).style(style)
@@ -83,28 +98,28 @@ fn draw_registers(frame: &mut Frame, area: Rect, app: &DebuggerApp) {
     let content = vec![
         Line::from(vec![
             Span::raw("S: "),
-            Span::styled(format!("{:>20}", app.cpu.regs.s), Style::default().fg(Color::White)),
-            Span::raw(format!(" = {}", app.cpu.regs.s.to_i64())),
+            Span::styled(format!("{:>20}", app.session.cpu.regs.s), Style::default().fg(Color::White)),
+            Span::raw(format!(" = {}", app.session.cpu.regs.s.to_i64())),
         ]),
         Line::from(vec![
             Span::raw("R: "),
-            Span::styled(format!("{:>20}", app.cpu.regs.r), Style::default().fg(Color::White)),
-            Span::raw(format!(" = {}", app.cpu.regs.r.to_i64())),
+            Span::styled(format!("{:>20}", app.session.cpu.regs.r), Style::default().fg(Color::White)),
+            Span::raw(format!(" = {}", app.session.cpu.regs.r.to_i64())),
         ]),
         Line::from(vec![
             Span::raw("F: "),
-            Span::styled(format!("{:>5}", app.cpu.regs.f.to_i32()), Style::default().fg(Color::White)),
+            Span::styled(format!("{:>5}", app.session.cpu.regs.f.to_i32()), Style::default().fg(Color::White)),
             Span::raw("   C: "),
-            Span::styled(format!("{}", app.cpu.regs.c.to_i32()), Style::default().fg(Color::Yellow)),
+            Span::styled(format!("{}", app.session.cpu.regs.c.to_i32()), Style::default().fg(Color::Yellow)),
             Span::raw("   ω: "),
-            Span::styled(format!("{:?}", app.cpu.regs.omega), trit_style(app.cpu.regs.omega)),
+            Span::styled(format!("{:?}", app.session.cpu.regs.omega), trit_style(app.session.cpu.regs.omega)),
         ]),
         Line::from(vec![
             Span::raw("Cycles: "),
-            Span::styled(format!("{}", app.cpu.cycles), Style::default().fg(Color::Cyan)),
+            Span::styled(format!("{}", app.session.cpu.cycles), Style::default().fg(Color::Cyan)),
             Span::raw("   State: "),
-            Span::styled(format!("{:?}", app.cpu.state), 
-                if app.cpu.is_running() { 
+            Span::styled(format!("{:?}", app.session.cpu.state), 
+                if app.session.cpu.is_running() { 
                     Style::default().fg(Color::Green) 
                 } else { 
                     Style::default().fg(Color::Red) 
@@ -125,13 +140,13 @@ fn draw_registers(frame: &mut Frame, area: Rect, app: &DebuggerApp) {
 fn draw_memory(frame: &mut Frame, area: Rect, app: &DebuggerApp) {
     let visible_rows = (area.height as usize).saturating_sub(2);
     let start = app.mem_scroll;
-    let end = (start + visible_rows).min(162);
+    let end = (start + visible_rows).min(app.session.cpu.mem.size());
     
     let items: Vec<ListItem> = (start..end)
         .map(|idx| {
-            let value = app.cpu.mem.read(idx);
-            let addr = idx as i32 - 81;
-            let is_pc = addr == app.cpu.regs.c.to_i32();
+            let value = app.session.cpu.mem.read(idx);
+            let addr = idx as i32 - app.session.cpu.mem.origin() as i32;
+            let is_pc = addr == app.session.cpu.regs.c.to_i32();
             
             let text = format!("{:03}: {} = {}", addr, value, value.to_i32());
             
@@ -156,9 +171,33 @@ fn draw_memory(frame: &mut Frame, area: Rect, app: &DebuggerApp) {
     frame.render_widget(list, area);
 }
 
+/// Draw the pinned cells with their names.
+fn draw_variables(frame: &mut Frame, area: Rect, pinned: &[(i32, String, crate::Tryte9)]) {
+    let width = pinned.iter().map(|(_, label, _)| label.chars().count()).max().unwrap_or(0);
+    let items: Vec<ListItem> = pinned
+        .iter()
+        .map(|(_, label, value)| {
+            let style = if value.is_zero() { Style::default().fg(Color::DarkGray) } else { Style::default().fg(Color::White) };
+            ListItem::new(format!("{:<width$} {} = {}", label, value, value.to_i32())).style(style)
+        })
+        .collect();
+    
+    let list = List::new(items)
+        .block(Block::default()
+            .title(" Variables ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)));
+    
+    frame.render_widget(list, area);
+}
+
 /// Draw status bar.
 fn draw_status(frame: &mut Frame, area: Rect, app: &DebuggerApp) {
-    let status = Paragraph::new(app.status.clone())
+    let text = match &app.command {
+        Some(command) => format!(":{}", command),
+        None => app.status.clone(),
+    };
+    let status = Paragraph::new(text)
         .style(Style::default().fg(Color::White))
         .block(Block::default()
             .title(" Status ")
@@ -167,11 +206,34 @@ fn draw_status(frame: &mut Frame, area: Rect, app: &DebuggerApp) {
     frame.render_widget(status, area);
 }
 
+/// Draw the console, showing the last lines the program printed.
+fn draw_console(frame: &mut Frame, area: Rect, app: &DebuggerApp) {
+    let visible_rows = (area.height as usize).saturating_sub(2);
+    let lines: Vec<&str> = app.console_text.split('\n').collect();
+    let start = lines.len().saturating_sub(visible_rows);
+    let text: Vec<Line> = lines[start..].iter().map(|l| Line::from(l.to_string())).collect();
+    
+    let console = Paragraph::new(text)
+        .block(Block::default()
+            .title(" Console ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Blue)));
+    
+    frame.render_widget(console, area);
+}
+
 /// Draw help panel.
 fn draw_help(frame: &mut Frame, area: Rect) {
     let help = Paragraph::new(vec![
         Line::from("s: Step  r: Run  p: Pause  b: Breakpoint"),
         Line::from("x: Reset  ↑↓: Scroll memory  q: Quit"),
+        Line::from(":script <file>: Run a Rhai script"),
+        Line::from(":explain 5+7: Show arithmetic trit by trit"),
+        Line::from(":type <text>: Type a line on the console (117)"),
+        Line::from(":coverage [clear]: Executed cells and branches"),
+        Line::from(":pin <addr|label> [name], :unpin: Variables"),
+        Line::from(":until S < 0 && [20] != 0: Run until it holds"),
+        Line::from(":export <file.md|file.html>: Save a transcript"),
     ])
     .style(Style::default().fg(Color::DarkGray))
     .block(Block::default()
@@ -181,6 +243,28 @@ fn draw_help(frame: &mut Frame, area: Rect) {
     frame.render_widget(help, area);
 }
 
+/// Draw an arithmetic explanation centred over the other panels.
+fn draw_explanation(frame: &mut Frame, text: &str) {
+    let full = frame.area();
+    let width = (text.lines().map(|l| l.chars().count()).max().unwrap_or(0) as u16 + 4).min(full.width);
+    let height = (text.lines().count() as u16 + 2).min(full.height);
+    let area = Rect {
+        x: full.x + (full.width - width) / 2,
+        y: full.y + (full.height - height) / 2,
+        width,
+        height,
+    };
+    
+    let paragraph = Paragraph::new(text.to_string())
+        .block(Block::default()
+            .title(" Explain (any key to close) ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)));
+    
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
 /// Get color style for a trit.
 fn trit_style(t: Trit) -> Style {
     match t {
@@ -1,12 +1,20 @@
 //! WebAssembly bindings for the Setun emulator.
 //!
 //! This module provides JavaScript-friendly wrappers around the core emulator.
+//! See [`worker`] for running the emulator inside a Web Worker.
+
+pub mod worker;
 
 use wasm_bindgen::prelude::*;
-use crate::{Cpu, Tryte9, CpuState};
-use crate::asm::assembler::assemble;
+use crate::Tryte9;
+use crate::asm::assembler::{assemble, assemble_detailed, Assembly};
 use crate::asm::disasm::disassemble_instruction;
 use crate::cpu::decode::encode;
+use crate::cpu::{ConsoleDevice, CpuConfig, DeviceId, IsaDescription, Snapshot};
+use crate::session::EmulatorSession;
+use crate::ternary::arith::{explain, explain_add, explain_mul, explain_sub};
+use crate::ternary::convert::{convert, Width};
+use crate::Word18;
 
 /// Initialize panic hook for better error messages in console.
 #[wasm_bindgen(start)]
@@ -18,8 +26,9 @@ pub fn init() {
 /// WebAssembly-friendly CPU wrapper.
 #[wasm_bindgen]
 pub struct WasmCpu {
-    cpu: Cpu,
-    program: Vec<Tryte9>,
+    session: EmulatorSession,
+    console: Option<DeviceId>,
+    console_callback: Option<js_sys::Function>,
 }
 
 #[wasm_bindgen]
@@ -27,121 +36,263 @@ impl WasmCpu {
     /// Create a new CPU instance.
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
-        Self {
-            cpu: Cpu::new(),
-            program: Vec::new(),
-        }
+        Self::from_session(EmulatorSession::new())
+    }
+    
+    /// Create a CPU from a JSON machine configuration, e.g.
+    /// `{"memory_size": 729, "isa": "historical", "overflow": "trap", "timing": true}`.
+    /// Omitted fields take their defaults.
+    #[wasm_bindgen]
+    pub fn with_config(config_json: &str) -> Result<WasmCpu, JsError> {
+        let config: CpuConfig = serde_json::from_str(config_json)
+            .map_err(|e| JsError::new(&format!("invalid config: {}", e)))?;
+        config.validate().map_err(|e| JsError::new(&format!("{}", e)))?;
+        Ok(Self::from_session(EmulatorSession::with_config(config)))
+    }
+    
+    /// Create a CPU from a named preset ("simplified" or "historical").
+    #[wasm_bindgen]
+    pub fn with_preset(name: &str) -> Result<WasmCpu, JsError> {
+        let config = CpuConfig::preset(name)
+            .ok_or_else(|| JsError::new(&format!("unknown machine preset: {}", name)))?;
+        Ok(Self::from_session(EmulatorSession::with_config(config)))
+    }
+    
+    /// Current machine configuration as an object.
+    #[wasm_bindgen]
+    pub fn config(&self) -> Result<JsValue, JsError> {
+        to_js_value(&self.session.cpu.config)
     }
     
     /// Load a program from assembly source code.
     #[wasm_bindgen]
     pub fn load_asm(&mut self, source: &str) -> Result<usize, JsError> {
-        let instructions = assemble(source)
-            .map_err(|e| JsError::new(&format!("{}", e)))?;
-        
-        let len = instructions.len();
-        self.program = instructions.clone();
-        self.cpu = Cpu::new();
-        self.cpu.load_program(&instructions)
-            .map_err(|e| JsError::new(&format!("{}", e)))?;
-        
-        Ok(len)
+        self.session.load_source(source)
+            .map_err(|e| JsError::new(&format!("{}", e)))
     }
     
     /// Step one instruction. Returns the disassembled instruction.
     #[wasm_bindgen]
     pub fn step(&mut self) -> Result<String, JsError> {
-        if !self.cpu.is_running() {
+        if !self.session.cpu.is_running() {
             return Err(JsError::new("CPU is halted"));
         }
         
-        let instr = self.cpu.step()
-            .map_err(|e| JsError::new(&format!("{}", e)))?;
+        let result = self.session.cpu.step();
+        self.flush_console()?;
+        let instr = result.map_err(|e| JsError::new(&format!("{}", e)))?;
         
         Ok(disassemble_instruction(encode(&instr)))
     }
     
-    /// Run until halt or max cycles.
+    /// Execute one instruction and return its trit-serial micro-steps as
+    /// `{ record, ops }`, for animating the fetch, adder and write-back.
+    #[wasm_bindgen]
+    pub fn step_micro(&mut self) -> Result<JsValue, JsError> {
+        let result = self.session.cpu.step_micro();
+        self.flush_console()?;
+        let micro = result.map_err(|e| JsError::new(&format!("{}", e)))?;
+        to_js_value(&micro)
+    }
+    
+    /// Execute up to `n` instructions in one call.
+    ///
+    /// Returns `{ executed, stop_reason, error, changed_memory, regs }`,
+    /// avoiding a boundary crossing per instruction.
+    #[wasm_bindgen]
+    pub fn step_many(&mut self, n: u32) -> Result<JsValue, JsError> {
+        let summary = self.session.cpu.step_many(n as u64);
+        self.flush_console()?;
+        to_js_value(&summary)
+    }
+    
+    /// Run until halt, breakpoint, or max cycles. Returns the cycle count.
     #[wasm_bindgen]
     pub fn run(&mut self, max_cycles: u32) -> u64 {
-        let _ = self.cpu.run_limited(max_cycles as u64);
-        self.cpu.cycles
+        self.session.run(max_cycles as u64);
+        let _ = self.flush_console();
+        self.session.cpu.cycles
+    }
+    
+    /// Lamp states for drawing a front panel: `{ s, r, f, c, omega,
+    /// running, state, cycles, word, instruction }`, register trits least
+    /// significant first.
+    #[wasm_bindgen]
+    pub fn front_panel(&self) -> Result<JsValue, JsError> {
+        to_js_value(&self.session.cpu.front_panel())
+    }
+    
+    /// Type text on the console (address 117) for the program to read.
+    #[wasm_bindgen]
+    pub fn console_feed(&mut self, text: &str) {
+        if let Some(device) = self.console_device() {
+            device.feed(text);
+        }
+    }
+    
+    /// Take the text the program printed on the console since the last
+    /// call. Empty when an output callback is set.
+    #[wasm_bindgen]
+    pub fn console_output(&mut self) -> String {
+        self.console_device().map(|d| d.take_output()).unwrap_or_default()
+    }
+    
+    /// Call `callback(text)` with console output after every step or run,
+    /// instead of buffering it for `console_output`.
+    #[wasm_bindgen]
+    pub fn on_console_output(&mut self, callback: js_sys::Function) {
+        self.console_callback = Some(callback);
+    }
+    
+    /// Stop `run` before executing the instruction at `addr`.
+    /// Returns false if a breakpoint was already set there.
+    #[wasm_bindgen]
+    pub fn set_breakpoint(&mut self, addr: i32) -> bool {
+        self.session.set_breakpoint(addr)
+    }
+    
+    /// Remove the breakpoint at `addr`. Returns false if none was set.
+    #[wasm_bindgen]
+    pub fn clear_breakpoint(&mut self, addr: i32) -> bool {
+        self.session.clear_breakpoint(addr)
+    }
+    
+    /// Run until halt or max cycles, returning the trace records for
+    /// every executed instruction as an array of objects.
+    #[wasm_bindgen]
+    pub fn run_traced(&mut self, max_cycles: u32) -> Result<JsValue, JsError> {
+        let mut records = Vec::new();
+        self.session.cpu.run_traced(max_cycles as u64, |r| records.push(r))
+            .map_err(|e| JsError::new(&format!("{}", e)))?;
+        to_js_value(&records)
+    }
+    
+    /// Run until halt or max cycles, invoking `callback` with each trace
+    /// record as it is produced. Returns the number of instructions executed.
+    #[wasm_bindgen]
+    pub fn run_streaming(&mut self, max_cycles: u32, callback: &js_sys::Function) -> Result<u64, JsError> {
+        let mut callback_error = None;
+        let executed = self.session.cpu.run_traced(max_cycles as u64, |r| {
+            if callback_error.is_some() {
+                return;
+            }
+            let result = to_js_value(&r)
+                .and_then(|v| callback.call1(&JsValue::NULL, &v)
+                    .map_err(|_| JsError::new("trace callback threw")));
+            if let Err(e) = result {
+                callback_error = Some(e);
+            }
+        }).map_err(|e| JsError::new(&format!("{}", e)))?;
+        self.flush_console()?;
+        
+        match callback_error {
+            Some(e) => Err(e),
+            None => Ok(executed),
+        }
     }
     
     /// Reset CPU to initial state with loaded program.
     #[wasm_bindgen]
     pub fn reset(&mut self) {
-        self.cpu = Cpu::new();
-        if !self.program.is_empty() {
-            let _ = self.cpu.load_program(&self.program);
-        }
+        let _ = self.session.reset();
+    }
+    
+    /// Save the full machine state as a versioned JSON snapshot.
+    ///
+    /// The result can be stored in localStorage or a URL and passed back
+    /// to `restore_state` later.
+    #[wasm_bindgen]
+    pub fn save_state(&self) -> String {
+        self.session.snapshot().to_json()
+    }
+    
+    /// Restore a snapshot produced by `save_state`.
+    #[wasm_bindgen]
+    pub fn restore_state(&mut self, state: &str) -> Result<(), JsError> {
+        let snapshot = Snapshot::from_json(state)
+            .map_err(|e| JsError::new(&format!("{}", e)))?;
+        self.session = EmulatorSession::from_snapshot(snapshot);
+        self.console = self.session.cpu.devices.attach(ConsoleDevice::new()).ok();
+        Ok(())
     }
     
     /// Check if CPU is running.
     #[wasm_bindgen]
     pub fn is_running(&self) -> bool {
-        self.cpu.is_running()
+        self.session.cpu.is_running()
     }
     
     /// Check if CPU is halted.
     #[wasm_bindgen]
     pub fn is_halted(&self) -> bool {
-        self.cpu.is_halted()
+        self.session.cpu.is_halted()
     }
     
     /// Get cycle count.
     #[wasm_bindgen]
     pub fn cycles(&self) -> u64 {
-        self.cpu.cycles
+        self.session.cpu.cycles
+    }
+    
+    /// Get emulated time in microseconds (zero unless timing is enabled).
+    #[wasm_bindgen]
+    pub fn elapsed_us(&self) -> u64 {
+        self.session.cpu.elapsed_us
+    }
+    
+    /// Get the number of memory cells.
+    #[wasm_bindgen]
+    pub fn memory_size(&self) -> usize {
+        self.session.cpu.mem.size()
     }
     
     /// Get program counter.
     #[wasm_bindgen]
     pub fn pc(&self) -> i32 {
-        self.cpu.regs.c.to_i32()
+        self.session.cpu.regs.c.to_i32()
     }
     
     /// Get accumulator value (S register).
     #[wasm_bindgen]
     pub fn accumulator(&self) -> i64 {
-        self.cpu.regs.s.to_i64()
+        self.session.cpu.regs.s.to_i64()
     }
     
     /// Get accumulator as ternary string.
     #[wasm_bindgen]
     pub fn accumulator_ternary(&self) -> String {
-        format!("{}", self.cpu.regs.s)
+        format!("{}", self.session.cpu.regs.s)
     }
     
     /// Get multiplier register value (R register).
     #[wasm_bindgen]
     pub fn multiplier(&self) -> i64 {
-        self.cpu.regs.r.to_i64()
+        self.session.cpu.regs.r.to_i64()
     }
     
     /// Get index register value (F register).
     #[wasm_bindgen]
     pub fn index(&self) -> i32 {
-        self.cpu.regs.f.to_i32()
+        self.session.cpu.regs.f.to_i32()
     }
     
     /// Get omega (sign) register as string.
     #[wasm_bindgen]
     pub fn omega(&self) -> String {
-        format!("{:?}", self.cpu.regs.omega)
+        format!("{:?}", self.session.cpu.regs.omega)
     }
     
     /// Get state as string.
     #[wasm_bindgen]
     pub fn state(&self) -> String {
-        format!("{:?}", self.cpu.state)
+        format!("{:?}", self.session.cpu.state)
     }
     
     /// Get memory cell value at index (0-161).
     #[wasm_bindgen]
     pub fn memory_at(&self, index: usize) -> i32 {
-        if index < 162 {
-            self.cpu.mem.read(index).to_i32()
+        if index < self.session.cpu.mem.size() {
+            self.session.cpu.mem.read(index).to_i32()
         } else {
             0
         }
@@ -150,39 +301,152 @@ impl WasmCpu {
     /// Get memory cell as ternary string.
     #[wasm_bindgen]
     pub fn memory_ternary_at(&self, index: usize) -> String {
-        if index < 162 {
-            format!("{}", self.cpu.mem.read(index))
+        if index < self.session.cpu.mem.size() {
+            format!("{}", self.session.cpu.mem.read(index))
         } else {
             "OOOOOOOOO".to_string()
         }
     }
     
+    /// Get the memory indices written since the previous call, so the UI
+    /// can redraw only the changed cells.
+    #[wasm_bindgen]
+    pub fn take_memory_changes(&mut self) -> Vec<u32> {
+        self.session.cpu.mem.take_changes().into_iter().map(|i| i as u32).collect()
+    }
+    
     /// Get all memory as JSON array of values.
     #[wasm_bindgen]
     pub fn memory_all(&self) -> Vec<i32> {
-        (0..162).map(|i| self.cpu.mem.read(i).to_i32()).collect()
+        self.session.cpu.mem.as_slice().iter().map(|cell| cell.to_i32()).collect()
     }
     
     /// Get registers as JSON string.
     #[wasm_bindgen]
     pub fn registers_json(&self) -> String {
-        format!(r#"{{"s":{},"r":{},"f":{},"c":{},"omega":"{}","cycles":{}}}"#,
-            self.cpu.regs.s.to_i64(),
-            self.cpu.regs.r.to_i64(),
-            self.cpu.regs.f.to_i32(),
-            self.cpu.regs.c.to_i32(),
-            format!("{:?}", self.cpu.regs.omega),
-            self.cpu.cycles
+        format!(r#"{{"s":{},"r":{},"f":{},"c":{},"omega":"{:?}","cycles":{}}}"#,
+            self.session.cpu.regs.s.to_i64(),
+            self.session.cpu.regs.r.to_i64(),
+            self.session.cpu.regs.f.to_i32(),
+            self.session.cpu.regs.c.to_i32(),
+            self.session.cpu.regs.omega,
+            self.session.cpu.cycles
         )
     }
 }
 
+impl WasmCpu {
+    /// Wrap a session, attaching the console.
+    fn from_session(mut session: EmulatorSession) -> Self {
+        let console = session.cpu.devices.attach(ConsoleDevice::new()).ok();
+        Self { session, console, console_callback: None }
+    }
+    
+    fn console_device(&mut self) -> Option<&mut ConsoleDevice> {
+        self.session.cpu.devices.get_mut(self.console?)
+    }
+    
+    /// Pass pending console output to the callback, if one is set.
+    fn flush_console(&mut self) -> Result<(), JsError> {
+        let Some(callback) = self.console_callback.clone() else {
+            return Ok(());
+        };
+        let text = self.console_output();
+        if text.is_empty() {
+            return Ok(());
+        }
+        callback.call1(&JsValue::NULL, &JsValue::from_str(&text))
+            .map(|_| ())
+            .map_err(|_| JsError::new("console callback threw"))
+    }
+}
+
 impl Default for WasmCpu {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Convert a serializable value to a plain JS object via JSON.
+fn to_js_value<T: serde::Serialize>(value: &T) -> Result<JsValue, JsError> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| JsError::new(&format!("{}", e)))?;
+    js_sys::JSON::parse(&json).map_err(|_| JsError::new("failed to build JS value"))
+}
+
+/// WebAssembly-friendly assembler exposing symbols and source mapping.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct WasmAssembler {
+    assembly: Assembly,
+}
+
+#[wasm_bindgen]
+impl WasmAssembler {
+    /// Create an assembler with no program.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Assemble source code, replacing any previous result.
+    /// Returns the number of words produced.
+    #[wasm_bindgen]
+    pub fn assemble(&mut self, source: &str) -> Result<usize, JsError> {
+        self.assembly = assemble_detailed(source)
+            .map_err(|e| JsError::new(&format!("{}", e)))?;
+        Ok(self.assembly.program.len())
+    }
+    
+    /// Assembled words as decimal values.
+    #[wasm_bindgen]
+    pub fn program(&self) -> Vec<i32> {
+        self.assembly.program.iter().map(|w| w.to_i32()).collect()
+    }
+    
+    /// Assembled words as ternary strings.
+    #[wasm_bindgen]
+    pub fn program_ternary(&self) -> Vec<String> {
+        self.assembly.program.iter().map(|w| format!("{}", w)).collect()
+    }
+    
+    /// Symbol table as an object mapping label names to addresses.
+    #[wasm_bindgen]
+    pub fn symbols(&self) -> Result<JsValue, JsError> {
+        to_js_value(&self.assembly.symbols)
+    }
+    
+    /// Address of a label (case-insensitive), if defined.
+    #[wasm_bindgen]
+    pub fn symbol_address(&self, name: &str) -> Option<i32> {
+        self.assembly.symbols.get(&name.to_uppercase()).copied()
+    }
+    
+    /// Source line (1-based) for each assembled word.
+    #[wasm_bindgen]
+    pub fn source_map(&self) -> Vec<u32> {
+        self.assembly.source_map.iter().map(|&l| l as u32).collect()
+    }
+    
+    /// Source line that produced the word at `index`.
+    #[wasm_bindgen]
+    pub fn line_for_index(&self, index: usize) -> Option<u32> {
+        self.assembly.line_for_index(index).map(|l| l as u32)
+    }
+    
+    /// Index of the first word produced by `line`, for gutter breakpoints.
+    #[wasm_bindgen]
+    pub fn index_for_line(&self, line: usize) -> Option<u32> {
+        self.assembly.index_for_line(line).map(|i| i as u32)
+    }
+    
+    /// Warnings as an array of `{ line, message }` objects.
+    #[wasm_bindgen]
+    pub fn warnings(&self) -> Result<JsValue, JsError> {
+        to_js_value(&self.assembly.warnings)
+    }
+}
+
 /// Assemble source code and return instruction count.
 #[wasm_bindgen]
 pub fn wasm_assemble(source: &str) -> Result<usize, JsError> {
@@ -197,3 +461,72 @@ pub fn wasm_disassemble(value: i32) -> String {
     let tryte = Tryte9::from_i32(value);
     disassemble_instruction(tryte)
 }
+
+/// Describe the instruction set of a machine preset ("simplified" or
+/// "historical") as an object; see [`IsaDescription`].
+#[wasm_bindgen]
+pub fn wasm_isa(preset: &str) -> Result<JsValue, JsError> {
+    let config = CpuConfig::preset(preset)
+        .ok_or_else(|| JsError::new(&format!("unknown machine preset: {}", preset)))?;
+    to_js_value(&IsaDescription::new(config.isa))
+}
+
+/// Explain `a + b` trit by trit, as an object of the passes made, their
+/// per-trit carries and the result; see [`Explanation`](crate::ternary::arith::Explanation).
+#[wasm_bindgen]
+pub fn wasm_explain_add(a: i32, b: i32) -> Result<JsValue, JsError> {
+    to_js_value(&explain_add(&operand(a)?, &operand(b)?))
+}
+
+/// Explain `a - b` trit by trit, like [`wasm_explain_add`].
+#[wasm_bindgen]
+pub fn wasm_explain_sub(a: i32, b: i32) -> Result<JsValue, JsError> {
+    to_js_value(&explain_sub(&operand(a)?, &operand(b)?))
+}
+
+/// Explain `a * b` partial product by partial product, like
+/// [`wasm_explain_add`].
+#[wasm_bindgen]
+pub fn wasm_explain_mul(a: i32, b: i32) -> Result<JsValue, JsError> {
+    to_js_value(&explain_mul(&operand(a)?, &operand(b)?))
+}
+
+/// Explain an expression such as `"5 + 7"` or `"0t+- * -3"`, with
+/// operands in any notation `wasm_convert` reads.
+#[wasm_bindgen]
+pub fn wasm_explain(expr: &str) -> Result<JsValue, JsError> {
+    let explanation = explain(expr).map_err(|e| JsError::new(&format!("{}", e)))?;
+    to_js_value(&explanation)
+}
+
+/// Convert `value`, written in decimal, `0t` ternary, `0n` balanced
+/// base 9 or `0k` balanced base 27, to the notation `base` names:
+/// "decimal" (or "10"), "ternary" (or "3"), "trits" for a +/0/- pattern,
+/// "base9" (or "9") or "base27" (or "27"). The value must fit in 18 trits.
+#[wasm_bindgen]
+pub fn wasm_convert(value: &str, base: &str) -> Result<String, JsError> {
+    let conversion = convert(value, Width::Word18).map_err(|e| JsError::new(&format!("{}", e)))?;
+    match base.to_ascii_lowercase().as_str() {
+        "decimal" | "10" => Ok(conversion.decimal.to_string()),
+        "ternary" | "3" => Ok(conversion.ternary),
+        "trits" => Ok(conversion.trits),
+        "base9" | "9" => Ok(conversion.base9),
+        "base27" | "27" => Ok(conversion.base27),
+        _ => Err(JsError::new(&format!("unknown base: {}", base))),
+    }
+}
+
+/// Convert `value` to every notation at once, for a width of 9 or 18
+/// trits, as `{ width, decimal, ternary, trits, base9, base27 }`.
+#[wasm_bindgen]
+pub fn wasm_conversion(value: &str, trits: usize) -> Result<JsValue, JsError> {
+    let width = Width::from_trits(trits)
+        .ok_or_else(|| JsError::new(&format!("no {}-trit width; use 9 or 18", trits)))?;
+    let conversion = convert(value, width).map_err(|e| JsError::new(&format!("{}", e)))?;
+    to_js_value(&conversion)
+}
+
+/// An explainer operand, which must fit in 18 trits.
+fn operand(value: i32) -> Result<Word18, JsError> {
+    Word18::try_from_i64(value as i64).map_err(|e| JsError::new(&format!("{}", e)))
+}
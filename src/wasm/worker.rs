@@ -0,0 +1,184 @@
+//! Web Worker message protocol.
+//!
+//! Heavy runs should not block the browser's main thread. This module
+//! defines a small postMessage-style protocol: the page sends a JSON
+//! [`WorkerCommand`], the worker answers with a JSON [`WorkerResponse`].
+//!
+//! ```text
+//! // worker.js
+//! import init, { WasmWorker } from "./pkg/setun.js";
+//! await init();
+//! const core = new WasmWorker();
+//! onmessage = (e) => postMessage(core.handle_message(e.data));
+//!
+//! // main thread
+//! worker.postMessage(JSON.stringify({ cmd: "run", max_cycles: 100000 }));
+//! ```
+
+use wasm_bindgen::prelude::*;
+use serde::{Serialize, Deserialize};
+use crate::CpuState;
+use crate::cpu::{CpuConfig, RegisterState, Snapshot, TraceRecord};
+use crate::session::EmulatorSession;
+
+/// A request sent from the page to the worker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum WorkerCommand {
+    /// Replace the machine with one built from `config`.
+    Configure { config: CpuConfig },
+    /// Assemble and load a program, resetting the CPU.
+    LoadAsm { source: String },
+    /// Execute a single instruction.
+    Step,
+    /// Run until halt or `max_cycles` instructions.
+    Run { max_cycles: u64 },
+    /// Reset the CPU and reload the current program.
+    Reset,
+    /// Report the register file.
+    GetRegisters,
+    /// Read `count` memory cells starting at index `start`.
+    ReadMemory { start: usize, count: usize },
+    /// Capture a snapshot of the machine.
+    SaveState,
+    /// Restore a snapshot produced by `SaveState`.
+    RestoreState { state: String },
+}
+
+/// A reply sent from the worker back to the page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkerResponse {
+    /// A program was loaded.
+    Loaded { instructions: usize },
+    /// One instruction was executed.
+    Stepped { record: TraceRecord },
+    /// A run finished (halted, errored, or hit the cycle limit).
+    Ran { executed: u64, state: CpuState, regs: RegisterState },
+    /// Current register file.
+    Registers { regs: RegisterState, state: CpuState, cycles: u64 },
+    /// A block of memory cells (decimal values).
+    Memory { start: usize, values: Vec<i32> },
+    /// A serialized snapshot.
+    State { state: String },
+    /// The command succeeded with nothing to report.
+    Ok,
+    /// The command failed.
+    Error { message: String },
+}
+
+/// Protocol handler owning the emulator state.
+///
+/// Contains only plain data, so it is `Send` and can live in a worker.
+#[derive(Debug, Clone, Default)]
+pub struct WorkerCore {
+    session: EmulatorSession,
+}
+
+impl WorkerCore {
+    /// Create a handler with an empty machine.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Handle one command.
+    pub fn handle(&mut self, cmd: WorkerCommand) -> WorkerResponse {
+        match cmd {
+            WorkerCommand::Configure { config } => {
+                if let Err(e) = config.validate() {
+                    return error(e);
+                }
+                self.session = EmulatorSession::with_config(config);
+                WorkerResponse::Ok
+            }
+            WorkerCommand::LoadAsm { source } => {
+                match self.session.load_source(&source) {
+                    Ok(instructions) => WorkerResponse::Loaded { instructions },
+                    Err(e) => error(e),
+                }
+            }
+            WorkerCommand::Step => match self.session.cpu.step_traced() {
+                Ok(record) => WorkerResponse::Stepped { record },
+                Err(e) => error(e),
+            },
+            WorkerCommand::Run { max_cycles } => {
+                let summary = self.session.run(max_cycles);
+                match summary.error {
+                    Some(e) => error(e),
+                    None => WorkerResponse::Ran {
+                        executed: summary.executed,
+                        state: self.session.cpu.state,
+                        regs: RegisterState::capture(&self.session.cpu.regs),
+                    },
+                }
+            }
+            WorkerCommand::Reset => match self.session.reset() {
+                Ok(()) => WorkerResponse::Ok,
+                Err(e) => error(e),
+            },
+            WorkerCommand::GetRegisters => WorkerResponse::Registers {
+                regs: RegisterState::capture(&self.session.cpu.regs),
+                state: self.session.cpu.state,
+                cycles: self.session.cpu.cycles,
+            },
+            WorkerCommand::ReadMemory { start, count } => {
+                let values = self.session.cpu.mem.dump(start, count)
+                    .into_iter()
+                    .map(|(_, v)| v.to_i32())
+                    .collect();
+                WorkerResponse::Memory { start, values }
+            }
+            WorkerCommand::SaveState => WorkerResponse::State {
+                state: self.session.snapshot().to_json(),
+            },
+            WorkerCommand::RestoreState { state } => match Snapshot::from_json(&state) {
+                Ok(snapshot) => {
+                    self.session = EmulatorSession::from_snapshot(snapshot);
+                    WorkerResponse::Ok
+                }
+                Err(e) => error(e),
+            },
+        }
+    }
+    
+    /// Handle one JSON-encoded command, returning a JSON-encoded response.
+    pub fn handle_json(&mut self, message: &str) -> String {
+        let response = match serde_json::from_str(message) {
+            Ok(cmd) => self.handle(cmd),
+            Err(e) => error(format!("invalid command: {}", e)),
+        };
+        serde_json::to_string(&response).expect("worker response serialization cannot fail")
+    }
+}
+
+fn error(e: impl std::fmt::Display) -> WorkerResponse {
+    WorkerResponse::Error { message: e.to_string() }
+}
+
+/// WebAssembly entry point for the worker protocol.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct WasmWorker {
+    core: WorkerCore,
+}
+
+#[wasm_bindgen]
+impl WasmWorker {
+    /// Create a worker-side handler.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Handle a JSON command string and return the JSON response string.
+    #[wasm_bindgen]
+    pub fn handle_message(&mut self, message: &str) -> String {
+        self.core.handle_json(message)
+    }
+}
+
+// The worker core must stay movable across threads.
+const _: fn() = || {
+    fn assert_send<T: Send + 'static>() {}
+    assert_send::<WorkerCore>();
+};